@@ -47,6 +47,8 @@ use crate::conf::AccountSettings;
 use crate::connections::timeout;
 use crate::email::{parser::BytesExt, *};
 use crate::error::{MeliError, Result, ResultIntoMeliError};
+use crate::sasl::SaslMechanism;
+use crate::shellexpand::ShellExpandTrait;
 use futures::lock::Mutex as FutureMutex;
 use futures::stream::Stream;
 use std::collections::hash_map::DefaultHasher;
@@ -54,6 +56,7 @@ use std::collections::{BTreeSet, HashMap, HashSet};
 use std::convert::TryFrom;
 use std::convert::TryInto;
 use std::hash::Hasher;
+use std::path::PathBuf;
 use std::pin::Pin;
 use std::str::FromStr;
 use std::sync::{Arc, Mutex};
@@ -78,6 +81,8 @@ pub static SUPPORTED_CAPABILITIES: &[&str] = &[
     "LOGIN",
     "LOGINDISABLED",
     "MOVE",
+    "NOTIFY",
+    "QRESYNC",
     "SPECIAL-USE",
     "UNSELECT",
 ];
@@ -97,6 +102,10 @@ pub struct ImapServerConf {
     pub use_starttls: bool,
     pub use_tls: bool,
     pub danger_accept_invalid_certs: bool,
+    /// SASL mechanism to authenticate with; `None` picks one from the server's capabilities.
+    pub auth_mechanism: Option<SaslMechanism>,
+    /// PKCS #12 archive and its password, to present as a TLS client certificate.
+    pub client_certificate: Option<(PathBuf, String)>,
     pub protocol: ImapProtocol,
     pub timeout: Option<Duration>,
 }
@@ -132,6 +141,50 @@ macro_rules! get_conf_val {
     };
 }
 
+/// Parse the `auth_mechanism` setting, where `auto` (the default) means `None`.
+fn auth_mechanism(s: &AccountSettings) -> Result<Option<SaslMechanism>> {
+    match s.extra.get("auth_mechanism") {
+        None => Ok(None),
+        Some(v) if v.eq_ignore_ascii_case("auto") => Ok(None),
+        Some(v) => SaslMechanism::from_str(v).map(Some).map_err(|err| {
+            MeliError::new(format!(
+                "Configuration error ({}): Invalid value for field `auth_mechanism`: {}",
+                s.name.as_str(),
+                err
+            ))
+        }),
+    }
+}
+
+/// Return `server_password` or, if `server_password_command` is set, the output of that command.
+fn server_password(s: &AccountSettings) -> Result<String> {
+    if !s.extra.contains_key("server_password_command") {
+        if !s.extra.contains_key("server_password")
+            && auth_mechanism(s)? == Some(SaslMechanism::External)
+        {
+            return Ok(String::new());
+        }
+        return Ok(get_conf_val!(s["server_password"])?.to_string());
+    }
+    let invocation = get_conf_val!(s["server_password_command"])?;
+    let output = std::process::Command::new("sh")
+        .args(&["-c", invocation])
+        .stdin(std::process::Stdio::piped())
+        .stdout(std::process::Stdio::piped())
+        .stderr(std::process::Stdio::piped())
+        .output()?;
+    if !output.status.success() {
+        return Err(MeliError::new(format!(
+            "({}) server_password_command `{}` returned {}: {}",
+            s.name,
+            invocation,
+            output.status,
+            String::from_utf8_lossy(&output.stderr)
+        )));
+    }
+    Ok(std::str::from_utf8(&output.stdout)?.trim_end().to_string())
+}
+
 #[derive(Debug)]
 pub struct UIDStore {
     account_hash: AccountHash,
@@ -217,9 +270,11 @@ impl MailBackend for ImapType {
             extension_use:
                 ImapExtensionUse {
                     idle,
+                    notify,
                     #[cfg(feature = "deflate_compression")]
                     deflate,
                     condstore,
+                    qresync,
                     oauth2,
                 },
         } = self.server_conf.protocol
@@ -235,6 +290,15 @@ impl MailBackend for ImapType {
                             };
                         }
                     }
+                    "NOTIFY" => {
+                        if notify {
+                            *status = MailBackendExtensionStatus::Enabled { comment: None };
+                        } else {
+                            *status = MailBackendExtensionStatus::Supported {
+                                comment: Some("Disabled by user configuration"),
+                            };
+                        }
+                    }
                     "COMPRESS=DEFLATE" => {
                         #[cfg(feature = "deflate_compression")]
                         {
@@ -262,6 +326,15 @@ impl MailBackend for ImapType {
                             };
                         }
                     }
+                    "QRESYNC" => {
+                        if condstore && qresync {
+                            *status = MailBackendExtensionStatus::Enabled { comment: None };
+                        } else {
+                            *status = MailBackendExtensionStatus::Supported {
+                                comment: Some("Disabled by user configuration"),
+                            };
+                        }
+                    }
                     "AUTH=OAUTH2" => {
                         if oauth2 {
                             *status = MailBackendExtensionStatus::Enabled { comment: None };
@@ -455,20 +528,31 @@ impl MailBackend for ImapType {
         let main_conn = self.connection.clone();
         let uid_store = self.uid_store.clone();
         Ok(Box::pin(async move {
-            let has_idle: bool = match server_conf.protocol {
+            let has_capability = |name: &[u8]| -> bool {
+                uid_store
+                    .capabilities
+                    .lock()
+                    .unwrap()
+                    .iter()
+                    .any(|cap| cap.eq_ignore_ascii_case(name))
+            };
+            let (has_idle, mut has_notify): (bool, bool) = match server_conf.protocol {
                 ImapProtocol::IMAP {
-                    extension_use: ImapExtensionUse { idle, .. },
-                } => {
-                    idle && uid_store
-                        .capabilities
-                        .lock()
-                        .unwrap()
-                        .iter()
-                        .any(|cap| cap.eq_ignore_ascii_case(b"IDLE"))
-                }
-                _ => false,
+                    extension_use: ImapExtensionUse { idle, notify, .. },
+                } => (
+                    idle && has_capability(b"IDLE"),
+                    notify && has_capability(b"NOTIFY"),
+                ),
+                _ => (false, false),
             };
-            while let Err(err) = if has_idle {
+            while let Err(err) = if has_notify {
+                notify(ImapWatchKit {
+                    conn: ImapConnection::new_connection(&server_conf, uid_store.clone()),
+                    main_conn: main_conn.clone(),
+                    uid_store: uid_store.clone(),
+                })
+                .await
+            } else if has_idle {
                 idle(ImapWatchKit {
                     conn: ImapConnection::new_connection(&server_conf, uid_store.clone()),
                     main_conn: main_conn.clone(),
@@ -483,6 +567,11 @@ impl MailBackend for ImapType {
                 })
                 .await
             } {
+                if has_notify && !err.kind.is_network() {
+                    debug!("NOTIFY failed, falling back to IDLE/polling: {}", err);
+                    has_notify = false;
+                    continue;
+                }
                 let mut main_conn_lck = timeout(uid_store.timeout, main_conn.lock()).await?;
                 if err.kind.is_network() {
                     uid_store.is_online.lock().unwrap().1 = Err(err.clone());
@@ -1115,7 +1204,29 @@ impl MailBackend for ImapType {
             ));
         }
         let mailbox_hash = mailbox_hash.unwrap();
-        fn rec(q: &crate::search::Query, s: &mut String) {
+        /// Whether `s` can be sent as an IMAP atom, which flag keywords are.
+        fn is_atom(s: &str) -> bool {
+            !s.is_empty()
+                && s.bytes()
+                    .all(|b| b.is_ascii_graphic() && !b"(){%*\"\\]".contains(&b))
+        }
+        fn keyword(k: &str, s: &mut String) -> Result<()> {
+            if !is_atom(k) {
+                return Err(
+                    MeliError::new(format!("`{}` is not a valid IMAP keyword.", k))
+                        .set_kind(crate::error::ErrorKind::ValueError),
+                );
+            }
+            s.push_str(" KEYWORD ");
+            s.push_str(k);
+            s.push(' ');
+            Ok(())
+        }
+        /// Dates of SEARCH keys are days, in the `dd-Mon-yyyy` format.
+        fn date(t: crate::UnixTimestamp) -> String {
+            crate::datetime::timestamp_to_string(t, Some("%d-%b-%Y"), true)
+        }
+        fn rec(q: &crate::search::Query, s: &mut String) -> Result<()> {
             use crate::search::{escape_double_quote, Query::*};
             match q {
                 Subject(t) => {
@@ -1175,34 +1286,90 @@ impl MailBackend for ImapType {
                             "unanswered" => {
                                 s.push_str(" UNANSWERED ");
                             }
-                            keyword => {
-                                s.push_str(" KEYWORD ");
-                                s.push_str(keyword);
-                                s.push(' ');
+                            k => {
+                                keyword(k, s)?;
                             }
                         }
                     }
                 }
                 And(q1, q2) => {
-                    rec(q1, s);
+                    rec(q1, s)?;
                     s.push(' ');
-                    rec(q2, s);
+                    rec(q2, s)?;
                 }
                 Or(q1, q2) => {
                     s.push_str(" OR ");
-                    rec(q1, s);
+                    rec(q1, s)?;
                     s.push(' ');
-                    rec(q2, s);
+                    rec(q2, s)?;
                 }
                 Not(q) => {
                     s.push_str(" NOT ");
-                    rec(q, s);
+                    rec(q, s)?;
+                }
+                Tag(t) => {
+                    keyword(t, s)?;
+                }
+                Larger(size) => {
+                    s.push_str(&format!(" LARGER {}", size));
+                }
+                Smaller(size) => {
+                    s.push_str(&format!(" SMALLER {}", size));
+                }
+                MessageId(t) => {
+                    s.push_str(" HEADER Message-ID \"");
+                    s.extend(escape_double_quote(t).chars());
+                    s.push('"');
+                }
+                ListId(t) => {
+                    s.push_str(" HEADER List-Id \"");
+                    s.extend(escape_double_quote(t).chars());
+                    s.push('"');
+                }
+                Header(name, t) => {
+                    s.push_str(" HEADER \"");
+                    s.extend(escape_double_quote(name).chars());
+                    s.push_str("\" \"");
+                    s.extend(escape_double_quote(t).chars());
+                    s.push('"');
+                }
+                Body(t) => {
+                    s.push_str(" BODY \"");
+                    s.extend(escape_double_quote(t).chars());
+                    s.push('"');
+                }
+                Before(t) => {
+                    s.push_str(" SENTBEFORE ");
+                    s.push_str(&date(*t));
+                }
+                After(t) => {
+                    s.push_str(" SENTSINCE ");
+                    s.push_str(&date(*t));
+                }
+                Between(t_a, t_b) => {
+                    s.push_str(" SENTSINCE ");
+                    s.push_str(&date(*t_a));
+                    s.push_str(" SENTBEFORE ");
+                    s.push_str(&date(*t_b));
+                }
+                On(t) => {
+                    s.push_str(" SENTON ");
+                    s.push_str(&date(*t));
+                }
+                InReplyTo(_) | References(_) | AllAddresses(_) | Regex(_) | HasAttachment
+                | Mailbox(_) => {
+                    /* Searching without these terms would give wrong results */
+                    return Err(MeliError::new(format!(
+                        "IMAP SEARCH can't express the term {:?}",
+                        q
+                    ))
+                    .set_kind(crate::error::ErrorKind::NotSupported));
                 }
-                _ => {}
             }
+            Ok(())
         }
         let mut query_str = String::new();
-        rec(&query, &mut query_str);
+        rec(&query, &mut query_str)?;
         let connection = self.connection.clone();
         let uid_store = self.uid_store.clone();
 
@@ -1251,38 +1418,33 @@ impl ImapType {
         let server_hostname = get_conf_val!(s["server_hostname"])?;
         let server_username = get_conf_val!(s["server_username"])?;
         let use_oauth2: bool = get_conf_val!(s["use_oauth2"], false)?;
-        let server_password = if !s.extra.contains_key("server_password_command") {
-            if use_oauth2 {
-                return Err(MeliError::new(format!(
-                    "({}) `use_oauth2` use requires `server_password_command` set with a command that returns an OAUTH2 token. Consult documentation for guidance.",
-                    s.name,
-                )));
-            }
-            get_conf_val!(s["server_password"])?.to_string()
-        } else {
-            let invocation = get_conf_val!(s["server_password_command"])?;
-            let output = std::process::Command::new("sh")
-                .args(&["-c", invocation])
-                .stdin(std::process::Stdio::piped())
-                .stdout(std::process::Stdio::piped())
-                .stderr(std::process::Stdio::piped())
-                .output()?;
-            if !output.status.success() {
-                return Err(MeliError::new(format!(
-                    "({}) server_password_command `{}` returned {}: {}",
-                    s.name,
-                    get_conf_val!(s["server_password_command"])?,
-                    output.status,
-                    String::from_utf8_lossy(&output.stderr)
-                )));
-            }
-            std::str::from_utf8(&output.stdout)?.trim_end().to_string()
-        };
+        if use_oauth2 && !s.extra.contains_key("server_password_command") {
+            return Err(MeliError::new(format!(
+                "({}) `use_oauth2` use requires `server_password_command` set with a command that returns an OAUTH2 token. Consult documentation for guidance.",
+                s.name,
+            )));
+        }
+        let auth_mechanism = auth_mechanism(s)?;
+        let server_password = server_password(s)?;
         let server_port = get_conf_val!(s["server_port"], 143)?;
         let use_tls = get_conf_val!(s["use_tls"], true)?;
         let use_starttls = use_tls && get_conf_val!(s["use_starttls"], !(server_port == 993))?;
         let danger_accept_invalid_certs: bool =
             get_conf_val!(s["danger_accept_invalid_certs"], false)?;
+        if get_conf_val!(s["client_certificate"]).is_err() {
+            if s.extra.contains_key("client_certificate_password") {
+                return Err(MeliError::new(format!(
+                    "Configuration error ({}): `client_certificate_password` is set but `client_certificate` is not",
+                    s.name.as_str(),
+                )));
+            }
+        } else if !use_tls {
+            return Err(MeliError::new(format!(
+                "Configuration error ({}): `client_certificate` requires use_tls = true",
+                s.name.as_str(),
+            )));
+        }
+        let _ = get_conf_val!(s["client_certificate_password"]);
         #[cfg(feature = "sqlite3")]
         let keep_offline_cache = get_conf_val!(s["offline_cache"], true)?;
         #[cfg(not(feature = "sqlite3"))]
@@ -1308,10 +1470,22 @@ impl ImapType {
             use_tls,
             use_starttls,
             danger_accept_invalid_certs,
+            auth_mechanism,
+            client_certificate: s.extra.get("client_certificate").map(|path| {
+                (
+                    PathBuf::from(path).expand(),
+                    s.extra
+                        .get("client_certificate_password")
+                        .cloned()
+                        .unwrap_or_default(),
+                )
+            }),
             protocol: ImapProtocol::IMAP {
                 extension_use: ImapExtensionUse {
                     idle: get_conf_val!(s["use_idle"], true)?,
+                    notify: get_conf_val!(s["use_notify"], true)?,
                     condstore: get_conf_val!(s["use_condstore"], true)?,
+                    qresync: get_conf_val!(s["use_qresync"], true)?,
                     #[cfg(feature = "deflate_compression")]
                     deflate: get_conf_val!(s["use_deflate"], true)?,
                     oauth2: use_oauth2,
@@ -1534,6 +1708,15 @@ impl ImapType {
         get_conf_val!(s["server_hostname"])?;
         get_conf_val!(s["server_username"])?;
         let use_oauth2: bool = get_conf_val!(s["use_oauth2"], false)?;
+        let auth_mechanism = auth_mechanism(s)?;
+        keys.insert("auth_mechanism");
+        s.extra.remove("auth_mechanism");
+        if use_oauth2 && auth_mechanism.is_some() {
+            return Err(MeliError::new(format!(
+                "Configuration error ({}): `use_oauth2` and `auth_mechanism` are both set, cannot choose",
+                s.name.as_str(),
+            )));
+        }
         keys.insert("server_password_command");
         if !s.extra.contains_key("server_password_command") {
             if use_oauth2 {
@@ -1542,7 +1725,11 @@ impl ImapType {
                     s.name,
                 )));
             }
-            get_conf_val!(s["server_password"])?;
+            if auth_mechanism == Some(SaslMechanism::External) {
+                let _ = get_conf_val!(s["server_password"]);
+            } else {
+                get_conf_val!(s["server_password"])?;
+            }
         } else if s.extra.contains_key("server_password") {
             return Err(MeliError::new(format!(
                 "Configuration error ({}): both server_password and server_password_command are set, cannot choose",
@@ -1573,7 +1760,9 @@ impl ImapType {
             }
         }
         get_conf_val!(s["use_idle"], true)?;
+        get_conf_val!(s["use_notify"], true)?;
         get_conf_val!(s["use_condstore"], true)?;
+        get_conf_val!(s["use_qresync"], true)?;
         #[cfg(feature = "deflate_compression")]
         get_conf_val!(s["use_deflate"], true)?;
         #[cfg(not(feature = "deflate_compression"))]
@@ -1584,6 +1773,7 @@ impl ImapType {
             )));
         }
         let _timeout = get_conf_val!(s["timeout"], 16_u64)?;
+        get_conf_val!(s["managesieve_port"], 4190_u16)?;
         let extra_keys = s
             .extra
             .keys()
@@ -615,12 +615,255 @@ impl ImapConnection {
     }
 
     //rfc7162_Quick Flag Changes Resynchronization (CONDSTORE)_and Quick Mailbox Resynchronization (QRESYNC)
+    //Section 3.2.5.  QRESYNC Parameter to SELECT/EXAMINE
     pub async fn resync_condstoreqresync(
         &mut self,
-        _cache_handle: Box<dyn ImapCache>,
-        _mailbox_hash: MailboxHash,
+        mut cache_handle: Box<dyn ImapCache>,
+        mailbox_hash: MailboxHash,
     ) -> Result<Option<Vec<Envelope>>> {
-        Ok(None)
+        let mut payload = vec![];
+        debug!("resync_condstoreqresync");
+        let mut response = Vec::with_capacity(8 * 1024);
+        let cached_uidvalidity = self
+            .uid_store
+            .uidvalidity
+            .lock()
+            .unwrap()
+            .get(&mailbox_hash)
+            .cloned();
+        let cached_max_uid = self
+            .uid_store
+            .max_uids
+            .lock()
+            .unwrap()
+            .get(&mailbox_hash)
+            .cloned();
+        let cached_highestmodseq = self
+            .uid_store
+            .highestmodseqs
+            .lock()
+            .unwrap()
+            .get(&mailbox_hash)
+            .cloned();
+        if cached_uidvalidity.is_none()
+            || cached_max_uid.is_none()
+            || cached_highestmodseq.is_none()
+        {
+            // This means the mailbox is not cached.
+            return Ok(None);
+        }
+        let cached_uidvalidity: UID = cached_uidvalidity.unwrap();
+        let cached_max_uid: UID = cached_max_uid.unwrap();
+        let cached_highestmodseq: ModSequence = match cached_highestmodseq.unwrap() {
+            Ok(v) => v,
+            // No MODSEQ is available for __this__ mailbox, fallback to basic sync
+            Err(()) => return self.resync_basic(cache_handle, mailbox_hash).await,
+        };
+
+        let (mailbox_path, mailbox_exists, unseen) = {
+            let f = &self.uid_store.mailboxes.lock().await[&mailbox_hash];
+            (
+                f.imap_path().to_string(),
+                f.exists.clone(),
+                f.unseen.clone(),
+            )
+        };
+        let known_uids: BTreeSet<UID> = self
+            .uid_store
+            .envelopes
+            .lock()
+            .unwrap()
+            .values()
+            .filter(|cenv| cenv.mailbox_hash == mailbox_hash)
+            .map(|cenv| cenv.uid)
+            .collect();
+        // 1. SELECT with the QRESYNC parameter. The server replies with
+        //    * VANISHED (EARLIER) <uids>  for the known UIDs that were expunged, and
+        //    * <msn> FETCH (UID FLAGS MODSEQ)  for messages changed since the cached modseq,
+        //    unless UIDVALIDITY changed.
+        let select_response = self
+            .select_mailbox_qresync(
+                mailbox_hash,
+                &mut response,
+                cached_uidvalidity,
+                cached_highestmodseq,
+                &known_uids,
+            )
+            .await?;
+        if select_response.uidvalidity != cached_uidvalidity {
+            cache_handle.clear(mailbox_hash, &select_response)?;
+            return Ok(None);
+        }
+        let new_highestmodseq = match select_response.highestmodseq {
+            Some(Ok(v)) => v,
+            Some(Err(())) => {
+                self.uid_store
+                    .highestmodseqs
+                    .lock()
+                    .unwrap()
+                    .insert(mailbox_hash, Err(()));
+                return self.resync_basic(cache_handle, mailbox_hash).await;
+            }
+            None => return self.resync_basic(cache_handle, mailbox_hash).await,
+        };
+        cache_handle.update_mailbox(mailbox_hash, &select_response)?;
+        if cached_highestmodseq == new_highestmodseq {
+            /* Nothing was changed, added or expunged since the last sync. */
+            return Ok(Some(vec![]));
+        }
+
+        // 2. Find out which messages got expunged and update flags of old messages from the
+        //    responses to SELECT.
+        let mut refresh_events = vec![];
+        let mut vanished_uids = vec![];
+        let mut changed = vec![];
+        for l in response.split_rn() {
+            match protocol_parser::untagged_responses(l).map(|(_, v, _)| v) {
+                Ok(Some(UntaggedResponse::Vanished { uids, .. })) => {
+                    vanished_uids.extend(uids);
+                }
+                Ok(Some(UntaggedResponse::Fetch(FetchResponse {
+                    uid: Some(uid),
+                    flags: Some(flags),
+                    modseq,
+                    ..
+                }))) if uid <= cached_max_uid => {
+                    changed.push((uid, flags, modseq));
+                }
+                _ => {}
+            }
+        }
+        refresh_events.extend(self.vanished(mailbox_hash, &vanished_uids).await);
+        {
+            let mut env_lck = self.uid_store.envelopes.lock().unwrap();
+            let mut modseq_lck = self.uid_store.modseq.lock().unwrap();
+            for (uid, (flags, tags), modseq) in changed {
+                let env_hash = generate_envelope_hash(&mailbox_path, &uid);
+                if !env_lck.contains_key(&env_hash) {
+                    continue;
+                }
+                if let Some(modseq) = modseq {
+                    modseq_lck.insert(env_hash, modseq);
+                }
+                if env_lck[&env_hash].inner.flags() != flags
+                    || env_lck[&env_hash].inner.labels()
+                        != &tags
+                            .iter()
+                            .map(|t| tag_hash!(t))
+                            .collect::<SmallVec<[u64; 8]>>()
+                {
+                    env_lck.entry(env_hash).and_modify(|entry| {
+                        entry.inner.set_flags(flags);
+                        entry.inner.labels_mut().clear();
+                        entry
+                            .inner
+                            .labels_mut()
+                            .extend(tags.iter().map(|t| tag_hash!(t)));
+                    });
+                    if flags.contains(Flag::SEEN) {
+                        unseen.lock().unwrap().remove(env_hash);
+                    } else {
+                        unseen.lock().unwrap().insert_new(env_hash);
+                    }
+                    refresh_events.push((
+                        uid,
+                        RefreshEvent {
+                            mailbox_hash,
+                            account_hash: self.uid_store.account_hash,
+                            kind: RefreshEventKind::NewFlags(env_hash, (flags, tags)),
+                        },
+                    ));
+                }
+            }
+        }
+
+        // 3.  tag1 UID FETCH <lastseenuid+1>:* <descriptors>
+        let mut new_unseen = BTreeSet::default();
+        self.send_command(
+            format!(
+                "UID FETCH {}:* (UID FLAGS ENVELOPE BODY.PEEK[HEADER.FIELDS (REFERENCES)] BODYSTRUCTURE)",
+                cached_max_uid + 1,
+            )
+            .as_bytes(),
+        )
+        .await?;
+        self.read_response(&mut response, RequiredResponses::FETCH_REQUIRED)
+            .await?;
+        let (_, mut v, _) = protocol_parser::fetch_responses(&response)?;
+        /* "UID FETCH <n>:*" always returns the message with the highest UID, even if it is
+         * older than <n>. */
+        v.retain(|r| r.uid.map(|uid| uid > cached_max_uid).unwrap_or(false));
+        debug!("responses len is {}", v.len());
+        for FetchResponse {
+            ref uid,
+            ref mut envelope,
+            ref mut flags,
+            ref references,
+            ..
+        } in v.iter_mut()
+        {
+            let uid = uid.unwrap();
+            let env = envelope.as_mut().unwrap();
+            env.set_hash(generate_envelope_hash(&mailbox_path, &uid));
+            if let Some(value) = references {
+                env.set_references(value);
+            }
+            let mut tag_lck = self.uid_store.collection.tag_index.write().unwrap();
+            if let Some((flags, keywords)) = flags {
+                env.set_flags(*flags);
+                if !env.is_seen() {
+                    new_unseen.insert(env.hash());
+                }
+                for f in keywords {
+                    let hash = tag_hash!(f);
+                    tag_lck.entry(hash).or_insert_with(|| f.to_string());
+                    env.labels_mut().push(hash);
+                }
+            }
+        }
+        cache_handle
+            .insert_envelopes(mailbox_hash, &v)
+            .chain_err_summary(|| {
+                format!(
+                    "Could not save envelopes in cache for mailbox {}",
+                    mailbox_path
+                )
+            })?;
+        for FetchResponse { uid, envelope, .. } in v {
+            let uid = uid.unwrap();
+            let env = envelope.unwrap();
+            self.uid_store
+                .hash_index
+                .lock()
+                .unwrap()
+                .insert(env.hash(), (uid, mailbox_hash));
+            self.uid_store
+                .uid_index
+                .lock()
+                .unwrap()
+                .insert((mailbox_hash, uid), env.hash());
+            payload.push((uid, env));
+        }
+        let payload_hash_set: BTreeSet<_> =
+            payload.iter().map(|(_, env)| env.hash()).collect::<_>();
+        {
+            let mut unseen_lck = unseen.lock().unwrap();
+            for &seen_env_hash in payload_hash_set.difference(&new_unseen) {
+                unseen_lck.remove(seen_env_hash);
+            }
+            unseen_lck.insert_set(new_unseen);
+        }
+        mailbox_exists.lock().unwrap().insert_set(payload_hash_set);
+        self.uid_store
+            .highestmodseqs
+            .lock()
+            .unwrap()
+            .insert(mailbox_hash, Ok(new_highestmodseq));
+        cache_handle.update(mailbox_hash, &refresh_events)?;
+        for (_uid, ev) in refresh_events {
+            self.add_refresh_event(ev);
+        }
+        Ok(Some(payload.into_iter().map(|(_, env)| env).collect()))
     }
 
     pub async fn init_mailbox(&mut self, mailbox_hash: MailboxHash) -> Result<SelectResponse> {
@@ -24,11 +24,13 @@ use crate::backends::{MailboxHash, RefreshEvent};
 use crate::connections::{lookup_ipv4, timeout, Connection};
 use crate::email::parser::BytesExt;
 use crate::error::*;
+use crate::sasl::{SaslMechanism, SaslSession};
+use data_encoding::BASE64;
 extern crate native_tls;
 use futures::io::{AsyncReadExt, AsyncWriteExt};
 use native_tls::TlsConnector;
 pub use smol::Async as AsyncWrapper;
-use std::collections::HashSet;
+use std::collections::{BTreeSet, HashSet};
 use std::convert::TryFrom;
 use std::future::Future;
 use std::iter::FromIterator;
@@ -38,8 +40,9 @@ use std::time::{Duration, Instant, SystemTime};
 
 const IMAP_PROTOCOL_TIMEOUT: Duration = Duration::from_secs(60 * 28);
 
+use super::managesieve;
 use super::protocol_parser;
-use super::{Capabilities, ImapServerConf, UIDStore};
+use super::{Capabilities, ImapServerConf, ModSequence, UIDStore, UID, UIDVALIDITY};
 
 #[derive(Debug, Clone, Copy)]
 pub enum SyncPolicy {
@@ -60,7 +63,9 @@ pub enum ImapProtocol {
 #[derive(Debug, Clone, Copy)]
 pub struct ImapExtensionUse {
     pub condstore: bool,
+    pub qresync: bool,
     pub idle: bool,
+    pub notify: bool,
     #[cfg(feature = "deflate_compression")]
     pub deflate: bool,
     pub oauth2: bool,
@@ -70,7 +75,9 @@ impl Default for ImapExtensionUse {
     fn default() -> Self {
         Self {
             condstore: true,
+            qresync: true,
             idle: true,
+            notify: true,
             #[cfg(feature = "deflate_compression")]
             deflate: true,
             oauth2: false,
@@ -125,6 +132,18 @@ impl ImapStream {
             if server_conf.danger_accept_invalid_certs {
                 connector.danger_accept_invalid_certs(true);
             }
+            if let Some((ref path, ref password)) = server_conf.client_certificate {
+                let der = std::fs::read(path).chain_err_summary(|| {
+                    format!("Could not read client certificate {}", path.display())
+                })?;
+                connector.identity(
+                    native_tls::Identity::from_pkcs12(&der, password)
+                        .chain_err_summary(|| {
+                            format!("Could not load client certificate {}", path.display())
+                        })
+                        .chain_err_kind(crate::error::ErrorKind::Authentication)?,
+                );
+            }
             let connector = connector
                 .build()
                 .chain_err_kind(crate::error::ErrorKind::Network)?;
@@ -163,11 +182,20 @@ impl ImapStream {
                         .chain_err_summary(err_fn)
                         .chain_err_kind(crate::error::ErrorKind::Network)?,
                     ImapProtocol::ManageSieve => {
-                        socket
-                            .read(&mut buf)
-                            .await
-                            .chain_err_summary(err_fn)
-                            .chain_err_kind(crate::error::ErrorKind::Network)?;
+                        /* Read the capability greeting before asking for STARTTLS; the server
+                         * sends it again once the TLS layer is in place. */
+                        let mut greeting = Vec::with_capacity(1024);
+                        while !managesieve::response_is_complete(&greeting) {
+                            let len = socket
+                                .read(&mut buf)
+                                .await
+                                .chain_err_summary(err_fn)
+                                .chain_err_kind(crate::error::ErrorKind::Network)?;
+                            if len == 0 {
+                                break;
+                            }
+                            greeting.extend_from_slice(&buf[0..len]);
+                        }
                         socket
                             .write_all(b"STARTTLS\r\n")
                             .await
@@ -200,7 +228,11 @@ impl ImapStream {
                             }
                         }
                         ImapProtocol::ManageSieve => {
-                            if response.starts_with(b"OK ") && response.find(b"\r\n").is_some() {
+                            if managesieve::response_is_complete(&response) {
+                                managesieve::split_response(&response)?
+                                    .1
+                                    .into_result()
+                                    .chain_err_summary(err_fn)?;
                                 response.clear();
                                 broken = true;
                                 break;
@@ -292,8 +324,24 @@ impl ImapStream {
             timeout: server_conf.timeout,
         };
         if let ImapProtocol::ManageSieve = server_conf.protocol {
-            use data_encoding::BASE64;
             ret.read_response(&mut res).await?;
+            let capabilities = managesieve::managesieve_capabilities_set(
+                &managesieve::managesieve_capabilities(&res).map_err(|err| {
+                    MeliError::new(format!(
+                        "Could not connect to {}: expected ManageSieve capabilities but got:{}",
+                        &server_conf.server_hostname,
+                        String::from_utf8_lossy(&res)
+                    ))
+                    .set_source(Some(Arc::new(err)))
+                })?,
+            );
+            if !capabilities.contains(&b"SASL=PLAIN"[..]) {
+                return Err(MeliError::new(format!(
+                    "Could not connect to {}: server does not support SASL PLAIN authentication.",
+                    &server_conf.server_hostname
+                ))
+                .set_err_kind(crate::error::ErrorKind::Authentication));
+            }
             ret.send_command(
                 format!(
                     "AUTHENTICATE \"PLAIN\" \"{}\"",
@@ -309,7 +357,14 @@ impl ImapStream {
             )
             .await?;
             ret.read_response(&mut res).await?;
-            return Ok((Default::default(), ret));
+            managesieve::split_response(&res)?
+                .1
+                .into_result()
+                .chain_err_summary(|| {
+                    format!("Could not connect to {}", &server_conf.server_hostname)
+                })
+                .chain_err_kind(crate::error::ErrorKind::Authentication)?;
+            return Ok((capabilities, ret));
         }
 
         ret.send_command(b"CAPABILITY").await?;
@@ -342,17 +397,24 @@ impl ImapStream {
                 "Could not connect to {}: server is not IMAP4rev1 compliant",
                 &server_conf.server_hostname
             )));
-        } else if capabilities
-            .iter()
-            .any(|cap| cap.eq_ignore_ascii_case(b"LOGINDISABLED"))
-        {
-            return Err(MeliError::new(format!(
-                "Could not connect to {}: server does not accept logins [LOGINDISABLED]",
-                &server_conf.server_hostname
-            ))
-            .set_err_kind(crate::error::ErrorKind::Authentication));
         }
+        let login_disabled = capabilities
+            .iter()
+            .any(|cap| cap.eq_ignore_ascii_case(b"LOGINDISABLED"));
+        let auth_mechanisms = capabilities
+            .iter()
+            .filter(|cap| cap.len() > b"AUTH=".len() && cap[..5].eq_ignore_ascii_case(b"AUTH="))
+            .map(|cap| String::from_utf8_lossy(&cap[5..]).to_string())
+            .collect::<Vec<String>>();
+        let returned_capabilities = || {
+            capabilities
+                .iter()
+                .map(|capability| String::from_utf8_lossy(capability).to_string())
+                .collect::<Vec<String>>()
+                .join(" ")
+        };
 
+        let mut sasl_session: Option<SaslSession> = None;
         match server_conf.protocol {
             ImapProtocol::IMAP {
                 extension_use: ImapExtensionUse { oauth2, .. },
@@ -362,10 +424,10 @@ impl ImapStream {
                     .any(|cap| cap.eq_ignore_ascii_case(b"AUTH=XOAUTH2"))
                 {
                     return Err(MeliError::new(format!(
-                                "Could not connect to {}: OAUTH2 is enabled but server did not return AUTH=XOAUTH2 capability. Returned capabilities were: {}",
-                                &server_conf.server_hostname,
-                                capabilities.iter().map(|capability|
-                                    String::from_utf8_lossy(capability).to_string()).collect::<Vec<String>>().join(" ")
+                        "Could not connect to {}: OAUTH2 is enabled but server did not return \
+                         AUTH=XOAUTH2 capability. Returned capabilities were: {}",
+                        &server_conf.server_hostname,
+                        returned_capabilities()
                     )));
                 }
                 ret.send_command(
@@ -374,24 +436,101 @@ impl ImapStream {
                 .await?;
             }
             _ => {
-                ret.send_command(
-                    format!(
-                        r#"LOGIN "{}" {{{}}}"#,
-                        &server_conf
-                            .server_username
-                            .replace(r#"\"#, r#"\\"#)
-                            .replace('"', r#"\""#)
-                            .replace('{', r#"\{"#)
-                            .replace('}', r#"\}"#),
-                        &server_conf.server_password.as_bytes().len()
+                let mechanism = match server_conf.auth_mechanism {
+                    Some(mechanism) => {
+                        if !auth_mechanisms
+                            .iter()
+                            .any(|m| m.eq_ignore_ascii_case(mechanism.name()))
+                        {
+                            return Err(MeliError::new(format!(
+                                "Could not connect to {}: auth_mechanism is set to {} but server \
+                                 did not return AUTH={} capability. Returned capabilities were: {}",
+                                &server_conf.server_hostname,
+                                mechanism,
+                                mechanism,
+                                returned_capabilities()
+                            ))
+                            .set_err_kind(crate::error::ErrorKind::Authentication));
+                        }
+                        Some(mechanism)
+                    }
+                    /* Prefer SCRAM if available, otherwise use LOGIN unless the server has
+                     * disabled it. */
+                    None => {
+                        match SaslMechanism::negotiate(auth_mechanisms.iter().map(String::as_str)) {
+                            Some(m @ SaslMechanism::ScramSha256)
+                            | Some(m @ SaslMechanism::ScramSha1) => Some(m),
+                            Some(m) if login_disabled => Some(m),
+                            _ if login_disabled => {
+                                return Err(MeliError::new(format!(
+                                    "Could not connect to {}: server does not accept logins \
+                                 [LOGINDISABLED]",
+                                    &server_conf.server_hostname
+                                ))
+                                .set_err_kind(crate::error::ErrorKind::Authentication));
+                            }
+                            _ => None,
+                        }
+                    }
+                };
+                if let Some(mechanism) = mechanism {
+                    let mut session = SaslSession::new(
+                        mechanism,
+                        &server_conf.server_username,
+                        &server_conf.server_password,
+                        &server_conf.server_hostname,
+                        server_conf.server_port,
+                    );
+                    let has_sasl_ir = capabilities
+                        .iter()
+                        .any(|cap| cap.eq_ignore_ascii_case(b"SASL-IR"));
+                    /* Without SASL-IR the initial response is sent when the server asks for it
+                     * with an empty challenge. RFC 4959: an empty initial response is sent as
+                     * "=" */
+                    let initial_response = if has_sasl_ir {
+                        session.initial_response()
+                    } else {
+                        None
+                    };
+                    match initial_response {
+                        Some(initial_response) => {
+                            let initial_response = if initial_response.is_empty() {
+                                "=".to_string()
+                            } else {
+                                BASE64.encode(&initial_response)
+                            };
+                            ret.send_command(
+                                format!("AUTHENTICATE {} {}", mechanism, initial_response)
+                                    .as_bytes(),
+                            )
+                            .await?;
+                        }
+                        None => {
+                            ret.send_command(format!("AUTHENTICATE {}", mechanism).as_bytes())
+                                .await?;
+                        }
+                    }
+                    sasl_session = Some(session);
+                } else {
+                    ret.send_command(
+                        format!(
+                            r#"LOGIN "{}" {{{}}}"#,
+                            &server_conf
+                                .server_username
+                                .replace(r#"\"#, r#"\\"#)
+                                .replace('"', r#"\""#)
+                                .replace('{', r#"\{"#)
+                                .replace('}', r#"\}"#),
+                            &server_conf.server_password.as_bytes().len()
+                        )
+                        .as_bytes(),
                     )
-                    .as_bytes(),
-                )
-                .await?;
-                // wait for "+ Ready for literal data" reply
-                ret.wait_for_continuation_request().await?;
-                ret.send_literal(server_conf.server_password.as_bytes())
                     .await?;
+                    // wait for "+ Ready for literal data" reply
+                    ret.wait_for_continuation_request().await?;
+                    ret.send_literal(server_conf.server_password.as_bytes())
+                        .await?;
+                }
             }
         }
         let tag_start = format!("M{} ", (ret.cmd_id - 1));
@@ -401,6 +540,25 @@ impl ImapStream {
             ret.read_lines(&mut res, &[], false).await?;
             let mut should_break = false;
             for l in res.split_rn() {
+                if let Some(ref mut session) = sasl_session {
+                    if l.starts_with(b"+") {
+                        let challenge = BASE64.decode(l[1..].trim()).map_err(|err| {
+                            MeliError::new(format!(
+                                "Could not connect to {}: invalid {} challenge: {}",
+                                &server_conf.server_hostname,
+                                session.mechanism(),
+                                String::from_utf8_lossy(l)
+                            ))
+                            .set_source(Some(Arc::new(err)))
+                            .set_err_kind(crate::error::ErrorKind::Authentication)
+                        })?;
+                        let response = session.step(&challenge).chain_err_summary(|| {
+                            format!("Could not connect to {}", &server_conf.server_hostname)
+                        })?;
+                        ret.send_raw(BASE64.encode(&response).as_bytes()).await?;
+                        continue;
+                    }
+                }
                 if l.starts_with(b"* CAPABILITY") {
                     capabilities = protocol_parser::capabilities(l)
                         .map(|(_, capabilities)| {
@@ -417,6 +575,11 @@ impl ImapStream {
                         ))
                         .set_err_kind(crate::error::ErrorKind::Authentication));
                     }
+                    if let Some(ref session) = sasl_session {
+                        session.finish().chain_err_summary(|| {
+                            format!("Could not connect to {}", &server_conf.server_hostname)
+                        })?;
+                    }
                     should_break = true;
                 }
             }
@@ -443,12 +606,36 @@ impl ImapStream {
     pub async fn read_response(&mut self, ret: &mut Vec<u8>) -> Result<()> {
         let id = match self.protocol {
             ImapProtocol::IMAP { .. } => format!("M{} ", self.cmd_id - 1).into_bytes(),
-            ImapProtocol::ManageSieve => Vec::new(),
+            ImapProtocol::ManageSieve => {
+                return self.read_managesieve_response(ret).await;
+            }
         };
         self.read_lines(ret, &id, true).await?;
         Ok(())
     }
 
+    /// ManageSieve responses have no tags; read until the final `OK`/`NO`/`BYE` line, taking
+    /// care to not mistake the contents of literals (e.g. scripts) for status lines.
+    async fn read_managesieve_response(&mut self, ret: &mut Vec<u8>) -> Result<()> {
+        let mut buf: Vec<u8> = vec![0; Connection::IO_BUF_SIZE];
+        ret.clear();
+        while !managesieve::response_is_complete(ret) {
+            match timeout(self.timeout, self.stream.read(&mut buf)).await? {
+                Ok(0) => {
+                    return Err(MeliError::new("Disconnected")
+                        .set_err_kind(crate::error::ErrorKind::Network));
+                }
+                Ok(b) => {
+                    ret.extend_from_slice(&buf[0..b]);
+                }
+                Err(e) => {
+                    return Err(MeliError::from(e).set_err_kind(crate::error::ErrorKind::Network));
+                }
+            }
+        }
+        Ok(())
+    }
+
     pub async fn read_lines(
         &mut self,
         ret: &mut Vec<u8>,
@@ -526,12 +713,14 @@ impl ImapStream {
                 self.stream.flush().await?;
                 match self.protocol {
                     ImapProtocol::IMAP { .. } => {
-                        if !command.starts_with(b"LOGIN") {
+                        if command.starts_with(b"LOGIN") {
+                            debug!("sent: M{} LOGIN ..", self.cmd_id - 1);
+                        } else if command.starts_with(b"AUTHENTICATE") {
+                            debug!("sent: M{} AUTHENTICATE ..", self.cmd_id - 1);
+                        } else {
                             debug!("sent: M{} {}", self.cmd_id - 1, unsafe {
                                 std::str::from_utf8_unchecked(command)
                             });
-                        } else {
-                            debug!("sent: M{} LOGIN ..", self.cmd_id - 1);
                         }
                     }
                     ImapProtocol::ManageSieve => {}
@@ -637,15 +826,36 @@ impl ImapConnection {
                     extension_use:
                         ImapExtensionUse {
                             condstore,
+                            qresync,
                             #[cfg(feature = "deflate_compression")]
                             deflate,
                             idle: _idle,
+                            notify: _,
                             oauth2: _,
                         },
                 } => {
                     if capabilities.contains(&b"CONDSTORE"[..]) && condstore {
                         match self.sync_policy {
                             SyncPolicy::None => { /* do nothing, sync is disabled */ }
+                            _ if capabilities.contains(&b"ENABLE"[..])
+                                && capabilities.contains(&b"QRESYNC"[..])
+                                && qresync =>
+                            {
+                                /* Upgrade to Condstore and Qresync. Enabling QRESYNC implies
+                                 * CONDSTORE. */
+                                let mut ret = Vec::new();
+                                self.send_command(b"ENABLE QRESYNC").await?;
+                                self.read_response(&mut ret, RequiredResponses::empty())
+                                    .await?;
+                                if ret.split_rn().any(|l| {
+                                    l.starts_with(b"* ENABLED")
+                                        && l.contains_subsequence(b"QRESYNC")
+                                }) {
+                                    self.sync_policy = SyncPolicy::CondstoreQresync;
+                                } else {
+                                    self.sync_policy = SyncPolicy::Condstore;
+                                }
+                            }
                             _ => {
                                 /* Upgrade to Condstore */
                                 let mut ret = Vec::new();
@@ -863,6 +1073,38 @@ impl ImapConnection {
         {
             return Ok(None);
         }
+        self.select_mailbox_with(mailbox_hash, ret, None)
+            .await
+            .map(Some)
+    }
+
+    /// `SELECT` a mailbox with the `QRESYNC` parameter (RFC 7162), which makes the server include
+    /// `VANISHED (EARLIER)` responses for expunged messages out of `known_uids` and `FETCH`
+    /// responses for messages changed since `modseq` in the response.
+    pub async fn select_mailbox_qresync(
+        &mut self,
+        mailbox_hash: MailboxHash,
+        ret: &mut Vec<u8>,
+        uidvalidity: UIDVALIDITY,
+        modseq: ModSequence,
+        known_uids: &BTreeSet<UID>,
+    ) -> Result<SelectResponse> {
+        let mut parameter = format!(" (QRESYNC ({} {}", uidvalidity, modseq);
+        if !known_uids.is_empty() {
+            parameter.push(' ');
+            parameter.push_str(&protocol_parser::format_sequence_set(known_uids));
+        }
+        parameter.push_str("))");
+        self.select_mailbox_with(mailbox_hash, ret, Some(parameter))
+            .await
+    }
+
+    async fn select_mailbox_with(
+        &mut self,
+        mailbox_hash: MailboxHash,
+        ret: &mut Vec<u8>,
+        qresync_parameter: Option<String>,
+    ) -> Result<SelectResponse> {
         let (imap_path, no_select, permissions) = {
             let m = &self.uid_store.mailboxes.lock().await[&mailbox_hash];
             (
@@ -878,10 +1120,28 @@ impl ImapConnection {
             ))
             .set_kind(crate::error::ErrorKind::Bug));
         }
-        self.send_command(format!("SELECT \"{}\"", imap_path).as_bytes())
-            .await?;
-        self.read_response(ret, RequiredResponses::SELECT_REQUIRED)
-            .await?;
+        self.send_command(
+            format!(
+                "SELECT \"{}\"{}",
+                imap_path,
+                qresync_parameter.as_deref().unwrap_or_default()
+            )
+            .as_bytes(),
+        )
+        .await?;
+        /* Keep the VANISHED and FETCH responses of QRESYNC in the response instead of processing
+         * them as unsolicited responses of the previously selected mailbox. */
+        self.read_response(
+            ret,
+            if qresync_parameter.is_some() {
+                RequiredResponses::SELECT_REQUIRED
+                    | RequiredResponses::FETCH
+                    | RequiredResponses::VANISHED
+            } else {
+                RequiredResponses::SELECT_REQUIRED
+            },
+        )
+        .await?;
         debug!(
             "{} select response {}",
             imap_path,
@@ -939,7 +1199,7 @@ impl ImapConnection {
             self.create_uid_msn_cache(mailbox_hash, 1, &select_response)
                 .await?;
         }
-        Ok(Some(select_response))
+        Ok(select_response)
     }
 
     pub async fn examine_mailbox(
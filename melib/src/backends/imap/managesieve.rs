@@ -19,14 +19,24 @@
  * along with meli. If not, see <http://www.gnu.org/licenses/>.
  */
 
-use super::{ImapConnection, ImapProtocol, ImapServerConf, UIDStore};
+use super::{ImapConnection, ImapProtocol, ImapServerConf, RequiredResponses, UIDStore};
 use crate::conf::AccountSettings;
+use crate::email::parser::BytesExt;
 use crate::error::{MeliError, Result};
 use crate::get_conf_val;
 use nom::{
-    branch::alt, bytes::complete::tag, combinator::map, error::Error as NomError, error::ErrorKind,
-    multi::separated_list1, sequence::separated_pair, IResult,
+    branch::alt,
+    bytes::complete::{is_not, tag, tag_no_case, take},
+    character::complete::digit1,
+    combinator::{map, map_res, opt},
+    error::Error as NomError,
+    error::ErrorKind,
+    multi::{many0, separated_list1},
+    sequence::{delimited, pair, preceded, separated_pair, terminated},
+    IResult,
 };
+use std::future::Future;
+use std::pin::Pin;
 use std::str::FromStr;
 use std::sync::{Arc, Mutex};
 use std::time::SystemTime;
@@ -78,17 +88,397 @@ pub fn quoted_raw(input: &[u8]) -> IResult<&[u8], &[u8]> {
     }))
 }
 
+/// Parse a `literal-s2c` string: `{N}` CRLF followed by N octets. The non-synchronizing form
+/// `{N+}` is also accepted.
+pub fn literal(input: &[u8]) -> IResult<&[u8], &[u8]> {
+    let (input, _) = tag(b"{")(input)?;
+    let (input, len) = map_res(digit1, |s: &[u8]| {
+        usize::from_str(unsafe { std::str::from_utf8_unchecked(s) })
+    })(input)?;
+    let (input, _) = opt(tag(b"+"))(input)?;
+    let (input, _) = tag(b"}\r\n")(input)?;
+    take(len)(input)
+}
+
+/// Parse a `string`, which is either a quoted string or a literal.
+pub fn string(input: &[u8]) -> IResult<&[u8], &[u8]> {
+    alt((quoted_raw, literal))(input)
+}
+
+/// Remove quoted-string escapes (`\\` and `\"`).
+fn unescape(input: &[u8]) -> Vec<u8> {
+    let mut ret = Vec::with_capacity(input.len());
+    let mut escaped = false;
+    for &b in input {
+        if b == b'\\' && !escaped {
+            escaped = true;
+            continue;
+        }
+        escaped = false;
+        ret.push(b);
+    }
+    ret
+}
+
+/// Quote a value so that it can be sent as a command argument.
+fn quoted(input: &[u8]) -> Result<Vec<u8>> {
+    if input.iter().any(|&b| b == b'\r' || b == b'\n' || b == 0) {
+        return Err(MeliError::new(format!(
+            "Invalid ManageSieve string `{}`: it must not contain CR, LF or NUL.",
+            String::from_utf8_lossy(input)
+        )));
+    }
+    let mut ret = Vec::with_capacity(input.len() + 2);
+    ret.push(b'"');
+    for &b in input {
+        if b == b'"' || b == b'\\' {
+            ret.push(b'\\');
+        }
+        ret.push(b);
+    }
+    ret.push(b'"');
+    Ok(ret)
+}
+
+/// The final status line that terminates every ManageSieve response.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ManageSieveResponse<'a> {
+    Ok {
+        code: Option<&'a [u8]>,
+        message: Option<&'a [u8]>,
+    },
+    No {
+        code: Option<&'a [u8]>,
+        message: Option<&'a [u8]>,
+    },
+    Bye {
+        code: Option<&'a [u8]>,
+        message: Option<&'a [u8]>,
+    },
+}
+
+impl ManageSieveResponse<'_> {
+    pub fn is_ok(&self) -> bool {
+        matches!(self, ManageSieveResponse::Ok { .. })
+    }
+
+    pub fn code(&self) -> Option<&[u8]> {
+        match self {
+            ManageSieveResponse::Ok { code, .. }
+            | ManageSieveResponse::No { code, .. }
+            | ManageSieveResponse::Bye { code, .. } => *code,
+        }
+    }
+
+    pub fn message(&self) -> Option<&[u8]> {
+        match self {
+            ManageSieveResponse::Ok { message, .. }
+            | ManageSieveResponse::No { message, .. }
+            | ManageSieveResponse::Bye { message, .. } => *message,
+        }
+    }
+
+    /// Convert `NO` and `BYE` responses into errors, keeping the response code and the human
+    /// readable message of the server.
+    pub fn into_result(self) -> Result<()> {
+        let (kind, summary) = match self {
+            ManageSieveResponse::Ok { .. } => return Ok(()),
+            ManageSieveResponse::Bye { .. } => (
+                crate::error::ErrorKind::Network,
+                "ManageSieve server closed the connection",
+            ),
+            ManageSieveResponse::No { .. } => {
+                let code = self.code().unwrap_or_default();
+                if code.starts_with_ignore_ascii_case(b"AUTH-TOO-WEAK")
+                    || code.starts_with_ignore_ascii_case(b"ENCRYPT-NEEDED")
+                    || code.starts_with_ignore_ascii_case(b"TRANSITION-NEEDED")
+                {
+                    (
+                        crate::error::ErrorKind::Authentication,
+                        "ManageSieve authentication failed",
+                    )
+                } else if code.starts_with_ignore_ascii_case(b"QUOTA") {
+                    (
+                        crate::error::ErrorKind::External,
+                        "ManageSieve quota exceeded",
+                    )
+                } else if code.starts_with_ignore_ascii_case(b"TRYLATER") {
+                    (
+                        crate::error::ErrorKind::Timeout,
+                        "ManageSieve server is temporarily unavailable",
+                    )
+                } else {
+                    (
+                        crate::error::ErrorKind::External,
+                        "ManageSieve server replied with NO",
+                    )
+                }
+            }
+        };
+        let mut details = String::new();
+        if let Some(code) = self.code() {
+            details.push('(');
+            details.push_str(&String::from_utf8_lossy(code));
+            details.push(')');
+        }
+        if let Some(message) = self.message() {
+            if !details.is_empty() {
+                details.push(' ');
+            }
+            details.push_str(&String::from_utf8_lossy(&unescape(message)));
+        }
+        Err(MeliError::new(details).set_summary(summary).set_kind(kind))
+    }
+}
+
+trait StartsWithIgnoreCase {
+    fn starts_with_ignore_ascii_case(&self, prefix: &[u8]) -> bool;
+}
+
+impl StartsWithIgnoreCase for [u8] {
+    fn starts_with_ignore_ascii_case(&self, prefix: &[u8]) -> bool {
+        self.len() >= prefix.len() && self[..prefix.len()].eq_ignore_ascii_case(prefix)
+    }
+}
+
+/// Parse a `response-ok`, `response-nobye` status line.
+pub fn response(input: &[u8]) -> IResult<&[u8], ManageSieveResponse<'_>> {
+    let (input, status) =
+        alt((tag_no_case(b"OK"), tag_no_case(b"NO"), tag_no_case(b"BYE")))(input)?;
+    let (input, code) = opt(preceded(
+        tag(b" "),
+        delimited(tag(b"("), is_not(")"), tag(b")")),
+    ))(input)?;
+    let (input, message) = opt(preceded(tag(b" "), string))(input)?;
+    let (input, _) = tag(b"\r\n")(input)?;
+    Ok((
+        input,
+        if status.eq_ignore_ascii_case(b"OK") {
+            ManageSieveResponse::Ok { code, message }
+        } else if status.eq_ignore_ascii_case(b"NO") {
+            ManageSieveResponse::No { code, message }
+        } else {
+            ManageSieveResponse::Bye { code, message }
+        },
+    ))
+}
+
+/// If `line` ends with a literal prefix (`{N}` or `{N+}`), return `N`.
+fn literal_length(line: &[u8]) -> Option<usize> {
+    if !line.ends_with(b"}") {
+        return None;
+    }
+    let start = line.rfind(b"{")?;
+    let digits = &line[start + 1..line.len() - 1];
+    let digits = digits.strip_suffix(b"+").unwrap_or(digits);
+    if digits.is_empty() || !digits.iter().all(u8::is_ascii_digit) {
+        return None;
+    }
+    usize::from_str(unsafe { std::str::from_utf8_unchecked(digits) }).ok()
+}
+
+/// Return the offset of the status line that terminates the response in `input`, skipping over
+/// the contents of any literals. Returns `None` if the response is not complete yet.
+fn status_line_offset(input: &[u8]) -> Option<usize> {
+    let mut line_start = 0;
+    let mut pos = 0;
+    while let Some(eol) = input[pos..].find(b"\r\n").map(|p| p + pos) {
+        if let Some(len) = literal_length(&input[pos..eol]) {
+            pos = eol + 2 + len;
+            if pos > input.len() {
+                return None;
+            }
+            continue;
+        }
+        let line = &input[line_start..eol];
+        if [&b"OK"[..], b"NO", b"BYE"].iter().any(|status| {
+            line.starts_with_ignore_ascii_case(status)
+                && (line.len() == status.len() || line[status.len()] == b' ')
+        }) {
+            return Some(line_start);
+        }
+        line_start = eol + 2;
+        pos = line_start;
+    }
+    None
+}
+
+/// Whether `input` contains a whole response, i.e. everything up to and including its status
+/// line.
+pub fn response_is_complete(input: &[u8]) -> bool {
+    status_line_offset(input).is_some()
+}
+
+/// Split a complete response into its data lines and its status line.
+pub fn split_response(input: &[u8]) -> Result<(&[u8], ManageSieveResponse<'_>)> {
+    let offset = status_line_offset(input).ok_or_else(|| {
+        MeliError::new(format!(
+            "Incomplete ManageSieve response: {}",
+            String::from_utf8_lossy(input)
+        ))
+    })?;
+    let (_, status) = response(&input[offset..])?;
+    Ok((&input[..offset], status))
+}
+
+/// Parse the data lines of a `LISTSCRIPTS` response into `(script name, is active)` pairs.
+pub fn listscripts(input: &[u8]) -> Result<Vec<(Vec<u8>, bool)>> {
+    let (_, ret) = many0(terminated(
+        pair(string, opt(tag_no_case(b" ACTIVE"))),
+        tag(b"\r\n"),
+    ))(input)?;
+    Ok(ret
+        .into_iter()
+        .map(|(name, active)| (unescape(name), active.is_some()))
+        .collect())
+}
+
+/// Parse the data lines of a `GETSCRIPT` response into the script source.
+pub fn getscript(input: &[u8]) -> Result<Vec<u8>> {
+    let (_, ret) = terminated(
+        alt((map(quoted_raw, unescape), map(literal, <[u8]>::to_vec))),
+        tag(b"\r\n"),
+    )(input)?;
+    Ok(ret)
+}
+
+/// Convert the parsed capability list into the same form as IMAP capabilities, e.g.
+/// `SASL=PLAIN` and `SIEVE=fileinto` next to the bare capability names.
+pub(super) fn managesieve_capabilities_set(capabilities: &[(&[u8], &[u8])]) -> super::Capabilities {
+    let mut ret = super::Capabilities::default();
+    for (name, value) in capabilities {
+        let name = name.to_ascii_uppercase();
+        for v in value.split(|&b| b == b' ').filter(|v| !v.is_empty()) {
+            let mut cap = name.clone();
+            cap.push(b'=');
+            cap.extend_from_slice(v);
+            ret.insert(cap);
+        }
+        ret.insert(name);
+    }
+    ret
+}
+
+#[test]
+fn test_managesieve_responses() {
+    assert!(!response_is_complete(b"\"IMPLEMENTATION\" \"Dovecot\"\r\n"));
+    assert!(response_is_complete(
+        b"\"IMPLEMENTATION\" \"Dovecot\"\r\n\"STARTTLS\"\r\nOK \"Ready.\"\r\n"
+    ));
+    assert!(response_is_complete(b"OK\r\n"));
+    assert!(!response_is_complete(b"{22}\r\nOK this is not the end"));
+    assert!(!response_is_complete(
+        b"{28}\r\nrequire \"fileinto\";\r\nOK x\r\n"
+    ));
+    assert!(response_is_complete(
+        b"{25}\r\nrequire \"fileinto\";\r\nOK x\r\nOK\r\n"
+    ));
+
+    let (data, status) = split_response(
+        b"\"summer_script\"\r\n\"vacation_script\" ACTIVE\r\n{13}\r\nclever\"script\r\nOK\r\n",
+    )
+    .unwrap();
+    assert!(status.is_ok());
+    assert_eq!(
+        listscripts(data).unwrap(),
+        vec![
+            (b"summer_script".to_vec(), false),
+            (b"vacation_script".to_vec(), true),
+            (b"clever\"script".to_vec(), false),
+        ]
+    );
+
+    let (data, status) = split_response(
+        b"{54}\r\n#this is my wonderful script\r\nreject \"I reject all\";\r\n\r\nOK\r\n",
+    )
+    .unwrap();
+    assert!(status.is_ok());
+    assert_eq!(
+        getscript(data).unwrap(),
+        b"#this is my wonderful script\r\nreject \"I reject all\";\r\n".to_vec()
+    );
+
+    let (_, status) = split_response(b"NO (QUOTA/MAXSIZE) \"Quota exceeded\"\r\n").unwrap();
+    assert_eq!(
+        status,
+        ManageSieveResponse::No {
+            code: Some(&b"QUOTA/MAXSIZE"[..]),
+            message: Some(&b"Quota exceeded"[..]),
+        }
+    );
+    let err = status.into_result().unwrap_err();
+    assert_eq!(err.kind, crate::error::ErrorKind::External);
+    assert_eq!(err.details.as_ref(), "(QUOTA/MAXSIZE) Quota exceeded");
+
+    let (_, status) = split_response(b"NO {12}\r\nline1\r\nline2\r\n").unwrap();
+    assert_eq!(
+        status,
+        ManageSieveResponse::No {
+            code: None,
+            message: Some(&b"line1\r\nline2"[..]),
+        }
+    );
+
+    let caps = managesieve_capabilities_set(
+        &managesieve_capabilities(b"\"SASL\" \"PLAIN LOGIN\"\r\n\"STARTTLS\"\r\nOK\r\n").unwrap(),
+    );
+    assert!(caps.contains(&b"SASL=PLAIN"[..]));
+    assert!(caps.contains(&b"SASL=LOGIN"[..]));
+    assert!(caps.contains(&b"STARTTLS"[..]));
+}
+
+/// Names of the stored scripts, each with whether it is the active one.
+pub type ScriptList = Vec<(Vec<u8>, bool)>;
+
+/// Script management operations of RFC 5804 "A Protocol for Remotely Managing Sieve Scripts".
 pub trait ManageSieve {
-    fn havespace(&mut self) -> Result<()>;
-    fn putscript(&mut self) -> Result<()>;
+    /// Ask the server whether a script of `size` octets named `script_name` can be stored.
+    fn havespace<'a>(
+        &'a mut self,
+        script_name: &'a [u8],
+        size: usize,
+    ) -> Pin<Box<dyn Future<Output = Result<()>> + Send + 'a>>;
 
-    fn listscripts(&mut self) -> Result<()>;
-    fn setactive(&mut self) -> Result<()>;
+    /// Store `script` as `script_name`, replacing any script with the same name. The server
+    /// validates the script and refuses it if it contains errors.
+    fn putscript<'a>(
+        &'a mut self,
+        script_name: &'a [u8],
+        script: &'a [u8],
+    ) -> Pin<Box<dyn Future<Output = Result<()>> + Send + 'a>>;
 
-    fn getscript(&mut self) -> Result<()>;
+    /// Validate `script` without storing it.
+    fn checkscript<'a>(
+        &'a mut self,
+        script: &'a [u8],
+    ) -> Pin<Box<dyn Future<Output = Result<()>> + Send + 'a>>;
 
-    fn deletescript(&mut self) -> Result<()>;
-    fn renamescript(&mut self) -> Result<()>;
+    /// List the stored scripts along with whether each is the active one.
+    fn listscripts<'a>(
+        &'a mut self,
+    ) -> Pin<Box<dyn Future<Output = Result<ScriptList>> + Send + 'a>>;
+
+    /// Mark `script_name` as the active script. An empty name deactivates all scripts.
+    fn setactive<'a>(
+        &'a mut self,
+        script_name: &'a [u8],
+    ) -> Pin<Box<dyn Future<Output = Result<()>> + Send + 'a>>;
+
+    fn getscript<'a>(
+        &'a mut self,
+        script_name: &'a [u8],
+    ) -> Pin<Box<dyn Future<Output = Result<Vec<u8>>> + Send + 'a>>;
+
+    fn deletescript<'a>(
+        &'a mut self,
+        script_name: &'a [u8],
+    ) -> Pin<Box<dyn Future<Output = Result<()>> + Send + 'a>>;
+
+    fn renamescript<'a>(
+        &'a mut self,
+        old_name: &'a [u8],
+        new_name: &'a [u8],
+    ) -> Pin<Box<dyn Future<Output = Result<()>> + Send + 'a>>;
 }
 
 pub fn new_managesieve_connection(
@@ -99,8 +489,11 @@ pub fn new_managesieve_connection(
 ) -> Result<ImapConnection> {
     let server_hostname = get_conf_val!(s["server_hostname"])?;
     let server_username = get_conf_val!(s["server_username"])?;
-    let server_password = get_conf_val!(s["server_password"])?;
-    let server_port = get_conf_val!(s["server_port"], 4190)?;
+    let server_password = super::server_password(s)?;
+    let server_port = get_conf_val!(s["managesieve_port"], 4190)?;
+    /* ManageSieve servers always start in plaintext and upgrade with STARTTLS. */
+    let use_tls = get_conf_val!(s["use_tls"], true)?;
+    let use_starttls = use_tls;
     let danger_accept_invalid_certs: bool = get_conf_val!(s["danger_accept_invalid_certs"], false)?;
     let timeout = get_conf_val!(s["timeout"], 16_u64)?;
     let timeout = if timeout == 0 {
@@ -111,11 +504,13 @@ pub fn new_managesieve_connection(
     let server_conf = ImapServerConf {
         server_hostname: server_hostname.to_string(),
         server_username: server_username.to_string(),
-        server_password: server_password.to_string(),
+        server_password,
         server_port,
-        use_starttls: true,
-        use_tls: true,
+        use_starttls,
+        use_tls,
         danger_accept_invalid_certs,
+        auth_mechanism: None,
+        client_certificate: None,
         protocol: ImapProtocol::ManageSieve,
         timeout,
     };
@@ -134,29 +529,160 @@ pub fn new_managesieve_connection(
     Ok(ImapConnection::new_connection(&server_conf, uid_store))
 }
 
+impl ImapConnection {
+    /// Send a ManageSieve command, optionally followed by a literal argument, and return the data
+    /// lines of the response if the server replied with `OK`.
+    async fn managesieve_command(
+        &mut self,
+        command: &[u8],
+        literal: Option<&[u8]>,
+    ) -> Result<Vec<u8>> {
+        self.connect().await?;
+        let mut ret = Vec::new();
+        if let Some(literal) = literal {
+            let mut raw = command.to_vec();
+            raw.extend_from_slice(format!(" {{{}+}}\r\n", literal.len()).as_bytes());
+            raw.extend_from_slice(literal);
+            self.send_raw(&raw).await?;
+        } else {
+            self.send_command(command).await?;
+        }
+        self.read_response(&mut ret, RequiredResponses::empty())
+            .await?;
+        let (data, status) = split_response(&ret)?;
+        if let ManageSieveResponse::Bye { .. } = status {
+            self.stream =
+                Err(MeliError::new("Offline: received BYE")
+                    .set_kind(crate::error::ErrorKind::Network));
+        }
+        status.into_result()?;
+        Ok(data.to_vec())
+    }
+
+    /// Capabilities advertised by the server, in the form produced by
+    /// [`managesieve_capabilities_set`].
+    pub fn managesieve_capabilities(&self) -> Vec<String> {
+        self.uid_store
+            .capabilities
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|c| String::from_utf8_lossy(c).into())
+            .collect::<Vec<String>>()
+    }
+
+    fn managesieve_has_capability(&self, capability: &[u8]) -> bool {
+        self.uid_store
+            .capabilities
+            .lock()
+            .unwrap()
+            .contains(capability)
+    }
+}
+
 impl ManageSieve for ImapConnection {
-    fn havespace(&mut self) -> Result<()> {
-        Ok(())
+    fn havespace<'a>(
+        &'a mut self,
+        script_name: &'a [u8],
+        size: usize,
+    ) -> Pin<Box<dyn Future<Output = Result<()>> + Send + 'a>> {
+        Box::pin(async move {
+            let mut command = b"HAVESPACE ".to_vec();
+            command.extend(quoted(script_name)?);
+            command.extend_from_slice(format!(" {}", size).as_bytes());
+            self.managesieve_command(&command, None).await?;
+            Ok(())
+        })
+    }
+
+    fn putscript<'a>(
+        &'a mut self,
+        script_name: &'a [u8],
+        script: &'a [u8],
+    ) -> Pin<Box<dyn Future<Output = Result<()>> + Send + 'a>> {
+        Box::pin(async move {
+            let mut command = b"PUTSCRIPT ".to_vec();
+            command.extend(quoted(script_name)?);
+            self.managesieve_command(&command, Some(script)).await?;
+            Ok(())
+        })
     }
-    fn putscript(&mut self) -> Result<()> {
-        Ok(())
+
+    fn checkscript<'a>(
+        &'a mut self,
+        script: &'a [u8],
+    ) -> Pin<Box<dyn Future<Output = Result<()>> + Send + 'a>> {
+        Box::pin(async move {
+            self.connect().await?;
+            if !self.managesieve_has_capability(b"VERSION") {
+                return Err(
+                    MeliError::new("ManageSieve server does not support CHECKSCRIPT.")
+                        .set_kind(crate::error::ErrorKind::NotSupported),
+                );
+            }
+            self.managesieve_command(b"CHECKSCRIPT", Some(script))
+                .await?;
+            Ok(())
+        })
     }
 
-    fn listscripts(&mut self) -> Result<()> {
-        Ok(())
+    fn listscripts<'a>(
+        &'a mut self,
+    ) -> Pin<Box<dyn Future<Output = Result<ScriptList>> + Send + 'a>> {
+        Box::pin(async move {
+            let data = self.managesieve_command(b"LISTSCRIPTS", None).await?;
+            listscripts(&data)
+        })
     }
-    fn setactive(&mut self) -> Result<()> {
-        Ok(())
+
+    fn setactive<'a>(
+        &'a mut self,
+        script_name: &'a [u8],
+    ) -> Pin<Box<dyn Future<Output = Result<()>> + Send + 'a>> {
+        Box::pin(async move {
+            let mut command = b"SETACTIVE ".to_vec();
+            command.extend(quoted(script_name)?);
+            self.managesieve_command(&command, None).await?;
+            Ok(())
+        })
     }
 
-    fn getscript(&mut self) -> Result<()> {
-        Ok(())
+    fn getscript<'a>(
+        &'a mut self,
+        script_name: &'a [u8],
+    ) -> Pin<Box<dyn Future<Output = Result<Vec<u8>>> + Send + 'a>> {
+        Box::pin(async move {
+            let mut command = b"GETSCRIPT ".to_vec();
+            command.extend(quoted(script_name)?);
+            let data = self.managesieve_command(&command, None).await?;
+            getscript(&data)
+        })
     }
 
-    fn deletescript(&mut self) -> Result<()> {
-        Ok(())
+    fn deletescript<'a>(
+        &'a mut self,
+        script_name: &'a [u8],
+    ) -> Pin<Box<dyn Future<Output = Result<()>> + Send + 'a>> {
+        Box::pin(async move {
+            let mut command = b"DELETESCRIPT ".to_vec();
+            command.extend(quoted(script_name)?);
+            self.managesieve_command(&command, None).await?;
+            Ok(())
+        })
     }
-    fn renamescript(&mut self) -> Result<()> {
-        Ok(())
+
+    fn renamescript<'a>(
+        &'a mut self,
+        old_name: &'a [u8],
+        new_name: &'a [u8],
+    ) -> Pin<Box<dyn Future<Output = Result<()>> + Send + 'a>> {
+        Box::pin(async move {
+            let mut command = b"RENAMESCRIPT ".to_vec();
+            command.extend(quoted(old_name)?);
+            command.push(b' ');
+            command.extend(quoted(new_name)?);
+            self.managesieve_command(&command, None).await?;
+            Ok(())
+        })
     }
 }
@@ -58,6 +58,7 @@ bitflags! {
         const SEARCH              = 0b0010_0000_0000_0000;
         const FETCH               = 0b0100_0000_0000_0000;
         const NO_REQUIRED         = 0b1000_0000_0000_0000;
+        const VANISHED            = 0b0001_0000_0000_0000_0000;
         const CAPABILITY_REQUIRED = Self::CAPABILITY.bits;
         const LOGOUT_REQUIRED     = Self::BYE.bits;
         const SELECT_REQUIRED     = Self::FLAGS.bits | Self::EXISTS.bits | Self::RECENT.bits | Self::UNSEEN.bits | Self::PERMANENTFLAGS.bits | Self::UIDNEXT.bits | Self::UIDVALIDITY.bits;
@@ -117,6 +118,9 @@ impl RequiredResponses {
         if self.intersects(RequiredResponses::SEARCH) {
             ret |= line.starts_with(b"SEARCH");
         }
+        if self.intersects(RequiredResponses::VANISHED) {
+            ret |= line.starts_with(b"VANISHED");
+        }
         if self.intersects(RequiredResponses::FETCH) {
             let mut ptr = 0;
             for (i, l) in line.iter().enumerate() {
@@ -892,6 +896,23 @@ pub enum UntaggedResponse<'s> {
     /// ```
     Recent(ImapNum),
     Fetch(FetchResponse<'s>),
+    /// ```text
+    /// RFC 7162 3.2.10.  VANISHED Response
+    ///
+    /// The VANISHED response reports that the specified UIDs have been
+    /// permanently removed from the mailbox.  This response is similar to
+    /// the EXPUNGE response (RFC 3501); however, it can return information
+    /// about multiple messages, and it returns UIDs instead of message
+    /// numbers.
+    ///
+    /// The VANISHED UID FETCH modifier or the QRESYNC parameter of SELECT
+    /// cause VANISHED responses with the EARLIER tag to be returned for
+    /// messages that were expunged since the given mod-sequence.
+    /// ```
+    Vanished {
+        earlier: bool,
+        uids: Vec<std::ops::RangeInclusive<UID>>,
+    },
     Bye {
         reason: &'s str,
     },
@@ -899,6 +920,13 @@ pub enum UntaggedResponse<'s> {
 
 pub fn untagged_responses(input: &[u8]) -> ImapParseResult<Option<UntaggedResponse<'_>>> {
     let orig_input = input;
+    if let Ok((input, (earlier, uids))) = vanished_response(input) {
+        return Ok((
+            input,
+            Some(UntaggedResponse::Vanished { earlier, uids }),
+            None,
+        ));
+    }
     let (input, _) = tag::<_, &[u8], (&[u8], nom::error::ErrorKind)>(b"* ")(input)?;
     let (input, num) = map_res::<_, _, _, (&[u8], nom::error::ErrorKind), _, _, _>(digit1, |s| {
         ImapNum::from_str(unsafe { std::str::from_utf8_unchecked(s) })
@@ -977,6 +1005,85 @@ fn test_untagged_responses() {
     );
 }
 
+/// Parses `* VANISHED [(EARLIER)] <known-uids>\r\n`.
+pub fn vanished_response(
+    input: &[u8],
+) -> IResult<&[u8], (bool, Vec<std::ops::RangeInclusive<UID>>)> {
+    let (input, _) = tag("* VANISHED ")(input)?;
+    let (input, earlier) = opt(tag("(EARLIER) "))(input)?;
+    let (input, uids) = sequence_set(input)?;
+    let (input, _) = tag("\r\n")(input)?;
+    Ok((input, (earlier.is_some(), uids)))
+}
+
+/// Parses a `sequence-set` of numbers and number ranges such as `41,43:116,118`.
+pub fn sequence_set(input: &[u8]) -> IResult<&[u8], Vec<std::ops::RangeInclusive<ImapNum>>> {
+    fn num(input: &[u8]) -> IResult<&[u8], ImapNum> {
+        map_res(digit1, |s: &[u8]| {
+            ImapNum::from_str(unsafe { std::str::from_utf8_unchecked(s) })
+        })(input)
+    }
+    separated_list1(tag(","), |input| {
+        let (input, start) = num(input)?;
+        let (input, end) = opt(preceded(tag(":"), num))(input)?;
+        let end = end.unwrap_or(start);
+        /* "2:4" and "4:2" are equivalent */
+        Ok((input, std::cmp::min(start, end)..=std::cmp::max(start, end)))
+    })(input)
+}
+
+/// Formats a set of numbers as a `sequence-set`, merging consecutive numbers into ranges.
+pub fn format_sequence_set(nums: &BTreeSet<ImapNum>) -> String {
+    let mut ret = String::new();
+    let mut iter = nums.iter().peekable();
+    while let Some(&start) = iter.next() {
+        let mut end = start;
+        while iter.peek() == Some(&&(end + 1)) {
+            end += 1;
+            iter.next();
+        }
+        if !ret.is_empty() {
+            ret.push(',');
+        }
+        if start == end {
+            ret.push_str(&start.to_string());
+        } else {
+            ret.push_str(&format!("{}:{}", start, end));
+        }
+    }
+    ret
+}
+
+#[test]
+fn test_imap_vanished() {
+    assert_eq!(
+        untagged_responses(b"* VANISHED (EARLIER) 41,43:116,118,120:211,214:540\r\n")
+            .map(|(_, v, _)| v)
+            .unwrap()
+            .unwrap(),
+        UntaggedResponse::Vanished {
+            earlier: true,
+            uids: vec![41..=41, 43..=116, 118..=118, 120..=211, 214..=540],
+        }
+    );
+    assert_eq!(
+        untagged_responses(b"* VANISHED 405,407,410:409\r\n")
+            .map(|(_, v, _)| v)
+            .unwrap()
+            .unwrap(),
+        UntaggedResponse::Vanished {
+            earlier: false,
+            uids: vec![405..=405, 407..=407, 409..=410],
+        }
+    );
+    assert!(RequiredResponses::VANISHED.check(b"* VANISHED (EARLIER) 1:3\r\n"));
+    assert_eq!(
+        format_sequence_set(&[1, 2, 3, 5, 7, 8].iter().cloned().collect()),
+        "1:3,5,7:8"
+    );
+    assert_eq!(format_sequence_set(&BTreeSet::new()), "");
+}
+
 pub fn search_results<'a>(input: &'a [u8]) -> IResult<&'a [u8], Vec<ImapNum>> {
     alt((
         |input: &'a [u8]| -> IResult<&'a [u8], Vec<ImapNum>> {
@@ -25,11 +25,13 @@ use crate::backends::imap::protocol_parser::{
 };
 use crate::backends::BackendMailbox;
 use crate::backends::{
-    RefreshEvent,
+    MailboxHash, RefreshEvent,
     RefreshEventKind::{self, *},
 };
+use crate::email::EnvelopeHash;
 use crate::error::*;
 use std::convert::TryInto;
+use std::ops::RangeInclusive;
 
 impl ImapConnection {
     pub async fn process_untagged(&mut self, line: &[u8]) -> Result<bool> {
@@ -191,6 +193,21 @@ impl ImapConnection {
                     },
                 ));
             }
+            UntaggedResponse::Vanished { earlier, uids } => {
+                let events = self.vanished(mailbox_hash, &uids).await;
+                debug!(
+                    "vanished (earlier: {}) {:?}: {} known envelopes",
+                    earlier,
+                    uids,
+                    events.len()
+                );
+                if self.uid_store.keep_offline_cache {
+                    cache_handle.update(mailbox_hash, &events)?;
+                }
+                for (_, event) in events {
+                    self.add_refresh_event(event);
+                }
+            }
             UntaggedResponse::Exists(n) => {
                 debug!("exists {}", n);
                 try_fail!(
@@ -526,4 +543,63 @@ impl ImapConnection {
         }
         Ok(true)
     }
+
+    /// Forget the envelopes of `mailbox_hash` whose UIDs were reported by a `VANISHED` response
+    /// and return a `Remove` event for each one of them.
+    pub async fn vanished(
+        &mut self,
+        mailbox_hash: MailboxHash,
+        uids: &[RangeInclusive<UID>],
+    ) -> Vec<(UID, RefreshEvent)> {
+        let vanished = |uid: &UID| uids.iter().any(|range| range.contains(uid));
+        let (mailbox_exists, unseen) = {
+            let f = &self.uid_store.mailboxes.lock().await[&mailbox_hash];
+            (f.exists.clone(), f.unseen.clone())
+        };
+        self.uid_store
+            .msn_index
+            .lock()
+            .unwrap()
+            .entry(mailbox_hash)
+            .or_default()
+            .retain(|uid| !vanished(uid));
+        let deleted = self
+            .uid_store
+            .uid_index
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|((mailbox_hash_, uid), _)| *mailbox_hash_ == mailbox_hash && vanished(uid))
+            .map(|((_, uid), hash)| (*uid, *hash))
+            .collect::<Vec<(UID, EnvelopeHash)>>();
+        let mut events = Vec::with_capacity(deleted.len());
+        for (deleted_uid, deleted_hash) in deleted {
+            mailbox_exists.lock().unwrap().remove(deleted_hash);
+            unseen.lock().unwrap().remove(deleted_hash);
+            self.uid_store
+                .uid_index
+                .lock()
+                .unwrap()
+                .remove(&(mailbox_hash, deleted_uid));
+            self.uid_store
+                .hash_index
+                .lock()
+                .unwrap()
+                .remove(&deleted_hash);
+            self.uid_store
+                .envelopes
+                .lock()
+                .unwrap()
+                .remove(&deleted_hash);
+            events.push((
+                deleted_uid,
+                RefreshEvent {
+                    account_hash: self.uid_store.account_hash,
+                    mailbox_hash,
+                    kind: Remove(deleted_hash),
+                },
+            ));
+        }
+        events
+    }
 }
@@ -60,52 +60,8 @@ pub async fn idle(kit: ImapWatchKit) -> Result<()> {
         uid_store,
     } = kit;
     conn.connect().await?;
-    let mailbox: ImapMailbox = match uid_store
-        .mailboxes
-        .lock()
-        .await
-        .values()
-        .find(|f| f.parent.is_none() && (f.special_usage() == SpecialUsageMailbox::Inbox))
-        .map(std::clone::Clone::clone)
-    {
-        Some(mailbox) => mailbox,
-        None => {
-            return Err(MeliError::new("INBOX mailbox not found in local mailbox index. meli may have not parsed the IMAP mailboxes correctly"));
-        }
-    };
-    let mailbox_hash = mailbox.hash();
+    let mailbox_hash = examine_inbox(&mut conn, &uid_store).await?;
     let mut response = Vec::with_capacity(8 * 1024);
-    let select_response = conn
-        .examine_mailbox(mailbox_hash, &mut response, true)
-        .await?
-        .unwrap();
-    {
-        let mut uidvalidities = uid_store.uidvalidity.lock().unwrap();
-
-        if let Some(v) = uidvalidities.get(&mailbox_hash) {
-            if *v != select_response.uidvalidity {
-                if uid_store.keep_offline_cache {
-                    #[cfg(not(feature = "sqlite3"))]
-                    let mut cache_handle = super::cache::DefaultCache::get(uid_store.clone())?;
-                    #[cfg(feature = "sqlite3")]
-                    let mut cache_handle = super::cache::Sqlite3Cache::get(uid_store.clone())?;
-                    cache_handle.clear(mailbox_hash, &select_response)?;
-                }
-                conn.add_refresh_event(RefreshEvent {
-                    account_hash: uid_store.account_hash,
-                    mailbox_hash,
-                    kind: RefreshEventKind::Rescan,
-                });
-                /*
-                uid_store.uid_index.lock().unwrap().clear();
-                uid_store.hash_index.lock().unwrap().clear();
-                uid_store.byte_cache.lock().unwrap().clear();
-                */
-            }
-        } else {
-            uidvalidities.insert(mailbox_hash, select_response.uidvalidity);
-        }
-    }
     let mailboxes: HashMap<MailboxHash, ImapMailbox> = {
         let mailboxes_lck = timeout(uid_store.timeout, uid_store.mailboxes.lock()).await?;
         mailboxes_lck.clone()
@@ -193,6 +149,173 @@ pub async fn idle(kit: ImapWatchKit) -> Result<()> {
     }
 }
 
+pub async fn notify(kit: ImapWatchKit) -> Result<()> {
+    debug!("NOTIFY");
+    /* NOTIFY (RFC 5465) lets the server report changes to every subscribed mailbox over this
+     * connection. The selected mailbox (INBOX) gets the usual EXISTS/EXPUNGE/FETCH responses,
+     * while changes to the others are announced with STATUS responses, upon which we examine
+     * the mailbox with the main connection. */
+    let ImapWatchKit {
+        mut conn,
+        main_conn,
+        uid_store,
+    } = kit;
+    conn.connect().await?;
+    let mailbox_hash = examine_inbox(&mut conn, &uid_store).await?;
+    let mut response = Vec::with_capacity(8 * 1024);
+    let mailboxes: HashMap<MailboxHash, ImapMailbox> = {
+        let mailboxes_lck = timeout(uid_store.timeout, uid_store.mailboxes.lock()).await?;
+        mailboxes_lck.clone()
+    };
+    notify_set(&mut conn, &mut response).await?;
+    {
+        /* Examine the rest with the main connection, to keep INBOX selected in this one. */
+        let mut main_conn_lck = timeout(uid_store.timeout, main_conn.lock()).await?;
+        for (h, mailbox) in mailboxes.clone() {
+            if mailbox_hash == h {
+                continue;
+            }
+            examine_updates(mailbox, &mut main_conn_lck, &uid_store).await?;
+        }
+    }
+    let mut blockn = ImapBlockingConnection::from(conn);
+    /* duration interval to send heartbeat */
+    const _10_MINS: std::time::Duration = std::time::Duration::from_secs(10 * 60);
+    loop {
+        let line = match timeout(Some(_10_MINS), blockn.as_stream()).await {
+            Ok(Some(line)) => line,
+            Ok(None) => {
+                debug!("NOTIFY connection dropped: {:?}", &blockn.err());
+                blockn.conn.connect().await?;
+                examine_inbox(&mut blockn.conn, &uid_store).await?;
+                notify_set(&mut blockn.conn, &mut response).await?;
+                let mut main_conn_lck = timeout(uid_store.timeout, main_conn.lock()).await?;
+                main_conn_lck.connect().await?;
+                continue;
+            }
+            Err(_) => {
+                /* Timeout */
+                blockn.conn.send_command(b"NOOP").await?;
+                blockn
+                    .conn
+                    .read_response(&mut response, RequiredResponses::empty())
+                    .await?;
+                let mut main_conn_lck = timeout(uid_store.timeout, main_conn.lock()).await?;
+                main_conn_lck.connect().await?;
+                continue;
+            }
+        };
+        for l in line.split_rn() {
+            debug!("notify line {:?}", &l);
+            if l.starts_with(b"* STATUS ") {
+                let h = match l
+                    .strip_prefix(b"* STATUS ".as_ref())
+                    .and_then(|m| protocol_parser::mailbox_token(m).ok())
+                {
+                    Some((_, path)) => get_path_hash!(path),
+                    None => continue,
+                };
+                if let Some(mailbox) = mailboxes.get(&h) {
+                    let mut conn = timeout(uid_store.timeout, main_conn.lock()).await?;
+                    examine_updates(std::clone::Clone::clone(mailbox), &mut conn, &uid_store)
+                        .await?;
+                }
+            } else if l.contains_subsequence(b"[NOTIFICATIONOVERFLOW]") {
+                /* The server has given up on notifications and is in the `NOTIFY NONE` state;
+                 * catch up on everything and ask for them again. */
+                debug!("NOTIFY notification overflow");
+                {
+                    let mut conn = timeout(uid_store.timeout, main_conn.lock()).await?;
+                    for (_h, mailbox) in mailboxes.clone() {
+                        examine_updates(mailbox, &mut conn, &uid_store).await?;
+                    }
+                }
+                notify_set(&mut blockn.conn, &mut response).await?;
+            } else if l.starts_with(b"* ") {
+                blockn.conn.process_untagged(l).await?;
+            }
+        }
+    }
+}
+
+/// Ask for `MessageNew`, `MessageExpunge` and `FlagChange` events of the selected and
+/// subscribed mailboxes. A rejection is returned as a non-network error so that the caller can
+/// fall back to `IDLE` or polling.
+async fn notify_set(conn: &mut ImapConnection, response: &mut Vec<u8>) -> Result<()> {
+    conn.send_command(
+        b"NOTIFY SET (SELECTED (MessageNew MessageExpunge FlagChange)) \
+(SUBSCRIBED (MessageNew MessageExpunge FlagChange))",
+    )
+    .await?;
+    conn.read_response(response, RequiredResponses::NO_REQUIRED)
+        .await?;
+    match ImapResponse::try_from(response.as_slice()) {
+        Ok(ImapResponse::Ok(_)) => Ok(()),
+        Ok(ImapResponse::No(code)) | Ok(ImapResponse::Bad(code)) => Err(MeliError::new(format!(
+            "NOTIFY SET was rejected by the server: {}",
+            code
+        ))),
+        _ => Err(MeliError::new(format!(
+            "Unexpected NOTIFY SET response: {}",
+            String::from_utf8_lossy(response)
+        ))),
+    }
+}
+
+/// Find the INBOX mailbox and `EXAMINE` it, checking whether its `UIDVALIDITY` has changed.
+async fn examine_inbox(
+    conn: &mut ImapConnection,
+    uid_store: &Arc<UIDStore>,
+) -> Result<MailboxHash> {
+    let mailbox: ImapMailbox = match uid_store
+        .mailboxes
+        .lock()
+        .await
+        .values()
+        .find(|f| f.parent.is_none() && (f.special_usage() == SpecialUsageMailbox::Inbox))
+        .map(std::clone::Clone::clone)
+    {
+        Some(mailbox) => mailbox,
+        None => {
+            return Err(MeliError::new("INBOX mailbox not found in local mailbox index. meli may have not parsed the IMAP mailboxes correctly"));
+        }
+    };
+    let mailbox_hash = mailbox.hash();
+    let mut response = Vec::with_capacity(8 * 1024);
+    let select_response = conn
+        .examine_mailbox(mailbox_hash, &mut response, true)
+        .await?
+        .unwrap();
+    {
+        let mut uidvalidities = uid_store.uidvalidity.lock().unwrap();
+
+        if let Some(v) = uidvalidities.get(&mailbox_hash) {
+            if *v != select_response.uidvalidity {
+                if uid_store.keep_offline_cache {
+                    #[cfg(not(feature = "sqlite3"))]
+                    let mut cache_handle = super::cache::DefaultCache::get(uid_store.clone())?;
+                    #[cfg(feature = "sqlite3")]
+                    let mut cache_handle = super::cache::Sqlite3Cache::get(uid_store.clone())?;
+                    cache_handle.clear(mailbox_hash, &select_response)?;
+                }
+                conn.add_refresh_event(RefreshEvent {
+                    account_hash: uid_store.account_hash,
+                    mailbox_hash,
+                    kind: RefreshEventKind::Rescan,
+                });
+                /*
+                uid_store.uid_index.lock().unwrap().clear();
+                uid_store.hash_index.lock().unwrap().clear();
+                uid_store.byte_cache.lock().unwrap().clear();
+                */
+            }
+        } else {
+            uidvalidities.insert(mailbox_hash, select_response.uidvalidity);
+        }
+    }
+    Ok(mailbox_hash)
+}
+
 pub async fn examine_updates(
     mailbox: ImapMailbox,
     conn: &mut ImapConnection,
@@ -23,7 +23,7 @@ use crate::backends::*;
 use crate::conf::AccountSettings;
 use crate::connections::timeout;
 use crate::email::*;
-use crate::error::{MeliError, Result};
+use crate::error::{ErrorKind, MeliError, Result};
 use crate::Collection;
 use futures::lock::Mutex as FutureMutex;
 use isahc::config::RedirectPolicy;
@@ -82,6 +82,8 @@ use connection::*;
 pub mod protocol;
 use protocol::*;
 
+pub mod push;
+
 pub mod rfc8620;
 use rfc8620::*;
 
@@ -203,6 +205,16 @@ pub struct Store {
 }
 
 impl Store {
+    pub fn subscribed_mailboxes(&self) -> HashMap<MailboxHash, Mailbox> {
+        self.mailboxes
+            .read()
+            .unwrap()
+            .iter()
+            .filter(|(_, f)| f.is_subscribed)
+            .map(|(&h, f)| (h, BackendMailbox::clone(f) as Mailbox))
+            .collect()
+    }
+
     pub fn add_envelope(&self, obj: EmailObject) -> Envelope {
         let mut tag_lck = self.collection.tag_index.write().unwrap();
         let tags = obj
@@ -303,7 +315,7 @@ impl MailBackend for JmapType {
             supports_search: true,
             extensions: None,
             supports_tags: true,
-            supports_submission: false,
+            supports_submission: true,
         };
         CAPABILITIES
     }
@@ -360,8 +372,10 @@ impl MailBackend for JmapType {
                 let mut conn = connection.lock().await;
                 conn.connect().await?;
             }
+            let mut reconnect_delay = push::MIN_RECONNECT_DELAY;
             loop {
                 {
+                    /* Catch up with changes made while push was not connected. */
                     let mailbox_hashes = {
                         store
                             .mailboxes
@@ -372,10 +386,27 @@ impl MailBackend for JmapType {
                             .collect::<SmallVec<[MailboxHash; 16]>>()
                     };
                     let conn = connection.lock().await;
+                    conn.mailbox_changes().await?;
                     for mailbox_hash in mailbox_hashes {
                         conn.email_changes(mailbox_hash).await?;
                     }
                 }
+                let connected_at = Instant::now();
+                match push::listen(&connection).await {
+                    Ok(()) => {
+                        if connected_at.elapsed() >= push::MAX_RECONNECT_DELAY {
+                            reconnect_delay = push::MIN_RECONNECT_DELAY;
+                        } else {
+                            crate::connections::sleep(reconnect_delay).await;
+                            reconnect_delay =
+                                std::cmp::min(2 * reconnect_delay, push::MAX_RECONNECT_DELAY);
+                        }
+                        continue;
+                    }
+                    Err(err) => {
+                        debug!("JMAP push unavailable, polling instead: {}", err);
+                    }
+                }
                 crate::connections::sleep(Duration::from_secs(60)).await;
             }
         }))
@@ -392,16 +423,7 @@ impl MailBackend for JmapType {
                 *store.mailboxes.write().unwrap() = new_mailboxes;
             }
 
-            let ret = store
-                .mailboxes
-                .read()
-                .unwrap()
-                .iter()
-                .filter(|(_, f)| f.is_subscribed)
-                .map(|(&h, f)| (h, BackendMailbox::clone(f) as Mailbox))
-                .collect();
-
-            Ok(ret)
+            Ok(store.subscribed_mailboxes())
         }))
     }
 
@@ -488,13 +510,197 @@ impl MailBackend for JmapType {
                 }
             })?;
 
-            if let Some(err) = m.not_created.get(&creation_id) {
+            if let Some(err) = m
+                .not_created
+                .as_ref()
+                .and_then(|errs| errs.get(&creation_id))
+            {
                 return Err(MeliError::new(format!("Could not save message: {:?}", err)));
             }
             Ok(())
         }))
     }
 
+    fn submit(
+        &self,
+        bytes: Vec<u8>,
+        mailbox_hash: Option<MailboxHash>,
+        flags: Option<Flag>,
+    ) -> ResultFuture<()> {
+        let store = self.store.clone();
+        let connection = self.connection.clone();
+        Ok(Box::pin(async move {
+            let mut conn = connection.lock().await;
+            conn.connect().await?;
+            if !conn
+                .session
+                .lock()
+                .unwrap()
+                .capabilities
+                .contains_key("urn:ietf:params:jmap:submission")
+            {
+                return Err(MeliError::new(format!(
+                    "Server {} does not support JMAP Submission capability \
+                     (urn:ietf:params:jmap:submission).",
+                    &conn.server_conf.server_hostname
+                ))
+                .set_kind(ErrorKind::NotSupported));
+            }
+            /*
+             * 1. upload binary blob, get blobId
+             * 2. find an Identity that matches the From: address of the message
+             * 3. Email/import into Drafts and EmailSubmission/set in the same request; on
+             *    success the server moves the message to Sent (or destroys it if there is no
+             *    Sent mailbox).
+             */
+            let from = Envelope::from_bytes(&bytes, None)?
+                .from()
+                .get(0)
+                .map(|addr| addr.get_email())
+                .ok_or_else(|| MeliError::new("Message has no From: address."))?;
+            let identities = protocol::get_identities(&conn).await?;
+            let identity_id = if let Some(identity) = identities.iter().find(|i| i.matches(&from)) {
+                identity.id.clone()
+            } else {
+                return Err(MeliError::new(format!(
+                    "No JMAP identity matches From: address {}. Available identities are: {}",
+                    from,
+                    identities
+                        .iter()
+                        .map(|i| i.email.as_str())
+                        .collect::<Vec<&str>>()
+                        .join(", ")
+                )));
+            };
+
+            let (drafts_id, sent_id): (Option<Id<MailboxObject>>, Option<Id<MailboxObject>>) = {
+                let mailboxes_lck = store.mailboxes.read().unwrap();
+                let find_role = |role: &str| {
+                    mailboxes_lck
+                        .values()
+                        .find(|m| m.role.as_deref() == Some(role))
+                        .map(|m| m.id.clone())
+                };
+                let sent_id = if let Some(mailbox_hash) = mailbox_hash {
+                    if let Some(mailbox) = mailboxes_lck.get(&mailbox_hash) {
+                        Some(mailbox.id.clone())
+                    } else {
+                        return Err(MeliError::new(format!(
+                            "Mailbox with hash {} not found",
+                            mailbox_hash
+                        )));
+                    }
+                } else {
+                    find_role("sent")
+                };
+                (find_role("drafts"), sent_id)
+            };
+            let import_mailbox_id = if let Some(id) = drafts_id.or_else(|| sent_id.clone()) {
+                id
+            } else {
+                return Err(MeliError::new(
+                    "Could not find a Drafts or Sent mailbox to store the submitted message in.",
+                ));
+            };
+
+            let (api_url, upload_url) = {
+                let lck = conn.session.lock().unwrap();
+                (lck.api_url.clone(), lck.upload_url.clone())
+            };
+            let mut res = conn
+                .client
+                .post_async(
+                    &upload_request_format(upload_url.as_str(), &conn.mail_account_id()),
+                    bytes,
+                )
+                .await?;
+            let res_text = res.text_async().await?;
+            let upload_response: UploadResponse = serde_json::from_str(&res_text)?;
+
+            let mut req = Request::new(conn.request_no.clone()).with_submission();
+            let email_creation_id: Id<EmailObject> = "e1".to_string().into();
+            let submission_creation_id: Id<EmailSubmissionObject> = "k1".to_string().into();
+            let mut email_imports = HashMap::default();
+            let mut mailbox_ids = HashMap::default();
+            mailbox_ids.insert(import_mailbox_id.clone(), true);
+            let mut keywords = HashMap::default();
+            keywords.insert("$draft".to_string(), true);
+            email_imports.insert(
+                email_creation_id.clone(),
+                EmailImport::new()
+                    .blob_id(upload_response.blob_id)
+                    .mailbox_ids(mailbox_ids)
+                    .keywords(keywords),
+            );
+            let import_call: ImportCall = ImportCall::new()
+                .account_id(conn.mail_account_id().clone())
+                .emails(email_imports);
+            req.add_call(&import_call);
+
+            let mut create = HashMap::default();
+            create.insert(
+                submission_creation_id.clone(),
+                EmailSubmissionObject::new(identity_id, format!("#{}", email_creation_id).into()),
+            );
+            let mut submission_call = EmailSubmissionSet::new(
+                Set::<EmailSubmissionObject>::new()
+                    .account_id(conn.mail_account_id().clone())
+                    .create(Some(create)),
+            );
+            let submission_ref: Id<EmailSubmissionObject> =
+                format!("#{}", submission_creation_id).into();
+            if let Some(sent_id) = sent_id {
+                let mut patch = serde_json::Map::new();
+                patch.insert("keywords/$draft".to_string(), Value::Null);
+                for keyword in protocol::flags_to_keywords(flags.unwrap_or(Flag::SEEN)) {
+                    if keyword != "$draft" {
+                        patch.insert(format!("keywords/{}", keyword), Value::Bool(true));
+                    }
+                }
+                if import_mailbox_id != sent_id {
+                    patch.insert(format!("mailboxIds/{}", import_mailbox_id), Value::Null);
+                    patch.insert(format!("mailboxIds/{}", sent_id), Value::Bool(true));
+                }
+                let mut on_success_update_email = HashMap::default();
+                on_success_update_email.insert(submission_ref, Value::Object(patch));
+                submission_call =
+                    submission_call.on_success_update_email(Some(on_success_update_email));
+            } else {
+                submission_call =
+                    submission_call.on_success_destroy_email(Some(vec![submission_ref]));
+            }
+            req.add_call(&submission_call);
+
+            let mut res = conn
+                .client
+                .post_async(api_url.as_str(), serde_json::to_string(&req)?)
+                .await?;
+            let res_text = res.text_async().await?;
+
+            let mut v: MethodResponse = serde_json::from_str(&res_text)?;
+            let m = ImportResponse::try_from(v.method_responses.remove(0))?;
+            if let Some(err) = m
+                .not_created
+                .as_ref()
+                .and_then(|errs| errs.get(&email_creation_id))
+            {
+                return Err(MeliError::new(format!(
+                    "Could not submit message: {:?}",
+                    err
+                )));
+            }
+            let m = SetResponse::<EmailSubmissionObject>::try_from(v.method_responses.remove(0))?;
+            if let Some(err) = m
+                .not_created
+                .as_ref()
+                .and_then(|errs| errs.get(&submission_creation_id))
+            {
+                return Err(MeliError::new(format!("Could not submit message: {}", err)));
+            }
+            Ok(())
+        }))
+    }
+
     fn as_any(&self) -> &dyn Any {
         self
     }
@@ -514,20 +720,21 @@ impl MailBackend for JmapType {
     ) -> ResultFuture<SmallVec<[EnvelopeHash; 512]>> {
         let store = self.store.clone();
         let connection = self.connection.clone();
-        let filter = if let Some(mailbox_hash) = mailbox_hash {
-            let mailbox_id = self.store.mailboxes.read().unwrap()[&mailbox_hash]
-                .id
-                .clone();
-
-            let mut f = Filter::Condition(
-                EmailFilterCondition::new()
-                    .in_mailbox(Some(mailbox_id))
-                    .into(),
-            );
-            f &= Filter::<EmailFilterCondition, EmailObject>::from(q);
-            f
-        } else {
-            Filter::<EmailFilterCondition, EmailObject>::from(q)
+        let filter = {
+            let mailboxes_lck = self.store.mailboxes.read().unwrap();
+            let query_filter =
+                Filter::<EmailFilterCondition, EmailObject>::from_query(&q, &mailboxes_lck)?;
+            if let Some(mailbox_hash) = mailbox_hash {
+                let mut f = Filter::Condition(
+                    EmailFilterCondition::new()
+                        .in_mailbox(Some(mailboxes_lck[&mailbox_hash].id.clone()))
+                        .into(),
+                );
+                f &= query_filter;
+                f
+            } else {
+                query_filter
+            }
         };
 
         Ok(Box::pin(async move {
@@ -560,19 +767,276 @@ impl MailBackend for JmapType {
         }))
     }
 
+    fn create_mailbox(
+        &mut self,
+        path: String,
+    ) -> ResultFuture<(MailboxHash, HashMap<MailboxHash, Mailbox>)> {
+        let store = self.store.clone();
+        let connection = self.connection.clone();
+        Ok(Box::pin(async move {
+            let mut conn = connection.lock().await;
+            conn.connect().await?;
+            let (name, parent_id) = {
+                let mailboxes_lck = store.mailboxes.read().unwrap();
+                if mailboxes_lck.values().any(|m| m.path == path) {
+                    return Err(MeliError::new(format!(
+                        "Mailbox named `{}` already exists.",
+                        path,
+                    )));
+                }
+                protocol::mailbox_name_and_parent(&mailboxes_lck, &path)?
+            };
+            let creation_id: Id<MailboxObject> = "1".to_string().into();
+            let mut create = HashMap::default();
+            create.insert(
+                creation_id.clone(),
+                MailboxObject::new(name.clone(), parent_id.clone()),
+            );
+            let m = protocol::mailbox_set(
+                &conn,
+                MailboxSet::new(
+                    Set::<MailboxObject>::new()
+                        .account_id(conn.mail_account_id().clone())
+                        .create(Some(create)),
+                ),
+            )
+            .await?;
+            if let Some(err) = m.not_created.and_then(|mut errs| errs.remove(&creation_id)) {
+                return Err(MeliError::from(err)
+                    .set_summary(format!("Could not create mailbox `{}`", path)));
+            }
+            /* The server only returns the properties it has set. */
+            let MailboxObject {
+                id,
+                my_rights,
+                total_emails,
+                total_threads,
+                unread_emails,
+                unread_threads,
+                ..
+            } = m
+                .created
+                .and_then(|mut created| created.remove(&creation_id))
+                .ok_or_else(|| {
+                    MeliError::new(format!(
+                        "Server did not return the id of the created mailbox `{}`",
+                        path
+                    ))
+                })?;
+            let (mailbox_hash, mailbox) = protocol::mailbox_object_to_jmap(MailboxObject {
+                id,
+                my_rights,
+                total_emails,
+                total_threads,
+                unread_emails,
+                unread_threads,
+                ..MailboxObject::new(name, parent_id)
+            });
+            {
+                let mut mailboxes_lck = store.mailboxes.write().unwrap();
+                if let Some(parent_hash) = mailbox.parent_hash {
+                    mailboxes_lck
+                        .entry(parent_hash)
+                        .and_modify(|parent| parent.children.push(mailbox_hash));
+                }
+                mailboxes_lck.insert(mailbox_hash, mailbox);
+                protocol::set_mailbox_paths(&mut mailboxes_lck);
+            }
+            Ok((mailbox_hash, store.subscribed_mailboxes()))
+        }))
+    }
+
+    fn delete_mailbox(
+        &mut self,
+        mailbox_hash: MailboxHash,
+    ) -> ResultFuture<HashMap<MailboxHash, Mailbox>> {
+        let store = self.store.clone();
+        let connection = self.connection.clone();
+        Ok(Box::pin(async move {
+            let mut conn = connection.lock().await;
+            conn.connect().await?;
+            let mailbox_id = {
+                let mailboxes_lck = store.mailboxes.read().unwrap();
+                let mailbox = mailboxes_lck.get(&mailbox_hash).ok_or_else(|| {
+                    MeliError::new(format!("Mailbox with hash {} not found", mailbox_hash))
+                        .set_kind(ErrorKind::NotFound)
+                })?;
+                if !mailbox.my_rights.may_delete {
+                    return Err(MeliError::new(format!(
+                        "You do not have permission to delete `{}`. Set permissions for this \
+                         mailbox are {}",
+                        mailbox.name,
+                        mailbox.permissions()
+                    ))
+                    .set_kind(ErrorKind::PermissionDenied));
+                }
+                mailbox.id.clone()
+            };
+            let mut mailbox_set_call = MailboxSet::new(
+                Set::<MailboxObject>::new()
+                    .account_id(conn.mail_account_id().clone())
+                    .destroy(Some(vec![mailbox_id.clone()])),
+            );
+            /* Like IMAP DELETE, emails that are in no other mailbox are deleted. */
+            mailbox_set_call.on_destroy_remove_emails = true;
+            let m = protocol::mailbox_set(&conn, mailbox_set_call).await?;
+            if let Some(err) = m
+                .not_destroyed
+                .and_then(|mut errs| errs.remove(&mailbox_id))
+            {
+                return Err(MeliError::from(err).set_summary("Could not delete mailbox"));
+            }
+            {
+                let mut mailboxes_lck = store.mailboxes.write().unwrap();
+                if let Some(parent_hash) = mailboxes_lck
+                    .remove(&mailbox_hash)
+                    .and_then(|mailbox| mailbox.parent_hash)
+                {
+                    mailboxes_lck
+                        .entry(parent_hash)
+                        .and_modify(|parent| parent.children.retain(|c| *c != mailbox_hash));
+                }
+            }
+            store.mailboxes_index.write().unwrap().remove(&mailbox_hash);
+            Ok(store.subscribed_mailboxes())
+        }))
+    }
+
+    fn set_mailbox_subscription(
+        &mut self,
+        mailbox_hash: MailboxHash,
+        new_val: bool,
+    ) -> ResultFuture<()> {
+        let store = self.store.clone();
+        let connection = self.connection.clone();
+        Ok(Box::pin(async move {
+            let mut conn = connection.lock().await;
+            conn.connect().await?;
+            let mailbox_id = {
+                let mailboxes_lck = store.mailboxes.read().unwrap();
+                let mailbox = mailboxes_lck.get(&mailbox_hash).ok_or_else(|| {
+                    MeliError::new(format!("Mailbox with hash {} not found", mailbox_hash))
+                        .set_kind(ErrorKind::NotFound)
+                })?;
+                if mailbox.is_subscribed == new_val {
+                    return Ok(());
+                }
+                mailbox.id.clone()
+            };
+            let mut update = HashMap::default();
+            update.insert(
+                mailbox_id.clone(),
+                serde_json::json!({ "isSubscribed": new_val }),
+            );
+            let m = protocol::mailbox_set(
+                &conn,
+                MailboxSet::new(
+                    Set::<MailboxObject>::new()
+                        .account_id(conn.mail_account_id().clone())
+                        .update(Some(update)),
+                ),
+            )
+            .await?;
+            if let Some(err) = m.not_updated.and_then(|mut errs| errs.remove(&mailbox_id)) {
+                return Err(MeliError::from(err));
+            }
+            store
+                .mailboxes
+                .write()
+                .unwrap()
+                .entry(mailbox_hash)
+                .and_modify(|mailbox| {
+                    let _ = mailbox.set_is_subscribed(new_val);
+                });
+            Ok(())
+        }))
+    }
+
     fn rename_mailbox(
         &mut self,
-        _mailbox_hash: MailboxHash,
-        _new_path: String,
+        mailbox_hash: MailboxHash,
+        new_path: String,
     ) -> ResultFuture<Mailbox> {
-        Err(MeliError::new("Unimplemented."))
+        let store = self.store.clone();
+        let connection = self.connection.clone();
+        Ok(Box::pin(async move {
+            let mut conn = connection.lock().await;
+            conn.connect().await?;
+            let (mailbox_id, name, parent_id) = {
+                let mailboxes_lck = store.mailboxes.read().unwrap();
+                let mailbox = mailboxes_lck.get(&mailbox_hash).ok_or_else(|| {
+                    MeliError::new(format!("Mailbox with hash {} not found", mailbox_hash))
+                        .set_kind(ErrorKind::NotFound)
+                })?;
+                if !mailbox.my_rights.may_rename {
+                    return Err(MeliError::new(format!(
+                        "You do not have permission to rename mailbox `{}`.",
+                        mailbox.name,
+                    ))
+                    .set_kind(ErrorKind::PermissionDenied));
+                }
+                if mailboxes_lck.values().any(|m| m.path == new_path) {
+                    return Err(MeliError::new(format!(
+                        "Mailbox named `{}` already exists.",
+                        new_path,
+                    )));
+                }
+                let (name, parent_id) =
+                    protocol::mailbox_name_and_parent(&mailboxes_lck, &new_path)?;
+                (mailbox.id.clone(), name, parent_id)
+            };
+            let mut update = HashMap::default();
+            update.insert(
+                mailbox_id.clone(),
+                serde_json::json!({ "name": &name, "parentId": &parent_id }),
+            );
+            let m = protocol::mailbox_set(
+                &conn,
+                MailboxSet::new(
+                    Set::<MailboxObject>::new()
+                        .account_id(conn.mail_account_id().clone())
+                        .update(Some(update)),
+                ),
+            )
+            .await?;
+            if let Some(err) = m.not_updated.and_then(|mut errs| errs.remove(&mailbox_id)) {
+                return Err(MeliError::from(err)
+                    .set_summary(format!("Could not rename mailbox to `{}`", new_path)));
+            }
+            let new_parent_hash = parent_id.as_ref().map(|id| id.into_hash());
+            let mut mailboxes_lck = store.mailboxes.write().unwrap();
+            let old_parent_hash = mailboxes_lck[&mailbox_hash].parent_hash;
+            if old_parent_hash != new_parent_hash {
+                if let Some(old_parent_hash) = old_parent_hash {
+                    mailboxes_lck
+                        .entry(old_parent_hash)
+                        .and_modify(|parent| parent.children.retain(|c| *c != mailbox_hash));
+                }
+                if let Some(new_parent_hash) = new_parent_hash {
+                    mailboxes_lck
+                        .entry(new_parent_hash)
+                        .and_modify(|parent| parent.children.push(mailbox_hash));
+                }
+            }
+            let mailbox = mailboxes_lck.get_mut(&mailbox_hash).unwrap();
+            mailbox.name = name;
+            mailbox.parent_id = parent_id;
+            mailbox.parent_hash = new_parent_hash;
+            /* The paths of its descendants change too. */
+            protocol::set_mailbox_paths(&mut mailboxes_lck);
+            Ok(BackendMailbox::clone(&mailboxes_lck[&mailbox_hash]))
+        }))
     }
 
-    fn create_mailbox(
+    fn set_mailbox_permissions(
         &mut self,
-        _path: String,
-    ) -> ResultFuture<(MailboxHash, HashMap<MailboxHash, Mailbox>)> {
-        Err(MeliError::new("Unimplemented."))
+        _mailbox_hash: MailboxHash,
+        _val: MailboxPermissions,
+    ) -> ResultFuture<()> {
+        Err(
+            MeliError::new("JMAP mailbox permissions are set by the server and can't be changed.")
+                .set_kind(ErrorKind::NotSupported),
+        )
     }
 
     fn copy_messages(
@@ -655,7 +1119,7 @@ impl MailBackend for JmapType {
                     return Err(MeliError::new(format!(
                         "Could not update ids: {}",
                         ids.into_iter()
-                            .map(|err| err.to_string())
+                            .map(|(id, err)| format!("{}: {}", id, err))
                             .collect::<Vec<String>>()
                             .join(",")
                     )));
@@ -758,7 +1222,7 @@ impl MailBackend for JmapType {
             if let Some(ids) = m.not_updated {
                 return Err(MeliError::new(
                     ids.into_iter()
-                        .map(|err| err.to_string())
+                        .map(|(id, err)| format!("{}: {}", id, err))
                         .collect::<Vec<String>>()
                         .join(","),
                 ));
@@ -822,10 +1286,161 @@ impl MailBackend for JmapType {
 
     fn delete_messages(
         &mut self,
-        _env_hashes: EnvelopeHashBatch,
-        _mailbox_hash: MailboxHash,
+        env_hashes: EnvelopeHashBatch,
+        mailbox_hash: MailboxHash,
     ) -> ResultFuture<()> {
-        Err(MeliError::new("Unimplemented."))
+        let store = self.store.clone();
+        let connection = self.connection.clone();
+        Ok(Box::pin(async move {
+            /* Messages are moved to the mailbox with the "trash" role. Messages that are already
+             * in it, or all messages if there is no such mailbox, are destroyed. */
+            let (mailbox_id, trash) = {
+                let mailboxes_lck = store.mailboxes.read().unwrap();
+                let mailbox = mailboxes_lck.get(&mailbox_hash).ok_or_else(|| {
+                    MeliError::new(format!("Mailbox with hash {} not found", mailbox_hash))
+                        .set_kind(ErrorKind::NotFound)
+                })?;
+                if !mailbox.my_rights.may_remove_items {
+                    return Err(MeliError::new(format!(
+                        "You do not have permission to delete messages from `{}`.",
+                        mailbox.name,
+                    ))
+                    .set_kind(ErrorKind::PermissionDenied));
+                }
+                let trash = mailboxes_lck
+                    .values()
+                    .find(|m| m.role.as_deref() == Some("trash") && m.hash != mailbox_hash)
+                    .map(|m| (m.hash, m.id.clone()));
+                (mailbox.id.clone(), trash)
+            };
+            let mut id_map: HashMap<Id<EmailObject>, EnvelopeHash> = HashMap::default();
+            {
+                let id_store_lck = store.id_store.lock().unwrap();
+                for env_hash in env_hashes.iter() {
+                    if let Some(id) = id_store_lck.get(&env_hash) {
+                        id_map.insert(id.clone(), env_hash);
+                    }
+                }
+            }
+            let mut set_call = Set::<EmailObject>::new();
+            if let Some((_, ref trash_id)) = trash {
+                let mut update_map: HashMap<Id<EmailObject>, Value> = HashMap::default();
+                for id in id_map.keys() {
+                    update_map.insert(
+                        id.clone(),
+                        serde_json::json!({
+                            format!("mailboxIds/{}", &mailbox_id): null,
+                            format!("mailboxIds/{}", trash_id): true,
+                        }),
+                    );
+                }
+                set_call = set_call.update(Some(update_map));
+            } else {
+                set_call = set_call.destroy(Some(id_map.keys().cloned().collect()));
+            }
+            let mut conn = connection.lock().await;
+            conn.connect().await?;
+            let email_set_call: EmailSet =
+                EmailSet::new(set_call.account_id(conn.mail_account_id().clone()));
+
+            let mut req = Request::new(conn.request_no.clone());
+            req.add_call(&email_set_call);
+            let api_url = conn.session.lock().unwrap().api_url.clone();
+            let mut res = conn
+                .client
+                .post_async(api_url.as_str(), serde_json::to_string(&req)?)
+                .await?;
+
+            let res_text = res.text_async().await?;
+            let mut v: MethodResponse = serde_json::from_str(&res_text)?;
+            *store.online_status.lock().await = (std::time::Instant::now(), Ok(()));
+            let m = SetResponse::<EmailObject>::try_from(v.method_responses.remove(0))?;
+            let mut errors = m
+                .not_updated
+                .into_iter()
+                .chain(m.not_destroyed.into_iter())
+                .flatten()
+                .collect::<Vec<(Id<EmailObject>, SetError)>>();
+            let removed: Vec<EnvelopeHash> = m
+                .updated
+                .map(|updated| updated.into_keys().collect::<Vec<_>>())
+                .into_iter()
+                .chain(m.destroyed.into_iter())
+                .flatten()
+                .filter_map(|id| id_map.get(&id).cloned())
+                .collect();
+
+            /* Update mailbox counts and the collection */
+            for &env_hash in &removed {
+                let envelope = store
+                    .collection
+                    .envelopes
+                    .read()
+                    .unwrap()
+                    .get(&env_hash)
+                    .cloned();
+                {
+                    let mailboxes_lck = store.mailboxes.read().unwrap();
+                    if let Some(mailbox) = mailboxes_lck.get(&mailbox_hash) {
+                        mailbox.total_emails.lock().unwrap().remove(env_hash);
+                        mailbox.unread_emails.lock().unwrap().remove(env_hash);
+                    }
+                    if let Some(trash) = trash
+                        .as_ref()
+                        .and_then(|(trash_hash, _)| mailboxes_lck.get(trash_hash))
+                    {
+                        trash.total_emails.lock().unwrap().insert_new(env_hash);
+                        if !envelope.as_ref().map(|e| e.is_seen()).unwrap_or(true) {
+                            trash.unread_emails.lock().unwrap().insert_new(env_hash);
+                        }
+                    }
+                }
+                {
+                    let mut mailboxes_index_lck = store.mailboxes_index.write().unwrap();
+                    if let Some(set) = mailboxes_index_lck.get_mut(&mailbox_hash) {
+                        set.remove(&env_hash);
+                    }
+                    if let Some((trash_hash, _)) = trash {
+                        mailboxes_index_lck
+                            .entry(trash_hash)
+                            .or_default()
+                            .insert(env_hash);
+                    }
+                }
+                conn.add_refresh_event(RefreshEvent {
+                    account_hash: store.account_hash,
+                    mailbox_hash,
+                    kind: RefreshEventKind::Remove(env_hash),
+                });
+                match (trash.as_ref(), envelope) {
+                    (Some((trash_hash, _)), Some(envelope)) => {
+                        conn.add_refresh_event(RefreshEvent {
+                            account_hash: store.account_hash,
+                            mailbox_hash: *trash_hash,
+                            kind: RefreshEventKind::Create(Box::new(envelope)),
+                        });
+                    }
+                    (None, _) => {
+                        if let Some(id) = store.id_store.lock().unwrap().get(&env_hash).cloned() {
+                            store.remove_envelope(id);
+                        }
+                    }
+                    _ => {}
+                }
+            }
+            if let Some((id, err)) = errors.pop() {
+                let summary = format!(
+                    "Could not delete {} of {} messages",
+                    errors.len() + 1,
+                    id_map.len()
+                );
+                let kind = err.kind();
+                return Err(MeliError::new(format!("{}: {}", id, err))
+                    .set_summary(summary)
+                    .set_kind(kind));
+            }
+            Ok(())
+        }))
     }
 }
 
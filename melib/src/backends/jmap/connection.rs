@@ -21,6 +21,7 @@
 
 use super::*;
 use isahc::config::Configurable;
+use std::convert::TryInto;
 
 #[derive(Debug)]
 pub struct JmapConnection {
@@ -339,4 +340,187 @@ impl JmapConnection {
 
         Ok(())
     }
+
+    pub async fn mailbox_changes(&self) -> Result<()> {
+        let mut current_state: State<MailboxObject> =
+            self.store.mailbox_state.lock().unwrap().clone();
+        if current_state.is_empty() {
+            return Ok(());
+        }
+        loop {
+            let mailbox_changes_call: MailboxChanges = MailboxChanges::new(
+                Changes::<MailboxObject>::new()
+                    .account_id(self.mail_account_id().clone())
+                    .since_state(current_state.clone()),
+            );
+
+            let mut req = Request::new(self.request_no.clone());
+            let prev_seq = req.add_call(&mailbox_changes_call);
+            for result_field in &["/created", "/updated"] {
+                let mailbox_get_call: MailboxGet = MailboxGet::new(
+                    Get::new()
+                        .ids(Some(JmapArgument::reference(
+                            prev_seq,
+                            ResultField::<MailboxChanges, MailboxObject>::new(*result_field),
+                        )))
+                        .account_id(self.mail_account_id().clone()),
+                );
+                req.add_call(&mailbox_get_call);
+            }
+            let api_url = self.session.lock().unwrap().api_url.clone();
+            let mut res = self
+                .client
+                .post_async(api_url.as_str(), serde_json::to_string(&req)?)
+                .await?;
+
+            let res_text = res.text_async().await?;
+            debug!(&res_text);
+            let mut v: MethodResponse = serde_json::from_str(&res_text)?;
+            let changes_response =
+                ChangesResponse::<MailboxObject>::try_from(v.method_responses.remove(0))?;
+            if changes_response.new_state == current_state {
+                return Ok(());
+            }
+            let GetResponse::<MailboxObject> { list: created, .. } =
+                GetResponse::<MailboxObject>::try_from(v.method_responses.remove(0))?;
+            let GetResponse::<MailboxObject> { list: updated, .. } =
+                GetResponse::<MailboxObject>::try_from(v.method_responses.remove(0))?;
+
+            for obj in created {
+                let (mailbox_hash, mailbox) = protocol::mailbox_object_to_jmap(obj);
+                let mailbox = {
+                    let mut mailboxes_lck = self.store.mailboxes.write().unwrap();
+                    if mailboxes_lck.contains_key(&mailbox_hash) {
+                        /* Created by us with `create_mailbox`. */
+                        continue;
+                    }
+                    if let Some(parent_hash) = mailbox.parent_hash {
+                        mailboxes_lck
+                            .entry(parent_hash)
+                            .and_modify(|parent| parent.children.push(mailbox_hash));
+                    }
+                    mailboxes_lck.insert(mailbox_hash, mailbox);
+                    protocol::set_mailbox_paths(&mut mailboxes_lck);
+                    BackendMailbox::clone(&mailboxes_lck[&mailbox_hash])
+                };
+                self.add_refresh_event(RefreshEvent {
+                    account_hash: self.store.account_hash,
+                    mailbox_hash,
+                    kind: RefreshEventKind::MailboxCreate(mailbox),
+                });
+            }
+            for obj in updated {
+                let mailbox_hash = obj.id.into_hash();
+                let mut events = SmallVec::<[RefreshEventKind; 2]>::new();
+                {
+                    let mut mailboxes_lck = self.store.mailboxes.write().unwrap();
+                    let mailbox = if let Some(mailbox) = mailboxes_lck.get_mut(&mailbox_hash) {
+                        mailbox
+                    } else {
+                        continue;
+                    };
+                    let is_renamed = mailbox.name != obj.name;
+                    if mailbox.is_subscribed != obj.is_subscribed {
+                        events.push(if obj.is_subscribed {
+                            RefreshEventKind::MailboxSubscribe(mailbox_hash)
+                        } else {
+                            RefreshEventKind::MailboxUnsubscribe(mailbox_hash)
+                        });
+                    }
+                    mailbox.name = obj.name;
+                    mailbox.is_subscribed = obj.is_subscribed;
+                    mailbox.my_rights = obj.my_rights;
+                    mailbox.role = obj.role;
+                    mailbox.sort_order = obj.sort_order;
+                    mailbox.total_threads = obj.total_threads;
+                    mailbox.unread_threads = obj.unread_threads;
+                    /* Counts of mailboxes whose emails have been fetched are kept up to date by
+                     * `email_changes`. */
+                    if mailbox.email_state.lock().unwrap().is_none() {
+                        mailbox
+                            .total_emails
+                            .lock()
+                            .unwrap()
+                            .set_not_yet_seen(obj.total_emails.try_into().unwrap_or(0));
+                        mailbox
+                            .unread_emails
+                            .lock()
+                            .unwrap()
+                            .set_not_yet_seen(obj.unread_emails.try_into().unwrap_or(0));
+                    }
+                    if is_renamed {
+                        protocol::set_mailbox_paths(&mut mailboxes_lck);
+                        events.push(RefreshEventKind::MailboxRename {
+                            old_mailbox_hash: mailbox_hash,
+                            new_mailbox: BackendMailbox::clone(&mailboxes_lck[&mailbox_hash]),
+                        });
+                    }
+                }
+                for kind in events {
+                    self.add_refresh_event(RefreshEvent {
+                        account_hash: self.store.account_hash,
+                        mailbox_hash,
+                        kind,
+                    });
+                }
+            }
+            for id in changes_response.destroyed {
+                let mailbox_hash = id.into_hash();
+                let removed = {
+                    let mut mailboxes_lck = self.store.mailboxes.write().unwrap();
+                    let removed = mailboxes_lck.remove(&mailbox_hash);
+                    if let Some(parent_hash) = removed.as_ref().and_then(|m| m.parent_hash) {
+                        mailboxes_lck
+                            .entry(parent_hash)
+                            .and_modify(|parent| parent.children.retain(|c| *c != mailbox_hash));
+                    }
+                    removed.is_some()
+                };
+                if removed {
+                    self.add_refresh_event(RefreshEvent {
+                        account_hash: self.store.account_hash,
+                        mailbox_hash,
+                        kind: RefreshEventKind::MailboxDelete(mailbox_hash),
+                    });
+                }
+            }
+            if changes_response.has_more_changes {
+                current_state = changes_response.new_state;
+            } else {
+                *self.store.mailbox_state.lock().unwrap() = changes_response.new_state;
+                break;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Fetch changes of the data types a push `StateChange` reports as changed for the mail
+    /// account.
+    pub async fn handle_state_change(&self, state_change: StateChange) -> Result<()> {
+        let account_id = self.mail_account_id();
+        if let Some(new_state) = state_change.state::<MailboxObject>(&account_id) {
+            let is_changed = *self.store.mailbox_state.lock().unwrap() != new_state;
+            if is_changed {
+                self.mailbox_changes().await?;
+            }
+        }
+        if let Some(new_state) = state_change.state::<EmailObject>(&account_id) {
+            let mailbox_hashes = self
+                .store
+                .mailboxes
+                .read()
+                .unwrap()
+                .iter()
+                .filter(|(_, mbox)| {
+                    matches!(*mbox.email_state.lock().unwrap(), Some(ref s) if *s != new_state)
+                })
+                .map(|(k, _)| *k)
+                .collect::<SmallVec<[MailboxHash; 16]>>();
+            for mailbox_hash in mailbox_hashes {
+                self.email_changes(mailbox_hash).await?;
+            }
+        }
+        Ok(())
+    }
 }
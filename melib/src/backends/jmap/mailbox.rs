@@ -73,7 +73,16 @@ impl BackendMailbox for JmapMailbox {
     }
 
     fn permissions(&self) -> MailboxPermissions {
-        MailboxPermissions::default()
+        MailboxPermissions {
+            create_messages: self.my_rights.may_add_items,
+            remove_messages: self.my_rights.may_remove_items,
+            set_flags: self.my_rights.may_set_keywords,
+            create_child: self.my_rights.may_create_child,
+            rename_messages: self.my_rights.may_add_items && self.my_rights.may_remove_items,
+            delete_messages: self.my_rights.may_remove_items,
+            delete_mailbox: self.my_rights.may_delete,
+            change_permissions: false,
+        }
     }
 
     fn special_usage(&self) -> SpecialUsageMailbox {
@@ -99,8 +108,8 @@ impl BackendMailbox for JmapMailbox {
         self.is_subscribed
     }
     fn set_is_subscribed(&mut self, new_val: bool) -> Result<()> {
+        /* The server is updated with Mailbox/set in `JmapType::set_mailbox_subscription`. */
         self.is_subscribed = new_val;
-        // FIXME: jmap subscribe
         Ok(())
     }
 
@@ -26,3 +26,9 @@ pub use email::*;
 
 mod mailbox;
 pub use mailbox::*;
+
+mod identity;
+pub use identity::*;
+
+mod email_submission;
+pub use email_submission::*;
@@ -575,10 +575,20 @@ pub enum MessageProperty {
     Sender,
 }
 
-impl From<crate::search::Query> for Filter<EmailFilterCondition, EmailObject> {
-    fn from(val: crate::search::Query) -> Self {
+impl Filter<EmailFilterCondition, EmailObject> {
+    /// Translates a search query. `Mailbox` terms are looked up in `mailboxes`; terms that JMAP
+    /// filters can't express are `NotSupported` errors, so that the caller matches the envelopes
+    /// itself instead of getting results that ignore them.
+    pub fn from_query(
+        query: &crate::search::Query,
+        mailboxes: &HashMap<MailboxHash, JmapMailbox>,
+    ) -> Result<Self> {
         let mut ret = Filter::Condition(EmailFilterCondition::new().into());
-        fn rec(q: &crate::search::Query, f: &mut Filter<EmailFilterCondition, EmailObject>) {
+        fn rec(
+            q: &crate::search::Query,
+            f: &mut Filter<EmailFilterCondition, EmailObject>,
+            mailboxes: &HashMap<MailboxHash, JmapMailbox>,
+        ) -> Result<()> {
             use crate::datetime::{timestamp_to_string, RFC3339_FMT};
             use crate::search::Query::*;
             match q {
@@ -630,7 +640,7 @@ impl From<crate::search::Query> for Filter<EmailFilterCondition, EmailObject> {
                     );
                 }
                 On(t) => {
-                    rec(&Between(*t, *t), f);
+                    rec(&Between(*t, *t), f, mailboxes)?;
                 }
                 InReplyTo(ref s) => {
                     *f = Filter::Condition(
@@ -646,8 +656,72 @@ impl From<crate::search::Query> for Filter<EmailFilterCondition, EmailObject> {
                             .into(),
                     );
                 }
-                AllAddresses(_) => {
-                    //TODO
+                MessageId(ref s) => {
+                    *f = Filter::Condition(
+                        EmailFilterCondition::new()
+                            .header(vec!["Message-ID".to_string().into(), s.to_string().into()])
+                            .into(),
+                    );
+                }
+                ListId(ref s) => {
+                    *f = Filter::Condition(
+                        EmailFilterCondition::new()
+                            .header(vec!["List-Id".to_string().into(), s.to_string().into()])
+                            .into(),
+                    );
+                }
+                Header(ref name, ref s) => {
+                    *f = Filter::Condition(
+                        EmailFilterCondition::new()
+                            .header(vec![name.to_string().into(), s.to_string().into()])
+                            .into(),
+                    );
+                }
+                Mailbox(pattern) => {
+                    let mut ids = mailboxes
+                        .values()
+                        .filter(|m| crate::search::mailbox_is_match(&m.path, pattern))
+                        .map(|m| m.id.clone());
+                    let first = ids.next().ok_or_else(|| {
+                        MeliError::new(format!("No mailbox matches `{}`.", pattern))
+                            .set_kind(ErrorKind::NotSupported)
+                    })?;
+                    let mut accum = Filter::Condition(
+                        EmailFilterCondition::new().in_mailbox(Some(first)).into(),
+                    );
+                    for id in ids {
+                        accum |= Filter::Condition(
+                            EmailFilterCondition::new().in_mailbox(Some(id)).into(),
+                        );
+                    }
+                    *f = accum;
+                }
+                AllAddresses(_) | Regex(_) => {
+                    /* Searching without these terms would give wrong results */
+                    return Err(MeliError::new(format!(
+                        "JMAP Email/query can't express the term {:?}",
+                        q
+                    ))
+                    .set_kind(ErrorKind::NotSupported));
+                }
+                Tag(t) => {
+                    *f = Filter::Condition(
+                        EmailFilterCondition::new().has_keyword(t.clone()).into(),
+                    );
+                }
+                Larger(size) => {
+                    *f = Filter::Condition(
+                        EmailFilterCondition::new()
+                            .min_size(Some(*size as u64 + 1))
+                            .into(),
+                    );
+                }
+                Smaller(size) => {
+                    *f = Filter::Condition(
+                        EmailFilterCondition::new()
+                            .max_size(Some(*size as u64))
+                            .into(),
+                    );
                 }
                 Flags(v) => {
                     fn flag_to_filter(f: &str) -> Filter<EmailFilterCondition, EmailObject> {
@@ -709,28 +783,29 @@ impl From<crate::search::Query> for Filter<EmailFilterCondition, EmailObject> {
                 And(q1, q2) => {
                     let mut rhs = Filter::Condition(EmailFilterCondition::new().into());
                     let mut lhs = Filter::Condition(EmailFilterCondition::new().into());
-                    rec(q1, &mut rhs);
-                    rec(q2, &mut lhs);
+                    rec(q1, &mut rhs, mailboxes)?;
+                    rec(q2, &mut lhs, mailboxes)?;
                     rhs &= lhs;
                     *f = rhs;
                 }
                 Or(q1, q2) => {
                     let mut rhs = Filter::Condition(EmailFilterCondition::new().into());
                     let mut lhs = Filter::Condition(EmailFilterCondition::new().into());
-                    rec(q1, &mut rhs);
-                    rec(q2, &mut lhs);
+                    rec(q1, &mut rhs, mailboxes)?;
+                    rec(q2, &mut lhs, mailboxes)?;
                     rhs |= lhs;
                     *f = rhs;
                 }
                 Not(q) => {
                     let mut qhs = Filter::Condition(EmailFilterCondition::new().into());
-                    rec(q, &mut qhs);
+                    rec(q, &mut qhs, mailboxes)?;
                     *f = !qhs;
                 }
             }
+            Ok(())
         }
-        rec(&val, &mut ret);
-        ret
+        rec(query, &mut ret, mailboxes)?;
+        Ok(ret)
     }
 }
 
@@ -741,7 +816,8 @@ fn test_jmap_query() {
         "subject:wah or (from:Manos and (subject:foo or subject:bar))",
     )
     .unwrap();
-    let f: Filter<EmailFilterCondition, EmailObject> = Filter::from(q);
+    let f: Filter<EmailFilterCondition, EmailObject> =
+        Filter::from_query(&q, &HashMap::default()).unwrap();
     assert_eq!(
         r#"{"operator":"OR","conditions":[{"subject":"wah"},{"operator":"AND","conditions":[{"from":"Manos"},{"operator":"OR","conditions":[{"subject":"foo"},{"subject":"bar"}]}]}]}"#,
         serde_json::to_string(&f).unwrap().as_str()
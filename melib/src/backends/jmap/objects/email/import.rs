@@ -172,13 +172,15 @@ pub struct ImportResponse {
     ///A map of the creation id to an object containing the "id",
     ///"blobId", "threadId", and "size" properties for each successfully
     ///imported Email, or null if none.
-    pub created: HashMap<Id<EmailObject>, ImportEmailResult>,
+    #[serde(default)]
+    pub created: Option<HashMap<Id<EmailObject>, ImportEmailResult>>,
 
     ///o  notCreated: "Id[SetError]|null"
     ///A map of the creation id to a SetError object for each Email that
     ///failed to be created, or null if all successful.  The possible
     ///errors are defined above.
-    pub not_created: HashMap<Id<EmailObject>, ImportError>,
+    #[serde(default)]
+    pub not_created: Option<HashMap<Id<EmailObject>, ImportError>>,
 }
 
 impl std::convert::TryFrom<&RawValue> for ImportResponse {
@@ -0,0 +1,196 @@
+/*
+ * meli - jmap module.
+ *
+ * Copyright 2026 Manos Pitsidianakis
+ *
+ * This file is part of meli.
+ *
+ * meli is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * meli is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with meli. If not, see <http://www.gnu.org/licenses/>.
+ */
+
+use super::*;
+use serde_json::Value;
+
+/// # EmailSubmission
+///
+/// An *EmailSubmission* object represents the submission of an Email for delivery to one or
+/// more recipients. (RFC 8621, Section 7)
+#[derive(Deserialize, Serialize, Debug, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct EmailSubmissionObject {
+    ///o  id: "Id" (immutable; server-set)
+    ///
+    ///   The id of the EmailSubmission.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Id::is_empty")]
+    pub id: Id<EmailSubmissionObject>,
+    ///o  identityId: "Id" (immutable)
+    ///
+    ///   The id of the Identity to associate with this submission.
+    #[serde(default)]
+    pub identity_id: Id<IdentityObject>,
+    ///o  emailId: "Id" (immutable)
+    ///
+    ///   The id of the Email to send.  The Email being sent does not have
+    ///   to be a draft, for example, when "redirecting" an existing Email
+    ///   to a different address. A creation id reference (e.g. `"#e1"`) to an Email created in
+    ///   the same request may be used instead.
+    #[serde(default)]
+    pub email_id: Id<EmailObject>,
+    ///o  envelope: "Envelope|null" (immutable)
+    ///
+    ///   Information for use when sending via SMTP.  If null or omitted on
+    ///   creation, the server derives it from the "From", "To", "Cc" and "Bcc" headers of the
+    ///   Email.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub envelope: Option<EnvelopeObject>,
+    ///o  sendAt: "UTCDate" (immutable; server-set)
+    ///
+    ///   The date the submission was/will be released for delivery.
+    #[serde(default)]
+    #[serde(skip_serializing)]
+    pub send_at: Option<String>,
+    ///o  undoStatus: "String"
+    ///
+    ///   One of "pending", "final" or "canceled".
+    #[serde(default)]
+    #[serde(skip_serializing)]
+    pub undo_status: Option<String>,
+    ///o  deliveryStatus: "String[DeliveryStatus]|null" (server-set)
+    #[serde(default)]
+    #[serde(skip_serializing)]
+    pub delivery_status: Option<HashMap<String, Value>>,
+}
+
+impl Object for EmailSubmissionObject {
+    const NAME: &'static str = "EmailSubmission";
+}
+
+impl EmailSubmissionObject {
+    pub fn new(identity_id: Id<IdentityObject>, email_id: Id<EmailObject>) -> Self {
+        Self {
+            identity_id,
+            email_id,
+            ..Self::default()
+        }
+    }
+
+    _impl!(envelope: Option<EnvelopeObject>);
+}
+
+/// The SMTP envelope of an [`EmailSubmissionObject`].
+#[derive(Deserialize, Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct EnvelopeObject {
+    ///o  mailFrom: "Address"
+    ///
+    ///   The email address to use as the return address in the SMTP
+    ///   submission, plus any parameters to pass with the MAIL FROM
+    ///   address.
+    pub mail_from: EnvelopeAddress,
+    ///o  rcptTo: "Address[]"
+    ///
+    ///   The email addresses to send the message to, and any RCPT TO
+    ///   parameters to pass with the recipient.
+    pub rcpt_to: Vec<EnvelopeAddress>,
+}
+
+#[derive(Deserialize, Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct EnvelopeAddress {
+    ///o  email: "String"
+    ///
+    ///   The email address being represented by the object.
+    pub email: String,
+    ///o  parameters: "Object|null"
+    ///
+    ///   Any parameters to send with the email address (either mail-
+    ///   parameter or rcpt-parameter as appropriate, as specified in
+    ///   [RFC5321]).
+    #[serde(default)]
+    pub parameters: Option<HashMap<String, Option<String>>>,
+}
+
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct EmailSubmissionSet {
+    #[serde(flatten)]
+    pub set_call: Set<EmailSubmissionObject>,
+    ///o  onSuccessUpdateEmail: "Id[PatchObject]|null"
+    ///
+    ///   A map of EmailSubmission id to an object containing properties to
+    ///   update on the Email object referenced by the EmailSubmission if the
+    ///   create/update/destroy succeeds.  (For references to
+    ///   EmailSubmissions created in the same "/set" invocation, this is
+    ///   equivalent to a creation-reference, so the id will be the creation
+    ///   id prefixed with a "#".)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub on_success_update_email: Option<HashMap<Id<EmailSubmissionObject>, Value>>,
+    ///o  onSuccessDestroyEmail: "Id[]|null"
+    ///
+    ///   A list of EmailSubmission ids for which the Email with the
+    ///   corresponding "emailId" should be destroyed if the
+    ///   create/update/destroy succeeds.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub on_success_destroy_email: Option<Vec<Id<EmailSubmissionObject>>>,
+}
+
+impl Method<EmailSubmissionObject> for EmailSubmissionSet {
+    const NAME: &'static str = "EmailSubmission/set";
+}
+
+impl EmailSubmissionSet {
+    pub fn new(set_call: Set<EmailSubmissionObject>) -> Self {
+        EmailSubmissionSet {
+            set_call,
+            on_success_update_email: None,
+            on_success_destroy_email: None,
+        }
+    }
+
+    _impl!(on_success_update_email: Option<HashMap<Id<EmailSubmissionObject>, Value>>);
+    _impl!(on_success_destroy_email: Option<Vec<Id<EmailSubmissionObject>>>);
+}
+
+#[test]
+fn test_jmap_email_submission() {
+    use std::sync::{Arc, Mutex};
+
+    let mut create = HashMap::default();
+    create.insert(
+        "k1".to_string().into(),
+        EmailSubmissionObject::new("identity_id".to_string().into(), "#e1".to_string().into()),
+    );
+    let mut on_success_update_email = HashMap::default();
+    on_success_update_email.insert(
+        "#k1".to_string().into(),
+        serde_json::json!({ "keywords/$draft": null }),
+    );
+    let submission_call = EmailSubmissionSet::new(
+        Set::<EmailSubmissionObject>::new()
+            .account_id("account_id".to_string().into())
+            .create(Some(create)),
+    )
+    .on_success_update_email(Some(on_success_update_email));
+
+    let request_no = Arc::new(Mutex::new(0));
+    let mut req = Request::new(request_no).with_submission();
+    req.add_call(&submission_call);
+
+    assert_eq!(
+        r##"{"using":["urn:ietf:params:jmap:core","urn:ietf:params:jmap:mail","urn:ietf:params:jmap:submission"],"methodCalls":[["EmailSubmission/set",{"accountId":"account_id","create":{"k1":{"emailId":"#e1","identityId":"identity_id"}},"destroy":null,"ifInState":null,"onSuccessUpdateEmail":{"#k1":{"keywords/$draft":null}},"update":null},"m0"]]}"##,
+        serde_json::to_string(&req).unwrap().as_str()
+    );
+}
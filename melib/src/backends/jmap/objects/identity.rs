@@ -0,0 +1,107 @@
+/*
+ * meli - jmap module.
+ *
+ * Copyright 2026 Manos Pitsidianakis
+ *
+ * This file is part of meli.
+ *
+ * meli is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * meli is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with meli. If not, see <http://www.gnu.org/licenses/>.
+ */
+
+use super::*;
+
+/// # Identity
+///
+/// An *Identity* object stores information about an email address or domain the user may send
+/// from. (RFC 8621, Section 6)
+#[derive(Deserialize, Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct IdentityObject {
+    ///o  id: "Id" (immutable; server-set)
+    ///
+    ///   The id of the Identity.
+    pub id: Id<IdentityObject>,
+    ///o  name: "String" (default: "")
+    ///
+    ///   The "From" name the client SHOULD use when creating a new Email
+    ///   from this Identity.
+    #[serde(default)]
+    pub name: String,
+    ///o  email: "String" (immutable)
+    ///
+    ///   The "From" email address the client MUST use when creating a new
+    ///   Email from this Identity.  If the "mailbox" part of the address
+    ///   (the section before the "@") is the single character "*" (e.g.,
+    ///   "*@example.com"), the client may use any valid address ending in
+    ///   that domain (e.g., "foo@example.com").
+    pub email: String,
+    ///o  replyTo: "EmailAddress[]|null" (default: null)
+    ///
+    ///   The Reply-To value the client SHOULD set when creating a new Email
+    ///   from this Identity.
+    #[serde(default)]
+    pub reply_to: Option<Vec<EmailAddress>>,
+    ///o  bcc: "EmailAddress[]|null" (default: null)
+    ///
+    ///   The Bcc value the client SHOULD set when creating a new Email from
+    ///   this Identity.
+    #[serde(default)]
+    pub bcc: Option<Vec<EmailAddress>>,
+    ///o  textSignature: "String" (default: "")
+    #[serde(default)]
+    pub text_signature: String,
+    ///o  htmlSignature: "String" (default: "")
+    #[serde(default)]
+    pub html_signature: String,
+    ///o  mayDelete: "Boolean" (server-set)
+    ///
+    ///   Is the user allowed to delete this Identity?
+    #[serde(default)]
+    pub may_delete: bool,
+}
+
+impl Object for IdentityObject {
+    const NAME: &'static str = "Identity";
+}
+
+impl IdentityObject {
+    /// Returns true if a message with `From` address `address` may be sent with this identity.
+    pub fn matches(&self, address: &str) -> bool {
+        if let Some(domain) = self.email.strip_prefix("*@") {
+            address
+                .rsplit_once('@')
+                .map(|(_, d)| d.eq_ignore_ascii_case(domain))
+                .unwrap_or(false)
+        } else {
+            self.email.eq_ignore_ascii_case(address)
+        }
+    }
+}
+
+#[derive(Deserialize, Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct IdentityGet {
+    #[serde(flatten)]
+    pub get_call: Get<IdentityObject>,
+}
+
+impl IdentityGet {
+    pub fn new(get_call: Get<IdentityObject>) -> Self {
+        IdentityGet { get_call }
+    }
+}
+
+impl Method<IdentityObject> for IdentityGet {
+    const NAME: &'static str = "Identity/get";
+}
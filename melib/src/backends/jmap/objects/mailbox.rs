@@ -29,19 +29,29 @@ impl Id<MailboxObject> {
     }
 }
 
-#[derive(Deserialize, Serialize, Debug)]
+/* Server-set properties are not serialized, so that a `MailboxObject` can be used in the
+ * `create` argument of `Mailbox/set`. Missing properties default, because `Mailbox/set`
+ * responses only include properties the client did not send. */
+#[derive(Deserialize, Serialize, Debug, Default)]
 #[serde(rename_all = "camelCase")]
+#[serde(default)]
 pub struct MailboxObject {
+    #[serde(skip_serializing)]
     pub id: Id<MailboxObject>,
     pub is_subscribed: bool,
+    #[serde(skip_serializing)]
     pub my_rights: JmapRights,
     pub name: String,
     pub parent_id: Option<Id<MailboxObject>>,
     pub role: Option<String>,
     pub sort_order: u64,
+    #[serde(skip_serializing)]
     pub total_emails: u64,
+    #[serde(skip_serializing)]
     pub total_threads: u64,
+    #[serde(skip_serializing)]
     pub unread_emails: u64,
+    #[serde(skip_serializing)]
     pub unread_threads: u64,
 }
 
@@ -49,7 +59,18 @@ impl Object for MailboxObject {
     const NAME: &'static str = "Mailbox";
 }
 
-#[derive(Deserialize, Serialize, Debug, Clone)]
+impl MailboxObject {
+    pub fn new(name: String, parent_id: Option<Id<MailboxObject>>) -> Self {
+        MailboxObject {
+            name,
+            parent_id,
+            is_subscribed: true,
+            ..Default::default()
+        }
+    }
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, Default)]
 #[serde(rename_all = "camelCase")]
 pub struct JmapRights {
     pub may_add_items: bool,
@@ -77,3 +98,113 @@ impl MailboxGet {
 impl Method<MailboxObject> for MailboxGet {
     const NAME: &'static str = "Mailbox/get";
 }
+
+#[derive(Deserialize, Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct MailboxChanges {
+    #[serde(flatten)]
+    pub changes_call: Changes<MailboxObject>,
+}
+
+impl MailboxChanges {
+    pub fn new(changes_call: Changes<MailboxObject>) -> Self {
+        MailboxChanges { changes_call }
+    }
+}
+
+impl Method<MailboxObject> for MailboxChanges {
+    const NAME: &'static str = "Mailbox/changes";
+}
+
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct MailboxSet {
+    #[serde(flatten)]
+    pub set_call: Set<MailboxObject>,
+    ///o  onDestroyRemoveEmails: "Boolean" (default: false)
+    ///
+    ///   If false, any attempt to destroy a Mailbox that still has Emails
+    ///   in it will be rejected with a "mailboxHasEmail" SetError.  If
+    ///   true, any Emails that were in the Mailbox will be removed from it,
+    ///   and if in no other Mailboxes, they will be destroyed when the
+    ///   Mailbox is destroyed.
+    pub on_destroy_remove_emails: bool,
+}
+
+impl MailboxSet {
+    pub fn new(set_call: Set<MailboxObject>) -> Self {
+        MailboxSet {
+            set_call,
+            on_destroy_remove_emails: false,
+        }
+    }
+}
+
+impl Method<MailboxObject> for MailboxSet {
+    const NAME: &'static str = "Mailbox/set";
+}
+
+#[test]
+fn test_jmap_mailbox_set() {
+    use crate::error::{ErrorKind, MeliError};
+    use serde_json::json;
+    use std::convert::TryFrom;
+
+    let mut create = HashMap::default();
+    create.insert(
+        "1".to_string().into(),
+        MailboxObject::new("child".to_string(), Some("parent_id".to_string().into())),
+    );
+    let mailbox_set_call = MailboxSet::new(
+        Set::<MailboxObject>::new()
+            .account_id("account_id".to_string().into())
+            .create(Some(create)),
+    );
+    assert_eq!(
+        serde_json::to_value(&mailbox_set_call).unwrap(),
+        json!({
+            "accountId": "account_id",
+            "ifInState": null,
+            "create": {
+                "1": {
+                    "isSubscribed": true,
+                    "name": "child",
+                    "parentId": "parent_id",
+                    "role": null,
+                    "sortOrder": 0
+                }
+            },
+            "update": null,
+            "destroy": null,
+            "onDestroyRemoveEmails": false
+        })
+    );
+
+    let response = serde_json::to_string(&json!(["Mailbox/set", {
+        "accountId": "account_id",
+        "oldState": "1",
+        "newState": "2",
+        "created": { "1": { "id": "M1", "totalEmails": 0 } },
+        "notDestroyed": {
+            "M2": { "type": "mailboxHasEmail" },
+            "M3": { "type": "forbidden", "description": "No." },
+            "M4": { "type": "somethingNew" }
+        }
+    }, "m0"]))
+    .unwrap();
+    let raw = serde_json::value::RawValue::from_string(response).unwrap();
+    let m = SetResponse::<MailboxObject>::try_from(raw.as_ref()).unwrap();
+    let id = |s: &str| -> Id<MailboxObject> { s.to_string().into() };
+    assert_eq!(m.created.unwrap()[&id("1")].id, id("M1"));
+    let mut not_destroyed = m.not_destroyed.unwrap();
+    let err = MeliError::from(not_destroyed.remove(&id("M2")).unwrap());
+    assert_eq!(err.kind, ErrorKind::ValueError);
+    assert_eq!(err.details, "MailboxHasEmail");
+    let err = MeliError::from(not_destroyed.remove(&id("M3")).unwrap());
+    assert_eq!(err.kind, ErrorKind::PermissionDenied);
+    assert_eq!(err.details, "Forbidden: No.");
+    assert!(matches!(
+        not_destroyed.remove(&id("M4")),
+        Some(SetError::Unknown)
+    ));
+}
@@ -47,6 +47,11 @@ pub trait Method<OBJ: Object>: Serialize {
 }
 
 static USING: &[&str] = &["urn:ietf:params:jmap:core", "urn:ietf:params:jmap:mail"];
+static USING_SUBMISSION: &[&str] = &[
+    "urn:ietf:params:jmap:core",
+    "urn:ietf:params:jmap:mail",
+    "urn:ietf:params:jmap:submission",
+];
 
 #[derive(Serialize)]
 #[serde(rename_all = "camelCase")]
@@ -69,6 +74,13 @@ impl Request {
         }
     }
 
+    /// Declare the `urn:ietf:params:jmap:submission` capability, required for `Identity` and
+    /// `EmailSubmission` method calls.
+    pub fn with_submission(mut self) -> Self {
+        self.using = USING_SUBMISSION;
+        self
+    }
+
     pub fn add_call<M: Method<O>, O: Object>(&mut self, call: &M) -> usize {
         let seq = get_request_no!(self.request_no);
         self.method_calls
@@ -106,66 +118,149 @@ pub async fn get_mailboxes(conn: &JmapConnection) -> Result<HashMap<MailboxHash,
     *conn.store.online_status.lock().await = (std::time::Instant::now(), Ok(()));
     let m = GetResponse::<MailboxObject>::try_from(v.method_responses.remove(0))?;
     let GetResponse::<MailboxObject> {
-        list, account_id, ..
+        list,
+        account_id,
+        state,
+        ..
     } = m;
     *conn.store.account_id.lock().unwrap() = account_id;
-    let mut ret: HashMap<MailboxHash, JmapMailbox> = list
-        .into_iter()
-        .map(|r| {
-            let MailboxObject {
-                id,
-                is_subscribed,
-                my_rights,
-                name,
-                parent_id,
-                role,
-                sort_order,
-                total_emails,
-                total_threads,
-                unread_emails,
-                unread_threads,
-            } = r;
-            let mut total_emails_set = LazyCountSet::default();
-            total_emails_set.set_not_yet_seen(total_emails.try_into().unwrap_or(0));
-            let total_emails = total_emails_set;
-            let mut unread_emails_set = LazyCountSet::default();
-            unread_emails_set.set_not_yet_seen(unread_emails.try_into().unwrap_or(0));
-            let unread_emails = unread_emails_set;
-            let hash = id.into_hash();
-            let parent_hash = parent_id.clone().map(|id| id.into_hash());
-            (
-                hash,
-                JmapMailbox {
-                    name: name.clone(),
-                    hash,
-                    path: name,
-                    children: Vec::new(),
-                    id,
-                    is_subscribed,
-                    my_rights,
-                    parent_id,
-                    parent_hash,
-                    role,
-                    usage: Default::default(),
-                    sort_order,
-                    total_emails: Arc::new(Mutex::new(total_emails)),
-                    total_threads,
-                    unread_emails: Arc::new(Mutex::new(unread_emails)),
-                    unread_threads,
-                    email_state: Arc::new(Mutex::new(None)),
-                    email_query_state: Arc::new(Mutex::new(None)),
-                },
-            )
-        })
-        .collect();
+    *conn.store.mailbox_state.lock().unwrap() = state;
+    let mut ret: HashMap<MailboxHash, JmapMailbox> =
+        list.into_iter().map(mailbox_object_to_jmap).collect();
     for key in ret.keys().cloned().collect::<SmallVec<[MailboxHash; 24]>>() {
         if let Some(parent_hash) = ret[&key].parent_hash.clone() {
             ret.entry(parent_hash).and_modify(|e| e.children.push(key));
         }
     }
+    set_mailbox_paths(&mut ret);
     Ok(ret)
 }
 
+/// The path of mailbox `mailbox_hash`: the names of its ancestors and its own, separated by `/`.
+pub fn mailbox_path(
+    mailboxes: &HashMap<MailboxHash, JmapMailbox>,
+    mailbox_hash: MailboxHash,
+) -> String {
+    let mut names = vec![];
+    let mut mailbox = mailboxes.get(&mailbox_hash);
+    while let Some(m) = mailbox {
+        names.push(m.name.as_str());
+        /* A server could send parents that form a cycle. */
+        if names.len() > mailboxes.len() {
+            break;
+        }
+        mailbox = m.parent_hash.and_then(|h| mailboxes.get(&h));
+    }
+    names.reverse();
+    names.join("/")
+}
+
+/// Set the `path` of every mailbox with [`mailbox_path`], after names or parents have changed.
+pub fn set_mailbox_paths(mailboxes: &mut HashMap<MailboxHash, JmapMailbox>) {
+    let paths = mailboxes
+        .keys()
+        .map(|h| (*h, mailbox_path(mailboxes, *h)))
+        .collect::<Vec<(MailboxHash, String)>>();
+    for (mailbox_hash, path) in paths {
+        if let Some(mailbox) = mailboxes.get_mut(&mailbox_hash) {
+            mailbox.path = path;
+        }
+    }
+}
+
+pub fn mailbox_object_to_jmap(r: MailboxObject) -> (MailboxHash, JmapMailbox) {
+    let MailboxObject {
+        id,
+        is_subscribed,
+        my_rights,
+        name,
+        parent_id,
+        role,
+        sort_order,
+        total_emails,
+        total_threads,
+        unread_emails,
+        unread_threads,
+    } = r;
+    let mut total_emails_set = LazyCountSet::default();
+    total_emails_set.set_not_yet_seen(total_emails.try_into().unwrap_or(0));
+    let total_emails = total_emails_set;
+    let mut unread_emails_set = LazyCountSet::default();
+    unread_emails_set.set_not_yet_seen(unread_emails.try_into().unwrap_or(0));
+    let unread_emails = unread_emails_set;
+    let hash = id.into_hash();
+    let parent_hash = parent_id.clone().map(|id| id.into_hash());
+    (
+        hash,
+        JmapMailbox {
+            name: name.clone(),
+            hash,
+            path: name,
+            children: Vec::new(),
+            id,
+            is_subscribed,
+            my_rights,
+            parent_id,
+            parent_hash,
+            role,
+            usage: Default::default(),
+            sort_order,
+            total_emails: Arc::new(Mutex::new(total_emails)),
+            total_threads,
+            unread_emails: Arc::new(Mutex::new(unread_emails)),
+            unread_threads,
+            email_state: Arc::new(Mutex::new(None)),
+            email_query_state: Arc::new(Mutex::new(None)),
+        },
+    )
+}
+
+pub async fn mailbox_set(
+    conn: &JmapConnection,
+    mailbox_set_call: MailboxSet,
+) -> Result<SetResponse<MailboxObject>> {
+    let mut req = Request::new(conn.request_no.clone());
+    req.add_call(&mailbox_set_call);
+
+    let api_url = conn.session.lock().unwrap().api_url.clone();
+    let mut res = conn
+        .client
+        .post_async(api_url.as_str(), serde_json::to_string(&req)?)
+        .await?;
+
+    let res_text = res.text_async().await?;
+    let mut v: MethodResponse = serde_json::from_str(&res_text)?;
+    *conn.store.online_status.lock().await = (std::time::Instant::now(), Ok(()));
+    SetResponse::<MailboxObject>::try_from(v.method_responses.remove(0))
+}
+
+/// Split a mailbox path into the name of the mailbox and the id of its parent, which must be
+/// the existing mailbox with the path before the last `/`.
+pub fn mailbox_name_and_parent(
+    mailboxes: &HashMap<MailboxHash, JmapMailbox>,
+    path: &str,
+) -> Result<(String, Option<Id<MailboxObject>>)> {
+    let (parent_path, name) = match path.rsplit_once('/') {
+        Some(v) => v,
+        None => return Ok((path.to_string(), None)),
+    };
+    let parent = mailboxes
+        .values()
+        .find(|m| m.path == parent_path)
+        .ok_or_else(|| {
+            MeliError::new(format!("Parent mailbox `{}` does not exist.", parent_path))
+                .set_kind(ErrorKind::NotFound)
+        })?;
+    if !parent.my_rights.may_create_child {
+        return Err(MeliError::new(format!(
+            "You do not have permission to create child mailboxes in `{}`.",
+            parent.path
+        ))
+        .set_kind(ErrorKind::PermissionDenied));
+    }
+    Ok((name.to_string(), Some(parent.id.clone())))
+}
+
 pub async fn get_message_list(
     conn: &JmapConnection,
     mailbox: &JmapMailbox,
@@ -323,6 +418,27 @@ pub async fn fetch(
     Ok(ret)
 }
 
+pub async fn get_identities(conn: &JmapConnection) -> Result<Vec<IdentityObject>> {
+    let identity_call: IdentityGet =
+        IdentityGet::new(Get::new().account_id(conn.mail_account_id().clone()));
+
+    let mut req = Request::new(conn.request_no.clone()).with_submission();
+    req.add_call(&identity_call);
+
+    let api_url = conn.session.lock().unwrap().api_url.clone();
+    let mut res = conn
+        .client
+        .post_async(api_url.as_str(), serde_json::to_string(&req)?)
+        .await?;
+
+    let res_text = res.text_async().await?;
+    let mut v: MethodResponse = serde_json::from_str(&res_text)?;
+    *conn.store.online_status.lock().await = (std::time::Instant::now(), Ok(()));
+    let GetResponse::<IdentityObject> { list, .. } =
+        GetResponse::<IdentityObject>::try_from(v.method_responses.remove(0))?;
+    Ok(list)
+}
+
 pub fn keywords_to_flags(keywords: Vec<String>) -> (Flag, Vec<String>) {
     let mut f = Flag::default();
     let mut tags = vec![];
@@ -346,3 +462,20 @@ pub fn keywords_to_flags(keywords: Vec<String>) -> (Flag, Vec<String>) {
     }
     (f, tags)
 }
+
+pub fn flags_to_keywords(flags: Flag) -> Vec<&'static str> {
+    let mut ret = vec![];
+    if flags.is_draft() {
+        ret.push("$draft");
+    }
+    if flags.is_seen() {
+        ret.push("$seen");
+    }
+    if flags.is_flagged() {
+        ret.push("$flagged");
+    }
+    if flags.is_replied() {
+        ret.push("$answered");
+    }
+    ret
+}
@@ -0,0 +1,224 @@
+/*
+ * meli - jmap module.
+ *
+ * Copyright 2021 Manos Pitsidianakis
+ *
+ * This file is part of meli.
+ *
+ * meli is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * meli is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with meli. If not, see <http://www.gnu.org/licenses/>.
+ */
+
+//! Push notifications over an RFC 8620 EventSource (Server-Sent Events) connection.
+
+use super::*;
+use futures::io::{AsyncBufReadExt, BufReader};
+use isahc::config::Configurable;
+
+/// Seconds between keepalive `ping` events the server is asked to send.
+const PING_INTERVAL: u64 = 60;
+
+/// The connection is re-established after this long, even if it's still alive.
+const CONNECTION_LIFETIME: Duration = Duration::from_secs(30 * 60);
+
+/// How long to wait before reconnecting when the server closes the connection right away,
+/// doubled every time it happens again up to `MAX_RECONNECT_DELAY`.
+pub const MIN_RECONNECT_DELAY: Duration = Duration::from_secs(1);
+pub const MAX_RECONNECT_DELAY: Duration = Duration::from_secs(60);
+
+/// A dispatched Server-Sent Event.
+#[derive(Debug, PartialEq)]
+pub struct Event {
+    pub event_type: String,
+    pub data: String,
+}
+
+/// Incremental parser for the `text/event-stream` format, fed one line at a time.
+#[derive(Debug, Default)]
+pub struct EventSourceParser {
+    event_type: String,
+    data: String,
+}
+
+impl EventSourceParser {
+    /// Feed a line, with or without its line terminator. Returns an event when `line` is the
+    /// blank line that ends it.
+    pub fn feed_line(&mut self, line: &str) -> Option<Event> {
+        let line = line.trim_end_matches(|c| c == '\n' || c == '\r');
+        if line.is_empty() {
+            let event_type = std::mem::take(&mut self.event_type);
+            let mut data = std::mem::take(&mut self.data);
+            if data.is_empty() {
+                return None;
+            }
+            data.pop();
+            return Some(Event {
+                event_type: if event_type.is_empty() {
+                    "message".to_string()
+                } else {
+                    event_type
+                },
+                data,
+            });
+        }
+        if line.starts_with(':') {
+            /* comment */
+            return None;
+        }
+        let (field, value) = match line.split_once(':') {
+            Some((field, value)) => (field, value.strip_prefix(' ').unwrap_or(value)),
+            None => (line, ""),
+        };
+        match field {
+            "event" => {
+                self.event_type = value.to_string();
+            }
+            "data" => {
+                self.data.push_str(value);
+                self.data.push('\n');
+            }
+            _ => { /* "id" and "retry" are not used */ }
+        }
+        None
+    }
+}
+
+/// Expand the `eventSourceUrl` URI template of the session resource.
+pub fn event_source_url(template: &str, types: &[&str], close_after: &str, ping: u64) -> String {
+    template
+        .replace("{types}", &types.join("%2C"))
+        .replace("{closeafter}", close_after)
+        .replace("{ping}", &ping.to_string())
+}
+
+/// Listen for `StateChange` push notifications and fetch the changes they announce. Returns
+/// `Ok(())` when the server closes the connection or it has been open for
+/// `CONNECTION_LIFETIME`, and an error if push is not available or the connection is lost.
+pub async fn listen(connection: &Arc<FutureMutex<JmapConnection>>) -> Result<()> {
+    let (client, url) = {
+        let conn = connection.lock().await;
+        let template = conn.session.lock().unwrap().event_source_url.clone();
+        if template.is_empty() {
+            return Err(
+                MeliError::new("Server does not advertise an eventSourceUrl.")
+                    .set_kind(ErrorKind::NotSupported),
+            );
+        }
+        (
+            conn.client.clone(),
+            event_source_url(
+                &template,
+                &[EmailObject::NAME, MailboxObject::NAME],
+                "no",
+                PING_INTERVAL,
+            ),
+        )
+    };
+    let request = isahc::Request::get(url.as_str())
+        .header("Accept", "text/event-stream")
+        /* `CONNECTION_LIFETIME` is enforced while reading, so that reaching it is not an
+         * error; this only has to be longer. */
+        .timeout(CONNECTION_LIFETIME + Duration::from_secs(2 * PING_INTERVAL))
+        .body(())?;
+    let connected_at = Instant::now();
+    let response = client.send_async(request).await?;
+    if !response.status().is_success() {
+        return Err(MeliError::new(format!(
+            "Could not connect to JMAP EventSource {}: {}",
+            url,
+            response.status()
+        )));
+    }
+    debug!("Connected to JMAP EventSource {}", &url);
+    let mut reader = BufReader::new(response.into_body());
+    let mut parser = EventSourceParser::default();
+    let mut line = String::new();
+    loop {
+        line.clear();
+        let remaining = match CONNECTION_LIFETIME.checked_sub(connected_at.elapsed()) {
+            Some(remaining) => remaining,
+            None => return Ok(()),
+        };
+        /* The server sends a ping event every PING_INTERVAL seconds, so silence for longer
+         * means the connection is dead. */
+        let read = match timeout(
+            Some(std::cmp::min(
+                remaining,
+                Duration::from_secs(2 * PING_INTERVAL),
+            )),
+            reader.read_line(&mut line),
+        )
+        .await
+        {
+            Ok(read) => read?,
+            Err(_) if connected_at.elapsed() >= CONNECTION_LIFETIME => return Ok(()),
+            Err(err) => return Err(err),
+        };
+        if read == 0 {
+            return Ok(());
+        }
+        match parser.feed_line(&line) {
+            Some(Event { event_type, data }) if event_type == "state" => {
+                let state_change: StateChange = serde_json::from_str(&data)?;
+                let conn = connection.lock().await;
+                conn.handle_state_change(state_change).await?;
+            }
+            _ => {}
+        }
+    }
+}
+
+#[test]
+fn test_jmap_event_source_parser() {
+    let mut parser = EventSourceParser::default();
+    let mut events = vec![];
+    let stream = ": keepalive\n\nevent: ping\ndata: {\"interval\":60}\n\r\nevent: state\n\
+                  data:{\"@type\":\"StateChange\",\n\
+                  data: \"changed\":{\"a1\":{\"Email\":\"s2\"}}}\n\n";
+    for line in stream.split_inclusive('\n') {
+        events.extend(parser.feed_line(line));
+    }
+    assert_eq!(
+        events,
+        vec![
+            Event {
+                event_type: "ping".to_string(),
+                data: "{\"interval\":60}".to_string(),
+            },
+            Event {
+                event_type: "state".to_string(),
+                data: "{\"@type\":\"StateChange\",\n\"changed\":{\"a1\":{\"Email\":\"s2\"}}}"
+                    .to_string(),
+            },
+        ]
+    );
+    let state_change: StateChange = serde_json::from_str(&events[1].data).unwrap();
+    let account_id: Id<Account> = "a1".to_string().into();
+    assert_eq!(
+        state_change
+            .state::<EmailObject>(&account_id)
+            .map(|s| s.inner)
+            .as_deref(),
+        Some("s2")
+    );
+    assert!(state_change.state::<MailboxObject>(&account_id).is_none());
+    assert_eq!(
+        event_source_url(
+            "https://jmap.example.com/events/?types={types}&closeafter={closeafter}&ping={ping}",
+            &["Email", "Mailbox"],
+            "no",
+            60
+        ),
+        "https://jmap.example.com/events/?types=Email%2CMailbox&closeafter=no&ping=60"
+    );
+}
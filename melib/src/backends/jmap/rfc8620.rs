@@ -782,7 +782,21 @@ where
         ///   state.
         if_in_state: Option<State<OBJ>>
     );
+    _impl!(
+        ///o  create: "Id[Foo]|null"
+        ///
+        ///   A map of a *creation id* (a temporary id set by the client) to Foo
+        ///   objects, or null if no objects are to be created.
+        create: Option<HashMap<Id<OBJ>, OBJ>>
+    );
     _impl!(update: Option<HashMap<Id<OBJ>, Value>>);
+    _impl!(
+        ///o  destroy: "Id[]|null"
+        ///
+        ///   A list of ids for Foo objects to permanently delete, or null if no
+        ///   objects are to be destroyed.
+        destroy: Option<Vec<Id<OBJ>>>
+    );
 }
 
 #[derive(Serialize, Deserialize, Debug)]
@@ -797,7 +811,7 @@ pub struct SetResponse<OBJ: Object> {
     ///   The state string that would have been returned by "Foo/get" before
     ///   making the requested changes, or null if the server doesn't know
     ///   what the previous state string was.
-    pub old_state: State<OBJ>,
+    pub old_state: Option<State<OBJ>>,
     ///o  newState: "String"
     ///
     ///   The state string that will now be returned by "Foo/get".
@@ -833,17 +847,17 @@ pub struct SetResponse<OBJ: Object> {
     ///
     ///   A map of the creation id to a SetError object for each record that
     ///   failed to be created, or null if all successful.
-    pub not_created: Option<Vec<SetError>>,
+    pub not_created: Option<HashMap<Id<OBJ>, SetError>>,
     ///o  notUpdated: "Id[SetError]|null"
     ///
     ///   A map of the Foo id to a SetError object for each record that
     ///   failed to be updated, or null if all successful.
-    pub not_updated: Option<Vec<SetError>>,
+    pub not_updated: Option<HashMap<Id<OBJ>, SetError>>,
     ///o  notDestroyed: "Id[SetError]|null"
     ///
     ///   A map of the Foo id to a SetError object for each record that
     ///   failed to be destroyed, or null if all successful.//
-    pub not_destroyed: Option<Vec<SetError>>,
+    pub not_destroyed: Option<HashMap<Id<OBJ>, SetError>>,
 }
 
 impl<OBJ: Object + DeserializeOwned> std::convert::TryFrom<&RawValue> for SetResponse<OBJ> {
@@ -857,76 +871,149 @@ impl<OBJ: Object + DeserializeOwned> std::convert::TryFrom<&RawValue> for SetRes
 
 #[derive(Deserialize, Serialize, Debug)]
 #[serde(rename_all = "camelCase")]
-#[serde(tag = "type", content = "description")]
+#[serde(tag = "type")]
 pub enum SetError {
     ///(create; update; destroy).  The create/update/destroy would violate an ACL or other permissions policy.
-    Forbidden(Option<String>),
+    Forbidden {
+        description: Option<String>,
+    },
     ///(create; update).  The create would exceed a server- defined limit on the number or total size of objects of this type.
-    OverQuota(Option<String>),
+    OverQuota {
+        description: Option<String>,
+    },
 
     ///(create; update).  The create/update would result in an object that exceeds a server-defined limit for the maximum size of a single object of this type.
-    TooLarge(Option<String>),
+    TooLarge {
+        description: Option<String>,
+    },
 
     ///(create).  Too many objects of this type have been created recently, and a server-defined rate limit has been reached.  It may work if tried again later.
-    RateLimit(Option<String>),
+    RateLimit {
+        description: Option<String>,
+    },
 
     ///(update; destroy).  The id given to update/destroy cannot be found.
-    NotFound(Option<String>),
+    NotFound {
+        description: Option<String>,
+    },
 
     ///(update).  The PatchObject given to update the record was not a valid patch (see the patch description).
-    InvalidPatch(Option<String>),
+    InvalidPatch {
+        description: Option<String>,
+    },
 
     ///(update).  The client requested that an object be both updated and destroyed in the same /set request, and the server has decided to therefore ignore the update.
-    WillDestroy(Option<String>),
+    WillDestroy {
+        description: Option<String>,
+    },
     ///(create; update).  The record given is invalid in some way.
     InvalidProperties {
         description: Option<String>,
+        #[serde(default)]
         properties: Vec<String>,
     },
     ///(create; destroy).  This is a singleton type, so you cannot create another one or destroy the existing one.
-    Singleton(Option<String>),
-    RequestTooLarge(Option<String>),
-    StateMismatch(Option<String>),
+    Singleton {
+        description: Option<String>,
+    },
+    RequestTooLarge {
+        description: Option<String>,
+    },
+    StateMismatch {
+        description: Option<String>,
+    },
+    ///(destroy; Mailbox).  The Mailbox still has at least one child Mailbox. (RFC 8621)
+    MailboxHasChild {
+        description: Option<String>,
+    },
+    ///(destroy; Mailbox).  The Mailbox has at least one Email assigned to it, and the
+    ///"onDestroyRemoveEmails" argument was false. (RFC 8621)
+    MailboxHasEmail {
+        description: Option<String>,
+    },
+    ///An error type this client does not know about.
+    #[serde(other)]
+    Unknown,
+}
+
+impl SetError {
+    pub fn description(&self) -> Option<&str> {
+        use SetError::*;
+        match self {
+            Forbidden { description }
+            | OverQuota { description }
+            | TooLarge { description }
+            | RateLimit { description }
+            | NotFound { description }
+            | InvalidPatch { description }
+            | WillDestroy { description }
+            | InvalidProperties { description, .. }
+            | Singleton { description }
+            | RequestTooLarge { description }
+            | StateMismatch { description }
+            | MailboxHasChild { description }
+            | MailboxHasEmail { description } => description.as_deref(),
+            Unknown => None,
+        }
+    }
+
+    /// The `ErrorKind` of the `MeliError` this error is converted to.
+    pub fn kind(&self) -> crate::error::ErrorKind {
+        use crate::error::ErrorKind;
+        use SetError::*;
+        match self {
+            Forbidden { .. } => ErrorKind::PermissionDenied,
+            NotFound { .. } => ErrorKind::NotFound,
+            InvalidPatch { .. }
+            | WillDestroy { .. }
+            | InvalidProperties { .. }
+            | Singleton { .. }
+            | StateMismatch { .. }
+            | MailboxHasChild { .. }
+            | MailboxHasEmail { .. } => ErrorKind::ValueError,
+            OverQuota { .. } | TooLarge { .. } | RateLimit { .. } | RequestTooLarge { .. } => {
+                ErrorKind::External
+            }
+            Unknown => ErrorKind::None,
+        }
+    }
 }
 
 impl core::fmt::Display for SetError {
     fn fmt(&self, fmt: &mut core::fmt::Formatter) -> core::fmt::Result {
         use SetError::*;
-        match self {
-            Forbidden(Some(description)) => write!(fmt, "Forbidden: {}", description),
-            Forbidden(None) => write!(fmt, "Forbidden"),
-            OverQuota(Some(description)) => write!(fmt, "OverQuota: {}", description),
-            OverQuota(None) => write!(fmt, "OverQuota"),
-            TooLarge(Some(description)) => write!(fmt, "TooLarge: {}", description),
-            TooLarge(None) => write!(fmt, "TooLarge"),
-            RateLimit(Some(description)) => write!(fmt, "RateLimit: {}", description),
-            RateLimit(None) => write!(fmt, "RateLimit"),
-            NotFound(Some(description)) => write!(fmt, "NotFound: {}", description),
-            NotFound(None) => write!(fmt, "NotFound"),
-            InvalidPatch(Some(description)) => write!(fmt, "InvalidPatch: {}", description),
-            InvalidPatch(None) => write!(fmt, "InvalidPatch"),
-            WillDestroy(Some(description)) => write!(fmt, "WillDestroy: {}", description),
-            WillDestroy(None) => write!(fmt, "WillDestroy"),
-            InvalidProperties {
-                description: Some(description),
-                properties,
-            } => write!(
-                fmt,
-                "InvalidProperties: {}, {}",
-                description,
-                properties.join(",")
-            ),
-            InvalidProperties {
-                description: None,
-                properties,
-            } => write!(fmt, "InvalidProperties: {}", properties.join(",")),
-            Singleton(Some(description)) => write!(fmt, "Singleton: {}", description),
-            Singleton(None) => write!(fmt, "Singleton"),
-            RequestTooLarge(Some(description)) => write!(fmt, "RequestTooLarge: {}", description),
-            RequestTooLarge(None) => write!(fmt, "RequestTooLarge"),
-            StateMismatch(Some(description)) => write!(fmt, "StateMismatch: {}", description),
-            StateMismatch(None) => write!(fmt, "StateMismatch"),
+        let name = match self {
+            Forbidden { .. } => "Forbidden",
+            OverQuota { .. } => "OverQuota",
+            TooLarge { .. } => "TooLarge",
+            RateLimit { .. } => "RateLimit",
+            NotFound { .. } => "NotFound",
+            InvalidPatch { .. } => "InvalidPatch",
+            WillDestroy { .. } => "WillDestroy",
+            InvalidProperties { .. } => "InvalidProperties",
+            Singleton { .. } => "Singleton",
+            RequestTooLarge { .. } => "RequestTooLarge",
+            StateMismatch { .. } => "StateMismatch",
+            MailboxHasChild { .. } => "MailboxHasChild",
+            MailboxHasEmail { .. } => "MailboxHasEmail",
+            Unknown => "Unknown error",
+        };
+        write!(fmt, "{}", name)?;
+        if let Some(description) = self.description() {
+            write!(fmt, ": {}", description)?;
+        }
+        if let InvalidProperties { properties, .. } = self {
+            if !properties.is_empty() {
+                write!(fmt, " ({})", properties.join(","))?;
+            }
         }
+        Ok(())
+    }
+}
+
+impl From<SetError> for crate::error::MeliError {
+    fn from(err: SetError) -> crate::error::MeliError {
+        crate::error::MeliError::new(err.to_string()).set_kind(err.kind())
     }
 }
 
@@ -1172,3 +1259,41 @@ pub struct AddedItem<OBJ: Object> {
     pub id: Id<OBJ>,
     pub index: usize,
 }
+
+/// #`StateChange`
+///
+/// Push notification sent by the server when the state of one or more data types changes.
+/// (RFC 8620, Section 7.1)
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct StateChange {
+    ///o  @type: "String"
+    ///
+    ///   This MUST be the string "StateChange".
+    #[serde(rename = "@type")]
+    pub _type: String,
+    ///o  changed: "Id[TypeState]"
+    ///
+    ///   A map of an "account id" to an object encoding the state of data
+    ///   types that have changed for that account since the last
+    ///   StateChange object was pushed, for each of the accounts to which
+    ///   the user has access and for which something has changed.
+    ///
+    ///   A *TypeState* object is a map.  The keys are the type name "Foo"
+    ///   (e.g., "Mailbox" or "Email"), and the value is the "state"
+    ///   property that would currently be returned by a call to "Foo/get".
+    pub changed: HashMap<Id<Account>, HashMap<String, String>>,
+}
+
+impl StateChange {
+    /// The new state of data type `OBJ` for account `account_id`, if it changed.
+    pub fn state<OBJ: Object>(&self, account_id: &Id<Account>) -> Option<State<OBJ>> {
+        self.changed
+            .get(account_id)
+            .and_then(|types| types.get(OBJ::NAME))
+            .map(|s| State {
+                inner: s.clone(),
+                _ph: PhantomData,
+            })
+    }
+}
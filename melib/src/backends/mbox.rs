@@ -138,7 +138,8 @@ use self::notify::{watcher, DebouncedEvent, RecursiveMode, Watcher};
 use std::collections::hash_map::{DefaultHasher, HashMap};
 use std::fs::File;
 use std::hash::Hasher;
-use std::io::{BufReader, Read};
+use std::io::{BufReader, Read, Write};
+use std::os::unix::fs::MetadataExt;
 use std::os::unix::io::AsRawFd;
 use std::path::{Path, PathBuf};
 use std::str::FromStr;
@@ -150,6 +151,9 @@ pub mod write;
 pub type Offset = usize;
 pub type Length = usize;
 
+/// Where each message of an mbox file starts, right after its From_ line, and how long it is.
+type MessageIndex = HashMap<EnvelopeHash, (Offset, Length)>;
+
 #[cfg(target_os = "linux")]
 const F_OFD_SETLKW: libc::c_int = 38;
 
@@ -183,6 +187,229 @@ fn get_rw_lock_blocking(f: &File, path: &Path) -> Result<()> {
     Ok(())
 }
 
+/// Open the mbox file at `path` and lock it with [`get_rw_lock_blocking`].
+///
+/// [`replace_file_contents`] renames a new file over `path` while the old one is locked, so a
+/// process that was waiting for the lock may get it on a file that is no longer the mailbox. In
+/// that case the file is opened and locked again.
+fn open_locked(path: &Path, options: &std::fs::OpenOptions) -> Result<File> {
+    loop {
+        let file = options.open(path)?;
+        get_rw_lock_blocking(&file, path)?;
+        let (locked, current) = (file.metadata()?, std::fs::metadata(path)?);
+        if locked.dev() == current.dev() && locked.ino() == current.ino() {
+            return Ok(file);
+        }
+    }
+}
+
+/// Atomically replace the contents of the mbox file at `path`.
+///
+/// The new contents are written to a temporary file in the same directory which is then renamed
+/// over `path`, so that readers see either the old or the new mailbox but never a partial one.
+/// The caller is expected to hold the lock of the original file. The new file is locked before it
+/// is renamed and is returned so that the caller can keep it locked until it is done.
+fn replace_file_contents(path: &Path, contents: &[u8]) -> Result<File> {
+    let file_name = path
+        .file_name()
+        .map(|f| f.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let tmp_path = path.with_file_name(format!(".{}.{}.tmp", file_name, std::process::id()));
+    let permissions = std::fs::metadata(path)?.permissions();
+    let write_tmp = || -> Result<File> {
+        let mut file = std::fs::OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create_new(true)
+            .open(&tmp_path)?;
+        get_rw_lock_blocking(&file, &tmp_path)?;
+        file.write_all(contents)?;
+        file.set_permissions(permissions)?;
+        file.sync_all()?;
+        std::fs::rename(&tmp_path, path)?;
+        Ok(file)
+    };
+    let file = match write_tmp() {
+        Ok(file) => file,
+        Err(err) => {
+            let _ = std::fs::remove_file(&tmp_path);
+            return Err(
+                MeliError::new(format!("Could not rewrite {}", path.display()))
+                    .set_source(Some(Arc::new(err))),
+            );
+        }
+    };
+    if let Some(parent) = path.parent() {
+        if let Ok(dir) = File::open(parent) {
+            let _ = dir.sync_all();
+        }
+    }
+    Ok(file)
+}
+
+/// Read the flags stored in the CClient `Status` and `X-Status` headers of `message`.
+fn cclient_flags(message: &[u8]) -> Flag {
+    let mut flags = Flag::empty();
+    let headers = match parser::headers::headers_raw(message) {
+        Ok((_, headers)) => headers,
+        Err(_) => return flags,
+    };
+    for name in [&b"Status:"[..], &b"X-Status:"[..]] {
+        let start = if headers.starts_with(name) {
+            Some(0)
+        } else {
+            headers.find([&b"\n"[..], name].concat()).map(|v| v + 1)
+        };
+        if let Some(start) = start {
+            let start = start + name.len();
+            let end = headers[start..]
+                .find(b"\n")
+                .map(|v| start + v)
+                .unwrap_or_else(|| headers.len());
+            let status = headers[start..end].trim();
+            if status.contains(&b'F') {
+                flags.set(Flag::FLAGGED, true);
+            }
+            if status.contains(&b'A') {
+                flags.set(Flag::REPLIED, true);
+            }
+            if status.contains(&b'R') {
+                flags.set(Flag::SEEN, true);
+            }
+            if status.contains(&b'D') {
+                flags.set(Flag::TRASHED, true);
+            }
+            if status.contains(&b'T') {
+                flags.set(Flag::DRAFT, true);
+            }
+        }
+    }
+    flags
+}
+
+/// Replace the CClient `Status` and `X-Status` headers of `message` with ones describing
+/// `flags`. The rest of the message, including the `O` (non-Recent) status, is left untouched.
+fn set_cclient_flags(message: &[u8], flags: Flag) -> Vec<u8> {
+    let line_ending: &[u8] = match message.find(b"\n") {
+        Some(pos) if pos > 0 && message[pos - 1] == b'\r' => b"\r\n",
+        _ => b"\n",
+    };
+    let mut ret = Vec::with_capacity(message.len() + 32);
+    let mut is_recent = true;
+    let mut skip_continuation = false;
+    let mut body_offset = message.len();
+    let mut offset = 0;
+    for line in message.split_inclusive(|b| *b == b'\n') {
+        if line == b"\n" || line == b"\r\n" {
+            body_offset = offset;
+            break;
+        }
+        offset += line.len();
+        if line.starts_with(b" ") || line.starts_with(b"\t") {
+            if !skip_continuation {
+                ret.extend_from_slice(line);
+            }
+            continue;
+        }
+        let name = line.split(|b| *b == b':').next().unwrap_or_default();
+        skip_continuation =
+            name.eq_ignore_ascii_case(b"Status") || name.eq_ignore_ascii_case(b"X-Status");
+        if name.eq_ignore_ascii_case(b"Status") && line[name.len()..].contains(&b'O') {
+            is_recent = false;
+        }
+        if !skip_continuation {
+            ret.extend_from_slice(line);
+            if !line.ends_with(b"\n") {
+                ret.extend_from_slice(line_ending);
+            }
+        }
+    }
+    let status = format!(
+        "{seen}{old}",
+        seen = if flags.is_seen() { "R" } else { "" },
+        old = if is_recent { "" } else { "O" },
+    );
+    if !status.is_empty() {
+        ret.extend_from_slice(b"Status: ");
+        ret.extend_from_slice(status.as_bytes());
+        ret.extend_from_slice(line_ending);
+    }
+    let x_status = format!(
+        "{flagged}{replied}{draft}{trashed}",
+        flagged = if flags.is_flagged() { "F" } else { "" },
+        replied = if flags.is_replied() { "A" } else { "" },
+        draft = if flags.is_draft() { "T" } else { "" },
+        trashed = if flags.is_trashed() { "D" } else { "" }
+    );
+    if !x_status.is_empty() {
+        ret.extend_from_slice(b"X-Status: ");
+        ret.extend_from_slice(x_status.as_bytes());
+        ret.extend_from_slice(line_ending);
+    }
+    ret.extend_from_slice(&message[body_offset..]);
+    ret
+}
+
+/// What to do with a message when rewriting an mbox file with [`rewrite_messages`].
+enum Rewrite {
+    Keep,
+    Replace(Vec<u8>),
+    Remove,
+}
+
+/// Build new mbox contents from `contents` by passing each message of `index` through `f`.
+///
+/// Bytes that are not part of any indexed message, such as messages that could not be parsed,
+/// are copied verbatim. Returns the new contents along with the updated index of the messages
+/// that were kept.
+fn rewrite_messages(
+    contents: &[u8],
+    index: &MessageIndex,
+    mut f: impl FnMut(EnvelopeHash, &[u8]) -> Result<Rewrite>,
+) -> Result<(Vec<u8>, MessageIndex)> {
+    let mut messages = index
+        .iter()
+        .map(|(&hash, &(offset, length))| (hash, offset, length))
+        .filter(|(_, offset, length)| offset + length <= contents.len())
+        .collect::<Vec<_>>();
+    messages.sort_by_key(|(_, offset, _)| *offset);
+    let mut ret = Vec::with_capacity(contents.len());
+    let mut new_index = HashMap::with_capacity(index.len());
+    let mut cursor = 0;
+    for (hash, offset, length) in messages {
+        if offset < cursor {
+            continue;
+        }
+        /* The index points right after the From_ line of each message. */
+        let from_line_start = if offset == 0 {
+            0
+        } else {
+            contents[cursor..offset - 1]
+                .rfind(b"\n")
+                .map(|v| cursor + v + 1)
+                .unwrap_or(cursor)
+        };
+        let message = &contents[offset..offset + length];
+        ret.extend_from_slice(&contents[cursor..from_line_start]);
+        match f(hash, message)? {
+            Rewrite::Keep => {
+                ret.extend_from_slice(&contents[from_line_start..offset]);
+                new_index.insert(hash, (ret.len(), length));
+                ret.extend_from_slice(message);
+            }
+            Rewrite::Replace(new_message) => {
+                ret.extend_from_slice(&contents[from_line_start..offset]);
+                new_index.insert(hash, (ret.len(), new_message.len()));
+                ret.extend_from_slice(&new_message);
+            }
+            Rewrite::Remove => {}
+        }
+        cursor = offset + length;
+    }
+    ret.extend_from_slice(&contents[cursor..]);
+    Ok((ret, new_index))
+}
+
 #[derive(Debug)]
 struct MboxMailbox {
     hash: MailboxHash,
@@ -293,11 +520,10 @@ impl MboxOp {
 impl BackendOp for MboxOp {
     fn as_bytes(&mut self) -> ResultFuture<Vec<u8>> {
         if self.slice.get_mut().is_none() {
-            let file = std::fs::OpenOptions::new()
-                .read(true)
-                .write(true)
-                .open(&self.path)?;
-            get_rw_lock_blocking(&file, &self.path)?;
+            let file = open_locked(
+                &self.path,
+                std::fs::OpenOptions::new().read(true).write(true),
+            )?;
             let mut buf_reader = BufReader::new(file);
             let mut contents = Vec::new();
             buf_reader.read_to_end(&mut contents)?;
@@ -310,62 +536,19 @@ impl BackendOp for MboxOp {
     }
 
     fn fetch_flags(&self) -> ResultFuture<Flag> {
-        let mut flags = Flag::empty();
         if self.slice.borrow().is_none() {
-            let file = std::fs::OpenOptions::new()
-                .read(true)
-                .write(true)
-                .open(&self.path)?;
-            get_rw_lock_blocking(&file, &self.path)?;
+            let file = open_locked(
+                &self.path,
+                std::fs::OpenOptions::new().read(true).write(true),
+            )?;
             let mut buf_reader = BufReader::new(file);
             let mut contents = Vec::new();
             buf_reader.read_to_end(&mut contents)?;
             *self.slice.borrow_mut() = Some(contents);
         }
         let slice_ref = self.slice.borrow();
-        let (_, headers) = parser::headers::headers_raw(slice_ref.as_ref().unwrap().as_slice())?;
-        if let Some(start) = headers.find(b"Status:") {
-            if let Some(end) = headers[start..].find(b"\n") {
-                let start = start + b"Status:".len();
-                let status = headers[start..start + end].trim();
-                if status.contains(&b'F') {
-                    flags.set(Flag::FLAGGED, true);
-                }
-                if status.contains(&b'A') {
-                    flags.set(Flag::REPLIED, true);
-                }
-                if status.contains(&b'R') {
-                    flags.set(Flag::SEEN, true);
-                }
-                if status.contains(&b'D') {
-                    flags.set(Flag::TRASHED, true);
-                }
-                if status.contains(&b'T') {
-                    flags.set(Flag::DRAFT, true);
-                }
-            }
-        }
-        if let Some(start) = headers.find(b"X-Status:") {
-            let start = start + b"X-Status:".len();
-            if let Some(end) = headers[start..].find(b"\n") {
-                let status = headers[start..start + end].trim();
-                if status.contains(&b'F') {
-                    flags.set(Flag::FLAGGED, true);
-                }
-                if status.contains(&b'A') {
-                    flags.set(Flag::REPLIED, true);
-                }
-                if status.contains(&b'R') {
-                    flags.set(Flag::SEEN, true);
-                }
-                if status.contains(&b'D') {
-                    flags.set(Flag::TRASHED, true);
-                }
-                if status.contains(&b'T') {
-                    flags.set(Flag::DRAFT, true);
-                }
-            }
-        }
+        let slice = slice_ref.as_ref().unwrap().as_slice();
+        let flags = cclient_flags(&slice[self.offset..self.offset + self.length]);
         Ok(Box::pin(async move { Ok(flags) }))
     }
 }
@@ -834,6 +1017,214 @@ impl<'a> Iterator for MessageIterator<'a> {
     }
 }
 
+/// Re-read the mbox file of `mailbox_hash` and report the messages that were appended to it
+/// since it was last read. If the file was modified in any other way, request a rescan instead.
+fn sync_mailbox(
+    mailbox_hash: MailboxHash,
+    mailboxes: &Arc<Mutex<HashMap<MailboxHash, MboxMailbox>>>,
+    mailbox_index: &Arc<Mutex<HashMap<EnvelopeHash, MailboxHash>>>,
+    prefer_mbox_type: Option<MboxFormat>,
+    account_hash: AccountHash,
+    sender: &BackendEventConsumer,
+) -> Result<()> {
+    let path = match mailboxes.lock().unwrap().get(&mailbox_hash) {
+        Some(f) => f.fs_path.clone(),
+        None => return Ok(()),
+    };
+    let file = open_locked(&path, std::fs::OpenOptions::new().read(true).write(true))?;
+    let mut mailbox_lock = mailboxes.lock().unwrap();
+    let mut buf_reader = BufReader::new(file);
+    let mut contents = Vec::new();
+    buf_reader.read_to_end(&mut contents)?;
+    if contents == mailbox_lock[&mailbox_hash].content {
+        return Ok(());
+    }
+    if contents.starts_with(mailbox_lock[&mailbox_hash].content.as_slice()) {
+        if let Ok((_, envelopes)) = mbox_parse(
+            mailbox_lock[&mailbox_hash].index.clone(),
+            &contents,
+            mailbox_lock[&mailbox_hash].content.len(),
+            prefer_mbox_type,
+        ) {
+            let mut mailbox_index_lck = mailbox_index.lock().unwrap();
+            for env in envelopes {
+                mailbox_index_lck.insert(env.hash(), mailbox_hash);
+                (sender)(
+                    account_hash,
+                    BackendEvent::Refresh(RefreshEvent {
+                        account_hash,
+                        mailbox_hash,
+                        kind: RefreshEventKind::Create(Box::new(env)),
+                    }),
+                );
+            }
+        }
+    } else {
+        (sender)(
+            account_hash,
+            BackendEvent::Refresh(RefreshEvent {
+                account_hash,
+                mailbox_hash,
+                kind: RefreshEventKind::Rescan,
+            }),
+        );
+    }
+    mailbox_lock
+        .entry(mailbox_hash)
+        .and_modify(|f| f.content = contents);
+    Ok(())
+}
+
+/// Append `messages` to the mbox file at `path`, storing their flags as CClient metadata.
+fn append_messages(
+    path: &Path,
+    messages: &[(&[u8], Flag)],
+    format: Option<MboxFormat>,
+) -> Result<()> {
+    let mut file = open_locked(path, std::fs::OpenOptions::new().read(true).append(true))?;
+    let mut contents = Vec::new();
+    file.read_to_end(&mut contents)?;
+    let crlf = matches!(contents.find(b"\n"), Some(pos) if pos > 0 && contents[pos - 1] == b'\r');
+    let mut is_empty = contents.is_empty();
+    let mut buf = Vec::new();
+    for &(bytes, flags) in messages {
+        append_message(
+            &mut buf,
+            bytes,
+            flags,
+            format.unwrap_or_default(),
+            is_empty,
+            crlf,
+        )?;
+        is_empty = false;
+    }
+    file.write_all(&buf)?;
+    file.sync_all()?;
+    Ok(())
+}
+
+/// Append `bytes` to `buf` in `format`. A message that starts with a From_ line, such as one
+/// from [`message_for_copy`], keeps it.
+fn append_message(
+    buf: &mut Vec<u8>,
+    bytes: &[u8],
+    flags: Flag,
+    format: MboxFormat,
+    is_empty: bool,
+    crlf: bool,
+) -> Result<()> {
+    if bytes.starts_with(b"From ") {
+        let (from_line, bytes) = match bytes.find(b"\n") {
+            Some(pos) => (&bytes[..pos], &bytes[pos + 1..]),
+            None => (bytes, &b""[..]),
+        };
+        format.append_with_from_line(
+            buf,
+            bytes,
+            from_line.strip_suffix(b"\r").unwrap_or(from_line),
+            (flags, vec![]),
+            MboxMetadata::CClient,
+            is_empty,
+            crlf,
+        )
+    } else {
+        let envelope_from = Envelope::from_bytes(bytes, None)
+            .ok()
+            .and_then(|env| env.from().first().cloned());
+        format.append(
+            buf,
+            bytes,
+            envelope_from.as_ref(),
+            None,
+            (flags, vec![]),
+            MboxMetadata::CClient,
+            is_empty,
+            crlf,
+        )
+    }
+}
+
+/// The message at `offset` of mbox `contents` as [`append_message`] should get it to copy it to
+/// another file: with its From_ line and without the quoting of `format`, so that appending does
+/// not quote it twice.
+fn message_for_copy(
+    contents: &[u8],
+    offset: Offset,
+    length: Length,
+    format: MboxFormat,
+) -> (Vec<u8>, Flag) {
+    let mut message = &contents[offset..offset + length];
+    /* Strip the line endings that `MboxFormat::append` puts between it and the next message,
+     * which the index may include. */
+    if offset + length < contents.len() {
+        for _ in 0..2 {
+            if message.ends_with(b"\r\n\r\n") {
+                message = &message[..message.len() - 2];
+            } else if message.ends_with(b"\n\n") {
+                message = &message[..message.len() - 1];
+            }
+        }
+    }
+    /* The index usually points right after the From_ line, but may point at it. */
+    let from_line = if message.starts_with(b"From ") {
+        let end = message.find(b"\n").map(|v| v + 1).unwrap_or(message.len());
+        let from_line = &message[..end];
+        message = &message[end..];
+        from_line
+    } else if offset == 0 {
+        &b""[..]
+    } else {
+        let start = contents[..offset - 1]
+            .rfind(b"\n")
+            .map(|v| v + 1)
+            .unwrap_or(0);
+        &contents[start..offset]
+    };
+    let mut ret = from_line.to_vec();
+    ret.extend_from_slice(&format.unquote(message));
+    (ret, cclient_flags(message))
+}
+
+/// Rewrite the messages of mailbox `mailbox_hash` with [`rewrite_messages`] while holding the
+/// lock of its file, and update its index accordingly.
+fn rewrite_mailbox(
+    mailbox_hash: MailboxHash,
+    mailboxes: &Arc<Mutex<HashMap<MailboxHash, MboxMailbox>>>,
+    f: impl FnMut(EnvelopeHash, &[u8]) -> Result<Rewrite>,
+) -> Result<()> {
+    let path = mailboxes.lock().unwrap()[&mailbox_hash].fs_path.clone();
+    let file = open_locked(&path, std::fs::OpenOptions::new().read(true).write(true))?;
+    let mut buf_reader = BufReader::new(&file);
+    let mut contents = Vec::new();
+    buf_reader.read_to_end(&mut contents)?;
+    let mut mailbox_lock = mailboxes.lock().unwrap();
+    let mailbox = mailbox_lock.get_mut(&mailbox_hash).unwrap();
+    /* Offsets in the index are only valid if the file was not modified behind our back. Appended
+     * messages are fine; they are copied verbatim and picked up by the watcher afterwards. */
+    if !contents.starts_with(&mailbox.content) {
+        return Err(MeliError::new(format!(
+            "{} was modified by another program; refresh the mailbox and try again.",
+            path.display()
+        )));
+    }
+    let unparsed_len = contents.len() - mailbox.content.len();
+    let mut index_lck = mailbox.index.lock().unwrap();
+    let (new_contents, new_index) =
+        rewrite_messages(&contents[..mailbox.content.len()], &index_lck, f)?;
+    if new_contents.as_slice() == mailbox.content.as_slice() {
+        return Ok(());
+    }
+    let mut new_file_contents = new_contents.clone();
+    new_file_contents.extend_from_slice(&contents[contents.len() - unparsed_len..]);
+    let new_file = replace_file_contents(&path, &new_file_contents)?;
+    *index_lck = new_index;
+    drop(index_lck);
+    mailbox.content = new_contents;
+    drop(file);
+    drop(new_file);
+    Ok(())
+}
+
 /// Mbox backend
 #[derive(Debug)]
 pub struct MboxType {
@@ -931,11 +1322,10 @@ impl MailBackend for MboxType {
         let mailboxes = self.mailboxes.clone();
 
         let mailbox_path = mailboxes.lock().unwrap()[&mailbox_hash].fs_path.clone();
-        let file = std::fs::OpenOptions::new()
-            .read(true)
-            .write(true)
-            .open(&mailbox_path)?;
-        get_rw_lock_blocking(&file, &mailbox_path)?;
+        let file = open_locked(
+            &mailbox_path,
+            std::fs::OpenOptions::new().read(true).write(true),
+        )?;
         let mut buf_reader = BufReader::new(file);
         let mut contents = Vec::new();
         buf_reader.read_to_end(&mut contents)?;
@@ -961,8 +1351,22 @@ impl MailBackend for MboxType {
         }))
     }
 
-    fn refresh(&mut self, _mailbox_hash: MailboxHash) -> ResultFuture<()> {
-        Err(MeliError::new("Unimplemented."))
+    fn refresh(&mut self, mailbox_hash: MailboxHash) -> ResultFuture<()> {
+        let mailboxes = self.mailboxes.clone();
+        let mailbox_index = self.mailbox_index.clone();
+        let prefer_mbox_type = self.prefer_mbox_type;
+        let account_hash = self.account_hash();
+        let sender = self.event_consumer.clone();
+        Ok(Box::pin(async move {
+            sync_mailbox(
+                mailbox_hash,
+                &mailboxes,
+                &mailbox_index,
+                prefer_mbox_type,
+                account_hash,
+                &sender,
+            )
+        }))
     }
 
     fn watch(&self) -> ResultFuture<()> {
@@ -978,15 +1382,13 @@ impl MailBackend for MboxType {
                 .map_err(MeliError::new)?;
             debug!("watching {:?}", f.fs_path.as_path());
         }
-        let account_hash = {
-            let mut hasher = DefaultHasher::new();
-            hasher.write(self.account_name.as_bytes());
-            hasher.finish()
-        };
+        let account_hash = self.account_hash();
         let mailboxes = self.mailboxes.clone();
         let mailbox_index = self.mailbox_index.clone();
         let prefer_mbox_type = self.prefer_mbox_type;
         Ok(Box::pin(async move {
+            // Move `watcher` in the closure's scope so that it doesn't get dropped.
+            let mut watcher = watcher;
             loop {
                 match rx.recv() {
                     /*
@@ -1003,58 +1405,16 @@ impl MailBackend for MboxType {
                         /* Update */
                         DebouncedEvent::NoticeWrite(pathbuf) | DebouncedEvent::Write(pathbuf) => {
                             let mailbox_hash = get_path_hash!(&pathbuf);
-                            let file = match std::fs::OpenOptions::new()
-                                .read(true)
-                                .write(true)
-                                .open(&pathbuf)
-                            {
-                                Ok(f) => f,
-                                Err(_) => {
-                                    continue;
-                                }
-                            };
-                            get_rw_lock_blocking(&file, &pathbuf)?;
-                            let mut mailbox_lock = mailboxes.lock().unwrap();
-                            let mut buf_reader = BufReader::new(file);
-                            let mut contents = Vec::new();
-                            if let Err(e) = buf_reader.read_to_end(&mut contents) {
-                                debug!(e);
-                                continue;
-                            };
-                            if contents.starts_with(mailbox_lock[&mailbox_hash].content.as_slice())
-                            {
-                                if let Ok((_, envelopes)) = mbox_parse(
-                                    mailbox_lock[&mailbox_hash].index.clone(),
-                                    &contents,
-                                    mailbox_lock[&mailbox_hash].content.len(),
-                                    prefer_mbox_type,
-                                ) {
-                                    let mut mailbox_index_lck = mailbox_index.lock().unwrap();
-                                    for env in envelopes {
-                                        mailbox_index_lck.insert(env.hash(), mailbox_hash);
-                                        (sender)(
-                                            account_hash,
-                                            BackendEvent::Refresh(RefreshEvent {
-                                                account_hash,
-                                                mailbox_hash,
-                                                kind: RefreshEventKind::Create(Box::new(env)),
-                                            }),
-                                        );
-                                    }
-                                }
-                            } else {
-                                (sender)(
-                                    account_hash,
-                                    BackendEvent::Refresh(RefreshEvent {
-                                        account_hash,
-                                        mailbox_hash,
-                                        kind: RefreshEventKind::Rescan,
-                                    }),
-                                );
+                            if let Err(err) = sync_mailbox(
+                                mailbox_hash,
+                                &mailboxes,
+                                &mailbox_index,
+                                prefer_mbox_type,
+                                account_hash,
+                                &sender,
+                            ) {
+                                debug!("could not read {}: {}", pathbuf.display(), err);
                             }
-                            mailbox_lock
-                                .entry(mailbox_hash)
-                                .and_modify(|f| f.content = contents);
                         }
                         /* Remove */
                         DebouncedEvent::NoticeRemove(pathbuf) | DebouncedEvent::Remove(pathbuf) => {
@@ -1065,6 +1425,27 @@ impl MailBackend for MboxType {
                                 .any(|f| f.fs_path == pathbuf)
                             {
                                 let mailbox_hash = get_path_hash!(&pathbuf);
+                                if pathbuf.is_file() {
+                                    /* The file was atomically replaced, eg. after an expunge.
+                                     * Watch the new file instead. */
+                                    let _ = watcher.unwatch(&pathbuf);
+                                    if let Err(err) =
+                                        watcher.watch(&pathbuf, RecursiveMode::Recursive)
+                                    {
+                                        debug!("could not watch {}: {}", pathbuf.display(), err);
+                                    }
+                                    if let Err(err) = sync_mailbox(
+                                        mailbox_hash,
+                                        &mailboxes,
+                                        &mailbox_index,
+                                        prefer_mbox_type,
+                                        account_hash,
+                                        &sender,
+                                    ) {
+                                        debug!("could not read {}: {}", pathbuf.display(), err);
+                                    }
+                                    continue;
+                                }
                                 (sender)(
                                     account_hash,
                                     BackendEvent::Refresh(RefreshEvent {
@@ -1148,38 +1529,231 @@ impl MailBackend for MboxType {
 
     fn copy_messages(
         &mut self,
-        _env_hashes: EnvelopeHashBatch,
-        _source_mailbox_hash: MailboxHash,
-        _destination_mailbox_hash: MailboxHash,
-        _move_: bool,
+        env_hashes: EnvelopeHashBatch,
+        source_mailbox_hash: MailboxHash,
+        destination_mailbox_hash: MailboxHash,
+        move_: bool,
     ) -> ResultFuture<()> {
-        Err(MeliError::new("Unimplemented."))
+        if source_mailbox_hash == destination_mailbox_hash {
+            return Err(MeliError::new(
+                "Source and destination mailboxes must be different.",
+            ));
+        }
+        let mailboxes = self.mailboxes.clone();
+        for h in [source_mailbox_hash, destination_mailbox_hash] {
+            if !mailboxes.lock().unwrap().contains_key(&h) {
+                return Err(MeliError::new(format!(
+                    "Mailbox with hash {} not found.",
+                    h
+                )));
+            }
+        }
+        let mailbox_index = self.mailbox_index.clone();
+        let prefer_mbox_type = self.prefer_mbox_type;
+        let account_hash = self.account_hash();
+        let sender = self.event_consumer.clone();
+        Ok(Box::pin(async move {
+            let (source_path, destination_path) = {
+                let mailboxes_lck = mailboxes.lock().unwrap();
+                (
+                    mailboxes_lck[&source_mailbox_hash].fs_path.clone(),
+                    mailboxes_lck[&destination_mailbox_hash].fs_path.clone(),
+                )
+            };
+            let messages: Vec<(Vec<u8>, Flag)> = {
+                let file = open_locked(
+                    &source_path,
+                    std::fs::OpenOptions::new().read(true).write(true),
+                )?;
+                let mut buf_reader = BufReader::new(file);
+                let mut contents = Vec::new();
+                buf_reader.read_to_end(&mut contents)?;
+                let mailboxes_lck = mailboxes.lock().unwrap();
+                let index = mailboxes_lck[&source_mailbox_hash].index.lock().unwrap();
+                let format = prefer_mbox_type.unwrap_or_default();
+                let mut messages = Vec::with_capacity(env_hashes.len());
+                for env_hash in env_hashes.iter() {
+                    if let Some(&(offset, length)) = index.get(&env_hash) {
+                        if offset + length > contents.len() {
+                            return Err(MeliError::new(format!(
+                                "{} was modified by another program; refresh the mailbox and try again.",
+                                source_path.display()
+                            )));
+                        }
+                        messages.push(message_for_copy(&contents, offset, length, format));
+                    }
+                }
+                messages
+            };
+            if messages.is_empty() {
+                return Ok(());
+            }
+            append_messages(
+                &destination_path,
+                &messages
+                    .iter()
+                    .map(|(m, flags)| (m.as_slice(), *flags))
+                    .collect::<Vec<_>>(),
+                prefer_mbox_type,
+            )?;
+            sync_mailbox(
+                destination_mailbox_hash,
+                &mailboxes,
+                &mailbox_index,
+                prefer_mbox_type,
+                account_hash,
+                &sender,
+            )?;
+            if move_ {
+                let mut removed = vec![];
+                rewrite_mailbox(source_mailbox_hash, &mailboxes, |env_hash, _| {
+                    Ok(if env_hashes.iter().any(|h| h == env_hash) {
+                        removed.push(env_hash);
+                        Rewrite::Remove
+                    } else {
+                        Rewrite::Keep
+                    })
+                })?;
+                let mut mailbox_index_lck = mailbox_index.lock().unwrap();
+                for env_hash in removed {
+                    mailbox_index_lck.remove(&env_hash);
+                    (sender)(
+                        account_hash,
+                        BackendEvent::Refresh(RefreshEvent {
+                            account_hash,
+                            mailbox_hash: source_mailbox_hash,
+                            kind: RefreshEventKind::Remove(env_hash),
+                        }),
+                    );
+                }
+            }
+            Ok(())
+        }))
     }
 
     fn set_flags(
         &mut self,
-        _env_hashes: EnvelopeHashBatch,
-        _mailbox_hash: MailboxHash,
-        _flags: SmallVec<[(std::result::Result<Flag, String>, bool); 8]>,
+        env_hashes: EnvelopeHashBatch,
+        mailbox_hash: MailboxHash,
+        flags: SmallVec<[(std::result::Result<Flag, String>, bool); 8]>,
     ) -> ResultFuture<()> {
-        Err(MeliError::new("Unimplemented."))
+        if flags.iter().any(|(f, _)| f.is_err()) {
+            return Err(MeliError::new("mbox doesn't support tags."));
+        }
+        if !self.mailboxes.lock().unwrap().contains_key(&mailbox_hash) {
+            return Err(MeliError::new(format!(
+                "Mailbox with hash {} not found.",
+                mailbox_hash
+            )));
+        }
+        let mailboxes = self.mailboxes.clone();
+        let account_hash = self.account_hash();
+        let sender = self.event_consumer.clone();
+        Ok(Box::pin(async move {
+            let mut updated = vec![];
+            rewrite_mailbox(mailbox_hash, &mailboxes, |env_hash, message| {
+                if !env_hashes.iter().any(|h| h == env_hash) {
+                    return Ok(Rewrite::Keep);
+                }
+                let mut new_flags = cclient_flags(message);
+                for (f, value) in flags.iter() {
+                    if let Ok(f) = f {
+                        new_flags.set(*f, *value);
+                    }
+                }
+                updated.push((env_hash, new_flags));
+                Ok(Rewrite::Replace(set_cclient_flags(message, new_flags)))
+            })?;
+            for (env_hash, new_flags) in updated {
+                (sender)(
+                    account_hash,
+                    BackendEvent::Refresh(RefreshEvent {
+                        account_hash,
+                        mailbox_hash,
+                        kind: RefreshEventKind::NewFlags(env_hash, (new_flags, vec![])),
+                    }),
+                );
+            }
+            Ok(())
+        }))
     }
 
     fn delete_messages(
         &mut self,
-        _env_hashes: EnvelopeHashBatch,
-        _mailbox_hash: MailboxHash,
+        env_hashes: EnvelopeHashBatch,
+        mailbox_hash: MailboxHash,
     ) -> ResultFuture<()> {
-        Err(MeliError::new("Unimplemented."))
+        if !self.mailboxes.lock().unwrap().contains_key(&mailbox_hash) {
+            return Err(MeliError::new(format!(
+                "Mailbox with hash {} not found.",
+                mailbox_hash
+            )));
+        }
+        let mailboxes = self.mailboxes.clone();
+        let mailbox_index = self.mailbox_index.clone();
+        let account_hash = self.account_hash();
+        let sender = self.event_consumer.clone();
+        Ok(Box::pin(async move {
+            let mut removed = vec![];
+            rewrite_mailbox(mailbox_hash, &mailboxes, |env_hash, _| {
+                Ok(if env_hashes.iter().any(|h| h == env_hash) {
+                    removed.push(env_hash);
+                    Rewrite::Remove
+                } else {
+                    Rewrite::Keep
+                })
+            })?;
+            let mut mailbox_index_lck = mailbox_index.lock().unwrap();
+            for env_hash in removed {
+                mailbox_index_lck.remove(&env_hash);
+                (sender)(
+                    account_hash,
+                    BackendEvent::Refresh(RefreshEvent {
+                        account_hash,
+                        mailbox_hash,
+                        kind: RefreshEventKind::Remove(env_hash),
+                    }),
+                );
+            }
+            Ok(())
+        }))
     }
 
     fn save(
         &self,
-        _bytes: Vec<u8>,
-        _mailbox_hash: MailboxHash,
-        _flags: Option<Flag>,
+        bytes: Vec<u8>,
+        mailbox_hash: MailboxHash,
+        flags: Option<Flag>,
     ) -> ResultFuture<()> {
-        Err(MeliError::new("Unimplemented."))
+        let path = match self.mailboxes.lock().unwrap().get(&mailbox_hash) {
+            Some(f) => f.fs_path.clone(),
+            None => {
+                return Err(MeliError::new(format!(
+                    "Mailbox with hash {} not found.",
+                    mailbox_hash
+                )))
+            }
+        };
+        let mailboxes = self.mailboxes.clone();
+        let mailbox_index = self.mailbox_index.clone();
+        let prefer_mbox_type = self.prefer_mbox_type;
+        let account_hash = self.account_hash();
+        let sender = self.event_consumer.clone();
+        Ok(Box::pin(async move {
+            append_messages(
+                &path,
+                &[(bytes.as_slice(), flags.unwrap_or_default())],
+                prefer_mbox_type,
+            )?;
+            sync_mailbox(
+                mailbox_hash,
+                &mailboxes,
+                &mailbox_index,
+                prefer_mbox_type,
+                account_hash,
+                &sender,
+            )
+        }))
     }
 
     fn as_any(&self) -> &dyn Any {
@@ -1224,6 +1798,12 @@ macro_rules! get_conf_val {
 }
 
 impl MboxType {
+    fn account_hash(&self) -> AccountHash {
+        let mut hasher = DefaultHasher::new();
+        hasher.write(self.account_name.as_bytes());
+        hasher.finish()
+    }
+
     pub fn new(
         s: &AccountSettings,
         _is_subscribed: Box<dyn Fn(&str) -> bool>,
@@ -1396,3 +1976,194 @@ impl MboxType {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MESSAGES: [&[u8]; 3] = [
+        b"From: <a@example.com>\nSubject: first\nMessage-ID: <1@example.com>\n\nHello\nFrom here\n",
+        b"From: <b@example.com>\nSubject: second\nMessage-ID: <2@example.com>\n\n>From there\n",
+        b"From: <c@example.com>\nSubject: third\nMessage-ID: <3@example.com>\n\nBye\n",
+    ];
+
+    fn build_mbox(format: MboxFormat, flags: &[Flag]) -> Vec<u8> {
+        let mut ret = vec![];
+        for (i, (message, flags)) in MESSAGES.iter().zip(flags.iter()).enumerate() {
+            format
+                .append(
+                    &mut ret,
+                    message,
+                    None,
+                    Some(0),
+                    (*flags, vec![]),
+                    MboxMetadata::CClient,
+                    i == 0,
+                    false,
+                )
+                .unwrap();
+        }
+        ret
+    }
+
+    fn parse_mbox(
+        contents: &[u8],
+        format: MboxFormat,
+    ) -> (Vec<Envelope>, HashMap<EnvelopeHash, (Offset, Length)>) {
+        let index: Arc<Mutex<HashMap<EnvelopeHash, (Offset, Length)>>> = Default::default();
+        let (_, envelopes) = mbox_parse(index.clone(), contents, 0, Some(format)).unwrap();
+        let index = index.lock().unwrap().clone();
+        (envelopes, index)
+    }
+
+    #[test]
+    fn test_mbox_append_formats() {
+        let flags = [Flag::SEEN, Flag::FLAGGED | Flag::REPLIED, Flag::empty()];
+        for format in [
+            MboxFormat::MboxO,
+            MboxFormat::MboxRd,
+            MboxFormat::MboxCl,
+            MboxFormat::MboxCl2,
+        ] {
+            let contents = build_mbox(format, &flags);
+            let (envelopes, index) = parse_mbox(&contents, format);
+            assert_eq!(
+                envelopes.iter().map(|e| e.subject()).collect::<Vec<_>>(),
+                vec!["first", "second", "third"],
+                "{:?}",
+                format
+            );
+            for (env, flags) in envelopes.iter().zip(flags.iter()) {
+                let (offset, length) = index[&env.hash()];
+                assert_eq!(cclient_flags(&contents[offset..offset + length]), *flags);
+            }
+        }
+        let contents = build_mbox(MboxFormat::MboxO, &flags);
+        assert!(contents.find(b"\n>From here\n").is_some());
+        assert!(contents.find(b"\n>From there\n").is_some());
+        let contents = build_mbox(MboxFormat::MboxRd, &flags);
+        assert!(contents.find(b"\n>From here\n").is_some());
+        assert!(contents.find(b"\n>>From there\n").is_some());
+    }
+
+    #[test]
+    fn test_mbox_copy_messages() {
+        /* Copying every message to an empty file in the same format reproduces the file: quoted
+         * lines are not quoted again and the From_ lines are kept. */
+        let flags = [Flag::SEEN, Flag::FLAGGED | Flag::REPLIED, Flag::empty()];
+        for format in [
+            MboxFormat::MboxO,
+            MboxFormat::MboxRd,
+            MboxFormat::MboxCl,
+            MboxFormat::MboxCl2,
+        ] {
+            let contents = build_mbox(format, &flags);
+            let (envelopes, index) = parse_mbox(&contents, format);
+            let mut copy = vec![];
+            for (i, env) in envelopes.iter().enumerate() {
+                let (offset, length) = index[&env.hash()];
+                let (message, flags) = message_for_copy(&contents, offset, length, format);
+                append_message(&mut copy, &message, flags, format, i == 0, false).unwrap();
+            }
+            assert_eq!(
+                String::from_utf8_lossy(&copy),
+                String::from_utf8_lossy(&contents),
+                "{:?}",
+                format
+            );
+        }
+    }
+
+    #[test]
+    fn test_mbox_set_cclient_flags() {
+        let message: &[u8] =
+            b"From: <a@example.com>\nStatus: O\nX-Status: F\n  continued\nSubject: a\n\nStatus: body\n";
+        let new = set_cclient_flags(message, Flag::SEEN | Flag::REPLIED);
+        assert_eq!(
+            new.as_slice(),
+            &b"From: <a@example.com>\nSubject: a\nStatus: RO\nX-Status: A\n\nStatus: body\n"[..]
+        );
+        assert_eq!(cclient_flags(&new), Flag::SEEN | Flag::REPLIED);
+        let new = set_cclient_flags(&new, Flag::empty());
+        assert_eq!(
+            new.as_slice(),
+            &b"From: <a@example.com>\nSubject: a\nStatus: O\n\nStatus: body\n"[..]
+        );
+        assert_eq!(cclient_flags(&new), Flag::empty());
+    }
+
+    #[test]
+    fn test_mbox_rewrite_messages() {
+        for format in [MboxFormat::MboxRd, MboxFormat::MboxCl2] {
+            let contents = build_mbox(format, &[Flag::empty(); 3]);
+            let (envelopes, index) = parse_mbox(&contents, format);
+            let (first, second, third) = (
+                envelopes[0].hash(),
+                envelopes[1].hash(),
+                envelopes[2].hash(),
+            );
+            let (new_contents, new_index) = rewrite_messages(&contents, &index, |h, message| {
+                Ok(if h == first {
+                    Rewrite::Replace(set_cclient_flags(message, Flag::SEEN | Flag::FLAGGED))
+                } else if h == second {
+                    Rewrite::Remove
+                } else {
+                    Rewrite::Keep
+                })
+            })
+            .unwrap();
+            assert_eq!(new_index.len(), 2);
+            assert!(!new_index.contains_key(&second));
+            let (offset, length) = new_index[&first];
+            assert_eq!(
+                cclient_flags(&new_contents[offset..offset + length]),
+                Flag::SEEN | Flag::FLAGGED
+            );
+            let (offset, length) = new_index[&third];
+            assert_eq!(
+                &new_contents[offset..offset + length],
+                &contents[index[&third].0..index[&third].0 + index[&third].1]
+            );
+
+            let (envelopes, _) = parse_mbox(&new_contents, format);
+            assert_eq!(
+                envelopes
+                    .iter()
+                    .map(|e| (e.subject().to_string(), e.flags()))
+                    .collect::<Vec<_>>(),
+                vec![
+                    ("first".to_string(), Flag::SEEN | Flag::FLAGGED),
+                    ("third".to_string(), Flag::empty())
+                ],
+                "{:?}",
+                format
+            );
+        }
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_mbox_append_during_rewrite() {
+        /* A writer waiting for the lock while the mailbox is rewritten must append to the new
+         * file and not to the one that was renamed away. */
+        let path = std::env::temp_dir().join(format!("meli-mbox-test-{}", std::process::id()));
+        let contents = build_mbox(MboxFormat::MboxCl2, &[Flag::empty(); 2]);
+        std::fs::write(&path, &contents).unwrap();
+        let file = open_locked(&path, std::fs::OpenOptions::new().read(true).write(true)).unwrap();
+        let writer = {
+            let path = path.clone();
+            std::thread::spawn(move || {
+                append_messages(&path, &[(MESSAGES[2], Flag::empty())], None).unwrap()
+            })
+        };
+        std::thread::sleep(std::time::Duration::from_millis(100));
+        let new_file = replace_file_contents(&path, &contents[..contents.len() / 2]).unwrap();
+        drop(file);
+        drop(new_file);
+        writer.join().unwrap();
+        let new_contents = std::fs::read(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert!(new_contents.starts_with(&contents[..contents.len() / 2]));
+        assert!(new_contents.find(b"Subject: third").is_some());
+    }
+}
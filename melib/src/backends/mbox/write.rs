@@ -33,22 +33,14 @@ impl MboxFormat {
         is_empty: bool,
         crlf: bool,
     ) -> Result<()> {
-        if tags.iter().any(|t| t.contains(' ')) {
-            return Err(MeliError::new("mbox tags/keywords can't contain spaces"));
-        }
-        let line_ending: &'static [u8] = if crlf { &b"\r\n"[..] } else { &b"\n"[..] };
-        if !is_empty {
-            writer.write_all(line_ending)?;
-            writer.write_all(line_ending)?;
-        }
-        writer.write_all(&b"From "[..])?;
+        let mut from_line = b"From ".to_vec();
         if let Some(from) = envelope_from {
-            writer.write_all(from.address_spec_raw())?;
+            from_line.extend_from_slice(from.address_spec_raw());
         } else {
-            writer.write_all(&b"MAILER-DAEMON"[..])?;
+            from_line.extend_from_slice(b"MAILER-DAEMON");
         }
-        writer.write_all(&b" "[..])?;
-        writer.write_all(
+        from_line.push(b' ');
+        from_line.extend_from_slice(
             crate::datetime::timestamp_to_string(
                 delivery_date.unwrap_or_else(|| crate::datetime::now()),
                 Some(crate::datetime::ASCTIME_FMT),
@@ -56,7 +48,40 @@ impl MboxFormat {
             )
             .trim()
             .as_bytes(),
-        )?;
+        );
+        self.append_with_from_line(
+            writer,
+            input,
+            &from_line,
+            (flags, tags),
+            metadata_format,
+            is_empty,
+            crlf,
+        )
+    }
+
+    /// Like [`MboxFormat::append`], but with the given From_ line (without its line ending),
+    /// such as the one the message had in another mbox file.
+    #[allow(clippy::too_many_arguments)]
+    pub fn append_with_from_line(
+        &self,
+        writer: &mut dyn std::io::Write,
+        input: &[u8],
+        from_line: &[u8],
+        (flags, tags): (Flag, Vec<&str>),
+        metadata_format: MboxMetadata,
+        is_empty: bool,
+        crlf: bool,
+    ) -> Result<()> {
+        if tags.iter().any(|t| t.contains(' ')) {
+            return Err(MeliError::new("mbox tags/keywords can't contain spaces"));
+        }
+        let line_ending: &'static [u8] = if crlf { &b"\r\n"[..] } else { &b"\n"[..] };
+        if !is_empty {
+            writer.write_all(line_ending)?;
+            writer.write_all(line_ending)?;
+        }
+        writer.write_all(from_line)?;
         writer.write_all(line_ending)?;
         let (mut headers, body) = parser::mail(input)?;
         headers.retain(|(header_name, _)| {
@@ -152,7 +177,38 @@ impl MboxFormat {
         };
 
         match self {
-            MboxFormat::MboxO | MboxFormat::MboxRd => Err(MeliError::new("Unimplemented.")),
+            MboxFormat::MboxO | MboxFormat::MboxRd => {
+                for (h, v) in headers {
+                    writer.write_all(h)?;
+                    writer.write_all(&b": "[..])?;
+                    write_header_val_fn(writer, v)?;
+                    writer.write_all(line_ending)?;
+                }
+                write_metadata_fn(writer)?;
+                writer.write_all(line_ending)?;
+
+                /* mboxo quotes body lines that begin with "From ", mboxrd also quotes lines that
+                 * are already quoted so that the quoting is reversible. */
+                let is_rd = matches!(self, MboxFormat::MboxRd);
+                for line in body.split_inclusive(|b| *b == b'\n') {
+                    let quote = if is_rd {
+                        line[line.iter().take_while(|b| **b == b'>').count()..]
+                            .starts_with(b"From ")
+                    } else {
+                        line.starts_with(b"From ")
+                    };
+                    if quote {
+                        writer.write_all(b">")?;
+                    }
+                    if let Some(line) = line.strip_suffix(b"\n") {
+                        writer.write_all(line.strip_suffix(b"\r").unwrap_or(line))?;
+                        writer.write_all(line_ending)?;
+                    } else {
+                        writer.write_all(line)?;
+                    }
+                }
+                Ok(())
+            }
             MboxFormat::MboxCl => {
                 let len = (body_len
                     + body
@@ -257,4 +313,32 @@ impl MboxFormat {
             }
         }
     }
+
+    /// Undo the quoting of body lines that begin with "From " that [`MboxFormat::append`] does
+    /// in this format, so that the message can be appended again, possibly in another file.
+    pub fn unquote(&self, message: &[u8]) -> Vec<u8> {
+        let body_start = [
+            message.find(b"\n\n").map(|p| p + 2),
+            message.find(b"\n\r\n").map(|p| p + 3),
+        ]
+        .iter()
+        .flatten()
+        .min()
+        .cloned()
+        .unwrap_or(message.len());
+        let mut ret = message[..body_start].to_vec();
+        for line in message[body_start..].split_inclusive(|b| *b == b'\n') {
+            let quoted = match self {
+                MboxFormat::MboxO | MboxFormat::MboxCl => line.starts_with(b">From "),
+                MboxFormat::MboxRd => {
+                    line.starts_with(b">")
+                        && line[line.iter().take_while(|b| **b == b'>').count()..]
+                            .starts_with(b"From ")
+                }
+                MboxFormat::MboxCl2 => false,
+            };
+            ret.extend_from_slice(if quoted { &line[1..] } else { line });
+        }
+        ret
+    }
 }
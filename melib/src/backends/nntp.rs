@@ -31,16 +31,22 @@ mod operations;
 pub use operations::*;
 mod connection;
 pub use connection::*;
+mod newsrc;
+pub use newsrc::*;
+mod cache;
+pub use cache::*;
 
 use crate::conf::AccountSettings;
 use crate::connections::timeout;
 use crate::email::*;
 use crate::error::{MeliError, Result, ResultIntoMeliError};
+use crate::shellexpand::ShellExpandTrait;
 use crate::{backends::*, Collection};
 use futures::lock::Mutex as FutureMutex;
 use futures::stream::Stream;
 use std::collections::{hash_map::DefaultHasher, BTreeSet, HashMap, HashSet};
 use std::hash::Hasher;
+use std::path::Path;
 use std::pin::Pin;
 use std::str::FromStr;
 use std::sync::{Arc, Mutex};
@@ -113,10 +119,12 @@ pub struct UIDStore {
     message_id_index: Arc<Mutex<HashMap<String, EnvelopeHash>>>,
     hash_index: Arc<Mutex<HashMap<EnvelopeHash, (UID, MailboxHash)>>>,
     uid_index: Arc<Mutex<HashMap<(MailboxHash, UID), EnvelopeHash>>>,
+    max_uids: Arc<Mutex<HashMap<MailboxHash, UID>>>,
 
     collection: Collection,
     mailboxes: Arc<FutureMutex<HashMap<MailboxHash, NntpMailbox>>>,
     is_online: Arc<Mutex<(Instant, Result<()>)>>,
+    newsrc: Arc<Mutex<Newsrc>>,
     event_consumer: BackendEventConsumer,
 }
 
@@ -135,12 +143,14 @@ impl UIDStore {
             message_id_index: Default::default(),
             hash_index: Default::default(),
             uid_index: Default::default(),
+            max_uids: Default::default(),
             mailboxes: Arc::new(FutureMutex::new(Default::default())),
             collection: Collection::new(),
             is_online: Arc::new(Mutex::new((
                 Instant::now(),
                 Err(MeliError::new("Account is uninitialised.")),
             ))),
+            newsrc: Arc::new(Mutex::new(Newsrc::new(None))),
         }
     }
 }
@@ -227,6 +237,8 @@ impl MailBackend for NntpType {
             uid_store: self.uid_store.clone(),
             connection: self.connection.clone(),
             high_low_total: None,
+            cache_handle: cache::cache_handle(&self.uid_store),
+            loaded_cache: false,
         };
         Ok(Box::pin(async_stream::try_stream! {
             {
@@ -234,6 +246,7 @@ impl MailBackend for NntpType {
                 f.exists.lock().unwrap().clear();
                 f.unseen.lock().unwrap().clear();
             };
+            state.uid_store.max_uids.lock().unwrap().remove(&state.mailbox_hash);
             loop {
                 if let Some(ret) = state.fetch_envs().await? {
                     yield ret;
@@ -248,82 +261,52 @@ impl MailBackend for NntpType {
         let uid_store = self.uid_store.clone();
         let connection = self.connection.clone();
         Ok(Box::pin(async move {
-            /* To get updates, either issue NEWNEWS if it's supported by the server, and fallback
-             * to OVER otherwise */
+            /* Articles are numbered in increasing order within a group, so to get updates ask
+             * for the overview of the articles after the last one we know of. */
             let mbox: NntpMailbox = uid_store.mailboxes.lock().await.get(&mailbox_hash).map(std::clone::Clone::clone).ok_or_else(|| MeliError::new(format!("Mailbox with hash {} not found in NNTP connection, this could possibly be a bug or it was deleted.", mailbox_hash)))?;
-            let latest_article: Option<crate::UnixTimestamp> =
-                mbox.latest_article.lock().unwrap().clone();
-            let (over_msgid_support, newnews_support): (bool, bool) = {
-                let caps = uid_store.capabilities.lock().unwrap();
-
-                (
-                    caps.iter().any(|c| c.eq_ignore_ascii_case("OVER MSGID")),
-                    caps.iter().any(|c| c.eq_ignore_ascii_case("NEWNEWS")),
-                )
-            };
+            let mut cache_handle = cache::cache_handle(&uid_store);
             let mut res = String::with_capacity(8 * 1024);
             let mut conn = timeout(Some(Duration::from_secs(60 * 16)), connection.lock()).await?;
-            if let Some(mut latest_article) = latest_article {
-                let timestamp = latest_article - 10 * 60;
-                let datetime_str =
-                    crate::datetime::timestamp_to_string(timestamp, Some("%Y%m%d %H%M%S"), true);
-
-                if newnews_support {
-                    conn.send_command(
-                        format!("NEWNEWS {} {}", &mbox.nntp_path, datetime_str).as_bytes(),
-                    )
-                    .await?;
-                    conn.read_response(&mut res, true, &["230 "]).await?;
-                    let message_ids = {
-                        let message_id_lck = uid_store.message_id_index.lock().unwrap();
-                        res.split_rn()
-                            .skip(1)
-                            .map(|s| s.trim())
-                            .filter(|msg_id| !message_id_lck.contains_key(*msg_id))
-                            .map(str::to_string)
-                            .collect::<Vec<String>>()
-                    };
-                    if message_ids.is_empty() || !over_msgid_support {
-                        return Ok(());
-                    }
-                    let mut env_hash_set: BTreeSet<EnvelopeHash> = Default::default();
-                    for msg_id in message_ids {
-                        conn.send_command(format!("OVER {}", msg_id).as_bytes())
-                            .await?;
-                        conn.read_response(&mut res, true, &["224 "]).await?;
-                        let mut message_id_lck = uid_store.message_id_index.lock().unwrap();
-                        let mut hash_index_lck = uid_store.hash_index.lock().unwrap();
-                        let mut uid_index_lck = uid_store.uid_index.lock().unwrap();
-                        for l in res.split_rn().skip(1) {
-                            let (_, (num, env)) = protocol_parser::over_article(l)?;
-                            env_hash_set.insert(env.hash());
-                            message_id_lck.insert(env.message_id_display().to_string(), env.hash());
-                            hash_index_lck.insert(env.hash(), (num, mailbox_hash));
-                            uid_index_lck.insert((mailbox_hash, num), env.hash());
-                            latest_article = std::cmp::max(latest_article, env.timestamp);
-                            (uid_store.event_consumer)(
-                                uid_store.account_hash,
-                                crate::backends::BackendEvent::Refresh(RefreshEvent {
-                                    mailbox_hash,
-                                    account_hash: uid_store.account_hash,
-                                    kind: RefreshEventKind::Create(Box::new(env)),
-                                }),
-                            );
-                        }
-                    }
-                    {
-                        let f = &uid_store.mailboxes.lock().await[&mailbox_hash];
-                        *f.latest_article.lock().unwrap() = Some(latest_article);
-                        f.exists
-                            .lock()
-                            .unwrap()
-                            .insert_existing_set(env_hash_set.clone());
-                        f.unseen.lock().unwrap().insert_existing_set(env_hash_set);
-                    }
-                    return Ok(());
+            conn.select_group(mailbox_hash, true, &mut res).await?;
+            let (_, low, high) = protocol_parser::group_response(&res).ok_or_else(|| {
+                MeliError::new(format!(
+                    "{} Could not select newsgroup {}: expected GROUP response but got: {}",
+                    &uid_store.account_name,
+                    mbox.nntp_path(),
+                    res
+                ))
+            })?;
+            let max_uid = uid_store
+                .max_uids
+                .lock()
+                .unwrap()
+                .get(&mailbox_hash)
+                .cloned()
+                .unwrap_or(0);
+            let start = std::cmp::max(low, max_uid + 1);
+            if high < start {
+                return Ok(());
+            }
+            conn.send_command(uid_store.over_command(start, high).as_bytes())
+                .await?;
+            conn.read_response(&mut res, true, command_to_replycodes("OVER"))
+                .await?;
+            if let Some(ref mut cache_handle) = cache_handle {
+                if let Err(err) = cache_handle.update_mailbox(mailbox_hash, high, low) {
+                    (uid_store.event_consumer)(uid_store.account_hash, err.into());
                 }
             }
-            //conn.select_group(mailbox_hash, false, &mut res).await?;
+            let envelopes = uid_store.insert_over_response(&mbox, &res, cache_handle.as_mut())?;
+            for env in envelopes {
+                (uid_store.event_consumer)(
+                    uid_store.account_hash,
+                    crate::backends::BackendEvent::Refresh(RefreshEvent {
+                        mailbox_hash,
+                        account_hash: uid_store.account_hash,
+                        kind: RefreshEventKind::Create(Box::new(env)),
+                    }),
+                );
+            }
             Ok(())
         }))
     }
@@ -332,7 +315,13 @@ impl MailBackend for NntpType {
         let uid_store = self.uid_store.clone();
         let connection = self.connection.clone();
         Ok(Box::pin(async move {
-            NntpType::nntp_mailboxes(&connection).await?;
+            if let Err(err) = NntpType::nntp_mailboxes(&connection).await {
+                /* Cached groups can still be read while offline. */
+                if !uid_store.offline_cache {
+                    return Err(err);
+                }
+                debug!("Could not get newsgroups from server: {}", err);
+            }
             let mailboxes_lck = uid_store.mailboxes.lock().await;
             let ret = mailboxes_lck
                 .iter()
@@ -405,11 +394,57 @@ impl MailBackend for NntpType {
 
     fn set_flags(
         &mut self,
-        _env_hashes: EnvelopeHashBatch,
-        _mailbox_hash: MailboxHash,
-        _flags: SmallVec<[(std::result::Result<Flag, String>, bool); 8]>,
+        env_hashes: EnvelopeHashBatch,
+        mailbox_hash: MailboxHash,
+        flags: SmallVec<[(std::result::Result<Flag, String>, bool); 8]>,
     ) -> ResultFuture<()> {
-        Err(MeliError::new("NNTP doesn't support flags."))
+        /* Servers don't keep any per-user state, so the only flag we can offer is Seen, which is
+         * kept in the newsrc file. */
+        let mut set_seen: Option<bool> = None;
+        for (f, value) in flags.iter() {
+            match f {
+                Ok(flag) if *flag == Flag::SEEN => set_seen = Some(*value),
+                _ => {
+                    return Err(MeliError::new("NNTP only supports the Seen flag.")
+                        .set_kind(ErrorKind::NotSupported));
+                }
+            }
+        }
+        let uid_store = self.uid_store.clone();
+        Ok(Box::pin(async move {
+            let set_seen = if let Some(set_seen) = set_seen {
+                set_seen
+            } else {
+                return Ok(());
+            };
+            let mailboxes_lck = uid_store.mailboxes.lock().await;
+            if !mailboxes_lck.contains_key(&mailbox_hash) {
+                return Err(MeliError::new(format!("Mailbox with hash {} not found in NNTP connection, this could possibly be a bug or it was deleted.", mailbox_hash)).set_kind(ErrorKind::NotFound));
+            }
+            let hash_index_lck = uid_store.hash_index.lock().unwrap();
+            let mut newsrc_lck = uid_store.newsrc.lock().unwrap();
+            for env_hash in env_hashes.iter() {
+                /* Articles are numbered per group, so use the group the article was fetched
+                 * from. */
+                let (uid, mailbox) = match hash_index_lck
+                    .get(&env_hash)
+                    .and_then(|(uid, h)| Some((*uid, mailboxes_lck.get(h)?)))
+                {
+                    Some(v) => v,
+                    None => continue,
+                };
+                let group = newsrc_lck.group_entry(mailbox.nntp_path(), mailbox.is_subscribed);
+                let mut unseen_lck = mailbox.unseen.lock().unwrap();
+                if set_seen {
+                    group.read.insert(uid);
+                    unseen_lck.remove(env_hash);
+                } else {
+                    group.read.remove(uid);
+                    unseen_lck.insert_new(env_hash);
+                }
+            }
+            newsrc_lck.save()
+        }))
     }
 
     fn delete_messages(
@@ -448,10 +483,24 @@ impl MailBackend for NntpType {
 
     fn set_mailbox_subscription(
         &mut self,
-        _mailbox_hash: MailboxHash,
-        _new_val: bool,
+        mailbox_hash: MailboxHash,
+        new_val: bool,
     ) -> ResultFuture<()> {
-        Err(MeliError::new("Unimplemented."))
+        let uid_store = self.uid_store.clone();
+        Ok(Box::pin(async move {
+            let mut mailboxes_lck = uid_store.mailboxes.lock().await;
+            let mailbox = mailboxes_lck.get_mut(&mailbox_hash).ok_or_else(|| MeliError::new(format!("Mailbox with hash {} not found in NNTP connection, this could possibly be a bug or it was deleted.", mailbox_hash)).set_kind(ErrorKind::NotFound))?;
+            if mailbox.is_subscribed == new_val {
+                return Ok(());
+            }
+            let mut newsrc_lck = uid_store.newsrc.lock().unwrap();
+            newsrc_lck
+                .group_entry(mailbox.nntp_path(), new_val)
+                .is_subscribed = new_val;
+            newsrc_lck.save()?;
+            mailbox.is_subscribed = new_val;
+            Ok(())
+        }))
     }
 
     fn rename_mailbox(
@@ -485,6 +534,24 @@ impl MailBackend for NntpType {
         _flags: Option<Flag>,
     ) -> ResultFuture<()> {
         let connection = self.connection.clone();
+        let newsgroups = crate::email::parser::headers::headers(&bytes)
+            .ok()
+            .and_then(|(_, headers)| {
+                headers
+                    .into_iter()
+                    .find(|(name, _)| name.eq_ignore_ascii_case(b"Newsgroups"))
+                    .map(|(_, value)| {
+                        crate::email::compose::newsgroups_list(&String::from_utf8_lossy(value))
+                    })
+            })
+            .unwrap_or_default();
+        if newsgroups.is_empty() {
+            return Err(MeliError::new(
+                "Articles posted to an NNTP server need a Newsgroups header. To reply to the poster by e-mail, use a send_mail setting other than server_submission.",
+            )
+            .set_summary("Message not sent.")
+            .set_kind(ErrorKind::ValueError));
+        }
         Ok(Box::pin(async move {
             match timeout(Some(Duration::from_secs(60 * 16)), connection.lock()).await {
                 Ok(mut conn) => {
@@ -497,6 +564,13 @@ impl MailBackend for NntpType {
                         Err(err) => return Err(err.clone()),
                     }
                     let mut res = String::with_capacity(8 * 1024);
+                    /* Check that every group of a cross-post exists and accepts posts, since
+                     * servers reject the whole article otherwise. */
+                    conn.send_command(format!("LIST ACTIVE {}", newsgroups.join(",")).as_bytes())
+                        .await?;
+                    conn.read_response(&mut res, true, command_to_replycodes("LIST"))
+                        .await?;
+                    check_newsgroups(&newsgroups, &res)?;
                     if let Some(mailbox_hash) = mailbox_hash {
                         conn.select_group(mailbox_hash, false, &mut res).await?;
                     }
@@ -570,20 +644,47 @@ impl NntpType {
                 deflate: get_conf_val!(s["use_deflate"], false)?,
             },
         };
+        #[cfg(feature = "sqlite3")]
+        let offline_cache = get_conf_val!(s["offline_cache"], true)?;
+        #[cfg(not(feature = "sqlite3"))]
+        let offline_cache = get_conf_val!(s["offline_cache"], false)?;
+        #[cfg(not(feature = "sqlite3"))]
+        if offline_cache {
+            return Err(MeliError::new(format!(
+                "({}) offline_cache is true but melib is not compiled with sqlite3",
+                s.name,
+            )));
+        }
         let account_hash = {
             let mut hasher = DefaultHasher::new();
             hasher.write(s.name.as_bytes());
             hasher.finish()
         };
         let account_name = Arc::new(s.name().to_string());
+        let newsrc_path = if let Some(path) = s.extra.get("newsrc_path") {
+            Path::new(path).expand()
+        } else {
+            xdg::BaseDirectories::with_profile("meli", s.name())
+                .map_err(|err| MeliError::new(err.to_string()))?
+                .place_data_file("newsrc")?
+        };
+        let mut newsrc = Newsrc::open(newsrc_path)?;
         let mut mailboxes = HashMap::default();
         for (k, _f) in s.mailboxes.iter() {
             let mailbox_hash = get_path_hash!(&k);
+            /* An existing newsrc entry takes precedence, so that subscription changes made with
+             * other newsreaders are respected. */
+            let is_subscribed = newsrc
+                .group(k)
+                .map(|g| g.is_subscribed)
+                .unwrap_or_else(|| is_subscribed(k));
+            newsrc.group_entry(k, is_subscribed);
             mailboxes.insert(
                 mailbox_hash,
                 NntpMailbox {
                     hash: mailbox_hash,
                     nntp_path: k.to_string(),
+                    is_subscribed,
                     high_watermark: Arc::new(Mutex::new(0)),
                     low_watermark: Arc::new(Mutex::new(0)),
                     latest_article: Arc::new(Mutex::new(None)),
@@ -599,8 +700,9 @@ impl NntpType {
             )));
         }
         let uid_store: Arc<UIDStore> = Arc::new(UIDStore {
-            offline_cache: false, //get_conf_val!(s["X_header_caching"], false)?,
+            offline_cache,
             mailboxes: Arc::new(FutureMutex::new(mailboxes)),
+            newsrc: Arc::new(Mutex::new(newsrc)),
             ..UIDStore::new(account_hash, account_name, event_consumer)
         });
         let connection = NntpConnection::new_connection(&server_conf, uid_store.clone());
@@ -716,6 +818,19 @@ impl NntpType {
             )));
         }
         get_conf_val!(s["danger_accept_invalid_certs"], false)?;
+        let _ = get_conf_val!(s["newsrc_path"]);
+        #[cfg(feature = "sqlite3")]
+        get_conf_val!(s["offline_cache"], true)?;
+        #[cfg(not(feature = "sqlite3"))]
+        {
+            let offline_cache = get_conf_val!(s["offline_cache"], false)?;
+            if offline_cache {
+                return Err(MeliError::new(format!(
+                    "({}) offline_cache is true but melib is not compiled with sqlite3",
+                    s.name,
+                )));
+            }
+        }
         let extra_keys = s
             .extra
             .keys()
@@ -742,11 +857,72 @@ impl NntpType {
     }
 }
 
+/// Checks the newsgroups of an article against the server's `LIST ACTIVE` response, whose lines
+/// are of the form `group high low status`. Groups with status `n` don't accept posts, and groups
+/// with status `x` don't accept posts from readers.
+pub fn check_newsgroups(newsgroups: &[String], list_active: &str) -> Result<()> {
+    let active = list_active
+        .split_rn()
+        .skip(1)
+        .filter_map(|l| {
+            let s = l.split_whitespace().collect::<SmallVec<[&str; 4]>>();
+            if s.len() == 4 {
+                Some((s[0], s[3]))
+            } else {
+                None
+            }
+        })
+        .collect::<HashMap<&str, &str>>();
+    for group in newsgroups {
+        match active.get(group.as_str()) {
+            None => {
+                return Err(MeliError::new(format!(
+                    "Newsgroup `{}` does not exist on the server.",
+                    group
+                ))
+                .set_summary("Message not sent.")
+                .set_kind(ErrorKind::NotFound));
+            }
+            Some(status) if status.starts_with('n') || status.starts_with('x') => {
+                return Err(MeliError::new(format!(
+                    "The server does not allow posting to `{}`.",
+                    group
+                ))
+                .set_summary("Message not sent.")
+                .set_kind(ErrorKind::PermissionDenied));
+            }
+            Some(_) => {}
+        }
+    }
+    Ok(())
+}
+
+#[test]
+fn test_nntp_check_newsgroups() {
+    let res = "215 list of newsgroups follows\r\nmisc.test 3002322 3000234 y\r\ncomp.risks 442001 441099 m\r\nalt.rfc-writers.recovery 4 1 n\r\n.\r\n";
+    let groups = |g: &[&str]| g.iter().map(|s| s.to_string()).collect::<Vec<String>>();
+    assert!(check_newsgroups(&groups(&["misc.test", "comp.risks"]), res).is_ok());
+    assert_eq!(
+        check_newsgroups(&groups(&["misc.test", "alt.rfc-writers.recovery"]), res)
+            .unwrap_err()
+            .kind,
+        ErrorKind::PermissionDenied
+    );
+    assert_eq!(
+        check_newsgroups(&groups(&["misc.test", "tx.natives.recovery"]), res)
+            .unwrap_err()
+            .kind,
+        ErrorKind::NotFound
+    );
+}
+
 struct FetchState {
     mailbox_hash: MailboxHash,
     connection: Arc<FutureMutex<NntpConnection>>,
     uid_store: Arc<UIDStore>,
     high_low_total: Option<(usize, usize, usize)>,
+    cache_handle: Option<Box<dyn NntpCache>>,
+    loaded_cache: bool,
 }
 
 impl FetchState {
@@ -756,49 +932,89 @@ impl FetchState {
             ref connection,
             ref uid_store,
             ref mut high_low_total,
+            ref mut cache_handle,
+            ref mut loaded_cache,
         } = self;
         let mailbox_hash = *mailbox_hash;
+        let mbox: NntpMailbox =
+            std::clone::Clone::clone(&uid_store.mailboxes.lock().await[&mailbox_hash]);
+        if let Some(ref mut cache_handle) = cache_handle {
+            if !*loaded_cache {
+                /* Return cached articles first, so that the group can be read without a
+                 * connection. */
+                *loaded_cache = true;
+                match cache_handle.envelopes(mailbox_hash).chain_err_summary(|| {
+                    format!("Could not load cached articles of {}", mbox.nntp_path())
+                }) {
+                    Ok(Some(envelopes)) if !envelopes.is_empty() => {
+                        return Ok(Some(uid_store.insert_envelopes(&mbox, envelopes)));
+                    }
+                    Ok(_) => {}
+                    Err(err) => {
+                        (uid_store.event_consumer)(uid_store.account_hash, err.into());
+                    }
+                }
+            }
+        }
         let mut res = String::with_capacity(8 * 1024);
         let mut conn = connection.lock().await;
         if high_low_total.is_none() {
-            conn.select_group(mailbox_hash, true, &mut res).await?;
-            /*
-             *   Parameters
-                     group     Name of newsgroup
-                     number    Estimated number of articles in the group
-                     low       Reported low water mark
-                     high      Reported high water mark
-            */
-            let s = res.split_whitespace().collect::<SmallVec<[&str; 6]>>();
-            let path = conn.uid_store.mailboxes.lock().await[&mailbox_hash]
-                .name()
-                .to_string();
-            if s.len() != 5 {
-                return Err(MeliError::new(format!(
+            if let Err(err) = conn.select_group(mailbox_hash, true, &mut res).await {
+                if uid_store
+                    .max_uids
+                    .lock()
+                    .unwrap()
+                    .contains_key(&mailbox_hash)
+                {
+                    debug!(
+                        "Could not select {}, showing cached articles only: {}",
+                        mbox.nntp_path(),
+                        err
+                    );
+                    return Ok(None);
+                }
+                return Err(err);
+            }
+            let (total, low, high) = protocol_parser::group_response(&res).ok_or_else(|| {
+                MeliError::new(format!(
                     "{} Could not select newsgroup {}: expected GROUP response but got: {}",
-                    &uid_store.account_name, path, res
-                )));
+                    &uid_store.account_name,
+                    mbox.nntp_path(),
+                    res
+                ))
+            })?;
+            let mut max_uid = uid_store
+                .max_uids
+                .lock()
+                .unwrap()
+                .get(&mailbox_hash)
+                .cloned()
+                .unwrap_or(0);
+            if let Some(ref mut cache_handle) = cache_handle {
+                if let Err(err) = uid_store.sync_cache(cache_handle, &mbox, high, low, &mut max_uid)
+                {
+                    (uid_store.event_consumer)(uid_store.account_hash, err.into());
+                }
             }
-            let total = usize::from_str(s[1]).unwrap_or(0);
-            let _low = usize::from_str(s[2]).unwrap_or(0);
-            let high = usize::from_str(s[3]).unwrap_or(0);
-            *high_low_total = Some((high, _low, total));
+            /* Only fetch the overview of articles that aren't cached yet. */
+            let low = std::cmp::max(low, max_uid + 1);
+            *high_low_total = Some((high, low, total));
             {
-                let f = &uid_store.mailboxes.lock().await[&mailbox_hash];
-                f.exists.lock().unwrap().set_not_yet_seen(total);
-                f.unseen.lock().unwrap().set_not_yet_seen(total);
+                let cached = mbox.exists.lock().unwrap().len();
+                let new = total.saturating_sub(cached);
+                mbox.exists.lock().unwrap().set_not_yet_seen(new);
+                mbox.unseen.lock().unwrap().set_not_yet_seen(new);
             };
         }
         let (high, low, _) = high_low_total.unwrap();
-        if high <= low {
+        if high == 0 || high < low {
             return Ok(None);
         }
         const CHUNK_SIZE: usize = 50000;
-        let new_low = std::cmp::max(low, high.saturating_sub(CHUNK_SIZE));
-        high_low_total.as_mut().unwrap().0 = new_low;
+        let new_low = std::cmp::max(low, high.saturating_sub(CHUNK_SIZE - 1));
+        high_low_total.as_mut().unwrap().0 = new_low.saturating_sub(1);
 
-        // FIXME: server might not implement OVER capability
-        conn.send_command(format!("OVER {}-{}", new_low, high).as_bytes())
+        conn.send_command(uid_store.over_command(new_low, high).as_bytes())
             .await?;
         conn.read_response(&mut res, true, command_to_replycodes("OVER"))
             .await
@@ -808,37 +1024,156 @@ impl FetchState {
                     &uid_store.account_name, res
                 )
             })?;
-        let mut ret = Vec::with_capacity(high - new_low);
-        //hash_index: Arc<Mutex<HashMap<EnvelopeHash, (UID, MailboxHash)>>>,
-        //uid_index: Arc<Mutex<HashMap<(MailboxHash, UID), EnvelopeHash>>>,
-        let mut latest_article: Option<crate::UnixTimestamp> = None;
+        Ok(Some(uid_store.insert_over_response(
+            &mbox,
+            &res,
+            cache_handle.as_mut(),
+        )?))
+    }
+}
+
+impl UIDStore {
+    /// `OVER` is the standardised form of the `XOVER` extension (RFC 2980), which older
+    /// servers that don't advertise capabilities still support.
+    fn over_command(&self, low: UID, high: UID) -> String {
+        let over_support = self
+            .capabilities
+            .lock()
+            .unwrap()
+            .iter()
+            .any(|c| c.eq_ignore_ascii_case("OVER"));
+        format!(
+            "{} {}-{}",
+            if over_support { "OVER" } else { "XOVER" },
+            low,
+            high
+        )
+    }
+
+    /// Brings the cached state of `mailbox` up to date with the water marks reported by the
+    /// server, and sets `max_uid` to the last article that doesn't have to be fetched again.
+    fn sync_cache(
+        &self,
+        cache_handle: &mut Box<dyn NntpCache>,
+        mailbox: &NntpMailbox,
+        high: UID,
+        low: UID,
+        max_uid: &mut UID,
+    ) -> Result<()> {
+        let mailbox_hash = mailbox.hash;
+        /* If the server has renumbered the group, none of the cached article numbers are valid
+         * anymore. */
+        let renumbered = high < *max_uid;
+        let expire_below = if renumbered { UID::MAX } else { low };
+        let mut expired = vec![];
         {
-            let mut message_id_lck = uid_store.message_id_index.lock().unwrap();
-            let mut hash_index_lck = uid_store.hash_index.lock().unwrap();
-            let mut uid_index_lck = uid_store.uid_index.lock().unwrap();
-            for l in res.split_rn().skip(1) {
-                let (_, (num, env)) = protocol_parser::over_article(l)?;
+            let mut hash_index_lck = self.hash_index.lock().unwrap();
+            let mut uid_index_lck = self.uid_index.lock().unwrap();
+            uid_index_lck.retain(|(h, uid), env_hash| {
+                if *h == mailbox_hash && *uid < expire_below {
+                    hash_index_lck.remove(env_hash);
+                    expired.push(*env_hash);
+                    false
+                } else {
+                    true
+                }
+            });
+        }
+        for env_hash in expired {
+            mailbox.exists.lock().unwrap().remove(env_hash);
+            mailbox.unseen.lock().unwrap().remove(env_hash);
+            (self.event_consumer)(
+                self.account_hash,
+                crate::backends::BackendEvent::Refresh(RefreshEvent {
+                    mailbox_hash,
+                    account_hash: self.account_hash,
+                    kind: RefreshEventKind::Remove(env_hash),
+                }),
+            );
+        }
+        if renumbered {
+            cache_handle.clear(mailbox_hash)?;
+            self.max_uids.lock().unwrap().insert(mailbox_hash, 0);
+            *max_uid = 0;
+        } else {
+            cache_handle.expire(mailbox_hash, low)?;
+        }
+        cache_handle.update_mailbox(mailbox_hash, high, low)
+    }
+
+    /// Parses an `OVER` response, saving the articles in the cache if there is one.
+    fn insert_over_response(
+        &self,
+        mailbox: &NntpMailbox,
+        res: &str,
+        cache_handle: Option<&mut Box<dyn NntpCache>>,
+    ) -> Result<Vec<Envelope>> {
+        let mut envelopes = Vec::with_capacity(res.len() / 256);
+        for l in res.split_rn().skip(1) {
+            let (_, (num, env)) = protocol_parser::over_article(l)?;
+            envelopes.push((num, env));
+        }
+        if let Some(cache_handle) = cache_handle {
+            if let Err(err) = cache_handle
+                .insert_envelopes(mailbox.hash, &envelopes)
+                .chain_err_summary(|| {
+                    format!(
+                        "Could not save envelopes in cache for mailbox {}",
+                        mailbox.nntp_path()
+                    )
+                })
+            {
+                (self.event_consumer)(self.account_hash, err.into());
+            }
+        }
+        Ok(self.insert_envelopes(mailbox, envelopes))
+    }
+
+    /// Adds fetched or cached articles to the indexes and sets their seen state from the newsrc.
+    fn insert_envelopes(
+        &self,
+        mailbox: &NntpMailbox,
+        envelopes: Vec<(UID, Envelope)>,
+    ) -> Vec<Envelope> {
+        let mailbox_hash = mailbox.hash;
+        let mut ret = Vec::with_capacity(envelopes.len());
+        let mut hash_set: BTreeSet<EnvelopeHash> = Default::default();
+        let mut unseen_hash_set: BTreeSet<EnvelopeHash> = Default::default();
+        let mut latest_article: Option<crate::UnixTimestamp> =
+            *mailbox.latest_article.lock().unwrap();
+        {
+            let mut message_id_lck = self.message_id_index.lock().unwrap();
+            let mut hash_index_lck = self.hash_index.lock().unwrap();
+            let mut uid_index_lck = self.uid_index.lock().unwrap();
+            let mut max_uids_lck = self.max_uids.lock().unwrap();
+            let max_uid = max_uids_lck.entry(mailbox_hash).or_default();
+            let newsrc_lck = self.newsrc.lock().unwrap();
+            for (num, mut env) in envelopes {
                 message_id_lck.insert(env.message_id_display().to_string(), env.hash());
                 hash_index_lck.insert(env.hash(), (num, mailbox_hash));
                 uid_index_lck.insert((mailbox_hash, num), env.hash());
-                if let Some(ref mut v) = latest_article {
-                    *v = std::cmp::max(*v, env.timestamp);
+                *max_uid = std::cmp::max(*max_uid, num);
+                latest_article = Some(std::cmp::max(
+                    latest_article.unwrap_or(env.timestamp),
+                    env.timestamp,
+                ));
+                hash_set.insert(env.hash());
+                if newsrc_lck.is_read(mailbox.nntp_path(), num) {
+                    env.set_seen();
                 } else {
-                    latest_article = Some(env.timestamp);
+                    env.set_flags(env.flags() & !Flag::SEEN);
+                    unseen_hash_set.insert(env.hash());
                 }
                 ret.push(env);
             }
         }
-        {
-            let hash_set: BTreeSet<EnvelopeHash> = ret.iter().map(|env| env.hash()).collect();
-            let f = &uid_store.mailboxes.lock().await[&mailbox_hash];
-            *f.latest_article.lock().unwrap() = latest_article;
-            f.exists
-                .lock()
-                .unwrap()
-                .insert_existing_set(hash_set.clone());
-            f.unseen.lock().unwrap().insert_existing_set(hash_set);
-        };
-        Ok(Some(ret))
+        *mailbox.latest_article.lock().unwrap() = latest_article;
+        mailbox.exists.lock().unwrap().insert_existing_set(hash_set);
+        mailbox
+            .unseen
+            .lock()
+            .unwrap()
+            .insert_existing_set(unseen_hash_set);
+        ret
     }
 }
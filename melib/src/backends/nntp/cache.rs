@@ -0,0 +1,280 @@
+/*
+ * meli - nntp module.
+ *
+ * Copyright 2020 Manos Pitsidianakis
+ *
+ * This file is part of meli.
+ *
+ * meli is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * meli is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with meli. If not, see <http://www.gnu.org/licenses/>.
+ */
+
+use super::*;
+use crate::{backends::MailboxHash, email::Envelope, error::*};
+
+/// Offline storage of overview data and article bodies, keyed by group and article number.
+pub trait NntpCache: Send + core::fmt::Debug {
+    fn reset(&mut self) -> Result<()>;
+
+    /// The high and low water marks of the group when it was last fetched.
+    fn mailbox_state(&mut self, mailbox_hash: MailboxHash) -> Result<Option<(UID, UID)>>;
+
+    fn update_mailbox(&mut self, mailbox_hash: MailboxHash, high: UID, low: UID) -> Result<()>;
+
+    fn clear(&mut self, mailbox_hash: MailboxHash) -> Result<()>;
+
+    fn envelopes(&mut self, mailbox_hash: MailboxHash) -> Result<Option<Vec<(UID, Envelope)>>>;
+
+    fn insert_envelopes(
+        &mut self,
+        mailbox_hash: MailboxHash,
+        envelopes: &[(UID, Envelope)],
+    ) -> Result<()>;
+
+    /// Removes articles numbered below `low`, which have expired from the server.
+    fn expire(&mut self, mailbox_hash: MailboxHash, low: UID) -> Result<()>;
+
+    fn rfc822(&mut self, mailbox_hash: MailboxHash, uid: UID) -> Result<Option<Vec<u8>>>;
+
+    fn insert_rfc822(&mut self, mailbox_hash: MailboxHash, uid: UID, bytes: &[u8]) -> Result<()>;
+}
+
+/// Opens the cache of the account if `offline_cache` is enabled. Errors are reported to the
+/// event consumer and the cache is not used.
+pub(super) fn cache_handle(uid_store: &Arc<UIDStore>) -> Option<Box<dyn NntpCache>> {
+    #[cfg(feature = "sqlite3")]
+    if uid_store.offline_cache {
+        return match Sqlite3Cache::get(uid_store.clone()).chain_err_summary(|| {
+            format!(
+                "Could not initialize cache for NNTP account {}",
+                uid_store.account_name
+            )
+        }) {
+            Ok(v) => Some(v),
+            Err(err) => {
+                (uid_store.event_consumer)(uid_store.account_hash, err.into());
+                None
+            }
+        };
+    }
+    #[cfg(not(feature = "sqlite3"))]
+    let _ = uid_store;
+    None
+}
+
+#[cfg(feature = "sqlite3")]
+pub use sqlite3_m::*;
+
+#[cfg(feature = "sqlite3")]
+mod sqlite3_m {
+    use super::*;
+    use crate::sqlite3::{self, DatabaseDescription};
+
+    type Sqlite3UID = i64;
+
+    #[derive(Debug)]
+    pub struct Sqlite3Cache {
+        connection: crate::sqlite3::Connection,
+        uid_store: Arc<UIDStore>,
+    }
+
+    const DB_DESCRIPTION: DatabaseDescription = DatabaseDescription {
+        name: "nntp_cache.db",
+        init_script: Some(
+            "PRAGMA foreign_keys = true;
+    PRAGMA encoding = 'UTF-8';
+
+    CREATE TABLE IF NOT EXISTS article (
+                    hash             INTEGER NOT NULL,
+                    mailbox_hash     INTEGER NOT NULL,
+                    uid              INTEGER NOT NULL,
+                    rfc822           BLOB,
+                    envelope         BLOB NOT NULL,
+                    PRIMARY KEY (mailbox_hash, uid),
+                    FOREIGN KEY (mailbox_hash) REFERENCES mailbox(mailbox_hash) ON DELETE CASCADE
+                   );
+    CREATE TABLE IF NOT EXISTS mailbox (
+                mailbox_hash     INTEGER UNIQUE,
+                high             INTEGER NOT NULL,
+                low              INTEGER NOT NULL,
+                PRIMARY KEY (mailbox_hash)
+               );
+    CREATE INDEX IF NOT EXISTS article_idx ON article(hash);",
+        ),
+        version: 1,
+    };
+
+    impl Sqlite3Cache {
+        pub fn get(uid_store: Arc<UIDStore>) -> Result<Box<dyn NntpCache>> {
+            Ok(Box::new(Self {
+                connection: sqlite3::open_or_create_db(
+                    &DB_DESCRIPTION,
+                    Some(uid_store.account_name.as_str()),
+                )?,
+                uid_store,
+            }))
+        }
+    }
+
+    impl NntpCache for Sqlite3Cache {
+        fn reset(&mut self) -> Result<()> {
+            sqlite3::reset_db(&DB_DESCRIPTION, Some(self.uid_store.account_name.as_str()))
+        }
+
+        fn mailbox_state(&mut self, mailbox_hash: MailboxHash) -> Result<Option<(UID, UID)>> {
+            let mut stmt = self
+                .connection
+                .prepare("SELECT high, low FROM mailbox WHERE mailbox_hash = ?1;")?;
+            let mut ret: Vec<(UID, UID)> = stmt
+                .query_map(sqlite3::params![mailbox_hash as i64], |row| {
+                    Ok((
+                        row.get(0).map(|u: Sqlite3UID| u as UID)?,
+                        row.get(1).map(|u: Sqlite3UID| u as UID)?,
+                    ))
+                })?
+                .collect::<std::result::Result<_, _>>()?;
+            Ok(ret.pop())
+        }
+
+        fn update_mailbox(&mut self, mailbox_hash: MailboxHash, high: UID, low: UID) -> Result<()> {
+            self.connection
+                .execute(
+                    "INSERT OR REPLACE INTO mailbox (mailbox_hash, high, low) VALUES (?1, ?2, ?3)",
+                    sqlite3::params![mailbox_hash as i64, high as Sqlite3UID, low as Sqlite3UID],
+                )
+                .chain_err_summary(|| {
+                    format!(
+                        "Could not update mailbox {} in cache of account {}",
+                        mailbox_hash, self.uid_store.account_name
+                    )
+                })?;
+            Ok(())
+        }
+
+        fn clear(&mut self, mailbox_hash: MailboxHash) -> Result<()> {
+            debug!("clear mailbox_hash {}", mailbox_hash);
+            self.connection
+                .execute_batch(&format!(
+                    "DELETE FROM article WHERE mailbox_hash = {0}; DELETE FROM mailbox WHERE mailbox_hash = {0};",
+                    mailbox_hash as i64
+                ))
+                .chain_err_summary(|| {
+                    format!(
+                        "Could not clear cache of mailbox {} account {}",
+                        mailbox_hash, self.uid_store.account_name
+                    )
+                })?;
+            Ok(())
+        }
+
+        fn envelopes(&mut self, mailbox_hash: MailboxHash) -> Result<Option<Vec<(UID, Envelope)>>> {
+            debug!("envelopes mailbox_hash {}", mailbox_hash);
+            if self.mailbox_state(mailbox_hash)?.is_none() {
+                return Ok(None);
+            }
+            let mut stmt = self
+                .connection
+                .prepare("SELECT uid, envelope FROM article WHERE mailbox_hash = ?1;")?;
+            let ret: Vec<(UID, Envelope)> = stmt
+                .query_map(sqlite3::params![mailbox_hash as i64], |row| {
+                    Ok((row.get(0).map(|i: Sqlite3UID| i as UID)?, row.get(1)?))
+                })?
+                .collect::<std::result::Result<_, _>>()?;
+            Ok(Some(ret))
+        }
+
+        fn insert_envelopes(
+            &mut self,
+            mailbox_hash: MailboxHash,
+            envelopes: &[(UID, Envelope)],
+        ) -> Result<()> {
+            debug!(
+                "insert_envelopes mailbox_hash {} len {}",
+                mailbox_hash,
+                envelopes.len()
+            );
+            if self.mailbox_state(mailbox_hash)?.is_none() {
+                return Err(MeliError::new("Mailbox is not in cache").set_kind(ErrorKind::Bug));
+            }
+            let Self {
+                ref mut connection,
+                ref uid_store,
+            } = self;
+            let tx = connection.transaction()?;
+            for (uid, envelope) in envelopes {
+                tx.execute(
+                    "INSERT OR REPLACE INTO article (hash, uid, mailbox_hash, envelope) VALUES (?1, ?2, ?3, ?4)",
+                    sqlite3::params![envelope.hash() as i64, *uid as Sqlite3UID, mailbox_hash as i64, envelope],
+                )
+                .chain_err_summary(|| {
+                    format!(
+                        "Could not insert envelope {} {} in cache of account {}",
+                        envelope.message_id(),
+                        envelope.hash(),
+                        uid_store.account_name
+                    )
+                })?;
+            }
+            tx.commit()?;
+            Ok(())
+        }
+
+        fn expire(&mut self, mailbox_hash: MailboxHash, low: UID) -> Result<()> {
+            self.connection
+                .execute(
+                    "DELETE FROM article WHERE mailbox_hash = ?1 AND uid < ?2;",
+                    sqlite3::params![mailbox_hash as i64, low as Sqlite3UID],
+                )
+                .chain_err_summary(|| {
+                    format!(
+                        "Could not remove expired articles of mailbox {} from cache of account {}",
+                        mailbox_hash, self.uid_store.account_name
+                    )
+                })?;
+            Ok(())
+        }
+
+        fn rfc822(&mut self, mailbox_hash: MailboxHash, uid: UID) -> Result<Option<Vec<u8>>> {
+            let mut stmt = self
+                .connection
+                .prepare("SELECT rfc822 FROM article WHERE mailbox_hash = ?1 AND uid = ?2;")?;
+            let mut ret: Vec<Option<Vec<u8>>> = stmt
+                .query_map(
+                    sqlite3::params![mailbox_hash as i64, uid as Sqlite3UID],
+                    |row| row.get(0),
+                )?
+                .collect::<std::result::Result<_, _>>()?;
+            Ok(ret.pop().flatten())
+        }
+
+        fn insert_rfc822(
+            &mut self,
+            mailbox_hash: MailboxHash,
+            uid: UID,
+            bytes: &[u8],
+        ) -> Result<()> {
+            self.connection
+                .execute(
+                    "UPDATE article SET rfc822 = ?1 WHERE mailbox_hash = ?2 AND uid = ?3;",
+                    sqlite3::params![bytes, mailbox_hash as i64, uid as Sqlite3UID],
+                )
+                .chain_err_summary(|| {
+                    format!(
+                        "Could not save article {} of mailbox {} in cache of account {}",
+                        uid, mailbox_hash, self.uid_store.account_name
+                    )
+                })?;
+            Ok(())
+        }
+    }
+}
@@ -559,7 +559,7 @@ impl NntpConnection {
 }
 
 pub fn command_to_replycodes(c: &str) -> &'static [&'static str] {
-    if c.starts_with("OVER") {
+    if c.starts_with("OVER") || c.starts_with("XOVER") {
         &["224 "]
     } else if c.starts_with("LIST") {
         &["215 "]
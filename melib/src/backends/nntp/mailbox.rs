@@ -29,6 +29,7 @@ use std::sync::{Arc, Mutex};
 pub struct NntpMailbox {
     pub(super) hash: MailboxHash,
     pub(super) nntp_path: String,
+    pub is_subscribed: bool,
 
     pub high_watermark: Arc<Mutex<usize>>,
     pub low_watermark: Arc<Mutex<usize>>,
@@ -83,11 +84,12 @@ impl BackendMailbox for NntpMailbox {
     }
 
     fn is_subscribed(&self) -> bool {
-        true
+        self.is_subscribed
     }
 
-    fn set_is_subscribed(&mut self, _new_val: bool) -> Result<()> {
-        Err(MeliError::new("Cannot set subscription in NNTP."))
+    fn set_is_subscribed(&mut self, new_val: bool) -> Result<()> {
+        self.is_subscribed = new_val;
+        Ok(())
     }
 
     fn set_special_usage(&mut self, _new_val: SpecialUsageMailbox) -> Result<()> {
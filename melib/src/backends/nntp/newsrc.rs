@@ -0,0 +1,484 @@
+/*
+ * meli - nntp module.
+ *
+ * Copyright 2020 Manos Pitsidianakis
+ *
+ * This file is part of meli.
+ *
+ * meli is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * meli is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with meli. If not, see <http://www.gnu.org/licenses/>.
+ */
+
+//! Read state and group subscriptions stored in the `.newsrc` format shared by most newsreaders.
+//!
+//! Each line of a `.newsrc` file describes one newsgroup: its name, followed by `:` if the user is
+//! subscribed to it or `!` if not, followed by a comma separated list of read article numbers or
+//! ranges of article numbers:
+//!
+//! ```text
+//! comp.lang.rust: 1-1040,1042,1050-1093
+//! alt.test! 1-5
+//! ```
+
+use crate::error::{MeliError, Result};
+use std::fmt;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::time::SystemTime;
+
+/// A set of article numbers, kept as sorted and coalesced inclusive ranges.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct ArticleRanges(Vec<(usize, usize)>);
+
+impl ArticleRanges {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    pub fn contains(&self, num: usize) -> bool {
+        self.position(num).is_ok()
+    }
+
+    /// Returns `true` if `num` was not already in the set.
+    pub fn insert(&mut self, num: usize) -> bool {
+        let idx = match self.position(num) {
+            Ok(_) => return false,
+            Err(idx) => idx,
+        };
+        let joins_prev = idx > 0 && self.0[idx - 1].1 + 1 == num;
+        let joins_next = idx < self.0.len() && self.0[idx].0 == num + 1;
+        match (joins_prev, joins_next) {
+            (true, true) => {
+                self.0[idx - 1].1 = self.0[idx].1;
+                self.0.remove(idx);
+            }
+            (true, false) => self.0[idx - 1].1 = num,
+            (false, true) => self.0[idx].0 = num,
+            (false, false) => self.0.insert(idx, (num, num)),
+        }
+        true
+    }
+
+    /// Returns `true` if `num` was in the set.
+    pub fn remove(&mut self, num: usize) -> bool {
+        let idx = match self.position(num) {
+            Ok(idx) => idx,
+            Err(_) => return false,
+        };
+        let (start, end) = self.0[idx];
+        if start == end {
+            self.0.remove(idx);
+        } else if start == num {
+            self.0[idx].0 = num + 1;
+        } else if end == num {
+            self.0[idx].1 = num - 1;
+        } else {
+            self.0[idx].1 = num - 1;
+            self.0.insert(idx + 1, (num + 1, end));
+        }
+        true
+    }
+
+    /// The numbers that are in `self` or in `other`.
+    pub fn union(&self, other: &Self) -> Self {
+        let mut ret = self.clone();
+        for &(start, end) in &other.0 {
+            ret.insert_range(start, end);
+        }
+        ret
+    }
+
+    /// The numbers that are in `self` but not in `other`.
+    pub fn difference(&self, other: &Self) -> Self {
+        let mut ret = vec![];
+        for &(start, end) in &self.0 {
+            /* Start of the part of the range that is not yet known to be in `other`. */
+            let mut rest = Some(start);
+            for &(o_start, o_end) in &other.0 {
+                let start = match rest {
+                    Some(start) => start,
+                    None => break,
+                };
+                if o_end < start {
+                    continue;
+                }
+                if o_start > end {
+                    break;
+                }
+                if o_start > start {
+                    ret.push((start, o_start - 1));
+                }
+                rest = o_end.checked_add(1).filter(|n| *n <= end);
+            }
+            if let Some(start) = rest {
+                ret.push((start, end));
+            }
+        }
+        ArticleRanges(ret)
+    }
+
+    fn insert_range(&mut self, start: usize, end: usize) {
+        self.0.push((start, end));
+        self.0.sort_unstable();
+        let mut merged: Vec<(usize, usize)> = Vec::with_capacity(self.0.len());
+        for (start, end) in self.0.drain(..) {
+            match merged.last_mut() {
+                Some(last) if start <= last.1.saturating_add(1) => {
+                    last.1 = std::cmp::max(last.1, end);
+                }
+                _ => merged.push((start, end)),
+            }
+        }
+        self.0 = merged;
+    }
+
+    /// Binary search for the range containing `num`. On failure, returns the index where a range
+    /// starting with `num` would be inserted.
+    fn position(&self, num: usize) -> std::result::Result<usize, usize> {
+        self.0.binary_search_by(|&(start, end)| {
+            if end < num {
+                std::cmp::Ordering::Less
+            } else if start > num {
+                std::cmp::Ordering::Greater
+            } else {
+                std::cmp::Ordering::Equal
+            }
+        })
+    }
+}
+
+impl FromStr for ArticleRanges {
+    type Err = MeliError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let mut ret = ArticleRanges::new();
+        for part in s.split(',').map(str::trim).filter(|p| !p.is_empty()) {
+            let (start, end) = if let Some(dash) = part.find('-') {
+                (
+                    usize::from_str(part[..dash].trim()),
+                    usize::from_str(part[dash + 1..].trim()),
+                )
+            } else {
+                (usize::from_str(part), usize::from_str(part))
+            };
+            match (start, end) {
+                (Ok(start), Ok(end)) if start <= end => ret.insert_range(start, end),
+                /* Some newsreaders write reversed ranges for empty groups, e.g. "1-0". */
+                (Ok(_), Ok(_)) => {}
+                _ => {
+                    return Err(MeliError::new(format!(
+                        "Invalid article range `{}` in newsrc entry.",
+                        part
+                    )));
+                }
+            }
+        }
+        Ok(ret)
+    }
+}
+
+impl fmt::Display for ArticleRanges {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (i, (start, end)) in self.0.iter().enumerate() {
+            if i > 0 {
+                write!(f, ",")?;
+            }
+            if start == end {
+                write!(f, "{}", start)?;
+            } else {
+                write!(f, "{}-{}", start, end)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NewsrcGroup {
+    pub name: String,
+    pub is_subscribed: bool,
+    pub read: ArticleRanges,
+}
+
+/// The contents of a `.newsrc` file.
+///
+/// Lines that are not group entries (such as the `options` line some newsreaders write) are kept
+/// as they are and written back at the top of the file.
+#[derive(Debug, Default, Clone)]
+pub struct Newsrc {
+    pub path: Option<PathBuf>,
+    other_lines: Vec<String>,
+    groups: Vec<NewsrcGroup>,
+    /// The groups as they were in the file when it was last read or written, to tell changes
+    /// made since then apart from changes other programs made to the file.
+    base: Vec<NewsrcGroup>,
+    /// Modification time of the file when it was last read or written.
+    mtime: Option<SystemTime>,
+}
+
+impl Newsrc {
+    pub fn new(path: Option<PathBuf>) -> Self {
+        Newsrc {
+            path,
+            ..Default::default()
+        }
+    }
+
+    /// Reads the file at `path`. A missing file is treated as an empty one, since it will be
+    /// created on the first save.
+    pub fn open(path: PathBuf) -> Result<Self> {
+        let mut ret = match std::fs::read_to_string(&path) {
+            Ok(s) => Self::parse(&s).map_err(|err| {
+                MeliError::new(format!("Could not parse newsrc file {}", path.display()))
+                    .set_source(Some(std::sync::Arc::new(err)))
+            })?,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => Newsrc::default(),
+            Err(err) => {
+                return Err(MeliError::new(format!(
+                    "Could not read newsrc file {}",
+                    path.display()
+                ))
+                .set_source(Some(std::sync::Arc::new(err))));
+            }
+        };
+        ret.base = ret.groups.clone();
+        ret.mtime = modified(&path);
+        ret.path = Some(path);
+        Ok(ret)
+    }
+
+    pub fn parse(s: &str) -> Result<Self> {
+        let mut ret = Newsrc::default();
+        for line in s.lines() {
+            let trimmed = line.trim_end();
+            if trimmed.is_empty() {
+                continue;
+            }
+            let sep = match trimmed.find(&[':', '!'][..]) {
+                Some(sep) if !trimmed[..sep].contains(char::is_whitespace) => sep,
+                _ => {
+                    ret.other_lines.push(trimmed.to_string());
+                    continue;
+                }
+            };
+            ret.groups.push(NewsrcGroup {
+                name: trimmed[..sep].to_string(),
+                is_subscribed: trimmed.as_bytes()[sep] == b':',
+                read: ArticleRanges::from_str(&trimmed[sep + 1..])?,
+            });
+        }
+        Ok(ret)
+    }
+
+    pub fn group(&self, name: &str) -> Option<&NewsrcGroup> {
+        self.groups.iter().find(|g| g.name == name)
+    }
+
+    /// Returns the entry for `name`, appending a new one with the given subscription state if it
+    /// doesn't exist.
+    pub fn group_entry(&mut self, name: &str, is_subscribed: bool) -> &mut NewsrcGroup {
+        if let Some(idx) = self.groups.iter().position(|g| g.name == name) {
+            &mut self.groups[idx]
+        } else {
+            self.groups.push(NewsrcGroup {
+                name: name.to_string(),
+                is_subscribed,
+                read: ArticleRanges::new(),
+            });
+            self.groups.last_mut().unwrap()
+        }
+    }
+
+    pub fn is_read(&self, name: &str, num: usize) -> bool {
+        self.group(name)
+            .map(|g| g.read.contains(num))
+            .unwrap_or(false)
+    }
+
+    /// Apply the changes made since the file was last read or written on top of `theirs`, the
+    /// current contents of the file.
+    fn merge(&mut self, theirs: Newsrc) {
+        let mut groups = theirs.groups;
+        for ours in &self.groups {
+            let base = self.base.iter().find(|g| g.name == ours.name);
+            if base == Some(ours) {
+                continue;
+            }
+            let group = match groups.iter_mut().find(|g| g.name == ours.name) {
+                Some(group) => group,
+                /* New here, or removed by another program but changed here. */
+                None => {
+                    groups.push(ours.clone());
+                    continue;
+                }
+            };
+            match base {
+                Some(base) => {
+                    if base.is_subscribed != ours.is_subscribed {
+                        group.is_subscribed = ours.is_subscribed;
+                    }
+                    group.read = group
+                        .read
+                        .difference(&base.read.difference(&ours.read))
+                        .union(&ours.read.difference(&base.read));
+                }
+                None => {
+                    group.is_subscribed = ours.is_subscribed;
+                    group.read = group.read.union(&ours.read);
+                }
+            }
+        }
+        self.groups = groups;
+        self.other_lines = theirs.other_lines;
+    }
+
+    /// Writes the newsrc back to its path, if it has one.
+    ///
+    /// If another program changed the file since it was last read or written, the changes made
+    /// since are merged into its current contents instead of overwriting them. The new contents
+    /// are written to a temporary file in the same directory which is then renamed over the old
+    /// file, so that other newsreaders never see a partially written file.
+    pub fn save(&mut self) -> Result<()> {
+        let path = match self.path.clone() {
+            Some(p) => p,
+            None => return Ok(()),
+        };
+        if modified(&path) != self.mtime {
+            let theirs = Newsrc::open(path.clone())?;
+            self.merge(theirs);
+        }
+        let path = path.as_path();
+        let file_name = path
+            .file_name()
+            .map(|f| f.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        let tmp_path = path.with_file_name(format!(".{}.{}.tmp", file_name, std::process::id()));
+        let contents = self.to_string();
+        let write_tmp = |tmp_path: &Path| -> std::io::Result<()> {
+            let mut file = std::fs::OpenOptions::new()
+                .write(true)
+                .create(true)
+                .truncate(true)
+                .open(tmp_path)?;
+            if let Ok(metadata) = std::fs::metadata(path) {
+                file.set_permissions(metadata.permissions())?;
+            }
+            file.write_all(contents.as_bytes())?;
+            file.sync_all()?;
+            std::fs::rename(tmp_path, path)
+        };
+        if let Err(err) = write_tmp(&tmp_path) {
+            let _ = std::fs::remove_file(&tmp_path);
+            return Err(
+                MeliError::new(format!("Could not write newsrc file {}", path.display()))
+                    .set_source(Some(std::sync::Arc::new(err))),
+            );
+        }
+        self.base = self.groups.clone();
+        self.mtime = modified(path);
+        Ok(())
+    }
+}
+
+fn modified(path: &Path) -> Option<SystemTime> {
+    std::fs::metadata(path).and_then(|m| m.modified()).ok()
+}
+
+impl fmt::Display for Newsrc {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for l in &self.other_lines {
+            writeln!(f, "{}", l)?;
+        }
+        for g in &self.groups {
+            write!(f, "{}{}", g.name, if g.is_subscribed { ':' } else { '!' })?;
+            if !g.read.is_empty() {
+                write!(f, " {}", g.read)?;
+            }
+            writeln!(f)?;
+        }
+        Ok(())
+    }
+}
+
+#[test]
+fn test_nntp_article_ranges() {
+    let mut r = ArticleRanges::from_str("1-5, 7,9-12,10-15").unwrap();
+    assert_eq!(r.to_string(), "1-5,7,9-15");
+    assert!(r.contains(1) && r.contains(5) && r.contains(7) && r.contains(15));
+    assert!(!r.contains(0) && !r.contains(6) && !r.contains(8) && !r.contains(16));
+    assert!(r.insert(6));
+    assert!(!r.insert(6));
+    assert_eq!(r.to_string(), "1-7,9-15");
+    assert!(r.insert(8));
+    assert_eq!(r.to_string(), "1-15");
+    assert!(r.remove(10));
+    assert!(!r.remove(10));
+    assert_eq!(r.to_string(), "1-9,11-15");
+    assert!(r.remove(1) && r.remove(15));
+    assert_eq!(r.to_string(), "2-9,11-14");
+    assert!(r.insert(20));
+    assert_eq!(r.to_string(), "2-9,11-14,20");
+    assert!(r.remove(20));
+    assert_eq!(r.to_string(), "2-9,11-14");
+    let a = ArticleRanges::from_str("1-10,20-30,40").unwrap();
+    let b = ArticleRanges::from_str("3,8-22,30-45").unwrap();
+    assert_eq!(a.difference(&b).to_string(), "1-2,4-7,23-29");
+    assert_eq!(b.difference(&a).to_string(), "11-19,31-39,41-45");
+    assert_eq!(a.union(&b).to_string(), "1-45");
+    assert!(a.difference(&a).is_empty());
+    assert!(ArticleRanges::from_str("1-0").unwrap().is_empty());
+    assert!(ArticleRanges::from_str("1-a").is_err());
+}
+
+#[test]
+fn test_nntp_newsrc() {
+    let s = "options -n all\ncomp.lang.rust: 1-1040,1042\nalt.test! 1-5\nsci.math:\n";
+    let mut newsrc = Newsrc::parse(s).unwrap();
+    assert_eq!(newsrc.to_string(), s);
+    assert!(newsrc.is_read("comp.lang.rust", 1042));
+    assert!(!newsrc.is_read("comp.lang.rust", 1041));
+    assert!(!newsrc.group("alt.test").unwrap().is_subscribed);
+    assert!(newsrc.group("sci.math").unwrap().read.is_empty());
+    assert!(!newsrc.is_read("misc.test", 1));
+
+    newsrc.group_entry("comp.lang.rust", true).read.insert(1041);
+    newsrc.group_entry("alt.test", true).is_subscribed = true;
+    newsrc.group_entry("misc.test", true).read.insert(3);
+    assert_eq!(
+        newsrc.to_string(),
+        "options -n all\ncomp.lang.rust: 1-1042\nalt.test: 1-5\nsci.math:\nmisc.test: 3\n"
+    );
+}
+
+#[test]
+fn test_nntp_newsrc_merge() {
+    let mut ours = Newsrc::parse("comp.lang.rust: 1-10\nalt.test! 1-5\nsci.math: 1-3\n").unwrap();
+    ours.base = ours.groups.clone();
+    ours.group_entry("comp.lang.rust", true).read.insert(11);
+    ours.group_entry("comp.lang.rust", true).read.remove(2);
+    ours.group_entry("alt.test", true).is_subscribed = true;
+    ours.group_entry("misc.test", true).read.insert(3);
+    /* Meanwhile another newsreader marked other articles read, unsubscribed from a group and
+     * removed one. */
+    let theirs = Newsrc::parse("options -n\ncomp.lang.rust: 1-10,20\nalt.test! 1-6\n").unwrap();
+    ours.merge(theirs);
+    assert_eq!(
+        ours.to_string(),
+        "options -n\ncomp.lang.rust: 1,3-11,20\nalt.test: 1-6\nmisc.test: 3\n"
+    );
+}
@@ -58,6 +58,12 @@ impl BackendOp for NntpOp {
         let uid_store = self.uid_store.clone();
         let connection = self.connection.clone();
         Ok(Box::pin(async move {
+            let mut cache_handle = cache::cache_handle(&uid_store);
+            if let Some(ref mut cache_handle) = cache_handle {
+                if let Ok(Some(bytes)) = cache_handle.rfc822(mailbox_hash, uid) {
+                    return Ok(bytes);
+                }
+            }
             let mut res = String::with_capacity(8 * 1024);
             let mut conn = connection.lock().await;
             let path = uid_store.mailboxes.lock().await[&mailbox_hash]
@@ -82,8 +88,13 @@ impl BackendOp for NntpOp {
                 )));
             }
             let pos = res.find("\r\n").unwrap_or(0) + 2;
-
-            Ok(res.as_bytes()[pos..].to_vec())
+            let bytes = res.as_bytes()[pos..].to_vec();
+            if let Some(ref mut cache_handle) = cache_handle {
+                if let Err(err) = cache_handle.insert_rfc822(mailbox_hash, uid, &bytes) {
+                    (uid_store.event_consumer)(uid_store.account_hash, err.into());
+                }
+            }
+            Ok(bytes)
         }))
     }
 
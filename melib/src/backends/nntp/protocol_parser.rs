@@ -154,3 +154,37 @@ pub fn over_article(input: &str) -> IResult<&str, (UID, Envelope)> {
         }),
     ))
 }
+
+/// Parses a `211` response to `GROUP` into the estimated number of articles and the low and high
+/// water marks of the group:
+///
+/// ```text
+/// 211 number low high group
+/// ```
+pub fn group_response(input: &str) -> Option<(usize, usize, usize)> {
+    let s = input.split_whitespace().collect::<SmallVec<[&str; 6]>>();
+    if s.len() != 5 || s[0] != "211" {
+        return None;
+    }
+    Some((
+        usize::from_str(s[1]).ok()?,
+        usize::from_str(s[2]).ok()?,
+        usize::from_str(s[3]).ok()?,
+    ))
+}
+
+#[test]
+fn test_nntp_group_response() {
+    assert_eq!(
+        group_response("211 1234 3000234 3002322 misc.test\r\n"),
+        Some((1234, 3000234, 3002322))
+    );
+    assert_eq!(
+        group_response("211 0 4000 3999 example.empty\r\n"),
+        Some((0, 4000, 3999))
+    );
+    assert_eq!(
+        group_response("411 example.is.sob.bradner.or.barber is unknown\r\n"),
+        None
+    );
+}
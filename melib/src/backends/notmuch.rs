@@ -818,6 +818,8 @@ impl MailBackend for NotmuchDb {
             self.lib.clone(),
             false,
         )?;
+        let mut melib_query_s = String::new();
+        melib_query.query_to_string(&mut melib_query_s)?;
         let mailboxes = self.mailboxes.clone();
         Ok(Box::pin(async move {
             let mut ret = SmallVec::new();
@@ -833,7 +835,7 @@ impl MailBackend for NotmuchDb {
             } else {
                 String::new()
             };
-            melib_query.query_to_string(&mut query_s);
+            query_s.push_str(&melib_query_s);
             let query: Query = Query::new(&database, &query_s)?;
             let iter = query.search()?;
             for message in iter {
@@ -954,11 +956,13 @@ impl Drop for Query<'_> {
 }
 
 pub trait MelibQueryToNotmuchQuery {
-    fn query_to_string(&self, ret: &mut String);
+    /// Append the notmuch query of `self` to `ret`. Fails with `ErrorKind::NotSupported` if it has
+    /// terms that notmuch can't search for.
+    fn query_to_string(&self, ret: &mut String) -> Result<()>;
 }
 
 impl MelibQueryToNotmuchQuery for crate::search::Query {
-    fn query_to_string(&self, ret: &mut String) {
+    fn query_to_string(&self, ret: &mut String) -> Result<()> {
         use crate::search::Query::*;
         match self {
             Before(timestamp) => {
@@ -1003,7 +1007,32 @@ impl MelibQueryToNotmuchQuery for crate::search::Query {
                 }
                 ret.push_str("\"");
             }
-            InReplyTo(_s) | References(_s) | AllAddresses(_s) => {}
+            InReplyTo(_)
+            | References(_)
+            | AllAddresses(_)
+            | ListId(_)
+            | Header(_, _)
+            | Regex(_)
+            | Larger(_)
+            | Smaller(_) => {
+                /* Searching without these terms would give wrong results */
+                return Err(MeliError::new(format!(
+                    "notmuch can't search for the term {:?}",
+                    self
+                ))
+                .set_kind(crate::error::ErrorKind::NotSupported));
+            }
+            MessageId(s) => {
+                ret.push_str("id:\"");
+                for c in s.chars() {
+                    if c == '"' {
+                        ret.push_str("\\\"");
+                    } else {
+                        ret.push(c);
+                    }
+                }
+                ret.push('"');
+            }
             /* * * * */
             Body(s) => {
                 ret.push_str("body:\"");
@@ -1055,28 +1084,51 @@ impl MelibQueryToNotmuchQuery for crate::search::Query {
                     ret.pop();
                 }
             }
+            Tag(t) => {
+                ret.push_str("tag:\"");
+                for c in t.chars() {
+                    if c == '"' {
+                        ret.push_str("\\\"");
+                    } else {
+                        ret.push(c);
+                    }
+                }
+                ret.push('"');
+            }
             HasAttachment => {
                 ret.push_str("tag:attachment");
             }
+            Mailbox(s) => {
+                ret.push_str("folder:\"");
+                for c in s.chars() {
+                    if c == '"' {
+                        ret.push_str("\\\"");
+                    } else {
+                        ret.push(c);
+                    }
+                }
+                ret.push('"');
+            }
             And(q1, q2) => {
                 ret.push('(');
-                q1.query_to_string(ret);
+                q1.query_to_string(ret)?;
                 ret.push_str(") AND (");
-                q2.query_to_string(ret);
+                q2.query_to_string(ret)?;
                 ret.push(')');
             }
             Or(q1, q2) => {
                 ret.push('(');
-                q1.query_to_string(ret);
+                q1.query_to_string(ret)?;
                 ret.push_str(") OR (");
-                q2.query_to_string(ret);
+                q2.query_to_string(ret)?;
                 ret.push(')');
             }
             Not(q) => {
                 ret.push_str("(NOT (");
-                q.query_to_string(ret);
+                q.query_to_string(ret)?;
                 ret.push_str("))");
             }
         }
+        Ok(())
     }
 }
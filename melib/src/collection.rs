@@ -464,6 +464,81 @@ impl Collection {
                 .insert(mailbox_hash, Threads::default());
         }
     }
+
+    /// Replace the envelopes of `mailbox_hash` with `env_hashes` and rebuild its threads, leaving
+    /// every other mailbox untouched. This is meant for virtual mailboxes whose envelopes belong
+    /// to other mailboxes of the collection, such as saved searches.
+    ///
+    /// Hashes that are not in the collection are ignored. Returns whether the mailbox changed.
+    pub fn set_mailbox(
+        &self,
+        mailbox_hash: MailboxHash,
+        env_hashes: HashSet<EnvelopeHash>,
+    ) -> bool {
+        let mut ordered_hash_set = {
+            let envelopes_lck = self.envelopes.read().unwrap();
+            let mut mailboxes_lck = self.mailboxes.write().unwrap();
+            let env_hashes = env_hashes
+                .into_iter()
+                .filter(|h| envelopes_lck.contains_key(h))
+                .collect::<HashSet<EnvelopeHash>>();
+            if mailboxes_lck.get(&mailbox_hash) == Some(&env_hashes) {
+                return false;
+            }
+            let mut ordered_hash_set = env_hashes.iter().cloned().collect::<Vec<EnvelopeHash>>();
+            ordered_hash_set.sort_by(|a, b| {
+                envelopes_lck[a]
+                    .date()
+                    .partial_cmp(&envelopes_lck[b].date())
+                    .unwrap()
+            });
+            mailboxes_lck.insert(mailbox_hash, env_hashes);
+            ordered_hash_set
+        };
+        let mut threads = Threads::new(ordered_hash_set.len());
+        for h in ordered_hash_set.drain(..) {
+            threads.insert(&self.envelopes, h);
+        }
+        self.threads.write().unwrap().insert(mailbox_hash, threads);
+        true
+    }
+}
+
+#[test]
+fn test_collection_set_mailbox() {
+    let collection = Collection::new();
+    let envelopes = [
+        "From: a@example.com\nMessage-ID: <a@example.com>\nSubject: a\n\nbody\n",
+        "From: b@example.com\nMessage-ID: <b@example.com>\nIn-Reply-To: <a@example.com>\nSubject: Re: a\n\nbody\n",
+        "From: c@example.com\nMessage-ID: <c@example.com>\nSubject: c\n\nbody\n",
+    ]
+    .iter()
+    .map(|s| Envelope::from_bytes(s.as_bytes(), None).unwrap())
+    .map(|e| (e.hash(), e))
+    .collect::<HashMap<EnvelopeHash, Envelope>>();
+    let hashes = envelopes.keys().cloned().collect::<Vec<EnvelopeHash>>();
+    collection.new_mailbox(1);
+    collection.new_mailbox(2);
+    collection.merge(envelopes, 1, None);
+
+    let subset = hashes[..2]
+        .iter()
+        .cloned()
+        .collect::<HashSet<EnvelopeHash>>();
+    assert!(collection.set_mailbox(2, subset.clone()));
+    assert!(!collection.set_mailbox(2, subset.clone()));
+    assert_eq!(*collection.get_mailbox(2), subset);
+    assert_eq!(collection.get_threads(2).hash_set, subset);
+    assert_eq!(collection.get_mailbox(1).len(), 3);
+    assert_eq!(collection.get_threads(1).hash_set.len(), 3);
+
+    /* Unknown hashes are dropped */
+    let mut with_unknown = subset;
+    with_unknown.insert(0);
+    assert!(!collection.set_mailbox(2, with_unknown));
+    assert!(collection.set_mailbox(2, HashSet::default()));
+    assert!(collection.get_threads(2).hash_set.is_empty());
+    assert_eq!(collection.get_threads(1).hash_set.len(), 3);
 }
 
 pub struct RwRef<'g, K: std::cmp::Eq + std::hash::Hash, V> {
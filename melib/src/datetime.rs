@@ -437,10 +437,53 @@ where
         if ret.is_null() {
             return Ok(None);
         }
+        /* Let mktime() figure out whether daylight saving time is in effect */
+        new_tm.tm_isdst = -1;
         Ok(Some(mktime(&new_tm as *const _) as u64))
     }
 }
 
+/// Parses the time at which to do something later, such as sending a message.
+///
+/// Accepts local times `YYYY-MM-DD HH:MM[:SS]` (also with a `T` separator), `YYYY-MM-DD`,
+/// `HH:MM` (today, or tomorrow if that time has passed), RFC 822 dates and offsets from `now`
+/// in `m`inutes, `h`ours, `d`ays or `w`eeks such as `+30m` or `+2d`.
+pub fn parse_future_timestamp(s: &str, now: UnixTimestamp) -> Option<UnixTimestamp> {
+    let s = s.trim();
+    if let Some(offset) = s.strip_prefix('+') {
+        let unit: UnixTimestamp = match offset.chars().last()? {
+            'm' => 60,
+            'h' => 60 * 60,
+            'd' => 60 * 60 * 24,
+            'w' => 60 * 60 * 24 * 7,
+            _ => return None,
+        };
+        let n = offset[..offset.len() - 1].parse::<UnixTimestamp>().ok()?;
+        return now.checked_add(n.checked_mul(unit)?);
+    }
+    if s.len() == 5 && s.as_bytes()[2] == b':' {
+        let today = timestamp_to_string(now, Some("%Y-%m-%d"), true);
+        let ret = timestamp_from_string(format!("{} {}", today, s), "%Y-%m-%d %H:%M")
+            .ok()
+            .flatten()?;
+        return Some(if ret <= now { ret + 60 * 60 * 24 } else { ret });
+    }
+    for fmt in &[
+        "%Y-%m-%d %H:%M:%S",
+        "%Y-%m-%dT%H:%M:%S",
+        "%Y-%m-%d %H:%M",
+        "%Y-%m-%dT%H:%M",
+        RFC3339_FMT,
+    ] {
+        if let Ok(Some(timestamp)) = timestamp_from_string(s, fmt) {
+            return Some(timestamp);
+        }
+    }
+    rfc822_to_timestamp(s)
+        .ok()
+        .filter(|timestamp| *timestamp != 0)
+}
+
 pub fn now() -> UnixTimestamp {
     use std::mem::MaybeUninit;
     let mut tv = MaybeUninit::<libc::timeval>::uninit();
@@ -459,6 +502,31 @@ fn test_datetime_timestamp() {
     timestamp_to_string(0, None, false);
 }
 
+#[test]
+fn test_datetime_parse_future_timestamp() {
+    let now = 1_600_000_000;
+    assert_eq!(parse_future_timestamp("+30m", now), Some(now + 30 * 60));
+    assert_eq!(
+        parse_future_timestamp("+2d", now),
+        Some(now + 2 * 24 * 60 * 60)
+    );
+    assert_eq!(parse_future_timestamp("+2y", now), None);
+    assert_eq!(parse_future_timestamp("tomorrow", now), None);
+    let at = parse_future_timestamp("2030-01-02 10:30", now).unwrap();
+    assert_eq!(
+        timestamp_to_string(at, Some("%Y-%m-%d %H:%M"), true),
+        "2030-01-02 10:30"
+    );
+    assert_eq!(parse_future_timestamp("2030-01-02T10:30", now), Some(at));
+    let at = parse_future_timestamp("10:30", now).unwrap();
+    assert!(at > now && at <= now + 24 * 60 * 60);
+    assert_eq!(timestamp_to_string(at, Some("%H:%M"), true), "10:30");
+    assert_eq!(
+        parse_future_timestamp("Wed, 2 Jan 2030 10:30:00 +0000", now),
+        Some(1_893_580_200)
+    );
+}
+
 #[test]
 fn test_datetime_rfcs() {
     if unsafe { libc::setlocale(libc::LC_ALL, b"\0".as_ptr() as _) }.is_null() {
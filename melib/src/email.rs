@@ -93,6 +93,7 @@ pub mod address;
 pub mod attachment_types;
 pub mod attachments;
 pub mod compose;
+pub mod delivery_status;
 pub mod headers;
 pub mod list_management;
 pub mod mailto;
@@ -207,6 +207,7 @@ pub enum MultipartType {
     Encrypted,
     Mixed,
     Related,
+    Report,
     Signed,
 }
 
@@ -227,6 +228,7 @@ impl Display for MultipartType {
                 MultipartType::Encrypted => "multipart/encrypted",
                 MultipartType::Mixed => "multipart/mixed",
                 MultipartType::Related => "multipart/related",
+                MultipartType::Report => "multipart/report",
                 MultipartType::Signed => "multipart/signed",
             }
         )
@@ -247,6 +249,8 @@ impl From<&[u8]> for MultipartType {
             MultipartType::Signed
         } else if val.eq_ignore_ascii_case(b"related") {
             MultipartType::Related
+        } else if val.eq_ignore_ascii_case(b"report") {
+            MultipartType::Report
         } else {
             Default::default()
         }
@@ -337,6 +341,13 @@ impl PartialEq<&str> for ContentType {
                 },
                 "multipart/related",
             ) => true,
+            (
+                ContentType::Multipart {
+                    kind: MultipartType::Report,
+                    ..
+                },
+                "multipart/report",
+            ) => true,
             (
                 ContentType::Multipart {
                     kind: MultipartType::Signed,
@@ -630,6 +630,33 @@ impl Attachment {
             _ => false,
         }
     }
+    /// If this is a `format=flowed` text part, whether its `DelSp` parameter is `yes`.
+    pub fn format_flowed_delsp(&self) -> Option<bool> {
+        match self.content_type {
+            ContentType::Text { ref parameters, .. } => {
+                let param = |name: &[u8]| {
+                    parameters
+                        .iter()
+                        .find(|(n, _)| n.eq_ignore_ascii_case(name))
+                        .map(|(_, v)| v.as_slice())
+                };
+                if param(b"format")
+                    .map(|v| v.eq_ignore_ascii_case(b"flowed"))
+                    .unwrap_or(false)
+                {
+                    Some(
+                        param(b"delsp")
+                            .map(|v| v.eq_ignore_ascii_case(b"yes"))
+                            .unwrap_or(false),
+                    )
+                } else {
+                    None
+                }
+            }
+            _ => None,
+        }
+    }
+
     pub fn is_html(&self) -> bool {
         match self.content_type {
             ContentType::Text {
@@ -800,8 +827,124 @@ impl Attachment {
     }
 }
 
-pub fn interpret_format_flowed(_t: &str) -> String {
-    unimplemented!()
+/// A paragraph of a `format=flowed` text ([RFC3676](https://tools.ietf.org/html/rfc3676)):
+/// its lines joined at their soft line breaks, without quote marks and space-stuffing.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FlowedLine {
+    pub quote_depth: usize,
+    pub text: String,
+}
+
+/// Decode a `format=flowed` text into its paragraphs. `delsp` is the value of the `DelSp`
+/// parameter: if true, the space of each soft line break is deleted when lines are joined.
+pub fn decode_format_flowed(text: &str, delsp: bool) -> Vec<FlowedLine> {
+    let mut ret = vec![];
+    let mut paragraph: Option<FlowedLine> = None;
+    let text = text.strip_suffix('\n').unwrap_or(text);
+    for line in text.split('\n') {
+        let line = line.strip_suffix('\r').unwrap_or(line);
+        let quote_depth = line.bytes().take_while(|&b| b == b'>').count();
+        let mut line = &line[quote_depth..];
+        /* Remove space-stuffing [rfc3676#section-4.4] */
+        if line.starts_with(' ') {
+            line = &line[1..];
+        }
+        /* A signature separator is always a fixed line [rfc3676#section-4.3] */
+        let is_flowed = line.ends_with(' ') && line != "-- ";
+        if paragraph
+            .as_ref()
+            .map(|p| p.quote_depth != quote_depth || line == "-- ")
+            .unwrap_or(false)
+        {
+            /* Malformed text: a flowed line must be followed by a line of the same quote depth
+             * [rfc3676#section-4.5] */
+            ret.extend(paragraph.take());
+        }
+        if is_flowed && delsp {
+            line = &line[..line.len() - 1];
+        }
+        match paragraph {
+            Some(ref mut p) => p.text.push_str(line),
+            None => {
+                paragraph = Some(FlowedLine {
+                    quote_depth,
+                    text: line.to_string(),
+                })
+            }
+        }
+        if !is_flowed {
+            ret.extend(paragraph.take());
+        }
+    }
+    ret.extend(paragraph.take());
+    ret
+}
+
+/// Decode a `format=flowed` text into plain text where each line is a paragraph, prefixed by
+/// its quote marks.
+pub fn interpret_format_flowed(text: &str, delsp: bool) -> String {
+    let mut ret = String::with_capacity(text.len());
+    for line in decode_format_flowed(text, delsp) {
+        if line.quote_depth > 0 {
+            ret.push_str(&">".repeat(line.quote_depth));
+            if !line.text.is_empty() {
+                ret.push(' ');
+            }
+        }
+        ret.push_str(&line.text);
+        ret.push('\n');
+    }
+    ret
+}
+
+/// Encode plain text as `format=flowed` text with `DelSp=no`: lines longer than 78 columns are
+/// broken at spaces with soft line breaks, lines are space-stuffed where needed and trailing
+/// spaces are removed. Lines starting with `>` are taken as quoted.
+pub fn encode_format_flowed(text: &str) -> String {
+    const MAX_LINE_LENGTH: usize = 78;
+    let mut ret = String::with_capacity(text.len());
+    let text = text.strip_suffix('\n').unwrap_or(text);
+    for line in text.split('\n') {
+        let line = line.strip_suffix('\r').unwrap_or(line);
+        let quote_depth = line.bytes().take_while(|&b| b == b'>').count();
+        let quotes = &line[..quote_depth];
+        let mut line = &line[quote_depth..];
+        if quote_depth > 0 && line.starts_with(' ') {
+            line = &line[1..];
+        }
+        if line != "-- " {
+            line = line.trim_end_matches(' ');
+        }
+        let limit = MAX_LINE_LENGTH.saturating_sub(quote_depth + 1);
+        let mut chunks = vec![];
+        let mut rest = line;
+        while rest.len() > limit {
+            /* Break after the last space that fits, or else after the first one */
+            let mut fits = limit + 1;
+            while !rest.is_char_boundary(fits) {
+                fits -= 1;
+            }
+            let end = match rest[..fits].rfind(' ').filter(|&i| i > 0) {
+                Some(i) => i + 1,
+                None => match rest.find(' ') {
+                    Some(i) if i + 1 < rest.len() => i + 1,
+                    _ => break,
+                },
+            };
+            chunks.push(&rest[..end]);
+            rest = &rest[end..];
+        }
+        chunks.push(rest);
+        for chunk in chunks {
+            ret.push_str(quotes);
+            if quote_depth > 0 || chunk.starts_with(' ') || chunk.starts_with("From ") {
+                ret.push(' ');
+            }
+            ret.push_str(chunk);
+            ret.push('\n');
+        }
+    }
+    ret
 }
 
 type Filter<'a> = Box<dyn FnMut(&Attachment, &mut Vec<u8>) + 'a>;
@@ -914,3 +1057,73 @@ fn decode_helper<'a, 'b>(a: &'a Attachment, filter: &mut Option<Filter<'b>>) ->
 pub fn decode<'a, 'b>(a: &'a Attachment, mut filter: Option<Filter<'b>>) -> Vec<u8> {
     decode_helper(a, &mut filter)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_decode_format_flowed() {
+        let text = "Soft line \r\nbreaks are \r\njoined.\r\n \r\n From here, space-stuffing\r\n\
+                    >> Quoted \r\n>> twice.\r\n> Depth \r\n>>changes.\r\n-- \r\nSignature\r\n";
+        let line = |quote_depth, text: &str| FlowedLine {
+            quote_depth,
+            text: text.to_string(),
+        };
+        assert_eq!(
+            decode_format_flowed(text, false),
+            vec![
+                line(0, "Soft line breaks are joined."),
+                line(0, ""),
+                line(0, "From here, space-stuffing"),
+                line(2, "Quoted twice."),
+                line(1, "Depth "),
+                line(2, "changes."),
+                line(0, "-- "),
+                line(0, "Signature"),
+            ]
+        );
+        assert_eq!(
+            interpret_format_flowed("Deleted sp \r\naces are \r\njoined.\r\n", true),
+            "Deleted spaces arejoined.\n"
+        );
+        assert_eq!(
+            interpret_format_flowed(">> Quoted \n>> twice.\n>\n> Once.\n", false),
+            ">> Quoted twice.\n>\n> Once.\n"
+        );
+    }
+
+    #[test]
+    fn test_encode_format_flowed() {
+        let text = "A paragraph that is much longer than seventy-eight columns, so it has to be broken with soft line breaks.\n\
+                    \n\
+                    > A quoted paragraph that is much longer than seventy-eight columns, so it is broken too.\n\
+                    >> Quoted twice.\n\
+                    \x20 Indented line\n\
+                    From the start\n\
+                    Trailing spaces   \n\
+                    -- \n\
+                    Signature\n";
+        let encoded = encode_format_flowed(text);
+        assert!(encoded.lines().all(|l| l.len() <= 78));
+        assert!(encoded.contains("\n   Indented line\n"));
+        assert!(encoded.contains("\n From the start\n"));
+        assert!(encoded.contains("\n-- \n"));
+        assert_eq!(
+            interpret_format_flowed(&encoded, false),
+            text.replace("Trailing spaces   ", "Trailing spaces")
+        );
+
+        /* Long lines with multi-byte characters are not split inside a character */
+        for line in [
+            format!("a{}", "日".repeat(40)),
+            format!("{} {}", "x".repeat(10), "é".repeat(60)),
+        ]
+        .iter()
+        {
+            let text = format!("{}\n", line);
+            let encoded = encode_format_flowed(&text);
+            assert_eq!(interpret_format_flowed(&encoded, false), text);
+        }
+    }
+}
@@ -115,6 +115,22 @@ impl Draft {
     }
 
     pub fn new_reply(envelope: &Envelope, bytes: &[u8], reply_to_all: bool) -> Self {
+        Self::reply(envelope, bytes, reply_to_all, None)
+    }
+
+    /// A follow-up to the news article `envelope`, posted to the newsgroups its `Followup-To` or
+    /// `Newsgroups` headers ask for. Meant for articles of news accounts; e-mail that merely has
+    /// a `Newsgroups` header is answered with [`Draft::new_reply`].
+    pub fn new_followup(envelope: &Envelope, bytes: &[u8]) -> Self {
+        Self::reply(envelope, bytes, true, NewsFollowup::detect(envelope))
+    }
+
+    fn reply(
+        envelope: &Envelope,
+        bytes: &[u8],
+        reply_to_all: bool,
+        followup: Option<NewsFollowup>,
+    ) -> Self {
         let mut ret = Draft::default();
         ret.headers_mut().insert(
             HeaderName::new_unchecked("References"),
@@ -137,10 +153,13 @@ impl Draft {
             HeaderName::new_unchecked("In-Reply-To"),
             envelope.message_id_display().into(),
         );
+        let attribution = envelope.field_from_to_string();
         // "Mail-Followup-To/(To+Cc+(Mail-Reply-To/Reply-To/From)) for follow-up,
         // Mail-Reply-To/Reply-To/From for reply-to-author."
         // source: https://cr.yp.to/proto/replyto.html
-        if reply_to_all {
+        if let Some(NewsFollowup::Newsgroups(ref groups)) = followup {
+            ret.set_news_mode(Some(groups));
+        } else if reply_to_all && followup.is_none() {
             if let Some(reply_to) = envelope.other_headers().get("Mail-Followup-To") {
                 ret.headers_mut()
                     .insert(HeaderName::new_unchecked("To"), reply_to.to_string());
@@ -166,10 +185,12 @@ impl Draft {
                 envelope.field_from_to_string(),
             );
         }
-        ret.headers_mut().insert(
-            HeaderName::new_unchecked("Cc"),
-            envelope.field_cc_to_string(),
-        );
+        if followup.is_none() {
+            ret.headers_mut().insert(
+                HeaderName::new_unchecked("Cc"),
+                envelope.field_cc_to_string(),
+            );
+        }
         let body = envelope.body_bytes(bytes);
         ret.body = {
             let reply_body_bytes = decode_rec(&body, None);
@@ -178,7 +199,7 @@ impl Draft {
             let mut ret = format!(
                 "On {} {} wrote:\n",
                 envelope.date_as_str(),
-                &ret.headers()["To"]
+                ret.headers().get("To").unwrap_or(&attribution)
             );
             for l in lines {
                 ret.push('>');
@@ -192,6 +213,32 @@ impl Draft {
         ret
     }
 
+    /// Turns the draft into a news article posted to `newsgroups`, or into an e-mail if
+    /// `newsgroups` is `None`.
+    ///
+    /// News articles have a `Newsgroups` header instead of recipient addresses.
+    pub fn set_news_mode(&mut self, newsgroups: Option<&[String]>) -> &mut Self {
+        if let Some(newsgroups) = newsgroups {
+            for h in &["To", "Cc", "Bcc"] {
+                self.headers.remove(h);
+            }
+            self.set_header("Newsgroups", newsgroups.join(","));
+        } else {
+            self.headers.remove("Newsgroups");
+            self.headers.remove("Followup-To");
+            for h in &["To", "Cc", "Bcc"] {
+                if !self.headers.contains_key(h) {
+                    self.set_header(h, String::new());
+                }
+            }
+        }
+        self
+    }
+
+    pub fn is_news(&self) -> bool {
+        self.headers.contains_key("Newsgroups")
+    }
+
     pub fn headers_mut(&mut self) -> &mut HeaderMap {
         &mut self.headers
     }
@@ -297,6 +344,17 @@ fn build_multipart(ret: &mut String, kind: MultipartType, parts: Vec<AttachmentB
     if kind == MultipartType::Encrypted {
         ret.push_str(r#"; protocol="application/pgp-encrypted""#);
     }
+    if kind == MultipartType::Report {
+        /* rfc6522: the report type is the subtype of the second, machine-readable, part */
+        let report_type = parts.get(1).map(|p| p.content_type.to_string());
+        if let Some(subtype) = report_type
+            .as_ref()
+            .filter(|t| t.starts_with("message/"))
+            .map(|t| &t["message/".len()..])
+        {
+            ret.push_str(&format!("; report-type={}", subtype));
+        }
+    }
     ret.push_str("\r\n\r\n");
     /* rfc1341 */
     ret.push_str("This is a MIME formatted message with attachments. Use a MIME-compliant client to view it properly.\r\n");
@@ -357,6 +415,14 @@ fn print_attachment(ret: &mut String, a: AttachmentBuilder) {
                 ret.push_str("\r\n");
             }
         }
+        Other { ref tag, .. } if tag.eq_ignore_ascii_case(b"message/disposition-notification") => {
+            ret.push_str(&format!("Content-Type: {}\r\n", a.content_type));
+            ret.push_str("\r\n");
+            for line in String::from_utf8_lossy(a.raw()).lines() {
+                ret.push_str(line);
+                ret.push_str("\r\n");
+            }
+        }
         PGPSignature => {
             ret.push_str(&format!(
                 "Content-Type: {}; charset=\"utf-8\"; name=\"signature.asc\"\r\n",
@@ -409,11 +475,97 @@ fn print_attachment(ret: &mut String, a: AttachmentBuilder) {
     }
 }
 
+/// Where a follow-up to a news article should be posted, according to its `Followup-To` and
+/// `Newsgroups` headers (RFC 5536 section 3.2.6).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum NewsFollowup {
+    Newsgroups(Vec<String>),
+    /// The poster asked for replies by e-mail with `Followup-To: poster`.
+    Poster,
+}
+
+impl NewsFollowup {
+    /// Returns `None` if `envelope` is not a news article.
+    pub fn detect(envelope: &Envelope) -> Option<Self> {
+        let headers = envelope.other_headers();
+        let newsgroups = newsgroups_list(headers.get("Newsgroups")?);
+        if newsgroups.is_empty() {
+            return None;
+        }
+        match headers.get("Followup-To").map(|f| f.trim()) {
+            Some(f) if f.eq_ignore_ascii_case("poster") => Some(NewsFollowup::Poster),
+            Some(f) if !newsgroups_list(f).is_empty() => {
+                Some(NewsFollowup::Newsgroups(newsgroups_list(f)))
+            }
+            _ => Some(NewsFollowup::Newsgroups(newsgroups)),
+        }
+    }
+}
+
+/// Splits the value of a `Newsgroups` or `Followup-To` header into newsgroup names.
+pub fn newsgroups_list(value: &str) -> Vec<String> {
+    let mut ret: Vec<String> = Vec::new();
+    for group in value.split(',').map(|g| g.trim()).filter(|g| !g.is_empty()) {
+        if !ret.iter().any(|g| g == group) {
+            ret.push(group.to_string());
+        }
+    }
+    ret
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use std::str::FromStr;
 
+    #[test]
+    fn test_news_reply() {
+        let raw = "From: Poster <poster@example.com>\r\nNewsgroups: comp.lang.rust, comp.lang.c,comp.lang.rust\r\nSubject: test\r\nMessage-ID: <article@example.com>\r\nReferences: <parent@example.com>\r\n\r\nbody\r\n";
+        let mut envelope = Envelope::from_bytes(raw.as_bytes(), None).unwrap();
+        assert_eq!(
+            NewsFollowup::detect(&envelope),
+            Some(NewsFollowup::Newsgroups(vec![
+                "comp.lang.rust".to_string(),
+                "comp.lang.c".to_string()
+            ]))
+        );
+        /* E-mail replies never become news articles. */
+        assert!(!Draft::new_reply(&envelope, raw.as_bytes(), true).is_news());
+        let followup = Draft::new_followup(&envelope, raw.as_bytes());
+        assert!(followup.is_news());
+        assert_eq!(
+            &followup.headers()["Newsgroups"],
+            "comp.lang.rust,comp.lang.c"
+        );
+        assert_eq!(
+            &followup.headers()["References"],
+            "<parent@example.com> <article@example.com>"
+        );
+        assert!(!followup.headers().contains_key("To"));
+        assert!(!followup.headers().contains_key("Cc"));
+
+        let reply = Draft::new_reply(&envelope, raw.as_bytes(), false);
+        assert!(!reply.is_news());
+        assert_eq!(&reply.headers()["To"], "Poster <poster@example.com>");
+
+        envelope.other_headers_mut().insert(
+            HeaderName::new_unchecked("Followup-To"),
+            "comp.lang.rust".to_string(),
+        );
+        assert_eq!(
+            NewsFollowup::detect(&envelope),
+            Some(NewsFollowup::Newsgroups(vec!["comp.lang.rust".to_string()]))
+        );
+        envelope.other_headers_mut().insert(
+            HeaderName::new_unchecked("Followup-To"),
+            "poster".to_string(),
+        );
+        assert_eq!(NewsFollowup::detect(&envelope), Some(NewsFollowup::Poster));
+        let followup = Draft::new_followup(&envelope, raw.as_bytes());
+        assert!(!followup.is_news());
+        assert_eq!(&followup.headers()["To"], "Poster <poster@example.com>");
+    }
+
     #[test]
     fn test_new() {
         let mut default = Draft::default();
@@ -0,0 +1,840 @@
+/*
+ * meli - melib crate.
+ *
+ * Copyright 2020 Manos Pitsidianakis
+ *
+ * This file is part of meli.
+ *
+ * meli is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * meli is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with meli. If not, see <http://www.gnu.org/licenses/>.
+ */
+
+/*! Parsing of rfc3464 delivery status notifications and rfc8098 message disposition notifications,
+ * and composing of rfc8098 read receipts */
+use super::attachment_types::{ContentType, MultipartType, Text};
+use super::attachments::{decode, AttachmentBuilder};
+use super::compose::Draft;
+use super::parser;
+use super::{Address, Attachment, Envelope, StrBuild};
+use crate::error::{MeliError, Result};
+use std::fmt;
+
+/// The `Action` field of a per-recipient delivery status.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum DeliveryAction {
+    Failed,
+    Delayed,
+    Delivered,
+    Relayed,
+    Expanded,
+}
+
+impl DeliveryAction {
+    fn parse(value: &str) -> Option<Self> {
+        match value.to_ascii_lowercase().as_str() {
+            "failed" => Some(DeliveryAction::Failed),
+            "delayed" => Some(DeliveryAction::Delayed),
+            "delivered" => Some(DeliveryAction::Delivered),
+            "relayed" => Some(DeliveryAction::Relayed),
+            "expanded" => Some(DeliveryAction::Expanded),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for DeliveryAction {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{}",
+            match self {
+                DeliveryAction::Failed => "failed",
+                DeliveryAction::Delayed => "delayed",
+                DeliveryAction::Delivered => "delivered",
+                DeliveryAction::Relayed => "relayed",
+                DeliveryAction::Expanded => "expanded",
+            }
+        )
+    }
+}
+
+/// Delivery status of a single recipient.
+#[derive(Debug, PartialEq, Clone)]
+pub struct RecipientStatus {
+    pub final_recipient: String,
+    pub original_recipient: Option<String>,
+    pub action: DeliveryAction,
+    /// Status code such as `5.1.1`.
+    pub status: String,
+    pub remote_mta: Option<String>,
+    pub diagnostic_code: Option<String>,
+    pub last_attempt_date: Option<String>,
+    pub will_retry_until: Option<String>,
+}
+
+impl fmt::Display for RecipientStatus {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} {}", self.action, self.status)?;
+        if let Some(ref diagnostic_code) = self.diagnostic_code {
+            write!(f, " ({})", diagnostic_code)?;
+        }
+        Ok(())
+    }
+}
+
+/// Content of a `message/delivery-status` part.
+#[derive(Debug, Default, PartialEq, Clone)]
+pub struct DeliveryStatus {
+    pub original_envelope_id: Option<String>,
+    pub reporting_mta: Option<String>,
+    pub arrival_date: Option<String>,
+    pub recipients: Vec<RecipientStatus>,
+}
+
+impl DeliveryStatus {
+    pub fn parse(input: &[u8]) -> Result<Self> {
+        let mut groups = field_groups(input)?.into_iter();
+        let mut ret = DeliveryStatus::default();
+        for (name, value) in groups.next().unwrap_or_default() {
+            match name.as_str() {
+                "original-envelope-id" => {
+                    ret.original_envelope_id =
+                        Some(String::from_utf8_lossy(&xtext_decode(&value)).into_owned());
+                }
+                "reporting-mta" => ret.reporting_mta = Some(typed_value(value)),
+                "arrival-date" => ret.arrival_date = Some(value),
+                _ => {}
+            }
+        }
+        for group in groups {
+            let mut final_recipient = None;
+            let mut action = None;
+            let mut status = None;
+            let mut recipient = RecipientStatus {
+                final_recipient: String::new(),
+                original_recipient: None,
+                action: DeliveryAction::Failed,
+                status: String::new(),
+                remote_mta: None,
+                diagnostic_code: None,
+                last_attempt_date: None,
+                will_retry_until: None,
+            };
+            for (name, value) in group {
+                match name.as_str() {
+                    "final-recipient" => final_recipient = Some(typed_value(value)),
+                    "original-recipient" => recipient.original_recipient = Some(typed_value(value)),
+                    "action" => action = DeliveryAction::parse(&value),
+                    "status" => {
+                        /* The status code may be followed by a comment */
+                        status = value.split_whitespace().next().map(str::to_string)
+                    }
+                    "remote-mta" => recipient.remote_mta = Some(typed_value(value)),
+                    "diagnostic-code" => recipient.diagnostic_code = Some(value),
+                    "last-attempt-date" => recipient.last_attempt_date = Some(value),
+                    "will-retry-until" => recipient.will_retry_until = Some(value),
+                    _ => {}
+                }
+            }
+            match (final_recipient, action, status) {
+                (Some(final_recipient), Some(action), Some(status)) => {
+                    recipient.final_recipient = final_recipient;
+                    recipient.action = action;
+                    recipient.status = status;
+                    ret.recipients.push(recipient);
+                }
+                _ => {
+                    return Err(MeliError::new(
+                        "Delivery status recipient fields must contain Final-Recipient, Action and Status.",
+                    ));
+                }
+            }
+        }
+        if ret.recipients.is_empty() {
+            return Err(MeliError::new(
+                "Delivery status does not contain any recipients.",
+            ));
+        }
+        Ok(ret)
+    }
+}
+
+/// The disposition type of a message disposition notification.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum DispositionType {
+    Displayed,
+    Deleted,
+    Dispatched,
+    Processed,
+}
+
+impl fmt::Display for DispositionType {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{}",
+            match self {
+                DispositionType::Displayed => "displayed",
+                DispositionType::Deleted => "deleted",
+                DispositionType::Dispatched => "dispatched",
+                DispositionType::Processed => "processed",
+            }
+        )
+    }
+}
+
+/// The `Disposition` field, e.g. `manual-action/MDN-sent-manually; displayed`.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct Disposition {
+    pub automatic_action: bool,
+    pub automatic_sending: bool,
+    pub kind: DispositionType,
+}
+
+impl fmt::Display for Disposition {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{}/{}; {}",
+            if self.automatic_action {
+                "automatic-action"
+            } else {
+                "manual-action"
+            },
+            if self.automatic_sending {
+                "MDN-sent-automatically"
+            } else {
+                "MDN-sent-manually"
+            },
+            self.kind
+        )
+    }
+}
+
+impl Disposition {
+    fn parse(value: &str) -> Option<Self> {
+        let value = value.to_ascii_lowercase();
+        let (mode, kind) = value.split_at(value.find(';')?);
+        let mut mode = mode.trim().split('/');
+        let automatic_action = mode.next()? == "automatic-action";
+        let automatic_sending = mode.next()? == "mdn-sent-automatically";
+        /* Obsolete disposition modifiers may follow the type after a slash */
+        let kind = match kind[1..].split('/').next()?.trim() {
+            "displayed" => DispositionType::Displayed,
+            "deleted" => DispositionType::Deleted,
+            "dispatched" => DispositionType::Dispatched,
+            "processed" => DispositionType::Processed,
+            _ => return None,
+        };
+        Some(Disposition {
+            automatic_action,
+            automatic_sending,
+            kind,
+        })
+    }
+}
+
+/// Content of a `message/disposition-notification` part.
+#[derive(Debug, PartialEq, Clone)]
+pub struct DispositionNotification {
+    pub reporting_ua: Option<String>,
+    pub original_recipient: Option<String>,
+    pub final_recipient: String,
+    pub original_message_id: Option<String>,
+    pub disposition: Disposition,
+}
+
+impl DispositionNotification {
+    pub fn parse(input: &[u8]) -> Result<Self> {
+        let mut reporting_ua = None;
+        let mut original_recipient = None;
+        let mut final_recipient = None;
+        let mut original_message_id = None;
+        let mut disposition = None;
+        for (name, value) in field_groups(input)?.into_iter().flatten() {
+            match name.as_str() {
+                "reporting-ua" => reporting_ua = Some(value),
+                "original-recipient" => original_recipient = Some(typed_value(value)),
+                "final-recipient" => final_recipient = Some(typed_value(value)),
+                "original-message-id" => original_message_id = Some(value),
+                "disposition" => disposition = Disposition::parse(&value),
+                _ => {}
+            }
+        }
+        match (final_recipient, disposition) {
+            (Some(final_recipient), Some(disposition)) => Ok(DispositionNotification {
+                reporting_ua,
+                original_recipient,
+                final_recipient,
+                original_message_id,
+                disposition,
+            }),
+            _ => Err(MeliError::new(
+                "Disposition notification must contain Final-Recipient and Disposition fields.",
+            )),
+        }
+    }
+}
+
+impl fmt::Display for DispositionNotification {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if let Some(ref reporting_ua) = self.reporting_ua {
+            writeln!(f, "Reporting-UA: {}", reporting_ua)?;
+        }
+        if let Some(ref original_recipient) = self.original_recipient {
+            writeln!(f, "Original-Recipient: rfc822;{}", original_recipient)?;
+        }
+        writeln!(f, "Final-Recipient: rfc822;{}", self.final_recipient)?;
+        if let Some(ref original_message_id) = self.original_message_id {
+            writeln!(f, "Original-Message-ID: {}", original_message_id)?;
+        }
+        writeln!(f, "Disposition: {}", self.disposition)
+    }
+}
+
+/// A request for a read receipt, made with the `Disposition-Notification-To` header of a
+/// received message (rfc8098 section 2.1).
+#[derive(Debug, PartialEq, Clone)]
+pub struct MdnRequest {
+    pub notify_to: Vec<Address>,
+    /// The receipt must not be sent without asking the user, because it would go to more than
+    /// one address or to an address other than the `Return-Path` of the message.
+    pub needs_confirmation: bool,
+}
+
+impl MdnRequest {
+    /// Returns `None` if `envelope` does not ask for a read receipt, or is itself a report.
+    pub fn from_envelope(envelope: &Envelope) -> Option<Self> {
+        let headers = envelope.other_headers();
+        let value = headers.get("Disposition-Notification-To")?;
+        if DeliveryReport::is_report(envelope) {
+            return None;
+        }
+        let notify_to = parser::address::rfc2822address_list(value.as_bytes())
+            .ok()?
+            .1
+            .to_vec();
+        let first = notify_to.first()?.get_email();
+        let return_path = headers
+            .get("Return-Path")
+            .and_then(|value| parser::address::rfc2822address_list(value.as_bytes()).ok())
+            .and_then(|(_, list)| list.first().map(|addr| addr.get_email()));
+        let needs_confirmation = notify_to.len() > 1
+            || return_path
+                .map(|return_path| !return_path.eq_ignore_ascii_case(&first))
+                .unwrap_or(true);
+        Some(MdnRequest {
+            notify_to,
+            needs_confirmation,
+        })
+    }
+
+    /// Composes the `multipart/report` read receipt (rfc8098 section 3) for `envelope`, reporting
+    /// that it was displayed to `final_recipient`. `automatic_sending` is set when the receipt is
+    /// sent without asking the user.
+    pub fn to_draft(
+        &self,
+        envelope: &Envelope,
+        from: &str,
+        final_recipient: &str,
+        reporting_ua: &str,
+        automatic_sending: bool,
+    ) -> Draft {
+        let mut draft = Draft::default();
+        draft.headers_mut().remove("Cc");
+        draft.headers_mut().remove("Bcc");
+        draft
+            .set_header("From", from.to_string())
+            .set_header(
+                "To",
+                self.notify_to
+                    .iter()
+                    .map(|addr| addr.to_string())
+                    .collect::<Vec<String>>()
+                    .join(", "),
+            )
+            .set_header("Subject", format!("Read: {}", envelope.subject()));
+        let message_id = envelope.message_id_display().into_owned();
+        if !message_id.is_empty() {
+            draft
+                .set_header("In-Reply-To", message_id.clone())
+                .set_header("References", message_id.clone());
+        }
+        if automatic_sending {
+            draft.set_header("Auto-Submitted", "auto-replied".to_string());
+        }
+        let notification = DispositionNotification {
+            reporting_ua: Some(reporting_ua.to_string()),
+            original_recipient: envelope
+                .other_headers()
+                .get("Original-Recipient")
+                .map(|value| typed_value(value.to_string())),
+            final_recipient: final_recipient.to_string(),
+            original_message_id: Some(message_id).filter(|id| !id.is_empty()),
+            disposition: Disposition {
+                automatic_action: false,
+                automatic_sending,
+                kind: DispositionType::Displayed,
+            },
+        };
+        let mut text = AttachmentBuilder::default();
+        text.set_raw(
+            format!(
+                "This is a receipt for the message \"{}\" sent on {} to {}.\n\nIt only confirms that the message was displayed, not that it was read or understood.\n",
+                envelope.subject(),
+                envelope.date_as_str(),
+                final_recipient
+            )
+            .into_bytes(),
+        );
+        let mut report = AttachmentBuilder::default();
+        report
+            .set_content_type(ContentType::Other {
+                tag: b"message/disposition-notification".to_vec(),
+                name: None,
+            })
+            .set_raw(notification.to_string().into_bytes());
+        let mut multipart = AttachmentBuilder::default();
+        multipart.set_content_type(ContentType::Multipart {
+            boundary: vec![],
+            kind: MultipartType::Report,
+            parts: vec![text.build(), report.build()],
+        });
+        draft.attachments_mut().push(multipart);
+        draft
+    }
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum ReportKind {
+    DeliveryStatus(DeliveryStatus),
+    Disposition(DispositionNotification),
+}
+
+/// A `multipart/report` message about a message we sent.
+#[derive(Debug, PartialEq, Clone)]
+pub struct DeliveryReport {
+    /// Message-ID of the reported message, without angle brackets.
+    pub original_message_id: Option<String>,
+    pub kind: ReportKind,
+}
+
+impl DeliveryReport {
+    /// Checks the `Content-Type` header of `envelope` for a `multipart/report` of a type this
+    /// module can parse, without needing the message body.
+    pub fn is_report(envelope: &Envelope) -> bool {
+        envelope
+            .other_headers()
+            .get("Content-Type")
+            .map(|ct| {
+                let ct = ct.to_ascii_lowercase();
+                ct.contains("multipart/report")
+                    && (ct.contains("delivery-status") || ct.contains("disposition-notification"))
+            })
+            .unwrap_or(false)
+    }
+
+    /// The Message-ID (without angle brackets) that the `In-Reply-To` or `References` headers of
+    /// report `envelope` refer to, which is the reported message for most reports. Bounces of
+    /// some MTAs have neither header and must be parsed with `from_attachment` instead.
+    pub fn referenced_message_id(envelope: &Envelope) -> Option<String> {
+        envelope
+            .in_reply_to()
+            .map(|id| normalize_message_id(&String::from_utf8_lossy(id.raw())))
+            .filter(|id| !id.is_empty())
+    }
+
+    pub fn from_attachment(attachment: &Attachment) -> Option<Self> {
+        let mut kind = None;
+        let mut returned_message_id = None;
+        find_report_parts(attachment, &mut kind, &mut returned_message_id);
+        let kind = kind?;
+        let original_message_id = match kind {
+            ReportKind::Disposition(DispositionNotification {
+                ref original_message_id,
+                ..
+            }) => original_message_id.clone().or(returned_message_id),
+            ReportKind::DeliveryStatus(DeliveryStatus {
+                ref original_envelope_id,
+                ..
+            }) => returned_message_id.or_else(|| original_envelope_id.clone()),
+        }
+        .map(|id| normalize_message_id(&id))
+        .filter(|id| !id.is_empty());
+        Some(DeliveryReport {
+            original_message_id,
+            kind,
+        })
+    }
+
+    /// Status of each recipient, e.g. `("user@example.com", "failed 5.1.1 (smtp; 550 No such
+    /// user)")`.
+    pub fn recipient_statuses(&self) -> Vec<(String, String)> {
+        match self.kind {
+            ReportKind::DeliveryStatus(ref status) => status
+                .recipients
+                .iter()
+                .map(|r| (r.final_recipient.clone(), r.to_string()))
+                .collect(),
+            ReportKind::Disposition(ref mdn) => vec![(
+                mdn.final_recipient.clone(),
+                mdn.disposition.kind.to_string(),
+            )],
+        }
+    }
+}
+
+fn find_report_parts(
+    attachment: &Attachment,
+    kind: &mut Option<ReportKind>,
+    returned_message_id: &mut Option<String>,
+) {
+    match attachment.content_type() {
+        ContentType::Multipart { parts, .. } => {
+            for p in parts {
+                find_report_parts(p, kind, returned_message_id);
+            }
+        }
+        ContentType::Other { tag, .. } if kind.is_none() => {
+            let body = decode(attachment, None);
+            if tag.eq_ignore_ascii_case(b"message/delivery-status")
+                || tag.eq_ignore_ascii_case(b"message/global-delivery-status")
+            {
+                *kind = DeliveryStatus::parse(&body)
+                    .ok()
+                    .map(ReportKind::DeliveryStatus);
+            } else if tag.eq_ignore_ascii_case(b"message/disposition-notification")
+                || tag.eq_ignore_ascii_case(b"message/global-disposition-notification")
+            {
+                *kind = DispositionNotification::parse(&body)
+                    .ok()
+                    .map(ReportKind::Disposition);
+            }
+        }
+        ContentType::MessageRfc822
+        | ContentType::Text {
+            kind: Text::Other { .. },
+            ..
+        } if returned_message_id.is_none() => {
+            /* The returned message, or only its headers for `text/rfc822-headers` */
+            if let ContentType::Text {
+                kind: Text::Other { tag },
+                ..
+            } = attachment.content_type()
+            {
+                if !tag.eq_ignore_ascii_case(b"rfc822-headers") {
+                    return;
+                }
+            }
+            let body = decode(attachment, None);
+            if let Ok((_, headers)) = parser::headers::headers(&body) {
+                *returned_message_id = headers
+                    .into_iter()
+                    .find(|(name, _)| name.eq_ignore_ascii_case(b"message-id"))
+                    .map(|(_, value)| String::from_utf8_lossy(value).into_owned());
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Splits the body of a report part into groups of fields separated by blank lines. Field names
+/// are lowercased and folded values are unfolded.
+fn field_groups(input: &[u8]) -> Result<Vec<Vec<(String, String)>>> {
+    let input = String::from_utf8_lossy(input).replace("\r\n", "\n");
+    let mut ret = vec![];
+    for block in input.split("\n\n") {
+        let block = block.trim_matches('\n');
+        if block.trim().is_empty() {
+            continue;
+        }
+        let block = format!("{}\n", block);
+        let (_, fields) = parser::headers::headers(block.as_bytes())
+            .map_err(|err| MeliError::new(format!("Could not parse report fields: {}", err)))?;
+        ret.push(
+            fields
+                .into_iter()
+                .map(|(name, value)| {
+                    (
+                        String::from_utf8_lossy(name).to_ascii_lowercase(),
+                        String::from_utf8_lossy(value)
+                            .split_whitespace()
+                            .collect::<Vec<&str>>()
+                            .join(" "),
+                    )
+                })
+                .collect(),
+        );
+    }
+    Ok(ret)
+}
+
+/// Strips the address type of fields like `Final-Recipient: rfc822; user@example.com`.
+fn typed_value(value: String) -> String {
+    if let Some(pos) = value.find(';') {
+        value[pos + 1..].trim().to_string()
+    } else {
+        value
+    }
+}
+
+fn normalize_message_id(id: &str) -> String {
+    id.trim()
+        .trim_start_matches('<')
+        .trim_end_matches('>')
+        .trim()
+        .to_string()
+}
+
+/// Encodes `input` as rfc3461 `xtext`, as used in the `ENVID` parameter of `MAIL FROM`.
+pub fn xtext_encode(input: &[u8]) -> String {
+    let mut ret = String::with_capacity(input.len());
+    for &b in input {
+        if (33..=126).contains(&b) && b != b'+' && b != b'=' {
+            ret.push(b as char);
+        } else {
+            ret.push_str(&format!("+{:02X}", b));
+        }
+    }
+    ret
+}
+
+pub fn xtext_decode(input: &str) -> Vec<u8> {
+    let input = input.as_bytes();
+    let mut ret = Vec::with_capacity(input.len());
+    let mut i = 0;
+    while i < input.len() {
+        if input[i] == b'+' && i + 2 < input.len() {
+            if let Some(b) = std::str::from_utf8(&input[i + 1..i + 3])
+                .ok()
+                .and_then(|hex| u8::from_str_radix(hex, 16).ok())
+            {
+                ret.push(b);
+                i += 3;
+                continue;
+            }
+        }
+        ret.push(input[i]);
+        i += 1;
+    }
+    ret
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::email::AttachmentBuilder;
+
+    #[test]
+    fn test_delivery_status_notification() {
+        let bytes = b"From: Mail Delivery System <MAILER-DAEMON@example.com>
+To: user@example.com
+Subject: Undelivered Mail Returned to Sender
+MIME-Version: 1.0
+Content-Type: multipart/report; report-type=delivery-status; boundary=\"XX\"
+
+--XX
+Content-Type: text/plain
+
+Your message could not be delivered.
+
+--XX
+Content-Type: message/delivery-status
+
+Reporting-MTA: dns; mail.example.com
+Original-Envelope-Id: +3Cabc+2B1@example.com+3E
+Arrival-Date: Tue, 1 Sep 2020 10:00:00 +0300
+
+Final-Recipient: rfc822; alice@example.org
+Original-Recipient: rfc822;alice@example.org
+Action: failed
+Status: 5.1.1
+Diagnostic-Code: smtp; 550 5.1.1 <alice@example.org>:
+ Recipient address rejected
+
+Final-Recipient: rfc822; bob@example.org
+Action: delayed
+Status: 4.4.1 (connection timed out)
+Will-Retry-Until: Thu, 3 Sep 2020 10:00:00 +0300
+
+--XX
+Content-Type: text/rfc822-headers
+
+From: user@example.com
+Message-ID: <abc+1@example.com>
+Subject: hello
+
+--XX--
+";
+        let report =
+            DeliveryReport::from_attachment(&AttachmentBuilder::new(bytes).build()).unwrap();
+        assert_eq!(
+            report.original_message_id.as_deref(),
+            Some("abc+1@example.com")
+        );
+        let status = match report.kind {
+            ReportKind::DeliveryStatus(ref status) => status,
+            _ => panic!("Expected a delivery status: {:?}", report),
+        };
+        assert_eq!(
+            status.original_envelope_id.as_deref(),
+            Some("<abc+1@example.com>")
+        );
+        assert_eq!(status.reporting_mta.as_deref(), Some("mail.example.com"));
+        assert_eq!(status.recipients.len(), 2);
+        assert_eq!(status.recipients[0].final_recipient, "alice@example.org");
+        assert_eq!(status.recipients[0].action, DeliveryAction::Failed);
+        assert_eq!(status.recipients[1].status, "4.4.1");
+        assert_eq!(
+            report.recipient_statuses(),
+            vec![
+                (
+                    "alice@example.org".to_string(),
+                    "failed 5.1.1 (smtp; 550 5.1.1 <alice@example.org>: Recipient address rejected)"
+                        .to_string()
+                ),
+                ("bob@example.org".to_string(), "delayed 4.4.1".to_string())
+            ]
+        );
+
+        assert_eq!(
+            xtext_encode(b"<abc+1@example.com>"),
+            "<abc+2B1@example.com>"
+        );
+        assert_eq!(
+            xtext_decode("<abc+2B1@example.com>"),
+            b"<abc+1@example.com>"
+        );
+    }
+
+    #[test]
+    fn test_disposition_notification() {
+        let bytes = b"From: alice@example.org
+To: user@example.com
+Subject: Read: hello
+MIME-Version: 1.0
+Content-Type: multipart/report; report-type=disposition-notification;
+ boundary=\"XX\"
+
+--XX
+Content-Type: text/plain
+
+The message was displayed.
+
+--XX
+Content-Type: message/disposition-notification
+
+Reporting-UA: example.org; Some Mail Client
+Final-Recipient: rfc822; alice@example.org
+Original-Message-ID: <abc@example.com>
+Disposition: manual-action/MDN-sent-manually; displayed
+
+--XX--
+";
+        let report =
+            DeliveryReport::from_attachment(&AttachmentBuilder::new(bytes).build()).unwrap();
+        assert_eq!(
+            report.original_message_id.as_deref(),
+            Some("abc@example.com")
+        );
+        assert_eq!(
+            report.kind,
+            ReportKind::Disposition(DispositionNotification {
+                reporting_ua: Some("example.org; Some Mail Client".to_string()),
+                original_recipient: None,
+                final_recipient: "alice@example.org".to_string(),
+                original_message_id: Some("<abc@example.com>".to_string()),
+                disposition: Disposition {
+                    automatic_action: false,
+                    automatic_sending: false,
+                    kind: DispositionType::Displayed,
+                },
+            })
+        );
+    }
+
+    #[test]
+    fn test_read_receipt() {
+        let bytes = b"Return-Path: <bob@example.com>
+From: Bob <bob@example.com>
+To: alice@example.org
+Subject: hello
+Message-ID: <abc@example.com>
+Disposition-Notification-To: Bob <bob@example.com>
+Date: Thu, 01 Oct 2020 12:00:00 +0000
+
+body
+";
+        let envelope = Envelope::from_bytes(bytes, None).unwrap();
+        let request = MdnRequest::from_envelope(&envelope).unwrap();
+        assert_eq!(request.notify_to.len(), 1);
+        assert!(!request.needs_confirmation);
+
+        let message = request
+            .to_draft(
+                &envelope,
+                "Alice <alice@example.org>",
+                "alice@example.org",
+                "meli; meli",
+                false,
+            )
+            .finalise()
+            .unwrap();
+        let mdn = Envelope::from_bytes(message.as_bytes(), None).unwrap();
+        assert!(DeliveryReport::is_report(&mdn));
+        assert_eq!(
+            DeliveryReport::referenced_message_id(&mdn).as_deref(),
+            Some("abc@example.com")
+        );
+        assert_eq!(mdn.subject(), "Read: hello");
+        assert_eq!(mdn.field_to_to_string(), "Bob <bob@example.com>");
+        /* A receipt never asks for a receipt */
+        assert_eq!(MdnRequest::from_envelope(&mdn), None);
+        let report =
+            DeliveryReport::from_attachment(&AttachmentBuilder::new(message.as_bytes()).build())
+                .unwrap();
+        assert_eq!(
+            report.original_message_id.as_deref(),
+            Some("abc@example.com")
+        );
+        assert_eq!(
+            report.kind,
+            ReportKind::Disposition(DispositionNotification {
+                reporting_ua: Some("meli; meli".to_string()),
+                original_recipient: None,
+                final_recipient: "alice@example.org".to_string(),
+                original_message_id: Some("<abc@example.com>".to_string()),
+                disposition: Disposition {
+                    automatic_action: false,
+                    automatic_sending: false,
+                    kind: DispositionType::Displayed,
+                },
+            })
+        );
+
+        /* Receipts to a third party need the user's confirmation */
+        let bytes = b"Return-Path: <bounces@lists.example.com>
+From: Bob <bob@example.com>
+Subject: hello
+Disposition-Notification-To: tracker@example.net
+
+body
+";
+        let envelope = Envelope::from_bytes(bytes, None).unwrap();
+        assert!(
+            MdnRequest::from_envelope(&envelope)
+                .unwrap()
+                .needs_confirmation
+        );
+    }
+}
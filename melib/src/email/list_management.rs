@@ -104,6 +104,37 @@ pub fn list_id(header: Option<&'_ str>) -> Option<&'_ str> {
     })
 }
 
+/// The URLs of the rfc2369 `List-*` headers of `envelope`, with the name of the header each one
+/// is found in.
+pub fn list_header_urls(envelope: &Envelope) -> Vec<(&'static str, String)> {
+    let mut ret = vec![];
+    for header in &[
+        "List-Post",
+        "List-Unsubscribe",
+        "List-Subscribe",
+        "List-Archive",
+        "List-Help",
+        "List-Owner",
+    ] {
+        let value = match envelope.other_headers().get(header) {
+            Some(value) => value,
+            None => continue,
+        };
+        for action in ListAction::parse_options_list(value.as_bytes())
+            .unwrap_or_default()
+            .into_iter()
+        {
+            match action {
+                ListAction::Url(url) | ListAction::Email(url) => {
+                    ret.push((*header, String::from_utf8_lossy(url).to_string()));
+                }
+                ListAction::No => {}
+            }
+        }
+    }
+    ret
+}
+
 impl<'a> ListActions<'a> {
     pub fn detect(envelope: &'a Envelope) -> Option<ListActions<'a>> {
         let mut ret = ListActions::default();
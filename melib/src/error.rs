@@ -45,6 +45,9 @@ pub enum ErrorKind {
     OSError,
     NotImplemented,
     NotSupported,
+    NotFound,
+    PermissionDenied,
+    ValueError,
 }
 
 impl fmt::Display for ErrorKind {
@@ -62,6 +65,9 @@ impl fmt::Display for ErrorKind {
                 ErrorKind::OSError => "OS Error",
                 ErrorKind::NotImplemented => "Not implemented",
                 ErrorKind::NotSupported => "Not supported",
+                ErrorKind::NotFound => "Not found",
+                ErrorKind::PermissionDenied => "Permission denied",
+                ErrorKind::ValueError => "Invalid value",
             }
         )
     }
@@ -286,6 +292,14 @@ impl From<isahc::Error> for MeliError {
     }
 }
 
+#[cfg(feature = "jmap_backend")]
+impl From<isahc::http::Error> for MeliError {
+    #[inline]
+    fn from(kind: isahc::http::Error) -> MeliError {
+        MeliError::new(kind.to_string()).set_source(Some(Arc::new(kind)))
+    }
+}
+
 #[cfg(feature = "jmap_backend")]
 impl From<serde_json::error::Error> for MeliError {
     #[inline]
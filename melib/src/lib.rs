@@ -121,7 +121,10 @@ pub mod thread;
 pub use thread::*;
 pub mod connections;
 pub mod parsec;
+#[cfg(any(feature = "imap_backend", feature = "smtp"))]
+pub mod sasl;
 pub mod search;
+pub mod sieve;
 
 #[cfg(feature = "gpgme")]
 pub mod gpgme;
@@ -133,7 +136,7 @@ pub mod sqlite3;
 #[macro_use]
 extern crate serde_derive;
 /* parser */
-extern crate data_encoding;
+pub extern crate data_encoding;
 extern crate encoding;
 pub extern crate nom;
 
@@ -0,0 +1,592 @@
+/*
+ * meli - melib crate.
+ *
+ * Copyright 2022 Manos Pitsidianakis
+ *
+ * This file is part of meli.
+ *
+ * meli is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * meli is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with meli. If not, see <http://www.gnu.org/licenses/>.
+ */
+
+//! SASL (RFC 4422 "Simple Authentication and Security Layer") client mechanisms, shared by the
+//! IMAP and SMTP clients.
+//!
+//! Only the client side of each mechanism lives here; the protocol specific framing (IMAP's
+//! `AUTHENTICATE` and continuation requests, SMTP's `AUTH` and `334` replies) and the base64
+//! encoding of the exchanged messages are left to the caller, which feeds server challenges to
+//! [`SaslSession::step`] and sends back what it returns.
+//!
+//! SCRAM usernames and passwords are used as given: SASLprep (RFC 4013) is not applied, so
+//! credentials with non-ASCII characters only work if they are already in the normalized form the
+//! server stored.
+
+use crate::error::{ErrorKind, MeliError, Result};
+use hmac::{Hmac, Mac};
+use sha1::Sha1;
+use sha2::{Digest, Sha256};
+use std::str::FromStr;
+
+/// Authentication mechanisms the client knows how to perform.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum SaslMechanism {
+    /// RFC 4616
+    #[serde(rename = "PLAIN", alias = "plain")]
+    Plain,
+    /// The obsolete `LOGIN` mechanism, only used with SMTP.
+    #[serde(rename = "LOGIN", alias = "login")]
+    Login,
+    /// RFC 5802
+    #[serde(rename = "SCRAM-SHA-1", alias = "scram-sha-1")]
+    ScramSha1,
+    /// RFC 7677
+    #[serde(rename = "SCRAM-SHA-256", alias = "scram-sha-256")]
+    ScramSha256,
+    /// RFC 7628. The password is used as the OAuth 2.0 bearer token.
+    #[serde(rename = "OAUTHBEARER", alias = "oauthbearer")]
+    OAuthBearer,
+    /// Google's pre-standard OAuth 2.0 mechanism. The password is used as the bearer token.
+    #[serde(rename = "XOAUTH2", alias = "xoauth2")]
+    XOAuth2,
+    /// RFC 4422 Appendix A. The client is authenticated by an external layer, for example with a
+    /// TLS client certificate.
+    #[serde(rename = "EXTERNAL", alias = "external")]
+    External,
+}
+
+impl SaslMechanism {
+    /// Mechanisms picked by [`SaslMechanism::negotiate`], in order of preference. Token and
+    /// certificate based mechanisms need different credentials than a password, so they are only
+    /// used when asked for explicitly.
+    pub const AUTO_PREFERENCE: &'static [SaslMechanism] = &[
+        SaslMechanism::ScramSha256,
+        SaslMechanism::ScramSha1,
+        SaslMechanism::Plain,
+        SaslMechanism::Login,
+    ];
+
+    /// Mechanism name as used in `AUTHENTICATE`/`AUTH` commands and capability lists.
+    pub fn name(self) -> &'static str {
+        match self {
+            SaslMechanism::Plain => "PLAIN",
+            SaslMechanism::Login => "LOGIN",
+            SaslMechanism::ScramSha1 => "SCRAM-SHA-1",
+            SaslMechanism::ScramSha256 => "SCRAM-SHA-256",
+            SaslMechanism::OAuthBearer => "OAUTHBEARER",
+            SaslMechanism::XOAuth2 => "XOAUTH2",
+            SaslMechanism::External => "EXTERNAL",
+        }
+    }
+
+    /// Pick the most preferable password based mechanism among those the server advertises.
+    pub fn negotiate<'a, I>(advertised: I) -> Option<SaslMechanism>
+    where
+        I: IntoIterator<Item = &'a str>,
+    {
+        let advertised = advertised.into_iter().collect::<Vec<&str>>();
+        Self::AUTO_PREFERENCE.iter().copied().find(|m| {
+            advertised
+                .iter()
+                .any(|a| a.trim().eq_ignore_ascii_case(m.name()))
+        })
+    }
+}
+
+impl std::fmt::Display for SaslMechanism {
+    fn fmt(&self, fmt: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(fmt, "{}", self.name())
+    }
+}
+
+impl FromStr for SaslMechanism {
+    type Err = MeliError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        [
+            SaslMechanism::Plain,
+            SaslMechanism::Login,
+            SaslMechanism::ScramSha1,
+            SaslMechanism::ScramSha256,
+            SaslMechanism::OAuthBearer,
+            SaslMechanism::XOAuth2,
+            SaslMechanism::External,
+        ]
+        .iter()
+        .copied()
+        .find(|m| m.name().eq_ignore_ascii_case(s.trim()))
+        .ok_or_else(|| {
+            MeliError::new(format!(
+                "Unknown SASL mechanism `{}`. Supported mechanisms are PLAIN, LOGIN, \
+                 SCRAM-SHA-1, SCRAM-SHA-256, OAUTHBEARER, XOAUTH2 and EXTERNAL.",
+                s
+            ))
+        })
+    }
+}
+
+/// Client state of a single authentication exchange.
+#[derive(Debug)]
+pub struct SaslSession {
+    mechanism: SaslMechanism,
+    username: String,
+    password: String,
+    hostname: String,
+    port: u16,
+    state: SessionState,
+}
+
+#[derive(Debug)]
+enum SessionState {
+    Start,
+    LoginUsernameSent,
+    InitialResponseSent,
+    Scram(ScramClient),
+    ScramVerified,
+    Done,
+}
+
+impl SaslSession {
+    /// `hostname` and `port` are only used by `OAUTHBEARER`, which sends them to the server.
+    pub fn new(
+        mechanism: SaslMechanism,
+        username: &str,
+        password: &str,
+        hostname: &str,
+        port: u16,
+    ) -> Self {
+        SaslSession {
+            mechanism,
+            username: username.to_string(),
+            password: password.to_string(),
+            hostname: hostname.to_string(),
+            port,
+            state: SessionState::Start,
+        }
+    }
+
+    pub fn mechanism(&self) -> SaslMechanism {
+        self.mechanism
+    }
+
+    /// The client message that can be sent along with the authentication command (IMAP's
+    /// `SASL-IR`, SMTP's `AUTH` initial response). `LOGIN` has none.
+    pub fn initial_response(&mut self) -> Option<Vec<u8>> {
+        if !matches!(self.state, SessionState::Start) {
+            return None;
+        }
+        let ret = match self.mechanism {
+            SaslMechanism::Login => return None,
+            SaslMechanism::Plain => {
+                // # RFC 4616 The PLAIN SASL Mechanism
+                // message   = [authzid] UTF8NUL authcid UTF8NUL passwd
+                let mut buf = Vec::with_capacity(2 + self.username.len() + self.password.len());
+                buf.push(b'\0');
+                buf.extend_from_slice(self.username.as_bytes());
+                buf.push(b'\0');
+                buf.extend_from_slice(self.password.as_bytes());
+                buf
+            }
+            SaslMechanism::ScramSha1 | SaslMechanism::ScramSha256 => {
+                let scram = ScramClient::new(self.mechanism, &self.username, &self.password);
+                let ret = scram.client_first();
+                self.state = SessionState::Scram(scram);
+                return Some(ret);
+            }
+            SaslMechanism::OAuthBearer => {
+                // # RFC 7628 3.1. Client Response
+                // gs2-header kvsep *kvpair kvsep, with kvsep = %x01
+                format!(
+                    "n,a={},\x01host={}\x01port={}\x01auth=Bearer {}\x01\x01",
+                    scram_escape(&self.username),
+                    self.hostname,
+                    self.port,
+                    self.password
+                )
+                .into_bytes()
+            }
+            SaslMechanism::XOAuth2 => format!(
+                "user={}\x01auth=Bearer {}\x01\x01",
+                self.username, self.password
+            )
+            .into_bytes(),
+            SaslMechanism::External => Vec::new(),
+        };
+        self.state = SessionState::InitialResponseSent;
+        Some(ret)
+    }
+
+    /// Answer a (base64-decoded) server challenge.
+    pub fn step(&mut self, challenge: &[u8]) -> Result<Vec<u8>> {
+        match std::mem::replace(&mut self.state, SessionState::Done) {
+            SessionState::Start if self.mechanism == SaslMechanism::Login => {
+                self.state = SessionState::LoginUsernameSent;
+                Ok(self.username.as_bytes().to_vec())
+            }
+            SessionState::Start => {
+                /* The server asked for the initial response with an empty challenge. */
+                self.state = SessionState::Start;
+                Ok(self.initial_response().unwrap_or_default())
+            }
+            SessionState::LoginUsernameSent => Ok(self.password.as_bytes().to_vec()),
+            SessionState::InitialResponseSent => {
+                /* OAUTHBEARER and XOAUTH2 send a JSON error document as a challenge on failure,
+                 * which has to be acknowledged before the server replies with the actual error.
+                 * RFC 7628 specifies a single %x01 byte for that. */
+                debug!(
+                    "{} authentication challenge: {}",
+                    self.mechanism,
+                    String::from_utf8_lossy(challenge)
+                );
+                if self.mechanism == SaslMechanism::OAuthBearer {
+                    Ok(vec![0x01])
+                } else {
+                    Ok(Vec::new())
+                }
+            }
+            SessionState::Scram(mut scram) => {
+                if scram.server_signature.is_none() {
+                    let ret = scram.client_final(challenge)?;
+                    self.state = SessionState::Scram(scram);
+                    Ok(ret)
+                } else {
+                    scram.verify_server_final(challenge)?;
+                    self.state = SessionState::ScramVerified;
+                    Ok(Vec::new())
+                }
+            }
+            SessionState::ScramVerified | SessionState::Done => Err(MeliError::new(format!(
+                "Unexpected {} authentication challenge: {}",
+                self.mechanism,
+                String::from_utf8_lossy(challenge)
+            ))
+            .set_kind(ErrorKind::Authentication)),
+        }
+    }
+
+    /// Call once the server has reported success. SCRAM authenticates the server as well, so
+    /// success without a verified server signature is an error.
+    pub fn finish(&self) -> Result<()> {
+        match self.state {
+            SessionState::Scram(_) => Err(MeliError::new(format!(
+                "{} authentication: server reported success without proving it knows the \
+                 password.",
+                self.mechanism
+            ))
+            .set_kind(ErrorKind::Authentication)),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Highest SCRAM iteration count accepted from a server. Each iteration is two HMAC computations,
+/// so a hostile server could otherwise keep the client busy for a very long time.
+const MAX_SCRAM_ITERATIONS: u32 = 1_000_000;
+
+/// `=` and `,` have to be escaped in SCRAM and GS2 usernames.
+fn scram_escape(s: &str) -> String {
+    s.replace('=', "=3D").replace(',', "=2C")
+}
+
+#[derive(Debug)]
+struct ScramClient {
+    mechanism: SaslMechanism,
+    username: String,
+    password: String,
+    client_nonce: String,
+    client_first_bare: String,
+    server_signature: Option<Vec<u8>>,
+}
+
+impl ScramClient {
+    fn new(mechanism: SaslMechanism, username: &str, password: &str) -> Self {
+        let client_nonce = uuid::Uuid::new_v4().as_simple().to_string();
+        Self::with_nonce(mechanism, username, password, client_nonce)
+    }
+
+    fn with_nonce(
+        mechanism: SaslMechanism,
+        username: &str,
+        password: &str,
+        client_nonce: String,
+    ) -> Self {
+        let client_first_bare = format!("n={},r={}", scram_escape(username), client_nonce);
+        ScramClient {
+            mechanism,
+            username: username.to_string(),
+            password: password.to_string(),
+            client_nonce,
+            client_first_bare,
+            server_signature: None,
+        }
+    }
+
+    fn hmac(&self, key: &[u8], data: &[u8]) -> Vec<u8> {
+        match self.mechanism {
+            SaslMechanism::ScramSha1 => {
+                let mut mac = Hmac::<Sha1>::new_from_slice(key).unwrap();
+                mac.update(data);
+                mac.finalize().into_bytes().to_vec()
+            }
+            _ => {
+                let mut mac = Hmac::<Sha256>::new_from_slice(key).unwrap();
+                mac.update(data);
+                mac.finalize().into_bytes().to_vec()
+            }
+        }
+    }
+
+    fn hash(&self, data: &[u8]) -> Vec<u8> {
+        match self.mechanism {
+            SaslMechanism::ScramSha1 => Sha1::digest(data).to_vec(),
+            _ => Sha256::digest(data).to_vec(),
+        }
+    }
+
+    /// `Hi()` of RFC 5802, which is PBKDF2 with the HMAC as the pseudorandom function.
+    fn salted_password(&self, salt: &[u8], iterations: u32) -> Vec<u8> {
+        let mut salt = salt.to_vec();
+        salt.extend_from_slice(&1_u32.to_be_bytes());
+        let mut u = self.hmac(self.password.as_bytes(), &salt);
+        let mut ret = u.clone();
+        for _ in 1..iterations {
+            u = self.hmac(self.password.as_bytes(), &u);
+            for (r, u) in ret.iter_mut().zip(u.iter()) {
+                *r ^= u;
+            }
+        }
+        ret
+    }
+
+    fn client_first(&self) -> Vec<u8> {
+        /* No channel binding: gs2-header is "n,," */
+        format!("n,,{}", self.client_first_bare).into_bytes()
+    }
+
+    fn client_final(&mut self, server_first: &[u8]) -> Result<Vec<u8>> {
+        let err = |msg: &str| {
+            MeliError::new(format!(
+                "{} authentication: {} Server sent: {}",
+                self.mechanism,
+                msg,
+                String::from_utf8_lossy(server_first)
+            ))
+            .set_kind(ErrorKind::Authentication)
+        };
+        let server_first_str = std::str::from_utf8(server_first)
+            .map_err(|_| err("server message is not valid UTF-8."))?;
+        let (mut nonce, mut salt, mut iterations) = (None, None, None);
+        for attr in server_first_str.split(',') {
+            if let Some(v) = attr.strip_prefix("r=") {
+                nonce = Some(v);
+            } else if let Some(v) = attr.strip_prefix("s=") {
+                salt = Some(
+                    data_encoding::BASE64
+                        .decode(v.as_bytes())
+                        .map_err(|_| err("invalid salt."))?,
+                );
+            } else if let Some(v) = attr.strip_prefix("i=") {
+                iterations = Some(
+                    u32::from_str(v)
+                        .ok()
+                        .filter(|i| *i > 0)
+                        .ok_or_else(|| err("invalid iteration count."))?,
+                );
+                if iterations > Some(MAX_SCRAM_ITERATIONS) {
+                    return Err(err("iteration count is too large."));
+                }
+            } else if attr.starts_with("m=") {
+                return Err(err("unsupported mandatory extension."));
+            } else if let Some(v) = attr.strip_prefix("e=") {
+                return Err(err(&format!("server error `{}`.", v)));
+            }
+        }
+        let (nonce, salt, iterations) = match (nonce, salt, iterations) {
+            (Some(n), Some(s), Some(i)) => (n, s, i),
+            _ => return Err(err("missing nonce, salt or iteration count.")),
+        };
+        if !nonce.starts_with(&self.client_nonce) || nonce.len() == self.client_nonce.len() {
+            return Err(err("server nonce does not extend the client nonce."));
+        }
+        let client_final_without_proof = format!("c=biws,r={}", nonce);
+        let auth_message = format!(
+            "{},{},{}",
+            self.client_first_bare, server_first_str, client_final_without_proof
+        );
+        let salted_password = self.salted_password(&salt, iterations);
+        let client_key = self.hmac(&salted_password, b"Client Key");
+        let stored_key = self.hash(&client_key);
+        let client_signature = self.hmac(&stored_key, auth_message.as_bytes());
+        let client_proof = client_key
+            .iter()
+            .zip(client_signature.iter())
+            .map(|(k, s)| k ^ s)
+            .collect::<Vec<u8>>();
+        let server_key = self.hmac(&salted_password, b"Server Key");
+        self.server_signature = Some(self.hmac(&server_key, auth_message.as_bytes()));
+        debug!("{} authentication for {}", self.mechanism, self.username);
+        Ok(format!(
+            "{},p={}",
+            client_final_without_proof,
+            data_encoding::BASE64.encode(&client_proof)
+        )
+        .into_bytes())
+    }
+
+    fn verify_server_final(&self, server_final: &[u8]) -> Result<()> {
+        let server_final = String::from_utf8_lossy(server_final);
+        if let Some(e) = server_final.strip_prefix("e=") {
+            return Err(
+                MeliError::new(format!("{} authentication failed: {}", self.mechanism, e))
+                    .set_kind(ErrorKind::Authentication),
+            );
+        }
+        let verifier = server_final
+            .split(',')
+            .find_map(|attr| attr.strip_prefix("v="))
+            .and_then(|v| data_encoding::BASE64.decode(v.as_bytes()).ok());
+        if verifier.is_some() && verifier == self.server_signature {
+            Ok(())
+        } else {
+            Err(MeliError::new(format!(
+                "{} authentication: server signature does not match, the server may not be \
+                 who it claims to be.",
+                self.mechanism
+            ))
+            .set_kind(ErrorKind::Authentication))
+        }
+    }
+}
+
+#[test]
+fn test_sasl_scram() {
+    /* RFC 5802 Section 5 */
+    let mut scram = ScramClient::with_nonce(
+        SaslMechanism::ScramSha1,
+        "user",
+        "pencil",
+        "fyko+d2lbbFgONRv9qkxdawL".to_string(),
+    );
+    assert_eq!(
+        scram.client_first().as_slice(),
+        b"n,,n=user,r=fyko+d2lbbFgONRv9qkxdawL"
+    );
+    assert_eq!(
+        scram
+            .client_final(b"r=fyko+d2lbbFgONRv9qkxdawL3rfcNHYJY1ZVvWVs7j,s=QSXCR+Q6sek8bf92,i=4096")
+            .unwrap()
+            .as_slice(),
+        &b"c=biws,r=fyko+d2lbbFgONRv9qkxdawL3rfcNHYJY1ZVvWVs7j,p=v0X8v3Bz2T0CJGbJQyF0X+HI4Ts="[..]
+    );
+    assert!(scram
+        .verify_server_final(b"v=rmF9pqV8S7suAoZWja4dJRkFsKQ=")
+        .is_ok());
+    assert!(scram
+        .verify_server_final(b"v=dHzbZapWIk4jUhN+Ute9ytag9zjfMHgsqmmiz7AndVQ=")
+        .is_err());
+
+    /* RFC 7677 Section 3 */
+    let mut scram = ScramClient::with_nonce(
+        SaslMechanism::ScramSha256,
+        "user",
+        "pencil",
+        "rOprNGfwEbeRWgbNEkqO".to_string(),
+    );
+    assert_eq!(
+        scram
+            .client_final(
+                b"r=rOprNGfwEbeRWgbNEkqO%hvYDpWUa2RaTCAfuxFIlj)hNlF$k0,s=W22ZaJ0SNY7soEsUEjb6gQ==,i=4096"
+            )
+            .unwrap()
+            .as_slice(),
+        &b"c=biws,r=rOprNGfwEbeRWgbNEkqO%hvYDpWUa2RaTCAfuxFIlj)hNlF$k0,p=dHzbZapWIk4jUhN+Ute9ytag9zjfMHgsqmmiz7AndVQ="[..]
+    );
+    assert!(scram
+        .verify_server_final(b"v=6rriTRBi23WpRR/wtup+mMhUZUn/dB5nLTJRsjl95G4=")
+        .is_ok());
+
+    /* The server nonce must extend ours */
+    let mut scram = ScramClient::with_nonce(
+        SaslMechanism::ScramSha256,
+        "user",
+        "pencil",
+        "abcdef".to_string(),
+    );
+    assert!(scram
+        .client_final(b"r=abcdef,s=QSXCR+Q6sek8bf92,i=4096")
+        .is_err());
+    assert!(scram
+        .client_final(b"r=xyz123,s=QSXCR+Q6sek8bf92,i=4096")
+        .is_err());
+
+    /* Iteration counts a server could use to stall the client */
+    assert!(scram
+        .client_final(b"r=abcdef123,s=QSXCR+Q6sek8bf92,i=4294967295")
+        .is_err());
+    assert!(scram
+        .client_final(b"r=abcdef123,s=QSXCR+Q6sek8bf92,i=1000001")
+        .is_err());
+}
+
+#[test]
+fn test_sasl_session() {
+    assert_eq!(
+        SaslMechanism::negotiate("PLAIN LOGIN SCRAM-SHA-1".split_whitespace()),
+        Some(SaslMechanism::ScramSha1)
+    );
+    assert_eq!(
+        SaslMechanism::negotiate(vec!["XOAUTH2", "OAUTHBEARER"]),
+        None
+    );
+    assert_eq!(
+        SaslMechanism::from_str("scram-sha-256").unwrap(),
+        SaslMechanism::ScramSha256
+    );
+    assert!(SaslMechanism::from_str("CRAM-MD5").is_err());
+
+    let mut session = SaslSession::new(SaslMechanism::Plain, "user", "pass", "", 0);
+    assert_eq!(
+        session.initial_response().unwrap().as_slice(),
+        b"\0user\0pass"
+    );
+    assert!(session.finish().is_ok());
+
+    let mut session = SaslSession::new(SaslMechanism::Login, "user", "pass", "", 0);
+    assert_eq!(session.initial_response(), None);
+    assert_eq!(session.step(b"Username:").unwrap().as_slice(), b"user");
+    assert_eq!(session.step(b"Password:").unwrap().as_slice(), b"pass");
+
+    let mut session = SaslSession::new(
+        SaslMechanism::OAuthBearer,
+        "user@example.com",
+        "vF9dft4qmT",
+        "server.example.com",
+        143,
+    );
+    /* RFC 7628 Section 4.1 */
+    assert_eq!(
+        session.step(b"").unwrap().as_slice(),
+        &b"n,a=user@example.com,\x01host=server.example.com\x01port=143\x01auth=Bearer vF9dft4qmT\x01\x01"[..]
+    );
+    assert_eq!(
+        session
+            .step(b"{\"status\":\"invalid_token\"}")
+            .unwrap()
+            .as_slice(),
+        b"\x01"
+    );
+
+    /* Success without the server proving itself is an error */
+    let mut session = SaslSession::new(SaslMechanism::ScramSha256, "user", "pencil", "", 0);
+    session.initial_response().unwrap();
+    assert!(session.finish().is_err());
+}
@@ -46,8 +46,22 @@ pub enum Query {
     Subject(String),
     AllText(String),
     /* * * * */
+    MessageId(String),
+    /// Mailing list, matched against the `List-Id` header.
+    ListId(String),
+    /// Header name and value.
+    Header(String, String),
+    /// Case insensitive regular expression matched against the subject and body text.
+    Regex(QueryRegex),
+    /* * * * */
     Flags(Vec<String>),
+    Tag(String),
     HasAttachment,
+    /// Size of the message in bytes.
+    Larger(usize),
+    Smaller(usize),
+    /// Mailbox path or glob pattern.
+    Mailbox(String),
     And(Box<Query>, Box<Query>),
     Or(Box<Query>, Box<Query>),
     Not(Box<Query>),
@@ -57,37 +71,309 @@ pub trait QueryTrait {
     fn is_match(&self, query: &Query) -> bool;
 }
 
+impl Query {
+    /// Whether evaluating this query needs the message's raw bytes, for its body text or its
+    /// size, and not only its envelope.
+    pub fn needs_body(&self) -> bool {
+        self.any_term(&|q| matches!(q, Body(_) | AllText(_) | Larger(_) | Smaller(_) | Regex(_)))
+    }
+
+    /// Whether this query has `Mailbox` terms.
+    pub fn needs_mailbox(&self) -> bool {
+        self.any_term(&|q| matches!(q, Mailbox(_)))
+    }
+
+    /// Whether this query may match messages of mailbox `path`. It is `false` only if `Mailbox`
+    /// terms rule the mailbox out.
+    pub fn may_match_mailbox(&self, path: &str) -> bool {
+        match self {
+            Mailbox(pattern) => mailbox_is_match(path, pattern),
+            And(q_a, q_b) => q_a.may_match_mailbox(path) && q_b.may_match_mailbox(path),
+            Or(q_a, q_b) => q_a.may_match_mailbox(path) || q_b.may_match_mailbox(path),
+            _ => true,
+        }
+    }
+
+    fn any_term(&self, pred: &dyn Fn(&Query) -> bool) -> bool {
+        match self {
+            And(q_a, q_b) | Or(q_a, q_b) => q_a.any_term(pred) || q_b.any_term(pred),
+            Not(q) => q.any_term(pred),
+            q => pred(q),
+        }
+    }
+}
+
+/// What a query is matched against besides the envelope, when it is known.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct MatchContext<'a> {
+    /// The raw bytes of the message, needed by body, size and regular expression terms.
+    pub bytes: Option<&'a [u8]>,
+    /// The path of the mailbox the message is in, needed by `Mailbox` terms.
+    pub mailbox_path: Option<&'a str>,
+}
+
+/// Matches using the envelope only; `Body`, `Larger`, `Smaller` and `Mailbox` terms never match
+/// and `AllText` and `Regex` terms are matched against the headers instead of the body.
 impl QueryTrait for crate::Envelope {
     fn is_match(&self, query: &Query) -> bool {
-        use Query::*;
-        match query {
-            Before(timestamp) => self.date() < *timestamp,
-            After(timestamp) => self.date() > *timestamp,
-            Between(timestamp_a, timestamp_b) => {
-                self.date() > *timestamp_a && self.date() < *timestamp_b
-            }
-            On(timestamp) => {
-                self.date() > timestamp.saturating_sub(60 * 60 * 24)
-                    && self.date() < *timestamp + 60 * 60 * 24
-            }
-            From(s) => self.other_headers()["From"].contains(s),
-            To(s) => self.other_headers()["To"].contains(s),
-            Cc(s) => self.other_headers()["Cc"].contains(s),
-            Bcc(s) => self.other_headers()["Bcc"].contains(s),
-            AllAddresses(s) => {
-                self.is_match(&From(s.clone()))
-                    || self.is_match(&To(s.clone()))
-                    || self.is_match(&Cc(s.clone()))
-                    || self.is_match(&Bcc(s.clone()))
+        (self, MatchContext::default()).is_match(query)
+    }
+}
+
+/// Matches an envelope along with the raw bytes of its message, so that `Body` and `AllText`
+/// terms are matched against the decoded text of the message body, like the sqlite3 index does.
+impl QueryTrait for (&crate::Envelope, &[u8]) {
+    fn is_match(&self, query: &Query) -> bool {
+        (
+            self.0,
+            MatchContext {
+                bytes: Some(self.1),
+                mailbox_path: None,
+            },
+        )
+            .is_match(query)
+    }
+}
+
+impl QueryTrait for (&crate::Envelope, MatchContext<'_>) {
+    fn is_match(&self, query: &Query) -> bool {
+        let (envelope, context) = *self;
+        let body_text = match context.bytes {
+            Some(bytes) if query.needs_body() => Some(envelope.body_bytes(bytes).text()),
+            _ => None,
+        };
+        let state = MatchState {
+            body_text_lowercase: body_text.as_ref().map(|t| t.to_lowercase()),
+            body_text,
+            size: context.bytes.map(<[u8]>::len),
+            mailbox_path: context.mailbox_path,
+        };
+        envelope_is_match(envelope, &state, query)
+    }
+}
+
+struct MatchState<'a> {
+    body_text: Option<String>,
+    body_text_lowercase: Option<String>,
+    size: Option<usize>,
+    mailbox_path: Option<&'a str>,
+}
+
+/// Case insensitive substring match.
+fn contains_anycase(haystack: &str, needle: &str) -> bool {
+    haystack.to_lowercase().contains(&needle.to_lowercase())
+}
+
+/// MIME decoded value of header `name`, if the envelope has it.
+fn decoded_header(envelope: &crate::Envelope, name: &str) -> Option<String> {
+    envelope.other_headers().get(name).map(|v| {
+        crate::email::parser::encodings::phrase(v.as_bytes(), false)
+            .map(|(_, d)| String::from_utf8_lossy(&d).into_owned())
+            .unwrap_or_else(|_| v.to_string())
+    })
+}
+
+/// Matches `s` against an address header, using the parsed addresses if there are any and the
+/// MIME decoded raw header value otherwise.
+fn address_is_match(
+    envelope: &crate::Envelope,
+    addresses: &[crate::email::Address],
+    header: &str,
+    s: &str,
+) -> bool {
+    if addresses.is_empty() {
+        decoded_header(envelope, header)
+            .map(|v| contains_anycase(&v, s))
+            .unwrap_or(false)
+    } else {
+        addresses
+            .iter()
+            .any(|a| contains_anycase(&a.to_string(), s))
+    }
+}
+
+/// Whether `envelope` has the flag or tag `name`. Flag names are the ones accepted by the sqlite3
+/// search backend, any other name is looked up in the envelope's tags.
+fn flag_is_match(envelope: &crate::Envelope, name: &str) -> bool {
+    use crate::Flag;
+    let flags = envelope.flags();
+    match name.to_lowercase().as_str() {
+        "draft" => flags.contains(Flag::DRAFT),
+        "deleted" | "trashed" => flags.contains(Flag::TRASHED),
+        "flagged" => flags.contains(Flag::FLAGGED),
+        "passed" | "forwarded" => flags.contains(Flag::PASSED),
+        "seen" | "read" => flags.contains(Flag::SEEN),
+        "recent" | "unseen" | "unread" => !flags.contains(Flag::SEEN),
+        "answered" | "replied" => flags.contains(Flag::REPLIED),
+        "unanswered" => !flags.contains(Flag::REPLIED),
+        _ => tag_is_match(envelope, name),
+    }
+}
+
+fn tag_is_match(envelope: &crate::Envelope, name: &str) -> bool {
+    use std::hash::Hasher;
+    let mut hasher = std::collections::hash_map::DefaultHasher::new();
+    hasher.write(name.as_bytes());
+    envelope.labels().contains(&hasher.finish())
+}
+
+/// Whether mailbox path `path` matches `pattern`, either case insensitively or as a glob. Globs
+/// need the `unicode_algorithms` feature.
+pub fn mailbox_is_match(path: &str, pattern: &str) -> bool {
+    #[cfg(feature = "unicode_algorithms")]
+    {
+        use crate::text_processing::GlobMatch;
+        path.eq_ignore_ascii_case(pattern) || path.matches_glob(pattern)
+    }
+    #[cfg(not(feature = "unicode_algorithms"))]
+    {
+        path.eq_ignore_ascii_case(pattern)
+    }
+}
+
+/// Builds the regular expression of a `Regex` term. Patterns are case insensitive like every other
+/// term.
+#[cfg(feature = "regexp")]
+pub fn build_regex(pattern: &str) -> crate::Result<pcre2::bytes::Regex> {
+    pcre2::bytes::RegexBuilder::new()
+        .caseless(true)
+        .utf(true)
+        .build(pattern)
+        .map_err(|err| crate::MeliError::new(err.to_string()))
+}
+
+/// The pattern of a `Regex` term, compiled once when the term is created instead of for every
+/// message it is matched against.
+#[derive(Clone)]
+pub struct QueryRegex {
+    pattern: String,
+    #[cfg(feature = "regexp")]
+    regex: std::sync::Arc<pcre2::bytes::Regex>,
+}
+
+impl QueryRegex {
+    #[cfg(feature = "regexp")]
+    pub fn new(pattern: &str) -> crate::Result<Self> {
+        Ok(QueryRegex {
+            pattern: pattern.to_string(),
+            regex: std::sync::Arc::new(build_regex(pattern)?),
+        })
+    }
+
+    #[cfg(not(feature = "regexp"))]
+    pub fn new(_pattern: &str) -> crate::Result<Self> {
+        Err(crate::MeliError::new(
+            "Regular expressions need melib to be built with the `regexp` feature.",
+        )
+        .set_kind(crate::error::ErrorKind::NotSupported))
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.pattern
+    }
+
+    #[cfg(feature = "regexp")]
+    pub fn is_match(&self, haystack: &str) -> bool {
+        self.regex.is_match(haystack.as_bytes()).unwrap_or(false)
+    }
+
+    #[cfg(not(feature = "regexp"))]
+    pub fn is_match(&self, _haystack: &str) -> bool {
+        false
+    }
+}
+
+impl PartialEq for QueryRegex {
+    fn eq(&self, other: &Self) -> bool {
+        self.pattern == other.pattern
+    }
+}
+
+impl std::fmt::Debug for QueryRegex {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        std::fmt::Debug::fmt(&self.pattern, f)
+    }
+}
+
+impl serde::Serialize for QueryRegex {
+    fn serialize<S>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        serializer.serialize_str(&self.pattern)
+    }
+}
+
+fn envelope_is_match(envelope: &crate::Envelope, state: &MatchState, query: &Query) -> bool {
+    match query {
+        Before(timestamp) => envelope.date() < *timestamp,
+        After(timestamp) => envelope.date() > *timestamp,
+        Between(timestamp_a, timestamp_b) => {
+            envelope.date() > *timestamp_a && envelope.date() < *timestamp_b
+        }
+        On(timestamp) => {
+            envelope.date() > timestamp.saturating_sub(60 * 60 * 24)
+                && envelope.date() < *timestamp + 60 * 60 * 24
+        }
+        From(s) => address_is_match(envelope, envelope.from(), "From", s),
+        To(s) => address_is_match(envelope, envelope.to(), "To", s),
+        Cc(s) => address_is_match(envelope, envelope.cc(), "Cc", s),
+        Bcc(s) => address_is_match(envelope, envelope.bcc(), "Bcc", s),
+        AllAddresses(s) => {
+            address_is_match(envelope, envelope.from(), "From", s)
+                || address_is_match(envelope, envelope.to(), "To", s)
+                || address_is_match(envelope, envelope.cc(), "Cc", s)
+                || address_is_match(envelope, envelope.bcc(), "Bcc", s)
+        }
+        InReplyTo(s) => envelope
+            .in_reply_to_display()
+            .map(|m| contains_anycase(&m, s))
+            .unwrap_or(false),
+        References(s) => contains_anycase(&envelope.field_references_to_string(), s),
+        MessageId(s) => contains_anycase(&envelope.message_id_display(), s),
+        ListId(s) => decoded_header(envelope, "List-Id")
+            .map(|v| contains_anycase(&v, s))
+            .unwrap_or(false),
+        Header(name, s) => decoded_header(envelope, name)
+            .map(|v| contains_anycase(&v, s))
+            .unwrap_or(false),
+        Subject(s) => contains_anycase(&envelope.subject(), s),
+        Body(s) => state
+            .body_text_lowercase
+            .as_ref()
+            .map(|b| b.contains(&s.to_lowercase()))
+            .unwrap_or(false),
+        AllText(s) => match state.body_text_lowercase {
+            Some(ref b) => b.contains(&s.to_lowercase()),
+            None => {
+                contains_anycase(&envelope.subject(), s)
+                    || envelope_is_match(envelope, state, &AllAddresses(s.clone()))
             }
-            Flags(v) => v.iter().any(|s| self.flags() == s.as_str()),
-            Subject(s) => self.other_headers()["Subject"].contains(s),
-            HasAttachment => self.has_attachments(),
-            And(q_a, q_b) => self.is_match(q_a) && self.is_match(q_b),
-            Or(q_a, q_b) => self.is_match(q_a) || self.is_match(q_b),
-            Not(q) => !self.is_match(q),
-            _ => false,
+        },
+        Regex(regex) => {
+            regex.is_match(&envelope.subject())
+                || state
+                    .body_text
+                    .as_ref()
+                    .map(|b| regex.is_match(b))
+                    .unwrap_or(false)
+        }
+        Flags(v) => v.iter().all(|s| flag_is_match(envelope, s)),
+        Tag(t) => tag_is_match(envelope, t),
+        HasAttachment => envelope.has_attachments(),
+        Larger(size) => state.size.map(|s| s > *size).unwrap_or(false),
+        Smaller(size) => state.size.map(|s| s < *size).unwrap_or(false),
+        Mailbox(pattern) => state
+            .mailbox_path
+            .map(|path| mailbox_is_match(path, pattern))
+            .unwrap_or(false),
+        And(q_a, q_b) => {
+            envelope_is_match(envelope, state, q_a) && envelope_is_match(envelope, state, q_b)
         }
+        Or(q_a, q_b) => {
+            envelope_is_match(envelope, state, q_a) || envelope_is_match(envelope, state, q_b)
+        }
+        Not(q) => !envelope_is_match(envelope, state, q),
     }
 }
 
@@ -144,6 +430,133 @@ pub mod query_parser {
         .map(Query::Bcc)
     }
 
+    fn tag<'a>() -> impl Parser<'a, Query> {
+        prefix(
+            whitespace_wrap(match_literal_anycase("tag:")),
+            whitespace_wrap(token()),
+        )
+        .map(Query::Tag)
+    }
+
+    fn mailbox<'a>() -> impl Parser<'a, Query> {
+        prefix(
+            whitespace_wrap(either(
+                match_literal_anycase("mailbox:"),
+                match_literal_anycase("in:"),
+            )),
+            whitespace_wrap(token()),
+        )
+        .map(Query::Mailbox)
+    }
+
+    fn list<'a>() -> impl Parser<'a, Query> {
+        prefix(
+            whitespace_wrap(match_literal_anycase("list:")),
+            whitespace_wrap(token()),
+        )
+        .map(Query::ListId)
+    }
+
+    fn message_id<'a>() -> impl Parser<'a, Query> {
+        prefix(
+            whitespace_wrap(match_literal_anycase("message-id:")),
+            whitespace_wrap(token()),
+        )
+        .map(|id| Query::MessageId(id.trim_start_matches('<').trim_end_matches('>').to_string()))
+    }
+
+    /// `header:NAME:VALUE`
+    fn header<'a>() -> impl Parser<'a, Query> {
+        move |input| {
+            let (rest, _) = whitespace_wrap(match_literal_anycase("header:")).parse(input)?;
+            let (rest, name) = one_or_more(pred(any_char, |c| {
+                *c != ':' && !c.is_whitespace() && !c.is_control()
+            }))
+            .parse(rest)?;
+            let (rest, value) = prefix(match_literal(":"), whitespace_wrap(token())).parse(rest)?;
+            Ok((rest, Query::Header(name.into_iter().collect(), value)))
+        }
+    }
+
+    fn size<'a>(keyword: &'static str, variant: fn(usize) -> Query) -> impl Parser<'a, Query> {
+        move |input| {
+            let (rest, value) = prefix(
+                whitespace_wrap(match_literal_anycase(keyword)),
+                whitespace_wrap(token()),
+            )
+            .parse(input)?;
+            parse_size(&value)
+                .map(|size| (rest, variant(size)))
+                .ok_or(input)
+        }
+    }
+
+    fn date<'a>(
+        keyword: &'static str,
+        variant: fn(UnixTimestamp) -> Query,
+    ) -> impl Parser<'a, Query> {
+        move |input| {
+            let (rest, value) = prefix(
+                whitespace_wrap(match_literal_anycase(keyword)),
+                whitespace_wrap(token()),
+            )
+            .parse(input)?;
+            parse_date(&value)
+                .map(|timestamp| (rest, variant(timestamp)))
+                .ok_or(input)
+        }
+    }
+
+    #[cfg(feature = "regexp")]
+    fn regex<'a>() -> impl Parser<'a, Query> {
+        move |input| {
+            let (rest, pattern) = prefix(
+                whitespace_wrap(match_literal_anycase("regex:")),
+                whitespace_wrap(token()),
+            )
+            .parse(input)?;
+            match QueryRegex::new(&pattern) {
+                Ok(regex) => Ok((rest, Query::Regex(regex))),
+                Err(_) => Err(input),
+            }
+        }
+    }
+
+    #[cfg(not(feature = "regexp"))]
+    fn regex<'a>() -> impl Parser<'a, Query> {
+        move |input| Err(input)
+    }
+
+    /// Sizes in bytes, with an optional `k`, `M` or `G` suffix.
+    fn parse_size(s: &str) -> Option<usize> {
+        let (num, multiplier) = match s.chars().last()? {
+            'k' | 'K' => (&s[..s.len() - 1], 1024),
+            'm' | 'M' => (&s[..s.len() - 1], 1024 * 1024),
+            'g' | 'G' => (&s[..s.len() - 1], 1024 * 1024 * 1024),
+            _ => (s, 1),
+        };
+        num.parse::<usize>().ok()?.checked_mul(multiplier)
+    }
+
+    /// `YYYY-MM-DD` dates, or dates relative to now in `h`ours, `d`ays, `w`eeks, `m`onths or
+    /// `y`ears, such as `2w`.
+    fn parse_date(s: &str) -> Option<UnixTimestamp> {
+        use crate::datetime::{now, timestamp_from_string, RFC3339_FMT};
+        if let Ok(Some(timestamp)) = timestamp_from_string(s, RFC3339_FMT) {
+            return Some(timestamp);
+        }
+        let unit: UnixTimestamp = match s.chars().last()? {
+            'h' => 60 * 60,
+            'd' => 60 * 60 * 24,
+            'w' => 60 * 60 * 24 * 7,
+            'm' => 60 * 60 * 24 * 30,
+            'y' => 60 * 60 * 24 * 365,
+            _ => return None,
+        };
+        let n = s[..s.len() - 1].parse::<UnixTimestamp>().ok()?;
+        Some(now().saturating_sub(n.checked_mul(unit)?))
+    }
+
     fn or<'a>() -> impl Parser<'a, Query> {
         move |input| {
             whitespace_wrap(match_literal_anycase("or"))
@@ -183,6 +596,22 @@ pub mod query_parser {
         move |input| either(quoted_string(), string()).parse(input)
     }
 
+    /// A quoted string, or any characters up to whitespace or a parenthesis.
+    fn token<'a>() -> impl Parser<'a, String> {
+        move |input| {
+            either(
+                quoted_string(),
+                map(
+                    one_or_more(pred(any_char, |c| {
+                        !c.is_whitespace() && *c != '(' && *c != ')'
+                    })),
+                    |chars| chars.into_iter().collect::<String>(),
+                ),
+            )
+            .parse(input)
+        }
+    }
+
     fn parentheses_query<'a>() -> impl Parser<'a, Query> {
         move |input| {
             delimited(
@@ -251,7 +680,18 @@ pub mod query_parser {
                 .or_else(|_| bcc().parse(input))
                 .or_else(|_| subject().parse(input))
                 .or_else(|_| flags().parse(input))
+                .or_else(|_| tag().parse(input))
                 .or_else(|_| has_attachment().parse(input))
+                .or_else(|_| mailbox().parse(input))
+                .or_else(|_| list().parse(input))
+                .or_else(|_| message_id().parse(input))
+                .or_else(|_| header().parse(input))
+                .or_else(|_| size("larger:", Query::Larger).parse(input))
+                .or_else(|_| size("smaller:", Query::Smaller).parse(input))
+                .or_else(|_| date("before:", Query::Before).parse(input))
+                .or_else(|_| date("after:", Query::After).parse(input))
+                .or_else(|_| date("on:", Query::On).parse(input))
+                .or_else(|_| regex().parse(input))
             {
                 Ok(q)
             } else if let Ok((rest, query_a)) = not().parse(input) {
@@ -380,6 +820,69 @@ pub mod query_parser {
             Ok(("", Flags(vec!["f".to_string()]))),
             query().parse_complete("tags:f")
         );
+        assert_eq!(
+            Ok((
+                "",
+                And(
+                    Box::new(Tag("work".to_string())),
+                    Box::new(Mailbox("Lists/*".to_string()))
+                )
+            )),
+            query().parse_complete("tag:work and in:Lists/*")
+        );
+        assert_eq!(
+            Ok(("", Larger(10 * 1024))),
+            query().parse_complete("larger:10k")
+        );
+        assert_eq!(
+            Ok(("", Smaller(2 * 1024 * 1024))),
+            query().parse_complete("smaller: 2M")
+        );
+        assert!(query().parse_complete("larger:lots").is_err());
+        assert_eq!(
+            Ok(("", ListId("meli-devel.lists.example.com".to_string()))),
+            query().parse_complete("list:meli-devel.lists.example.com")
+        );
+        assert_eq!(
+            Ok(("", MessageId("abc@example.com".to_string()))),
+            query().parse_complete("message-id:<abc@example.com>")
+        );
+        assert_eq!(
+            Ok(("", Header("X-Mailer".to_string(), "meli 0.7".to_string()))),
+            query().parse_complete("header:X-Mailer:\"meli 0.7\"")
+        );
+        assert_eq!(
+            Ok(("", Mailbox("INBOX".to_string()))),
+            query().parse_complete("mailbox:INBOX")
+        );
+        match query().parse_complete("after:2w") {
+            Ok(("", After(timestamp))) => {
+                let two_weeks_ago = crate::datetime::now() - 2 * 7 * 24 * 60 * 60;
+                assert!(two_weeks_ago - timestamp < 60);
+            }
+            other => panic!("{:?}", other),
+        }
+        assert!(matches!(
+            query().parse_complete("before:2020-01-01"),
+            Ok(("", Before(_)))
+        ));
+        assert!(query().parse_complete("before:2x").is_err());
+        let (_, q) = query()
+            .parse_complete("mailbox:Lists/* and (from:alice or mailbox:INBOX)")
+            .unwrap();
+        assert!(!q.may_match_mailbox("INBOX"));
+        #[cfg(feature = "unicode_algorithms")]
+        assert!(q.may_match_mailbox("Lists/rust"));
+        let (_, q) = query().parse_complete("not mailbox:Trash").unwrap();
+        assert!(q.may_match_mailbox("Trash"));
+        #[cfg(feature = "regexp")]
+        {
+            assert_eq!(
+                Ok(("", Regex(QueryRegex::new("caf(e|é)").unwrap()))),
+                query().parse_complete("regex:\"caf(e|é)\"")
+            );
+            assert!(query().parse_complete("regex:\"(unclosed\"").is_err());
+        }
     }
 }
 
@@ -406,3 +909,86 @@ impl<'de> Deserialize<'de> for Query {
         ret
     }
 }
+
+#[test]
+fn test_query_envelope_match() {
+    use crate::parsec::Parser;
+    use crate::{Envelope, Flag};
+    let bytes = b"From: =?UTF-8?Q?Zo=C3=AB_Doe?= <zoe@example.com>
+To: list@lists.example.com
+List-Id: Example list <list.lists.example.com>
+X-Mailer: meli 0.7
+Subject: =?UTF-8?Q?Caf=C3=A9_meeting?=
+Message-ID: <reply@example.com>
+In-Reply-To: <original@example.com>
+References: <root@example.com> <original@example.com>
+Date: Wed, 1 Jan 2020 12:00:00 +0000
+Content-Type: text/plain; charset=utf-8
+Content-Transfer-Encoding: quoted-printable
+
+See you at the Caf=C3=A9 on Tuesday.
+";
+    let envelope = Envelope::from_bytes(bytes, Some(Flag::SEEN | Flag::FLAGGED)).unwrap();
+    let is_match = |q: &str| {
+        let q = query().parse_complete(q).unwrap().1;
+        (&envelope, &bytes[..]).is_match(&q)
+    };
+    assert!(is_match("from:zoë"));
+    assert!(is_match("from:\"ZOE@EXAMPLE\""));
+    assert!(!is_match("to:zoe"));
+    assert!(is_match("subject:\"café meeting\""));
+    assert!(is_match("tuesday"));
+    assert!(!is_match("meeting"));
+    assert!(is_match("flags:seen,flagged"));
+    assert!(!is_match("flags:seen,draft"));
+    assert!(is_match("is:read and not is:unread"));
+    assert!(is_match("from:nobody or subject:CAFÉ"));
+    assert!(!is_match("cc:zoe"));
+    assert!(!is_match("bcc:zoe"));
+    assert!(!is_match("has:attachment"));
+    assert!(envelope.is_match(&InReplyTo("original@".to_string())));
+    assert!(envelope.is_match(&References("ROOT@example".to_string())));
+    assert!(envelope.is_match(&AllAddresses("lists.example".to_string())));
+    assert!(envelope.is_match(&Before(envelope.date() + 1)));
+    assert!(!envelope.is_match(&After(envelope.date() + 1)));
+    assert!(envelope.is_match(&On(envelope.date())));
+    /* Without the message body, body terms cannot match */
+    assert!(!envelope.is_match(&Body("tuesday".to_string())));
+    assert!((&envelope, &bytes[..]).is_match(&Body("TUESDAY".to_string())));
+    assert!(!Subject("café".to_string()).needs_body());
+    assert!(Not(Box::new(Body("café".to_string()))).needs_body());
+
+    assert!(is_match("list:list.lists.example.com"));
+    assert!(is_match("message-id:<REPLY@example.com>"));
+    assert!(is_match("header:x-mailer:\"MELI 0.7\""));
+    assert!(!is_match("header:X-Spam:yes"));
+    assert!(is_match(&format!("larger:{}", bytes.len() - 1)));
+    assert!(!is_match(&format!("smaller:{}", bytes.len())));
+    assert!(!envelope.is_match(&Larger(0)));
+    #[cfg(feature = "regexp")]
+    {
+        assert!(is_match("regex:\"caf. meeting\""));
+        assert!(is_match("regex:\"on tues(day)?\""));
+        assert!(!is_match("regex:\"^meeting\""));
+    }
+
+    let mut tagged = envelope.clone();
+    tagged.labels_mut().push({
+        use std::hash::Hasher;
+        let mut hasher = std::collections::hash_map::DefaultHasher::new();
+        hasher.write(b"work");
+        hasher.finish()
+    });
+    assert!(tagged.is_match(&Tag("work".to_string())));
+    assert!(tagged.is_match(&Flags(vec!["work".to_string(), "seen".to_string()])));
+    assert!(!envelope.is_match(&Tag("work".to_string())));
+
+    let in_mailbox = |path| MatchContext {
+        bytes: None,
+        mailbox_path: Some(path),
+    };
+    assert!((&envelope, in_mailbox("INBOX")).is_match(&Mailbox("inbox".to_string())));
+    assert!((&envelope, in_mailbox("Lists/meli")).is_match(&Mailbox("Lists/*".to_string())));
+    assert!(!(&envelope, in_mailbox("Sent")).is_match(&Mailbox("INBOX".to_string())));
+    assert!(!envelope.is_match(&Mailbox("INBOX".to_string())));
+}
@@ -0,0 +1,788 @@
+/*
+ * meli - sieve
+ *
+ * Copyright 2020 Manos Pitsidianakis
+ *
+ * This file is part of meli.
+ *
+ * meli is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * meli is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with meli. If not, see <http://www.gnu.org/licenses/>.
+ */
+
+//! Parsing and validation of Sieve scripts (RFC 5228 "Sieve: An Email Filtering Language").
+//!
+//! The parser only checks the syntax of a script and the structure of its control commands; it
+//! doesn't evaluate anything. It is meant to catch mistakes locally before a script is uploaded to
+//! a ManageSieve server.
+//!
+//! # Examples
+//!
+//! ```rust
+//! # use melib::sieve::*;
+//! let script = r#"require "fileinto";
+//! if header :contains "List-Id" "meli-devel" {
+//!     fileinto "meli";
+//! }"#;
+//! let commands = parse_sieve(script).unwrap();
+//! assert_eq!(commands.len(), 2);
+//! assert!(validate_sieve(script).is_ok());
+//!
+//! let err = validate_sieve("if true { discard }").unwrap_err();
+//! assert_eq!(err.details.as_ref(), "line 1, column 19: expected `;`, found `}`");
+//! ```
+
+use crate::error::{MeliError, Result};
+use std::fmt;
+
+/// A location in a script, for error messages. Both values start from 1.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Position {
+    pub line: usize,
+    pub column: usize,
+}
+
+impl fmt::Display for Position {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        write!(fmt, "line {}, column {}", self.line, self.column)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Argument {
+    StringList(Vec<String>),
+    Number(u64),
+    Tag(String),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Test {
+    pub identifier: String,
+    pub arguments: Vec<Argument>,
+    pub tests: Vec<Test>,
+    pub position: Position,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Command {
+    pub identifier: String,
+    pub arguments: Vec<Argument>,
+    pub tests: Vec<Test>,
+    pub block: Option<Vec<Command>>,
+    pub position: Position,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Identifier(String),
+    Tag(String),
+    Number(u64),
+    String(String),
+    Punct(char),
+}
+
+impl fmt::Display for Token {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Token::Identifier(s) => write!(fmt, "`{}`", s),
+            Token::Tag(s) => write!(fmt, "`:{}`", s),
+            Token::Number(n) => write!(fmt, "`{}`", n),
+            Token::String(_) => write!(fmt, "string"),
+            Token::Punct(c) => write!(fmt, "`{}`", c),
+        }
+    }
+}
+
+fn error<T>(position: Position, msg: impl fmt::Display) -> Result<T> {
+    Err(MeliError::new(format!("{}: {}", position, msg)))
+}
+
+struct Lexer<'s> {
+    input: &'s [u8],
+    pos: usize,
+    line: usize,
+    line_start: usize,
+}
+
+impl<'s> Lexer<'s> {
+    fn new(input: &'s str) -> Self {
+        Lexer {
+            input: input.as_bytes(),
+            pos: 0,
+            line: 1,
+            line_start: 0,
+        }
+    }
+
+    fn position(&self) -> Position {
+        Position {
+            line: self.line,
+            column: self.pos - self.line_start + 1,
+        }
+    }
+
+    fn peek_byte(&self) -> Option<u8> {
+        self.input.get(self.pos).copied()
+    }
+
+    fn bump(&mut self) -> Option<u8> {
+        let b = self.peek_byte()?;
+        self.pos += 1;
+        if b == b'\n' {
+            self.line += 1;
+            self.line_start = self.pos;
+        }
+        Some(b)
+    }
+
+    fn skip_whitespace_and_comments(&mut self) -> Result<()> {
+        loop {
+            match self.peek_byte() {
+                Some(b' ') | Some(b'\t') | Some(b'\r') | Some(b'\n') => {
+                    self.bump();
+                }
+                Some(b'#') => while !matches!(self.bump(), Some(b'\n') | None) {},
+                Some(b'/') if self.input.get(self.pos + 1) == Some(&b'*') => {
+                    let start = self.position();
+                    self.bump();
+                    self.bump();
+                    loop {
+                        match self.bump() {
+                            Some(b'*') if self.peek_byte() == Some(b'/') => {
+                                self.bump();
+                                break;
+                            }
+                            Some(_) => {}
+                            None => return error(start, "unterminated comment"),
+                        }
+                    }
+                }
+                _ => return Ok(()),
+            }
+        }
+    }
+
+    fn take_while(&mut self, f: impl Fn(u8) -> bool) -> &'s str {
+        let start = self.pos;
+        while self.peek_byte().map(&f).unwrap_or(false) {
+            self.bump();
+        }
+        /* Only ASCII bytes are matched by the callers so this is a valid str. */
+        std::str::from_utf8(&self.input[start..self.pos]).unwrap_or_default()
+    }
+
+    fn quoted_string(&mut self, start: Position) -> Result<String> {
+        let mut ret = Vec::new();
+        loop {
+            match self.bump() {
+                Some(b'\\') => match self.bump() {
+                    Some(b) => ret.push(b),
+                    None => return error(start, "unterminated string"),
+                },
+                Some(b'"') => break,
+                Some(b) => ret.push(b),
+                None => return error(start, "unterminated string"),
+            }
+        }
+        Ok(String::from_utf8_lossy(&ret).into_owned())
+    }
+
+    fn multiline_string(&mut self, start: Position) -> Result<String> {
+        /* "text:" *(SP / HTAB) (hash-comment / CRLF) */
+        while matches!(self.peek_byte(), Some(b' ') | Some(b'\t')) {
+            self.bump();
+        }
+        match self.peek_byte() {
+            Some(b'#') => while !matches!(self.bump(), Some(b'\n') | None) {},
+            Some(b'\r') | Some(b'\n') => {
+                if self.bump() == Some(b'\r') && self.bump() != Some(b'\n') {
+                    return error(self.position(), "expected newline after `text:`");
+                }
+            }
+            _ => return error(self.position(), "expected newline after `text:`"),
+        }
+        let mut ret = String::new();
+        loop {
+            let line_start = self.pos;
+            while !matches!(self.peek_byte(), Some(b'\n') | None) {
+                self.bump();
+            }
+            if self.peek_byte().is_none() {
+                return error(start, "unterminated multi-line string, expected `.` line");
+            }
+            let line = String::from_utf8_lossy(&self.input[line_start..self.pos]);
+            let line = line.strip_suffix('\r').unwrap_or(&line);
+            self.bump();
+            if line == "." {
+                break;
+            }
+            /* dot-stuffing */
+            ret.push_str(line.strip_prefix('.').unwrap_or(line));
+            ret.push('\n');
+        }
+        Ok(ret)
+    }
+
+    fn next_token(&mut self) -> Result<Option<(Token, Position)>> {
+        self.skip_whitespace_and_comments()?;
+        let position = self.position();
+        let b = match self.peek_byte() {
+            None => return Ok(None),
+            Some(b) => b,
+        };
+        let token = match b {
+            b'"' => {
+                self.bump();
+                Token::String(self.quoted_string(position)?)
+            }
+            b':' => {
+                self.bump();
+                let identifier =
+                    self.take_while(|b| b.is_ascii_alphanumeric() || b == b'_' || b == b'-');
+                if identifier.is_empty() {
+                    return error(position, "expected tag name after `:`");
+                }
+                Token::Tag(identifier.to_string())
+            }
+            b'0'..=b'9' => {
+                let digits = self.take_while(|b| b.is_ascii_digit());
+                let mut number: u64 = digits.parse().or_else(|_| {
+                    error(position, format_args!("number `{}` is too large", digits))
+                })?;
+                match self.peek_byte() {
+                    Some(b'K') | Some(b'k') => {
+                        number = number.saturating_mul(1 << 10);
+                        self.bump();
+                    }
+                    Some(b'M') | Some(b'm') => {
+                        number = number.saturating_mul(1 << 20);
+                        self.bump();
+                    }
+                    Some(b'G') | Some(b'g') => {
+                        number = number.saturating_mul(1 << 30);
+                        self.bump();
+                    }
+                    _ => {}
+                }
+                Token::Number(number)
+            }
+            b if b.is_ascii_alphabetic() || b == b'_' => {
+                let identifier = self
+                    .take_while(|b| b.is_ascii_alphanumeric() || b == b'_')
+                    .to_string();
+                if identifier.eq_ignore_ascii_case("text") && self.peek_byte() == Some(b':') {
+                    self.bump();
+                    Token::String(self.multiline_string(position)?)
+                } else {
+                    Token::Identifier(identifier)
+                }
+            }
+            b'[' | b']' | b'(' | b')' | b'{' | b'}' | b',' | b';' => {
+                self.bump();
+                Token::Punct(b as char)
+            }
+            _ => {
+                let c = std::str::from_utf8(&self.input[self.pos..])
+                    .ok()
+                    .and_then(|s| s.chars().next())
+                    .unwrap_or(b as char);
+                return error(position, format_args!("unexpected character `{}`", c));
+            }
+        };
+        Ok(Some((token, position)))
+    }
+}
+
+struct Parser<'s> {
+    lexer: Lexer<'s>,
+    peeked: Option<Option<(Token, Position)>>,
+}
+
+impl<'s> Parser<'s> {
+    fn peek(&mut self) -> Result<Option<&(Token, Position)>> {
+        if self.peeked.is_none() {
+            self.peeked = Some(self.lexer.next_token()?);
+        }
+        Ok(self.peeked.as_ref().unwrap().as_ref())
+    }
+
+    fn next(&mut self) -> Result<Option<(Token, Position)>> {
+        if let Some(peeked) = self.peeked.take() {
+            return Ok(peeked);
+        }
+        self.lexer.next_token()
+    }
+
+    fn peek_punct(&mut self, c: char) -> Result<bool> {
+        Ok(matches!(self.peek()?, Some((Token::Punct(p), _)) if *p == c))
+    }
+
+    fn expect_punct(&mut self, c: char) -> Result<()> {
+        match self.next()? {
+            Some((Token::Punct(p), _)) if p == c => Ok(()),
+            Some((token, position)) => {
+                error(position, format_args!("expected `{}`, found {}", c, token))
+            }
+            None => error(
+                self.lexer.position(),
+                format_args!("expected `{}`, found end of script", c),
+            ),
+        }
+    }
+
+    fn commands(&mut self, in_block: bool) -> Result<Vec<Command>> {
+        let mut ret = vec![];
+        loop {
+            match self.next()? {
+                None if in_block => {
+                    return error(self.lexer.position(), "expected `}`, found end of script");
+                }
+                None => return Ok(ret),
+                Some((Token::Punct('}'), _)) if in_block => return Ok(ret),
+                Some((Token::Identifier(identifier), position)) => {
+                    let (arguments, tests) = self.arguments()?;
+                    let block = if self.peek_punct('{')? {
+                        self.next()?;
+                        Some(self.commands(true)?)
+                    } else {
+                        self.expect_punct(';')?;
+                        None
+                    };
+                    ret.push(Command {
+                        identifier,
+                        arguments,
+                        tests,
+                        block,
+                        position,
+                    });
+                }
+                Some((token, position)) => {
+                    return error(position, format_args!("expected command, found {}", token));
+                }
+            }
+        }
+    }
+
+    fn string_list(&mut self) -> Result<Vec<String>> {
+        let mut ret = vec![];
+        loop {
+            match self.next()? {
+                Some((Token::String(s), _)) => ret.push(s),
+                Some((token, position)) => {
+                    return error(position, format_args!("expected string, found {}", token));
+                }
+                None => {
+                    return error(
+                        self.lexer.position(),
+                        "expected string, found end of script",
+                    )
+                }
+            }
+            match self.next()? {
+                Some((Token::Punct(','), _)) => {}
+                Some((Token::Punct(']'), _)) => return Ok(ret),
+                Some((token, position)) => {
+                    return error(
+                        position,
+                        format_args!("expected `,` or `]`, found {}", token),
+                    );
+                }
+                None => return error(self.lexer.position(), "expected `]`, found end of script"),
+            }
+        }
+    }
+
+    fn arguments(&mut self) -> Result<(Vec<Argument>, Vec<Test>)> {
+        let mut arguments = vec![];
+        loop {
+            let argument = match self.peek()? {
+                Some((Token::String(_), _)) => match self.next()? {
+                    Some((Token::String(s), _)) => Argument::StringList(vec![s]),
+                    _ => unreachable!(),
+                },
+                Some((Token::Number(n), _)) => {
+                    let n = *n;
+                    self.next()?;
+                    Argument::Number(n)
+                }
+                Some((Token::Tag(_), _)) => match self.next()? {
+                    Some((Token::Tag(t), _)) => Argument::Tag(t),
+                    _ => unreachable!(),
+                },
+                Some((Token::Punct('['), _)) => {
+                    self.next()?;
+                    Argument::StringList(self.string_list()?)
+                }
+                _ => break,
+            };
+            arguments.push(argument);
+        }
+        let tests = match self.peek()? {
+            Some((Token::Identifier(_), _)) => vec![self.test()?],
+            Some((Token::Punct('('), _)) => {
+                self.next()?;
+                self.test_list()?
+            }
+            _ => vec![],
+        };
+        Ok((arguments, tests))
+    }
+
+    fn test(&mut self) -> Result<Test> {
+        match self.next()? {
+            Some((Token::Identifier(identifier), position)) => {
+                let (arguments, tests) = self.arguments()?;
+                Ok(Test {
+                    identifier,
+                    arguments,
+                    tests,
+                    position,
+                })
+            }
+            Some((token, position)) => {
+                error(position, format_args!("expected test, found {}", token))
+            }
+            None => error(self.lexer.position(), "expected test, found end of script"),
+        }
+    }
+
+    fn test_list(&mut self) -> Result<Vec<Test>> {
+        let mut ret = vec![self.test()?];
+        loop {
+            match self.next()? {
+                Some((Token::Punct(','), _)) => ret.push(self.test()?),
+                Some((Token::Punct(')'), _)) => return Ok(ret),
+                Some((token, position)) => {
+                    return error(
+                        position,
+                        format_args!("expected `,` or `)`, found {}", token),
+                    );
+                }
+                None => return error(self.lexer.position(), "expected `)`, found end of script"),
+            }
+        }
+    }
+}
+
+/// Parse a Sieve script into its list of commands. Only the grammar is checked, see
+/// [`validate_sieve`] for checks on the commands themselves.
+pub fn parse_sieve(input: &str) -> Result<Vec<Command>> {
+    let mut parser = Parser {
+        lexer: Lexer::new(input),
+        peeked: None,
+    };
+    parser.commands(false)
+}
+
+/// Commands and tests defined by extensions, along with the capability string that must be
+/// `require`d before using them.
+const EXTENSIONS: &[(&str, &str)] = &[
+    ("fileinto", "fileinto"),
+    ("reject", "reject"),
+    ("ereject", "ereject"),
+    ("envelope", "envelope"),
+    ("vacation", "vacation"),
+    ("setflag", "imap4flags"),
+    ("addflag", "imap4flags"),
+    ("removeflag", "imap4flags"),
+    ("hasflag", "imap4flags"),
+    ("body", "body"),
+    ("set", "variables"),
+    ("string", "variables"),
+    ("notify", "enotify"),
+    ("valid_notify_method", "enotify"),
+    ("notify_method_capability", "enotify"),
+    ("include", "include"),
+    ("return", "include"),
+    ("global", "include"),
+    ("duplicate", "duplicate"),
+    ("date", "date"),
+    ("currentdate", "date"),
+    ("mailboxexists", "mailbox"),
+    ("metadata", "mboxmetadata"),
+    ("metadataexists", "mboxmetadata"),
+    ("servermetadata", "servermetadata"),
+    ("servermetadataexists", "servermetadata"),
+    ("environment", "environment"),
+    ("spamtest", "spamtest"),
+    ("virustest", "virustest"),
+    ("ihave", "ihave"),
+    ("error", "ihave"),
+    ("foreverypart", "foreverypart"),
+    ("break", "foreverypart"),
+    ("redirect", ""),
+    ("keep", ""),
+    ("discard", ""),
+    ("stop", ""),
+];
+
+/// Control commands that take a block.
+const BLOCK_COMMANDS: &[&str] = &["if", "elsif", "else", "foreverypart"];
+
+fn check_tests(tests: &[Test], required: &[String]) -> Result<()> {
+    for test in tests {
+        let identifier = test.identifier.to_ascii_lowercase();
+        match identifier.as_str() {
+            "not" => {
+                if test.tests.len() != 1 || !test.arguments.is_empty() {
+                    return error(test.position, "`not` takes exactly one test");
+                }
+            }
+            "anyof" | "allof" => {
+                if test.tests.is_empty() || !test.arguments.is_empty() {
+                    return error(
+                        test.position,
+                        format_args!("`{}` takes a list of tests", identifier),
+                    );
+                }
+            }
+            _ => {
+                if !test.tests.is_empty() {
+                    return error(
+                        test.position,
+                        format_args!("test `{}` does not take nested tests", identifier),
+                    );
+                }
+            }
+        }
+        check_required(&identifier, test.position, required)?;
+        check_tests(&test.tests, required)?;
+    }
+    Ok(())
+}
+
+fn check_required(identifier: &str, position: Position, required: &[String]) -> Result<()> {
+    if let Some((_, capability)) = EXTENSIONS.iter().find(|(i, _)| *i == identifier) {
+        if !capability.is_empty() && !required.iter().any(|r| r == capability) {
+            return error(
+                position,
+                format_args!(
+                    "`{}` requires the `{}` extension; add `require \"{}\";` to the top of the script",
+                    identifier, capability, capability
+                ),
+            );
+        }
+    }
+    Ok(())
+}
+
+fn check_commands(commands: &[Command], required: &mut Vec<String>, top_level: bool) -> Result<()> {
+    let mut prev: Option<&str> = None;
+    let mut seen_non_require = false;
+    for cmd in commands {
+        let identifier = cmd.identifier.to_ascii_lowercase();
+        match identifier.as_str() {
+            "require" => {
+                if !top_level || seen_non_require {
+                    return error(cmd.position, "`require` must come before any other command");
+                }
+                match (cmd.arguments.as_slice(), cmd.tests.is_empty()) {
+                    ([Argument::StringList(capabilities)], true) => {
+                        required.extend(capabilities.iter().map(|c| c.to_ascii_lowercase()));
+                    }
+                    _ => {
+                        return error(cmd.position, "`require` takes a string list");
+                    }
+                }
+            }
+            "if" | "elsif" => {
+                if identifier == "elsif" && !matches!(prev, Some("if") | Some("elsif")) {
+                    return error(cmd.position, "`elsif` without a preceding `if`");
+                }
+                if cmd.tests.len() != 1 || !cmd.arguments.is_empty() {
+                    return error(
+                        cmd.position,
+                        format_args!("`{}` takes exactly one test", identifier),
+                    );
+                }
+            }
+            "else" => {
+                if !matches!(prev, Some("if") | Some("elsif")) {
+                    return error(cmd.position, "`else` without a preceding `if`");
+                }
+                if !cmd.tests.is_empty() || !cmd.arguments.is_empty() {
+                    return error(cmd.position, "`else` does not take a test");
+                }
+            }
+            _ => {
+                if !cmd.tests.is_empty() && !BLOCK_COMMANDS.contains(&identifier.as_str()) {
+                    return error(
+                        cmd.position,
+                        format_args!("command `{}` does not take a test", identifier),
+                    );
+                }
+            }
+        }
+        if identifier != "require" {
+            seen_non_require = true;
+        }
+        match (
+            BLOCK_COMMANDS.contains(&identifier.as_str()),
+            cmd.block.as_ref(),
+        ) {
+            (true, None) => {
+                return error(
+                    cmd.position,
+                    format_args!("`{}` must be followed by a block", identifier),
+                );
+            }
+            (false, Some(_)) => {
+                return error(
+                    cmd.position,
+                    format_args!("command `{}` does not take a block", identifier),
+                );
+            }
+            (true, Some(block)) => check_commands(block, required, false)?,
+            (false, None) => {}
+        }
+        check_required(&identifier, cmd.position, required)?;
+        check_tests(&cmd.tests, required)?;
+        prev = Some(match identifier.as_str() {
+            "if" => "if",
+            "elsif" => "elsif",
+            _ => "",
+        });
+    }
+    Ok(())
+}
+
+/// Check a script's syntax and the structure of its commands: `require` placement, `if`/`elsif`/
+/// `else` chains and usage of extensions without requiring them.
+pub fn validate_sieve(input: &str) -> Result<()> {
+    let commands = parse_sieve(input)?;
+    check_commands(&commands, &mut vec![], true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sieve_parse() {
+        let script = r#"# Example from RFC 5228
+require ["fileinto", "reject"];
+
+if size :over 1M
+        {
+        reject text:
+Please do not send me large attachments.
+Put your file on a server and send me the URL.
+Thank you.
+.. Fred
+.
+;
+        stop;
+        }
+
+/* Move messages from the list */
+if anyof (header :contains ["From", "Sender"] "owner-ietf-mta-filters@imc.org",
+          not exists "X-Spam") {
+    fileinto "filter";
+} elsif header :is "subject" "\"quoted\"" {
+    discard;
+} else {
+    keep;
+}
+"#;
+        let commands = parse_sieve(script).unwrap();
+        assert_eq!(commands.len(), 5);
+        assert_eq!(
+            commands[0].arguments,
+            vec![Argument::StringList(vec![
+                "fileinto".to_string(),
+                "reject".to_string()
+            ])]
+        );
+        assert_eq!(
+            commands[1].tests[0].arguments[0],
+            Argument::Tag("over".into())
+        );
+        assert_eq!(commands[1].tests[0].arguments[1], Argument::Number(1 << 20));
+        let reject = &commands[1].block.as_ref().unwrap()[0];
+        assert_eq!(
+            reject.arguments,
+            vec![Argument::StringList(vec![
+                "Please do not send me large attachments.\nPut your file on a server and send me the URL.\nThank you.\n. Fred\n".to_string()
+            ])]
+        );
+        assert_eq!(commands[2].tests[0].identifier, "anyof");
+        assert_eq!(commands[2].tests[0].tests.len(), 2);
+        assert_eq!(
+            commands[3].tests[0].arguments[2],
+            Argument::StringList(vec!["\"quoted\"".to_string()])
+        );
+        assert_eq!(
+            commands[3].position,
+            Position {
+                line: 20,
+                column: 3
+            }
+        );
+        validate_sieve(script).unwrap();
+    }
+
+    #[test]
+    fn test_sieve_errors() {
+        let err = |s: &str| validate_sieve(s).unwrap_err().details.to_string();
+        assert_eq!(
+            err("keep"),
+            "line 1, column 5: expected `;`, found end of script"
+        );
+        assert_eq!(
+            err("if true {\n keep;\n"),
+            "line 3, column 1: expected `}`, found end of script"
+        );
+        assert_eq!(
+            err("if header \"Subject\" \"x {\n keep;\n}"),
+            "line 1, column 21: unterminated string"
+        );
+        assert_eq!(
+            err("else { keep; }"),
+            "line 1, column 1: `else` without a preceding `if`"
+        );
+        assert_eq!(
+            err("keep;\nrequire \"fileinto\";"),
+            "line 2, column 1: `require` must come before any other command"
+        );
+        assert_eq!(
+            err("fileinto \"INBOX.spam\";"),
+            "line 1, column 1: `fileinto` requires the `fileinto` extension; add `require \"fileinto\";` to the top of the script"
+        );
+        assert_eq!(
+            err("if true;"),
+            "line 1, column 1: `if` must be followed by a block"
+        );
+        assert_eq!(
+            err("if anyof (true, false { keep; }"),
+            "line 1, column 23: expected `,` or `)`, found `{`"
+        );
+        assert_eq!(err("/* keep;"), "line 1, column 1: unterminated comment");
+        assert_eq!(err("keep; @"), "line 1, column 7: unexpected character `@`");
+        assert_eq!(
+            err("foreverypart { keep; }"),
+            "line 1, column 1: `foreverypart` requires the `foreverypart` extension; add `require \"foreverypart\";` to the top of the script"
+        );
+        validate_sieve(
+            r#"require ["foreverypart", "mime", "fileinto"];
+foreverypart :name "outer" {
+  if header :mime :type "Content-Type" "application" {
+    fileinto "Attachments";
+    break :name "outer";
+  }
+}
+"#,
+        )
+        .unwrap();
+        validate_sieve("").unwrap();
+        validate_sieve("# only a comment\n").unwrap();
+    }
+}
@@ -74,6 +74,7 @@
 use crate::connections::{lookup_ipv4, Connection};
 use crate::email::{parser::BytesExt, Address, Envelope};
 use crate::error::{MeliError, Result, ResultIntoMeliError};
+use crate::sasl::{SaslMechanism, SaslSession};
 use futures::io::{AsyncReadExt, AsyncWriteExt};
 use native_tls::TlsConnector;
 use smallvec::SmallVec;
@@ -137,6 +138,10 @@ pub enum SmtpAuth {
         password: Password,
         #[serde(default = "true_val")]
         require_auth: bool,
+        /// SASL mechanism to use. If not set, the most secure one the server advertises among
+        /// SCRAM-SHA-256, SCRAM-SHA-1, PLAIN and LOGIN is picked.
+        #[serde(default)]
+        mechanism: Option<SaslMechanism>,
         #[serde(skip_serializing, skip_deserializing, default)]
         auth_type: SmtpAuthType,
     },
@@ -151,8 +156,8 @@ pub enum SmtpAuth {
 
 #[derive(Debug, PartialEq, Clone, Serialize, Deserialize, Default)]
 pub struct SmtpAuthType {
-    plain: bool,
-    login: bool,
+    /// Mechanisms advertised by the server's `AUTH` extension.
+    mechanisms: Vec<String>,
 }
 
 fn true_val() -> bool {
@@ -426,13 +431,7 @@ impl SmtpConnection {
                     .filter(|l| l.starts_with("AUTH"))
                 {
                     let l = l["AUTH ".len()..].trim();
-                    for _type in l.split_whitespace() {
-                        if _type == "PLAIN" {
-                            auth_type.plain = true;
-                        } else if _type == "LOGIN" {
-                            auth_type.login = true;
-                        }
-                    }
+                    auth_type.mechanisms = l.split_whitespace().map(str::to_string).collect();
                     break;
                 }
             }
@@ -443,6 +442,7 @@ impl SmtpConnection {
                 SmtpAuth::Auto {
                     username,
                     password,
+                    mechanism,
                     auth_type,
                     ..
                 } => {
@@ -474,41 +474,72 @@ impl SmtpConnection {
                             output.stdout
                         }
                     };
-                    if auth_type.login {
-                        let username = username.to_string();
-                        ret.send_command(&[b"AUTH LOGIN"]).await?;
-                        ret.read_lines(&mut res, Some((ReplyCode::_334, &[])))
-                            .await
-                            .chain_err_kind(crate::error::ErrorKind::Authentication)?;
-                        let buf = base64::encode(&username);
-                        ret.send_command(&[buf.as_bytes()]).await?;
-                        ret.read_lines(&mut res, Some((ReplyCode::_334, &[])))
+                    let mechanism = match mechanism {
+                        Some(SaslMechanism::External) => {
+                            return Err(MeliError::new(
+                                "SMTP authentication with the EXTERNAL mechanism is not supported.",
+                            )
+                            .set_kind(crate::error::ErrorKind::Authentication));
+                        }
+                        Some(mechanism) => *mechanism,
+                        None => SaslMechanism::negotiate(
+                            auth_type.mechanisms.iter().map(String::as_str),
+                        )
+                        .unwrap_or(SaslMechanism::Plain),
+                    };
+                    let mut session = SaslSession::new(
+                        mechanism,
+                        username,
+                        &String::from_utf8_lossy(&password),
+                        &ret.server_conf.hostname,
+                        ret.server_conf.port,
+                    );
+                    // # RFC 4954 SMTP Service Extension for Authentication
+                    // "AUTH" SP sasl-mech [SP initial-response], where a zero-length initial
+                    // response is sent as "="
+                    match session.initial_response() {
+                        Some(initial_response) => {
+                            let initial_response = if initial_response.is_empty() {
+                                "=".to_string()
+                            } else {
+                                base64::encode(initial_response)
+                            };
+                            ret.send_command(&[
+                                b"AUTH ",
+                                mechanism.name().as_bytes(),
+                                b" ",
+                                initial_response.as_bytes(),
+                            ])
+                            .await?;
+                        }
+                        None => {
+                            ret.send_command(&[b"AUTH ", mechanism.name().as_bytes()])
+                                .await?;
+                        }
+                    }
+                    loop {
+                        let reply = ret
+                            .read_lines(&mut res, Some((ReplyCode::_235, &[ReplyCode::_334])))
                             .await
                             .chain_err_kind(crate::error::ErrorKind::Authentication)?;
-                        let buf = base64::encode(&password);
-                        ret.send_command(&[buf.as_bytes()]).await?;
-                    } else {
-                        // # RFC 4616 The PLAIN SASL Mechanism
-                        // # https://www.ietf.org/rfc/rfc4616.txt
-                        // message   = [authzid] UTF8NUL authcid UTF8NUL passwd
-                        // authcid   = 1*SAFE ; MUST accept up to 255 octets
-                        // authzid   = 1*SAFE ; MUST accept up to 255 octets
-                        // passwd    = 1*SAFE ; MUST accept up to 255 octets
-                        // UTF8NUL   = %x00 ; UTF-8 encoded NUL character
-                        let username_password = {
-                            let mut buf = Vec::with_capacity(2 + username.len() + password.len());
-                            buf.push(b'\0');
-                            buf.extend(username.as_bytes().to_vec());
-                            buf.push(b'\0');
-                            buf.extend(password);
-                            base64::encode(buf)
-                        };
-                        ret.send_command(&[b"AUTH PLAIN ", username_password.as_bytes()])
+                        if reply.code == ReplyCode::_235 {
+                            break;
+                        }
+                        let challenge =
+                            base64::decode(reply.lines.join("").trim()).map_err(|err| {
+                                MeliError::new(format!(
+                                    "Invalid {} challenge: {}",
+                                    mechanism,
+                                    reply.lines.join("")
+                                ))
+                                .set_source(Some(std::sync::Arc::new(err)))
+                                .set_kind(crate::error::ErrorKind::Authentication)
+                            })?;
+                        let response = session.step(&challenge)?;
+                        ret.send_command(&[base64::encode(response).as_bytes()])
                             .await?;
                     }
-                    ret.read_lines(&mut res, Some((ReplyCode::_235, &[])))
-                        .await
-                        .chain_err_kind(crate::error::ErrorKind::Authentication)?;
+                    session.finish()?;
                     ret.send_command(&[b"EHLO meli.delivery"]).await?;
                 }
                 SmtpAuth::XOAuth2 { token_command, .. } => {
@@ -619,6 +650,8 @@ impl SmtpConnection {
         if tos.is_empty() && envelope.cc().is_empty() && envelope.bcc().is_empty() {
             return Err(MeliError::new("SMTP submission was aborted because there was no e-mail address found in the To: header field. Consider adding recipients."));
         }
+        let envid =
+            crate::email::delivery_status::xtext_encode(envelope.message_id_display().as_bytes());
         let mut current_command: SmallVec<[&[u8]; 16]> = SmallVec::new();
         //first step in the procedure is the MAIL command.
         // MAIL FROM:<reverse-path> [SP <mail-parameters> ] <CRLF>
@@ -637,6 +670,12 @@ impl SmtpConnection {
         if self.server_conf.extensions.prdr {
             current_command.push(b" PRDR");
         }
+        /* Delivery status notifications carry the envelope id, which links them to the sent
+         * message. rfc3461 limits its length to 100 characters. */
+        if dsn_notify.is_some() && !envid.is_empty() && envid.len() <= 100 {
+            current_command.push(b" ENVID=");
+            current_command.push(envid.as_bytes());
+        }
         self.send_command(&current_command).await?;
         current_command.clear();
         if !self.server_conf.extensions.pipelining {
@@ -43,7 +43,37 @@ pub fn open_db(db_path: PathBuf) -> Result<Connection> {
     if !db_path.exists() {
         return Err(MeliError::new("Database doesn't exist"));
     }
-    Connection::open(&db_path).map_err(|e| MeliError::new(e.to_string()))
+    let conn = Connection::open(&db_path).map_err(|e| MeliError::new(e.to_string()))?;
+    #[cfg(feature = "regexp")]
+    add_regexp_function(&conn)?;
+    Ok(conn)
+}
+
+/// Defines the `REGEXP` operator, with the same semantics as `Query::Regex` terms.
+#[cfg(feature = "regexp")]
+fn add_regexp_function(conn: &Connection) -> Result<()> {
+    use rusqlite::functions::FunctionFlags;
+    conn.create_scalar_function(
+        "regexp",
+        2,
+        FunctionFlags::SQLITE_UTF8 | FunctionFlags::SQLITE_DETERMINISTIC,
+        move |ctx| {
+            let regex: std::sync::Arc<pcre2::bytes::Regex> =
+                ctx.get_or_create_aux(0, |vr| -> Result<_> {
+                    crate::search::build_regex(
+                        vr.as_str().map_err(|err| MeliError::new(err.to_string()))?,
+                    )
+                })?;
+            let text = ctx
+                .get_raw(1)
+                .as_str()
+                .map_err(|err| rusqlite::Error::UserFunctionError(err.into()))?;
+            regex
+                .is_match(text.as_bytes())
+                .map_err(|err| rusqlite::Error::UserFunctionError(err.into()))
+        },
+    )
+    .map_err(|e| MeliError::new(e.to_string()))
 }
 
 pub fn open_or_create_db(
@@ -159,3 +189,18 @@ impl FromSql for Envelope {
         .map_err(|e| FromSqlError::Other(Box::new(e)))?)
     }
 }
+
+#[cfg(feature = "regexp")]
+#[test]
+fn test_regexp_function() {
+    let conn = Connection::open_in_memory().unwrap();
+    add_regexp_function(&conn).unwrap();
+    let is_match = |pattern: &str, text: &str| -> bool {
+        conn.query_row("SELECT ?2 REGEXP ?1", params![pattern, text], |row| {
+            row.get(0)
+        })
+        .unwrap()
+    };
+    assert!(is_match("caf(e|é)", "CAFÉ meeting"));
+    assert!(!is_match("^meeting", "café meeting"));
+}
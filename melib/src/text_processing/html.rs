@@ -0,0 +1,1185 @@
+/*
+ * meli - text_processing crate.
+ *
+ * Copyright 2020 Manos Pitsidianakis
+ *
+ * This file is part of meli.
+ *
+ * meli is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * meli is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with meli. If not, see <http://www.gnu.org/licenses/>.
+ */
+
+//! Render HTML as text for terminal display.
+//!
+//! This is not a browser: scripts, styles and forms are dropped, and nothing (images,
+//! stylesheets, frames) is ever fetched. Headings, emphasis, lists, quoted blocks, preformatted
+//! text and tables are laid out to a given width, and links are numbered and listed at the end of
+//! the text.
+
+use super::grapheme_clusters::TextProcessing;
+use super::line_break::{LineBreakCandidate, LineBreakCandidateIter};
+use std::fmt;
+
+bitflags! {
+    #[derive(Default)]
+    pub struct HtmlStyle: u8 {
+        const BOLD      = 0b0000_0001;
+        const ITALIC    = 0b0000_0010;
+        const UNDERLINE = 0b0000_0100;
+        const LINK      = 0b0000_1000;
+        const CODE      = 0b0001_0000;
+    }
+}
+
+/// A line of rendered text, as runs of text with the same style.
+pub type HtmlLine = Vec<(String, HtmlStyle)>;
+
+/// The result of `html_to_text`.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct HtmlText {
+    pub lines: Vec<HtmlLine>,
+    /// Link targets; link `n` in the text is `links[n - 1]`.
+    pub links: Vec<String>,
+}
+
+impl fmt::Display for HtmlText {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for line in &self.lines {
+            for (text, _) in line {
+                write!(f, "{}", text)?;
+            }
+            writeln!(f)?;
+        }
+        Ok(())
+    }
+}
+
+/// Render `html` as lines of at most `width` columns, except for preformatted text.
+pub fn html_to_text(html: &str, width: usize) -> HtmlText {
+    let mut renderer = Renderer::new(std::cmp::max(width, 20), vec![]);
+    renderer.render(html);
+    renderer.finish()
+}
+
+#[derive(Debug)]
+enum Token<'s> {
+    Text(&'s str),
+    Tag(Tag),
+    /// Comments, declarations and elements that are not displayed.
+    Skip,
+}
+
+/// Read the token that starts at `pos`, returning it and the position after it.
+fn next_token<'s>(html: &'s str, pos: usize) -> (Token<'s>, usize) {
+    let rest = &html[pos..];
+    if !rest.starts_with('<') {
+        let end = rest.find('<').unwrap_or(rest.len());
+        return (Token::Text(&rest[..end]), pos + end);
+    }
+    if rest.starts_with("<!--") {
+        let len = rest.find("-->").map(|i| i + 3).unwrap_or(rest.len());
+        return (Token::Skip, pos + len);
+    }
+    if rest.starts_with("<!") || rest.starts_with("<?") {
+        let len = rest.find('>').map(|i| i + 1).unwrap_or(rest.len());
+        return (Token::Skip, pos + len);
+    }
+    match parse_tag(rest) {
+        Some((tag, len)) if !tag.is_end && RAW_TEXT_ELEMENTS.contains(&tag.name.as_str()) => {
+            /* Skip the contents of elements that are not displayed */
+            let rest = &html[pos + len..];
+            let closing = format!("</{}", tag.name);
+            let content_len = rest
+                .to_ascii_lowercase()
+                .find(&closing)
+                .map(|i| i + rest[i..].find('>').map(|j| j + 1).unwrap_or(0))
+                .unwrap_or(rest.len());
+            (Token::Skip, pos + len + content_len)
+        }
+        Some((tag, len)) => (Token::Tag(tag), pos + len),
+        None => (Token::Text("<"), pos + 1),
+    }
+}
+
+/// Elements whose content is skipped.
+const RAW_TEXT_ELEMENTS: &[&str] = &["head", "script", "style", "template", "title"];
+
+#[derive(Debug)]
+struct Tag {
+    name: String,
+    is_end: bool,
+    attributes: Vec<(String, String)>,
+}
+
+impl Tag {
+    fn attribute(&self, name: &str) -> Option<&str> {
+        self.attributes
+            .iter()
+            .find(|(n, _)| n == name)
+            .map(|(_, v)| v.as_str())
+    }
+}
+
+/// Parse a tag at the start of `input`, returning it and its length in bytes.
+fn parse_tag(input: &str) -> Option<(Tag, usize)> {
+    let bytes = input.as_bytes();
+    let mut pos = 1;
+    let is_end = bytes.get(pos) == Some(&b'/');
+    if is_end {
+        pos += 1;
+    }
+    if !bytes.get(pos)?.is_ascii_alphabetic() {
+        return None;
+    }
+    let name_start = pos;
+    while pos < bytes.len() && (bytes[pos].is_ascii_alphanumeric() || b":-".contains(&bytes[pos])) {
+        pos += 1;
+    }
+    let name = input[name_start..pos].to_ascii_lowercase();
+    let mut attributes = vec![];
+    loop {
+        while pos < bytes.len() && (bytes[pos].is_ascii_whitespace() || bytes[pos] == b'/') {
+            pos += 1;
+        }
+        if pos >= bytes.len() {
+            return Some((
+                Tag {
+                    name,
+                    is_end,
+                    attributes,
+                },
+                pos,
+            ));
+        }
+        if bytes[pos] == b'>' {
+            pos += 1;
+            break;
+        }
+        let attr_start = pos;
+        while pos < bytes.len()
+            && !(bytes[pos].is_ascii_whitespace() || b"=>/".contains(&bytes[pos]))
+        {
+            pos += 1;
+        }
+        let attr_name = input[attr_start..pos].to_ascii_lowercase();
+        while pos < bytes.len() && bytes[pos].is_ascii_whitespace() {
+            pos += 1;
+        }
+        let mut value = String::new();
+        if bytes.get(pos) == Some(&b'=') {
+            pos += 1;
+            while pos < bytes.len() && bytes[pos].is_ascii_whitespace() {
+                pos += 1;
+            }
+            match bytes.get(pos) {
+                Some(&quote) if quote == b'"' || quote == b'\'' => {
+                    let end = input[pos + 1..]
+                        .find(quote as char)
+                        .map(|i| pos + 1 + i)
+                        .unwrap_or(bytes.len());
+                    value = decode_entities(&input[pos + 1..end]);
+                    pos = std::cmp::min(end + 1, bytes.len());
+                }
+                _ => {
+                    let value_start = pos;
+                    while pos < bytes.len()
+                        && !(bytes[pos].is_ascii_whitespace() || bytes[pos] == b'>')
+                    {
+                        pos += 1;
+                    }
+                    value = decode_entities(&input[value_start..pos]);
+                }
+            }
+        }
+        if pos == attr_start {
+            /* Stray character, skip it. */
+            pos += 1;
+        } else {
+            attributes.push((attr_name, value));
+        }
+    }
+    Some((
+        Tag {
+            name,
+            is_end,
+            attributes,
+        },
+        pos,
+    ))
+}
+
+/// Replace character references (`&amp;`, `&#8217;`, `&#x2014;`) with the characters they stand
+/// for. Unknown references are left as they are.
+pub fn decode_entities(input: &str) -> String {
+    let mut ret = String::with_capacity(input.len());
+    let mut rest = input;
+    while let Some(amp) = rest.find('&') {
+        ret.push_str(&rest[..amp]);
+        rest = &rest[amp..];
+        let end = match rest[1..].find(|c: char| !c.is_ascii_alphanumeric() && c != '#') {
+            Some(i) => i + 1,
+            None => rest.len(),
+        };
+        let reference = &rest[1..end];
+        let decoded = if let Some(num) = reference.strip_prefix('#') {
+            if let Some(hex) = num.strip_prefix('x').or_else(|| num.strip_prefix('X')) {
+                u32::from_str_radix(hex, 16).ok()
+            } else {
+                num.parse::<u32>().ok()
+            }
+            .and_then(std::char::from_u32)
+        } else {
+            named_entity(reference)
+        };
+        match decoded {
+            Some(c) => {
+                ret.push(c);
+                rest = &rest[end..];
+                if rest.starts_with(';') {
+                    rest = &rest[1..];
+                }
+            }
+            None => {
+                ret.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    ret.push_str(rest);
+    ret
+}
+
+fn named_entity(name: &str) -> Option<char> {
+    Some(match name {
+        "amp" | "AMP" => '&',
+        "lt" | "LT" => '<',
+        "gt" | "GT" => '>',
+        "quot" | "QUOT" => '"',
+        "apos" => '\'',
+        "nbsp" => '\u{a0}',
+        "shy" => '\u{ad}',
+        "zwnj" => '\u{200c}',
+        "zwj" => '\u{200d}',
+        "ensp" => '\u{2002}',
+        "emsp" => '\u{2003}',
+        "thinsp" => '\u{2009}',
+        "ndash" => '–',
+        "mdash" => '—',
+        "lsquo" => '‘',
+        "rsquo" => '’',
+        "sbquo" => '‚',
+        "ldquo" => '“',
+        "rdquo" => '”',
+        "bdquo" => '„',
+        "laquo" => '«',
+        "raquo" => '»',
+        "lsaquo" => '‹',
+        "rsaquo" => '›',
+        "hellip" => '…',
+        "bull" => '•',
+        "middot" => '·',
+        "dagger" => '†',
+        "Dagger" => '‡',
+        "prime" => '′',
+        "copy" => '©',
+        "reg" => '®',
+        "trade" => '™',
+        "deg" => '°',
+        "plusmn" => '±',
+        "times" => '×',
+        "divide" => '÷',
+        "minus" => '−',
+        "frac12" => '½',
+        "frac14" => '¼',
+        "frac34" => '¾',
+        "sect" => '§',
+        "para" => '¶',
+        "euro" => '€',
+        "pound" => '£',
+        "yen" => '¥',
+        "cent" => '¢',
+        "iexcl" => '¡',
+        "iquest" => '¿',
+        "larr" => '←',
+        "rarr" => '→',
+        "uarr" => '↑',
+        "darr" => '↓',
+        _ => return None,
+    })
+}
+
+/// Elements that start a new line.
+const BLOCK_ELEMENTS: &[&str] = &[
+    "address",
+    "article",
+    "aside",
+    "blockquote",
+    "center",
+    "dd",
+    "details",
+    "div",
+    "dl",
+    "dt",
+    "fieldset",
+    "figcaption",
+    "figure",
+    "footer",
+    "form",
+    "h1",
+    "h2",
+    "h3",
+    "h4",
+    "h5",
+    "h6",
+    "header",
+    "hr",
+    "li",
+    "main",
+    "nav",
+    "ol",
+    "p",
+    "pre",
+    "section",
+    "summary",
+    "table",
+    "ul",
+];
+
+/// Block elements that are separated from their surroundings by an empty line.
+const PARAGRAPH_ELEMENTS: &[&str] = &[
+    "blockquote",
+    "dl",
+    "h1",
+    "h2",
+    "h3",
+    "h4",
+    "h5",
+    "h6",
+    "ol",
+    "p",
+    "pre",
+    "table",
+    "ul",
+];
+
+#[derive(Debug)]
+enum IndentKind {
+    Quote,
+    /// Indentation of `width` columns, with `marker` in front of the first line.
+    Pad {
+        width: usize,
+        marker: Option<String>,
+    },
+}
+
+#[derive(Debug)]
+struct Block {
+    tag: String,
+    indent: Option<IndentKind>,
+    /// Counter of an ordered list.
+    counter: Option<usize>,
+}
+
+/// A table cell: its HTML source and whether it is a header cell.
+type Cell<'s> = (&'s str, bool);
+
+#[derive(Debug)]
+struct Renderer {
+    width: usize,
+    lines: Vec<HtmlLine>,
+    links: Vec<String>,
+    /// Text of the current paragraph. Runs consisting of `"\n"` are line breaks.
+    inline: HtmlLine,
+    styles: Vec<(String, HtmlStyle)>,
+    blocks: Vec<Block>,
+    open_links: Vec<Option<String>>,
+    pre: usize,
+    blank_line_pending: bool,
+}
+
+impl Renderer {
+    fn new(width: usize, links: Vec<String>) -> Self {
+        Renderer {
+            width,
+            lines: vec![],
+            links,
+            inline: vec![],
+            styles: vec![],
+            blocks: vec![],
+            open_links: vec![],
+            pre: 0,
+            blank_line_pending: false,
+        }
+    }
+
+    fn render(&mut self, html: &str) {
+        let mut pos = 0;
+        while pos < html.len() {
+            let (token, next) = next_token(html, pos);
+            pos = next;
+            match token {
+                Token::Text(text) => self.text(&decode_entities(text)),
+                Token::Tag(tag) if tag.is_end => self.end_tag(&tag.name),
+                Token::Tag(tag) if tag.name == "table" => {
+                    let (content_end, table_end) = find_table_end(html, pos);
+                    self.table(&html[pos..content_end]);
+                    pos = table_end;
+                }
+                Token::Tag(tag) => self.start_tag(&tag),
+                Token::Skip => {}
+            }
+        }
+    }
+
+    fn style(&self) -> HtmlStyle {
+        self.styles
+            .iter()
+            .fold(HtmlStyle::empty(), |acc, (_, s)| acc | *s)
+    }
+
+    fn push_run(&mut self, text: &str, style: HtmlStyle) {
+        if text.is_empty() {
+            return;
+        }
+        match self.inline.last_mut() {
+            Some((last, last_style)) if *last_style == style && last != "\n" && text != "\n" => {
+                last.push_str(text);
+            }
+            _ => self.inline.push((text.to_string(), style)),
+        }
+    }
+
+    fn ends_with_space(&self) -> bool {
+        match self.inline.last() {
+            None => true,
+            Some((last, _)) => last.ends_with(' ') || last == "\n",
+        }
+    }
+
+    fn line_break(&mut self) {
+        self.push_run("\n", HtmlStyle::empty());
+    }
+
+    fn text(&mut self, text: &str) {
+        let style = self.style();
+        if self.pre > 0 {
+            let mut text = text.replace('\t', "    ").replace("\r\n", "\n");
+            if self.inline.is_empty() && text.starts_with('\n') {
+                /* A newline right after `<pre>` is not part of the content */
+                text.remove(0);
+            }
+            for (i, part) in text.split('\n').enumerate() {
+                if i > 0 {
+                    self.line_break();
+                }
+                let part = part.replace('\u{a0}', " ");
+                self.push_run(&part, style);
+            }
+            return;
+        }
+        let mut collapsed = String::with_capacity(text.len());
+        let mut space = self.ends_with_space();
+        for c in text.chars() {
+            if c.is_whitespace() && c != '\u{a0}' {
+                if !space {
+                    collapsed.push(' ');
+                    space = true;
+                }
+            } else if c == '\u{ad}' || c == '\u{200b}' {
+                /* Soft hyphens and zero width spaces */
+            } else {
+                collapsed.push(if c == '\u{a0}' { ' ' } else { c });
+                space = false;
+            }
+        }
+        self.push_run(&collapsed, style);
+    }
+
+    fn start_tag(&mut self, tag: &Tag) {
+        let name = tag.name.as_str();
+        if name == "br" {
+            self.line_break();
+            return;
+        }
+        if name == "img" {
+            match tag.attribute("alt").map(str::trim) {
+                Some(alt) if !alt.is_empty() => self.text(&format!("[{}]", alt)),
+                _ => {}
+            }
+            return;
+        }
+        if BLOCK_ELEMENTS.contains(&name) {
+            if name == "li" {
+                /* `<li>` closes the previous list item */
+                if let Some(pos) = self
+                    .blocks
+                    .iter()
+                    .rposition(|b| b.tag == "li" || b.tag == "ul" || b.tag == "ol")
+                {
+                    if self.blocks[pos].tag == "li" {
+                        self.end_tag("li");
+                    }
+                }
+            } else if name == "p" && self.blocks.last().map(|b| b.tag == "p").unwrap_or(false) {
+                self.end_tag("p");
+            }
+            self.end_paragraph(name);
+            /* The empty line before an indented block is not part of it */
+            self.emit_blank_line();
+            let mut block = Block {
+                tag: name.to_string(),
+                indent: None,
+                counter: None,
+            };
+            match name {
+                "blockquote" => block.indent = Some(IndentKind::Quote),
+                "ol" => {
+                    block.counter = Some(
+                        tag.attribute("start")
+                            .and_then(|s| s.trim().parse::<usize>().ok())
+                            .unwrap_or(1),
+                    );
+                    block.indent = self.list_indent();
+                }
+                "ul" => block.indent = self.list_indent(),
+                "dd" => {
+                    block.indent = Some(IndentKind::Pad {
+                        width: 4,
+                        marker: None,
+                    })
+                }
+                "li" => {
+                    let marker = match self
+                        .blocks
+                        .iter_mut()
+                        .rev()
+                        .find(|b| b.tag == "ul" || b.tag == "ol")
+                    {
+                        Some(Block {
+                            counter: Some(ref mut counter),
+                            ..
+                        }) => {
+                            *counter += 1;
+                            format!("{}. ", *counter - 1)
+                        }
+                        _ => "• ".to_string(),
+                    };
+                    block.indent = Some(IndentKind::Pad {
+                        width: std::cmp::max(marker.grapheme_width(), 2),
+                        marker: Some(marker),
+                    });
+                }
+                "hr" => {
+                    let width = self.width.saturating_sub(self.indent_width());
+                    let rule = "─".repeat(std::cmp::min(width, 72));
+                    self.emit_line(vec![(rule, HtmlStyle::empty())]);
+                    self.blank_line_pending = true;
+                    return;
+                }
+                "pre" => {
+                    self.pre += 1;
+                    self.styles.push((name.to_string(), HtmlStyle::CODE));
+                }
+                "h1" => self
+                    .styles
+                    .push((name.to_string(), HtmlStyle::BOLD | HtmlStyle::UNDERLINE)),
+                "h2" | "h3" | "h4" | "h5" | "h6" | "dt" => {
+                    self.styles.push((name.to_string(), HtmlStyle::BOLD))
+                }
+                _ => {}
+            }
+            self.blocks.push(block);
+            return;
+        }
+        let style = match name {
+            "b" | "strong" => HtmlStyle::BOLD,
+            "i" | "em" | "cite" | "var" | "dfn" => HtmlStyle::ITALIC,
+            "u" | "ins" => HtmlStyle::UNDERLINE,
+            "code" | "tt" | "kbd" | "samp" => HtmlStyle::CODE,
+            "a" => {
+                let href = tag
+                    .attribute("href")
+                    .map(str::trim)
+                    .filter(|href| {
+                        !href.is_empty()
+                            && !href.starts_with('#')
+                            && !href.to_ascii_lowercase().starts_with("javascript:")
+                    })
+                    .map(str::to_string);
+                let style = if href.is_some() {
+                    HtmlStyle::LINK
+                } else {
+                    HtmlStyle::empty()
+                };
+                self.open_links.push(href);
+                style
+            }
+            _ => return,
+        };
+        self.styles.push((name.to_string(), style));
+    }
+
+    fn end_tag(&mut self, name: &str) {
+        if let Some(pos) = self.styles.iter().rposition(|(tag, _)| tag == name) {
+            self.styles.truncate(pos);
+            if name == "a" {
+                if let Some(Some(href)) = self.open_links.pop() {
+                    let number = match self.links.iter().position(|l| *l == href) {
+                        Some(i) => i + 1,
+                        None => {
+                            self.links.push(href);
+                            self.links.len()
+                        }
+                    };
+                    self.push_run(&format!("[{}]", number), HtmlStyle::empty());
+                }
+            }
+        }
+        if let Some(pos) = self.blocks.iter().rposition(|b| b.tag == name) {
+            self.end_paragraph(name);
+            if name == "pre" {
+                self.pre = self.pre.saturating_sub(1);
+            }
+            self.blocks.truncate(pos);
+            if self.is_paragraph(name) {
+                self.blank_line_pending = true;
+            }
+        } else if BLOCK_ELEMENTS.contains(&name) {
+            self.end_paragraph(name);
+        }
+    }
+
+    fn list_indent(&self) -> Option<IndentKind> {
+        /* Nested lists are already indented by the marker of the item they are in */
+        if self.blocks.iter().any(|b| b.tag == "li") {
+            None
+        } else {
+            Some(IndentKind::Pad {
+                width: 2,
+                marker: None,
+            })
+        }
+    }
+
+    fn indent_width(&self) -> usize {
+        self.blocks
+            .iter()
+            .filter_map(|b| b.indent.as_ref())
+            .map(|indent| match indent {
+                IndentKind::Quote => 2,
+                IndentKind::Pad { width, .. } => *width,
+            })
+            .sum()
+    }
+
+    fn marker_pending(&self) -> bool {
+        self.blocks.iter().any(|b| {
+            matches!(
+                b.indent,
+                Some(IndentKind::Pad {
+                    marker: Some(_),
+                    ..
+                })
+            )
+        })
+    }
+
+    /// Finish the current paragraph before block element `name` starts or ends.
+    fn end_paragraph(&mut self, name: &str) {
+        self.flush();
+        if self.is_paragraph(name) {
+            self.blank_line_pending = true;
+        }
+    }
+
+    /// Whether block element `name` is separated from its surroundings by an empty line. Lists
+    /// nested in list items are not.
+    fn is_paragraph(&self, name: &str) -> bool {
+        PARAGRAPH_ELEMENTS.contains(&name)
+            && !((name == "ul" || name == "ol") && self.blocks.iter().any(|b| b.tag == "li"))
+    }
+
+    fn flush(&mut self) {
+        let inline = std::mem::take(&mut self.inline);
+        let width = self.width.saturating_sub(self.indent_width());
+        let mut lines = if self.pre > 0 {
+            split_hard_breaks(inline)
+        } else {
+            wrap(inline, width)
+        };
+        /* A line break at the end of a paragraph doesn't add an empty line */
+        while lines
+            .last()
+            .map(|l| l.iter().all(|(t, _)| t.trim().is_empty()))
+            .unwrap_or(false)
+        {
+            lines.pop();
+        }
+        if lines.is_empty() {
+            return;
+        }
+        for line in lines {
+            self.emit_line(line);
+        }
+    }
+
+    /// Add the empty line that separates the next paragraph from the previous one, if one is
+    /// pending.
+    fn emit_blank_line(&mut self) {
+        if self.blank_line_pending
+            && self
+                .lines
+                .last()
+                .map(|l| !l.iter().all(|(t, _)| t.trim().is_empty()))
+                .unwrap_or(false)
+        {
+            let prefix = self.prefix(false);
+            self.lines
+                .push(vec![(prefix.trim_end().to_string(), HtmlStyle::empty())]);
+        }
+        self.blank_line_pending = false;
+    }
+
+    fn emit_line(&mut self, line: HtmlLine) {
+        /* Paragraphs at the start of a list item begin on the line of its marker */
+        if !self.marker_pending() {
+            self.emit_blank_line();
+        }
+        self.blank_line_pending = false;
+        let mut ret = vec![];
+        let prefix = self.prefix(true);
+        if !prefix.is_empty() {
+            ret.push((prefix, HtmlStyle::empty()));
+        }
+        ret.extend(line.into_iter().filter(|(t, _)| !t.is_empty()));
+        self.lines.push(ret);
+    }
+
+    fn prefix(&mut self, take_markers: bool) -> String {
+        let mut prefix = String::new();
+        for block in self.blocks.iter_mut() {
+            match block.indent {
+                Some(IndentKind::Quote) => prefix.push_str("> "),
+                Some(IndentKind::Pad {
+                    width,
+                    ref mut marker,
+                }) => {
+                    let marker = if take_markers { marker.take() } else { None };
+                    match marker {
+                        Some(marker) => {
+                            let w = marker.grapheme_width();
+                            prefix.push_str(&marker);
+                            prefix.push_str(&" ".repeat(width.saturating_sub(w)));
+                        }
+                        None => prefix.push_str(&" ".repeat(width)),
+                    }
+                }
+                None => {}
+            }
+        }
+        prefix
+    }
+
+    /// Lay out the rows and cells of a table whose content is `html`. The content of each cell
+    /// is rendered on its own, so that tables used for page layout keep the structure of their
+    /// content.
+    fn table(&mut self, html: &str) {
+        self.end_paragraph("table");
+        let rows = split_table(html);
+        let columns = rows.iter().map(Vec::len).max().unwrap_or(0);
+        if columns == 0 {
+            return;
+        }
+        const SEPARATOR: &str = "  ";
+        let available = std::cmp::max(
+            self.width
+                .saturating_sub(self.indent_width())
+                .saturating_sub(SEPARATOR.len() * (columns - 1)),
+            columns,
+        );
+        /* The width each column needs to fit its content without wrapping, and the width of its
+         * longest word */
+        let mut widths = vec![0; columns];
+        let mut min_widths = vec![0; columns];
+        for row in rows.iter() {
+            for (i, (cell, _)) in row.iter().enumerate() {
+                let (lines, _) = Self::render_cell(cell, 1000, self.links.clone());
+                for line in lines {
+                    let text = line.iter().map(|(t, _)| t.as_str()).collect::<String>();
+                    widths[i] = std::cmp::max(widths[i], text.grapheme_width());
+                    for word in text.split_whitespace() {
+                        min_widths[i] = std::cmp::max(min_widths[i], word.grapheme_width());
+                    }
+                }
+            }
+        }
+        while widths.iter().sum::<usize>() > available {
+            /* Wrap the widest column that can be wrapped between words, if any */
+            let widest = (0..columns)
+                .filter(|i| widths[*i] > min_widths[*i])
+                .max_by_key(|i| widths[*i])
+                .or_else(|| {
+                    (0..columns)
+                        .filter(|i| widths[*i] > 1)
+                        .max_by_key(|i| widths[*i])
+                });
+            match widest {
+                Some(i) => widths[i] -= 1,
+                None => break,
+            }
+        }
+        let header_rows = rows
+            .iter()
+            .take_while(|row| row.iter().all(|(_, header)| *header))
+            .count();
+        let row_count = rows.len();
+        for (row_index, row) in rows.into_iter().enumerate() {
+            let mut cells: Vec<Vec<HtmlLine>> = vec![];
+            for (i, (cell, header)) in row.into_iter().enumerate() {
+                let (mut lines, links) =
+                    Self::render_cell(cell, widths[i], std::mem::take(&mut self.links));
+                self.links = links;
+                if header {
+                    for (_, style) in lines.iter_mut().flatten() {
+                        *style |= HtmlStyle::BOLD;
+                    }
+                }
+                cells.push(lines);
+            }
+            let height = cells.iter().map(Vec::len).max().unwrap_or(0);
+            for y in 0..height {
+                let mut line: HtmlLine = vec![];
+                for (x, width) in widths.iter().enumerate() {
+                    if x > 0 {
+                        line.push((SEPARATOR.to_string(), HtmlStyle::empty()));
+                    }
+                    let mut used = 0;
+                    if let Some(cell_line) = cells.get(x).and_then(|c| c.get(y)) {
+                        for (text, style) in cell_line {
+                            used += text.as_str().grapheme_width();
+                            line.push((text.clone(), *style));
+                        }
+                    }
+                    line.push((" ".repeat(width.saturating_sub(used)), HtmlStyle::empty()));
+                }
+                trim_end(&mut line);
+                self.emit_line(line);
+            }
+            if row_index + 1 == header_rows && header_rows < row_count {
+                let rule = widths
+                    .iter()
+                    .map(|w| "─".repeat(*w))
+                    .collect::<Vec<String>>()
+                    .join(SEPARATOR);
+                self.emit_line(vec![(rule, HtmlStyle::empty())]);
+            }
+        }
+        self.blank_line_pending = true;
+    }
+
+    /// Render the content of a table cell in `width` columns, numbering its links after `links`.
+    fn render_cell(html: &str, width: usize, links: Vec<String>) -> (Vec<HtmlLine>, Vec<String>) {
+        let mut renderer = Renderer::new(std::cmp::max(width, 1), links);
+        renderer.render(html);
+        renderer.flush();
+        (renderer.lines, renderer.links)
+    }
+
+    fn finish(mut self) -> HtmlText {
+        self.flush();
+        if !self.links.is_empty() {
+            self.blocks.clear();
+            self.blank_line_pending = true;
+            for (i, link) in self.links.clone().into_iter().enumerate() {
+                self.emit_line(vec![(format!("[{}] {}", i + 1, link), HtmlStyle::empty())]);
+            }
+        }
+        HtmlText {
+            lines: self.lines,
+            links: self.links,
+        }
+    }
+}
+
+/// Find the end of the table whose content starts at `pos`, returning the positions of its end
+/// tag and of the end of its end tag.
+fn find_table_end(html: &str, mut pos: usize) -> (usize, usize) {
+    let mut depth = 1;
+    while pos < html.len() {
+        let (token, next) = next_token(html, pos);
+        if let Token::Tag(ref tag) = token {
+            if tag.name == "table" {
+                if tag.is_end {
+                    depth -= 1;
+                    if depth == 0 {
+                        return (pos, next);
+                    }
+                } else {
+                    depth += 1;
+                }
+            }
+        }
+        pos = next;
+    }
+    (html.len(), html.len())
+}
+
+/// Split the content of a table in rows of cells, leaving nested tables in their cells.
+fn split_table<'s>(html: &'s str) -> Vec<Vec<Cell<'s>>> {
+    let mut rows: Vec<Vec<Cell<'s>>> = vec![];
+    let mut open_cell: Option<(usize, bool)> = None;
+    let mut depth = 0;
+    let mut pos = 0;
+    while pos < html.len() {
+        let (token, next) = next_token(html, pos);
+        if let Token::Tag(tag) = token {
+            match (tag.name.as_str(), tag.is_end) {
+                ("table", false) => depth += 1,
+                ("table", true) => depth -= 1,
+                ("tr", _) | ("td", _) | ("th", _) if depth == 0 => {
+                    if let Some((start, header)) = open_cell.take() {
+                        if rows.is_empty() {
+                            rows.push(vec![]);
+                        }
+                        rows.last_mut().unwrap().push((&html[start..pos], header));
+                    }
+                    match (tag.name.as_str(), tag.is_end) {
+                        ("tr", false) => rows.push(vec![]),
+                        ("td", false) => open_cell = Some((next, false)),
+                        ("th", false) => open_cell = Some((next, true)),
+                        _ => {}
+                    }
+                }
+                _ => {}
+            }
+        }
+        pos = next;
+    }
+    if let Some((start, header)) = open_cell {
+        if rows.is_empty() {
+            rows.push(vec![]);
+        }
+        rows.last_mut().unwrap().push((&html[start..], header));
+    }
+    rows.retain(|row| !row.is_empty());
+    rows
+}
+
+fn trim_end(line: &mut HtmlLine) {
+    while let Some((last, _)) = line.last_mut() {
+        let trimmed_len = last.trim_end().len();
+        if trimmed_len == 0 {
+            line.pop();
+        } else {
+            last.truncate(trimmed_len);
+            break;
+        }
+    }
+}
+
+fn split_hard_breaks(inline: HtmlLine) -> Vec<HtmlLine> {
+    let mut lines = vec![vec![]];
+    for (text, style) in inline {
+        if text == "\n" {
+            lines.push(vec![]);
+        } else {
+            lines.last_mut().unwrap().push((text, style));
+        }
+    }
+    lines
+}
+
+/// Wrap styled text in lines of `width` columns at the line break opportunities of the Unicode
+/// line breaking algorithm. Words wider than `width` are split.
+fn wrap(inline: HtmlLine, width: usize) -> Vec<HtmlLine> {
+    let width = std::cmp::max(width, 1);
+    let mut ret = vec![];
+    for runs in split_hard_breaks(inline) {
+        let mut text = String::new();
+        let mut spans = vec![];
+        for (t, style) in runs {
+            spans.push((text.len(), text.len() + t.len(), style));
+            text.push_str(&t);
+        }
+        let text_width = |start: usize, end: usize| text[start..end].trim_end().grapheme_width();
+        let mut breaks = vec![];
+        let mut start = 0;
+        let mut last_fit = None;
+        let mut candidates = LineBreakCandidateIter::new(&text)
+            .filter(|(_, c)| *c != LineBreakCandidate::NoBreak)
+            .map(|(offset, _)| offset)
+            .collect::<Vec<usize>>();
+        if candidates.last() != Some(&text.len()) {
+            candidates.push(text.len());
+        }
+        let mut i = 0;
+        while i < candidates.len() {
+            let offset = candidates[i];
+            if offset <= start {
+                i += 1;
+                continue;
+            }
+            if text_width(start, offset) <= width {
+                last_fit = Some(offset);
+                i += 1;
+                continue;
+            }
+            if let Some(fit) = last_fit.take() {
+                breaks.push((start, fit));
+                start = fit;
+                continue;
+            }
+            /* A single word that is wider than the line */
+            let mut end = start;
+            let mut w = 0;
+            for (idx, g) in text[start..offset].graphemes_indices() {
+                let gw = g.grapheme_width();
+                if w + gw > width && idx > 0 {
+                    break;
+                }
+                w += gw;
+                end = start + idx + g.len();
+            }
+            breaks.push((start, end));
+            start = end;
+        }
+        if start < text.len() || breaks.is_empty() {
+            breaks.push((start, text.len()));
+        }
+        for (start, end) in breaks {
+            let mut line = vec![];
+            for (s, e, style) in spans.iter() {
+                let (s, e) = (std::cmp::max(*s, start), std::cmp::min(*e, end));
+                if s < e {
+                    line.push((text[s..e].to_string(), *style));
+                }
+            }
+            trim_end(&mut line);
+            if let Some((first, _)) = line.first_mut() {
+                if first.starts_with(' ') {
+                    *first = first.trim_start().to_string();
+                }
+            }
+            ret.push(line);
+        }
+    }
+    ret
+}
+
+#[test]
+fn test_html_to_text() {
+    let text = html_to_text(
+        r#"<!DOCTYPE html><html><head><title>Newsletter</title>
+<style>p { color: red; }</style></head>
+<body><h1>Hello &amp; welcome</h1>
+<p>This is <b>bold</b>, <i>italic</i>   and
+a <a href="https://example.com/a">link</a>.</p>
+<script>alert("no");</script>
+<ul><li>one<li>two<ol start="3"><li>three</li></ol></li></ul>
+<blockquote><p>quoted text</p></blockquote>
+<pre>  fn main() {
+      x &lt; 1;
+  }</pre>
+<img src="https://tracker.example.com/pixel.gif"><img src="x.png" alt="Logo">
+<p>Again <a href="https://example.com/a">the link</a> and <a href="https://example.com/b">another</a>.</p>
+</body></html>"#,
+        80,
+    );
+    assert_eq!(
+        text.to_string(),
+        "Hello & welcome
+
+This is bold, italic and a link[1].
+
+  • one
+  • two
+    3. three
+
+> quoted text
+
+  fn main() {
+      x < 1;
+  }
+
+[Logo]
+
+Again the link[1] and another[2].
+
+[1] https://example.com/a
+[2] https://example.com/b
+"
+    );
+    assert_eq!(
+        text.links,
+        vec![
+            "https://example.com/a".to_string(),
+            "https://example.com/b".to_string()
+        ]
+    );
+    assert_eq!(
+        text.lines[0],
+        vec![(
+            "Hello & welcome".to_string(),
+            HtmlStyle::BOLD | HtmlStyle::UNDERLINE
+        )]
+    );
+    assert_eq!(
+        text.lines[2],
+        vec![
+            ("This is ".to_string(), HtmlStyle::empty()),
+            ("bold".to_string(), HtmlStyle::BOLD),
+            (", ".to_string(), HtmlStyle::empty()),
+            ("italic".to_string(), HtmlStyle::ITALIC),
+            (" and a ".to_string(), HtmlStyle::empty()),
+            ("link".to_string(), HtmlStyle::LINK),
+            ("[1].".to_string(), HtmlStyle::empty()),
+        ]
+    );
+
+    let text = html_to_text(
+        "<p>The quick brown fox jumps over the lazy dog, again and again and again.</p>",
+        20,
+    );
+    assert_eq!(
+        text.to_string(),
+        "The quick brown fox
+jumps over the lazy
+dog, again and again
+and again.
+"
+    );
+
+    let text = html_to_text(
+        "<table><tr><th>Name</th><th>Value</th></tr>
+<tr><td>a</td><td>1</td></tr>
+<tr><td>longer name</td><td>22</td></tr></table>",
+        80,
+    );
+    assert_eq!(
+        text.to_string(),
+        "Name         Value
+───────────  ─────
+a            1
+longer name  22
+"
+    );
+
+    /* Tables used for page layout keep the structure of their content */
+    let text = html_to_text(
+        "<table width=\"100%\"><tr><td><table><tr><td><h2>Title</h2>
+<ul><li>one</li></ul></td></tr></table></td></tr></table><p>after</p>",
+        80,
+    );
+    assert_eq!(text.to_string(), "Title\n\n  • one\n\nafter\n");
+    assert_eq!(text.lines[0], vec![("Title".to_string(), HtmlStyle::BOLD)]);
+}
@@ -1014,76 +1014,10 @@ mod alg {
 pub fn split_lines_reflow(text: &str, reflow: Reflow, width: Option<usize>) -> Vec<String> {
     match reflow {
         Reflow::FormatFlowed => {
-            /* rfc3676 - The Text/Plain Format and DelSp Parameters
-             * https://tools.ietf.org/html/rfc3676 */
-
             let mut ret = Vec::new();
-            /*
-             * - Split lines with indices using str::match_indices()
-             * - Iterate and reflow flow regions, and pass fixed regions through
-             */
-            let lines_indices: Vec<usize> = text.match_indices('\n').map(|(i, _)| i).collect();
-            let mut prev_index = 0;
-            let mut in_paragraph = false;
-            let mut paragraph_start = 0;
-
-            let mut prev_quote_depth = 0;
-            for i in &lines_indices {
-                let line = &text[prev_index..*i];
-                let mut trimmed = line.trim_start().lines().next().unwrap_or("");
-                let mut quote_depth = 0;
-                let p_str: usize = trimmed
-                    .as_bytes()
-                    .iter()
-                    .position(|&b| {
-                        if b != b'>' {
-                            /* position() is short-circuiting */
-                            true
-                        } else {
-                            quote_depth += 1;
-                            false
-                        }
-                    })
-                    .unwrap_or(0);
-                trimmed = &trimmed[p_str..];
-                if trimmed.starts_with(' ') {
-                    /* Remove space stuffing before checking for ending space character.
-                     * [rfc3676#section-4.4] */
-                    trimmed = &trimmed[1..];
-                }
-
-                if trimmed.ends_with(' ') {
-                    if !in_paragraph {
-                        in_paragraph = true;
-                        paragraph_start = prev_index;
-                    } else if prev_quote_depth == quote_depth {
-                        /* This becomes part of the paragraph we're in */
-                    } else {
-                        /*Malformed line, different quote depths can't be in the same paragraph. */
-                        let paragraph = &text[paragraph_start..prev_index];
-                        reflow_helper(&mut ret, paragraph, prev_quote_depth, in_paragraph, width);
-
-                        paragraph_start = prev_index;
-                    }
-                } else {
-                    if prev_quote_depth == quote_depth || !in_paragraph {
-                        let paragraph = &text[paragraph_start..*i];
-                        reflow_helper(&mut ret, paragraph, quote_depth, in_paragraph, width);
-                    } else {
-                        /*Malformed line, different quote depths can't be in the same paragraph. */
-                        let paragraph = &text[paragraph_start..prev_index];
-                        reflow_helper(&mut ret, paragraph, prev_quote_depth, in_paragraph, width);
-                        let paragraph = &text[prev_index..*i];
-                        reflow_helper(&mut ret, paragraph, quote_depth, false, width);
-                    }
-                    paragraph_start = *i;
-                    in_paragraph = false;
-                }
-                prev_quote_depth = quote_depth;
-                prev_index = *i;
+            for line in text.split('\n') {
+                reflow_quoted_line(&mut ret, line, width);
             }
-            let paragraph = &text[paragraph_start..text.len()];
-            reflow_helper(&mut ret, paragraph, prev_quote_depth, in_paragraph, width);
             ret
         }
         Reflow::All => {
@@ -1167,47 +1101,32 @@ fn split(ret: &mut Vec<String>, mut line: &str, width: usize) {
         line = &line[chop_index..];
     }
 }
-fn reflow_helper(
-    ret: &mut Vec<String>,
-    paragraph: &str,
-    quote_depth: usize,
-    in_paragraph: bool,
-    width: Option<usize>,
-) {
-    if quote_depth > 0 {
-        let quotes: String = ">".repeat(quote_depth);
-        let paragraph = paragraph
-            .trim_start_matches(&quotes)
-            .replace(&format!("\n{}", &quotes), "")
-            .replace("\n", "")
-            .replace("\r", "");
-        if in_paragraph {
-            if let Some(width) = width {
-                ret.extend(
-                    linear(&paragraph, width.saturating_sub(quote_depth))
-                        .into_iter()
-                        .map(|l| format!("{}{}", &quotes, l)),
-                );
-            } else {
-                ret.push(format!("{}{}", &quotes, &paragraph));
-            }
-        } else {
-            ret.push(format!("{}{}", &quotes, &paragraph));
+/// Break a paragraph of text decoded from `format=flowed`
+/// ([RFC3676](https://tools.ietf.org/html/rfc3676)) to `width`, repeating its quote marks on
+/// every line.
+fn reflow_quoted_line<E: Extend<String>>(ret: &mut E, line: &str, width: Option<usize>) {
+    let line = line.trim_end_matches('\r');
+    let width = match width {
+        Some(width) if line.grapheme_width() > width => width,
+        _ => {
+            ret.extend(Some(line.to_string()));
+            return;
         }
+    };
+    let quote_depth = line.bytes().take_while(|&b| b == b'>').count();
+    let (quotes, paragraph) = if quote_depth > 0 && line[quote_depth..].starts_with(' ') {
+        line.split_at(quote_depth + 1)
     } else {
-        let paragraph = paragraph.replace("\n", "").replace("\r", "");
-
-        if in_paragraph {
-            if let Some(width) = width {
-                let ex = linear(&paragraph, width);
-                ret.extend(ex.into_iter());
-            } else {
-                ret.push(paragraph);
-            }
-        } else {
-            ret.push(paragraph);
-        }
-    }
+        line.split_at(quote_depth)
+    };
+    ret.extend(
+        linear(
+            paragraph,
+            std::cmp::max(width.saturating_sub(quotes.len()), 1),
+        )
+        .into_iter()
+        .map(|l| format!("{}{}", quotes, l.trim_end())),
+    );
 }
 
 #[test]
@@ -1246,6 +1165,32 @@ easy to take MORE than nothing.'"#;
     }
 }
 
+#[test]
+fn test_reflow_format_flowed() {
+    let text = ">> You mean you can't take LESS, it's very easy to take MORE than nothing.\n\
+                >\n\
+                > Short line.\n\
+                Take some more tea.";
+    let lines = split_lines_reflow(text, Reflow::FormatFlowed, Some(30));
+    assert!(lines.len() > 4);
+    assert!(lines.iter().all(|l| l.grapheme_width() <= 30));
+    assert!(lines[..lines.len() - 3]
+        .iter()
+        .all(|l| l.starts_with(">> ")));
+    assert_eq!(
+        &lines[lines.len() - 3..],
+        &[">", "> Short line.", "Take some more tea."]
+    );
+    assert_eq!(
+        lines[..lines.len() - 3]
+            .iter()
+            .map(|l| &l[">> ".len()..])
+            .collect::<Vec<&str>>()
+            .join(" "),
+        "You mean you can't take LESS, it's very easy to take MORE than nothing."
+    );
+}
+
 mod segment_tree {
     /*! Simple segment tree implementation for maximum in range queries. This is useful if given an
      *  array of numbers you want to get the maximum value inside an interval quickly.
@@ -1462,117 +1407,11 @@ impl Iterator for LineBreakText {
         }
         match self.state {
             ReflowState::FormatFlowed { ref mut cur_index } => {
-                /* rfc3676 - The Text/Plain Format and DelSp Parameters
-                 * https://tools.ietf.org/html/rfc3676 */
-
-                /*
-                 * - Split lines with indices using str::match_indices()
-                 * - Iterate and reflow flow regions, and pass fixed regions through
-                 */
-                self.paragraph_start_index = *cur_index;
-                let line_indices_iter = self.text[*cur_index..].match_indices('\n').map(|(i, _)| i);
-                let start_offset = *cur_index;
-                let mut prev_index = *cur_index;
-                let mut in_paragraph = false;
-                let mut paragraph_start = *cur_index;
-
-                let mut prev_quote_depth = 0;
+                let line = self.text[*cur_index..].split('\n').next().unwrap_or("");
+                *cur_index += line.len() + 1;
                 let mut paragraph = VecDeque::new();
-                for i in line_indices_iter {
-                    let i = i + start_offset + 1;
-                    let line = &self.text[prev_index..i];
-                    let mut trimmed = line.trim_start().lines().next().unwrap_or("");
-                    let mut quote_depth = 0;
-                    let p_str: usize = trimmed
-                        .as_bytes()
-                        .iter()
-                        .position(|&b| {
-                            if b != b'>' {
-                                /* position() is short-circuiting */
-                                true
-                            } else {
-                                quote_depth += 1;
-                                false
-                            }
-                        })
-                        .unwrap_or(0);
-                    trimmed = &trimmed[p_str..];
-                    if trimmed.starts_with(' ') {
-                        /* Remove space stuffing before checking for ending space character.
-                         * [rfc3676#section-4.4] */
-                        trimmed = &trimmed[1..];
-                    }
-
-                    if trimmed.ends_with(' ') {
-                        if !in_paragraph {
-                            in_paragraph = true;
-                            paragraph_start = prev_index;
-                        } else if prev_quote_depth == quote_depth {
-                            /* This becomes part of the paragraph we're in */
-                        } else {
-                            /*Malformed line, different quote depths can't be in the same paragraph. */
-                            let paragraph_s = &self.text[paragraph_start..prev_index];
-                            reflow_helper2(
-                                &mut paragraph,
-                                paragraph_s,
-                                prev_quote_depth,
-                                in_paragraph,
-                                self.width,
-                            );
-
-                            paragraph_start = prev_index;
-                        }
-                    } else {
-                        if prev_quote_depth == quote_depth || !in_paragraph {
-                            let paragraph_s = &self.text[paragraph_start..i];
-                            reflow_helper2(
-                                &mut paragraph,
-                                paragraph_s,
-                                quote_depth,
-                                in_paragraph,
-                                self.width,
-                            );
-                        } else {
-                            /*Malformed line, different quote depths can't be in the same paragraph. */
-                            let paragraph_s = &self.text[paragraph_start..prev_index];
-                            reflow_helper2(
-                                &mut paragraph,
-                                paragraph_s,
-                                prev_quote_depth,
-                                in_paragraph,
-                                self.width,
-                            );
-                            let paragraph_s = &self.text[prev_index..i];
-                            reflow_helper2(
-                                &mut paragraph,
-                                paragraph_s,
-                                quote_depth,
-                                false,
-                                self.width,
-                            );
-                        }
-                        *cur_index = i;
-                        std::mem::swap(&mut self.paragraph, &mut paragraph);
-                        paragraph_start = i;
-                        in_paragraph = false;
-                        break;
-                    }
-                    *cur_index = i;
-                    prev_quote_depth = quote_depth;
-                    prev_index = i;
-                }
-                if in_paragraph {
-                    let paragraph_s = &self.text[paragraph_start..self.text.len()];
-                    *cur_index = self.text.len();
-                    reflow_helper2(
-                        &mut paragraph,
-                        paragraph_s,
-                        prev_quote_depth,
-                        in_paragraph,
-                        self.width,
-                    );
-                    self.paragraph = paragraph;
-                }
+                reflow_quoted_line(&mut paragraph, line, self.width);
+                self.paragraph = paragraph;
                 return self.paragraph.pop_front();
             }
             ReflowState::AllWidth {
@@ -1751,46 +1590,3 @@ impl Iterator for LineBreakText {
         }
     }
 }
-
-fn reflow_helper2(
-    ret: &mut VecDeque<String>,
-    paragraph: &str,
-    quote_depth: usize,
-    in_paragraph: bool,
-    width: Option<usize>,
-) {
-    if quote_depth > 0 {
-        let quotes: String = ">".repeat(quote_depth);
-        let paragraph = paragraph
-            .trim_start_matches(&quotes)
-            .replace(&format!("\n{}", &quotes), "")
-            .replace("\n", "")
-            .replace("\r", "");
-        if in_paragraph {
-            if let Some(width) = width {
-                ret.extend(
-                    linear(&paragraph, width.saturating_sub(quote_depth))
-                        .into_iter()
-                        .map(|l| format!("{}{}", &quotes, l)),
-                );
-            } else {
-                ret.push_back(format!("{}{}", &quotes, &paragraph));
-            }
-        } else {
-            ret.push_back(format!("{}{}", &quotes, &paragraph));
-        }
-    } else {
-        let paragraph = paragraph.replace("\n", "").replace("\r", "");
-
-        if in_paragraph {
-            if let Some(width) = width {
-                let ex = linear(&paragraph, width);
-                ret.extend(ex.into_iter());
-            } else {
-                ret.push_back(paragraph);
-            }
-        } else {
-            ret.push_back(paragraph);
-        }
-    }
-}
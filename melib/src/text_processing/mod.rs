@@ -20,6 +20,7 @@
  */
 
 pub mod grapheme_clusters;
+pub mod html;
 pub mod line_break;
 pub mod search;
 mod tables;
@@ -126,6 +126,8 @@ impl From<&str> for LineBreakClass {
 pub enum Reflow {
     No,
     All,
+    /// Each line is a paragraph decoded from `format=flowed` text, broken to the width with its
+    /// quote marks repeated.
     FormatFlowed,
 }
 
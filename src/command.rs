@@ -42,6 +42,7 @@ pub mod history;
 pub use crate::actions::AccountAction::{self, *};
 pub use crate::actions::Action::{self, *};
 pub use crate::actions::ComposeAction::{self, *};
+pub use crate::actions::FiltersAction::{self, *};
 pub use crate::actions::ListingAction::{self, *};
 pub use crate::actions::MailingListAction::{self, *};
 pub use crate::actions::TabAction::{self, *};
@@ -608,6 +609,19 @@ Alternatives(&[to_stream!(One(Literal("add-attachment")), One(Filepath)), to_str
                       }
                   )
                 },
+                { tags: ["send-at "],
+                  desc: "send-at DATETIME, queue the draft in the outbox to be sent at DATETIME (+30m, +2h, +1d, HH:MM or YYYY-MM-DD HH:MM)",
+                  tokens: &[One(Literal("send-at")), One(QuotedStringValue)],
+                  parser:(
+                      fn send_at(input: &[u8]) -> IResult<&[u8], Action> {
+                          let (input, _) = tag("send-at")(input.trim())?;
+                          let (input, _) = is_a(" ")(input)?;
+                          let (input, datetime) = map_res(not_line_ending, std::str::from_utf8)(input)?;
+                          let (input, _) = eof(input)?;
+                          Ok((input, Compose(SendAt(datetime.trim().to_string()))))
+                      }
+                  )
+                },
                 { tags: ["toggle sign "],
                   desc: "switch between sign/unsign for this draft",
                   tokens: &[One(Literal("toggle")), One(Literal("sign"))],
@@ -621,6 +635,19 @@ Alternatives(&[to_stream!(One(Literal("add-attachment")), One(Filepath)), to_str
                       }
                   )
                 },
+                { tags: ["toggle read-receipt"],
+                  desc: "toggle asking for a read receipt for this draft",
+                  tokens: &[One(Literal("toggle")), One(Literal("read-receipt"))],
+                  parser:(
+                      fn toggle_read_receipt(input: &[u8]) -> IResult<&[u8], Action> {
+                          let (input, _) = tag("toggle")(input)?;
+                          let (input, _) = is_a(" ")(input)?;
+                          let (input, _) = tag("read-receipt")(input)?;
+                          let (input, _) = eof(input)?;
+                          Ok((input, Compose(ToggleReadReceipt)))
+                      }
+                  )
+                },
                 { tags: ["toggle encrypt"],
                   desc: "toggle encryption for this draft",
                   tokens: &[One(Literal("toggle")), One(Literal("encrypt"))],
@@ -724,6 +751,60 @@ Alternatives(&[to_stream!(One(Literal("add-attachment")), One(Filepath)), to_str
                       }
                   )
                 },
+                { tags: ["apply-rules "],
+                  desc: "apply-rules ACCOUNT MAILBOX_PATH, run the account's filtering rules over a mailbox",
+                  tokens: &[One(Literal("apply-rules")), One(AccountName), One(MailboxPath)],
+                  parser:(
+                      fn apply_rules(input: &[u8]) -> IResult<&[u8], Action> {
+                          let (input, _) = tag("apply-rules")(input.trim())?;
+                          let (input, _) = is_a(" ")(input)?;
+                          let (input, account) = quoted_argument(input)?;
+                          let (input, _) = is_a(" ")(input)?;
+                          let (input, path) = quoted_argument(input)?;
+                          let (input, _) = eof(input)?;
+                          Ok( (input, AccountAction(account.to_string(), ApplyRules(path.to_string()))))
+                      }
+                  )
+                },
+                { tags: ["manage-filters "],
+                  desc: "manage-filters ACCOUNT, edit the account's Sieve scripts in a new tab",
+                  tokens: &[One(Literal("manage-filters")), One(AccountName)],
+                  parser:(
+                      fn manage_filters(input: &[u8]) -> IResult<&[u8], Action> {
+                          let (input, _) = tag("manage-filters")(input.trim())?;
+                          let (input, _) = is_a(" ")(input)?;
+                          let (input, account) = quoted_argument(input)?;
+                          let (input, _) = eof(input)?;
+                          Ok( (input, AccountAction(account.to_string(), ManageFilters)))
+                      }
+                  )
+                },
+                { tags: ["manage-outbox "],
+                  desc: "manage-outbox ACCOUNT, view the account's scheduled and unsent messages in a new tab",
+                  tokens: &[One(Literal("manage-outbox")), One(AccountName)],
+                  parser:(
+                      fn manage_outbox(input: &[u8]) -> IResult<&[u8], Action> {
+                          let (input, _) = tag("manage-outbox")(input.trim())?;
+                          let (input, _) = is_a(" ")(input)?;
+                          let (input, account) = quoted_argument(input)?;
+                          let (input, _) = eof(input)?;
+                          Ok( (input, AccountAction(account.to_string(), ManageOutbox)))
+                      }
+                  )
+                },
+                { tags: ["new-filter "],
+                  desc: "new-filter NAME, create a new Sieve script in the filters tab",
+                  tokens: &[One(Literal("new-filter")), One(QuotedStringValue)],
+                  parser:(
+                      fn new_filter(input: &[u8]) -> IResult<&[u8], Action> {
+                          let (input, _) = tag("new-filter")(input.trim())?;
+                          let (input, _) = is_a(" ")(input)?;
+                          let (input, name) = quoted_argument(input)?;
+                          let (input, _) = eof(input)?;
+                          Ok( (input, Filters(NewScript(name.to_string()))))
+                      }
+                  )
+                },
                 { tags: ["open-in-tab"],
                   desc: "opens envelope view in new tab",
                   tokens: &[One(Literal("open-in-tab"))],
@@ -750,6 +831,41 @@ Alternatives(&[to_stream!(One(Literal("add-attachment")), One(Filepath)), to_str
                       }
                   )
                 },
+                /* Pipe decoded attachment to binary */
+                { tags: ["pipe-attachment "],
+                  desc: "pipe-attachment INDEX EXECUTABLE ARGS",
+                  tokens: &[One(Literal("pipe-attachment")), One(AttachmentIndexValue), One(Filepath), ZeroOrMore(QuotedStringValue)],
+                  parser:(
+                      fn pipe_attachment<'a>(input: &'a [u8]) -> IResult<&'a [u8], Action> {
+                          alt((
+                                  |input: &'a [u8]| -> IResult<&'a [u8], Action> {
+                                      let (input, _) = tag("pipe-attachment")(input.trim())?;
+                                      let (input, _) = is_a(" ")(input)?;
+                                      let (input, idx) = map_res(quoted_argument, usize::from_str)(input)?;
+                                      let (input, _) = is_a(" ")(input)?;
+                                      let (input, bin) = quoted_argument(input)?;
+                                      let (input, _) = is_a(" ")(input)?;
+                                      let (input, args) = separated_list1(is_a(" "), quoted_argument)(input)?;
+                                      let (input, _) = eof(input)?;
+                                      Ok((input, {
+                                          View(PipeAttachment(idx, bin.to_string(), args.into_iter().map(String::from).collect::<Vec<String>>()))
+                                      }))
+                                  },
+                                  |input: &'a [u8]| -> IResult<&'a [u8], Action> {
+                                      let (input, _) = tag("pipe-attachment")(input.trim())?;
+                                      let (input, _) = is_a(" ")(input)?;
+                                      let (input, idx) = map_res(quoted_argument, usize::from_str)(input)?;
+                                      let (input, _) = is_a(" ")(input)?;
+                                      let (input, bin) = quoted_argument(input.trim())?;
+                                      let (input, _) = eof(input)?;
+                                      Ok((input, {
+                                          View(PipeAttachment(idx, bin.to_string(), Vec::new()))
+                                      }))
+                                  }
+                          ))(input)
+                      }
+                  )
+                },
                 { tags: ["export-mail "],
                   desc: "export-mail PATH",
                   tokens: &[One(Literal("export-mail")), One(Filepath)],
@@ -851,6 +967,17 @@ Alternatives(&[to_stream!(One(Literal("add-attachment")), One(Filepath)), to_str
                       }
                   )
                 },
+                { tags: ["unified-inbox"],
+                  desc: "open the unified inbox of the mailboxes in listing.unified_mailboxes",
+                  tokens: &[One(Literal("unified-inbox"))],
+                  parser:(
+                      fn unified_inbox(input: &[u8]) -> IResult<&[u8], Action> {
+                          let (input, _) = tag("unified-inbox")(input.trim())?;
+                          let (input, _) = eof(input.trim())?;
+                          Ok((input, UnifiedInbox))
+                      }
+                  )
+                },
                 { tags: ["reload-config"],
                   desc: "reload configuration file",
                   tokens: &[One(Literal("reload-config"))],
@@ -923,17 +1050,36 @@ fn compose_action(input: &[u8]) -> IResult<&[u8], Action> {
         remove_attachment,
         toggle_sign,
         toggle_encrypt,
+        toggle_read_receipt,
         save_draft,
+        send_at,
+    ))(input)
+}
+
+fn mailbox_action(input: &[u8]) -> IResult<&[u8], Action> {
+    alt((
+        create_mailbox,
+        sub_mailbox,
+        unsub_mailbox,
+        delete_mailbox,
+        rename_mailbox,
     ))(input)
 }
 
 fn account_action(input: &[u8]) -> IResult<&[u8], Action> {
-    alt((reindex, print_account_setting))(input)
+    alt((
+        reindex,
+        apply_rules,
+        manage_filters,
+        manage_outbox,
+        print_account_setting,
+    ))(input)
 }
 
 fn view(input: &[u8]) -> IResult<&[u8], Action> {
     alt((
         filter,
+        pipe_attachment,
         pipe,
         save_attachment,
         export_mail,
@@ -953,14 +1099,12 @@ pub fn parse_command(input: &[u8]) -> Result<Action, MeliError> {
         printenv,
         view,
         compose_action,
-        create_mailbox,
-        sub_mailbox,
-        unsub_mailbox,
-        delete_mailbox,
-        rename_mailbox,
+        mailbox_action,
         account_action,
+        new_filter,
         print_setting,
         toggle_mouse,
+        unified_inbox,
         reload_config,
         quit,
     ))(input)
@@ -1014,6 +1158,7 @@ fn test_parser() {
             "toggle mouse".to_string(),
             "toggle sign".to_string(),
             "toggle encrypt".to_string(),
+            "toggle read-receipt".to_string(),
             "toggle thread_snooze".to_string()
         ])
         .collect(),
@@ -75,6 +75,7 @@ pub enum ViewAction {
     Pipe(String, Vec<String>),
     Filter(String),
     SaveAttachment(usize, String),
+    PipeAttachment(usize, String, Vec<String>),
     ExportMail(String),
     AddAddressesToContacts,
 }
@@ -86,6 +87,8 @@ pub enum ComposeAction {
     AddAttachmentPipe(String),
     RemoveAttachment(usize),
     SaveDraft,
+    SendAt(String),
+    ToggleReadReceipt,
     ToggleSign,
     ToggleEncrypt,
 }
@@ -94,6 +97,14 @@ pub enum ComposeAction {
 pub enum AccountAction {
     ReIndex,
     PrintAccountSetting(String),
+    ManageFilters,
+    ManageOutbox,
+    ApplyRules(MailboxPath),
+}
+
+#[derive(Debug)]
+pub enum FiltersAction {
+    NewScript(String),
 }
 
 #[derive(Debug)]
@@ -121,7 +132,11 @@ pub enum Action {
     Compose(ComposeAction),
     Mailbox(AccountName, MailboxOperation),
     AccountAction(AccountName, AccountAction),
+    Filters(FiltersAction),
     PrintSetting(String),
+    UnifiedInbox,
+    /// Copy text to the terminal's clipboard.
+    CopyToClipboard(String),
     ReloadConfiguration,
     ToggleMouse,
     Quit,
@@ -143,7 +158,10 @@ impl Action {
             Action::Compose(_) => false,
             Action::Mailbox(_, _) => true,
             Action::AccountAction(_, _) => false,
+            Action::Filters(_) => false,
             Action::PrintSetting(_) => false,
+            Action::UnifiedInbox => false,
+            Action::CopyToClipboard(_) => false,
             Action::ToggleMouse => false,
             Action::Quit => true,
             Action::ReloadConfiguration => false,
@@ -21,6 +21,7 @@
 
 use super::*;
 use melib::email::attachment_types::{ContentType, MultipartType};
+use melib::email::compose::NewsFollowup;
 use melib::list_management;
 use melib::Draft;
 
@@ -52,7 +53,7 @@ enum Cursor {
 }
 
 #[derive(Debug)]
-enum EmbedStatus {
+pub(crate) enum EmbedStatus {
     Stopped(Arc<Mutex<EmbedTerminal>>, File),
     Running(Arc<Mutex<EmbedTerminal>>, File),
 }
@@ -93,6 +94,8 @@ pub struct Composer {
     embed: Option<EmbedStatus>,
     #[cfg(feature = "gpgme")]
     gpg_state: gpg::GpgComposeState,
+    /// Queue the draft in the outbox to be sent at this time instead of sending it now.
+    send_at: Option<UnixTimestamp>,
     dirty: bool,
     has_changes: bool,
     initialized: bool,
@@ -137,7 +140,12 @@ impl fmt::Display for Composer {
         if self.reply_context.is_some() {
             write!(
                 f,
-                "reply: {}",
+                "{}: {}",
+                if self.draft.is_news() {
+                    "follow-up"
+                } else {
+                    "reply"
+                },
                 (&self.draft.headers()["Subject"]).trim_at_boundary(8)
             )
         } else {
@@ -161,6 +169,7 @@ impl Composer {
             mode: ViewMode::Edit,
             #[cfg(feature = "gpgme")]
             gpg_state: gpg::GpgComposeState::new(),
+            send_at: None,
             dirty: true,
             has_changes: false,
             embed_area: ((0, 0), (0, 0)),
@@ -193,6 +202,15 @@ impl Composer {
             ret.pager
                 .set_reflow(melib::text_processing::Reflow::FormatFlowed);
         }
+        /* New messages of news accounts are articles posted to newsgroups. */
+        if context.accounts[&account_hash].settings.account.format() == "nntp" {
+            ret.draft.set_news_mode(Some(&[]));
+        } else if *account_settings!(context[account_hash].composing.request_read_receipt) {
+            ret.draft.set_header(
+                "Disposition-Notification-To",
+                get_display_name(context, account_hash),
+            );
+        }
         ret
     }
 
@@ -211,6 +229,12 @@ impl Composer {
         Ok(ret)
     }
 
+    pub fn with_draft(account_hash: AccountHash, draft: Draft, context: &Context) -> Self {
+        let mut ret = Composer::with_account(account_hash, context);
+        ret.draft = draft;
+        ret
+    }
+
     pub fn reply_to(
         coordinates: (AccountHash, MailboxHash, EnvelopeHash),
         reply_body: String,
@@ -264,10 +288,32 @@ impl Composer {
             }
         }
 
+        /* Follow-ups to news articles are posted to the newsgroups in `Followup-To` or
+         * `Newsgroups`, unless the poster asked for replies by e-mail. Replies to the author are
+         * always sent by e-mail. Only articles of news accounts are treated this way; e-mail
+         * carrying a `Newsgroups` header is replied to by e-mail. */
+        let followup = if reply_to_all && account.settings.account().format() == "nntp" {
+            NewsFollowup::detect(&envelope)
+        } else {
+            None
+        };
+        if let Some(NewsFollowup::Newsgroups(ref groups)) = followup {
+            ret.draft.set_news_mode(Some(groups));
+        } else {
+            ret.draft.set_news_mode(None);
+        }
         // "Mail-Followup-To/(To+Cc+(Mail-Reply-To/Reply-To/From)) for follow-up,
         // Mail-Reply-To/Reply-To/From for reply-to-author."
         // source: https://cr.yp.to/proto/replyto.html
-        if reply_to_all {
+        if followup.is_some() {
+            if followup == Some(NewsFollowup::Poster) {
+                if let Some(reply_to) = envelope.other_headers().get("Reply-To") {
+                    ret.draft.set_header("To", reply_to.to_string());
+                } else {
+                    ret.draft.set_header("To", envelope.field_from_to_string());
+                }
+            }
+        } else if reply_to_all {
             let mut to = IndexSet::new();
 
             if let Some(actions) = list_management::ListActions::detect(&envelope) {
@@ -364,7 +410,13 @@ impl Composer {
         reply_body: String,
         context: &mut Context,
     ) -> Self {
-        let mut ret = Composer::reply_to(coordinates, reply_body, context, false);
+        let is_news = {
+            let account = &context.accounts[&coordinates.0];
+            account.settings.account().format() == "nntp"
+                && NewsFollowup::detect(&account.collection.get_env(coordinates.2)).is_some()
+        };
+        /* Replies to news articles are follow-ups to the newsgroups, like in other newsreaders */
+        let mut ret = Composer::reply_to(coordinates, reply_body, context, is_news);
         let account = &context.accounts[&coordinates.0];
         let parent_message = account.collection.get_env(coordinates.2);
         /* If message is from a mailing list and we detect a List-Post header, ask user if they
@@ -494,7 +546,21 @@ To: {}
         self.form.set_cursor(old_cursor);
         let headers = self.draft.headers();
         let account_hash = self.account_hash;
-        for &k in &["Date", "From", "To", "Cc", "Bcc", "Subject"] {
+        for &k in &[
+            "Date",
+            "From",
+            "Newsgroups",
+            "Followup-To",
+            "To",
+            "Cc",
+            "Bcc",
+            "Disposition-Notification-To",
+            "Subject",
+        ] {
+            /* News articles don't have recipients, and e-mails don't have newsgroups */
+            if !headers.contains_key(k) {
+                continue;
+            }
             if k == "To" || k == "Cc" || k == "Bcc" {
                 self.form.push_cl((
                     k.into(),
@@ -1041,6 +1107,7 @@ impl Component for Composer {
                         self.draft.clone(),
                         SpecialUsageMailbox::Sent,
                         Flag::SEEN,
+                        self.send_at,
                     ) {
                         Ok(job) => {
                             let handle = context.job_executor.spawn_blocking(job);
@@ -1082,11 +1149,14 @@ impl Component for Composer {
                             self.mode = ViewMode::Edit;
                         }
                     }
+                } else {
+                    self.send_at = None;
                 }
                 self.set_dirty(true);
                 return true;
             }
             (ViewMode::Send(ref dialog), UIEvent::ComponentKill(ref id)) if *id == dialog.id() => {
+                self.send_at = None;
                 self.mode = ViewMode::Edit;
                 self.set_dirty(true);
             }
@@ -1547,7 +1617,12 @@ impl Component for Composer {
             {
                 #[cfg(feature = "gpgme")]
                 match melib::email::parser::address::rfc2822address_list(
-                    self.form.values()["To"].as_str().as_bytes(),
+                    self.form
+                        .values()
+                        .get("To")
+                        .map(|s| s.as_str())
+                        .unwrap_or_default()
+                        .as_bytes(),
                 )
                 .map_err(|_err| -> MeliError { "No valid recipient addresses in `To:`".into() })
                 .and_then(|(_, list)| {
@@ -1893,6 +1968,40 @@ impl Component for Composer {
                     self.set_dirty(true);
                     return true;
                 }
+                Action::Compose(ComposeAction::SendAt(ref datetime)) => {
+                    let send_at = match melib::datetime::parse_future_timestamp(
+                        datetime,
+                        melib::datetime::now(),
+                    ) {
+                        Some(send_at) => send_at,
+                        None => {
+                            context.replies.push_back(UIEvent::StatusEvent(
+                                StatusEvent::DisplayMessage(format!(
+                                    "invalid date `{}`, expected +30m, +2h, +1d, HH:MM or YYYY-MM-DD HH:MM",
+                                    datetime
+                                )),
+                            ));
+                            return true;
+                        }
+                    };
+                    self.update_draft();
+                    self.send_at = Some(send_at);
+                    self.mode = ViewMode::Send(UIConfirmationDialog::new(
+                        &format!(
+                            "send mail at {}?",
+                            melib::datetime::timestamp_to_string(send_at, None, false)
+                        ),
+                        vec![(true, "yes".to_string()), (false, "no".to_string())],
+                        /* only one choice */
+                        true,
+                        Some(Box::new(move |id: ComponentId, result: bool| {
+                            Some(UIEvent::FinishedUIDialog(id, Box::new(result)))
+                        })),
+                        context,
+                    ));
+                    self.set_dirty(true);
+                    return true;
+                }
                 Action::Compose(ComposeAction::SaveDraft) => {
                     save_draft(
                         self.draft.clone().finalise().unwrap().as_bytes(),
@@ -1903,6 +2012,33 @@ impl Component for Composer {
                     );
                     return true;
                 }
+                Action::Compose(ComposeAction::ToggleReadReceipt) => {
+                    self.update_draft();
+                    let message = if self.draft.is_news() {
+                        "News articles cannot ask for read receipts."
+                    } else if self
+                        .draft
+                        .headers_mut()
+                        .remove("Disposition-Notification-To")
+                        .is_some()
+                    {
+                        "Read receipt will not be requested."
+                    } else {
+                        self.draft.set_header(
+                            "Disposition-Notification-To",
+                            get_display_name(context, self.account_hash),
+                        );
+                        "Read receipt will be requested."
+                    };
+                    context
+                        .replies
+                        .push_back(UIEvent::StatusEvent(StatusEvent::DisplayMessage(
+                            message.to_string(),
+                        )));
+                    self.update_form();
+                    self.set_dirty(true);
+                    return true;
+                }
                 #[cfg(feature = "gpgme")]
                 Action::Compose(ComposeAction::ToggleSign) => {
                     let is_true = self.gpg_state.sign_mail.is_true();
@@ -2136,14 +2272,21 @@ pub fn send_draft(
             let body: AttachmentBuilder = Attachment::new(
                 content_type,
                 Default::default(),
-                std::mem::replace(&mut draft.body, String::new()).into_bytes(),
+                melib::email::attachments::encode_format_flowed(&std::mem::take(&mut draft.body))
+                    .into_bytes(),
             )
             .into();
             draft.attachments.insert(0, body);
         }
     }
+    let is_news = draft.is_news();
     let bytes = draft.finalise().unwrap();
-    let send_mail = account_settings!(context[account_hash].composing.send_mail).clone();
+    let send_mail = if is_news {
+        /* Articles are posted through the news server */
+        crate::conf::composing::SendMail::ServerSubmission
+    } else {
+        account_settings!(context[account_hash].composing.send_mail).clone()
+    };
     let ret =
         context.accounts[&account_hash].send(bytes.clone(), send_mail, complete_in_background);
     save_draft(bytes.as_bytes(), context, mailbox_type, flags, account_hash);
@@ -2183,6 +2326,56 @@ pub fn save_draft(
     }
 }
 
+/// Queue `message` in the account's outbox, or save it in Drafts if that fails.
+fn queue_in_outbox(
+    message: &str,
+    context: &mut Context,
+    account_hash: AccountHash,
+    server_submission: bool,
+    send_at: UnixTimestamp,
+    error: Option<MeliError>,
+) {
+    match context.accounts[&account_hash].queue_message(
+        message.to_string(),
+        server_submission,
+        send_at,
+        error.as_ref(),
+    ) {
+        Ok(()) => {
+            context.replies.push_back(UIEvent::Notification(
+                Some("Message queued in outbox".into()),
+                if let Some(err) = error {
+                    format!(
+                        "Could not send message ({}). It will be retried in {} minute.",
+                        err,
+                        crate::conf::outbox::backoff(1) / 60
+                    )
+                } else {
+                    format!(
+                        "Message will be sent at {}.",
+                        melib::datetime::timestamp_to_string(send_at, None, false)
+                    )
+                },
+                Some(NotificationType::Info),
+            ));
+        }
+        Err(err) => {
+            context.replies.push_back(UIEvent::Notification(
+                Some("Could not queue message in outbox".into()),
+                err.to_string(),
+                Some(NotificationType::Error(err.kind)),
+            ));
+            save_draft(
+                message.as_bytes(),
+                context,
+                SpecialUsageMailbox::Drafts,
+                Flag::SEEN | Flag::DRAFT,
+                account_hash,
+            );
+        }
+    }
+}
+
 pub fn send_draft_async(
     #[cfg(feature = "gpgme")] gpg_state: gpg::GpgComposeState,
     context: &mut Context,
@@ -2190,6 +2383,7 @@ pub fn send_draft_async(
     mut draft: Draft,
     mailbox_type: SpecialUsageMailbox,
     flags: Flag,
+    send_at: Option<UnixTimestamp>,
 ) -> Result<Pin<Box<dyn Future<Output = Result<()>> + Send>>> {
     let store_sent_mail = *account_settings!(context[account_hash].composing.store_sent_mail);
     let format_flowed = *account_settings!(context[account_hash].composing.format_flowed);
@@ -2219,7 +2413,13 @@ pub fn send_draft_async(
             gpg_state.encrypt_keys.clone(),
         )?));
     }
-    let send_mail = account_settings!(context[account_hash].composing.send_mail).clone();
+    let server_submission = draft.is_news();
+    let send_mail = if server_submission {
+        /* Articles are posted through the news server */
+        crate::conf::composing::SendMail::ServerSubmission
+    } else {
+        account_settings!(context[account_hash].composing.send_mail).clone()
+    };
     let send_cb = context.accounts[&account_hash].send_async(send_mail);
     let mut content_type = ContentType::default();
     if format_flowed {
@@ -2230,12 +2430,12 @@ pub fn send_draft_async(
             parameters.push((b"format".to_vec(), b"flowed".to_vec()));
         }
     }
-    let mut body: AttachmentBuilder = Attachment::new(
-        content_type,
-        Default::default(),
-        std::mem::replace(&mut draft.body, String::new()).into_bytes(),
-    )
-    .into();
+    let mut body_text = std::mem::take(&mut draft.body);
+    if format_flowed {
+        body_text = melib::email::attachments::encode_format_flowed(&body_text);
+    }
+    let mut body: AttachmentBuilder =
+        Attachment::new(content_type, Default::default(), body_text.into_bytes()).into();
     if !draft.attachments.is_empty() {
         let mut parts = std::mem::replace(&mut draft.attachments, Vec::new());
         parts.insert(0, body);
@@ -2259,31 +2459,59 @@ pub fn send_draft_async(
 
         draft.attachments.insert(0, body);
         let message = Arc::new(draft.finalise()?);
-        let ret = send_cb(message.clone()).await;
-        let is_ok = ret.is_ok();
-        if !is_ok || (store_sent_mail && is_ok) {
+        let ret = match send_at {
+            Some(send_at) => {
+                event_sender
+                    .send(ThreadEvent::UIEvent(UIEvent::Callback(CallbackFn(
+                        Box::new(move |context| {
+                            queue_in_outbox(
+                                &message,
+                                context,
+                                account_hash,
+                                server_submission,
+                                send_at,
+                                None,
+                            );
+                        }),
+                    ))))
+                    .unwrap();
+                return Ok(());
+            }
+            None => send_cb(message.clone()).await,
+        };
+        if let Err(err) = ret {
+            /* Keep the message in the outbox and retry later */
+            event_sender
+                .send(ThreadEvent::UIEvent(UIEvent::Callback(CallbackFn(
+                    Box::new(move |context| {
+                        queue_in_outbox(
+                            &message,
+                            context,
+                            account_hash,
+                            server_submission,
+                            melib::datetime::now(),
+                            Some(err),
+                        );
+                    }),
+                ))))
+                .unwrap();
+            return Ok(());
+        }
+        if store_sent_mail {
             event_sender
                 .send(ThreadEvent::UIEvent(UIEvent::Callback(CallbackFn(
                     Box::new(move |context| {
                         save_draft(
                             message.as_bytes(),
                             context,
-                            if is_ok {
-                                mailbox_type
-                            } else {
-                                SpecialUsageMailbox::Drafts
-                            },
-                            if is_ok {
-                                flags
-                            } else {
-                                Flag::SEEN | Flag::DRAFT
-                            },
+                            mailbox_type,
+                            flags,
                             account_hash,
                         );
                     }),
                 ))))
                 .unwrap();
-        } else if !store_sent_mail && is_ok {
+        } else {
             let f = create_temp_file(message.as_bytes(), None, None, false);
             log(
                 format!(
@@ -22,10 +22,8 @@
 use super::*;
 use crate::conf::accounts::JobRequest;
 use crate::types::segment_tree::SegmentTree;
-use melib::backends::EnvelopeHashBatch;
 use smallvec::SmallVec;
 use std::collections::{HashMap, HashSet};
-use std::convert::TryFrom;
 use std::ops::{Deref, DerefMut};
 
 // TODO: emoji_text_presentation_selector should be printed along with the chars before it but not
@@ -61,6 +59,9 @@ pub use self::plain::*;
 mod offline;
 pub use self::offline::*;
 
+mod unified;
+pub use self::unified::*;
+
 #[derive(Debug, Copy, PartialEq, Clone)]
 pub enum Modifier {
     SymmetricDifference,
@@ -192,132 +193,152 @@ pub trait MailListingTrait: ListingTrait {
         if envs_to_set.is_empty() {
             return;
         }
-        let env_hashes = EnvelopeHashBatch::try_from(envs_to_set.as_slice()).unwrap();
+        let batches = account.envelopes_by_mailbox(mailbox_hash, &envs_to_set);
         match a {
             ListingAction::SetSeen => {
-                let job = account.backend.write().unwrap().set_flags(
-                    env_hashes.clone(),
-                    mailbox_hash,
-                    smallvec::smallvec![(Ok(Flag::SEEN), true)],
-                );
-                match job {
-                    Err(err) => {
-                        context.replies.push_back(UIEvent::StatusEvent(
-                            StatusEvent::DisplayMessage(err.to_string()),
-                        ));
-                    }
-                    Ok(fut) => {
-                        let handle = account.job_executor.spawn_specialized(fut);
-                        account
-                            .insert_job(handle.job_id, JobRequest::SetFlags { env_hashes, handle });
+                for (mailbox_hash, env_hashes) in batches {
+                    let job = account.backend.write().unwrap().set_flags(
+                        env_hashes.clone(),
+                        mailbox_hash,
+                        smallvec::smallvec![(Ok(Flag::SEEN), true)],
+                    );
+                    match job {
+                        Err(err) => {
+                            context.replies.push_back(UIEvent::StatusEvent(
+                                StatusEvent::DisplayMessage(err.to_string()),
+                            ));
+                        }
+                        Ok(fut) => {
+                            let handle = account.job_executor.spawn_specialized(fut);
+                            account.insert_job(
+                                handle.job_id,
+                                JobRequest::SetFlags { env_hashes, handle },
+                            );
+                        }
                     }
                 }
             }
             ListingAction::SetUnseen => {
-                let job = account.backend.write().unwrap().set_flags(
-                    env_hashes.clone(),
-                    mailbox_hash,
-                    smallvec::smallvec![(Ok(Flag::SEEN), false)],
-                );
-                match job {
-                    Err(err) => {
-                        context.replies.push_back(UIEvent::StatusEvent(
-                            StatusEvent::DisplayMessage(err.to_string()),
-                        ));
-                    }
-                    Ok(fut) => {
-                        let handle = account.job_executor.spawn_specialized(fut);
-                        account
-                            .insert_job(handle.job_id, JobRequest::SetFlags { env_hashes, handle });
+                for (mailbox_hash, env_hashes) in batches {
+                    let job = account.backend.write().unwrap().set_flags(
+                        env_hashes.clone(),
+                        mailbox_hash,
+                        smallvec::smallvec![(Ok(Flag::SEEN), false)],
+                    );
+                    match job {
+                        Err(err) => {
+                            context.replies.push_back(UIEvent::StatusEvent(
+                                StatusEvent::DisplayMessage(err.to_string()),
+                            ));
+                        }
+                        Ok(fut) => {
+                            let handle = account.job_executor.spawn_specialized(fut);
+                            account.insert_job(
+                                handle.job_id,
+                                JobRequest::SetFlags { env_hashes, handle },
+                            );
+                        }
                     }
                 }
             }
             ListingAction::Tag(Remove(ref tag_str)) => {
-                let job = account.backend.write().unwrap().set_flags(
-                    env_hashes.clone(),
-                    mailbox_hash,
-                    smallvec::smallvec![(Err(tag_str.to_string()), false)],
-                );
-                match job {
-                    Err(err) => {
-                        context.replies.push_back(UIEvent::StatusEvent(
-                            StatusEvent::DisplayMessage(err.to_string()),
-                        ));
-                    }
-                    Ok(fut) => {
-                        let handle = account.job_executor.spawn_specialized(fut);
-                        account
-                            .insert_job(handle.job_id, JobRequest::SetFlags { env_hashes, handle });
+                for (mailbox_hash, env_hashes) in batches {
+                    let job = account.backend.write().unwrap().set_flags(
+                        env_hashes.clone(),
+                        mailbox_hash,
+                        smallvec::smallvec![(Err(tag_str.to_string()), false)],
+                    );
+                    match job {
+                        Err(err) => {
+                            context.replies.push_back(UIEvent::StatusEvent(
+                                StatusEvent::DisplayMessage(err.to_string()),
+                            ));
+                        }
+                        Ok(fut) => {
+                            let handle = account.job_executor.spawn_specialized(fut);
+                            account.insert_job(
+                                handle.job_id,
+                                JobRequest::SetFlags { env_hashes, handle },
+                            );
+                        }
                     }
                 }
             }
             ListingAction::Tag(Add(ref tag_str)) => {
-                let job = account.backend.write().unwrap().set_flags(
-                    env_hashes.clone(),
-                    mailbox_hash,
-                    smallvec::smallvec![(Err(tag_str.to_string()), true)],
-                );
-                match job {
-                    Err(err) => {
-                        context.replies.push_back(UIEvent::StatusEvent(
-                            StatusEvent::DisplayMessage(err.to_string()),
-                        ));
-                    }
-                    Ok(fut) => {
-                        let handle = account.job_executor.spawn_specialized(fut);
-                        account
-                            .insert_job(handle.job_id, JobRequest::SetFlags { env_hashes, handle });
+                for (mailbox_hash, env_hashes) in batches {
+                    let job = account.backend.write().unwrap().set_flags(
+                        env_hashes.clone(),
+                        mailbox_hash,
+                        smallvec::smallvec![(Err(tag_str.to_string()), true)],
+                    );
+                    match job {
+                        Err(err) => {
+                            context.replies.push_back(UIEvent::StatusEvent(
+                                StatusEvent::DisplayMessage(err.to_string()),
+                            ));
+                        }
+                        Ok(fut) => {
+                            let handle = account.job_executor.spawn_specialized(fut);
+                            account.insert_job(
+                                handle.job_id,
+                                JobRequest::SetFlags { env_hashes, handle },
+                            );
+                        }
                     }
                 }
             }
             ListingAction::Delete => {
-                let job = account
-                    .backend
-                    .write()
-                    .unwrap()
-                    .delete_messages(env_hashes.clone(), mailbox_hash);
-                match job {
-                    Err(err) => {
-                        context.replies.push_back(UIEvent::StatusEvent(
-                            StatusEvent::DisplayMessage(err.to_string()),
-                        ));
-                    }
-                    Ok(fut) => {
-                        let handle = account.job_executor.spawn_specialized(fut);
-                        account.insert_job(
-                            handle.job_id,
-                            JobRequest::DeleteMessages { env_hashes, handle },
-                        );
+                for (mailbox_hash, env_hashes) in batches {
+                    let job = account
+                        .backend
+                        .write()
+                        .unwrap()
+                        .delete_messages(env_hashes.clone(), mailbox_hash);
+                    match job {
+                        Err(err) => {
+                            context.replies.push_back(UIEvent::StatusEvent(
+                                StatusEvent::DisplayMessage(err.to_string()),
+                            ));
+                        }
+                        Ok(fut) => {
+                            let handle = account.job_executor.spawn_specialized(fut);
+                            account.insert_job(
+                                handle.job_id,
+                                JobRequest::DeleteMessages { env_hashes, handle },
+                            );
+                        }
                     }
                 }
             }
             ListingAction::CopyTo(ref mailbox_path) => {
-                match account
-                    .mailbox_by_path(mailbox_path)
-                    .and_then(|destination_mailbox_hash| {
-                        account.backend.write().unwrap().copy_messages(
-                            env_hashes,
-                            mailbox_hash,
-                            destination_mailbox_hash,
-                            /* move? */ false,
-                        )
-                    }) {
-                    Err(err) => {
-                        context.replies.push_back(UIEvent::StatusEvent(
-                            StatusEvent::DisplayMessage(err.to_string()),
-                        ));
-                    }
-                    Ok(fut) => {
-                        let handle = account.job_executor.spawn_specialized(fut);
-                        account.insert_job(
-                            handle.job_id,
-                            JobRequest::Generic {
-                                name: "message copying".into(),
-                                handle,
-                                on_finish: None,
-                                logging_level: melib::LoggingLevel::INFO,
-                            },
-                        );
+                for (mailbox_hash, env_hashes) in batches {
+                    match account.mailbox_by_path(mailbox_path).and_then(
+                        |destination_mailbox_hash| {
+                            account.backend.write().unwrap().copy_messages(
+                                env_hashes,
+                                mailbox_hash,
+                                destination_mailbox_hash,
+                                /* move? */ false,
+                            )
+                        },
+                    ) {
+                        Err(err) => {
+                            context.replies.push_back(UIEvent::StatusEvent(
+                                StatusEvent::DisplayMessage(err.to_string()),
+                            ));
+                        }
+                        Ok(fut) => {
+                            let handle = account.job_executor.spawn_specialized(fut);
+                            account.insert_job(
+                                handle.job_id,
+                                JobRequest::Generic {
+                                    name: "message copying".into(),
+                                    handle,
+                                    on_finish: None,
+                                    logging_level: melib::LoggingLevel::INFO,
+                                },
+                            );
+                        }
                     }
                 }
             }
@@ -329,32 +350,34 @@ pub trait MailListingTrait: ListingTrait {
                     )));
             }
             ListingAction::MoveTo(ref mailbox_path) => {
-                match account
-                    .mailbox_by_path(mailbox_path)
-                    .and_then(|destination_mailbox_hash| {
-                        account.backend.write().unwrap().copy_messages(
-                            env_hashes,
-                            mailbox_hash,
-                            destination_mailbox_hash,
-                            /* move? */ true,
-                        )
-                    }) {
-                    Err(err) => {
-                        context.replies.push_back(UIEvent::StatusEvent(
-                            StatusEvent::DisplayMessage(err.to_string()),
-                        ));
-                    }
-                    Ok(fut) => {
-                        let handle = account.job_executor.spawn_specialized(fut);
-                        account.insert_job(
-                            handle.job_id,
-                            JobRequest::Generic {
-                                name: "message moving".into(),
-                                handle,
-                                on_finish: None,
-                                logging_level: melib::LoggingLevel::INFO,
-                            },
-                        );
+                for (mailbox_hash, env_hashes) in batches {
+                    match account.mailbox_by_path(mailbox_path).and_then(
+                        |destination_mailbox_hash| {
+                            account.backend.write().unwrap().copy_messages(
+                                env_hashes,
+                                mailbox_hash,
+                                destination_mailbox_hash,
+                                /* move? */ true,
+                            )
+                        },
+                    ) {
+                        Err(err) => {
+                            context.replies.push_back(UIEvent::StatusEvent(
+                                StatusEvent::DisplayMessage(err.to_string()),
+                            ));
+                        }
+                        Ok(fut) => {
+                            let handle = account.job_executor.spawn_specialized(fut);
+                            account.insert_job(
+                                handle.job_id,
+                                JobRequest::Generic {
+                                    name: "message moving".into(),
+                                    handle,
+                                    on_finish: None,
+                                    logging_level: melib::LoggingLevel::INFO,
+                                },
+                            );
+                        }
                     }
                 }
             }
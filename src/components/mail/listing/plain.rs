@@ -1050,16 +1050,17 @@ impl PlainListing {
 
     fn perform_action(&mut self, context: &mut Context, env_hash: EnvelopeHash, a: &ListingAction) {
         let account = &mut context.accounts[&self.cursor_pos.0];
+        let mailbox_hash = account.envelope_mailbox(self.cursor_pos.1, env_hash);
         match {
             match a {
                 ListingAction::SetSeen => account.backend.write().unwrap().set_flags(
                     env_hash.into(),
-                    self.cursor_pos.1,
+                    mailbox_hash,
                     smallvec::smallvec![(Ok(Flag::SEEN), true)],
                 ),
                 ListingAction::SetUnseen => account.backend.write().unwrap().set_flags(
                     env_hash.into(),
-                    self.cursor_pos.1,
+                    mailbox_hash,
                     smallvec::smallvec![(Ok(Flag::SEEN), false)],
                 ),
                 ListingAction::Delete => {
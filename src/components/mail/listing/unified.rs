@@ -0,0 +1,800 @@
+/*
+ * meli - listing/unified.rs
+ *
+ * Copyright 2020 Manos Pitsidianakis
+ *
+ * This file is part of meli.
+ *
+ * meli is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * meli is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with meli. If not, see <http://www.gnu.org/licenses/>.
+ */
+
+/*! A listing of threads from mailboxes of several accounts, sorted by date.
+ */
+use super::*;
+use crate::conf::unified_mailbox_matches;
+use melib::SpecialUsageMailbox;
+
+#[derive(Debug)]
+struct UnifiedRow {
+    account_hash: AccountHash,
+    mailbox_hash: MailboxHash,
+    thread_hash: ThreadHash,
+    date: UnixTimestamp,
+    account_name: String,
+    from: String,
+    subject: String,
+    unseen: bool,
+}
+
+/// Shows the threads of the mailboxes selected by `listing.unified_mailboxes` (the inbox of
+/// every account by default) in one list.
+///
+/// Every row remembers the account and mailbox it came from, so opened threads and actions on
+/// rows are handled by the backend of the owning account.
+#[derive(Debug)]
+pub struct UnifiedListing {
+    /// Mailboxes included in the listing and whether they have finished loading.
+    mailboxes: Vec<(AccountHash, MailboxHash, bool)>,
+    rows: Vec<UnifiedRow>,
+    selection: HashSet<(AccountHash, ThreadHash)>,
+    cursor_pos: usize,
+    /// Height of the list when it was last drawn.
+    page_rows: usize,
+    view: Option<ThreadView>,
+    color_cache: ColorCache,
+    /// Whether the listing is the visible tab; actions are only performed then since they are
+    /// also delivered to tabs in the background.
+    visible: bool,
+    initialized: bool,
+    dirty: bool,
+    id: ComponentId,
+}
+
+impl fmt::Display for UnifiedListing {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", UnifiedListing::DESCRIPTION)
+    }
+}
+
+impl UnifiedListing {
+    pub const DESCRIPTION: &'static str = "unified inbox";
+    pub fn new(context: &Context) -> Self {
+        let mut ret = UnifiedListing {
+            mailboxes: vec![],
+            rows: vec![],
+            selection: HashSet::default(),
+            cursor_pos: 0,
+            page_rows: 1,
+            view: None,
+            color_cache: ColorCache::default(),
+            visible: true,
+            initialized: false,
+            dirty: true,
+            id: ComponentId::new_v4(),
+        };
+        ret.set_colors(context);
+        ret
+    }
+
+    fn set_colors(&mut self, context: &Context) {
+        self.color_cache = ColorCache {
+            even_unseen: crate::conf::value(context, "mail.listing.compact.even_unseen"),
+            even_selected: crate::conf::value(context, "mail.listing.compact.even_selected"),
+            even_highlighted: crate::conf::value(context, "mail.listing.compact.even_highlighted"),
+            odd_unseen: crate::conf::value(context, "mail.listing.compact.odd_unseen"),
+            odd_selected: crate::conf::value(context, "mail.listing.compact.odd_selected"),
+            odd_highlighted: crate::conf::value(context, "mail.listing.compact.odd_highlighted"),
+            even: crate::conf::value(context, "mail.listing.compact.even"),
+            odd: crate::conf::value(context, "mail.listing.compact.odd"),
+            theme_default: crate::conf::value(context, "theme_default"),
+            ..ColorCache::default()
+        };
+        if !context.settings.terminal.use_color() {
+            self.color_cache.even_highlighted.attrs |= Attr::REVERSE;
+            self.color_cache.odd_highlighted.attrs |= Attr::REVERSE;
+        }
+    }
+
+    /// Find the mailboxes of every account that are selected by `listing.unified_mailboxes`.
+    fn select_mailboxes(&mut self, context: &Context) {
+        let patterns = &context.settings.listing.unified_mailboxes;
+        self.mailboxes.clear();
+        for (account_hash, account) in context.accounts.iter() {
+            if patterns.is_empty() {
+                if let Some(mailbox_hash) = account.special_use_mailbox(SpecialUsageMailbox::Inbox)
+                {
+                    self.mailboxes.push((*account_hash, mailbox_hash, false));
+                }
+                continue;
+            }
+            for mailbox_hash in account.mailboxes_order() {
+                let entry = &account[mailbox_hash];
+                if entry.is_saved_search() {
+                    continue;
+                }
+                let path = entry.ref_mailbox.path();
+                if patterns
+                    .iter()
+                    .any(|p| unified_mailbox_matches(p, account.name(), path))
+                {
+                    self.mailboxes.push((*account_hash, *mailbox_hash, false));
+                }
+            }
+        }
+    }
+
+    fn includes(&self, account_hash: AccountHash, mailbox_hash: MailboxHash) -> bool {
+        self.mailboxes
+            .iter()
+            .any(|(a, m, _)| *a == account_hash && *m == mailbox_hash)
+    }
+
+    fn refresh(&mut self, context: &mut Context) {
+        let (old_account, old_thread) = self
+            .rows
+            .get(self.cursor_pos)
+            .map(|row| (Some(row.account_hash), Some(row.thread_hash)))
+            .unwrap_or((None, None));
+        self.rows.clear();
+        for (account_hash, mailbox_hash, loaded) in self.mailboxes.iter_mut() {
+            *loaded = context.accounts[&*account_hash].load(*mailbox_hash).is_ok();
+            if !*loaded {
+                continue;
+            }
+            let account = &context.accounts[&*account_hash];
+            let threads = account.collection.get_threads(*mailbox_hash);
+            for thread_hash in threads.roots() {
+                let thread = threads.thread_ref(thread_hash);
+                let env_hash = match threads
+                    .thread_group_iter(thread_hash)
+                    .find_map(|(_, h)| threads.thread_nodes()[&h].message())
+                {
+                    Some(env_hash) if account.collection.contains_key(&env_hash) => env_hash,
+                    _ => continue,
+                };
+                let envelope = account.collection.get_env(env_hash);
+                let mut subject = envelope.subject().to_string();
+                subject.truncate_at_boundary(150);
+                if thread.len() > 1 {
+                    subject = format!("{} ({})", subject, thread.len());
+                }
+                self.rows.push(UnifiedRow {
+                    account_hash: *account_hash,
+                    mailbox_hash: *mailbox_hash,
+                    thread_hash,
+                    date: thread.date(),
+                    account_name: account.name().to_string(),
+                    from: address_list!((envelope.from()) as comma_sep_list),
+                    subject,
+                    unseen: thread.unseen() > 0,
+                });
+            }
+        }
+        self.rows.sort_by_key(|r| std::cmp::Reverse(r.date));
+        let rows = &self.rows;
+        self.selection.retain(|(a, t)| {
+            rows.iter()
+                .any(|r| r.account_hash == *a && r.thread_hash == *t)
+        });
+        if let Some(pos) = self
+            .rows
+            .iter()
+            .position(|r| Some(r.account_hash) == old_account && Some(r.thread_hash) == old_thread)
+        {
+            self.cursor_pos = pos;
+        } else {
+            self.cursor_pos = std::cmp::min(self.cursor_pos, self.rows.len().saturating_sub(1));
+        }
+        self.set_dirty(true);
+    }
+
+    /// Rows an action applies to: the selected rows, or the row under the cursor.
+    fn focused_rows(&self) -> Vec<&UnifiedRow> {
+        if self.selection.is_empty() {
+            self.rows.get(self.cursor_pos).into_iter().collect()
+        } else {
+            self.rows
+                .iter()
+                .filter(|r| self.selection.contains(&(r.account_hash, r.thread_hash)))
+                .collect()
+        }
+    }
+
+    /// Envelopes of the threads an action applies to, grouped by the mailbox they are listed in.
+    fn focused_envelopes(
+        &self,
+        context: &Context,
+    ) -> IndexMap<(AccountHash, MailboxHash), SmallVec<[EnvelopeHash; 8]>> {
+        let mut ret: IndexMap<(AccountHash, MailboxHash), SmallVec<[EnvelopeHash; 8]>> =
+            IndexMap::default();
+        for row in self.focused_rows() {
+            let threads = context.accounts[&row.account_hash]
+                .collection
+                .get_threads(row.mailbox_hash);
+            let env_hashes = ret.entry((row.account_hash, row.mailbox_hash)).or_default();
+            for (_, h) in threads.thread_group_iter(row.thread_hash) {
+                if let Some(env_hash) = threads.thread_nodes()[&h].message() {
+                    env_hashes.push(env_hash);
+                }
+            }
+        }
+        ret
+    }
+
+    fn toggle_snooze(&mut self, context: &mut Context) {
+        for row in self.focused_rows() {
+            context.accounts[&row.account_hash]
+                .collection
+                .threads
+                .write()
+                .unwrap()
+                .entry(row.mailbox_hash)
+                .and_modify(|threads| {
+                    let is_snoozed = threads.thread_ref(row.thread_hash).snoozed();
+                    threads
+                        .thread_ref_mut(row.thread_hash)
+                        .set_snoozed(!is_snoozed);
+                });
+        }
+        self.selection.clear();
+        self.refresh(context);
+    }
+
+    /// Write the focused threads of every account to one mbox file. The job is run by the
+    /// account of the first thread.
+    fn export_mbox(
+        &mut self,
+        format: Option<melib::backends::mbox::MboxFormat>,
+        path: &std::path::Path,
+        context: &mut Context,
+    ) {
+        use futures::future::try_join_all;
+        use std::future::Future;
+        use std::io::Write;
+        use std::pin::Pin;
+
+        let mut futures = vec![];
+        let mut envs = vec![];
+        for ((account_hash, _), env_hashes) in self.focused_envelopes(context) {
+            let account = &context.accounts[&account_hash];
+            let tags_lck = account.collection.tag_index.read().unwrap();
+            for env_hash in env_hashes {
+                futures.push(account.operation(env_hash).and_then(|mut op| op.as_bytes()));
+                let env = account.collection.get_env(env_hash);
+                let tags: Vec<String> = env
+                    .labels()
+                    .iter()
+                    .filter_map(|h| tags_lck.get(h).cloned())
+                    .collect();
+                envs.push(((*env).clone(), tags));
+            }
+        }
+        let account_hash = match self.focused_rows().first() {
+            Some(row) => row.account_hash,
+            None => return,
+        };
+        let futures: Result<Vec<_>> = futures.into_iter().collect();
+        let path_ = path.to_path_buf();
+        let format = format.unwrap_or_default();
+        let (sender, mut receiver) = crate::jobs::oneshot::channel();
+        let fut: Pin<Box<dyn Future<Output = Result<()>> + Send + 'static>> =
+            Box::pin(async move {
+                let cl = async move {
+                    use melib::backends::mbox::MboxMetadata;
+                    let bytes: Vec<Vec<u8>> = try_join_all(futures?).await?;
+                    let mut file = std::io::BufWriter::new(std::fs::File::create(&path_)?);
+                    for (i, ((env, tags), bytes)) in envs.iter().zip(bytes.iter()).enumerate() {
+                        format.append(
+                            &mut file,
+                            bytes.as_slice(),
+                            env.from().first(),
+                            Some(env.date()),
+                            (env.flags(), tags.iter().map(String::as_str).collect()),
+                            MboxMetadata::CClient,
+                            i == 0,
+                            false,
+                        )?;
+                    }
+                    file.flush()?;
+                    Ok(())
+                };
+                let r: Result<()> = cl.await;
+                let _ = sender.send(r);
+                Ok(())
+            });
+        let account = &mut context.accounts[&account_hash];
+        let handle = account.job_executor.spawn_blocking(fut);
+        let path = path.to_path_buf();
+        account.insert_job(
+            handle.job_id,
+            JobRequest::Generic {
+                name: "exporting mbox".into(),
+                handle,
+                on_finish: Some(CallbackFn(Box::new(move |context: &mut Context| {
+                    context.replies.push_back(match receiver.try_recv() {
+                        Err(_) | Ok(None) => UIEvent::Notification(
+                            Some("Could not export mbox".to_string()),
+                            "Job was canceled.".to_string(),
+                            Some(NotificationType::Info),
+                        ),
+                        Ok(Some(Err(err))) => UIEvent::Notification(
+                            Some("Could not export mbox".to_string()),
+                            err.to_string(),
+                            Some(NotificationType::Error(err.kind)),
+                        ),
+                        Ok(Some(Ok(()))) => UIEvent::Notification(
+                            Some("Succesfully exported mbox".to_string()),
+                            format!("Wrote to file {}", path.display()),
+                            Some(NotificationType::Info),
+                        ),
+                    });
+                }))),
+                logging_level: melib::LoggingLevel::INFO,
+            },
+        );
+        self.selection.clear();
+        self.set_dirty(true);
+    }
+
+    fn perform_action(&mut self, a: &ListingAction, context: &mut Context) {
+        let envs_to_set = self.focused_envelopes(context);
+        for ((account_hash, mailbox_hash), env_hashes) in envs_to_set {
+            let account = &mut context.accounts[&account_hash];
+            for (mailbox_hash, env_hashes) in
+                account.envelopes_by_mailbox(mailbox_hash, &env_hashes)
+            {
+                let job = match a {
+                    ListingAction::SetSeen => account.backend.write().unwrap().set_flags(
+                        env_hashes.clone(),
+                        mailbox_hash,
+                        smallvec::smallvec![(Ok(Flag::SEEN), true)],
+                    ),
+                    ListingAction::SetUnseen => account.backend.write().unwrap().set_flags(
+                        env_hashes.clone(),
+                        mailbox_hash,
+                        smallvec::smallvec![(Ok(Flag::SEEN), false)],
+                    ),
+                    ListingAction::Tag(Add(ref tag_str)) => {
+                        account.backend.write().unwrap().set_flags(
+                            env_hashes.clone(),
+                            mailbox_hash,
+                            smallvec::smallvec![(Err(tag_str.to_string()), true)],
+                        )
+                    }
+                    ListingAction::Tag(Remove(ref tag_str)) => {
+                        account.backend.write().unwrap().set_flags(
+                            env_hashes.clone(),
+                            mailbox_hash,
+                            smallvec::smallvec![(Err(tag_str.to_string()), false)],
+                        )
+                    }
+                    ListingAction::Delete => account
+                        .backend
+                        .write()
+                        .unwrap()
+                        .delete_messages(env_hashes.clone(), mailbox_hash),
+                    ListingAction::CopyTo(ref mailbox_path)
+                    | ListingAction::MoveTo(ref mailbox_path) => account
+                        .mailbox_by_path(mailbox_path)
+                        .and_then(|destination_mailbox_hash| {
+                            account.backend.write().unwrap().copy_messages(
+                                env_hashes.clone(),
+                                mailbox_hash,
+                                destination_mailbox_hash,
+                                /* move? */ matches!(a, ListingAction::MoveTo(_)),
+                            )
+                        }),
+                    _ => return,
+                };
+                match job {
+                    Err(err) => {
+                        context.replies.push_back(UIEvent::StatusEvent(
+                            StatusEvent::DisplayMessage(format!("{}: {}", account.name(), err)),
+                        ));
+                    }
+                    Ok(fut) => {
+                        let handle = account.job_executor.spawn_specialized(fut);
+                        let job_id = handle.job_id;
+                        let job = match a {
+                            ListingAction::Delete => {
+                                JobRequest::DeleteMessages { env_hashes, handle }
+                            }
+                            ListingAction::CopyTo(_) => JobRequest::Generic {
+                                name: "message copying".into(),
+                                handle,
+                                on_finish: None,
+                                logging_level: melib::LoggingLevel::INFO,
+                            },
+                            ListingAction::MoveTo(_) => JobRequest::Generic {
+                                name: "message moving".into(),
+                                handle,
+                                on_finish: None,
+                                logging_level: melib::LoggingLevel::INFO,
+                            },
+                            _ => JobRequest::SetFlags { env_hashes, handle },
+                        };
+                        account.insert_job(job_id, job);
+                    }
+                }
+            }
+        }
+        self.selection.clear();
+        self.set_dirty(true);
+    }
+
+    fn draw_list(&mut self, grid: &mut CellBuffer, area: Area, context: &mut Context) {
+        let upper_left = upper_left!(area);
+        let bottom_right = bottom_right!(area);
+        clear_area(grid, area, self.color_cache.theme_default);
+        if self.rows.is_empty() {
+            let message = if self.mailboxes.is_empty() {
+                "No mailboxes selected by listing.unified_mailboxes."
+            } else if self.mailboxes.iter().any(|(_, _, loaded)| !loaded) {
+                "Loading..."
+            } else {
+                "No messages."
+            };
+            write_string_to_grid(
+                message,
+                grid,
+                self.color_cache.theme_default.fg,
+                self.color_cache.theme_default.bg,
+                self.color_cache.theme_default.attrs,
+                area,
+                None,
+            );
+            context.dirty_areas.push_back(area);
+            return;
+        }
+        let rows = height!(area);
+        self.page_rows = std::cmp::max(1, rows);
+        let skip = (self.cursor_pos / rows) * rows;
+        let account_width = self
+            .rows
+            .iter()
+            .map(|r| r.account_name.grapheme_width())
+            .max()
+            .unwrap_or(0);
+        let dates = self
+            .rows
+            .iter()
+            .skip(skip)
+            .take(rows)
+            .map(|r| ConversationsListing::format_date(context, r.date))
+            .collect::<Vec<String>>();
+        let date_width = dates.iter().map(|d| d.grapheme_width()).max().unwrap_or(0);
+        let flag_width = std::cmp::max(
+            DEFAULT_SELECTED_FLAG.grapheme_width(),
+            DEFAULT_UNSEEN_FLAG.grapheme_width(),
+        ) + 1;
+        for (i, (row, date)) in self
+            .rows
+            .iter()
+            .skip(skip)
+            .take(rows)
+            .zip(dates.iter())
+            .enumerate()
+        {
+            let idx = skip + i;
+            let y = get_y(upper_left) + i;
+            let selected = self
+                .selection
+                .contains(&(row.account_hash, row.thread_hash));
+            let theme = match (idx & 1 == 0, idx == self.cursor_pos, selected, row.unseen) {
+                (true, true, _, _) => self.color_cache.even_highlighted,
+                (false, true, _, _) => self.color_cache.odd_highlighted,
+                (true, false, true, _) => self.color_cache.even_selected,
+                (false, false, true, _) => self.color_cache.odd_selected,
+                (true, false, false, true) => self.color_cache.even_unseen,
+                (false, false, false, true) => self.color_cache.odd_unseen,
+                (true, false, false, false) => self.color_cache.even,
+                (false, false, false, false) => self.color_cache.odd,
+            };
+            change_colors(
+                grid,
+                ((get_x(upper_left), y), (get_x(bottom_right), y)),
+                theme.fg,
+                theme.bg,
+            );
+            let flag = if selected {
+                DEFAULT_SELECTED_FLAG
+            } else if row.unseen {
+                DEFAULT_UNSEEN_FLAG
+            } else {
+                ""
+            };
+            let mut x = get_x(upper_left);
+            for (column, width) in [
+                (flag, flag_width),
+                (row.account_name.as_str(), account_width + 2),
+                (date.as_str(), date_width + 2),
+                (row.from.as_str(), 30),
+                (row.subject.as_str(), usize::MAX),
+            ] {
+                if x > get_x(bottom_right) {
+                    break;
+                }
+                write_string_to_grid(
+                    column,
+                    grid,
+                    theme.fg,
+                    theme.bg,
+                    theme.attrs,
+                    (
+                        (x, y),
+                        (
+                            std::cmp::min(x.saturating_add(width), get_x(bottom_right) + 1) - 1,
+                            y,
+                        ),
+                    ),
+                    None,
+                );
+                x = x.saturating_add(width);
+            }
+        }
+        context.dirty_areas.push_back(area);
+    }
+}
+
+impl Component for UnifiedListing {
+    fn draw(&mut self, grid: &mut CellBuffer, area: Area, context: &mut Context) {
+        self.visible = true;
+        if !self.initialized {
+            self.initialized = true;
+            self.select_mailboxes(context);
+            self.refresh(context);
+        }
+        if let Some(ref mut view) = self.view {
+            view.draw(grid, area, context);
+            return;
+        }
+        if !self.dirty {
+            return;
+        }
+        self.dirty = false;
+        self.draw_list(grid, area, context);
+    }
+
+    fn process_event(&mut self, event: &mut UIEvent, context: &mut Context) -> bool {
+        if let Some(ref mut view) = self.view {
+            if view.process_event(event, context) {
+                return true;
+            }
+        }
+        let shortcuts = self.get_shortcuts(context);
+        match *event {
+            UIEvent::ConfigReload { old_settings: _ } => {
+                self.set_colors(context);
+                self.select_mailboxes(context);
+                self.refresh(context);
+            }
+            UIEvent::VisibilityChange(value) => {
+                self.visible = value;
+            }
+            UIEvent::Resize | UIEvent::ChangeMode(UIMode::Normal) => {
+                self.set_dirty(true);
+            }
+            UIEvent::MailboxUpdate((account_hash, mailbox_hash))
+                if self.includes(account_hash, mailbox_hash) =>
+            {
+                self.refresh(context);
+            }
+            UIEvent::EnvelopeUpdate(_)
+            | UIEvent::EnvelopeRename(_, _)
+            | UIEvent::EnvelopeRemove(_, _) => {
+                self.refresh(context);
+            }
+            UIEvent::Action(Action::Listing(ref a)) if self.visible && self.view.is_none() => {
+                match a {
+                    ListingAction::SetSeen
+                    | ListingAction::SetUnseen
+                    | ListingAction::Delete
+                    | ListingAction::CopyTo(_)
+                    | ListingAction::MoveTo(_)
+                    | ListingAction::Tag(_) => self.perform_action(a, context),
+                    ListingAction::ExportMbox(format, ref path) => {
+                        self.export_mbox(*format, path, context)
+                    }
+                    ListingAction::ToggleThreadSnooze => self.toggle_snooze(context),
+                    ListingAction::OpenInNewTab if !self.rows.is_empty() => {
+                        let row = &self.rows[self.cursor_pos];
+                        context
+                            .replies
+                            .push_back(UIEvent::Action(Tab(New(Some(Box::new(ThreadView::new(
+                                (row.account_hash, row.mailbox_hash, self.cursor_pos),
+                                row.thread_hash,
+                                None,
+                                context,
+                            )))))));
+                    }
+                    ListingAction::SetPlain
+                    | ListingAction::SetThreaded
+                    | ListingAction::SetCompact
+                    | ListingAction::SetConversations => {
+                        context.replies.push_back(UIEvent::StatusEvent(
+                            StatusEvent::DisplayMessage(
+                                "The unified inbox only has the compact style.".to_string(),
+                            ),
+                        ));
+                    }
+                    _ => {
+                        context.replies.push_back(UIEvent::StatusEvent(
+                            StatusEvent::DisplayMessage(
+                                "This action is not supported in the unified inbox.".to_string(),
+                            ),
+                        ));
+                    }
+                }
+                return true;
+            }
+            UIEvent::Input(ref k)
+                if self.view.is_some()
+                    && shortcut!(k == shortcuts[CompactListing::DESCRIPTION]["exit_thread"]) =>
+            {
+                if let Some(mut view) = self.view.take() {
+                    view.process_event(&mut UIEvent::VisibilityChange(false), context);
+                }
+                self.refresh(context);
+                return true;
+            }
+            UIEvent::Input(_) if self.view.is_some() => {}
+            UIEvent::Input(ref k)
+                if !self.rows.is_empty()
+                    && shortcut!(k == shortcuts[CompactListing::DESCRIPTION]["open_thread"]) =>
+            {
+                let row = &self.rows[self.cursor_pos];
+                self.view = Some(ThreadView::new(
+                    (row.account_hash, row.mailbox_hash, self.cursor_pos),
+                    row.thread_hash,
+                    None,
+                    context,
+                ));
+                self.set_dirty(true);
+                return true;
+            }
+            UIEvent::Input(ref k)
+                if shortcut!(k == shortcuts[Listing::DESCRIPTION]["scroll_up"]) =>
+            {
+                self.cursor_pos = self.cursor_pos.saturating_sub(1);
+                self.set_dirty(true);
+                return true;
+            }
+            UIEvent::Input(ref k)
+                if shortcut!(k == shortcuts[Listing::DESCRIPTION]["scroll_down"]) =>
+            {
+                if self.cursor_pos + 1 < self.rows.len() {
+                    self.cursor_pos += 1;
+                    self.set_dirty(true);
+                }
+                return true;
+            }
+            UIEvent::Input(ref k)
+                if shortcut!(k == shortcuts[Listing::DESCRIPTION]["next_page"]) =>
+            {
+                self.cursor_pos = std::cmp::min(
+                    self.cursor_pos + self.page_rows,
+                    self.rows.len().saturating_sub(1),
+                );
+                self.set_dirty(true);
+                return true;
+            }
+            UIEvent::Input(ref k)
+                if shortcut!(k == shortcuts[Listing::DESCRIPTION]["prev_page"]) =>
+            {
+                self.cursor_pos = self.cursor_pos.saturating_sub(self.page_rows);
+                self.set_dirty(true);
+                return true;
+            }
+            UIEvent::Input(ref k)
+                if !self.rows.is_empty()
+                    && shortcut!(k == shortcuts[Listing::DESCRIPTION]["select_entry"]) =>
+            {
+                let row = &self.rows[self.cursor_pos];
+                let key = (row.account_hash, row.thread_hash);
+                if !self.selection.remove(&key) {
+                    self.selection.insert(key);
+                }
+                self.set_dirty(true);
+                return true;
+            }
+            UIEvent::Input(ref k)
+                if shortcut!(k == shortcuts[Listing::DESCRIPTION]["set_seen"]) =>
+            {
+                self.perform_action(&ListingAction::SetSeen, context);
+                return true;
+            }
+            UIEvent::Input(ref k) if shortcut!(k == shortcuts[Listing::DESCRIPTION]["refresh"]) => {
+                for (account_hash, mailbox_hash, _) in self.mailboxes.iter() {
+                    if let Err(err) = context.accounts[account_hash].refresh(*mailbox_hash) {
+                        context.replies.push_back(UIEvent::Notification(
+                            Some("Could not refresh.".to_string()),
+                            err.to_string(),
+                            Some(NotificationType::Error(err.kind)),
+                        ));
+                    }
+                }
+                return true;
+            }
+            UIEvent::Input(Key::Esc) if !self.selection.is_empty() => {
+                self.selection.clear();
+                self.set_dirty(true);
+                return true;
+            }
+            _ => {}
+        }
+        false
+    }
+
+    fn is_dirty(&self) -> bool {
+        self.dirty || self.view.as_ref().map(|v| v.is_dirty()).unwrap_or(false)
+    }
+
+    fn set_dirty(&mut self, value: bool) {
+        self.dirty = value;
+        if let Some(ref mut view) = self.view {
+            view.set_dirty(value);
+        }
+    }
+
+    fn get_shortcuts(&self, context: &Context) -> ShortcutMaps {
+        let mut map = if let Some(ref view) = self.view {
+            view.get_shortcuts(context)
+        } else {
+            ShortcutMaps::default()
+        };
+        map.insert(
+            CompactListing::DESCRIPTION,
+            context.settings.shortcuts.compact_listing.key_values(),
+        );
+        map.insert(
+            Listing::DESCRIPTION,
+            context.settings.shortcuts.listing.key_values(),
+        );
+        map
+    }
+
+    fn get_status(&self, _context: &Context) -> String {
+        let accounts = self
+            .mailboxes
+            .iter()
+            .map(|(account_hash, _, _)| account_hash)
+            .collect::<HashSet<_>>()
+            .len();
+        let unseen = self.rows.iter().filter(|r| r.unseen).count();
+        format!(
+            "{} threads from {} account{}, {} unseen",
+            self.rows.len(),
+            accounts,
+            if accounts == 1 { "" } else { "s" },
+            unseen
+        )
+    }
+
+    fn kill(&mut self, uuid: ComponentId, context: &mut Context) {
+        if self.id != uuid {
+            return;
+        }
+        context
+            .replies
+            .push_back(UIEvent::Action(Tab(Kill(self.id))));
+    }
+
+    fn id(&self) -> ComponentId {
+        self.id
+    }
+
+    fn set_id(&mut self, id: ComponentId) {
+        self.id = id;
+    }
+}
@@ -0,0 +1,418 @@
+/*
+ * meli - mail/outbox.rs
+ *
+ * Copyright 2020 Manos Pitsidianakis
+ *
+ * This file is part of meli.
+ *
+ * meli is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * meli is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with meli. If not, see <http://www.gnu.org/licenses/>.
+ */
+
+/*! View and manage an account's outbox: messages scheduled with `send-at` and messages that
+ * failed to be sent.
+ */
+use super::*;
+use crate::conf::outbox::OutboxItem;
+
+/// Lists the messages of an account's outbox with their send time and last error, and lets the
+/// user send, edit or delete them.
+#[derive(Debug)]
+pub struct OutboxView {
+    account_hash: AccountHash,
+    /// Copy of the outbox items as they were last drawn.
+    items: Vec<OutboxItem>,
+    cursor_pos: usize,
+    theme_default: ThemeAttribute,
+    highlight_theme: ThemeAttribute,
+    dirty: bool,
+    id: ComponentId,
+}
+
+impl fmt::Display for OutboxView {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", OutboxView::DESCRIPTION)
+    }
+}
+
+impl OutboxView {
+    pub const DESCRIPTION: &'static str = "outbox";
+    pub fn new(account_hash: AccountHash, context: &Context) -> OutboxView {
+        OutboxView {
+            account_hash,
+            items: context.accounts[&account_hash].outbox.items().to_vec(),
+            cursor_pos: 0,
+            theme_default: crate::conf::value(context, "theme_default"),
+            highlight_theme: crate::conf::value(context, "highlight"),
+            dirty: true,
+            id: ComponentId::new_v4(),
+        }
+    }
+
+    /// Pick up changes made to the outbox by send jobs and composers.
+    fn refresh(&mut self, context: &Context) {
+        let items = context.accounts[&self.account_hash].outbox.items();
+        if self.items.as_slice() != items {
+            self.items = items.to_vec();
+            self.cursor_pos = std::cmp::min(self.cursor_pos, self.items.len().saturating_sub(1));
+            self.set_dirty(true);
+        }
+    }
+
+    fn report_error(&mut self, title: &str, err: MeliError, context: &mut Context) {
+        context.replies.push_back(UIEvent::Notification(
+            Some(title.to_string()),
+            err.to_string(),
+            Some(NotificationType::Error(err.kind)),
+        ));
+        self.set_dirty(true);
+    }
+
+    fn send_now(&mut self, id: &str, context: &mut Context) {
+        let Context {
+            ref mut accounts,
+            ref settings,
+            ..
+        } = context;
+        let account = &mut accounts[&self.account_hash];
+        match account.outbox.get_mut(id) {
+            Some(item) if item.is_sending => return,
+            Some(item) => {
+                /* A message that is not retried anymore gets one more attempt */
+                item.send_at = melib::datetime::now();
+                item.failed = false;
+            }
+            None => return,
+        }
+        account.process_outbox(settings);
+        self.refresh(context);
+    }
+
+    /// Whether the message is being sent. It can't be edited or deleted then, since it would be
+    /// delivered anyway. `self.items` might not show sends that started since the last refresh,
+    /// so the account's outbox is checked.
+    fn refuse_if_sending(&mut self, id: &str, context: &mut Context) -> bool {
+        let is_sending = context.accounts[&self.account_hash]
+            .outbox
+            .get(id)
+            .map(|item| item.is_sending)
+            .unwrap_or(false);
+        if is_sending {
+            context
+                .replies
+                .push_back(UIEvent::StatusEvent(StatusEvent::DisplayMessage(
+                    "Message is being sent.".to_string(),
+                )));
+            self.refresh(context);
+        }
+        is_sending
+    }
+
+    /// Move the message out of the outbox and into a new composer tab.
+    fn edit(&mut self, id: &str, context: &mut Context) {
+        if self.refuse_if_sending(id, context) {
+            return;
+        }
+        let item = match context.accounts[&self.account_hash].outbox.remove(id) {
+            Ok(Some(item)) => item,
+            Ok(None) => return,
+            Err(err) => return self.report_error("Could not update outbox", err, context),
+        };
+        let bytes = item.message.as_bytes();
+        let draft = Envelope::from_bytes(bytes, None).and_then(|envelope| {
+            let mut draft = Draft::edit(&envelope, bytes)?;
+            /* Keep the attachments of multipart messages as attachments of the draft */
+            if let ContentType::Multipart {
+                kind: MultipartType::Mixed,
+                ref parts,
+                ..
+            } = envelope.body_bytes(bytes).content_type
+            {
+                if let Some((body, attachments)) = parts.split_first() {
+                    draft.body = body.text();
+                    draft
+                        .attachments_mut()
+                        .extend(attachments.iter().cloned().map(AttachmentBuilder::from));
+                }
+            }
+            Ok(draft)
+        });
+        match draft {
+            Ok(draft) => {
+                let composer = Composer::with_draft(self.account_hash, draft, context);
+                context
+                    .replies
+                    .push_back(UIEvent::Action(Tab(New(Some(Box::new(composer))))));
+            }
+            Err(err) => {
+                save_draft(
+                    bytes,
+                    context,
+                    SpecialUsageMailbox::Drafts,
+                    Flag::SEEN | Flag::DRAFT,
+                    self.account_hash,
+                );
+                self.report_error("Could not open message", err, context);
+            }
+        }
+        self.refresh(context);
+    }
+
+    fn delete(&mut self, id: &str, context: &mut Context) {
+        if self.refuse_if_sending(id, context) {
+            return;
+        }
+        match context.accounts[&self.account_hash].outbox.remove(id) {
+            Ok(_) => {
+                context
+                    .replies
+                    .push_back(UIEvent::StatusEvent(StatusEvent::DisplayMessage(
+                        "Deleted message from the outbox.".to_string(),
+                    )));
+            }
+            Err(err) => self.report_error("Could not update outbox", err, context),
+        }
+        self.refresh(context);
+    }
+}
+
+impl Component for OutboxView {
+    fn draw(&mut self, grid: &mut CellBuffer, area: Area, context: &mut Context) {
+        if !self.dirty {
+            return;
+        }
+        self.dirty = false;
+        clear_area(grid, area, self.theme_default);
+        let upper_left = upper_left!(area);
+        let bottom_right = bottom_right!(area);
+        let (x, y) = write_string_to_grid(
+            "Outbox of ",
+            grid,
+            self.theme_default.fg,
+            self.theme_default.bg,
+            self.theme_default.attrs | Attr::UNDERLINE,
+            (pos_inc(upper_left, (1, 0)), bottom_right),
+            None,
+        );
+        write_string_to_grid(
+            context.accounts[&self.account_hash].name(),
+            grid,
+            self.theme_default.fg,
+            self.theme_default.bg,
+            Attr::BOLD | Attr::UNDERLINE,
+            ((x, y), bottom_right),
+            None,
+        );
+        let mut line = get_y(upper_left) + 2;
+        if self.items.is_empty() {
+            write_string_to_grid(
+                "No queued messages.",
+                grid,
+                self.theme_default.fg,
+                self.theme_default.bg,
+                self.theme_default.attrs,
+                ((get_x(upper_left) + 1, line), bottom_right),
+                None,
+            );
+        } else {
+            let rows = height!(area).saturating_sub(2);
+            let skip = self
+                .cursor_pos
+                .checked_div(rows)
+                .map(|page| page * rows)
+                .unwrap_or(0);
+            for (idx, item) in self.items.iter().enumerate().skip(skip).take(rows) {
+                let theme = if idx == self.cursor_pos {
+                    self.highlight_theme
+                } else {
+                    self.theme_default
+                };
+                change_colors(
+                    grid,
+                    ((get_x(upper_left), line), (get_x(bottom_right), line)),
+                    theme.fg,
+                    theme.bg,
+                );
+                let (recipients, subject) = item.summary();
+                let status = if item.is_sending {
+                    " (sending)".to_string()
+                } else if let (true, Some(ref err)) = (item.failed, &item.last_error) {
+                    format!(
+                        " (not sent, gave up after {} attempts: {})",
+                        item.attempts, err
+                    )
+                } else if let Some(ref err) = item.last_error {
+                    format!(" (failed {} times: {})", item.attempts, err)
+                } else {
+                    String::new()
+                };
+                let (x, _) = write_string_to_grid(
+                    &format!(
+                        " {}  {}  {}",
+                        melib::datetime::timestamp_to_string(
+                            item.send_at,
+                            Some("%Y-%m-%d %H:%M"),
+                            false
+                        ),
+                        recipients,
+                        subject
+                    ),
+                    grid,
+                    theme.fg,
+                    theme.bg,
+                    theme.attrs,
+                    ((get_x(upper_left), line), bottom_right),
+                    None,
+                );
+                write_string_to_grid(
+                    &status,
+                    grid,
+                    if item.last_error.is_some() {
+                        Color::Red
+                    } else {
+                        theme.fg
+                    },
+                    theme.bg,
+                    theme.attrs,
+                    ((x, line), bottom_right),
+                    None,
+                );
+                line += 1;
+            }
+        }
+        context.dirty_areas.push_back(area);
+    }
+
+    fn process_event(&mut self, event: &mut UIEvent, context: &mut Context) -> bool {
+        let shortcuts = self.get_shortcuts(context);
+        match *event {
+            UIEvent::ConfigReload { old_settings: _ } => {
+                self.theme_default = crate::conf::value(context, "theme_default");
+                self.highlight_theme = crate::conf::value(context, "highlight");
+                self.set_dirty(true);
+            }
+            UIEvent::Resize => {
+                self.set_dirty(true);
+            }
+            UIEvent::VisibilityChange(true) | UIEvent::StatusEvent(StatusEvent::JobFinished(_)) => {
+                self.refresh(context);
+            }
+            UIEvent::FinishedUIDialog(id, ref mut result) if id == self.id() => {
+                if let Some(Some(id)) = result.downcast_mut::<Option<String>>() {
+                    let id = std::mem::take(id);
+                    self.delete(&id, context);
+                }
+                return true;
+            }
+            UIEvent::Input(ref key)
+                if shortcut!(key == shortcuts[Self::DESCRIPTION]["scroll_up"]) =>
+            {
+                self.cursor_pos = self.cursor_pos.saturating_sub(1);
+                self.set_dirty(true);
+                return true;
+            }
+            UIEvent::Input(ref key)
+                if shortcut!(key == shortcuts[Self::DESCRIPTION]["scroll_down"]) =>
+            {
+                if self.cursor_pos + 1 < self.items.len() {
+                    self.cursor_pos += 1;
+                    self.set_dirty(true);
+                }
+                return true;
+            }
+            UIEvent::Input(ref key)
+                if !self.items.is_empty()
+                    && shortcut!(key == shortcuts[Self::DESCRIPTION]["send_now"]) =>
+            {
+                let id = self.items[self.cursor_pos].id.clone();
+                self.send_now(&id, context);
+                return true;
+            }
+            UIEvent::Input(ref key)
+                if !self.items.is_empty()
+                    && shortcut!(key == shortcuts[Self::DESCRIPTION]["edit_message"]) =>
+            {
+                let id = self.items[self.cursor_pos].id.clone();
+                self.edit(&id, context);
+                return true;
+            }
+            UIEvent::Input(ref key)
+                if !self.items.is_empty()
+                    && shortcut!(key == shortcuts[Self::DESCRIPTION]["delete_message"]) =>
+            {
+                let (recipients, subject) = self.items[self.cursor_pos].summary();
+                let id = self.items[self.cursor_pos].id.clone();
+                let self_id = self.id();
+                context.replies.push_back(UIEvent::GlobalUIDialog(Box::new(
+                    UIConfirmationDialog::new(
+                        &format!("Delete \"{}\" to {}?", subject, recipients),
+                        vec![(true, "yes".to_string()), (false, "no".to_string())],
+                        true,
+                        Some(Box::new(move |_, result: bool| {
+                            Some(UIEvent::FinishedUIDialog(
+                                self_id,
+                                Box::new(if result { Some(id.clone()) } else { None }),
+                            ))
+                        })),
+                        context,
+                    ),
+                )));
+                return true;
+            }
+            _ => {}
+        }
+        false
+    }
+
+    fn get_shortcuts(&self, context: &Context) -> ShortcutMaps {
+        let mut ret: ShortcutMaps = Default::default();
+        ret.insert(
+            Self::DESCRIPTION,
+            context.settings.shortcuts.outbox.key_values(),
+        );
+        ret
+    }
+
+    fn get_status(&self, context: &Context) -> String {
+        format!(
+            "{} queued messages on {}",
+            self.items.len(),
+            context.accounts[&self.account_hash].name()
+        )
+    }
+
+    fn kill(&mut self, uuid: ComponentId, context: &mut Context) {
+        if self.id != uuid {
+            return;
+        }
+        context
+            .replies
+            .push_back(UIEvent::Action(Tab(Kill(self.id))));
+    }
+
+    fn is_dirty(&self) -> bool {
+        self.dirty
+    }
+
+    fn set_dirty(&mut self, value: bool) {
+        self.dirty = value;
+    }
+
+    fn id(&self) -> ComponentId {
+        self.id
+    }
+
+    fn set_id(&mut self, id: ComponentId) {
+        self.id = id;
+    }
+}
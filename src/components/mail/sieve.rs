@@ -0,0 +1,890 @@
+/*
+ * meli - mail/sieve.rs
+ *
+ * Copyright 2020 Manos Pitsidianakis
+ *
+ * This file is part of meli.
+ *
+ * meli is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * meli is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with meli. If not, see <http://www.gnu.org/licenses/>.
+ */
+
+/*! Edit an account's server-side Sieve scripts over ManageSieve.
+ */
+use super::*;
+use crate::jobs::{JobId, JoinHandle};
+use futures::lock::Mutex as FutureMutex;
+use melib::backends::imap::managesieve::{new_managesieve_connection, ManageSieve, ScriptList};
+use melib::backends::imap::ImapConnection;
+use melib::backends::BackendEventConsumer;
+use melib::sieve::validate_sieve;
+use nix::sys::wait::WaitStatus;
+use std::collections::HashMap;
+use std::process::{Command, Stdio};
+use std::sync::Arc;
+
+#[derive(Debug)]
+enum SieveJob {
+    List(JoinHandle<Result<ScriptList>>),
+    Get {
+        name: String,
+        handle: JoinHandle<Result<Vec<u8>>>,
+    },
+    Put {
+        name: String,
+        script: String,
+        handle: JoinHandle<Result<()>>,
+    },
+    SetActive {
+        name: String,
+        handle: JoinHandle<Result<()>>,
+    },
+    Delete {
+        name: String,
+        handle: JoinHandle<Result<()>>,
+    },
+}
+
+/// Lists the Sieve scripts of an account's ManageSieve server and lets the user edit, activate
+/// and delete them.
+///
+/// Edited scripts are checked with [`melib::sieve::validate_sieve`] before they are uploaded.
+/// Scripts that fail validation (or that the server rejects) are kept around so that the next
+/// edit resumes from the rejected text instead of the server's copy.
+#[derive(Debug)]
+pub struct SieveFilters {
+    account_hash: AccountHash,
+    connection: Option<Arc<FutureMutex<ImapConnection>>>,
+    scripts: Vec<(String, bool)>,
+    cursor_pos: usize,
+    active_jobs: HashMap<JobId, SieveJob>,
+    /// Script name and text as it was before the editor was launched.
+    editing: Option<(String, String)>,
+    /// Script name and text that were edited but not uploaded.
+    pending: Option<(String, String)>,
+    embed: Option<EmbedStatus>,
+    embed_area: Area,
+    error: Option<MeliError>,
+    initialized: bool,
+    theme_default: ThemeAttribute,
+    highlight_theme: ThemeAttribute,
+    dirty: bool,
+    id: ComponentId,
+}
+
+impl fmt::Display for SieveFilters {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", SieveFilters::DESCRIPTION)
+    }
+}
+
+impl SieveFilters {
+    pub const DESCRIPTION: &'static str = "filters";
+    pub fn new(account_hash: AccountHash, context: &Context) -> SieveFilters {
+        SieveFilters {
+            account_hash,
+            connection: None,
+            scripts: vec![],
+            cursor_pos: 0,
+            active_jobs: HashMap::default(),
+            editing: None,
+            pending: None,
+            embed: None,
+            embed_area: ((0, 0), (0, 0)),
+            error: None,
+            initialized: false,
+            theme_default: crate::conf::value(context, "theme_default"),
+            highlight_theme: crate::conf::value(context, "highlight"),
+            dirty: true,
+            id: ComponentId::new_v4(),
+        }
+    }
+
+    fn connect(&mut self, context: &Context) -> Result<Arc<FutureMutex<ImapConnection>>> {
+        if let Some(ref conn) = self.connection {
+            return Ok(conn.clone());
+        }
+        let account = &context.accounts[&self.account_hash];
+        if account.settings.account().format() != "imap" {
+            return Err(MeliError::new(format!(
+                "Account {} is not an IMAP account; Sieve scripts can only be managed for IMAP accounts.",
+                account.name()
+            ))
+            .set_kind(ErrorKind::NotSupported));
+        }
+        let sender = context.sender.clone();
+        let conn = new_managesieve_connection(
+            self.account_hash,
+            account.name().to_string(),
+            account.settings.account(),
+            BackendEventConsumer::new(Arc::new(
+                move |account_hash: AccountHash, ev: BackendEvent| {
+                    sender
+                        .send(ThreadEvent::UIEvent(UIEvent::BackendEvent(
+                            account_hash,
+                            ev,
+                        )))
+                        .unwrap();
+                },
+            )),
+        )?;
+        let conn = Arc::new(FutureMutex::new(conn));
+        self.connection = Some(conn.clone());
+        Ok(conn)
+    }
+
+    fn report_error(&mut self, title: &str, err: MeliError, context: &mut Context) {
+        context.replies.push_back(UIEvent::Notification(
+            Some(title.to_string()),
+            err.to_string(),
+            Some(NotificationType::Error(err.kind)),
+        ));
+        self.set_dirty(true);
+    }
+
+    fn insert_job(&mut self, job_id: JobId, job: SieveJob, context: &mut Context) {
+        context
+            .replies
+            .push_back(UIEvent::StatusEvent(StatusEvent::NewJob(job_id)));
+        self.active_jobs.insert(job_id, job);
+    }
+
+    fn refresh(&mut self, context: &mut Context) {
+        let conn = match self.connect(context) {
+            Ok(conn) => conn,
+            Err(err) => {
+                self.error = Some(err);
+                self.set_dirty(true);
+                return;
+            }
+        };
+        let handle = context.job_executor.spawn_specialized(async move {
+            let mut conn = conn.lock().await;
+            conn.listscripts().await
+        });
+        self.insert_job(handle.job_id, SieveJob::List(handle), context);
+    }
+
+    fn get_script(&mut self, name: String, context: &mut Context) {
+        if let Some((ref pending_name, ref script)) = self.pending {
+            if *pending_name == name {
+                let script = script.clone();
+                self.open_editor(name, script, context);
+                return;
+            }
+        }
+        let conn = match self.connect(context) {
+            Ok(conn) => conn,
+            Err(err) => return self.report_error("Could not fetch script", err, context),
+        };
+        let script_name = name.clone();
+        let handle = context.job_executor.spawn_specialized(async move {
+            let mut conn = conn.lock().await;
+            conn.getscript(script_name.as_bytes()).await
+        });
+        self.insert_job(handle.job_id, SieveJob::Get { name, handle }, context);
+    }
+
+    fn put_script(&mut self, name: String, script: String, context: &mut Context) {
+        let conn = match self.connect(context) {
+            Ok(conn) => conn,
+            Err(err) => {
+                self.pending = Some((name, script));
+                return self.report_error("Could not upload script", err, context);
+            }
+        };
+        let (script_name, script_text) = (name.clone(), script.clone());
+        let handle = context.job_executor.spawn_specialized(async move {
+            let mut conn = conn.lock().await;
+            conn.havespace(script_name.as_bytes(), script_text.len())
+                .await?;
+            conn.putscript(script_name.as_bytes(), script_text.as_bytes())
+                .await
+        });
+        self.insert_job(
+            handle.job_id,
+            SieveJob::Put {
+                name,
+                script,
+                handle,
+            },
+            context,
+        );
+    }
+
+    fn set_active(&mut self, name: String, context: &mut Context) {
+        let conn = match self.connect(context) {
+            Ok(conn) => conn,
+            Err(err) => return self.report_error("Could not activate script", err, context),
+        };
+        let script_name = name.clone();
+        let handle = context.job_executor.spawn_specialized(async move {
+            let mut conn = conn.lock().await;
+            conn.setactive(script_name.as_bytes()).await
+        });
+        self.insert_job(handle.job_id, SieveJob::SetActive { name, handle }, context);
+    }
+
+    fn delete_script(&mut self, name: String, context: &mut Context) {
+        let conn = match self.connect(context) {
+            Ok(conn) => conn,
+            Err(err) => return self.report_error("Could not delete script", err, context),
+        };
+        let script_name = name.clone();
+        let handle = context.job_executor.spawn_specialized(async move {
+            let mut conn = conn.lock().await;
+            conn.deletescript(script_name.as_bytes()).await
+        });
+        self.insert_job(handle.job_id, SieveJob::Delete { name, handle }, context);
+    }
+
+    fn open_editor(&mut self, name: String, script: String, context: &mut Context) {
+        let editor = if let Some(editor_command) =
+            account_settings!(context[self.account_hash].composing.editor_command).as_ref()
+        {
+            editor_command.to_string()
+        } else {
+            match std::env::var("EDITOR") {
+                Err(err) => {
+                    context.replies.push_back(UIEvent::Notification(
+                        Some(err.to_string()),
+                        "$EDITOR is not set. You can change an envvar's value with setenv or set composing.editor_command setting in your configuration.".to_string(),
+                        Some(NotificationType::Error(melib::error::ErrorKind::None)),
+                    ));
+                    return;
+                }
+                Ok(v) => v,
+            }
+        };
+        let f = create_temp_file(script.as_bytes(), None, None, true);
+        self.editing = Some((name, script));
+
+        if *account_settings!(context[self.account_hash].composing.embed) {
+            match crate::terminal::embed::create_pty(
+                width!(self.embed_area),
+                height!(self.embed_area),
+                [editor, f.path().display().to_string()].join(" "),
+            ) {
+                Ok(embed) => {
+                    self.embed = Some(EmbedStatus::Running(embed, f));
+                    self.set_dirty(true);
+                    context
+                        .replies
+                        .push_back(UIEvent::ChangeMode(UIMode::Embed));
+                    context.replies.push_back(UIEvent::Fork(ForkType::Embed(
+                        self.embed.as_ref().unwrap().lock().unwrap().child_pid,
+                    )));
+                }
+                Err(err) => {
+                    self.editing = None;
+                    context.replies.push_back(UIEvent::Notification(
+                        Some(format!("Failed to create pseudoterminal: {}", err)),
+                        err.to_string(),
+                        Some(NotificationType::Error(melib::error::ErrorKind::External)),
+                    ));
+                }
+            }
+            return;
+        }
+        /* Kill input thread so that spawned command can be sole receiver of stdin */
+        {
+            context.input_kill();
+        }
+
+        let editor_command = format!("{} {}", editor, f.path().display());
+        log(
+            format!(
+                "Executing: sh -c \"{}\"",
+                editor_command.replace("\"", "\\\"")
+            ),
+            DEBUG,
+        );
+        match Command::new("sh")
+            .args(["-c", &editor_command])
+            .stdin(Stdio::inherit())
+            .stdout(Stdio::inherit())
+            .spawn()
+        {
+            Ok(mut child) => {
+                let _ = child.wait();
+            }
+            Err(err) => {
+                self.editing = None;
+                context.replies.push_back(UIEvent::Notification(
+                    Some(format!("Failed to execute {}: {}", editor, err)),
+                    err.to_string(),
+                    Some(NotificationType::Error(melib::error::ErrorKind::External)),
+                ));
+                context.replies.push_back(UIEvent::Fork(ForkType::Finished));
+                context.restore_input();
+                return;
+            }
+        }
+        context.replies.push_back(UIEvent::Fork(ForkType::Finished));
+        self.finish_editing(f.read_to_string(), context);
+    }
+
+    /// Validate the edited script locally and upload it if it is well-formed.
+    fn finish_editing(&mut self, script: String, context: &mut Context) {
+        let (name, original) = match self.editing.take() {
+            Some(v) => v,
+            None => return,
+        };
+        self.set_dirty(true);
+        if script == original && self.scripts.iter().any(|(n, _)| *n == name) {
+            context
+                .replies
+                .push_back(UIEvent::StatusEvent(StatusEvent::DisplayMessage(format!(
+                    "Script {} was not modified.",
+                    name
+                ))));
+            return;
+        }
+        if let Err(err) = validate_sieve(&script) {
+            context.replies.push_back(UIEvent::Notification(
+                Some(format!("Script {} is not valid Sieve", name)),
+                format!(
+                    "{}\nThe script was not uploaded; edit it again to fix it.",
+                    err
+                ),
+                Some(NotificationType::Error(melib::error::ErrorKind::None)),
+            ));
+            self.pending = Some((name, script));
+            return;
+        }
+        self.put_script(name, script, context);
+    }
+
+    fn finish_embed(&mut self, context: &mut Context) {
+        if let Some(EmbedStatus::Running(_, f)) | Some(EmbedStatus::Stopped(_, f)) =
+            self.embed.take()
+        {
+            self.finish_editing(f.read_to_string(), context);
+        }
+    }
+
+    fn stop_embed(&mut self) {
+        if let Some(EmbedStatus::Running(e, f)) | Some(EmbedStatus::Stopped(e, f)) =
+            self.embed.take()
+        {
+            self.embed = Some(EmbedStatus::Stopped(e, f));
+        }
+    }
+
+    fn process_job(&mut self, job: SieveJob, context: &mut Context) {
+        match job {
+            SieveJob::List(mut handle) => match handle.chan.try_recv() {
+                Err(_) | Ok(None) => { /* Job was canceled */ }
+                Ok(Some(Err(err))) => {
+                    self.error = Some(err);
+                }
+                Ok(Some(Ok(scripts))) => {
+                    self.error = None;
+                    self.scripts = scripts
+                        .into_iter()
+                        .map(|(name, active)| (String::from_utf8_lossy(&name).to_string(), active))
+                        .collect();
+                    self.cursor_pos =
+                        std::cmp::min(self.cursor_pos, self.scripts.len().saturating_sub(1));
+                }
+            },
+            SieveJob::Get { name, mut handle } => match handle.chan.try_recv() {
+                Err(_) | Ok(None) => {}
+                Ok(Some(Err(err))) => {
+                    self.report_error(&format!("Could not fetch script {}", name), err, context)
+                }
+                Ok(Some(Ok(script))) => {
+                    self.open_editor(name, String::from_utf8_lossy(&script).to_string(), context)
+                }
+            },
+            SieveJob::Put {
+                name,
+                script,
+                mut handle,
+            } => match handle.chan.try_recv() {
+                Err(_) | Ok(None) => {
+                    self.pending = Some((name, script));
+                }
+                Ok(Some(Err(err))) => {
+                    self.pending = Some((name.clone(), script));
+                    self.report_error(&format!("Could not upload script {}", name), err, context)
+                }
+                Ok(Some(Ok(()))) => {
+                    if self
+                        .pending
+                        .as_ref()
+                        .map(|(n, _)| *n == name)
+                        .unwrap_or(false)
+                    {
+                        self.pending = None;
+                    }
+                    context
+                        .replies
+                        .push_back(UIEvent::StatusEvent(StatusEvent::DisplayMessage(format!(
+                            "Uploaded script {}.",
+                            name
+                        ))));
+                    self.refresh(context);
+                }
+            },
+            SieveJob::SetActive { name, mut handle } => match handle.chan.try_recv() {
+                Err(_) | Ok(None) => {}
+                Ok(Some(Err(err))) => {
+                    self.report_error(&format!("Could not activate script {}", name), err, context)
+                }
+                Ok(Some(Ok(()))) => {
+                    context
+                        .replies
+                        .push_back(UIEvent::StatusEvent(StatusEvent::DisplayMessage(
+                            if name.is_empty() {
+                                "Deactivated all scripts.".to_string()
+                            } else {
+                                format!("Activated script {}.", name)
+                            },
+                        )));
+                    self.refresh(context);
+                }
+            },
+            SieveJob::Delete { name, mut handle } => match handle.chan.try_recv() {
+                Err(_) | Ok(None) => {}
+                Ok(Some(Err(err))) => {
+                    self.report_error(&format!("Could not delete script {}", name), err, context)
+                }
+                Ok(Some(Ok(()))) => {
+                    context
+                        .replies
+                        .push_back(UIEvent::StatusEvent(StatusEvent::DisplayMessage(format!(
+                            "Deleted script {}.",
+                            name
+                        ))));
+                    self.refresh(context);
+                }
+            },
+        }
+        self.set_dirty(true);
+    }
+}
+
+impl Component for SieveFilters {
+    fn draw(&mut self, grid: &mut CellBuffer, area: Area, context: &mut Context) {
+        if !self.initialized {
+            self.initialized = true;
+            self.embed_area = area;
+            self.refresh(context);
+        }
+        if let Some(ref mut embed_pty) = self.embed {
+            match embed_pty {
+                EmbedStatus::Running(_, _) => {
+                    let mut guard = embed_pty.lock().unwrap();
+                    clear_area(grid, area, self.theme_default);
+                    copy_area(
+                        grid,
+                        guard.grid.buffer(),
+                        area,
+                        ((0, 0), pos_dec(guard.grid.terminal_size, (1, 1))),
+                    );
+                    guard.set_terminal_size((width!(area), height!(area)));
+                }
+                EmbedStatus::Stopped(_, _) => {
+                    let guard = embed_pty.lock().unwrap();
+                    copy_area(
+                        grid,
+                        guard.grid.buffer(),
+                        area,
+                        ((0, 0), pos_dec(guard.grid.terminal_size, (1, 1))),
+                    );
+                    change_colors(grid, area, Color::Byte(8), self.theme_default.bg);
+                    write_string_to_grid(
+                        "process has stopped, press 'e' to re-activate",
+                        grid,
+                        self.theme_default.fg,
+                        self.theme_default.bg,
+                        Attr::BOLD,
+                        area,
+                        None,
+                    );
+                }
+            }
+            self.embed_area = area;
+            context.dirty_areas.push_back(area);
+            self.dirty = false;
+            return;
+        }
+        if !self.dirty {
+            return;
+        }
+        self.dirty = false;
+        self.embed_area = area;
+        clear_area(grid, area, self.theme_default);
+        let upper_left = upper_left!(area);
+        let bottom_right = bottom_right!(area);
+        let (x, y) = write_string_to_grid(
+            "Sieve scripts of ",
+            grid,
+            self.theme_default.fg,
+            self.theme_default.bg,
+            self.theme_default.attrs | Attr::UNDERLINE,
+            (pos_inc(upper_left, (1, 0)), bottom_right),
+            None,
+        );
+        write_string_to_grid(
+            context.accounts[&self.account_hash].name(),
+            grid,
+            self.theme_default.fg,
+            self.theme_default.bg,
+            Attr::BOLD | Attr::UNDERLINE,
+            ((x, y), bottom_right),
+            None,
+        );
+        let mut line = get_y(upper_left) + 2;
+        if let Some(ref err) = self.error {
+            write_string_to_grid(
+                &err.to_string(),
+                grid,
+                Color::Red,
+                self.theme_default.bg,
+                self.theme_default.attrs,
+                ((get_x(upper_left) + 1, line), bottom_right),
+                Some(get_x(upper_left) + 1),
+            );
+        } else if self.scripts.is_empty() {
+            write_string_to_grid(
+                if self.active_jobs.is_empty() {
+                    "No scripts."
+                } else {
+                    "Loading..."
+                },
+                grid,
+                self.theme_default.fg,
+                self.theme_default.bg,
+                self.theme_default.attrs,
+                ((get_x(upper_left) + 1, line), bottom_right),
+                None,
+            );
+        } else {
+            let rows = height!(area).saturating_sub(2);
+            let skip = self
+                .cursor_pos
+                .checked_div(rows)
+                .map(|page| page * rows)
+                .unwrap_or(0);
+            for (idx, (name, active)) in self.scripts.iter().enumerate().skip(skip).take(rows) {
+                let theme = if idx == self.cursor_pos {
+                    self.highlight_theme
+                } else {
+                    self.theme_default
+                };
+                change_colors(
+                    grid,
+                    ((get_x(upper_left), line), (get_x(bottom_right), line)),
+                    theme.fg,
+                    theme.bg,
+                );
+                let pending = self
+                    .pending
+                    .as_ref()
+                    .map(|(n, _)| n == name)
+                    .unwrap_or(false);
+                write_string_to_grid(
+                    &format!(
+                        " {} {}{}",
+                        if *active { "[active]" } else { "        " },
+                        name,
+                        if pending { " (not uploaded)" } else { "" }
+                    ),
+                    grid,
+                    theme.fg,
+                    theme.bg,
+                    if *active {
+                        theme.attrs | Attr::BOLD
+                    } else {
+                        theme.attrs
+                    },
+                    ((get_x(upper_left), line), bottom_right),
+                    None,
+                );
+                line += 1;
+            }
+        }
+        context.dirty_areas.push_back(area);
+    }
+
+    fn process_event(&mut self, event: &mut UIEvent, context: &mut Context) -> bool {
+        let shortcuts = self.get_shortcuts(context);
+        match *event {
+            UIEvent::ConfigReload { old_settings: _ } => {
+                self.theme_default = crate::conf::value(context, "theme_default");
+                self.highlight_theme = crate::conf::value(context, "highlight");
+                self.set_dirty(true);
+            }
+            UIEvent::Resize => {
+                self.set_dirty(true);
+            }
+            UIEvent::StatusEvent(StatusEvent::JobFinished(ref job_id))
+                if self.active_jobs.contains_key(job_id) =>
+            {
+                let job = self.active_jobs.remove(job_id).unwrap();
+                self.process_job(job, context);
+                return true;
+            }
+            UIEvent::FinishedUIDialog(id, ref mut result) if id == self.id() => {
+                if let Some(Some(name)) = result.downcast_mut::<Option<String>>() {
+                    let name = std::mem::take(name);
+                    self.delete_script(name, context);
+                }
+                return true;
+            }
+            UIEvent::Action(Action::Filters(FiltersAction::NewScript(ref name))) => {
+                if self.scripts.iter().any(|(n, _)| n == name) {
+                    context
+                        .replies
+                        .push_back(UIEvent::StatusEvent(StatusEvent::DisplayMessage(format!(
+                            "Script {} already exists.",
+                            name
+                        ))));
+                } else {
+                    let script = format!("# Sieve script {}\n", name);
+                    self.open_editor(name.to_string(), script, context);
+                }
+                return true;
+            }
+            UIEvent::EmbedInput((Key::Ctrl('z'), _)) if self.embed.is_some() => {
+                self.embed.as_ref().unwrap().lock().unwrap().stop();
+                self.stop_embed();
+                context
+                    .replies
+                    .push_back(UIEvent::ChangeMode(UIMode::Normal));
+                self.set_dirty(true);
+                return true;
+            }
+            UIEvent::EmbedInput((ref k, ref b)) if self.embed.is_some() => {
+                use std::io::Write;
+                let status = {
+                    let mut embed_guard = self.embed.as_ref().unwrap().lock().unwrap();
+                    if embed_guard.stdin.write_all(b).is_ok() {
+                        None
+                    } else {
+                        Some(embed_guard.is_active())
+                    }
+                };
+                if let Some(status) = status {
+                    match status {
+                        Ok(WaitStatus::Exited(_, exit_code)) => {
+                            if exit_code != 0 {
+                                self.embed = None;
+                                self.editing = None;
+                                context.replies.push_back(UIEvent::Notification(
+                                    None,
+                                    format!("Subprocess has exited with exit code {}", exit_code),
+                                    Some(NotificationType::Error(
+                                        melib::error::ErrorKind::External,
+                                    )),
+                                ));
+                            } else {
+                                self.finish_embed(context);
+                            }
+                            context
+                                .replies
+                                .push_back(UIEvent::ChangeMode(UIMode::Normal));
+                        }
+                        #[cfg(any(target_os = "linux", target_os = "android"))]
+                        Ok(WaitStatus::PtraceEvent(_, _, _)) | Ok(WaitStatus::PtraceSyscall(_)) => {
+                            self.stop_embed();
+                            context
+                                .replies
+                                .push_back(UIEvent::ChangeMode(UIMode::Normal));
+                        }
+                        Ok(WaitStatus::Stopped(_, _)) => {
+                            self.stop_embed();
+                            context
+                                .replies
+                                .push_back(UIEvent::ChangeMode(UIMode::Normal));
+                        }
+                        Ok(WaitStatus::Continued(_)) | Ok(WaitStatus::StillAlive) => {
+                            context
+                                .replies
+                                .push_back(UIEvent::EmbedInput((k.clone(), b.to_vec())));
+                            return true;
+                        }
+                        Ok(WaitStatus::Signaled(_, signal, _)) => {
+                            self.embed = None;
+                            self.editing = None;
+                            context.replies.push_back(UIEvent::Notification(
+                                None,
+                                format!("Subprocess was killed by {} signal", signal),
+                                Some(NotificationType::Error(melib::error::ErrorKind::External)),
+                            ));
+                            context
+                                .replies
+                                .push_back(UIEvent::ChangeMode(UIMode::Normal));
+                        }
+                        Err(err) => {
+                            self.embed = None;
+                            self.editing = None;
+                            context.replies.push_back(UIEvent::Notification(
+                                Some("Embed editor crashed.".to_string()),
+                                format!("Subprocess has exited with reason {}", &err),
+                                Some(NotificationType::Error(melib::error::ErrorKind::External)),
+                            ));
+                            context
+                                .replies
+                                .push_back(UIEvent::ChangeMode(UIMode::Normal));
+                        }
+                    }
+                }
+                self.set_dirty(true);
+                return true;
+            }
+            UIEvent::Input(ref key)
+                if self.embed.is_some()
+                    && shortcut!(key == shortcuts[Self::DESCRIPTION]["edit_script"]) =>
+            {
+                self.embed.as_ref().unwrap().lock().unwrap().wake_up();
+                if let Some(EmbedStatus::Running(e, f)) | Some(EmbedStatus::Stopped(e, f)) =
+                    self.embed.take()
+                {
+                    self.embed = Some(EmbedStatus::Running(e, f));
+                }
+                context
+                    .replies
+                    .push_back(UIEvent::ChangeMode(UIMode::Embed));
+                self.set_dirty(true);
+                return true;
+            }
+            UIEvent::Input(_) if self.embed.is_some() => {
+                /* The stopped editor owns the tab until it is resumed. */
+            }
+            UIEvent::Input(ref key)
+                if shortcut!(key == shortcuts[Self::DESCRIPTION]["scroll_up"]) =>
+            {
+                self.cursor_pos = self.cursor_pos.saturating_sub(1);
+                self.set_dirty(true);
+                return true;
+            }
+            UIEvent::Input(ref key)
+                if shortcut!(key == shortcuts[Self::DESCRIPTION]["scroll_down"]) =>
+            {
+                if self.cursor_pos + 1 < self.scripts.len() {
+                    self.cursor_pos += 1;
+                    self.set_dirty(true);
+                }
+                return true;
+            }
+            UIEvent::Input(ref key)
+                if shortcut!(key == shortcuts[Self::DESCRIPTION]["refresh"]) =>
+            {
+                self.refresh(context);
+                self.set_dirty(true);
+                return true;
+            }
+            UIEvent::Input(ref key)
+                if !self.scripts.is_empty()
+                    && shortcut!(key == shortcuts[Self::DESCRIPTION]["edit_script"]) =>
+            {
+                let name = self.scripts[self.cursor_pos].0.clone();
+                self.get_script(name, context);
+                return true;
+            }
+            UIEvent::Input(ref key)
+                if !self.scripts.is_empty()
+                    && shortcut!(key == shortcuts[Self::DESCRIPTION]["toggle_active"]) =>
+            {
+                let (ref name, active) = self.scripts[self.cursor_pos];
+                /* Activating the empty script name deactivates all scripts. */
+                let name = if active { String::new() } else { name.clone() };
+                self.set_active(name, context);
+                return true;
+            }
+            UIEvent::Input(ref key)
+                if !self.scripts.is_empty()
+                    && shortcut!(key == shortcuts[Self::DESCRIPTION]["delete_script"]) =>
+            {
+                let name = self.scripts[self.cursor_pos].0.clone();
+                let id = self.id();
+                context.replies.push_back(UIEvent::GlobalUIDialog(Box::new(
+                    UIConfirmationDialog::new(
+                        &format!("Delete script {}?", name),
+                        vec![(true, "yes".to_string()), (false, "no".to_string())],
+                        true,
+                        Some(Box::new(move |_, result: bool| {
+                            Some(UIEvent::FinishedUIDialog(
+                                id,
+                                Box::new(if result { Some(name.clone()) } else { None }),
+                            ))
+                        })),
+                        context,
+                    ),
+                )));
+                return true;
+            }
+            _ => {}
+        }
+        false
+    }
+
+    fn get_shortcuts(&self, context: &Context) -> ShortcutMaps {
+        let mut ret: ShortcutMaps = Default::default();
+        ret.insert(
+            Self::DESCRIPTION,
+            context.settings.shortcuts.filters.key_values(),
+        );
+        ret
+    }
+
+    fn get_status(&self, context: &Context) -> String {
+        format!(
+            "{} scripts on {}",
+            self.scripts.len(),
+            context.accounts[&self.account_hash].name()
+        )
+    }
+
+    fn kill(&mut self, uuid: ComponentId, context: &mut Context) {
+        if self.id != uuid {
+            return;
+        }
+        for job in self.active_jobs.values() {
+            match job {
+                SieveJob::List(handle) => handle.cancel(),
+                SieveJob::Get { handle, .. } => handle.cancel(),
+                SieveJob::Put { handle, .. }
+                | SieveJob::SetActive { handle, .. }
+                | SieveJob::Delete { handle, .. } => handle.cancel(),
+            }
+        }
+        context
+            .replies
+            .push_back(UIEvent::Action(Tab(Kill(self.id))));
+    }
+
+    fn is_dirty(&self) -> bool {
+        self.dirty
+            || self
+                .embed
+                .as_ref()
+                .map(|e| matches!(e, EmbedStatus::Running(_, _)))
+                .unwrap_or(false)
+    }
+
+    fn set_dirty(&mut self, value: bool) {
+        self.dirty = value;
+    }
+
+    fn id(&self) -> ComponentId {
+        self.id
+    }
+
+    fn set_id(&mut self, id: ComponentId) {
+        self.id = id;
+    }
+}
@@ -121,7 +121,8 @@ impl Component for AccountStatus {
                 ..
             }
             | JobRequest::Refresh { mailbox_hash, .. }
-            | JobRequest::Fetch { mailbox_hash, .. } = req
+            | JobRequest::Fetch { mailbox_hash, .. }
+            | JobRequest::SavedSearch { mailbox_hash, .. } = req
             {
                 write_string_to_grid(
                     a.mailbox_entries[mailbox_hash].name(),
@@ -23,6 +23,8 @@ use super::*;
 use crate::conf::accounts::JobRequest;
 use crate::jobs::{JobId, JoinHandle};
 use melib::email::attachment_types::ContentType;
+use melib::email::compose::NewsFollowup;
+use melib::email::delivery_status::MdnRequest;
 use melib::list_management;
 use melib::parser::BytesExt;
 use smallvec::SmallVec;
@@ -33,8 +35,12 @@ use std::convert::TryFrom;
 use std::os::unix::fs::PermissionsExt;
 use std::process::{Command, Stdio};
 
+mod attachment_tree;
+pub use self::attachment_tree::*;
 mod html;
 pub use self::html::*;
+mod image;
+pub use self::image::*;
 mod thread;
 pub use self::thread::*;
 
@@ -102,6 +108,10 @@ pub enum AttachmentDisplay {
         comment: Option<String>,
         text: String,
     },
+    /// A `text/html` part shown with the built-in renderer, rendered to the width of the view.
+    InlineHtml {
+        inner: Attachment,
+    },
     InlineOther {
         inner: Attachment,
     },
@@ -162,12 +172,38 @@ pub struct MailView {
     force_draw_headers: bool,
     theme_default: ThemeAttribute,
     active_jobs: HashSet<JobId>,
+    delivery_reports_job: Option<JobId>,
+    /// The message was unseen when it was opened; if it asks for a read receipt, the
+    /// `read_receipts` policy is applied once its body is loaded.
+    read_receipt_pending: bool,
+    /// Bytes of a message/rfc822 part of the envelope at `coordinates`, if this view shows that
+    /// embedded message instead of the envelope.
+    embedded: Option<Vec<u8>>,
+    /// Columns that inline HTML parts are rendered to.
+    body_width: usize,
     state: MailViewState,
 
     cmd_buf: String,
     id: ComponentId,
 }
 
+/// The envelope a `MailView` shows: an envelope of the account, or a message embedded in it.
+enum ViewedEnvelope<'a> {
+    Collection(EnvelopeRef<'a>),
+    Embedded(Box<Envelope>),
+}
+
+impl std::ops::Deref for ViewedEnvelope<'_> {
+    type Target = Envelope;
+
+    fn deref(&self) -> &Envelope {
+        match self {
+            ViewedEnvelope::Collection(env) => env,
+            ViewedEnvelope::Embedded(env) => env,
+        }
+    }
+}
+
 #[derive(Debug, Copy, Clone)]
 pub enum PendingReplyAction {
     Reply,
@@ -195,6 +231,10 @@ enum MailViewState {
         body: Attachment,
         display: Vec<AttachmentDisplay>,
         body_text: String,
+        /// Attributes of the styled parts of `body_text`.
+        body_attrs: Vec<(usize, usize, usize, Attr)>,
+        /// Previews of the image attachments, drawn beneath `body_text` once they are decoded.
+        images: ImagePreviews,
         links: Vec<Link>,
     },
 }
@@ -229,6 +269,7 @@ impl Clone for MailView {
             mode: ViewMode::Normal,
             attachment_tree: self.attachment_tree.clone(),
             attachment_paths: self.attachment_paths.clone(),
+            embedded: self.embedded.clone(),
             state: MailViewState::default(),
             active_jobs: self.active_jobs.clone(),
             ..*self
@@ -267,6 +308,10 @@ impl MailView {
 
             theme_default: crate::conf::value(context, "mail.view.body"),
             active_jobs: Default::default(),
+            delivery_reports_job: None,
+            read_receipt_pending: false,
+            embedded: None,
+            body_width: 80,
             state: MailViewState::default(),
 
             cmd_buf: String::with_capacity(4),
@@ -277,12 +322,69 @@ impl MailView {
         ret
     }
 
+    /// View `bytes`, a message/rfc822 part of the envelope at `coordinates`.
+    pub fn new_embedded(
+        coordinates: (AccountHash, MailboxHash, EnvelopeHash),
+        bytes: Vec<u8>,
+        context: &mut Context,
+    ) -> Self {
+        let mut ret = MailView {
+            embedded: Some(bytes),
+            body_width: 80,
+            ..MailView::default()
+        };
+        ret.coordinates = coordinates;
+        ret.dirty = true;
+        ret.headers_no = 5;
+        ret.cmd_buf = String::with_capacity(4);
+        ret.id = ComponentId::new_v4();
+        ret.init_futures(context);
+        ret
+    }
+
     fn init_futures(&mut self, context: &mut Context) {
         debug!("init_futures");
         self.theme_default = crate::conf::value(context, "mail.view.body");
+        if let Some(ref bytes) = self.embedded {
+            let bytes = bytes.clone();
+            /* The message is already in memory and has no flags of its own to update */
+            match Envelope::from_bytes(&bytes, Some(Flag::SEEN)) {
+                Ok(env) => {
+                    let body = AttachmentBuilder::new(&bytes).build();
+                    let display = Self::attachment_to(
+                        &body,
+                        context,
+                        self.coordinates,
+                        &mut self.active_jobs,
+                    );
+                    let (paths, attachment_tree_s) = self.attachment_displays_to_tree(&display);
+                    self.attachment_tree = attachment_tree_s;
+                    self.attachment_paths = paths;
+                    let (body_text, body_attrs) =
+                        self.attachment_displays_to_text(&display, context, true);
+                    let images = self.image_previews(&body, context);
+                    self.state = MailViewState::Loaded {
+                        display,
+                        env,
+                        body,
+                        bytes,
+                        body_text,
+                        body_attrs,
+                        images,
+                        links: vec![],
+                    };
+                }
+                Err(err) => {
+                    self.state = MailViewState::Error { err };
+                }
+            }
+            return;
+        }
         let mut pending_action = None;
         let account = &mut context.accounts[&self.coordinates.0];
         if debug!(account.contains_key(self.coordinates.2)) {
+            /* Parse any new delivery reports, in case they are about this message */
+            self.delivery_reports_job = account.fetch_delivery_reports(self.coordinates.2);
             {
                 match account
                     .operation(self.coordinates.2)
@@ -326,14 +428,17 @@ impl MailView {
                                         self.attachment_displays_to_tree(&display);
                                     self.attachment_tree = attachment_tree_s;
                                     self.attachment_paths = paths;
-                                    let body_text =
+                                    let (body_text, body_attrs) =
                                         self.attachment_displays_to_text(&display, context, true);
+                                    let images = self.image_previews(&body, context);
                                     self.state = MailViewState::Loaded {
                                         display,
                                         env,
                                         body,
                                         bytes,
                                         body_text,
+                                        body_attrs,
+                                        images,
                                         links: vec![],
                                     };
                                 }
@@ -361,9 +466,11 @@ impl MailView {
             }
             let account = &mut context.accounts[&self.coordinates.0];
             if !account.collection.get_env(self.coordinates.2).is_seen() {
+                self.read_receipt_pending = true;
+                let mailbox_hash = account.envelope_mailbox(self.coordinates.1, self.coordinates.2);
                 let job = account.backend.write().unwrap().set_flags(
                     self.coordinates.2.into(),
-                    self.coordinates.1,
+                    mailbox_hash,
                     smallvec::smallvec![(Ok(Flag::SEEN), true)],
                 );
                 match job {
@@ -388,11 +495,68 @@ impl MailView {
                 };
             }
         }
+        self.check_read_receipt(context);
         if let Some(p) = pending_action {
             self.perform_action(p, context);
         }
     }
 
+    /// Apply the `read_receipts` policy to a newly opened message that asks for a read receipt.
+    fn check_read_receipt(&mut self, context: &mut Context) {
+        let env = match self.state {
+            MailViewState::Loaded { ref env, .. } if self.read_receipt_pending => env,
+            _ => return,
+        };
+        self.read_receipt_pending = false;
+        if NewsFollowup::detect(env).is_some() {
+            return;
+        }
+        let request = match MdnRequest::from_envelope(env) {
+            Some(request) => request,
+            None => return,
+        };
+        match mailbox_settings!(
+            context[self.coordinates.0][&self.coordinates.1]
+                .composing
+                .read_receipts
+        ) {
+            ReadReceiptPolicy::Never => {}
+            ReadReceiptPolicy::Always if !request.needs_confirmation => {
+                send_read_receipt(env, &request, true, self.coordinates.0, context);
+            }
+            _ => {
+                let id = self.id;
+                context.replies.push_back(UIEvent::GlobalUIDialog(Box::new(
+                    UIConfirmationDialog::new(
+                        &format!(
+                            "Send a read receipt to {}?{}",
+                            request
+                                .notify_to
+                                .iter()
+                                .map(|addr| addr.to_string())
+                                .collect::<Vec<String>>()
+                                .join(", "),
+                            if request.needs_confirmation {
+                                " It is not the return path of the message."
+                            } else {
+                                ""
+                            }
+                        ),
+                        vec![(true, "yes".to_string()), (false, "no".to_string())],
+                        true,
+                        Some(Box::new(move |_: ComponentId, result: bool| {
+                            Some(UIEvent::FinishedUIDialog(
+                                id,
+                                Box::new(if result { Some(request.clone()) } else { None }),
+                            ))
+                        })),
+                        context,
+                    ),
+                )));
+            }
+        }
+    }
+
     fn perform_action(&mut self, action: PendingReplyAction, context: &mut Context) {
         let (bytes, reply_body, env) = match self.state {
             MailViewState::Init {
@@ -415,13 +579,27 @@ impl MailView {
                 ..
             } => (
                 bytes,
-                self.attachment_displays_to_text(&display, context, false),
+                self.attachment_displays_to_text(display, context, false).0,
                 env,
             ),
             MailViewState::Error { .. } => {
                 return;
             }
         };
+        if self.embedded.is_some()
+            && !matches!(
+                action,
+                PendingReplyAction::ForwardAttachment | PendingReplyAction::ForwardInline
+            )
+        {
+            context
+                .replies
+                .push_back(UIEvent::StatusEvent(StatusEvent::DisplayMessage(
+                    "Replying to an embedded message is not supported; forward it instead."
+                        .to_string(),
+                )));
+            return;
+        }
         let composer = match action {
             PendingReplyAction::Reply => Box::new(Composer::reply_to_select(
                 self.coordinates,
@@ -459,13 +637,28 @@ impl MailView {
             .push_back(UIEvent::Action(Tab(New(Some(composer)))));
     }
 
+    /// The text of `displays` and the attributes of its styled parts (line, first column, column
+    /// after the last one).
     fn attachment_displays_to_text(
         &self,
         displays: &[AttachmentDisplay],
         context: &mut Context,
         show_comments: bool,
-    ) -> String {
+    ) -> (String, Vec<(usize, usize, usize, Attr)>) {
         let mut acc = String::new();
+        let mut attrs = vec![];
+        self.push_attachment_displays_text(displays, context, show_comments, &mut acc, &mut attrs);
+        (acc, attrs)
+    }
+
+    fn push_attachment_displays_text(
+        &self,
+        displays: &[AttachmentDisplay],
+        context: &mut Context,
+        show_comments: bool,
+        acc: &mut String,
+        attrs: &mut Vec<(usize, usize, usize, Attr)>,
+    ) {
         for d in displays {
             use AttachmentDisplay::*;
             match d {
@@ -474,11 +667,13 @@ impl MailView {
                     shown_display,
                     display,
                 } => {
-                    acc.push_str(&self.attachment_displays_to_text(
+                    self.push_attachment_displays_text(
                         &display[*shown_display..(*shown_display + 1)],
                         context,
                         show_comments,
-                    ));
+                        acc,
+                        attrs,
+                    );
                 }
                 InlineText {
                     inner: _,
@@ -489,13 +684,38 @@ impl MailView {
                     if !acc.ends_with("\n\n") {
                         acc.push_str("\n\n");
                     }
-                    acc.push_str(&text);
+                    acc.push_str(text);
                 }
                 InlineText {
                     inner: _,
                     text,
                     comment: _,
-                } => acc.push_str(&text),
+                } => acc.push_str(text),
+                InlineHtml { inner } => {
+                    if show_comments {
+                        acc.push_str(
+                            "Text rendered by the built-in HTML renderer. Press `v` to open in web browser. \n\n",
+                        );
+                    }
+                    if !acc.is_empty() && !acc.ends_with('\n') {
+                        acc.push('\n');
+                    }
+                    let first_line = acc.matches('\n').count();
+                    let mut lines = vec![];
+                    let mut html_attrs = vec![];
+                    html_to_lines(
+                        &String::from_utf8_lossy(&decode(inner, None)),
+                        self.body_width,
+                        &mut lines,
+                        &mut html_attrs,
+                    );
+                    attrs.extend(
+                        html_attrs
+                            .into_iter()
+                            .map(|(y, start, end, attr)| (first_line + y, start, end, attr)),
+                    );
+                    acc.push_str(&lines.join("\n"));
+                }
                 InlineOther { inner } => {
                     if !acc.ends_with("\n\n") {
                         acc.push_str("\n\n");
@@ -515,17 +735,13 @@ impl MailView {
                     if show_comments {
                         acc.push_str("Waiting for signature verification.\n\n");
                     }
-                    acc.push_str(&self.attachment_displays_to_text(
-                        display,
-                        context,
-                        show_comments,
-                    ));
+                    self.push_attachment_displays_text(display, context, show_comments, acc, attrs);
                 }
                 SignedUnverified { inner: _, display } => {
                     if show_comments {
                         acc.push_str("Unverified signature.\n\n");
                     }
-                    acc.push_str(&self.attachment_displays_to_text(display, context, show_comments))
+                    self.push_attachment_displays_text(display, context, show_comments, acc, attrs);
                 }
                 SignedFailed {
                     inner: _,
@@ -535,11 +751,7 @@ impl MailView {
                     if show_comments {
                         acc.push_str(&format!("Failed to verify signature: {}.\n\n", error));
                     }
-                    acc.push_str(&self.attachment_displays_to_text(
-                        display,
-                        context,
-                        show_comments,
-                    ));
+                    self.push_attachment_displays_text(display, context, show_comments, acc, attrs);
                 }
                 SignedVerified {
                     inner: _,
@@ -554,11 +766,7 @@ impl MailView {
                             acc.push_str("\n\n");
                         }
                     }
-                    acc.push_str(&self.attachment_displays_to_text(
-                        display,
-                        context,
-                        show_comments,
-                    ));
+                    self.push_attachment_displays_text(display, context, show_comments, acc, attrs);
                 }
                 EncryptedPending { .. } => acc.push_str("Waiting for decryption result."),
                 EncryptedFailed { inner: _, error } => {
@@ -578,15 +786,16 @@ impl MailView {
                             acc.push_str("\n\n");
                         }
                     }
-                    acc.push_str(&self.attachment_displays_to_text(
+                    self.push_attachment_displays_text(
                         plaintext_display,
                         context,
                         show_comments,
-                    ));
+                        acc,
+                        attrs,
+                    );
                 }
             }
         }
-        acc
     }
 
     fn attachment_displays_to_tree(
@@ -623,6 +832,7 @@ impl MailView {
                     text: _,
                     comment: _,
                 }
+                | InlineHtml { inner }
                 | InlineOther { inner }
                 | Attachment { inner }
                 | EncryptedPending { inner, handle: _ }
@@ -738,11 +948,17 @@ impl MailView {
                 acc.push(AttachmentDisplay::Attachment { inner: a.clone() });
             } else if a.content_type().is_text_html() {
                 let bytes = decode(a, None);
-                let filter_invocation =
-                    mailbox_settings!(context[coordinates.0][&coordinates.1].pager.html_filter)
-                        .as_ref()
-                        .map(|s| s.as_str())
-                        .unwrap_or("w3m -I utf-8 -T text/html");
+                let filter_invocation = match mailbox_settings!(
+                    context[coordinates.0][&coordinates.1].pager.html_filter
+                )
+                .as_ref()
+                {
+                    Some(filter_invocation) => filter_invocation.as_str(),
+                    None => {
+                        acc.push(AttachmentDisplay::InlineHtml { inner: a.clone() });
+                        return;
+                    }
+                };
                 let command_obj = Command::new("sh")
                     .args(&["-c", filter_invocation])
                     .stdin(Stdio::piped())
@@ -793,10 +1009,18 @@ impl MailView {
                 }
             } else if a.is_text() {
                 let bytes = decode(a, None);
+                let mut text = String::from_utf8_lossy(&bytes).to_string();
+                if let Some(delsp) = a.format_flowed_delsp() {
+                    if *mailbox_settings!(
+                        context[coordinates.0][&coordinates.1].pager.format_flowed
+                    ) {
+                        text = melib::email::attachments::interpret_format_flowed(&text, delsp);
+                    }
+                }
                 acc.push(AttachmentDisplay::InlineText {
                     inner: a.clone(),
                     comment: None,
-                    text: String::from_utf8_lossy(&bytes).to_string(),
+                    text,
                 });
             } else if let ContentType::Multipart {
                 ref kind,
@@ -953,6 +1177,88 @@ impl MailView {
         }
     }
 
+    /// The envelope shown; a copy of the loaded message if it is embedded.
+    fn envelope<'a>(&self, account: &'a Account) -> ViewedEnvelope<'a> {
+        match self.state {
+            MailViewState::Loaded { ref env, .. } if self.embedded.is_some() => {
+                ViewedEnvelope::Embedded(Box::new(env.clone()))
+            }
+            _ => ViewedEnvelope::Collection(account.collection.get_env(self.coordinates.2)),
+        }
+    }
+
+    /// Start decoding the image previews of `body` in a background job.
+    fn image_previews(&mut self, body: &Attachment, context: &mut Context) -> ImagePreviews {
+        let images =
+            ImagePreviews::new(body, context.settings.terminal.graphics_protocol(), context);
+        if let Some(job_id) = images.job_id() {
+            self.active_jobs.insert(job_id);
+            context
+                .replies
+                .push_back(UIEvent::StatusEvent(StatusEvent::NewJob(job_id)));
+        }
+        images
+    }
+
+    /// Draw the previews whose empty lines under their caption are all shown in `pager`.
+    fn draw_image_previews(
+        pager: &Pager,
+        images: &mut [ImagePreview],
+        grid: &mut CellBuffer,
+        area: Area,
+        context: &mut Context,
+    ) {
+        let cols = width!(area).saturating_sub(4);
+        /* The last row may be taken by a scrollbar */
+        let (top, bottom) = (
+            pager.cursor_pos(),
+            pager.cursor_pos() + height!(area).saturating_sub(1),
+        );
+        for image in images.iter_mut() {
+            let rows = image.rows(cols);
+            let line = match pager.find_line(image.caption()) {
+                Some(line) if line >= top && line + rows < bottom && rows > 0 => line + 1,
+                _ => continue,
+            };
+            let upper_left = pos_inc(upper_left!(area), (0, line - top));
+            image.draw(
+                grid,
+                (upper_left, pos_inc(upper_left, (cols - 1, rows - 1))),
+                context,
+            );
+        }
+    }
+
+    /// Links from the envelope's headers to offer in the pager's link hint mode: `List-*` URLs
+    /// and the message-ids of `In-Reply-To` and `References`.
+    fn header_links(&self, context: &Context) -> Vec<PagerLink> {
+        let account = &context.accounts[&self.coordinates.0];
+        if !account.contains_key(self.coordinates.2) {
+            return vec![];
+        }
+        let envelope = self.envelope(account);
+        let mut ret = list_management::list_header_urls(&envelope)
+            .into_iter()
+            .map(|(header, url)| PagerLink::from_url(&url, header))
+            .collect::<Vec<PagerLink>>();
+        for (header, msg_id) in envelope
+            .in_reply_to()
+            .into_iter()
+            .map(|m| ("In-Reply-To", m))
+            .chain(envelope.references().into_iter().map(|m| ("References", m)))
+        {
+            let target = String::from_utf8_lossy(msg_id.raw()).to_string();
+            if !ret.iter().any(|l| l.target == target) {
+                ret.push(PagerLink {
+                    kind: PagerLinkKind::MessageId,
+                    target,
+                    source: Some(header.to_string()),
+                });
+            }
+        }
+        ret
+    }
+
     fn open_attachment(
         &'_ self,
         lidx: usize,
@@ -987,6 +1293,7 @@ impl MailView {
                     text: _,
                     comment: _,
                 }
+                | InlineHtml { inner }
                 | InlineOther { inner }
                 | Attachment { inner }
                 | SignedPending {
@@ -1068,7 +1375,7 @@ impl MailView {
                 )));
             return;
         }
-        let envelope: EnvelopeRef = account.collection.get_env(self.coordinates.2);
+        let envelope = self.envelope(account);
 
         let mut entries = Vec::new();
         for addr in envelope.from().iter().chain(envelope.to().iter()) {
@@ -1110,7 +1417,7 @@ impl Component for MailView {
                  * arrive */
                 return;
             }
-            let envelope: EnvelopeRef = account.collection.get_env(self.coordinates.2);
+            let envelope = self.envelope(account);
 
             let headers = crate::conf::value(context, "mail.view.headers");
             let headers_names = crate::conf::value(context, "mail.view.headers_names");
@@ -1362,6 +1669,9 @@ impl Component for MailView {
                         y += 1;
                     }
                 }
+                for (recipient, status) in account.delivery_status(&envelope.message_id_raw()) {
+                    print_header!(("Delivery-Status:", format!("{}: {}", recipient, status)));
+                }
 
                 self.force_draw_headers = false;
                 clear_area(
@@ -1397,15 +1707,52 @@ impl Component for MailView {
         };
 
         if !self.initialised {
-            let (body, body_text, bytes, links) = if let MailViewState::Loaded {
+            /* Inline HTML parts are rendered to the width of the view */
+            let body_width = width!(area).saturating_sub(1);
+            if self.body_width != body_width {
+                self.body_width = body_width;
+                if let MailViewState::Loaded { ref display, .. } = self.state {
+                    let (new_body_text, new_body_attrs) =
+                        self.attachment_displays_to_text(display, context, true);
+                    if let MailViewState::Loaded {
+                        ref mut body_text,
+                        ref mut body_attrs,
+                        ref mut links,
+                        ..
+                    } = self.state
+                    {
+                        links.clear();
+                        *body_text = new_body_text;
+                        *body_attrs = new_body_attrs;
+                    }
+                }
+            }
+            /* Empty lines for the image previews to be drawn over */
+            let image_lines = if let MailViewState::Loaded { ref mut images, .. } = self.state {
+                let cols = width!(area).saturating_sub(4);
+                let mut lines = String::new();
+                for image in images.previews_mut() {
+                    lines.push_str(image.caption());
+                    lines.push('\n');
+                    for _ in 0..image.rows(cols) {
+                        lines.push('\n');
+                    }
+                    lines.push('\n');
+                }
+                lines
+            } else {
+                String::new()
+            };
+            let (body, body_text, body_attrs, bytes, links) = if let MailViewState::Loaded {
                 ref body,
                 ref body_text,
+                ref body_attrs,
                 ref bytes,
                 ref mut links,
                 ..
             } = self.state
             {
-                (body, body_text, bytes, links)
+                (body, body_text, body_attrs, bytes, links)
             } else if let MailViewState::Error { ref err } = self.state {
                 clear_area(
                     grid,
@@ -1444,7 +1791,9 @@ impl Component for MailView {
                     let mut text = "Viewing attachment. Press `r` to return \n".to_string();
                     if let Some(attachment) = self.open_attachment(aidx, context) {
                         if attachment.is_html() {
-                            self.subview = Some(Box::new(HtmlView::new(&attachment, context)));
+                            let mut html = HtmlView::new(attachment, context);
+                            html.set_account_hash(Some(self.coordinates.0));
+                            self.subview = Some(Box::new(html));
                             self.mode = ViewMode::Subview;
                         } else {
                             text.push_str(&attachment.text());
@@ -1475,7 +1824,10 @@ impl Component for MailView {
                     }
                 }
                 ViewMode::Normal if body.is_html() => {
-                    self.subview = Some(Box::new(HtmlView::new(&body, context)));
+                    let mut html = HtmlView::new(body, context);
+                    html.set_account_hash(Some(self.coordinates.0))
+                        .set_extra_links(self.header_links(context));
+                    self.subview = Some(Box::new(html));
                     self.mode = ViewMode::Subview;
                 }
                 ViewMode::Normal
@@ -1496,7 +1848,7 @@ impl Component for MailView {
                             _ => false,
                         } =>
                 {
-                    self.subview = Some(Box::new(HtmlView::new(
+                    let mut html = HtmlView::new(
                         &body
                             .content_type
                             .parts()
@@ -1505,7 +1857,10 @@ impl Component for MailView {
                             .find(|a| a.is_html())
                             .unwrap_or(&body),
                         context,
-                    )));
+                    );
+                    html.set_account_hash(Some(self.coordinates.0))
+                        .set_extra_links(self.header_links(context));
+                    self.subview = Some(Box::new(html));
                     self.mode = ViewMode::Subview;
                     self.initialised = false;
                 }
@@ -1609,6 +1964,19 @@ impl Component for MailView {
                     let colors = crate::conf::value(context, "mail.view.body");
                     self.pager =
                         Pager::from_string(text, Some(context), Some(cursor_pos), None, colors);
+                    let header_links = self.header_links(context);
+                    self.pager
+                        .set_account_hash(Some(self.coordinates.0))
+                        .set_extra_links(header_links);
+                    if *mailbox_settings!(
+                        context[self.coordinates.0][&self.coordinates.1]
+                            .pager
+                            .format_flowed
+                    ) && has_format_flowed(body)
+                    {
+                        self.pager
+                            .set_reflow(melib::text_processing::Reflow::FormatFlowed);
+                    }
                     if let Some(ref filter) = mailbox_settings!(
                         context[self.coordinates.0][&self.coordinates.1]
                             .pager
@@ -1623,6 +1991,7 @@ impl Component for MailView {
                     if !text.ends_with("\n\n") {
                         text.push_str("\n\n");
                     }
+                    text.push_str(&image_lines);
                     text.push_str(&self.attachment_tree);
                     let cursor_pos = if self.mode.is_attachment() {
                         0
@@ -1632,6 +2001,22 @@ impl Component for MailView {
                     let colors = crate::conf::value(context, "mail.view.body");
                     self.pager =
                         Pager::from_string(text, Some(context), Some(cursor_pos), None, colors);
+                    let header_links = self.header_links(context);
+                    self.pager
+                        .set_account_hash(Some(self.coordinates.0))
+                        .set_extra_links(header_links);
+                    if *mailbox_settings!(
+                        context[self.coordinates.0][&self.coordinates.1]
+                            .pager
+                            .format_flowed
+                    ) && has_format_flowed(body)
+                    {
+                        self.pager
+                            .set_reflow(melib::text_processing::Reflow::FormatFlowed);
+                    } else {
+                        /* Reflowing would move the styled parts of the text */
+                        self.pager.set_text_attrs(body_attrs.clone());
+                    }
                     if let Some(ref filter) = mailbox_settings!(
                         context[self.coordinates.0][&self.coordinates.1]
                             .pager
@@ -1654,8 +2039,20 @@ impl Component for MailView {
                 buf.draw(grid, (set_y(upper_left, y + 1), bottom_right), context);
             }*/
             _ => {
-                self.pager
-                    .draw(grid, (set_y(upper_left, y), bottom_right), context);
+                let pager_area = (set_y(upper_left, y), bottom_right);
+                let redraw_images = self.pager.is_dirty();
+                self.pager.draw(grid, pager_area, context);
+                if let (ViewMode::Normal, true, MailViewState::Loaded { ref mut images, .. }) =
+                    (&self.mode, redraw_images, &mut self.state)
+                {
+                    Self::draw_image_previews(
+                        &self.pager,
+                        images.previews_mut(),
+                        grid,
+                        pager_area,
+                        context,
+                    );
+                }
             }
         }
         if let ViewMode::ContactSelector(ref mut s) = self.mode {
@@ -1740,6 +2137,14 @@ impl Component for MailView {
                     self.pager.set_dirty(true);
                     return true;
                 }
+                UIEvent::StatusEvent(StatusEvent::JobFinished(ref job_id))
+                    if self.delivery_reports_job == Some(*job_id) =>
+                {
+                    self.delivery_reports_job = None;
+                    self.force_draw_headers = true;
+                    self.set_dirty(true);
+                    return true;
+                }
                 UIEvent::StatusEvent(StatusEvent::JobFinished(ref job_id))
                     if self.active_jobs.contains(job_id) =>
                 {
@@ -1779,8 +2184,9 @@ impl Component for MailView {
                                         self.attachment_displays_to_tree(&display);
                                     self.attachment_tree = attachment_tree_s;
                                     self.attachment_paths = paths;
-                                    let body_text =
+                                    let (body_text, body_attrs) =
                                         self.attachment_displays_to_text(&display, context, true);
+                                    let images = self.image_previews(&body, context);
                                     self.state = MailViewState::Loaded {
                                         bytes,
                                         env,
@@ -1788,7 +2194,10 @@ impl Component for MailView {
                                         display,
                                         links: vec![],
                                         body_text,
+                                        body_attrs,
+                                        images,
                                     };
+                                    self.check_read_receipt(context);
                                 }
                                 Ok(Some(Err(err))) => {
                                     self.state = MailViewState::Error { err };
@@ -1799,8 +2208,14 @@ impl Component for MailView {
                             self.init_futures(context);
                         }
                         MailViewState::Loaded {
-                            ref mut display, ..
+                            ref mut images,
+                            ref mut display,
+                            ..
                         } => {
+                            if images.job_finished(*job_id) {
+                                /* Make room for the previews in the pager's text */
+                                self.initialised = false;
+                            }
                             let mut caught = false;
                             for d in display.iter_mut() {
                                 match d {
@@ -1882,7 +2297,7 @@ impl Component for MailView {
                                 }
                             }
                             if caught {
-                                let mut new_body_text = String::new();
+                                let mut new_body_text = (String::new(), vec![]);
                                 if let MailViewState::Loaded { ref display, .. } = self.state {
                                     new_body_text =
                                         self.attachment_displays_to_text(&display, context, true);
@@ -1893,12 +2308,14 @@ impl Component for MailView {
                                 }
                                 if let MailViewState::Loaded {
                                     ref mut body_text,
+                                    ref mut body_attrs,
                                     ref mut links,
                                     ..
                                 } = self.state
                                 {
                                     links.clear();
-                                    *body_text = new_body_text;
+                                    *body_text = new_body_text.0;
+                                    *body_attrs = new_body_text.1;
                                 }
                             }
                         }
@@ -1983,6 +2400,37 @@ impl Component for MailView {
             UIEvent::FinishedUIDialog(id, ref result) if id == self.id() => {
                 if let Some(result) = result.downcast_ref::<PendingReplyAction>() {
                     self.perform_action(*result, context);
+                } else if let Some(Some(request)) = result.downcast_ref::<Option<MdnRequest>>() {
+                    if let MailViewState::Loaded { ref env, .. } = self.state {
+                        send_read_receipt(env, request, false, self.coordinates.0, context);
+                    }
+                }
+                return true;
+            }
+            UIEvent::Input(ref key)
+                if self.embedded.is_some()
+                    && shortcut!(key == shortcuts[MailView::DESCRIPTION]["edit"]) =>
+            {
+                if let MailViewState::Loaded {
+                    ref bytes, ref env, ..
+                } = self.state
+                {
+                    match Draft::edit(env, bytes) {
+                        Ok(draft) => {
+                            let composer = Composer::with_draft(self.coordinates.0, draft, context);
+                            context
+                                .replies
+                                .push_back(UIEvent::Action(Tab(New(Some(Box::new(composer))))));
+                        }
+                        Err(err) => {
+                            context.replies.push_back(UIEvent::StatusEvent(
+                                StatusEvent::DisplayMessage(format!(
+                                    "Failed to open embedded message: {}",
+                                    err
+                                )),
+                            ));
+                        }
+                    }
                 }
                 return true;
             }
@@ -2078,7 +2526,9 @@ impl Component for MailView {
                 self.initialised = false;
                 return true;
             }
-            UIEvent::Input(Key::Esc) | UIEvent::Input(Key::Alt('')) if !self.cmd_buf.is_empty() => {
+            UIEvent::Input(Key::Esc) | UIEvent::Input(Key::Alt(''))
+                if !self.cmd_buf.is_empty() =>
+            {
                 self.cmd_buf.clear();
                 context
                     .replies
@@ -2159,6 +2609,26 @@ impl Component for MailView {
                 }
                 return true;
             }
+            UIEvent::Input(ref key)
+                if (self.mode == ViewMode::Normal || self.mode == ViewMode::Url)
+                    && shortcut!(
+                        key == shortcuts[MailView::DESCRIPTION]["view_attachment_tree"]
+                    ) =>
+            {
+                match self.state {
+                    MailViewState::Error { .. } | MailViewState::LoadingBody { .. } => {}
+                    MailViewState::Loaded { ref body, .. } => {
+                        let tree = AttachmentTree::new(body.clone(), self.coordinates, context);
+                        self.subview = Some(Box::new(tree));
+                        self.mode = ViewMode::Subview;
+                        self.set_dirty(true);
+                    }
+                    MailViewState::Init { .. } => {
+                        self.init_futures(context);
+                    }
+                }
+                return true;
+            }
             UIEvent::Input(ref key)
                 if shortcut!(key == shortcuts[MailView::DESCRIPTION]["open_attachment"])
                     && !self.cmd_buf.is_empty()
@@ -2175,23 +2645,14 @@ impl Component for MailView {
                         if let Some(attachment) = self.open_attachment(lidx, context) {
                             match attachment.content_type() {
                                 ContentType::MessageRfc822 => {
-                                    match Mail::new(attachment.body().to_vec(), Some(Flag::SEEN)) {
-                                        Ok(wrapper) => {
-                                            context.replies.push_back(UIEvent::Action(Tab(New(
-                                                Some(Box::new(EnvelopeView::new(
-                                                    wrapper,
-                                                    None,
-                                                    None,
-                                                    self.coordinates.0,
-                                                ))),
-                                            ))));
-                                        }
-                                        Err(e) => {
-                                            context.replies.push_back(UIEvent::StatusEvent(
-                                                StatusEvent::DisplayMessage(format!("{}", e)),
-                                            ));
-                                        }
-                                    }
+                                    let view = MailView::new_embedded(
+                                        self.coordinates,
+                                        decode(attachment, None),
+                                        context,
+                                    );
+                                    context
+                                        .replies
+                                        .push_back(UIEvent::Action(Tab(New(Some(Box::new(view))))));
                                 }
 
                                 ContentType::Text { .. }
@@ -2210,55 +2671,7 @@ impl Component for MailView {
                                     ));
                                 }
                                 ContentType::Other { .. } => {
-                                    let attachment_type = attachment.mime_type();
-                                    let filename = attachment.filename();
-                                    if let Ok(command) = query_default_app(&attachment_type) {
-                                        let p = create_temp_file(
-                                            &decode(attachment, None),
-                                            filename.as_ref().map(|s| s.as_str()),
-                                            None,
-                                            true,
-                                        );
-                                        let (exec_cmd, argument) = desktop_exec_to_command(
-                                            &command,
-                                            p.path.display().to_string(),
-                                            false,
-                                        );
-                                        match Command::new(&exec_cmd)
-                                            .arg(&argument)
-                                            .stdin(Stdio::piped())
-                                            .stdout(Stdio::piped())
-                                            .spawn()
-                                        {
-                                            Ok(child) => {
-                                                context.temp_files.push(p);
-                                                context.children.push(child);
-                                            }
-                                            Err(err) => {
-                                                context.replies.push_back(UIEvent::StatusEvent(
-                                                    StatusEvent::DisplayMessage(format!(
-                                                        "Failed to start `{} {}`: {}",
-                                                        &exec_cmd, &argument, err
-                                                    )),
-                                                ));
-                                            }
-                                        }
-                                    } else {
-                                        context.replies.push_back(UIEvent::StatusEvent(
-                                        StatusEvent::DisplayMessage(if let Some(filename) = filename.as_ref() {
-                                            format!(
-                                                "Couldn't find a default application for file {} (type {})",
-                                                filename,
-                                                attachment_type
-                                            )
-                                        } else {
-                                            format!(
-                                                "Couldn't find a default application for type {}",
-                                                attachment_type
-                                            )
-                                        }),
-                                ));
-                                    }
+                                    open_with_default_app(attachment, context);
                                 }
                                 ContentType::OctetStream { ref name } => {
                                     context.replies.push_back(UIEvent::StatusEvent(
@@ -2308,6 +2721,8 @@ impl Component for MailView {
                         display: _,
                         env: _,
                         ref body_text,
+                        body_attrs: _,
+                        images: _,
                         ref links,
                     } => {
                         let (_kind, url) = {
@@ -2410,7 +2825,7 @@ impl Component for MailView {
                 let mut path = std::path::Path::new(path).to_path_buf();
 
                 if path.is_dir() {
-                    let envelope: EnvelopeRef = account.collection.get_env(self.coordinates.2);
+                    let envelope = self.envelope(account);
                     path.push(format!("{}.eml", envelope.message_id_raw()));
                 }
                 match save_attachment(&path, bytes) {
@@ -2468,46 +2883,14 @@ impl Component for MailView {
                     return true;
                 };
 
-                let mut path = std::path::Path::new(path).to_path_buf();
-
                 if let Some(u) = self.open_attachment(a_i, context) {
-                    if path.is_dir() {
-                        if let Some(filename) = u.filename() {
-                            path.push(filename);
-                        } else {
-                            let u = melib::uuid::Uuid::new_v4();
-                            path.push(u.as_hyphenated().to_string());
-                        }
-                    }
-                    match save_attachment(&path, &decode(u, None)) {
-                        Err(err) => {
-                            context.replies.push_back(UIEvent::Notification(
-                                Some(format!("Failed to create file at {}", path.display())),
-                                err.to_string(),
-                                Some(NotificationType::Error(melib::ErrorKind::External)),
-                            ));
-                            log(
-                                format!(
-                                    "Failed to create file at {}: {}",
-                                    path.display(),
-                                    err.to_string()
-                                ),
-                                ERROR,
-                            );
-                        }
-                        Ok(()) => {
-                            context.replies.push_back(UIEvent::Notification(
-                                None,
-                                format!("Saved at {}", path.display()),
-                                Some(NotificationType::Info),
-                            ));
-                        }
-                    }
+                    save_attachment_to_path(u, path, context);
                 } else if a_i == 0 {
+                    let mut path = std::path::Path::new(path).to_path_buf();
                     let account = &context.accounts[&self.coordinates.0];
                     // Save entire message as eml
                     if path.is_dir() {
-                        let envelope: EnvelopeRef = account.collection.get_env(self.coordinates.2);
+                        let envelope = self.envelope(account);
                         path.push(format!("{}.eml", envelope.message_id_raw()));
                     }
                     match save_attachment(&path, bytes) {
@@ -2547,6 +2930,12 @@ impl Component for MailView {
                 }
                 return true;
             }
+            UIEvent::Action(View(ViewAction::PipeAttachment(a_i, ref bin, ref args))) => {
+                if let Some(u) = self.open_attachment(a_i, context) {
+                    pipe_attachment(u, bin, args, context);
+                }
+                return true;
+            }
             UIEvent::Action(MailingListAction(ref e)) => {
                 let account = &context.accounts[&self.coordinates.0];
                 if !account.contains_key(self.coordinates.2) {
@@ -2554,7 +2943,7 @@ impl Component for MailView {
                      * arrive */
                     return true;
                 }
-                let envelope: EnvelopeRef = account.collection.get_env(self.coordinates.2);
+                let envelope = self.envelope(account);
                 let detect = list_management::ListActions::detect(&envelope);
                 if let Some(ref actions) = detect {
                     match e {
@@ -2794,6 +3183,192 @@ impl Component for MailView {
     }
 }
 
+/// Build a read receipt for `envelope` and send it with the account's `send_mail` setting.
+fn send_read_receipt(
+    envelope: &Envelope,
+    request: &MdnRequest,
+    automatic_sending: bool,
+    account_hash: AccountHash,
+    context: &mut Context,
+) {
+    let from = crate::components::mail::get_display_name(context, account_hash);
+    let final_recipient = context.accounts[&account_hash]
+        .settings
+        .account()
+        .identity
+        .clone();
+    let reporting_ua = format!("meli {}", option_env!("CARGO_PKG_VERSION").unwrap_or("0.0"));
+    let send_mail = account_settings!(context[account_hash].composing.send_mail).clone();
+    let draft = request.to_draft(
+        envelope,
+        &from,
+        &final_recipient,
+        &reporting_ua,
+        automatic_sending,
+    );
+    let result = draft
+        .finalise()
+        .and_then(|message| context.accounts[&account_hash].send(message, send_mail, true));
+    match result {
+        Ok(_) => {
+            context
+                .replies
+                .push_back(UIEvent::StatusEvent(StatusEvent::DisplayMessage(format!(
+                    "Sending read receipt to {}.",
+                    request
+                        .notify_to
+                        .iter()
+                        .map(|addr| addr.to_string())
+                        .collect::<Vec<String>>()
+                        .join(", ")
+                ))));
+        }
+        Err(err) => {
+            context.replies.push_back(UIEvent::Notification(
+                Some("Could not send read receipt".to_string()),
+                err.to_string(),
+                Some(NotificationType::Error(err.kind)),
+            ));
+        }
+    }
+}
+
+/// Whether `a` has `format=flowed` text parts that are shown inline.
+fn has_format_flowed(a: &Attachment) -> bool {
+    match a.content_type {
+        ContentType::Multipart { ref parts, .. } => parts.iter().any(has_format_flowed),
+        _ => !a.content_disposition.kind.is_attachment() && a.format_flowed_delsp().is_some(),
+    }
+}
+
+/// Open `attachment` with the default application for its MIME type.
+fn open_with_default_app(attachment: &Attachment, context: &mut Context) {
+    let attachment_type = attachment.mime_type();
+    let filename = attachment.filename();
+    if let Ok(command) = query_default_app(&attachment_type) {
+        let p = create_temp_file(
+            &decode(attachment, None),
+            filename.as_ref().map(|s| s.as_str()),
+            None,
+            true,
+        );
+        let (exec_cmd, argument) =
+            desktop_exec_to_command(&command, p.path.display().to_string(), false);
+        match Command::new(&exec_cmd)
+            .arg(&argument)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .spawn()
+        {
+            Ok(child) => {
+                context.temp_files.push(p);
+                context.children.push(child);
+            }
+            Err(err) => {
+                context
+                    .replies
+                    .push_back(UIEvent::StatusEvent(StatusEvent::DisplayMessage(format!(
+                        "Failed to start `{} {}`: {}",
+                        &exec_cmd, &argument, err
+                    ))));
+            }
+        }
+    } else {
+        context
+            .replies
+            .push_back(UIEvent::StatusEvent(StatusEvent::DisplayMessage(
+                if let Some(filename) = filename.as_ref() {
+                    format!(
+                        "Couldn't find a default application for file {} (type {})",
+                        filename, attachment_type
+                    )
+                } else {
+                    format!(
+                        "Couldn't find a default application for type {}",
+                        attachment_type
+                    )
+                },
+            )));
+    }
+}
+
+/// Save decoded `attachment` at `path`, or under its filename if `path` is a directory.
+fn save_attachment_to_path(attachment: &Attachment, path: &str, context: &mut Context) {
+    let mut path = std::path::Path::new(path).to_path_buf();
+    if path.is_dir() {
+        if let Some(filename) = attachment.filename() {
+            path.push(filename);
+        } else {
+            let u = melib::uuid::Uuid::new_v4();
+            path.push(u.as_hyphenated().to_string());
+        }
+    }
+    match save_attachment(&path, &decode(attachment, None)) {
+        Err(err) => {
+            context.replies.push_back(UIEvent::Notification(
+                Some(format!("Failed to create file at {}", path.display())),
+                err.to_string(),
+                Some(NotificationType::Error(melib::ErrorKind::External)),
+            ));
+            log(
+                format!(
+                    "Failed to create file at {}: {}",
+                    path.display(),
+                    err.to_string()
+                ),
+                ERROR,
+            );
+        }
+        Ok(()) => {
+            context.replies.push_back(UIEvent::Notification(
+                None,
+                format!("Saved at {}", path.display()),
+                Some(NotificationType::Info),
+            ));
+        }
+    }
+}
+
+/// Pipe decoded `attachment` to `bin` with `args`.
+fn pipe_attachment(attachment: &Attachment, bin: &str, args: &[String], context: &mut Context) {
+    let mut command_obj = match Command::new(bin)
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+    {
+        Ok(o) => o,
+        Err(e) => {
+            context
+                .replies
+                .push_back(UIEvent::StatusEvent(StatusEvent::DisplayMessage(format!(
+                    "Could not pipe to {}: {}",
+                    bin, e
+                ))));
+            return;
+        }
+    };
+    let stdin = command_obj.stdin.as_mut().expect("failed to open stdin");
+    if let Err(err) = stdin.write_all(&decode(attachment, None)) {
+        context
+            .replies
+            .push_back(UIEvent::StatusEvent(StatusEvent::DisplayMessage(format!(
+                "Could not pipe to {}: {}",
+                bin, err
+            ))));
+        return;
+    }
+    context.children.push(command_obj);
+    context
+        .replies
+        .push_back(UIEvent::StatusEvent(StatusEvent::DisplayMessage(format!(
+            "Attachment piped to '{}{}{}'",
+            bin,
+            if args.is_empty() { "" } else { " " },
+            args.join(" ")
+        ))));
+}
+
 fn save_attachment(path: &std::path::Path, bytes: &[u8]) -> Result<()> {
     let mut f = std::fs::File::create(path)?;
     let mut permissions = f.metadata()?.permissions();
@@ -0,0 +1,528 @@
+/*
+ * meli
+ *
+ * Copyright 2017-2020 Manos Pitsidianakis
+ *
+ * This file is part of meli.
+ *
+ * meli is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * meli is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with meli. If not, see <http://www.gnu.org/licenses/>.
+ */
+
+use super::*;
+
+/// A MIME part of the browsed envelope, in pre-order.
+#[derive(Debug, Clone)]
+struct AttachmentEntry {
+    attachment: Attachment,
+    /// Tree drawing in front of the description, e.g. `| \_ `.
+    prefix: String,
+    description: String,
+    /// Index one past the last descendant of this entry.
+    subtree_end: usize,
+    collapsed: bool,
+}
+
+impl AttachmentEntry {
+    fn describe(attachment: &Attachment) -> String {
+        let mut details = vec![];
+        let is_multipart = matches!(attachment.content_type, ContentType::Multipart { .. });
+        let size = if is_multipart {
+            attachment.raw().len()
+        } else {
+            decode(attachment, None).len()
+        };
+        match attachment.content_type {
+            ContentType::Text { ref charset, .. } => {
+                details.push(charset.to_string());
+            }
+            ContentType::MessageRfc822 => {
+                if let Ok(env) = Envelope::from_bytes(&decode(attachment, None), None) {
+                    details.push(format!("\"{}\"", env.subject()));
+                }
+            }
+            _ => {}
+        }
+        if !is_multipart {
+            details.push(attachment.content_transfer_encoding.to_string());
+        }
+        details.push(melib::Bytes(size).to_string());
+        if let Some(name) = attachment.filename() {
+            format!(
+                "{} \"{}\" ({})",
+                attachment.mime_type(),
+                name,
+                details.join(", ")
+            )
+        } else {
+            format!("{} ({})", attachment.mime_type(), details.join(", "))
+        }
+    }
+}
+
+/// Flatten the MIME structure of `root` into its pre-order list of parts. The body of an
+/// embedded `message/rfc822` part is its only child.
+fn attachment_entries(root: Attachment) -> Vec<AttachmentEntry> {
+    fn append_entry(
+        attachment: Attachment,
+        depth: usize,
+        branches: &mut Vec<bool>,
+        has_sibling: bool,
+        acc: &mut Vec<AttachmentEntry>,
+    ) {
+        let mut prefix = String::new();
+        for &b in branches.iter() {
+            prefix.push(if b { '|' } else { ' ' });
+            prefix.push(' ');
+        }
+        if depth > 0 {
+            prefix.push(if has_sibling { '|' } else { ' ' });
+            prefix.push_str("\\_ ");
+        }
+        let children = match attachment.content_type {
+            ContentType::Multipart { ref parts, .. } => parts.clone(),
+            ContentType::MessageRfc822 => {
+                vec![AttachmentBuilder::new(&decode(&attachment, None)).build()]
+            }
+            _ => vec![],
+        };
+        let idx = acc.len();
+        acc.push(AttachmentEntry {
+            description: AttachmentEntry::describe(&attachment),
+            attachment,
+            prefix,
+            subtree_end: idx + 1,
+            collapsed: false,
+        });
+        if depth > 0 {
+            branches.push(has_sibling);
+        }
+        let len = children.len();
+        for (i, child) in children.into_iter().enumerate() {
+            append_entry(child, depth + 1, branches, i + 1 < len, acc);
+        }
+        if depth > 0 {
+            branches.pop();
+        }
+        acc[idx].subtree_end = acc.len();
+    }
+
+    let mut ret = vec![];
+    append_entry(root, 0, &mut vec![], false, &mut ret);
+    ret
+}
+
+/// Browse the MIME structure of an envelope and view, save, pipe or open each part.
+///
+/// Parts are indexed in pre-order, the same indices `save-attachment` and `pipe-attachment`
+/// accept while the tree is open.
+#[derive(Debug)]
+pub struct AttachmentTree {
+    coordinates: (AccountHash, MailboxHash, EnvelopeHash),
+    entries: Vec<AttachmentEntry>,
+    /// Index of the selected entry.
+    cursor_pos: usize,
+    /// Part currently viewed in place of the tree.
+    view: Option<Box<dyn Component>>,
+    theme_default: ThemeAttribute,
+    highlight_theme: ThemeAttribute,
+    dirty: bool,
+    id: ComponentId,
+}
+
+impl fmt::Display for AttachmentTree {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", AttachmentTree::DESCRIPTION)
+    }
+}
+
+impl AttachmentTree {
+    pub const DESCRIPTION: &'static str = "attachment tree";
+    pub fn new(
+        body: Attachment,
+        coordinates: (AccountHash, MailboxHash, EnvelopeHash),
+        context: &Context,
+    ) -> Self {
+        AttachmentTree {
+            coordinates,
+            entries: attachment_entries(body),
+            cursor_pos: 0,
+            view: None,
+            theme_default: crate::conf::value(context, "theme_default"),
+            highlight_theme: crate::conf::value(context, "highlight"),
+            dirty: true,
+            id: ComponentId::new_v4(),
+        }
+    }
+
+    /// Indices of the entries not hidden inside a collapsed subtree.
+    fn visible_entries(&self) -> Vec<usize> {
+        let mut ret = Vec::with_capacity(self.entries.len());
+        let mut idx = 0;
+        while idx < self.entries.len() {
+            ret.push(idx);
+            idx = if self.entries[idx].collapsed {
+                self.entries[idx].subtree_end
+            } else {
+                idx + 1
+            };
+        }
+        ret
+    }
+
+    fn get_entry(&self, idx: usize, context: &mut Context) -> Option<&Attachment> {
+        if let Some(entry) = self.entries.get(idx) {
+            Some(&entry.attachment)
+        } else {
+            context
+                .replies
+                .push_back(UIEvent::StatusEvent(StatusEvent::DisplayMessage(format!(
+                    "Attachment `{}` not found.",
+                    idx
+                ))));
+            None
+        }
+    }
+
+    fn toggle_collapse(&mut self, idx: usize) {
+        let entry = &mut self.entries[idx];
+        if entry.subtree_end > idx + 1 {
+            entry.collapsed = !entry.collapsed;
+            self.set_dirty(true);
+        }
+    }
+
+    fn view_part(&mut self, idx: usize, context: &mut Context) {
+        let attachment = &self.entries[idx].attachment;
+        match attachment.content_type {
+            ContentType::MessageRfc822 => {
+                let view =
+                    MailView::new_embedded(self.coordinates, decode(attachment, None), context);
+                context
+                    .replies
+                    .push_back(UIEvent::Action(Tab(New(Some(Box::new(view))))));
+            }
+            ContentType::Multipart { .. } => {
+                self.toggle_collapse(idx);
+            }
+            _ if attachment.is_html() => {
+                let mut html = HtmlView::new(attachment, context);
+                html.set_account_hash(Some(self.coordinates.0));
+                self.view = Some(Box::new(html));
+                self.set_dirty(true);
+            }
+            ContentType::Text { .. } | ContentType::PGPSignature | ContentType::CMSSignature => {
+                let colors = crate::conf::value(context, "mail.view.body");
+                let mut pager =
+                    Pager::from_string(attachment.text(), Some(context), Some(0), None, colors);
+                if let Some(ref filter) = mailbox_settings!(
+                    context[self.coordinates.0][&self.coordinates.1]
+                        .pager
+                        .filter
+                ) {
+                    pager.filter(filter);
+                }
+                self.view = Some(Box::new(pager));
+                self.set_dirty(true);
+            }
+            ContentType::Other { .. }
+                if attachment.mime_type().starts_with("image/")
+                    && context.settings.terminal.graphics_protocol() != GraphicsProtocol::None =>
+            {
+                /* Images in formats that can't be decoded are opened like other attachments */
+                match ImageView::new(attachment, context.settings.terminal.graphics_protocol()) {
+                    Ok(view) => {
+                        self.view = Some(Box::new(view));
+                        self.set_dirty(true);
+                    }
+                    Err(_) => {
+                        open_with_default_app(attachment, context);
+                    }
+                }
+            }
+            ContentType::Other { .. } | ContentType::OctetStream { .. } => {
+                open_with_default_app(attachment, context);
+            }
+        }
+    }
+}
+
+impl Component for AttachmentTree {
+    fn draw(&mut self, grid: &mut CellBuffer, area: Area, context: &mut Context) {
+        if let Some(ref mut view) = self.view {
+            if self.dirty {
+                view.set_dirty(true);
+                self.dirty = false;
+            }
+            view.draw(grid, area, context);
+            return;
+        }
+        if !self.dirty || !is_valid_area!(area) {
+            return;
+        }
+        self.dirty = false;
+        clear_area(grid, area, self.theme_default);
+        let upper_left = upper_left!(area);
+        let bottom_right = bottom_right!(area);
+        let visible = self.visible_entries();
+        let cursor_row = visible
+            .iter()
+            .position(|&idx| idx == self.cursor_pos)
+            .unwrap_or(0);
+        /* `area` is valid, so there is at least one row */
+        let rows = height!(area);
+        let skip = (cursor_row / rows) * rows;
+        for (line, &idx) in (get_y(upper_left)..).zip(visible.iter().skip(skip).take(rows)) {
+            let entry = &self.entries[idx];
+            let theme = if idx == self.cursor_pos {
+                self.highlight_theme
+            } else {
+                self.theme_default
+            };
+            change_colors(
+                grid,
+                ((get_x(upper_left), line), (get_x(bottom_right), line)),
+                theme.fg,
+                theme.bg,
+            );
+            let hidden = if entry.collapsed {
+                format!(" ({} hidden)", entry.subtree_end - idx - 1)
+            } else {
+                String::new()
+            };
+            write_string_to_grid(
+                &format!("[{}] {}{}{}", idx, entry.prefix, entry.description, hidden),
+                grid,
+                theme.fg,
+                theme.bg,
+                theme.attrs,
+                ((get_x(upper_left), line), bottom_right),
+                None,
+            );
+        }
+        context.dirty_areas.push_back(area);
+    }
+
+    fn process_event(&mut self, event: &mut UIEvent, context: &mut Context) -> bool {
+        let shortcuts = self.get_shortcuts(context);
+        if let Some(ref mut view) = self.view {
+            if view.process_event(event, context) {
+                return true;
+            }
+            if let UIEvent::Input(ref key) = event {
+                if shortcut!(key == shortcuts[Self::DESCRIPTION]["return_to_tree"]) {
+                    self.view = None;
+                    self.set_dirty(true);
+                    return true;
+                }
+            }
+        }
+        match *event {
+            UIEvent::ConfigReload { old_settings: _ } => {
+                self.theme_default = crate::conf::value(context, "theme_default");
+                self.highlight_theme = crate::conf::value(context, "highlight");
+                self.set_dirty(true);
+            }
+            UIEvent::Resize => {
+                self.set_dirty(true);
+            }
+            UIEvent::Action(View(ViewAction::SaveAttachment(idx, ref path))) => {
+                if let Some(attachment) = self.get_entry(idx, context) {
+                    let attachment = attachment.clone();
+                    save_attachment_to_path(&attachment, path, context);
+                }
+                return true;
+            }
+            UIEvent::Action(View(ViewAction::PipeAttachment(idx, ref bin, ref args))) => {
+                if let Some(attachment) = self.get_entry(idx, context) {
+                    let attachment = attachment.clone();
+                    pipe_attachment(&attachment, bin, args, context);
+                }
+                return true;
+            }
+            _ if self.view.is_some() => {}
+            UIEvent::Input(ref key)
+                if shortcut!(key == shortcuts[Self::DESCRIPTION]["scroll_up"]) =>
+            {
+                let visible = self.visible_entries();
+                if let Some(row) = visible.iter().position(|&idx| idx == self.cursor_pos) {
+                    self.cursor_pos = visible[row.saturating_sub(1)];
+                }
+                self.set_dirty(true);
+                return true;
+            }
+            UIEvent::Input(ref key)
+                if shortcut!(key == shortcuts[Self::DESCRIPTION]["scroll_down"]) =>
+            {
+                let visible = self.visible_entries();
+                if let Some(&next) = visible.iter().find(|&&idx| idx > self.cursor_pos) {
+                    self.cursor_pos = next;
+                    self.set_dirty(true);
+                }
+                return true;
+            }
+            UIEvent::Input(ref key)
+                if shortcut!(key == shortcuts[Self::DESCRIPTION]["collapse_subtree"]) =>
+            {
+                self.toggle_collapse(self.cursor_pos);
+                return true;
+            }
+            UIEvent::Input(ref key)
+                if shortcut!(key == shortcuts[Self::DESCRIPTION]["view_part"]) =>
+            {
+                self.view_part(self.cursor_pos, context);
+                return true;
+            }
+            UIEvent::Input(ref key)
+                if shortcut!(key == shortcuts[Self::DESCRIPTION]["save_part"]) =>
+            {
+                context
+                    .replies
+                    .push_back(UIEvent::CmdInput(Key::Paste(format!(
+                        "save-attachment {} ",
+                        self.cursor_pos
+                    ))));
+                context
+                    .replies
+                    .push_back(UIEvent::ChangeMode(UIMode::Command));
+                return true;
+            }
+            UIEvent::Input(ref key)
+                if shortcut!(key == shortcuts[Self::DESCRIPTION]["pipe_part"]) =>
+            {
+                context
+                    .replies
+                    .push_back(UIEvent::CmdInput(Key::Paste(format!(
+                        "pipe-attachment {} ",
+                        self.cursor_pos
+                    ))));
+                context
+                    .replies
+                    .push_back(UIEvent::ChangeMode(UIMode::Command));
+                return true;
+            }
+            UIEvent::Input(ref key)
+                if shortcut!(key == shortcuts[Self::DESCRIPTION]["open_mailcap"]) =>
+            {
+                let attachment = &self.entries[self.cursor_pos].attachment;
+                if crate::mailcap::MailcapEntry::execute(attachment, context).is_err() {
+                    context
+                        .replies
+                        .push_back(UIEvent::StatusEvent(StatusEvent::DisplayMessage(format!(
+                            "no mailcap entry found for {}",
+                            attachment.content_type()
+                        ))));
+                }
+                self.set_dirty(true);
+                return true;
+            }
+            _ => {}
+        }
+        false
+    }
+
+    fn is_dirty(&self) -> bool {
+        self.dirty || self.view.as_ref().map(|v| v.is_dirty()).unwrap_or(false)
+    }
+
+    fn set_dirty(&mut self, value: bool) {
+        self.dirty = value;
+        if let Some(ref mut view) = self.view {
+            view.set_dirty(value);
+        }
+    }
+
+    fn get_shortcuts(&self, context: &Context) -> ShortcutMaps {
+        let mut map = if let Some(ref view) = self.view {
+            view.get_shortcuts(context)
+        } else {
+            ShortcutMaps::default()
+        };
+        let mut our_map = context.settings.shortcuts.attachment_tree.key_values();
+        if self.view.is_some() {
+            our_map.retain(|name, _| *name == "return_to_tree");
+        } else {
+            our_map.remove("return_to_tree");
+        }
+        map.insert(Self::DESCRIPTION, our_map);
+        map
+    }
+
+    fn id(&self) -> ComponentId {
+        self.id
+    }
+
+    fn set_id(&mut self, id: ComponentId) {
+        self.id = id;
+    }
+}
+
+#[test]
+fn test_attachment_tree_entries() {
+    let message = b"From: a@example.com
+To: b@example.com
+Subject: parent
+MIME-Version: 1.0
+Content-Type: multipart/mixed; boundary=\"outer\"
+
+--outer
+Content-Type: text/plain; charset=utf-8
+Content-Transfer-Encoding: quoted-printable
+
+Hello
+--outer
+Content-Type: message/rfc822
+
+From: c@example.com
+Subject: forwarded
+MIME-Version: 1.0
+Content-Type: multipart/alternative; boundary=\"inner\"
+
+--inner
+Content-Type: text/plain; charset=us-ascii
+
+plain
+--inner
+Content-Type: text/html; charset=us-ascii
+
+<p>html</p>
+--inner--
+--outer
+Content-Type: application/octet-stream; name=\"data.bin\"
+Content-Transfer-Encoding: base64
+
+AAEC
+--outer--
+";
+    let env = Envelope::from_bytes(message, None).unwrap();
+    let entries = attachment_entries(env.body_bytes(message));
+    let descriptions = entries
+        .iter()
+        .map(|e| format!("{}{}", e.prefix, e.description))
+        .collect::<Vec<String>>();
+    assert_eq!(
+        descriptions,
+        vec![
+            "multipart/mixed (615.00 bytes)",
+            "|\\_ text/plain (utf-8, quoted-printable, 5.00 bytes)",
+            "|\\_ message/rfc822 (\"forwarded\", 8bit, 242.00 bytes)",
+            "|  \\_ multipart/alternative (242.00 bytes)",
+            "|   |\\_ text/plain (us-ascii, 8bit, 5.00 bytes)",
+            "|    \\_ text/html (us-ascii, 8bit, 11.00 bytes)",
+            " \\_ application/octet-stream \"data.bin\" (base64, 3.00 bytes)",
+        ]
+    );
+    assert_eq!(entries[2].subtree_end, 6);
+    assert_eq!(entries[3].subtree_end, 6);
+    assert_eq!(entries[0].subtree_end, 7);
+}
@@ -20,6 +20,7 @@
  */
 
 use super::*;
+use melib::text_processing::html::{html_to_text, HtmlStyle};
 use std::io::Write;
 use std::process::{Command, Stdio};
 
@@ -27,6 +28,10 @@ use std::process::{Command, Stdio};
 pub struct HtmlView {
     pager: Pager,
     bytes: Vec<u8>,
+    /// Text appended to the rendered HTML, if it is rendered by the built-in renderer.
+    builtin_footer: Option<String>,
+    /// Width the HTML was last rendered to by the built-in renderer.
+    width: usize,
     id: ComponentId,
 }
 
@@ -72,32 +77,8 @@ impl HtmlView {
                     display_text
                 }
             }
-        } else if let Ok(mut html_filter) = Command::new("w3m")
-            .args(&["-I", "utf-8", "-T", "text/html"])
-            .stdin(Stdio::piped())
-            .stdout(Stdio::piped())
-            .spawn()
-        {
-            html_filter
-                .stdin
-                .as_mut()
-                .unwrap()
-                .write_all(&bytes)
-                .expect("Failed to write to html filter stdin");
-            let mut display_text =
-                String::from("Text piped through `w3m`. Press `v` to open in web browser. \n\n");
-            display_text.push_str(&String::from_utf8_lossy(
-                &html_filter.wait_with_output().unwrap().stdout,
-            ));
-
-            display_text
         } else {
-            context.replies.push_back(UIEvent::Notification(
-                Some("Failed to find any application to use as html filter".to_string()),
-                String::new(),
-                Some(NotificationType::Error(melib::error::ErrorKind::None)),
-            ));
-            String::from_utf8_lossy(&bytes).to_string()
+            String::new()
         };
         if body.count_attachments() > 1 {
             display_text =
@@ -110,11 +91,96 @@ impl HtmlView {
                     });
         }
         let colors = crate::conf::value(context, "mail.view.body");
+        let (builtin_footer, display_text) = if context.settings.pager.html_filter.is_none() {
+            (Some(display_text), String::new())
+        } else {
+            (None, display_text)
+        };
         let pager = Pager::from_string(display_text, None, None, None, colors);
-        HtmlView { pager, bytes, id }
+        HtmlView {
+            pager,
+            bytes,
+            builtin_footer,
+            width: 0,
+            id,
+        }
+    }
+
+    pub fn set_account_hash(&mut self, new_val: Option<AccountHash>) -> &mut Self {
+        self.pager.set_account_hash(new_val);
+        self
+    }
+
+    /// Set links to offer in the pager's link hint mode besides those in the text.
+    pub fn set_extra_links(&mut self, new_val: Vec<PagerLink>) -> &mut Self {
+        self.pager.set_extra_links(new_val);
+        self
+    }
+
+    /// Render the HTML with the built-in renderer to fit in `width` columns.
+    fn render(&mut self, width: usize) {
+        let footer = match self.builtin_footer {
+            Some(ref footer) => footer,
+            None => return,
+        };
+        self.width = width;
+        let mut lines = vec![
+            "Text rendered by the built-in HTML renderer. Press `v` to open in web browser."
+                .to_string(),
+            String::new(),
+        ];
+        let mut attrs = vec![];
+        html_to_lines(
+            &String::from_utf8_lossy(&self.bytes),
+            width,
+            &mut lines,
+            &mut attrs,
+        );
+        if !footer.is_empty() {
+            lines.push(String::new());
+            lines.extend(footer.lines().map(str::to_string));
+        }
+        self.pager.update_from_lines(lines, attrs);
+    }
+}
+
+/// Render `html` with the built-in renderer to fit in `width` columns, appending its lines to
+/// `lines` and the attributes of their styled parts to `attrs` (line, first column, column after
+/// the last one).
+pub fn html_to_lines(
+    html: &str,
+    width: usize,
+    lines: &mut Vec<String>,
+    attrs: &mut Vec<(usize, usize, usize, Attr)>,
+) {
+    for line in html_to_text(html, width).lines {
+        let mut s = String::new();
+        for (run, style) in line {
+            let start = s.grapheme_width();
+            s.push_str(&run);
+            let attr = style_to_attr(style);
+            if attr != Attr::DEFAULT {
+                attrs.push((lines.len(), start, s.grapheme_width(), attr));
+            }
+        }
+        lines.push(s);
     }
 }
 
+fn style_to_attr(style: HtmlStyle) -> Attr {
+    let mut attr = Attr::DEFAULT;
+    if style.contains(HtmlStyle::BOLD) {
+        attr |= Attr::BOLD;
+    }
+    if style.contains(HtmlStyle::ITALIC) {
+        attr |= Attr::ITALICS;
+    }
+    if style.intersects(HtmlStyle::UNDERLINE | HtmlStyle::LINK) {
+        attr |= Attr::UNDERLINE;
+    }
+    attr
+}
+
 impl fmt::Display for HtmlView {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         write!(f, "view")
@@ -123,6 +189,10 @@ impl fmt::Display for HtmlView {
 
 impl Component for HtmlView {
     fn draw(&mut self, grid: &mut CellBuffer, area: Area, context: &mut Context) {
+        let width = width!(area).saturating_sub(1);
+        if self.builtin_footer.is_some() && width != self.width {
+            self.render(width);
+        }
         self.pager.draw(grid, area, context);
     }
     fn process_event(&mut self, event: &mut UIEvent, context: &mut Context) -> bool {
@@ -0,0 +1,301 @@
+/*
+ * meli
+ *
+ * Copyright 2017-2020 Manos Pitsidianakis
+ *
+ * This file is part of meli.
+ *
+ * meli is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * meli is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with meli. If not, see <http://www.gnu.org/licenses/>.
+ */
+
+use super::*;
+
+/// Shows an image attachment in the terminal, scaled to fit the view.
+#[derive(Debug)]
+pub struct ImageView {
+    image: Image,
+    name: String,
+    protocol: GraphicsProtocol,
+    image_id: u32,
+    dirty: bool,
+    id: ComponentId,
+}
+
+impl ImageView {
+    /// Decode `attachment`, to be drawn with `protocol`. Fails if it is not an image in a
+    /// supported format.
+    pub fn new(attachment: &Attachment, protocol: GraphicsProtocol) -> Result<Self> {
+        let image = Image::decode(&decode(attachment, None))?;
+        Ok(ImageView {
+            image,
+            name: attachment_name(attachment),
+            protocol,
+            image_id: new_image_id(),
+            dirty: true,
+            id: ComponentId::new_v4(),
+        })
+    }
+}
+
+fn attachment_name(attachment: &Attachment) -> String {
+    attachment
+        .filename()
+        .unwrap_or_else(|| attachment.mime_type())
+}
+
+/// The previews of the image attachments of a message. Decoding can take a while for large
+/// images, so it is done in a background job.
+#[derive(Debug)]
+pub enum ImagePreviews {
+    Decoding(JoinHandle<Vec<ImagePreview>>),
+    Decoded(Vec<ImagePreview>),
+}
+
+impl ImagePreviews {
+    /// Start decoding the image attachments of `body` that can be drawn with `protocol`.
+    pub fn new(body: &Attachment, protocol: GraphicsProtocol, context: &Context) -> Self {
+        if protocol == GraphicsProtocol::None {
+            return ImagePreviews::Decoded(vec![]);
+        }
+        let attachments = body
+            .attachments()
+            .into_iter()
+            .filter(|a| {
+                matches!(a.content_type, ContentType::Other { .. })
+                    && a.mime_type().starts_with("image/")
+            })
+            .collect::<Vec<Attachment>>();
+        if attachments.is_empty() {
+            return ImagePreviews::Decoded(vec![]);
+        }
+        ImagePreviews::Decoding(
+            context
+                .job_executor
+                .spawn_blocking(async move { ImagePreview::decode_all(attachments, protocol) }),
+        )
+    }
+
+    /// The decoding job, if it hasn't finished yet.
+    pub fn job_id(&self) -> Option<JobId> {
+        match self {
+            ImagePreviews::Decoding(handle) => Some(handle.job_id),
+            ImagePreviews::Decoded(_) => None,
+        }
+    }
+
+    /// Take the result of the decoding job `job_id`. Returns `false` if it is not ours.
+    pub fn job_finished(&mut self, job_id: JobId) -> bool {
+        match self {
+            ImagePreviews::Decoding(handle) if handle.job_id == job_id => {
+                /* The job was canceled or a worker thread panicked if there is no result */
+                let previews = handle.chan.try_recv().ok().flatten().unwrap_or_default();
+                *self = ImagePreviews::Decoded(previews);
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// The previews that are decoded so far.
+    pub fn previews_mut(&mut self) -> &mut [ImagePreview] {
+        match self {
+            ImagePreviews::Decoding(_) => &mut [],
+            ImagePreviews::Decoded(previews) => previews,
+        }
+    }
+}
+
+/// A preview of an image attachment, drawn in the empty lines under its caption that follow the
+/// body text of the mail view.
+#[derive(Debug)]
+pub struct ImagePreview {
+    caption: String,
+    image: Image,
+    protocol: GraphicsProtocol,
+    image_id: u32,
+    /// The image scaled to fit in the last size it was laid out for, in cells.
+    scaled: Option<((usize, usize), Image)>,
+}
+
+impl ImagePreview {
+    /// Most rows of cells a preview takes.
+    const MAX_ROWS: usize = 16;
+
+    /// Previews of the image `attachments` that can be drawn with `protocol`. Each image is
+    /// decoded once, here.
+    fn decode_all(attachments: Vec<Attachment>, protocol: GraphicsProtocol) -> Vec<Self> {
+        let mut ret = vec![];
+        for attachment in attachments {
+            let bytes = decode(&attachment, None);
+            if !Image::is_supported(&bytes) {
+                continue;
+            }
+            match Image::decode(&bytes) {
+                Ok(image) => ret.push(ImagePreview {
+                    caption: format!(
+                        "[image {}: {} ({}×{})]",
+                        ret.len() + 1,
+                        attachment_name(&attachment)
+                            .chars()
+                            .take(40)
+                            .collect::<String>(),
+                        image.width(),
+                        image.height()
+                    ),
+                    image,
+                    protocol,
+                    image_id: new_image_id(),
+                    scaled: None,
+                }),
+                Err(err) => {
+                    debug!("Could not decode image preview: {}", err);
+                }
+            }
+        }
+        ret
+    }
+
+    /// The line of the text that the preview is drawn under.
+    pub fn caption(&self) -> &str {
+        &self.caption
+    }
+
+    /// The image scaled to fit in `cols` columns and `MAX_ROWS` rows, and the rows it takes.
+    fn scaled(&mut self, cols: usize) -> (&Image, usize) {
+        let cell_size = match self.protocol {
+            GraphicsProtocol::Kitty | GraphicsProtocol::Sixel => cell_size_pixels(),
+            /* Two pixels per cell, one above the other */
+            GraphicsProtocol::HalfBlocks | GraphicsProtocol::None => (1, 2),
+        };
+        let size = (cols * cell_size.0, Self::MAX_ROWS * cell_size.1);
+        if self
+            .scaled
+            .as_ref()
+            .map(|(s, _)| *s != size)
+            .unwrap_or(true)
+        {
+            self.scaled = Some((size, self.image.scale_to_fit(size.0, size.1)));
+        }
+        let image = &self.scaled.as_ref().unwrap().1;
+        (image, image.height().div_ceil(cell_size.1.max(1)))
+    }
+
+    /// Rows of cells the preview takes in `cols` columns.
+    pub fn rows(&mut self, cols: usize) -> usize {
+        self.scaled(cols).1
+    }
+
+    /// Draw the preview in `area`, which should be `rows` high.
+    pub fn draw(&mut self, grid: &mut CellBuffer, area: Area, context: &mut Context) {
+        let protocol = self.protocol;
+        let image_id = self.image_id;
+        let (image, _) = self.scaled(width!(area));
+        match protocol {
+            GraphicsProtocol::HalfBlocks => draw_half_blocks(image, grid, area),
+            GraphicsProtocol::Kitty | GraphicsProtocol::Sixel => {
+                context.images.push(ImagePlacement::new(
+                    image,
+                    image_id,
+                    protocol,
+                    area,
+                    cell_size_pixels(),
+                ));
+            }
+            GraphicsProtocol::None => {}
+        }
+        context.dirty_areas.push_back(area);
+    }
+}
+
+impl fmt::Display for ImageView {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "image")
+    }
+}
+
+impl Component for ImageView {
+    fn draw(&mut self, grid: &mut CellBuffer, area: Area, context: &mut Context) {
+        if !self.dirty || !is_valid_area!(area) {
+            return;
+        }
+        let theme_default = crate::conf::value(context, "theme_default");
+        clear_area(grid, area, theme_default);
+        let (x, _) = write_string_to_grid(
+            &format!(
+                "Viewing image `{}` ({}×{}). Press `r` to return.",
+                self.name,
+                self.image.width(),
+                self.image.height()
+            ),
+            grid,
+            theme_default.fg,
+            theme_default.bg,
+            theme_default.attrs,
+            area,
+            None,
+        );
+        for c in grid.row_iter(
+            x..(get_x(bottom_right!(area)) + 1),
+            get_y(upper_left!(area)),
+        ) {
+            grid[c]
+                .set_fg(theme_default.fg)
+                .set_bg(theme_default.bg)
+                .set_attrs(theme_default.attrs);
+        }
+        let image_area = (pos_inc(upper_left!(area), (0, 2)), bottom_right!(area));
+        if is_valid_area!(image_area) {
+            match self.protocol {
+                GraphicsProtocol::HalfBlocks => {
+                    draw_half_blocks(&self.image, grid, image_area);
+                }
+                GraphicsProtocol::Kitty | GraphicsProtocol::Sixel => {
+                    context.images.push(ImagePlacement::new(
+                        &self.image,
+                        self.image_id,
+                        self.protocol,
+                        image_area,
+                        cell_size_pixels(),
+                    ));
+                }
+                GraphicsProtocol::None => {}
+            }
+        }
+        context.dirty_areas.push_back(area);
+        self.dirty = false;
+    }
+
+    fn process_event(&mut self, event: &mut UIEvent, _context: &mut Context) -> bool {
+        if let UIEvent::Resize = event {
+            self.dirty = true;
+        }
+        false
+    }
+
+    fn is_dirty(&self) -> bool {
+        self.dirty
+    }
+
+    fn set_dirty(&mut self, value: bool) {
+        self.dirty = value;
+    }
+
+    fn id(&self) -> ComponentId {
+        self.id
+    }
+
+    fn set_id(&mut self, id: ComponentId) {
+        self.id = id;
+    }
+}
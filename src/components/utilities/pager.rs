@@ -22,6 +22,186 @@
 use super::*;
 use melib::text_processing::LineBreakText;
 
+/// Keys used to label links in link hint mode.
+const HINT_KEYS: &str = "asdfghjkl";
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum PagerLinkKind {
+    Url,
+    Mailto,
+    MessageId,
+}
+
+/// A link that can be chosen in link hint mode.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PagerLink {
+    pub kind: PagerLinkKind,
+    /// The URL, e-mail address or message-id (without angle brackets).
+    pub target: String,
+    /// Where the link comes from, if it is not in the text, e.g. `List-Unsubscribe`.
+    pub source: Option<String>,
+}
+
+impl PagerLink {
+    /// A URL from outside the text, such as a header.
+    pub fn from_url(url: &str, source: &str) -> Self {
+        PagerLink {
+            kind: if url.starts_with("mailto:") {
+                PagerLinkKind::Mailto
+            } else {
+                PagerLinkKind::Url
+            },
+            target: url.to_string(),
+            source: Some(source.to_string()),
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+enum LinkHintAction {
+    Open,
+    Yank,
+    Compose,
+}
+
+/// A link, its hint, and its position (line, column) in the text if it is in it.
+type HintedLink = (PagerLink, String, Option<(usize, usize)>);
+
+/// State of link hint mode: every link is labelled with a hint, and typing a hint chooses its
+/// link.
+#[derive(Debug, Clone)]
+struct LinkHints {
+    action: LinkHintAction,
+    links: Vec<HintedLink>,
+    typed: String,
+}
+
+/// Labels for `n` links: all hints have the same length so that none is a prefix of another.
+fn hint_labels(n: usize) -> Vec<String> {
+    let keys = HINT_KEYS.chars().collect::<Vec<char>>();
+    let mut len = 1;
+    while keys.len().pow(len as u32) < n {
+        len += 1;
+    }
+    (0..n)
+        .map(|mut i| {
+            let mut label = vec![keys[0]; len];
+            for c in label.iter_mut().rev() {
+                *c = keys[i % keys.len()];
+                i /= keys.len();
+            }
+            label.into_iter().collect()
+        })
+        .collect()
+}
+
+/// Find URLs, e-mail addresses and message-ids in `text`. Message-ids are recognised in `mid:`
+/// URLs, and in angle brackets on lines of `Message-ID`, `In-Reply-To` and `References` headers.
+pub fn find_links(text: &str) -> Vec<PagerLink> {
+    let mut ret: Vec<(usize, PagerLink)> = vec![];
+    let mut message_id_spans = vec![];
+    let mut offset = 0;
+    for line in text.split('\n') {
+        let lowercase = line.to_ascii_lowercase();
+        if ["message-id:", "in-reply-to:", "references:"]
+            .iter()
+            .any(|h| lowercase.trim_start().starts_with(h))
+        {
+            let mut rest = line;
+            while let Some(start) = rest.find('<') {
+                let end = match rest[start..].find('>') {
+                    Some(end) => start + end,
+                    None => break,
+                };
+                let id = &rest[start + 1..end];
+                if id.contains('@') && !id.contains(char::is_whitespace) {
+                    let pos = offset + (line.len() - rest.len()) + start;
+                    message_id_spans.push((pos, pos + id.len() + 2));
+                    ret.push((
+                        pos,
+                        PagerLink {
+                            kind: PagerLinkKind::MessageId,
+                            target: id.to_string(),
+                            source: None,
+                        },
+                    ));
+                }
+                rest = &rest[end + 1..];
+            }
+        }
+        offset += line.len() + 1;
+    }
+    let finder = linkify::LinkFinder::new();
+    for link in finder.links(text) {
+        if message_id_spans
+            .iter()
+            .any(|(start, end)| link.start() < *end && *start < link.end())
+        {
+            continue;
+        }
+        let s = link.as_str();
+        let (kind, target) = match link.kind() {
+            /* linkify takes `mid:` URLs for e-mail addresses */
+            linkify::LinkKind::Email if text[..link.start()].ends_with("mid:") => {
+                (PagerLinkKind::MessageId, percent_decode(s))
+            }
+            linkify::LinkKind::Email if s.starts_with("mid:") => {
+                (PagerLinkKind::MessageId, percent_decode(&s["mid:".len()..]))
+            }
+            linkify::LinkKind::Email => (PagerLinkKind::Mailto, s.to_string()),
+            linkify::LinkKind::Url if s.starts_with("mid:") => (
+                PagerLinkKind::MessageId,
+                percent_decode(&s["mid:".len()..])
+                    .split('/')
+                    .next()
+                    .unwrap_or_default()
+                    .to_string(),
+            ),
+            linkify::LinkKind::Url if s.starts_with("mailto:") => {
+                (PagerLinkKind::Mailto, s.to_string())
+            }
+            _ => (PagerLinkKind::Url, s.to_string()),
+        };
+        ret.push((
+            link.start(),
+            PagerLink {
+                kind,
+                target,
+                source: None,
+            },
+        ));
+    }
+    ret.sort_by_key(|(pos, _)| *pos);
+    let mut links: Vec<PagerLink> = vec![];
+    for (_, link) in ret {
+        if !links.iter().any(|l| l.target == link.target) {
+            links.push(link);
+        }
+    }
+    links
+}
+
+fn percent_decode(s: &str) -> String {
+    let bytes = s.as_bytes();
+    let mut ret = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%' && i + 2 < bytes.len() {
+            if let Some(b) = std::str::from_utf8(&bytes[i + 1..i + 3])
+                .ok()
+                .and_then(|hex| u8::from_str_radix(hex, 16).ok())
+            {
+                ret.push(b);
+                i += 3;
+                continue;
+            }
+        }
+        ret.push(bytes[i]);
+        i += 1;
+    }
+    String::from_utf8_lossy(&ret).to_string()
+}
+
 /// A pager for text.
 /// `Pager` holds its own content in its own `CellBuffer` and when `draw` is called, it draws the
 /// current view of the text. It is responsible for scrolling etc.
@@ -42,8 +222,17 @@ pub struct Pager {
     content: CellBuffer,
     filtered_content: Option<(String, Result<CellBuffer>)>,
     text_lines: Vec<String>,
+    /// Attributes of parts of `text_lines`: line, first column, column after the last one.
+    text_attrs: Vec<(usize, usize, usize, Attr)>,
+    /// `text_lines` were laid out by the caller and are not reflowed.
+    preformatted: bool,
     line_breaker: LineBreakText,
     movement: Option<PageMovement>,
+    /// Links that are not in the text, offered in link hint mode along with those found in it.
+    extra_links: Vec<PagerLink>,
+    link_hints: Option<LinkHints>,
+    /// Account to compose messages from and to look message-ids up in, in link hint mode.
+    account_hash: Option<AccountHash>,
     id: ComponentId,
 }
 
@@ -92,6 +281,268 @@ impl Pager {
         self.reflow
     }
 
+    /// Set links to offer in link hint mode besides those found in the text.
+    pub fn set_extra_links(&mut self, new_val: Vec<PagerLink>) -> &mut Self {
+        self.extra_links = new_val;
+        self
+    }
+
+    pub fn set_account_hash(&mut self, new_val: Option<AccountHash>) -> &mut Self {
+        self.account_hash = new_val;
+        self
+    }
+
+    fn start_link_hints(&mut self, action: LinkHintAction, context: &mut Context) {
+        if !self.line_breaker.is_finished() {
+            let old_lines_no = self.text_lines.len();
+            self.text_lines.extend(self.line_breaker.by_ref());
+            self.height += self.text_lines.len() - old_lines_no;
+        }
+        let mut links = find_links(&self.text);
+        for link in &self.extra_links {
+            if !links.iter().any(|l| l.target == link.target) {
+                links.push(link.clone());
+            }
+        }
+        if action == LinkHintAction::Compose {
+            links.retain(|l| l.kind == PagerLinkKind::Mailto);
+        }
+        if links.is_empty() {
+            context
+                .replies
+                .push_back(UIEvent::StatusEvent(StatusEvent::DisplayMessage(
+                    "No links found.".to_string(),
+                )));
+            return;
+        }
+        let labels = hint_labels(links.len());
+        let links = links
+            .into_iter()
+            .zip(labels)
+            .map(|(link, label)| {
+                let pos = if link.source.is_some() || self.filtered_content.is_some() {
+                    None
+                } else {
+                    self.find_link_position(&link.target)
+                };
+                (link, label, pos)
+            })
+            .collect();
+        self.link_hints = Some(LinkHints {
+            action,
+            links,
+            typed: String::new(),
+        });
+        self.dirty = true;
+    }
+
+    /// Position (line, column) of the first occurrence of `target`, or of its beginning if it
+    /// was broken across lines.
+    fn find_link_position(&self, target: &str) -> Option<(usize, usize)> {
+        let mut prefix = target;
+        prefix.truncate_at_boundary(20);
+        self.text_lines.iter().enumerate().find_map(|(y, l)| {
+            l.find(prefix)
+                .map(|offset| (y, l[..offset].grapheme_width()))
+        })
+    }
+
+    fn link_hint_chosen(&mut self, action: LinkHintAction, link: PagerLink, context: &mut Context) {
+        use std::convert::TryFrom;
+        let account_hash = match self
+            .account_hash
+            .or_else(|| context.accounts.keys().next().cloned())
+        {
+            Some(a) => a,
+            None => return,
+        };
+        match (action, link.kind) {
+            (LinkHintAction::Yank, _) => {
+                context
+                    .replies
+                    .push_back(UIEvent::Action(Action::CopyToClipboard(
+                        link.target.clone(),
+                    )));
+                context
+                    .replies
+                    .push_back(UIEvent::StatusEvent(StatusEvent::DisplayMessage(format!(
+                        "Copied {} to clipboard.",
+                        link.target
+                    ))));
+            }
+            (LinkHintAction::Open, PagerLinkKind::Mailto)
+            | (LinkHintAction::Compose, PagerLinkKind::Mailto) => {
+                let mailto = if link.target.starts_with("mailto:") {
+                    link.target.clone()
+                } else {
+                    format!("mailto:{}", link.target)
+                };
+                match melib::email::mailto::Mailto::try_from(mailto.as_bytes()) {
+                    Ok(mailto) => {
+                        let draft: Draft = mailto.into();
+                        let mut composer = Composer::with_account(account_hash, context);
+                        composer.set_draft(draft);
+                        context
+                            .replies
+                            .push_back(UIEvent::Action(Tab(New(Some(Box::new(composer))))));
+                    }
+                    Err(err) => {
+                        context.replies.push_back(UIEvent::StatusEvent(
+                            StatusEvent::DisplayMessage(format!(
+                                "Couldn't parse {}: {}",
+                                link.target, err
+                            )),
+                        ));
+                    }
+                }
+            }
+            (LinkHintAction::Compose, _) => {
+                context
+                    .replies
+                    .push_back(UIEvent::StatusEvent(StatusEvent::DisplayMessage(format!(
+                        "{} is not an e-mail address.",
+                        link.target
+                    ))));
+            }
+            (LinkHintAction::Open, PagerLinkKind::MessageId) => {
+                /* Look in the pager's account first */
+                let mut found = None;
+                for account in std::iter::once(&context.accounts[&account_hash])
+                    .chain(context.accounts.values())
+                {
+                    let envelopes = account.collection.envelopes.read().unwrap();
+                    if let Some(env_hash) = envelopes
+                        .values()
+                        .find(|e| e.message_id().raw() == link.target.as_bytes())
+                        .map(|e| e.hash())
+                    {
+                        let mailboxes = account.collection.mailboxes.read().unwrap();
+                        if let Some(mailbox_hash) = mailboxes
+                            .iter()
+                            .find(|(_, set)| set.contains(&env_hash))
+                            .map(|(h, _)| *h)
+                        {
+                            found = Some((account.hash(), mailbox_hash, env_hash));
+                            break;
+                        }
+                    }
+                }
+                if let Some(coordinates) = found {
+                    let view = MailView::new(coordinates, None, None, context);
+                    context
+                        .replies
+                        .push_back(UIEvent::Action(Tab(New(Some(Box::new(view))))));
+                } else {
+                    context
+                        .replies
+                        .push_back(UIEvent::StatusEvent(StatusEvent::DisplayMessage(format!(
+                            "Message <{}> not found.",
+                            link.target
+                        ))));
+                }
+            }
+            (LinkHintAction::Open, PagerLinkKind::Url) => {
+                use std::process::{Command, Stdio};
+                let url_launcher = context.settings.pager.url_launcher.as_deref().unwrap_or(
+                    #[cfg(target_os = "macos")]
+                    {
+                        "open"
+                    },
+                    #[cfg(not(target_os = "macos"))]
+                    {
+                        "xdg-open"
+                    },
+                );
+                match Command::new(url_launcher)
+                    .arg(&link.target)
+                    .stdin(Stdio::piped())
+                    .stdout(Stdio::piped())
+                    .spawn()
+                {
+                    Ok(child) => {
+                        context.children.push(child);
+                    }
+                    Err(err) => {
+                        context.replies.push_back(UIEvent::Notification(
+                            Some(format!("Failed to launch {:?}", url_launcher)),
+                            err.to_string(),
+                            Some(NotificationType::Error(melib::ErrorKind::External)),
+                        ));
+                    }
+                }
+            }
+        }
+    }
+
+    fn draw_link_hints(&self, grid: &mut CellBuffer, area: Area, context: &Context) {
+        let hints = match self.link_hints {
+            Some(ref hints) => hints,
+            None => return,
+        };
+        let highlight = crate::conf::value(context, "highlight");
+        let (upper_left, bottom_right) = area;
+        let visible = hints
+            .links
+            .iter()
+            .filter(|(_, label, _)| label.starts_with(&hints.typed));
+        let mut listed = vec![];
+        for (link, label, pos) in visible {
+            match *pos {
+                Some((y, x))
+                    if y >= self.cursor.1
+                        && y - self.cursor.1 <= height!(area)
+                        && x < width!(area) =>
+                {
+                    write_string_to_grid(
+                        label,
+                        grid,
+                        highlight.fg,
+                        highlight.bg,
+                        highlight.attrs | Attr::BOLD,
+                        (pos_inc(upper_left, (x, y - self.cursor.1)), bottom_right),
+                        None,
+                    );
+                }
+                Some(_) => {}
+                None => listed.push((link, label)),
+            }
+        }
+        /* Links that aren't in the text are listed at the bottom */
+        let rows = std::cmp::min(listed.len(), height!(area) / 2);
+        let start_y = (get_y(bottom_right) + 1).saturating_sub(rows);
+        let theme_default = crate::conf::value(context, "theme_default");
+        for (i, (link, label)) in listed.into_iter().take(rows).enumerate() {
+            let row = (
+                set_y(upper_left, start_y + i),
+                set_y(bottom_right, start_y + i),
+            );
+            clear_area(grid, row, theme_default);
+            let (x, _) = write_string_to_grid(
+                label,
+                grid,
+                highlight.fg,
+                highlight.bg,
+                highlight.attrs | Attr::BOLD,
+                row,
+                None,
+            );
+            let text = if let Some(ref source) = link.source {
+                format!(" {} ({})", link.target, source)
+            } else {
+                format!(" {}", link.target)
+            };
+            write_string_to_grid(
+                &text,
+                grid,
+                theme_default.fg,
+                theme_default.bg,
+                theme_default.attrs,
+                (set_x(row.0, x + 1), row.1),
+                None,
+            );
+        }
+    }
+
     pub fn update_from_str(&mut self, text: &str, mut width: Option<usize>) {
         if let Some(ref mut width) = width.as_mut() {
             if **width < self.minimum_width {
@@ -101,6 +552,8 @@ impl Pager {
 
         self.text = text.to_string();
         self.text_lines.clear();
+        self.text_attrs.clear();
+        self.preformatted = false;
         self.line_breaker = LineBreakText::new(self.text.clone(), self.reflow, width);
         self.height = 0;
         self.width = 0;
@@ -110,6 +563,33 @@ impl Pager {
         self.cursor = (0, 0);
     }
 
+    /// Show `lines` as they are, with `attrs` set on parts of them (line, first column, column
+    /// after the last one).
+    pub fn update_from_lines(
+        &mut self,
+        lines: Vec<String>,
+        attrs: Vec<(usize, usize, usize, Attr)>,
+    ) {
+        self.text = lines.join("\n");
+        self.text_lines = lines;
+        self.text_attrs = attrs;
+        self.preformatted = true;
+        self.line_breaker = LineBreakText::default();
+        self.height = self.text_lines.len();
+        self.width = 0;
+        self.search = None;
+        self.set_dirty(true);
+        self.initialised = false;
+        self.cursor = (0, 0);
+    }
+
+    /// Set `attrs` on parts of the text lines (line, first column, column after the last one).
+    /// Lines are counted after line breaking, so the text should fit in the pager's width.
+    pub fn set_text_attrs(&mut self, attrs: Vec<(usize, usize, usize, Attr)>) -> &mut Self {
+        self.text_attrs = attrs;
+        self
+    }
+
     pub fn from_string(
         text: String,
         context: Option<&Context>,
@@ -210,6 +690,14 @@ impl Pager {
         self.cursor.1
     }
 
+    /// Index of the first laid out line that is `line`, if the text isn't filtered.
+    pub fn find_line(&self, line: &str) -> Option<usize> {
+        if self.filtered_content.is_some() {
+            return None;
+        }
+        self.text_lines.iter().position(|l| l == line)
+    }
+
     pub fn size(&self) -> (usize, usize) {
         (self.width, self.height)
     }
@@ -220,7 +708,7 @@ impl Pager {
             width = self.minimum_width;
         }
         if self.filtered_content.is_none() {
-            if self.line_breaker.width() != Some(width.saturating_sub(4)) {
+            if !self.preformatted && self.line_breaker.width() != Some(width.saturating_sub(4)) {
                 let line_breaker = LineBreakText::new(
                     self.text.clone(),
                     self.reflow,
@@ -370,6 +858,18 @@ impl Pager {
         }
 
         let (upper_left, _bottom_right) = area;
+        for &(y, start, end, attrs) in self
+            .text_attrs
+            .iter()
+            .filter(|(y, _, _, _)| *y >= self.cursor.1 && *y < self.cursor.1 + height!(area))
+        {
+            let y = y - self.cursor.1 + get_y(upper_left);
+            let end = std::cmp::min(end, width!(area));
+            for c in grid.row_iter(get_x(upper_left) + start..get_x(upper_left) + end, y) {
+                let cell_attrs = grid[c].attrs();
+                grid[c].set_attrs(cell_attrs | attrs);
+            }
+        }
         #[cfg(feature = "regexp")]
         {
             for text_formatter in crate::conf::text_format_regexps(context, "pager.envelope.body") {
@@ -423,6 +923,7 @@ impl Pager {
                 }
             }
         }
+        self.draw_link_hints(grid, area, context);
     }
 }
 
@@ -647,6 +1148,39 @@ impl Component for Pager {
 
     fn process_event(&mut self, event: &mut UIEvent, context: &mut Context) -> bool {
         let shortcuts = self.get_shortcuts(context);
+        if let (Some(ref mut hints), UIEvent::Input(ref key)) = (self.link_hints.as_mut(), &event) {
+            match key {
+                Key::Esc => {
+                    self.link_hints = None;
+                }
+                Key::Backspace => {
+                    hints.typed.pop();
+                }
+                Key::Char(c) if HINT_KEYS.contains(*c) => {
+                    hints.typed.push(*c);
+                    let typed = hints.typed.as_str();
+                    if let Some((link, _, _)) =
+                        hints.links.iter().find(|(_, label, _)| label == typed)
+                    {
+                        let (action, link) = (hints.action, link.clone());
+                        self.link_hints = None;
+                        self.link_hint_chosen(action, link, context);
+                    } else if !hints
+                        .links
+                        .iter()
+                        .any(|(_, label, _)| label.starts_with(typed))
+                    {
+                        context.replies.push_back(UIEvent::StatusEvent(
+                            StatusEvent::DisplayMessage(format!("No link with hint `{}`.", typed)),
+                        ));
+                        self.link_hints = None;
+                    }
+                }
+                _ => {}
+            }
+            self.dirty = true;
+            return true;
+        }
         match event {
             UIEvent::ConfigReload { old_settings: _ } => {
                 self.set_colors(crate::conf::value(context, "theme_default"));
@@ -700,6 +1234,24 @@ impl Component for Pager {
                 self.dirty = true;
                 return true;
             }
+            UIEvent::Input(ref key)
+                if shortcut!(key == shortcuts[Self::DESCRIPTION]["open_link"]) =>
+            {
+                self.start_link_hints(LinkHintAction::Open, context);
+                return true;
+            }
+            UIEvent::Input(ref key)
+                if shortcut!(key == shortcuts[Self::DESCRIPTION]["yank_link"]) =>
+            {
+                self.start_link_hints(LinkHintAction::Yank, context);
+                return true;
+            }
+            UIEvent::Input(ref key)
+                if shortcut!(key == shortcuts[Self::DESCRIPTION]["compose_to_link"]) =>
+            {
+                self.start_link_hints(LinkHintAction::Compose, context);
+                return true;
+            }
             UIEvent::ChangeMode(UIMode::Normal) => {
                 self.dirty = true;
             }
@@ -842,3 +1394,36 @@ impl Component for Pager {
         self.id = id;
     }
 }
+
+#[test]
+fn test_pager_find_links() {
+    assert_eq!(hint_labels(3), vec!["a", "s", "d"]);
+    let labels = hint_labels(10);
+    assert_eq!(labels[0], "aa");
+    assert_eq!(labels[9], "sa");
+    assert!(labels.iter().all(|l| l.len() == 2));
+
+    let text = "Message-ID: <1234@example.com>\nIn-Reply-To: <abcd@example.com>\n\nSee \
+                https://example.com/page or write to user@example.com.\nAlso see \
+                mid:5678@example.com and https://example.com/page again.\n";
+    let links = find_links(text);
+    assert_eq!(
+        links
+            .iter()
+            .map(|l| (l.kind, l.target.as_str()))
+            .collect::<Vec<_>>(),
+        vec![
+            (PagerLinkKind::MessageId, "1234@example.com"),
+            (PagerLinkKind::MessageId, "abcd@example.com"),
+            (PagerLinkKind::Url, "https://example.com/page"),
+            (PagerLinkKind::Mailto, "user@example.com"),
+            (PagerLinkKind::MessageId, "5678@example.com"),
+        ]
+    );
+
+    assert_eq!(percent_decode("a%20b%2Fc"), "a b/c");
+    /* Invalid escapes, even before multi-byte characters, are kept as they are */
+    assert_eq!(percent_decode("%zz%"), "%zz%");
+    assert_eq!(percent_decode("%éa"), "%éa");
+    assert_eq!(percent_decode("a%2é"), "a%2é");
+}
@@ -36,6 +36,8 @@ pub mod composing;
 pub mod notifications;
 pub mod pager;
 pub mod pgp;
+pub mod outbox;
+pub mod rules;
 pub mod tags;
 #[macro_use]
 pub mod shortcuts;
@@ -47,6 +49,7 @@ pub use themes::*;
 pub mod accounts;
 pub use self::accounts::Account;
 pub use self::composing::*;
+pub use self::listing::unified_mailbox_matches;
 pub use self::pgp::*;
 pub use self::shortcuts::*;
 pub use self::tags::*;
@@ -54,6 +57,7 @@ pub use self::tags::*;
 use self::default_vals::*;
 use self::listing::ListingSettings;
 use self::notifications::NotificationsSettings;
+use self::rules::FilterRule;
 use self::terminal::TerminalSettings;
 use crate::pager::PagerSettings;
 use melib::conf::{AccountSettings, MailboxConf, ToggleFlag};
@@ -172,6 +176,10 @@ pub struct FileAccount {
     pub manual_refresh: bool,
     #[serde(default = "none")]
     pub refresh_command: Option<String>,
+    #[serde(default)]
+    pub rules: Vec<FilterRule>,
+    #[serde(default)]
+    pub saved_searches: IndexMap<String, Query>,
     #[serde(flatten)]
     pub conf_override: MailUIConf,
     #[serde(flatten)]
@@ -456,6 +464,8 @@ This is required so that you don't accidentally start meli and find out later th
                 manual_refresh,
                 refresh_command: _,
                 search_backend: _,
+                rules: _,
+                saved_searches: _,
                 conf_override: _,
             } = acc.clone();
 
@@ -1070,6 +1080,8 @@ mod dotaddressable {
                         "search_backend" => self.search_backend.lookup(field, tail),
                         "manual_refresh" => self.manual_refresh.lookup(field, tail),
                         "refresh_command" => self.refresh_command.lookup(field, tail),
+                        "rules" => self.rules.lookup(field, tail),
+                        "saved_searches" => self.saved_searches.lookup(field, tail),
                         "conf_override" => self.conf_override.lookup(field, tail),
                         "extra" => self.extra.lookup(field, tail),
                         other => Err(MeliError::new(format!(
@@ -1188,6 +1200,9 @@ server_username = "null"
 server_hostname = "example.com"
 server_password_command = "/bin/false"
 
+[accounts.imap.saved_searches]
+"Unread from boss" = "from:boss@example.com flags:unseen"
+
 [composing]
 send_mail = '/bin/false'
 "#;
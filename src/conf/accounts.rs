@@ -23,12 +23,17 @@
  * Account management from user configuration.
  */
 
+use super::outbox::{Outbox, OutboxItem};
+use super::rules::FilterRule;
 use super::{AccountConf, FileMailboxConf};
 use crate::jobs::{JobExecutor, JobId, JoinHandle};
 use indexmap::IndexMap;
 use melib::backends::*;
+use melib::datetime::UnixTimestamp;
+use melib::email::delivery_status::DeliveryReport;
 use melib::email::*;
 use melib::error::{ErrorKind, MeliError, Result};
+use melib::search::Query;
 use melib::text_processing::GlobMatch;
 use melib::thread::{SortField, SortOrder, Threads};
 use melib::AddressBook;
@@ -56,6 +61,9 @@ use std::pin::Pin;
 use std::result;
 use std::sync::{Arc, RwLock};
 
+/// How long saved searches wait for mailbox changes to settle before they run again.
+const SAVED_SEARCH_DELAY: std::time::Duration = std::time::Duration::from_secs(2);
+
 #[macro_export]
 macro_rules! try_recv_timeout {
     ($oneshot:expr) => {{
@@ -110,6 +118,9 @@ pub struct MailboxEntry {
     pub name: String,
     pub ref_mailbox: Mailbox,
     pub conf: FileMailboxConf,
+    /// The query of a saved search. Saved searches are virtual mailboxes that contain the
+    /// envelopes of the account's other mailboxes that match it.
+    pub saved_search: Option<Query>,
 }
 
 impl MailboxEntry {
@@ -135,6 +146,110 @@ impl MailboxEntry {
             &self.ref_mailbox.name()
         }
     }
+
+    pub fn is_saved_search(&self) -> bool {
+        self.saved_search.is_some()
+    }
+}
+
+/// The virtual mailbox of a saved search, configured with `[accounts.NAME.saved_searches]`.
+///
+/// Its envelopes are kept in the account's `Collection` like any other mailbox's, so its counts
+/// are computed from there.
+#[derive(Debug, Clone)]
+pub struct SavedSearchMailbox {
+    hash: MailboxHash,
+    name: String,
+    is_subscribed: bool,
+    envelopes: Arc<RwLock<HashMap<EnvelopeHash, Envelope>>>,
+    mailboxes: Arc<RwLock<HashMap<MailboxHash, HashSet<EnvelopeHash>>>>,
+}
+
+impl SavedSearchMailbox {
+    pub fn new(account_hash: AccountHash, name: &str, collection: &Collection) -> Self {
+        use std::collections::hash_map::DefaultHasher;
+        use std::hash::{Hash, Hasher};
+        let mut hasher = DefaultHasher::new();
+        "saved_searches".hash(&mut hasher);
+        account_hash.hash(&mut hasher);
+        name.hash(&mut hasher);
+        SavedSearchMailbox {
+            hash: hasher.finish(),
+            name: name.to_string(),
+            is_subscribed: true,
+            envelopes: collection.envelopes.clone(),
+            mailboxes: collection.mailboxes.clone(),
+        }
+    }
+}
+
+impl BackendMailbox for SavedSearchMailbox {
+    fn hash(&self) -> MailboxHash {
+        self.hash
+    }
+
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn path(&self) -> &str {
+        &self.name
+    }
+
+    fn change_name(&mut self, new_name: &str) {
+        self.name = new_name.to_string();
+    }
+
+    fn clone(&self) -> Mailbox {
+        Box::new(std::clone::Clone::clone(self))
+    }
+
+    fn children(&self) -> &[MailboxHash] {
+        &[]
+    }
+
+    fn parent(&self) -> Option<MailboxHash> {
+        None
+    }
+
+    fn is_subscribed(&self) -> bool {
+        self.is_subscribed
+    }
+
+    fn set_is_subscribed(&mut self, new_val: bool) -> Result<()> {
+        self.is_subscribed = new_val;
+        Ok(())
+    }
+
+    fn set_special_usage(&mut self, _new_val: SpecialUsageMailbox) -> Result<()> {
+        Err(MeliError::new("Saved searches have no special usage.")
+            .set_kind(ErrorKind::NotSupported))
+    }
+
+    fn special_usage(&self) -> SpecialUsageMailbox {
+        SpecialUsageMailbox::Normal
+    }
+
+    fn permissions(&self) -> MailboxPermissions {
+        MailboxPermissions {
+            delete_mailbox: false,
+            ..MailboxPermissions::default()
+        }
+    }
+
+    fn count(&self) -> Result<(usize, usize)> {
+        let mailboxes = self.mailboxes.read()?;
+        let envelopes = self.envelopes.read()?;
+        let env_hashes = match mailboxes.get(&self.hash) {
+            Some(env_hashes) => env_hashes,
+            None => return Ok((0, 0)),
+        };
+        let unseen = env_hashes
+            .iter()
+            .filter(|h| envelopes.get(h).map(|e| !e.is_seen()).unwrap_or(false))
+            .count();
+        Ok((unseen, env_hashes.len()))
+    }
 }
 
 #[derive(Debug)]
@@ -157,6 +272,18 @@ pub struct Account {
     sender: Sender<ThreadEvent>,
     event_queue: VecDeque<(MailboxHash, RefreshEvent)>,
     pub backend_capabilities: MailBackendCapabilities,
+    /// Delivery status and disposition notifications, by the Message-ID of the reported message.
+    pub delivery_reports: HashMap<String, Vec<(UnixTimestamp, DeliveryReport)>>,
+    /// Reports that haven't been parsed yet, by the Message-ID their `In-Reply-To` or
+    /// `References` headers refer to.
+    unparsed_delivery_reports: HashMap<String, SmallVec<[EnvelopeHash; 1]>>,
+    /// Reports without such headers; their body has to be parsed to find the reported message.
+    unreferenced_delivery_reports: HashSet<EnvelopeHash>,
+    /// Messages scheduled to be sent later and messages that failed to be sent.
+    pub outbox: Outbox,
+    /// Saved searches to run again when `saved_searches_timer` fires.
+    pending_saved_searches: HashSet<MailboxHash>,
+    saved_searches_timer: Option<crate::jobs::Timer>,
 }
 
 pub enum JobRequest {
@@ -216,6 +343,10 @@ pub enum JobRequest {
     Search {
         handle: JoinHandle<Result<()>>,
     },
+    SavedSearch {
+        mailbox_hash: MailboxHash,
+        handle: JoinHandle<Result<SmallVec<[EnvelopeHash; 512]>>>,
+    },
     AsBytes {
         handle: JoinHandle<Result<()>>,
     },
@@ -230,6 +361,14 @@ pub enum JobRequest {
     Watch {
         handle: JoinHandle<Result<()>>,
     },
+    DeliveryReports {
+        handle: JoinHandle<Result<Vec<(UnixTimestamp, DeliveryReport)>>>,
+    },
+    OutboxSend {
+        id: String,
+        store_sent_mail: bool,
+        handle: JoinHandle<Result<()>>,
+    },
 }
 
 impl Drop for JobRequest {
@@ -261,10 +400,15 @@ impl Drop for JobRequest {
             JobRequest::Fetch { handle, .. } => {
                 handle.cancel();
             }
+            JobRequest::SavedSearch { handle, .. } => {
+                handle.cancel();
+            }
             JobRequest::Mailboxes { handle, .. } => {
                 handle.cancel();
             }
             JobRequest::CopyTo { handle, .. } => { handle.cancel(); }
+            JobRequest::DeliveryReports { handle, .. } => { handle.cancel(); }
+            JobRequest::OutboxSend { handle, .. } => { handle.cancel(); }
             JobRequest::SendMessage => {}
         }
     }
@@ -290,6 +434,9 @@ impl core::fmt::Debug for JobRequest {
             }
             //JobRequest::RenameMailbox,
             JobRequest::Search { .. } => write!(f, "JobRequest::Search"),
+            JobRequest::SavedSearch { mailbox_hash, .. } => {
+                write!(f, "JobRequest::SavedSearch({})", mailbox_hash)
+            }
             JobRequest::AsBytes { .. } => write!(f, "JobRequest::AsBytes"),
             JobRequest::SetMailboxPermissions { .. } => {
                 write!(f, "JobRequest::SetMailboxPermissions")
@@ -298,6 +445,8 @@ impl core::fmt::Debug for JobRequest {
                 write!(f, "JobRequest::SetMailboxSubscription")
             }
             JobRequest::Watch { .. } => write!(f, "JobRequest::Watch"),
+            JobRequest::DeliveryReports { .. } => write!(f, "JobRequest::DeliveryReports"),
+            JobRequest::OutboxSend { id, .. } => write!(f, "JobRequest::OutboxSend({})", id),
             JobRequest::SendMessage => write!(f, "JobRequest::SendMessage"),
             JobRequest::SendMessageBackground { .. } => {
                 write!(f, "JobRequest::SendMessageBackground")
@@ -332,10 +481,13 @@ impl core::fmt::Display for JobRequest {
             JobRequest::DeleteMailbox { .. } => write!(f, "Delete mailbox"),
             //JobRequest::RenameMailbox,
             JobRequest::Search { .. } => write!(f, "Search"),
+            JobRequest::SavedSearch { .. } => write!(f, "Saved search"),
             JobRequest::AsBytes { .. } => write!(f, "Message body fetch"),
             JobRequest::SetMailboxPermissions { .. } => write!(f, "Set mailbox permissions"),
             JobRequest::SetMailboxSubscription { .. } => write!(f, "Set mailbox subscription"),
             JobRequest::Watch { .. } => write!(f, "Background watch"),
+            JobRequest::DeliveryReports { .. } => write!(f, "Fetch delivery reports"),
+            JobRequest::OutboxSend { .. } => write!(f, "Sending queued message"),
             JobRequest::SendMessageBackground { .. } | JobRequest::SendMessage => {
                 write!(f, "Sending message")
             }
@@ -366,6 +518,10 @@ impl JobRequest {
             _ => false,
         }
     }
+
+    pub fn is_saved_search(&self, mailbox_hash: MailboxHash) -> bool {
+        matches!(self, JobRequest::SavedSearch { mailbox_hash: h, .. } if *h == mailbox_hash)
+    }
 }
 
 impl Drop for Account {
@@ -505,6 +661,7 @@ impl Account {
                     .unwrap();
             }
         }
+        let outbox = Outbox::new(&name);
         Ok(Account {
             hash,
             name,
@@ -527,6 +684,12 @@ impl Account {
             event_queue: VecDeque::with_capacity(8),
             backend_capabilities: backend.capabilities(),
             backend: Arc::new(RwLock::new(backend)),
+            delivery_reports: Default::default(),
+            unparsed_delivery_reports: Default::default(),
+            unreferenced_delivery_reports: Default::default(),
+            outbox,
+            pending_saved_searches: Default::default(),
+            saved_searches_timer: None,
         })
     }
 
@@ -578,6 +741,7 @@ impl Account {
                         name: f.path().to_string(),
                         status: MailboxStatus::None,
                         conf: conf.clone(),
+                        saved_search: None,
                     },
                 );
             } else {
@@ -602,6 +766,7 @@ impl Account {
                         name: f.path().to_string(),
                         status: MailboxStatus::None,
                         conf: new,
+                        saved_search: None,
                     },
                 );
             }
@@ -686,11 +851,29 @@ impl Account {
             self.collection.new_mailbox(*h);
         }
 
+        for (sort_order, (name, query)) in self.settings.conf.saved_searches.iter().enumerate() {
+            let mailbox = SavedSearchMailbox::new(self.hash, name, &self.collection);
+            let mut conf = FileMailboxConf::default();
+            conf.mailbox_conf.sort_order = Some(sort_order);
+            self.collection.new_mailbox(mailbox.hash());
+            mailbox_entries.insert(
+                mailbox.hash(),
+                MailboxEntry {
+                    ref_mailbox: Box::new(mailbox),
+                    name: name.to_string(),
+                    status: MailboxStatus::None,
+                    conf,
+                    saved_search: Some(query.clone()),
+                },
+            );
+        }
+
         build_mailboxes_order(&mut tree, &mailbox_entries, &mut mailboxes_order);
         self.mailboxes_order = mailboxes_order;
         self.mailbox_entries = mailbox_entries;
         self.tree = tree;
         self.sent_mailbox = sent_mailbox;
+        self.run_saved_searches();
         Ok(())
     }
 
@@ -702,12 +885,44 @@ impl Account {
             return None;
         }
 
+        let (removed, changed): (SmallVec<[EnvelopeHash; 2]>, SmallVec<[EnvelopeHash; 2]>) =
+            match event.kind {
+                RefreshEventKind::Update(old_hash, ref envelope) => (
+                    smallvec::smallvec![old_hash],
+                    smallvec::smallvec![envelope.hash()],
+                ),
+                RefreshEventKind::Rename(old_hash, new_hash) => {
+                    (smallvec::smallvec![old_hash], smallvec::smallvec![new_hash])
+                }
+                RefreshEventKind::Create(ref envelope) => {
+                    (SmallVec::new(), smallvec::smallvec![envelope.hash()])
+                }
+                RefreshEventKind::NewFlags(env_hash, _) => {
+                    (SmallVec::new(), smallvec::smallvec![env_hash])
+                }
+                RefreshEventKind::Remove(env_hash) => {
+                    (smallvec::smallvec![env_hash], SmallVec::new())
+                }
+                _ => (SmallVec::new(), SmallVec::new()),
+            };
+        let ret = self.process_refresh_event(event, mailbox_hash);
+        if !removed.is_empty() || !changed.is_empty() {
+            self.update_saved_searches(mailbox_hash, &removed, &changed);
+        }
+        ret
+    }
+
+    fn process_refresh_event(
+        &mut self,
+        event: RefreshEvent,
+        mailbox_hash: MailboxHash,
+    ) -> Option<UIEvent> {
         {
             //let mailbox: &mut Mailbox = self.mailboxes[idx].as_mut().unwrap().as_mut().unwrap();
             match event.kind {
                 RefreshEventKind::Update(old_hash, envelope) => {
                     if !self.collection.contains_key(&old_hash) {
-                        return self.reload(
+                        return self.process_refresh_event(
                             RefreshEvent {
                                 account_hash: event.account_hash,
                                 mailbox_hash: event.mailbox_hash,
@@ -723,6 +938,11 @@ impl Account {
                                 (*envelope).clone(),
                                 self.backend.clone(),
                                 self.name.clone(),
+                                self.mailbox_entries[&mailbox_hash]
+                                    .ref_mailbox
+                                    .path()
+                                    .to_string(),
+                                self.collection.tag_index.clone(),
                             )
                         }) {
                             Err(err) => {
@@ -779,6 +999,11 @@ impl Account {
                                 self.collection.envelopes.read().unwrap()[&env_hash].clone(),
                                 self.backend.clone(),
                                 self.name.clone(),
+                                self.mailbox_entries[&mailbox_hash]
+                                    .ref_mailbox
+                                    .path()
+                                    .to_string(),
+                                self.collection.tag_index.clone(),
                             )
                         }) {
                             Ok(job) => {
@@ -826,6 +1051,11 @@ impl Account {
                                 self.collection.envelopes.read().unwrap()[&new_hash].clone(),
                                 self.backend.clone(),
                                 self.name.clone(),
+                                self.mailbox_entries[&mailbox_hash]
+                                    .ref_mailbox
+                                    .path()
+                                    .to_string(),
+                                self.collection.tag_index.clone(),
                             )
                         }) {
                             Err(err) => {
@@ -884,6 +1114,11 @@ impl Account {
                             (*envelope).clone(),
                             self.backend.clone(),
                             self.name.clone(),
+                            self.mailbox_entries[&mailbox_hash]
+                                .ref_mailbox
+                                .path()
+                                .to_string(),
+                            self.collection.tag_index.clone(),
                         ));
                         self.insert_job(
                             handle.job_id,
@@ -900,11 +1135,20 @@ impl Account {
                         );
                     }
 
+                    self.index_delivery_report(&envelope);
                     if self.collection.insert(*envelope, mailbox_hash) {
                         /* is a duplicate */
                         return None;
                     }
 
+                    if self
+                        .apply_rules(mailbox_hash, &[env_hash], false)
+                        .contains(&env_hash)
+                    {
+                        /* a rule moved the message away or marked it as seen */
+                        return Some(UIEvent::MailboxUpdate((self.hash, mailbox_hash)));
+                    }
+
                     if self.mailbox_entries[&mailbox_hash]
                         .conf
                         .mailbox_conf
@@ -981,6 +1225,7 @@ impl Account {
                         .get_threads(mailbox_hash)
                         .find_group(self.collection.get_threads(mailbox_hash)[&thread_hash].group);
                     self.collection.remove(env_hash, mailbox_hash);
+                    self.unreferenced_delivery_reports.remove(&env_hash);
                     return Some(EnvelopeRemove(env_hash, thread_hash));
                 }
                 RefreshEventKind::Rescan => {
@@ -1025,6 +1270,10 @@ impl Account {
         None
     }
     pub fn refresh(&mut self, mailbox_hash: MailboxHash) -> Result<()> {
+        if self.mailbox_entries[&mailbox_hash].is_saved_search() {
+            self.run_saved_search(mailbox_hash);
+            return Ok(());
+        }
         if let Some(ref refresh_command) = self.settings.conf().refresh_command {
             let child = std::process::Command::new("sh")
                 .args(&["-c", refresh_command])
@@ -1142,6 +1391,16 @@ impl Account {
             {
                 Ok(())
             }
+            MailboxStatus::None if self.mailbox_entries[&mailbox_hash].is_saved_search() => {
+                if !self
+                    .active_jobs
+                    .values()
+                    .any(|j| j.is_saved_search(mailbox_hash))
+                {
+                    self.run_saved_search(mailbox_hash);
+                }
+                Err(0)
+            }
             MailboxStatus::None => {
                 if !self.active_jobs.values().any(|j| j.is_fetch(mailbox_hash)) {
                     let mailbox_job = self.backend.write().unwrap().fetch(mailbox_hash);
@@ -1431,6 +1690,71 @@ impl Account {
         }
     }
 
+    /// Add `message` to the outbox, to be sent at `send_at`. If sending it already failed with
+    /// `error`, it is retried after the first backoff interval instead.
+    pub fn queue_message(
+        &mut self,
+        message: String,
+        server_submission: bool,
+        send_at: UnixTimestamp,
+        error: Option<&MeliError>,
+    ) -> Result<()> {
+        let mut item = OutboxItem::new(message, server_submission, send_at);
+        item.scheduled = error.is_none();
+        let id = item.id.clone();
+        self.outbox.push(item)?;
+        if let Some(err) = error {
+            self.outbox.failed(&id, err, send_at)?;
+        }
+        Ok(())
+    }
+
+    /// Start sending the outbox messages that are due.
+    pub fn process_outbox(&mut self, settings: &crate::conf::Settings) {
+        use crate::conf::composing::SendMail;
+        let send_mail = self
+            .settings
+            .conf_override
+            .composing
+            .send_mail
+            .as_ref()
+            .unwrap_or(&settings.composing.send_mail)
+            .clone();
+        let store_sent_mail = *self
+            .settings
+            .conf_override
+            .composing
+            .store_sent_mail
+            .as_ref()
+            .unwrap_or(&settings.composing.store_sent_mail);
+        let now = melib::datetime::now();
+        for id in self.outbox.due(now) {
+            let (message, server_submission) = match self.outbox.get_mut(&id) {
+                /* Wait for the connection instead of counting it as a failed attempt */
+                Some(item) if item.server_submission && self.is_online.is_err() => continue,
+                Some(item) => {
+                    item.is_sending = true;
+                    (item.message_to_send(now), item.server_submission)
+                }
+                None => continue,
+            };
+            let send_cb = self.send_async(if server_submission {
+                SendMail::ServerSubmission
+            } else {
+                send_mail.clone()
+            });
+            let handle = self.job_executor.spawn_blocking(send_cb(Arc::new(message)));
+            self.insert_job(
+                handle.job_id,
+                JobRequest::OutboxSend {
+                    id,
+                    store_sent_mail,
+                    handle,
+                },
+            );
+        }
+    }
+
     pub fn contains_key(&self, h: EnvelopeHash) -> bool {
         self.collection.contains_key(&h)
     }
@@ -1581,31 +1905,133 @@ impl Account {
     pub fn search(
         &self,
         search_term: &str,
-        _sort: (SortField, SortOrder),
+        sort: (SortField, SortOrder),
         mailbox_hash: MailboxHash,
     ) -> ResultFuture<SmallVec<[EnvelopeHash; 512]>> {
-        let query = melib::search::Query::try_from(search_term)?;
+        let query = Query::try_from(search_term)?;
+        self.search_query(query, sort, Some(mailbox_hash))
+    }
+
+    /// Search mailbox `mailbox_hash` with the account's search backend, or every mailbox that has
+    /// been loaded if it is `None`.
+    pub fn search_query(
+        &self,
+        query: Query,
+        _sort: (SortField, SortOrder),
+        mailbox_hash: Option<MailboxHash>,
+    ) -> ResultFuture<SmallVec<[EnvelopeHash; 512]>> {
+        /* The envelopes of saved searches are in other mailboxes, search those instead */
+        let mailbox_hash = mailbox_hash.filter(|h| {
+            self.mailbox_entries
+                .get(h)
+                .map(|entry| !entry.is_saved_search())
+                .unwrap_or(true)
+        });
+        let loaded_mailboxes = || -> SmallVec<[MailboxHash; 16]> {
+            self.mailbox_entries
+                .iter()
+                .filter(|(_, entry)| {
+                    !entry.is_saved_search()
+                        && (entry.status.is_available() || entry.status.is_parsing())
+                })
+                .map(|(h, _)| *h)
+                .collect()
+        };
         match self.settings.conf.search_backend {
             #[cfg(feature = "sqlite3")]
             crate::conf::SearchBackend::Sqlite3 => crate::sqlite3::search(&query, _sort),
             crate::conf::SearchBackend::Auto | crate::conf::SearchBackend::None => {
                 if self.backend_capabilities.supports_search {
-                    self.backend
-                        .read()
-                        .unwrap()
-                        .search(query, Some(mailbox_hash))
-                } else {
-                    use melib::search::QueryTrait;
+                    let backend_search = || -> ResultFuture<SmallVec<[EnvelopeHash; 512]>> {
+                        if let Some(mailbox_hash) = mailbox_hash {
+                            return self
+                                .backend
+                                .read()
+                                .unwrap()
+                                .search(query.clone(), Some(mailbox_hash));
+                        }
+                        /* Not every backend can search without a mailbox, so search them one
+                         * by one */
+                        let searches = loaded_mailboxes()
+                            .into_iter()
+                            .map(|mailbox_hash| {
+                                self.backend
+                                    .read()
+                                    .unwrap()
+                                    .search(query.clone(), Some(mailbox_hash))
+                            })
+                            .collect::<Result<Vec<_>>>()?;
+                        Ok(Box::pin(async move {
+                            let mut ret = SmallVec::new();
+                            for results in futures::future::try_join_all(searches).await? {
+                                ret.extend(results);
+                            }
+                            Ok(ret)
+                        }))
+                    };
+                    match backend_search() {
+                        /* The backend can't express some of the query's terms, match the
+                         * envelopes ourselves instead */
+                        Err(err) if err.kind == ErrorKind::NotSupported => {}
+                        ret => return ret,
+                    }
+                }
+                {
+                    use melib::search::{MatchContext, QueryTrait};
                     let mut ret = SmallVec::new();
-                    let envelopes = self.collection.envelopes.read().unwrap();
-                    for &env_hash in self.collection.get_mailbox(mailbox_hash).iter() {
-                        if let Some(envelope) = envelopes.get(&env_hash) {
-                            if envelope.is_match(&query) {
-                                ret.push(env_hash);
+                    /* Queries with mailbox terms are matched against every mailbox */
+                    let mailbox_hashes: SmallVec<[MailboxHash; 16]> = match mailbox_hash {
+                        Some(mailbox_hash) if !query.needs_mailbox() => {
+                            smallvec::smallvec![mailbox_hash]
+                        }
+                        _ => loaded_mailboxes(),
+                    };
+                    let mut ops = vec![];
+                    {
+                        let envelopes = self.collection.envelopes.read().unwrap();
+                        let mailboxes = self.collection.mailboxes.read().unwrap();
+                        for mailbox_hash in mailbox_hashes {
+                            let path = self.mailbox_entries[&mailbox_hash].ref_mailbox.path();
+                            for env_hash in mailboxes.get(&mailbox_hash).into_iter().flatten() {
+                                if let Some(envelope) = envelopes.get(env_hash) {
+                                    if query.needs_body() {
+                                        ops.push((
+                                            envelope.clone(),
+                                            path.to_string(),
+                                            self.operation(*env_hash)?.as_bytes()?,
+                                        ));
+                                    } else if (
+                                        envelope,
+                                        MatchContext {
+                                            bytes: None,
+                                            mailbox_path: Some(path),
+                                        },
+                                    )
+                                        .is_match(&query)
+                                    {
+                                        ret.push(*env_hash);
+                                    }
+                                }
                             }
                         }
                     }
-                    Ok(Box::pin(async { Ok(ret) }))
+                    Ok(Box::pin(async move {
+                        for (envelope, path, bytes_fut) in ops {
+                            let bytes = bytes_fut.await?;
+                            if (
+                                &envelope,
+                                MatchContext {
+                                    bytes: Some(&bytes),
+                                    mailbox_path: Some(&path),
+                                },
+                            )
+                                .is_match(&query)
+                            {
+                                ret.push(envelope.hash());
+                            }
+                        }
+                        Ok(ret)
+                    }))
                 }
             }
         }
@@ -1615,7 +2041,7 @@ impl Account {
         if let Some((mailbox_hash, _)) = self
             .mailbox_entries
             .iter()
-            .find(|(_, f)| f.ref_mailbox.path() == path)
+            .find(|(_, f)| !f.is_saved_search() && f.ref_mailbox.path() == path)
         {
             Ok(*mailbox_hash)
         } else {
@@ -1623,6 +2049,539 @@ impl Account {
         }
     }
 
+    /// The mailbox that actually contains envelope `env_hash` of mailbox `mailbox_hash`, which
+    /// is `mailbox_hash` itself unless it is a saved search.
+    pub fn envelope_mailbox(
+        &self,
+        mailbox_hash: MailboxHash,
+        env_hash: EnvelopeHash,
+    ) -> MailboxHash {
+        if !self
+            .mailbox_entries
+            .get(&mailbox_hash)
+            .map(MailboxEntry::is_saved_search)
+            .unwrap_or(false)
+        {
+            return mailbox_hash;
+        }
+        let mailboxes = self.collection.mailboxes.read().unwrap();
+        self.mailbox_entries
+            .iter()
+            .filter(|(_, entry)| !entry.is_saved_search())
+            .map(|(h, _)| *h)
+            .find(|h| {
+                mailboxes
+                    .get(h)
+                    .map(|env_hashes| env_hashes.contains(&env_hash))
+                    .unwrap_or(false)
+            })
+            .unwrap_or(mailbox_hash)
+    }
+
+    /// Group envelopes of mailbox `mailbox_hash` by the mailbox that actually contains them, for
+    /// backend operations that need it.
+    pub fn envelopes_by_mailbox(
+        &self,
+        mailbox_hash: MailboxHash,
+        env_hashes: &[EnvelopeHash],
+    ) -> IndexMap<MailboxHash, EnvelopeHashBatch> {
+        let mut ret: IndexMap<MailboxHash, EnvelopeHashBatch> = IndexMap::default();
+        for &env_hash in env_hashes {
+            match ret.entry(self.envelope_mailbox(mailbox_hash, env_hash)) {
+                indexmap::map::Entry::Occupied(mut batch) => batch.get_mut().rest.push(env_hash),
+                indexmap::map::Entry::Vacant(batch) => {
+                    batch.insert(env_hash.into());
+                }
+            }
+        }
+        ret
+    }
+
+    /// Run every saved search of the account again.
+    pub fn run_saved_searches(&mut self) {
+        let saved_searches = self
+            .mailbox_entries
+            .iter()
+            .filter(|(_, entry)| entry.is_saved_search())
+            .map(|(h, _)| *h)
+            .collect::<SmallVec<[MailboxHash; 8]>>();
+        for mailbox_hash in saved_searches {
+            self.run_saved_search(mailbox_hash);
+        }
+    }
+
+    /// Run the saved searches that may match envelopes of mailbox `mailbox_hash` again, once
+    /// changes have settled for `SAVED_SEARCH_DELAY`, so that a burst of fetches and refresh
+    /// events causes a single search.
+    fn schedule_saved_searches(&mut self, mailbox_hash: MailboxHash) {
+        let path = match self.mailbox_entries.get(&mailbox_hash) {
+            Some(entry) if !entry.is_saved_search() => entry.ref_mailbox.path().to_string(),
+            _ => return,
+        };
+        let saved_searches = self
+            .mailbox_entries
+            .iter()
+            .filter(|(_, entry)| {
+                entry
+                    .saved_search
+                    .as_ref()
+                    .map(|query| query.may_match_mailbox(&path))
+                    .unwrap_or(false)
+            })
+            .map(|(h, _)| *h)
+            .collect::<SmallVec<[MailboxHash; 8]>>();
+        for saved_search_hash in saved_searches {
+            self.schedule_saved_search(saved_search_hash);
+        }
+    }
+
+    fn schedule_saved_search(&mut self, saved_search_hash: MailboxHash) {
+        self.pending_saved_searches.insert(saved_search_hash);
+        if self.saved_searches_timer.is_none() {
+            self.saved_searches_timer = Some(
+                self.job_executor
+                    .clone()
+                    .create_timer(std::time::Duration::from_secs(0), SAVED_SEARCH_DELAY),
+            );
+        }
+    }
+
+    /// Whether `timer_id` is the timer of the saved searches scheduled to run again.
+    pub fn is_saved_searches_timer(&self, timer_id: melib::uuid::Uuid) -> bool {
+        self.saved_searches_timer
+            .as_ref()
+            .map(|timer| timer.id() == timer_id)
+            .unwrap_or(false)
+    }
+
+    /// Run the saved searches scheduled with `schedule_saved_searches`.
+    pub fn run_pending_saved_searches(&mut self) {
+        self.saved_searches_timer = None;
+        for mailbox_hash in std::mem::take(&mut self.pending_saved_searches) {
+            self.run_saved_search(mailbox_hash);
+        }
+    }
+
+    /// Search for the envelopes of saved search `mailbox_hash` in the background, replacing any
+    /// search of it that is still running.
+    pub fn run_saved_search(&mut self, mailbox_hash: MailboxHash) {
+        let query = match self
+            .mailbox_entries
+            .get(&mailbox_hash)
+            .and_then(|entry| entry.saved_search.clone())
+        {
+            Some(query) => query,
+            None => return,
+        };
+        let running_jobs = self
+            .active_jobs
+            .iter()
+            .filter(|(_, job)| job.is_saved_search(mailbox_hash))
+            .map(|(job_id, _)| *job_id)
+            .collect::<SmallVec<[JobId; 2]>>();
+        for job_id in running_jobs {
+            self.cancel_job(job_id);
+        }
+        match self.search_query(query, (SortField::Date, SortOrder::Desc), None) {
+            Ok(job) => {
+                if let MailboxStatus::None = self.mailbox_entries[&mailbox_hash].status {
+                    self.mailbox_entries[&mailbox_hash].status = MailboxStatus::Parsing(0, 0);
+                }
+                let handle = self.job_executor.spawn_specialized(job);
+                self.insert_job(
+                    handle.job_id,
+                    JobRequest::SavedSearch {
+                        mailbox_hash,
+                        handle,
+                    },
+                );
+            }
+            Err(err) => {
+                self.mailbox_entries[&mailbox_hash].status = MailboxStatus::Failed(err);
+                self.sender
+                    .send(ThreadEvent::UIEvent(UIEvent::MailboxUpdate((
+                        self.hash,
+                        mailbox_hash,
+                    ))))
+                    .unwrap();
+            }
+        }
+    }
+
+    /// Update the saved searches after envelopes of mailbox `mailbox_hash` were removed or
+    /// changed. Queries that need the message body are scheduled to run again, the rest are
+    /// matched against the changed envelopes only.
+    fn update_saved_searches(
+        &mut self,
+        mailbox_hash: MailboxHash,
+        removed: &[EnvelopeHash],
+        changed: &[EnvelopeHash],
+    ) {
+        use melib::search::{MatchContext, QueryTrait};
+        if self.mailbox_entries[&mailbox_hash].is_saved_search() {
+            return;
+        }
+        let path = self.mailbox_entries[&mailbox_hash]
+            .ref_mailbox
+            .path()
+            .to_string();
+        let saved_searches = self
+            .mailbox_entries
+            .iter()
+            .filter_map(|(h, entry)| Some((*h, entry.saved_search.clone()?)))
+            .filter(|(_, query)| query.may_match_mailbox(&path))
+            .collect::<SmallVec<[(MailboxHash, Query); 8]>>();
+        for (saved_search_hash, query) in saved_searches {
+            if query.needs_body() {
+                self.schedule_saved_search(saved_search_hash);
+                continue;
+            }
+            let mut env_hashes = self
+                .collection
+                .get_mailbox(saved_search_hash)
+                .iter()
+                .filter(|h| !removed.contains(h))
+                .cloned()
+                .collect::<HashSet<EnvelopeHash>>();
+            {
+                let envelopes = self.collection.envelopes.read().unwrap();
+                for env_hash in changed {
+                    match envelopes.get(env_hash) {
+                        Some(envelope)
+                            if (
+                                envelope,
+                                MatchContext {
+                                    bytes: None,
+                                    mailbox_path: Some(&path),
+                                },
+                            )
+                                .is_match(&query) =>
+                        {
+                            env_hashes.insert(*env_hash);
+                        }
+                        _ => {
+                            env_hashes.remove(env_hash);
+                        }
+                    }
+                }
+            }
+            if self.collection.set_mailbox(saved_search_hash, env_hashes) {
+                self.sender
+                    .send(ThreadEvent::UIEvent(UIEvent::MailboxUpdate((
+                        self.hash,
+                        saved_search_hash,
+                    ))))
+                    .unwrap();
+            }
+        }
+    }
+
+    /// Remember `envelope` if it is a delivery status or disposition notification, so that its
+    /// body is fetched only when the reported message is viewed.
+    fn index_delivery_report(&mut self, envelope: &Envelope) {
+        if !DeliveryReport::is_report(envelope) {
+            return;
+        }
+        match DeliveryReport::referenced_message_id(envelope) {
+            Some(message_id) => {
+                let env_hashes = self
+                    .unparsed_delivery_reports
+                    .entry(message_id)
+                    .or_default();
+                if !env_hashes.contains(&envelope.hash()) {
+                    env_hashes.push(envelope.hash());
+                }
+            }
+            None => {
+                self.unreferenced_delivery_reports.insert(envelope.hash());
+            }
+        }
+    }
+
+    /// Fetch and parse the delivery status and disposition notifications about envelope
+    /// `env_hash` that haven't been parsed yet. Reports that don't say which message they are
+    /// about are parsed too, if `env_hash` was sent from one of the account's identities.
+    /// Returns the id of the job, if there were any.
+    pub fn fetch_delivery_reports(&mut self, env_hash: EnvelopeHash) -> Option<JobId> {
+        let (message_id, is_ours) = {
+            let envelope = self.collection.get_env(env_hash);
+            let identities = &self.settings.account;
+            let is_ours = envelope.from().iter().any(|addr| {
+                let email = addr.get_email();
+                std::iter::once(&identities.identity)
+                    .chain(identities.extra_identities.iter())
+                    .any(|identity| identity.eq_ignore_ascii_case(&email))
+            });
+            (envelope.message_id_raw().into_owned(), is_ours)
+        };
+        let mut reports = self
+            .unparsed_delivery_reports
+            .remove(&message_id)
+            .unwrap_or_default();
+        if is_ours {
+            reports.extend(self.unreferenced_delivery_reports.drain());
+        }
+        if reports.is_empty() {
+            return None;
+        }
+        let mut futures = Vec::with_capacity(reports.len());
+        for env_hash in reports {
+            if !self.contains_key(env_hash) {
+                continue;
+            }
+            let timestamp = self.collection.get_env(env_hash).date();
+            match self.operation(env_hash).and_then(|mut op| op.as_bytes()) {
+                Ok(fut) => futures.push((timestamp, fut)),
+                Err(err) => {
+                    debug!("Could not fetch delivery report {}: {}", env_hash, err);
+                }
+            }
+        }
+        if futures.is_empty() {
+            return None;
+        }
+        let handle = self.job_executor.spawn_specialized(async move {
+            let mut ret = Vec::with_capacity(futures.len());
+            for (timestamp, fut) in futures {
+                match fut.await {
+                    Ok(bytes) => {
+                        if let Some(report) =
+                            DeliveryReport::from_attachment(&AttachmentBuilder::new(&bytes).build())
+                        {
+                            ret.push((timestamp, report));
+                        }
+                    }
+                    Err(err) => {
+                        debug!("Could not fetch delivery report: {}", err);
+                    }
+                }
+            }
+            Ok(ret)
+        });
+        let job_id = handle.job_id;
+        self.insert_job(job_id, JobRequest::DeliveryReports { handle });
+        Some(job_id)
+    }
+
+    /// The latest delivery status of each recipient of the message with `message_id` (without
+    /// angle brackets), from the reports fetched with `fetch_delivery_reports`.
+    pub fn delivery_status(&self, message_id: &str) -> Vec<(String, String)> {
+        let mut reports = match self.delivery_reports.get(message_id) {
+            Some(reports) => reports.iter().collect::<Vec<_>>(),
+            None => return vec![],
+        };
+        reports.sort_by_key(|(timestamp, _)| *timestamp);
+        let mut ret: IndexMap<String, String> = IndexMap::default();
+        for (_, report) in reports {
+            for (recipient, status) in report.recipient_statuses() {
+                ret.insert(recipient, status);
+            }
+        }
+        ret.into_iter().collect()
+    }
+
+    /// Apply the account's filtering rules to envelopes `env_hashes` of mailbox `mailbox_hash`.
+    ///
+    /// `on_request` is set when the user asked for the rules to run on this mailbox, in which
+    /// case rules without `mailboxes` apply to it too, not only to the inbox.
+    ///
+    /// The rules run in order in one background job, so that the actions of a rule on a message
+    /// are finished before the next rule's start. Returns the envelopes that a rule moved out of
+    /// the mailbox or marked as seen, as far as the envelopes tell: rules that need the message
+    /// body or size are only matched after the job has fetched the messages.
+    pub fn apply_rules(
+        &mut self,
+        mailbox_hash: MailboxHash,
+        env_hashes: &[EnvelopeHash],
+        on_request: bool,
+    ) -> HashSet<EnvelopeHash> {
+        let mut consumed = HashSet::default();
+        if self.settings.conf.rules.is_empty() || !self.mailbox_entries.contains_key(&mailbox_hash)
+        {
+            return consumed;
+        }
+        let path = self.mailbox_entries[&mailbox_hash]
+            .ref_mailbox
+            .path()
+            .to_string();
+        let is_inbox = self.special_use_mailbox(SpecialUsageMailbox::Inbox) == Some(mailbox_hash);
+        let mut rules = vec![];
+        for (idx, rule) in self.settings.conf.rules.iter().enumerate() {
+            if !rule.applies_to(&path, is_inbox || on_request) {
+                continue;
+            }
+            match self.rule_destinations(rule, mailbox_hash) {
+                Ok(destinations) => {
+                    rules.push((rule.display_name(idx), rule.clone(), destinations))
+                }
+                Err(err) => {
+                    self.sender
+                        .send(ThreadEvent::UIEvent(UIEvent::Notification(
+                            Some(format!("{}: {} failed", &self.name, rule.display_name(idx))),
+                            err.to_string(),
+                            Some(crate::types::NotificationType::Error(err.kind)),
+                        )))
+                        .expect("Could not send event on main channel");
+                }
+            }
+        }
+        let envelopes: Vec<Envelope> = env_hashes
+            .iter()
+            .filter(|env_hash| self.collection.contains_key(env_hash))
+            .map(|env_hash| self.collection.get_env(*env_hash).clone())
+            .collect();
+        if rules.is_empty() || envelopes.is_empty() {
+            return consumed;
+        }
+        let bytes = if rules.iter().any(|(_, rule, _)| rule.needs_bytes()) {
+            match envelopes
+                .iter()
+                .map(|envelope| {
+                    self.operation(envelope.hash())
+                        .and_then(|mut op| op.as_bytes())
+                })
+                .collect::<Result<Vec<_>>>()
+            {
+                Ok(futures) => Some(futures),
+                Err(err) => {
+                    self.sender
+                        .send(ThreadEvent::UIEvent(UIEvent::Notification(
+                            Some(format!("{}: filter rules failed", &self.name)),
+                            err.to_string(),
+                            Some(crate::types::NotificationType::Error(err.kind)),
+                        )))
+                        .expect("Could not send event on main channel");
+                    return consumed;
+                }
+            }
+        } else {
+            None
+        };
+        let mut stopped: HashSet<EnvelopeHash> = HashSet::default();
+        for (_, rule, _) in rules.iter().filter(|(_, rule, _)| !rule.query.needs_body()) {
+            for envelope in envelopes.iter() {
+                if !stopped.contains(&envelope.hash()) && rule.is_match(envelope, None, &path) {
+                    if rule.consumes() {
+                        consumed.insert(envelope.hash());
+                    }
+                    if rule.stop || rule.move_to.is_some() {
+                        stopped.insert(envelope.hash());
+                    }
+                }
+            }
+        }
+
+        let backend = self.backend.clone();
+        let messages_no = envelopes.len();
+        let fut = async move {
+            let mut messages = Vec::with_capacity(envelopes.len());
+            if let Some(bytes) = bytes {
+                for (envelope, fut) in envelopes.into_iter().zip(bytes) {
+                    messages.push((envelope, Some(fut.await?)));
+                }
+            } else {
+                messages.extend(envelopes.into_iter().map(|envelope| (envelope, None)));
+            }
+            let mut errors = vec![];
+            let mut stopped: HashSet<EnvelopeHash> = HashSet::default();
+            for (name, rule, destinations) in rules {
+                let matches: SmallVec<[EnvelopeHash; 8]> = messages
+                    .iter()
+                    .filter(|(envelope, bytes)| {
+                        !stopped.contains(&envelope.hash())
+                            && rule.is_match(envelope, bytes.as_deref(), &path)
+                    })
+                    .map(|(envelope, _)| envelope.hash())
+                    .collect();
+                let batch = match EnvelopeHashBatch::try_from(matches.as_slice()) {
+                    Ok(batch) => batch,
+                    Err(()) => continue,
+                };
+                /* Flags are set before the message is moved away */
+                let result: Result<()> = async {
+                    let flags = rule.flag_changes();
+                    if !flags.is_empty() {
+                        let fut = backend.write().unwrap().set_flags(
+                            batch.clone(),
+                            mailbox_hash,
+                            flags.into_iter().collect(),
+                        )?;
+                        fut.await?;
+                    }
+                    if let Some(ref command) = rule.pipe {
+                        for (envelope, bytes) in messages.iter() {
+                            if let (true, Some(bytes)) = (matches.contains(&envelope.hash()), bytes)
+                            {
+                                pipe_to_command(command, bytes)?;
+                            }
+                        }
+                    }
+                    for (destination_mailbox_hash, move_) in destinations {
+                        let fut = backend.write().unwrap().copy_messages(
+                            batch.clone(),
+                            mailbox_hash,
+                            destination_mailbox_hash,
+                            move_,
+                        )?;
+                        fut.await?;
+                    }
+                    Ok(())
+                }
+                .await;
+                if let Err(err) = result {
+                    errors.push(format!("{}: {}", name, err));
+                }
+                if rule.stop || rule.move_to.is_some() {
+                    stopped.extend(matches);
+                }
+            }
+            if errors.is_empty() {
+                Ok(())
+            } else {
+                Err(MeliError::new(errors.join("\n")))
+            }
+        };
+        /* `pipe` actions wait on a child process */
+        let handle = self.job_executor.spawn_blocking(fut);
+        self.insert_job(
+            handle.job_id,
+            JobRequest::Generic {
+                name: format!(
+                    "filter rules on {} message{}",
+                    messages_no,
+                    if messages_no == 1 { "" } else { "s" }
+                )
+                .into(),
+                handle,
+                on_finish: None,
+                logging_level: melib::LoggingLevel::TRACE,
+            },
+        );
+        consumed
+    }
+
+    /// The mailboxes `rule` copies or moves messages of `mailbox_hash` to, and whether it moves
+    /// them, in the order the copies are made.
+    fn rule_destinations(
+        &self,
+        rule: &FilterRule,
+        mailbox_hash: MailboxHash,
+    ) -> Result<SmallVec<[(MailboxHash, bool); 2]>> {
+        let mut ret = SmallVec::new();
+        for (path, move_) in rule
+            .copy_to
+            .iter()
+            .map(|p| (p, false))
+            .chain(rule.move_to.iter().map(|p| (p, true)))
+        {
+            let destination_mailbox_hash = self.mailbox_by_path(path)?;
+            if destination_mailbox_hash != mailbox_hash {
+                ret.push((destination_mailbox_hash, move_));
+            }
+        }
+        Ok(ret)
+    }
+
     pub fn process_event(&mut self, job_id: &JobId) -> bool {
         self.sender
             .send(ThreadEvent::UIEvent(UIEvent::StatusEvent(
@@ -1690,6 +2649,7 @@ impl Account {
                                     mailbox_hash,
                                 ))))
                                 .unwrap();
+                            self.schedule_saved_searches(mailbox_hash);
                             return true;
                         }
                         Ok(Some((Some(Err(err)), _))) => {
@@ -1730,6 +2690,9 @@ impl Account {
                                 .into_iter()
                                 .map(|e| (e.hash(), e))
                                 .collect::<HashMap<EnvelopeHash, Envelope>>();
+                            for envelope in envelopes.values() {
+                                self.index_delivery_report(envelope);
+                            }
                             if let Some(updated_mailboxes) =
                                 self.collection
                                     .merge(envelopes, mailbox_hash, self.sent_mailbox)
@@ -1876,6 +2839,82 @@ impl Account {
                     }
                 }
                 JobRequest::SendMessage => {}
+                JobRequest::OutboxSend {
+                    ref id,
+                    store_sent_mail,
+                    ref mut handle,
+                } => {
+                    let result = match handle.chan.try_recv() {
+                        Err(_) => Err(MeliError::new("Job was canceled")),
+                        Ok(None) => return true,
+                        Ok(Some(result)) => result,
+                    };
+                    match result {
+                        Ok(()) => match self.outbox.remove(id) {
+                            Ok(Some(item)) => {
+                                let (recipients, subject) = item.summary();
+                                if store_sent_mail {
+                                    if let Err(err) = self.save_special(
+                                        item.message.as_bytes(),
+                                        SpecialUsageMailbox::Sent,
+                                        Flag::SEEN,
+                                    ) {
+                                        melib::log(
+                                            format!("Could not save sent message: {}", err),
+                                            melib::ERROR,
+                                        );
+                                    }
+                                }
+                                self.sender
+                                    .send(ThreadEvent::UIEvent(UIEvent::Notification(
+                                        Some(format!("{}: queued message sent", &self.name)),
+                                        format!("\"{}\" sent to {}", subject, recipients),
+                                        Some(crate::types::NotificationType::SentMail),
+                                    )))
+                                    .expect("Could not send event on main channel");
+                            }
+                            Ok(None) => {}
+                            Err(err) => {
+                                melib::log(
+                                    format!("Could not update outbox of {}: {}", &self.name, err),
+                                    melib::ERROR,
+                                );
+                            }
+                        },
+                        Err(err) => {
+                            if let Err(err) = self.outbox.failed(id, &err, melib::datetime::now()) {
+                                melib::log(
+                                    format!("Could not update outbox of {}: {}", &self.name, err),
+                                    melib::ERROR,
+                                );
+                            }
+                            let (attempts, failed) = self
+                                .outbox
+                                .get_mut(id)
+                                .map(|item| (item.attempts, item.failed))
+                                .unwrap_or((1, false));
+                            self.sender
+                                .send(ThreadEvent::UIEvent(UIEvent::Notification(
+                                    Some(format!("{}: could not send queued message", &self.name)),
+                                    if failed {
+                                        format!(
+                                            "{} Gave up after {} attempts, the message is kept in \
+                                             the outbox.",
+                                            err, attempts
+                                        )
+                                    } else {
+                                        format!(
+                                            "{} Retrying in {} minutes.",
+                                            err,
+                                            super::outbox::backoff(attempts) / 60
+                                        )
+                                    },
+                                    Some(crate::types::NotificationType::Error(err.kind)),
+                                )))
+                                .expect("Could not send event on main channel");
+                        }
+                    }
+                }
                 JobRequest::SendMessageBackground { ref mut handle, .. } => {
                     if let Ok(Some(Err(err))) = handle.chan.try_recv() {
                         self.sender
@@ -1985,6 +3024,7 @@ impl Account {
                                         status,
                                         conf: new,
                                         ref_mailbox: mailboxes.remove(&mailbox_hash).unwrap(),
+                                        saved_search: None,
                                     },
                                 );
                                 self.collection
@@ -2083,6 +3123,68 @@ impl Account {
                 }
                 //JobRequest::RenameMailbox,
                 JobRequest::Search { .. } | JobRequest::AsBytes { .. } => {}
+                JobRequest::DeliveryReports { ref mut handle } => match handle.chan.try_recv() {
+                    Err(_) => { /* canceled */ }
+                    Ok(None) => {}
+                    Ok(Some(Err(err))) => {
+                        melib::log(
+                            format!("{}: could not fetch delivery reports: {}", &self.name, err),
+                            melib::ERROR,
+                        );
+                    }
+                    Ok(Some(Ok(reports))) => {
+                        for (timestamp, report) in reports {
+                            if let Some(message_id) = report.original_message_id.clone() {
+                                self.delivery_reports
+                                    .entry(message_id)
+                                    .or_default()
+                                    .push((timestamp, report));
+                            }
+                        }
+                    }
+                },
+                JobRequest::SavedSearch {
+                    mailbox_hash,
+                    ref mut handle,
+                } => match handle.chan.try_recv() {
+                    Err(_) => { /* canceled */ }
+                    Ok(None) => {}
+                    Ok(Some(Err(err))) => {
+                        melib::log(
+                            format!(
+                                "{}: saved search `{}` failed: {}",
+                                &self.name, self.mailbox_entries[&mailbox_hash].name, err
+                            ),
+                            melib::ERROR,
+                        );
+                        self.mailbox_entries
+                            .entry(mailbox_hash)
+                            .and_modify(|entry| {
+                                entry.status = MailboxStatus::Failed(err);
+                            });
+                        self.sender
+                            .send(ThreadEvent::UIEvent(UIEvent::MailboxUpdate((
+                                self.hash,
+                                mailbox_hash,
+                            ))))
+                            .unwrap();
+                    }
+                    Ok(Some(Ok(env_hashes))) => {
+                        self.collection
+                            .set_mailbox(mailbox_hash, env_hashes.into_iter().collect());
+                        self.mailbox_entries
+                            .entry(mailbox_hash)
+                            .and_modify(|entry| {
+                                entry.status = MailboxStatus::Available;
+                            });
+                        self.sender
+                            .send(ThreadEvent::UIEvent(UIEvent::MailboxUpdate((
+                                self.hash,
+                                mailbox_hash,
+                            ))))
+                            .unwrap();
+                    }
+                },
                 JobRequest::SetMailboxPermissions { ref mut handle, .. } => {
                     match handle.chan.try_recv() {
                         Err(_) => { /* canceled */ }
@@ -2243,6 +3345,41 @@ impl IndexMut<&MailboxHash> for Account {
     }
 }
 
+fn pipe_to_command(command: &str, bytes: &[u8]) -> Result<()> {
+    use std::io::Write;
+    use std::process::{Command, Stdio};
+    let mut child = Command::new("sh")
+        .args(["-c", command])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .spawn()?;
+    /* Write on another thread so that a command filling its stderr pipe before it has read
+     * all of its input can't block us both. */
+    let mut stdin = child.stdin.take().unwrap();
+    let bytes = bytes.to_vec();
+    let writer = std::thread::spawn(move || stdin.write_all(&bytes));
+    let output = child.wait_with_output()?;
+    let written = match writer.join() {
+        Ok(res) => res.map_err(MeliError::from),
+        Err(_) => Err(MeliError::new(format!(
+            "Could not write message to `{}`.",
+            command
+        ))),
+    };
+    if output.status.success() {
+        written
+    } else {
+        Err(MeliError::new(format!(
+            "`{}` exited with {}: {}",
+            command,
+            output.status,
+            String::from_utf8_lossy(&output.stderr).trim()
+        ))
+        .set_kind(ErrorKind::External))
+    }
+}
+
 fn build_mailboxes_order(
     tree: &mut Vec<MailboxNode>,
     mailbox_entries: &IndexMap<MailboxHash, MailboxEntry>,
@@ -2276,12 +3413,23 @@ fn build_mailboxes_order(
         }
     }
 
+    /* Saved searches are listed after every other mailbox */
     macro_rules! mailbox_eq_key {
         ($mailbox:expr) => {{
             if let Some(sort_order) = $mailbox.conf.mailbox_conf.sort_order {
-                (0, sort_order, $mailbox.ref_mailbox.path())
+                (
+                    $mailbox.is_saved_search(),
+                    0,
+                    sort_order,
+                    $mailbox.ref_mailbox.path(),
+                )
             } else {
-                (1, 0, $mailbox.ref_mailbox.path())
+                (
+                    $mailbox.is_saved_search(),
+                    1,
+                    0,
+                    $mailbox.ref_mailbox.path(),
+                )
             }
         }};
     }
@@ -77,6 +77,16 @@ pub struct ComposingSettings {
     /// Default: ask
     #[serde(default = "ask", alias = "forward-as-attachment")]
     pub forward_as_attachment: ToggleFlag,
+    /// Ask for a read receipt with the `Disposition-Notification-To` header in new drafts.
+    /// Default: false
+    #[serde(default = "false_val", alias = "request-read-receipt")]
+    pub request_read_receipt: bool,
+    /// Whether to send a read receipt when opening a message that asks for one: `ask`, `always`
+    /// or `never`. Receipts that would go to an address other than the return path of the
+    /// message are always confirmed first.
+    /// Default: ask
+    #[serde(default, alias = "read-receipts")]
+    pub read_receipts: ReadReceiptPolicy,
 }
 
 impl Default for ComposingSettings {
@@ -92,10 +102,22 @@ impl Default for ComposingSettings {
             attribution_format_string: None,
             attribution_use_posix_locale: true,
             forward_as_attachment: ToggleFlag::Ask,
+            request_read_receipt: false,
+            read_receipts: ReadReceiptPolicy::Ask,
         }
     }
 }
 
+/// What to do when a message asks for a read receipt.
+#[derive(Debug, Default, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ReadReceiptPolicy {
+    #[default]
+    Ask,
+    Always,
+    Never,
+}
+
 macro_rules! named_unit_variant {
     ($variant:ident) => {
         pub mod $variant {
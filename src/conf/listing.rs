@@ -21,6 +21,7 @@
 
 use super::{default_vals::*, DotAddressable, IndexStyle};
 use melib::search::Query;
+use melib::text_processing::GlobMatch;
 use melib::{MeliError, Result};
 
 /// Settings for mail listings
@@ -123,6 +124,12 @@ pub struct ListingSettings {
     /// Default: "📎"
     #[serde(default)]
     pub attachment_flag: Option<String>,
+
+    /// Mailboxes shown in the unified inbox tab, as `ACCOUNT:PATH` glob patterns. A pattern
+    /// without an account part matches the path in every account.
+    /// Default: [] (the inbox of every account)
+    #[serde(default, alias = "unified-mailboxes")]
+    pub unified_mailboxes: Vec<String>,
 }
 
 const fn default_divider() -> char {
@@ -152,6 +159,7 @@ impl Default for ListingSettings {
             thread_snoozed_flag: None,
             selected_flag: None,
             attachment_flag: None,
+            unified_mailboxes: vec![],
         }
     }
 }
@@ -186,6 +194,7 @@ impl DotAddressable for ListingSettings {
                     "thread_snoozed_flag" => self.thread_snoozed_flag.lookup(field, tail),
                     "selected_flag" => self.selected_flag.lookup(field, tail),
                     "attachment_flag" => self.attachment_flag.lookup(field, tail),
+                    "unified_mailboxes" => self.unified_mailboxes.lookup(field, tail),
                     other => Err(MeliError::new(format!(
                         "{} has no field named {}",
                         parent_field, other
@@ -196,3 +205,31 @@ impl DotAddressable for ListingSettings {
         }
     }
 }
+
+/// Whether mailbox `path` of account `account_name` is matched by the `unified_mailboxes` entry
+/// `pattern`.
+pub fn unified_mailbox_matches(pattern: &str, account_name: &str, path: &str) -> bool {
+    match pattern.split_once(':') {
+        Some((account_pattern, path_pattern)) => {
+            account_name.matches_glob(account_pattern) && path.matches_glob(path_pattern)
+        }
+        None => path.matches_glob(pattern),
+    }
+}
+
+#[test]
+fn test_unified_mailbox_matches() {
+    assert!(unified_mailbox_matches("INBOX", "work", "INBOX"));
+    assert!(unified_mailbox_matches("*:INBOX", "personal", "INBOX"));
+    assert!(unified_mailbox_matches(
+        "work:lists/*",
+        "work",
+        "lists/rust"
+    ));
+    assert!(!unified_mailbox_matches(
+        "work:lists/*",
+        "personal",
+        "lists/rust"
+    ));
+    assert!(!unified_mailbox_matches("work:INBOX", "work", "Sent"));
+}
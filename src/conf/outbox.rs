@@ -0,0 +1,393 @@
+/*
+ * meli - conf/outbox.rs
+ *
+ * Copyright 2020 Manos Pitsidianakis
+ *
+ * This file is part of meli.
+ *
+ * meli is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * meli is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with meli. If not, see <http://www.gnu.org/licenses/>.
+ */
+
+//! Queue of an account's messages that are scheduled to be sent later, or that failed to be sent
+//! and are retried.
+
+use melib::datetime::UnixTimestamp;
+use melib::error::{MeliError, Result};
+use melib::Envelope;
+use std::fs;
+use std::io::Write;
+use std::os::unix::fs::PermissionsExt;
+use std::path::{Path, PathBuf};
+
+/// Longest wait between retries of a message that failed to be sent, in seconds.
+const MAX_BACKOFF: UnixTimestamp = 6 * 60 * 60;
+
+/// Failed attempts after which a message is no longer retried and stays in the outbox until the
+/// user sends, edits or deletes it.
+pub const MAX_ATTEMPTS: u32 = 10;
+
+/// Seconds to wait before retrying a message that failed `attempts` times: one minute, doubled
+/// on every failure up to `MAX_BACKOFF`.
+pub fn backoff(attempts: u32) -> UnixTimestamp {
+    if attempts == 0 {
+        return 0;
+    }
+    std::cmp::min(60 << std::cmp::min(attempts - 1, 16), MAX_BACKOFF)
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct OutboxItem {
+    pub id: String,
+    /// The finalised message.
+    pub message: String,
+    /// Send with the backend's submission instead of `send_mail`, e.g. news articles.
+    pub server_submission: bool,
+    /// The message is sent, or retried, once this time has passed.
+    pub send_at: UnixTimestamp,
+    /// Failed attempts to send the message.
+    pub attempts: u32,
+    pub last_error: Option<String>,
+    /// Sending failed `MAX_ATTEMPTS` times and is not retried anymore.
+    #[serde(default)]
+    pub failed: bool,
+    /// The message was scheduled with `send-at`, so its `Date` header is set to the time it is
+    /// actually sent. Its Message-ID is kept, since replies and delivery reports refer to it.
+    #[serde(default)]
+    pub scheduled: bool,
+    /// A send job for this message is running.
+    #[serde(skip)]
+    pub is_sending: bool,
+}
+
+impl OutboxItem {
+    pub fn new(message: String, server_submission: bool, send_at: UnixTimestamp) -> Self {
+        OutboxItem {
+            id: melib::uuid::Uuid::new_v4().to_string(),
+            message,
+            server_submission,
+            send_at,
+            attempts: 0,
+            last_error: None,
+            failed: false,
+            scheduled: false,
+            is_sending: false,
+        }
+    }
+
+    /// The message to send at `now`.
+    pub fn message_to_send(&mut self, now: UnixTimestamp) -> String {
+        if self.scheduled {
+            self.message = replace_date(
+                &self.message,
+                &melib::datetime::timestamp_to_string(
+                    now,
+                    Some(melib::datetime::RFC822_DATE),
+                    true,
+                ),
+            );
+        }
+        self.message.clone()
+    }
+
+    /// Recipients and subject of the message, for display.
+    pub fn summary(&self) -> (String, String) {
+        match Envelope::from_bytes(self.message.as_bytes(), None) {
+            Ok(envelope) => {
+                let recipients =
+                    if let Some(newsgroups) = envelope.other_headers().get("Newsgroups") {
+                        newsgroups.to_string()
+                    } else {
+                        envelope.field_to_to_string()
+                    };
+                (recipients, envelope.subject().into_owned())
+            }
+            Err(_) => (String::new(), String::new()),
+        }
+    }
+}
+
+/// The outbox of an account, kept in a JSON file in the account's data directory so that queued
+/// messages survive restarts.
+#[derive(Debug, Default)]
+pub struct Outbox {
+    path: Option<PathBuf>,
+    items: Vec<OutboxItem>,
+}
+
+impl Outbox {
+    pub fn new(account_name: &str) -> Self {
+        let path = xdg::BaseDirectories::with_profile("meli", account_name)
+            .ok()
+            .and_then(|data_dir| data_dir.place_data_file("outbox.json").ok());
+        match path {
+            Some(path) => Outbox::open(&path).unwrap_or_else(|err| {
+                melib::log(
+                    format!(
+                        "Could not read outbox of account {} from {}: {}",
+                        account_name,
+                        path.display(),
+                        err
+                    ),
+                    melib::ERROR,
+                );
+                Outbox {
+                    path: Some(path),
+                    items: vec![],
+                }
+            }),
+            None => Outbox::default(),
+        }
+    }
+
+    /// Reads the outbox saved in `path`. A missing file is an empty outbox.
+    pub fn open(path: &Path) -> Result<Self> {
+        let items = if path.exists() {
+            serde_json::from_slice(&fs::read(path)?)
+                .map_err(|err| MeliError::new(format!("Invalid outbox file: {}", err)))?
+        } else {
+            vec![]
+        };
+        Ok(Outbox {
+            path: Some(path.to_path_buf()),
+            items,
+        })
+    }
+
+    pub fn save(&self) -> Result<()> {
+        let path = match self.path {
+            Some(ref path) => path,
+            None => return Ok(()),
+        };
+        let tmp_path = path.with_extension("json.tmp");
+        {
+            let mut file = fs::File::create(&tmp_path)?;
+            /* Queued messages are private, like drafts */
+            file.set_permissions(fs::Permissions::from_mode(0o600))?;
+            file.write_all(
+                serde_json::to_string(&self.items)
+                    .map_err(|err| MeliError::new(err.to_string()))?
+                    .as_bytes(),
+            )?;
+        }
+        fs::rename(&tmp_path, path)?;
+        Ok(())
+    }
+
+    pub fn items(&self) -> &[OutboxItem] {
+        &self.items
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+
+    pub fn len(&self) -> usize {
+        self.items.len()
+    }
+
+    pub fn get(&self, id: &str) -> Option<&OutboxItem> {
+        self.items.iter().find(|item| item.id == id)
+    }
+
+    pub fn get_mut(&mut self, id: &str) -> Option<&mut OutboxItem> {
+        self.items.iter_mut().find(|item| item.id == id)
+    }
+
+    pub fn push(&mut self, item: OutboxItem) -> Result<()> {
+        self.items.push(item);
+        self.items.sort_by_key(|item| item.send_at);
+        self.save()
+    }
+
+    pub fn remove(&mut self, id: &str) -> Result<Option<OutboxItem>> {
+        let ret = self
+            .items
+            .iter()
+            .position(|item| item.id == id)
+            .map(|pos| self.items.remove(pos));
+        if ret.is_some() {
+            self.save()?;
+        }
+        Ok(ret)
+    }
+
+    /// Ids of the messages that should be sent at `now`.
+    pub fn due(&self, now: UnixTimestamp) -> Vec<String> {
+        self.items
+            .iter()
+            .filter(|item| !item.is_sending && !item.failed && item.send_at <= now)
+            .map(|item| item.id.clone())
+            .collect()
+    }
+
+    /// Records a failed attempt to send message `id` and schedules the next one, unless it
+    /// failed `MAX_ATTEMPTS` times.
+    pub fn failed(&mut self, id: &str, err: &MeliError, now: UnixTimestamp) -> Result<()> {
+        if let Some(item) = self.get_mut(id) {
+            item.is_sending = false;
+            item.attempts += 1;
+            item.failed = item.attempts >= MAX_ATTEMPTS;
+            if !item.failed {
+                item.send_at = now + backoff(item.attempts);
+            }
+            item.last_error = Some(err.to_string());
+        }
+        self.items.sort_by_key(|item| item.send_at);
+        self.save()
+    }
+}
+
+/// Replaces the value of the `Date` header of `message`, or adds the header if it is missing.
+fn replace_date(message: &str, date: &str) -> String {
+    let header_end = message
+        .find("\r\n\r\n")
+        .or_else(|| message.find("\n\n"))
+        .unwrap_or(message.len());
+    let newline = if message[..header_end].contains("\r\n") {
+        "\r\n"
+    } else {
+        "\n"
+    };
+    let mut ret = String::with_capacity(message.len());
+    let mut replaced = false;
+    let mut in_date = false;
+    let mut rest = &message[..header_end];
+    while !rest.is_empty() {
+        let line_end = rest
+            .find('\n')
+            .map(|pos| pos + 1)
+            .unwrap_or_else(|| rest.len());
+        let (line, next) = rest.split_at(line_end);
+        rest = next;
+        if in_date && line.starts_with([' ', '\t']) {
+            /* folded continuation of the old value */
+            continue;
+        }
+        in_date = false;
+        if !replaced
+            && line.len() >= "Date:".len()
+            && line.as_bytes()[.."Date:".len()].eq_ignore_ascii_case(b"Date:")
+        {
+            ret.push_str("Date: ");
+            ret.push_str(date);
+            if line.ends_with('\n') {
+                ret.push_str(newline);
+            }
+            replaced = true;
+            in_date = true;
+            continue;
+        }
+        ret.push_str(line);
+    }
+    if !replaced {
+        ret.insert_str(0, &format!("Date: {}{}", date, newline));
+    }
+    ret.push_str(&message[header_end..]);
+    ret
+}
+
+#[test]
+fn test_outbox() {
+    assert_eq!(backoff(0), 0);
+    assert_eq!(backoff(1), 60);
+    assert_eq!(backoff(3), 240);
+    assert_eq!(backoff(10), MAX_BACKOFF);
+    assert_eq!(backoff(100), MAX_BACKOFF);
+
+    let path = std::env::temp_dir().join(format!(
+        "meli-test-outbox-{}.json",
+        melib::uuid::Uuid::new_v4()
+    ));
+    let mut outbox = Outbox::open(&path).unwrap();
+    assert!(outbox.is_empty());
+    let later = OutboxItem::new(
+        "From: a@example.com\nTo: b@example.com\nSubject: later\n\nbody\n".to_string(),
+        false,
+        2000,
+    );
+    let now = OutboxItem::new(
+        "From: a@example.com\nTo: c@example.com\nSubject: now\n\nbody\n".to_string(),
+        false,
+        1000,
+    );
+    let (later_id, now_id) = (later.id.clone(), now.id.clone());
+    outbox.push(later).unwrap();
+    outbox.push(now).unwrap();
+    assert_eq!(outbox.due(1500), vec![now_id.clone()]);
+    assert_eq!(
+        outbox.items()[0].summary(),
+        ("c@example.com".to_string(), "now".to_string())
+    );
+
+    outbox.get_mut(&now_id).unwrap().is_sending = true;
+    assert!(outbox.due(1500).is_empty());
+    outbox
+        .failed(&now_id, &MeliError::new("connection refused"), 1500)
+        .unwrap();
+    assert_eq!(outbox.due(1559), Vec::<String>::new());
+    assert_eq!(outbox.due(1560), vec![now_id.clone()]);
+    assert_eq!(outbox.due(2000).len(), 2);
+
+    /* The queue and the failed attempts survive a restart */
+    let mut reopened = Outbox::open(&path).unwrap();
+    assert_eq!(reopened.len(), 2);
+    let item = reopened.get_mut(&now_id).unwrap();
+    assert_eq!(item.attempts, 1);
+    assert_eq!(item.send_at, 1560);
+    assert_eq!(item.last_error.as_deref(), Some("connection refused"));
+    assert!(reopened.remove(&later_id).unwrap().is_some());
+    assert_eq!(Outbox::open(&path).unwrap().len(), 1);
+
+    /* Retries stop after MAX_ATTEMPTS failures */
+    for attempt in 2..=MAX_ATTEMPTS {
+        assert_eq!(reopened.due(1_000_000 * attempt as UnixTimestamp).len(), 1);
+        reopened
+            .failed(
+                &now_id,
+                &MeliError::new("connection refused"),
+                1_000_000 * attempt as UnixTimestamp,
+            )
+            .unwrap();
+    }
+    assert!(reopened.get_mut(&now_id).unwrap().failed);
+    assert!(reopened.due(UnixTimestamp::MAX).is_empty());
+    fs::remove_file(&path).unwrap();
+
+    /* Scheduled messages are dated when they are sent */
+    let mut scheduled = OutboxItem::new(
+        "Date: Thu, 01 Oct 2020 12:00:00 +0000\r\n\tfolded\r\nFrom: a@example.com\r\nSubject: Date: kept\r\n\r\nDate: body\r\n"
+            .to_string(),
+        false,
+        2000,
+    );
+    assert_eq!(scheduled.message_to_send(2000), scheduled.message.clone());
+    scheduled.scheduled = true;
+    let message = scheduled.message_to_send(0);
+    assert_eq!(
+        message,
+        format!(
+            "Date: {}\r\nFrom: a@example.com\r\nSubject: Date: kept\r\n\r\nDate: body\r\n",
+            melib::datetime::timestamp_to_string(0, Some(melib::datetime::RFC822_DATE), true)
+        )
+    );
+    assert_eq!(scheduled.message, message);
+    assert_eq!(
+        replace_date("From: a@example.com\n\nbody\n", "now"),
+        "Date: now\nFrom: a@example.com\n\nbody\n"
+    );
+    assert_eq!(
+        replace_date("From: a@example.com\nDATE: then\n\nbody\n", "now"),
+        "From: a@example.com\nDate: now\n\nbody\n"
+    );
+}
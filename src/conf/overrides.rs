@@ -169,6 +169,12 @@ pub struct ListingSettingsOverride {
     #[doc = " Default: \"📎\""]
     #[serde(default)]
     pub attachment_flag: Option<Option<String>>,
+    #[doc = " Mailboxes shown in the unified inbox tab, as `ACCOUNT:PATH` glob patterns. A pattern"]
+    #[doc = " without an account part matches the path in every account."]
+    #[doc = " Default: [] (the inbox of every account)"]
+    #[serde(alias = "unified-mailboxes")]
+    #[serde(default)]
+    pub unified_mailboxes: Option<Vec<String>>,
 }
 impl Default for ListingSettingsOverride {
     fn default() -> Self {
@@ -189,6 +195,7 @@ impl Default for ListingSettingsOverride {
             thread_snoozed_flag: None,
             selected_flag: None,
             attachment_flag: None,
+            unified_mailboxes: None,
         }
     }
 }
@@ -252,6 +259,13 @@ pub struct ShortcutsOverride {
     pub thread_view: Option<ThreadViewShortcuts>,
     #[serde(default)]
     pub pager: Option<PagerShortcuts>,
+    #[serde(default)]
+    pub filters: Option<FiltersShortcuts>,
+    #[serde(default)]
+    pub outbox: Option<OutboxShortcuts>,
+    #[serde(alias = "attachment-tree")]
+    #[serde(default)]
+    pub attachment_tree: Option<AttachmentTreeShortcuts>,
 }
 impl Default for ShortcutsOverride {
     fn default() -> Self {
@@ -264,6 +278,9 @@ impl Default for ShortcutsOverride {
             envelope_view: None,
             thread_view: None,
             pager: None,
+            filters: None,
+            outbox: None,
+            attachment_tree: None,
         }
     }
 }
@@ -321,6 +338,18 @@ pub struct ComposingSettingsOverride {
     #[serde(alias = "forward-as-attachment")]
     #[serde(default)]
     pub forward_as_attachment: Option<ToggleFlag>,
+    #[doc = " Ask for a read receipt with the `Disposition-Notification-To` header in new drafts."]
+    #[doc = " Default: false"]
+    #[serde(alias = "request-read-receipt")]
+    #[serde(default)]
+    pub request_read_receipt: Option<bool>,
+    #[doc = " Whether to send a read receipt when opening a message that asks for one: `ask`, `always`"]
+    #[doc = " or `never`. Receipts that would go to an address other than the return path of the"]
+    #[doc = " message are always confirmed first."]
+    #[doc = " Default: ask"]
+    #[serde(alias = "read-receipts")]
+    #[serde(default)]
+    pub read_receipts: Option<ReadReceiptPolicy>,
 }
 impl Default for ComposingSettingsOverride {
     fn default() -> Self {
@@ -335,6 +364,8 @@ impl Default for ComposingSettingsOverride {
             attribution_format_string: None,
             attribution_use_posix_locale: None,
             forward_as_attachment: None,
+            request_read_receipt: None,
+            read_receipts: None,
         }
     }
 }
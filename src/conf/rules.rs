@@ -0,0 +1,231 @@
+/*
+ * meli - conf/rules.rs
+ *
+ * Copyright 2020 Manos Pitsidianakis
+ *
+ * This file is part of meli.
+ *
+ * meli is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * meli is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with meli. If not, see <http://www.gnu.org/licenses/>.
+ */
+
+//! Client-side filtering rules, applied to new mail of an account.
+
+use super::default_vals::*;
+use super::DotAddressable;
+use melib::search::{MatchContext, Query, QueryTrait};
+use melib::text_processing::GlobMatch;
+use melib::{Envelope, Flag};
+use serde::{de, Deserialize, Deserializer};
+
+/// A filtering rule of an account, configured with `[[accounts.NAME.rules]]` tables.
+///
+/// Every envelope that matches `query` has the rule's actions applied to it, in the following
+/// order: flags and tags are changed, the message is piped to `pipe`, copied to `copy_to` and
+/// finally moved to `move_to`.
+#[derive(Debug, Deserialize, Clone, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct FilterRule {
+    /// Name shown in job status and error notifications.
+    /// Default: None
+    #[serde(default = "none")]
+    pub name: Option<String>,
+    /// Envelopes matching this query are acted upon.
+    pub query: Query,
+    /// Mailbox paths (glob patterns are accepted) whose new mail this rule applies to.
+    /// Default: the mailbox with the `Inbox` special usage.
+    #[serde(default)]
+    pub mailboxes: Vec<String>,
+    /// Move matching messages to this mailbox path.
+    /// Default: None
+    #[serde(default = "none", alias = "move-to")]
+    pub move_to: Option<String>,
+    /// Copy matching messages to this mailbox path.
+    /// Default: None
+    #[serde(default = "none", alias = "copy-to")]
+    pub copy_to: Option<String>,
+    /// Flags to set, one of `seen`, `replied`, `passed`, `flagged`, `draft` or `trashed`.
+    /// Default: []
+    #[serde(default, deserialize_with = "flag_names_de", alias = "set-flags")]
+    pub set_flags: Vec<String>,
+    /// Flags to unset.
+    /// Default: []
+    #[serde(default, deserialize_with = "flag_names_de", alias = "unset-flags")]
+    pub unset_flags: Vec<String>,
+    /// Tags to add, for backends that support tags.
+    /// Default: []
+    #[serde(default, alias = "add-tags")]
+    pub add_tags: Vec<String>,
+    /// Tags to remove, for backends that support tags.
+    /// Default: []
+    #[serde(default, alias = "remove-tags")]
+    pub remove_tags: Vec<String>,
+    /// Mark matching messages as seen.
+    /// Default: false
+    #[serde(default = "false_val")]
+    pub seen: bool,
+    /// Pipe every matching message's raw bytes to the standard input of this shell command.
+    /// Default: None
+    #[serde(default = "none")]
+    pub pipe: Option<String>,
+    /// Do not apply any following rules to matching messages.
+    /// Default: false
+    #[serde(default = "false_val")]
+    pub stop: bool,
+}
+
+impl FilterRule {
+    /// Whether this rule applies to new mail of mailbox `path`. Rules without `mailboxes`
+    /// apply only when `default` is set, i.e. to the inbox.
+    pub fn applies_to(&self, path: &str, default: bool) -> bool {
+        if self.mailboxes.is_empty() {
+            default
+        } else {
+            self.mailboxes.iter().any(|m| path.matches_glob(m))
+        }
+    }
+
+    /// Whether the message of `envelope` in mailbox `mailbox_path` matches `query`. `bytes` are
+    /// the raw bytes of the message, without which body and size terms don't match.
+    pub fn is_match(&self, envelope: &Envelope, bytes: Option<&[u8]>, mailbox_path: &str) -> bool {
+        (
+            envelope,
+            MatchContext {
+                bytes,
+                mailbox_path: Some(mailbox_path),
+            },
+        )
+            .is_match(&self.query)
+    }
+
+    /// Whether applying the rule needs the raw bytes of messages, to match body or size terms or
+    /// to pipe them to `pipe`.
+    pub fn needs_bytes(&self) -> bool {
+        self.query.needs_body() || self.pipe.is_some()
+    }
+
+    /// Flag and tag changes, in the form expected by `MailBackend::set_flags`.
+    pub fn flag_changes(&self) -> Vec<(std::result::Result<Flag, String>, bool)> {
+        let mut ret = vec![];
+        for (names, value) in [(&self.set_flags, true), (&self.unset_flags, false)] {
+            ret.extend(
+                names
+                    .iter()
+                    .filter_map(|n| flag_from_name(n))
+                    .map(|f| (Ok(f), value)),
+            );
+        }
+        if self.seen && !ret.contains(&(Ok(Flag::SEEN), true)) {
+            ret.push((Ok(Flag::SEEN), true));
+        }
+        for (tags, value) in [(&self.add_tags, true), (&self.remove_tags, false)] {
+            ret.extend(tags.iter().map(|t| (Err(t.to_string()), value)));
+        }
+        ret
+    }
+
+    /// Whether matching messages end up seen or outside of their mailbox, in which case no new
+    /// mail notification should be shown for them.
+    pub fn consumes(&self) -> bool {
+        self.move_to.is_some()
+            || self.seen
+            || self
+                .set_flags
+                .iter()
+                .any(|n| flag_from_name(n) == Some(Flag::SEEN))
+    }
+
+    /// `name`, or the rule's position among the account's rules if it has none.
+    pub fn display_name(&self, idx: usize) -> String {
+        self.name
+            .clone()
+            .unwrap_or_else(|| format!("filter rule #{}", idx + 1))
+    }
+}
+
+fn flag_from_name(name: &str) -> Option<Flag> {
+    match name.to_ascii_lowercase().as_str() {
+        "passed" => Some(Flag::PASSED),
+        "replied" => Some(Flag::REPLIED),
+        "seen" | "read" => Some(Flag::SEEN),
+        "trashed" | "trash" => Some(Flag::TRASHED),
+        "draft" => Some(Flag::DRAFT),
+        "flagged" => Some(Flag::FLAGGED),
+        _ => None,
+    }
+}
+
+fn flag_names_de<'de, D>(deserializer: D) -> std::result::Result<Vec<String>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let names = <Vec<String>>::deserialize(deserializer)?;
+    if let Some(unknown) = names.iter().find(|n| flag_from_name(n).is_none()) {
+        return Err(de::Error::custom(format!(
+            "unknown flag `{}`, expected one of seen, replied, passed, flagged, draft, trashed",
+            unknown
+        )));
+    }
+    Ok(names)
+}
+
+impl DotAddressable for FilterRule {}
+
+#[test]
+fn test_filter_rule_de() {
+    let rule: FilterRule = toml::from_str(
+        r#"
+query = "from:announce@example.com"
+mailboxes = ["INBOX", "Lists/*"]
+move_to = "Archive"
+set_flags = ["flagged"]
+add_tags = ["announcements"]
+seen = true
+"#,
+    )
+    .unwrap();
+    assert!(rule.applies_to("Lists/rust", false));
+    assert!(!rule.applies_to("Sent", true));
+    assert!(rule.consumes());
+    assert_eq!(
+        rule.flag_changes(),
+        vec![
+            (Ok(Flag::FLAGGED), true),
+            (Ok(Flag::SEEN), true),
+            (Err("announcements".to_string()), true)
+        ]
+    );
+
+    let rule: FilterRule =
+        toml::from_str(r#"query = "mailbox:INBOX and invoice and larger:10""#).unwrap();
+    assert!(rule.needs_bytes());
+    let bytes = b"From: a@example.com\r\nSubject: hi\r\n\r\nYour invoice is attached.\r\n";
+    let envelope = Envelope::from_bytes(bytes, None).unwrap();
+    assert!(!rule.is_match(&envelope, None, "INBOX"));
+    assert!(rule.is_match(&envelope, Some(bytes), "INBOX"));
+    assert!(!rule.is_match(&envelope, Some(bytes), "Archive"));
+
+    let rule: FilterRule = toml::from_str(r#"query = "subject:report""#).unwrap();
+    assert!(rule.applies_to("INBOX", true));
+    assert!(!rule.applies_to("Archive", false));
+    assert!(!rule.consumes());
+    assert!(rule.flag_changes().is_empty());
+
+    assert!(toml::from_str::<FilterRule>(
+        r#"
+query = "subject:report"
+set_flags = ["important"]
+"#
+    )
+    .is_err());
+}
@@ -53,6 +53,12 @@ pub struct Shortcuts {
     pub thread_view: ThreadViewShortcuts,
     #[serde(default)]
     pub pager: PagerShortcuts,
+    #[serde(default)]
+    pub filters: FiltersShortcuts,
+    #[serde(default)]
+    pub outbox: OutboxShortcuts,
+    #[serde(default, alias = "attachment-tree")]
+    pub attachment_tree: AttachmentTreeShortcuts,
 }
 
 impl Default for Shortcuts {
@@ -66,6 +72,9 @@ impl Default for Shortcuts {
             envelope_view: EnvelopeViewShortcuts::default(),
             thread_view: ThreadViewShortcuts::default(),
             pager: PagerShortcuts::default(),
+            filters: FiltersShortcuts::default(),
+            outbox: OutboxShortcuts::default(),
+            attachment_tree: AttachmentTreeShortcuts::default(),
         }
     }
 }
@@ -86,6 +95,11 @@ impl DotAddressable for Shortcuts {
                     "envelope_view" | "envelope-view" => self.envelope_view.lookup(field, tail),
                     "thread_view" | "thread-view" => self.thread_view.lookup(field, tail),
                     "pager" => self.pager.lookup(field, tail),
+                    "filters" => self.filters.lookup(field, tail),
+                    "outbox" => self.outbox.lookup(field, tail),
+                    "attachment_tree" | "attachment-tree" => {
+                        self.attachment_tree.lookup(field, tail)
+                    }
                     other => Err(MeliError::new(format!(
                         "{} has no field named {}",
                         parent_field, other
@@ -210,7 +224,10 @@ shortcut_key_values! { "pager",
         page_down |> "Go to next pager page" |>  Key::PageDown,
         page_up |> "Go to previous pager page" |>  Key::PageUp,
         scroll_down |> "Scroll down pager." |> Key::Char('j'),
-        scroll_up |> "Scroll up pager." |> Key::Char('k')
+        scroll_up |> "Scroll up pager." |> Key::Char('k'),
+        open_link |> "Show link hints and open the chosen link." |> Key::Char('f'),
+        yank_link |> "Show link hints and copy the chosen link to the clipboard." |> Key::Char('y'),
+        compose_to_link |> "Show link hints and compose a message to the chosen address." |> Key::Char('M')
     }
 }
 
@@ -253,6 +270,7 @@ shortcut_key_values! { "envelope-view",
         return_to_normal_view |> "Return to envelope if viewing raw source or attachment." |> Key::Char('r'),
         toggle_expand_headers |> "Expand extra headers (References and others)." |> Key::Char('h'),
         toggle_url_mode |> "Toggles url open mode." |> Key::Char('u'),
+        view_attachment_tree |> "Browse the MIME structure of the envelope in the attachment tree." |> Key::Char('A'),
         view_raw_source |> "View envelope source in a pager. (toggles between raw and decoded source)" |> Key::Alt('r')
     }
 }
@@ -269,3 +287,40 @@ shortcut_key_values! { "thread-view",
         toggle_threadview |> "toggle thread view visibility" |> Key::Char('t')
     }
 }
+
+shortcut_key_values! { "filters",
+    /// Shortcut listing for the Sieve filters tab
+    pub struct FiltersShortcuts {
+        scroll_up |> "Scroll up list." |> Key::Char('k'),
+        scroll_down |> "Scroll down list." |> Key::Char('j'),
+        edit_script |> "Edit script in $EDITOR." |> Key::Char('e'),
+        toggle_active |> "Activate or deactivate script." |> Key::Char('a'),
+        delete_script |> "Delete script." |> Key::Char('D'),
+        refresh |> "Reload script list from server." |> Key::F(5)
+    }
+}
+
+shortcut_key_values! { "outbox",
+    /// Shortcut listing for the outbox tab
+    pub struct OutboxShortcuts {
+        scroll_up |> "Scroll up list." |> Key::Char('k'),
+        scroll_down |> "Scroll down list." |> Key::Char('j'),
+        send_now |> "Send message now." |> Key::Char('s'),
+        edit_message |> "Remove message from the outbox and edit it in a new composing tab." |> Key::Char('e'),
+        delete_message |> "Delete message from the outbox." |> Key::Char('D')
+    }
+}
+
+shortcut_key_values! { "attachment-tree",
+    /// Shortcut listing for the attachment tree of the envelope view
+    pub struct AttachmentTreeShortcuts {
+        scroll_up |> "Scroll up list." |> Key::Char('k'),
+        scroll_down |> "Scroll down list." |> Key::Char('j'),
+        collapse_subtree |> "Collapse or expand the selected part's subparts." |> Key::Char('h'),
+        view_part |> "View part; embedded messages are opened in a new tab." |> Key::Char('\n'),
+        save_part |> "Save part to a file." |> Key::Char('s'),
+        pipe_part |> "Pipe decoded part to a command." |> Key::Char('|'),
+        open_mailcap |> "Open part according to its mailcap entry." |> Key::Char('m'),
+        return_to_tree |> "Return to the tree if viewing a part." |> Key::Char('r')
+    }
+}
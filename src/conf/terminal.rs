@@ -24,6 +24,7 @@
 use super::deserializers::non_empty_string;
 use super::DotAddressable;
 use super::Themes;
+use crate::terminal::GraphicsProtocol;
 use melib::{MeliError, Result, ToggleFlag};
 
 /// Settings for terminal display
@@ -52,6 +53,11 @@ pub struct TerminalSettings {
     /// Default: 0
     #[serde(default)]
     pub progress_spinner_sequence: Option<ProgressSpinnerSequence>,
+    /// How to show images: "kitty", "sixel", "half-blocks" or "none". If unset, it is detected
+    /// from the environment.
+    /// Default: None
+    #[serde(default)]
+    pub graphics_protocol: Option<GraphicsProtocol>,
 }
 
 impl Default for TerminalSettings {
@@ -66,6 +72,7 @@ impl Default for TerminalSettings {
             window_title: Some("meli".to_string()),
             file_picker_command: None,
             progress_spinner_sequence: None,
+            graphics_protocol: None,
         }
     }
 }
@@ -80,6 +87,12 @@ impl TerminalSettings {
             && (self.use_color.is_false() || self.use_color.is_internal()))
             || (self.use_color.is_false() && !self.use_color.is_internal()))
     }
+
+    /// The configured graphics protocol, or the one detected from the environment.
+    pub fn graphics_protocol(&self) -> GraphicsProtocol {
+        self.graphics_protocol
+            .unwrap_or_else(GraphicsProtocol::detect)
+    }
 }
 
 impl DotAddressable for TerminalSettings {
@@ -99,6 +112,7 @@ impl DotAddressable for TerminalSettings {
                     "progress_spinner_sequence" => {
                         self.progress_spinner_sequence.lookup(field, tail)
                     }
+                    "graphics_protocol" => self.graphics_protocol.lookup(field, tail),
                     other => Err(MeliError::new(format!(
                         "{} has no field named {}",
                         parent_field, other
@@ -126,3 +140,5 @@ const fn interval_ms_val() -> u64 {
 }
 
 impl DotAddressable for ProgressSpinnerSequence {}
+
+impl DotAddressable for GraphicsProtocol {}
@@ -19,23 +19,22 @@
  * along with meli. If not, see <http://www.gnu.org/licenses/>.
  */
 
-#[macro_use]
-extern crate melib;
-use melib::*;
-
 use std::collections::VecDeque;
-extern crate xdg_utils;
+
 #[macro_use]
 extern crate serde_derive;
 extern crate linkify;
-extern crate uuid;
+pub(crate) use melib::uuid;
 
+extern crate bitflags;
 extern crate serde_json;
 extern crate smallvec;
 extern crate termion;
 
-use melib::backends::imap::managesieve::new_managesieve_connection;
-use melib::Result;
+#[macro_use]
+extern crate melib;
+use melib::backends::imap::managesieve::{new_managesieve_connection, ManageSieve};
+use melib::*;
 
 #[macro_use]
 pub mod types;
@@ -64,7 +63,6 @@ pub mod sqlite3;
 
 pub mod jobs;
 pub mod mailcap;
-pub mod plugins;
 
 use futures::executor::block_on;
 
@@ -72,11 +70,11 @@ use futures::executor::block_on;
 ///
 /// # Example invocation:
 /// ```sh
-/// ./manage_sieve server_hostname server_username server_password server_port");
+/// ./manage_sieve CONFIG_PATH ACCOUNT_NAME
 /// ```
 ///
-/// `danger_accept_invalid_certs` is turned on by default, so no certificate validation is performed.
-
+/// The connection settings are taken from the IMAP account `ACCOUNT_NAME`; the ManageSieve port
+/// can be set with the `managesieve_port` account setting.
 fn main() -> Result<()> {
     let mut args = std::env::args().skip(1).collect::<Vec<String>>();
     if args.len() != 2 {
@@ -84,10 +82,7 @@ fn main() -> Result<()> {
         std::process::exit(1);
     }
 
-    let (config_path, account_name) = (
-        std::mem::replace(&mut args[0], String::new()),
-        std::mem::replace(&mut args[1], String::new()),
-    );
+    let (config_path, account_name) = (std::mem::take(&mut args[0]), std::mem::take(&mut args[1]));
     std::env::set_var("MELI_CONFIG", config_path);
     let settings = conf::Settings::new()?;
     if !settings.accounts.contains_key(&account_name) {
@@ -102,12 +97,47 @@ fn main() -> Result<()> {
         );
         std::process::exit(1);
     }
-    let mut conn = new_managesieve_connection(&settings.accounts[&account_name].account)?;
+    let account = &settings.accounts[&account_name].account;
+    let mut conn = new_managesieve_connection(
+        0,
+        account_name.clone(),
+        account,
+        melib::backends::BackendEventConsumer::new(std::sync::Arc::new(|_, _| {})),
+    )?;
     block_on(conn.connect())?;
-    let mut res = String::with_capacity(8 * 1024);
 
     let mut input = String::new();
-    println!("managesieve shell: use 'logout'");
+    const AVAILABLE_COMMANDS: &[&str] = &[
+        "help",
+        "logout",
+        "listscripts",
+        "checkscript",
+        "putscript",
+        "setactive",
+        "getscript",
+        "deletescript",
+        "renamescript",
+        "havespace",
+        "capabilities",
+    ];
+    const COMMANDS_HELP: &[&str] = &[
+        "help",
+        "logout",
+        "list scripts and whether each one is active",
+        "check script FILE for errors",
+        "upload FILE as script NAME: putscript NAME FILE",
+        "set script NAME as the active one, or deactivate all scripts if NAME is omitted",
+        "print contents of script NAME",
+        "delete script NAME",
+        "rename script: renamescript OLD NEW",
+        "check if a script of SIZE octets fits: havespace NAME SIZE",
+        "print server capabilities",
+    ];
+    println!("managesieve shell: use 'help' for available commands");
+    let print_script_result = |res: Result<()>| match res {
+        Ok(()) => println!("OK"),
+        Err(err) => println!("error: {}", err),
+    };
     loop {
         use std::io;
         use std::io::Write;
@@ -115,13 +145,86 @@ fn main() -> Result<()> {
         print!("> ");
         io::stdout().flush().unwrap();
         match io::stdin().read_line(&mut input) {
+            Ok(0) => break,
             Ok(_) => {
-                if input.trim().eq_ignore_ascii_case("logout") {
-                    break;
+                let args = input.split_whitespace().collect::<Vec<&str>>();
+                match args.as_slice() {
+                    [] => {}
+                    [cmd] if cmd.eq_ignore_ascii_case("logout") => break,
+                    [cmd] if cmd.eq_ignore_ascii_case("help") => {
+                        println!("available commands: [{}]", AVAILABLE_COMMANDS.join(", "));
+                        for (cmd, help) in AVAILABLE_COMMANDS.iter().zip(COMMANDS_HELP.iter()) {
+                            println!("  {}: {}", cmd, help);
+                        }
+                    }
+                    [cmd] if cmd.eq_ignore_ascii_case("capabilities") => {
+                        let mut capabilities = conn.managesieve_capabilities();
+                        capabilities.sort();
+                        println!("{}", capabilities.join("\n"));
+                    }
+                    [cmd] if cmd.eq_ignore_ascii_case("listscripts") => {
+                        match block_on(conn.listscripts()) {
+                            Ok(scripts) => {
+                                for (name, active) in scripts {
+                                    println!(
+                                        "{}{}",
+                                        String::from_utf8_lossy(&name),
+                                        if active { " (active)" } else { "" }
+                                    );
+                                }
+                            }
+                            Err(err) => println!("error: {}", err),
+                        }
+                    }
+                    [cmd, path] if cmd.eq_ignore_ascii_case("checkscript") => {
+                        match std::fs::read(path) {
+                            Ok(script) => print_script_result(block_on(conn.checkscript(&script))),
+                            Err(err) => println!("error: could not read {}: {}", path, err),
+                        }
+                    }
+                    [cmd, name, path] if cmd.eq_ignore_ascii_case("putscript") => {
+                        match std::fs::read(path) {
+                            Ok(script) => print_script_result(block_on(
+                                conn.putscript(name.as_bytes(), &script),
+                            )),
+                            Err(err) => println!("error: could not read {}: {}", path, err),
+                        }
+                    }
+                    [cmd] if cmd.eq_ignore_ascii_case("setactive") => {
+                        print_script_result(block_on(conn.setactive(b"")));
+                    }
+                    [cmd, name] if cmd.eq_ignore_ascii_case("setactive") => {
+                        print_script_result(block_on(conn.setactive(name.as_bytes())));
+                    }
+                    [cmd, name] if cmd.eq_ignore_ascii_case("getscript") => {
+                        match block_on(conn.getscript(name.as_bytes())) {
+                            Ok(script) => println!("{}", String::from_utf8_lossy(&script)),
+                            Err(err) => println!("error: {}", err),
+                        }
+                    }
+                    [cmd, name] if cmd.eq_ignore_ascii_case("deletescript") => {
+                        print_script_result(block_on(conn.deletescript(name.as_bytes())));
+                    }
+                    [cmd, old, new] if cmd.eq_ignore_ascii_case("renamescript") => {
+                        print_script_result(block_on(
+                            conn.renamescript(old.as_bytes(), new.as_bytes()),
+                        ));
+                    }
+                    [cmd, name, size] if cmd.eq_ignore_ascii_case("havespace") => {
+                        match size.parse::<usize>() {
+                            Ok(size) => {
+                                print_script_result(block_on(conn.havespace(name.as_bytes(), size)))
+                            }
+                            Err(err) => println!("error: invalid size {}: {}", size, err),
+                        }
+                    }
+                    _ => {
+                        println!(
+                            "invalid command, available commands: [{}]",
+                            AVAILABLE_COMMANDS.join(", ")
+                        );
+                    }
                 }
-                block_on(conn.send_command(input.as_bytes()))?;
-                block_on(conn.read_lines(&mut res, String::new()))?;
-                println!("out: {}", res.trim());
             }
             Err(error) => println!("error: {}", error),
         }
@@ -40,6 +40,7 @@ use melib::{
 };
 
 use smallvec::SmallVec;
+use std::collections::{BTreeMap, HashMap};
 use std::convert::TryInto;
 use std::path::PathBuf;
 use std::sync::{Arc, RwLock};
@@ -62,8 +63,17 @@ init_script:Some( "CREATE TABLE IF NOT EXISTS envelopes (
                     flags            INTEGER NOT NULL,
                     has_attachments  BOOLEAN NOT NULL,
                     body_text        TEXT NOT NULL,
-                    timestamp        BLOB NOT NULL
+                    timestamp        BLOB NOT NULL,
+                    size             INTEGER NOT NULL,
+                    list_id          TEXT NOT NULL,
+                    mailbox          TEXT NOT NULL,
+                    tags             TEXT NOT NULL
                    );
+        CREATE TABLE IF NOT EXISTS headers (
+                    hash             BLOB NOT NULL,
+                    name             TEXT NOT NULL,
+                    value            TEXT NOT NULL
+                  );
         CREATE TABLE IF NOT EXISTS folders (
                     id               INTEGER PRIMARY KEY,
                     account_id       INTEGER NOT NULL REFERENCES accounts ON UPDATE CASCADE,
@@ -93,6 +103,8 @@ CREATE INDEX IF NOT EXISTS envelope__to_index ON envelopes (_to);
 CREATE INDEX IF NOT EXISTS envelope_cc_index ON envelopes (cc);
 CREATE INDEX IF NOT EXISTS envelope_bcc_index ON envelopes (bcc);
 CREATE INDEX IF NOT EXISTS envelope_message_id_index ON envelopes (message_id);
+CREATE INDEX IF NOT EXISTS headers_hash_index ON headers (hash);
+CREATE INDEX IF NOT EXISTS headers_name_index ON headers (name);
 
         CREATE VIRTUAL TABLE IF NOT EXISTS fts USING fts5(subject, body_text, content=envelopes, content_rowid=id);
 
@@ -109,7 +121,7 @@ CREATE TRIGGER IF NOT EXISTS envelopes_au AFTER UPDATE ON envelopes BEGIN
   INSERT INTO fts(fts, rowid, subject, body_text) VALUES('delete', old.id, old.subject, old.body_text);
   INSERT INTO fts(rowid, subject, body_text) VALUES (new.id, new.subject, new.body_text);
 END; "),
-version: 1,
+version: 2,
 };
 
 pub fn db_path() -> Result<PathBuf> {
@@ -140,10 +152,56 @@ pub fn db_path() -> Result<PathBuf> {
 //}
 //
 //
+/// Inserts or replaces an envelope's row along with the rows of its headers.
+fn insert_envelope(
+    conn: &rusqlite::Connection,
+    account_id: i32,
+    e: &Envelope,
+    bytes: &[u8],
+    mailbox_path: &str,
+    tag_index: &BTreeMap<u64, String>,
+) -> rusqlite::Result<()> {
+    let body = e.body_bytes(bytes).text().replace('\0', "");
+    let tags = e.labels().iter().filter_map(|h| tag_index.get(h)).fold(
+        String::from(" "),
+        |mut acc, tag| {
+            acc.push_str(tag);
+            acc.push(' ');
+            acc
+        },
+    );
+    let list_id = e
+        .other_headers()
+        .get("List-Id")
+        .map(|v| decode_header_value(v))
+        .unwrap_or_default();
+    let hash = e.hash().to_be_bytes().to_vec();
+    conn.execute("INSERT OR REPLACE INTO envelopes (account_id, hash, date, _from, _to, cc, bcc, subject, message_id, in_reply_to, _references, flags, has_attachments, body_text, timestamp, size, list_id, mailbox, tags)
+              VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16, ?17, ?18, ?19)",
+              params![account_id, hash, e.date_as_str(), e.field_from_to_string(), e.field_to_to_string(), e.field_cc_to_string(), e.field_bcc_to_string(), e.subject().into_owned().trim_end_matches('\u{0}'), e.message_id_display().to_string(), e.in_reply_to_display().map(|f| f.to_string()).unwrap_or_default(), e.field_references_to_string(), i64::from(e.flags().bits()), if e.has_attachments() { 1 } else { 0 }, body, e.date().to_be_bytes().to_vec(), bytes.len() as i64, list_id, mailbox_path, tags],
+    )?;
+    conn.execute("DELETE FROM headers WHERE hash = ?1", params![hash])?;
+    for (name, value) in e.other_headers().iter() {
+        conn.execute(
+            "INSERT INTO headers (hash, name, value) VALUES (?1, ?2, ?3)",
+            params![hash, name.to_string(), decode_header_value(value)],
+        )?;
+    }
+    Ok(())
+}
+
+fn decode_header_value(value: &str) -> String {
+    melib::email::parser::encodings::phrase(value.as_bytes(), false)
+        .map(|(_, v)| String::from_utf8_lossy(&v).into_owned())
+        .unwrap_or_else(|_| value.to_string())
+}
+
 pub async fn insert(
     envelope: Envelope,
     backend: Arc<RwLock<Box<dyn MailBackend>>>,
     acc_name: String,
+    mailbox_path: String,
+    tag_index: Arc<RwLock<BTreeMap<u64, String>>>,
 ) -> Result<()> {
     let db_path = db_path()?;
     if !db_path.exists() {
@@ -160,8 +218,8 @@ pub async fn insert(
         .operation(envelope.hash())?
         .as_bytes()?;
 
-    let body = match op.await.map(|bytes| envelope.body_bytes(&bytes)) {
-        Ok(body) => body.text(),
+    let bytes = match op.await {
+        Ok(bytes) => bytes,
         Err(err) => {
             debug!(
                 "{}",
@@ -214,26 +272,30 @@ pub async fn insert(
             .unwrap();
         x
     };
-    if let Err(err) = conn.execute(
-            "INSERT OR REPLACE INTO envelopes (account_id, hash, date, _from, _to, cc, bcc, subject, message_id, in_reply_to, _references, flags, has_attachments, body_text, timestamp)
-              VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15)",
-              params![account_id, envelope.hash().to_be_bytes().to_vec(), envelope.date_as_str(), envelope.field_from_to_string(), envelope.field_to_to_string(), envelope.field_cc_to_string(), envelope.field_bcc_to_string(), envelope.subject().into_owned().trim_end_matches('\u{0}'), envelope.message_id_display().to_string(), envelope.in_reply_to_display().map(|f| f.to_string()).unwrap_or(String::new()), envelope.field_references_to_string(), i64::from(envelope.flags().bits()), if envelope.has_attachments() { 1 } else { 0 }, body, envelope.date().to_be_bytes().to_vec()],
-        )
-            .map_err(|e| MeliError::new(e.to_string())) {
-                debug!(
-                        "Failed to insert envelope {}: {}",
-                        envelope.message_id_display(),
-                        err.to_string()
-                    );
-                log(
-                    format!(
-                        "Failed to insert envelope {}: {}",
-                        envelope.message_id_display(),
-                        err.to_string()
-                    ),
-                    ERROR,
-                );
-              }
+    if let Err(err) = insert_envelope(
+        &conn,
+        account_id,
+        &envelope,
+        &bytes,
+        &mailbox_path,
+        &tag_index.read().unwrap(),
+    )
+    .map_err(|e| MeliError::new(e.to_string()))
+    {
+        debug!(
+            "Failed to insert envelope {}: {}",
+            envelope.message_id_display(),
+            err.to_string()
+        );
+        log(
+            format!(
+                "Failed to insert envelope {}: {}",
+                envelope.message_id_display(),
+                err.to_string()
+            ),
+            ERROR,
+        );
+    }
     Ok(())
 }
 
@@ -251,6 +313,12 @@ pub fn remove(env_hash: EnvelopeHash) -> Result<()> {
             "DELETE FROM envelopes WHERE hash = ?",
             params![env_hash.to_be_bytes().to_vec(),],
         )
+        .and_then(|_| {
+            conn.execute(
+                "DELETE FROM headers WHERE hash = ?",
+                params![env_hash.to_be_bytes().to_vec(),],
+            )
+        })
         .map_err(|e| MeliError::new(e.to_string()))
     {
         debug!(
@@ -278,6 +346,15 @@ pub fn index(context: &mut crate::state::Context, account_index: usize) -> Resul
         account.collection.envelopes.clone(),
         account.backend.clone(),
     );
+    let tag_index = account.collection.tag_index.clone();
+    let mut mailbox_paths: HashMap<EnvelopeHash, String> = HashMap::default();
+    for (mailbox_hash, env_hashes) in account.collection.mailboxes.read().unwrap().iter() {
+        if let Some(entry) = account.mailbox_entries.get(mailbox_hash) {
+            for env_hash in env_hashes {
+                mailbox_paths.insert(*env_hash, entry.ref_mailbox.path().to_string());
+            }
+        }
+    }
     let conn = melib_sqlite3::open_or_create_db(&DB, None)?;
     let env_hashes = acc_mutex
         .read()
@@ -325,11 +402,20 @@ pub fn index(context: &mut crate::state::Context, account_index: usize) -> Resul
                     .chain_err_summary(|| format!("Failed to open envelope {}", env_hash))?;
                 let envelopes_lck = acc_mutex.read().unwrap();
                 if let Some(e) = envelopes_lck.get(&env_hash) {
-                    let body = e.body_bytes(&bytes).text().replace('\0', "");
-                    conn.execute("INSERT OR REPLACE INTO envelopes (account_id, hash, date, _from, _to, cc, bcc, subject, message_id, in_reply_to, _references, flags, has_attachments, body_text, timestamp)
-              VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15)",
-              params![account_id, e.hash().to_be_bytes().to_vec(), e.date_as_str(), e.field_from_to_string(), e.field_to_to_string(), e.field_cc_to_string(), e.field_bcc_to_string(), e.subject().into_owned().trim_end_matches('\u{0}'), e.message_id_display().to_string(), e.in_reply_to_display().map(|f| f.to_string()).unwrap_or(String::new()), e.field_references_to_string(), i64::from(e.flags().bits()), if e.has_attachments() { 1 } else { 0 }, body, e.date().to_be_bytes().to_vec()],
-                        ).chain_err_summary(|| format!( "Failed to insert envelope {}", e.message_id_display()))?;
+                    insert_envelope(
+                        &conn,
+                        account_id,
+                        e,
+                        &bytes,
+                        mailbox_paths
+                            .get(env_hash)
+                            .map(String::as_str)
+                            .unwrap_or_default(),
+                        &tag_index.read().unwrap(),
+                    )
+                    .chain_err_summary(|| {
+                        format!("Failed to insert envelope {}", e.message_id_display())
+                    })?;
                 }
             }
             let sleep_dur = std::time::Duration::from_millis(20);
@@ -418,11 +504,53 @@ pub fn query_to_sql(q: &Query) -> String {
                 s.extend(escape_double_quote(t).chars());
                 s.push_str("%\" ");
             }
-            AllText(t) => {
+            AllAddresses(t) => {
+                let t = escape_double_quote(t);
+                s.push_str("(_from LIKE \"%");
+                s.extend(t.chars());
+                s.push_str("%\" OR _to LIKE \"%");
+                s.extend(t.chars());
+                s.push_str("%\" OR cc LIKE \"%");
+                s.extend(t.chars());
+                s.push_str("%\" OR bcc LIKE \"%");
+                s.extend(t.chars());
+                s.push_str("%\") ");
+            }
+            InReplyTo(t) => {
+                s.push_str("in_reply_to LIKE \"%");
+                s.extend(escape_double_quote(t).chars());
+                s.push_str("%\" ");
+            }
+            References(t) => {
+                s.push_str("_references LIKE \"%");
+                s.extend(escape_double_quote(t).chars());
+                s.push_str("%\" ");
+            }
+            AllText(t) | Body(t) => {
                 s.push_str("body_text LIKE \"%");
                 s.extend(escape_double_quote(t).chars());
                 s.push_str("%\" ");
             }
+            /* timestamp is stored as a big endian blob, so blob comparison orders it correctly */
+            Before(t) => {
+                s.push_str(&format!("timestamp < X'{:016X}' ", t));
+            }
+            After(t) => {
+                s.push_str(&format!("timestamp > X'{:016X}' ", t));
+            }
+            Between(a, b) => {
+                s.push_str(&format!(
+                    "timestamp > X'{:016X}' AND timestamp < X'{:016X}' ",
+                    a, b
+                ));
+            }
+            On(t) => {
+                s.push_str(&format!(
+                    "timestamp > X'{:016X}' AND timestamp < X'{:016X}' ",
+                    t.saturating_sub(60 * 60 * 24),
+                    t + 60 * 60 * 24
+                ));
+            }
             And(q1, q2) => {
                 s.push_str("(");
                 rec(q1, s);
@@ -450,17 +578,20 @@ pub fn query_to_sql(q: &Query) -> String {
                 for (i, f) in v.iter().enumerate() {
                     match f.as_str() {
                         "draft" => {
-                            s.push_str(" (flags & 8 > 0) ");
+                            s.push_str(" (flags & 16 > 0) ");
                         }
                         "deleted" | "trashed" => {
-                            s.push_str(" (flags & 6 > 0) ");
+                            s.push_str(" (flags & 8 > 0) ");
                         }
                         "flagged" => {
-                            s.push_str(" (flags & 16 > 0) ");
+                            s.push_str(" (flags & 32 > 0) ");
                         }
                         "recent" => {
                             s.push_str(" (flags & 4 == 0) ");
                         }
+                        "passed" | "forwarded" => {
+                            s.push_str(" (flags & 1 > 0) ");
+                        }
                         "seen" | "read" => {
                             s.push_str(" (flags & 4 > 0) ");
                         }
@@ -488,7 +619,50 @@ pub fn query_to_sql(q: &Query) -> String {
             HasAttachment => {
                 s.push_str("has_attachments == 1 ");
             }
-            _ => {}
+            Tag(t) => {
+                s.push_str("tags LIKE \"% ");
+                s.extend(escape_double_quote(t).chars());
+                s.push_str(" %\" ");
+            }
+            Larger(size) => {
+                s.push_str(&format!("size > {} ", size));
+            }
+            Smaller(size) => {
+                s.push_str(&format!("size < {} ", size));
+            }
+            Mailbox(t) => {
+                let t = escape_double_quote(t);
+                s.push_str("(mailbox LIKE \"");
+                s.extend(t.chars());
+                s.push_str("\" OR mailbox GLOB \"");
+                s.extend(t.chars());
+                s.push_str("\") ");
+            }
+            ListId(t) => {
+                s.push_str("list_id LIKE \"%");
+                s.extend(escape_double_quote(t).chars());
+                s.push_str("%\" ");
+            }
+            MessageId(t) => {
+                s.push_str("message_id LIKE \"%");
+                s.extend(escape_double_quote(t).chars());
+                s.push_str("%\" ");
+            }
+            Header(name, t) => {
+                s.push_str("hash IN (SELECT hash FROM headers WHERE name LIKE \"");
+                s.extend(escape_double_quote(name).chars());
+                s.push_str("\" AND value LIKE \"%");
+                s.extend(escape_double_quote(t).chars());
+                s.push_str("%\") ");
+            }
+            Regex(t) => {
+                let t = escape_double_quote(t.as_str());
+                s.push_str("(subject REGEXP \"");
+                s.extend(t.chars());
+                s.push_str("\" OR body_text REGEXP \"");
+                s.extend(t.chars());
+                s.push_str("\") ");
+            }
         }
     }
     let mut ret = String::new();
@@ -515,4 +689,34 @@ fn test_query_to_sql() {
                 .1
         )
     );
+    assert_eq!(
+        "( (flags & 32 > 0)  AND  (flags & 4 == 0) ) ",
+        &query_to_sql(&query().parse_complete("flags:flagged,unseen").unwrap().1)
+    );
+    assert_eq!(
+        "timestamp < X'000000005E0C89C0' ",
+        &query_to_sql(&melib::search::Query::Before(1577880000))
+    );
+    assert_eq!(
+        "(tags LIKE \"% work %\" ) AND ((size > 10240 ) AND ((mailbox LIKE \"Lists/*\" OR mailbox GLOB \"Lists/*\") ) ) ",
+        &query_to_sql(
+            &query()
+                .parse_complete("tag:work and larger:10k and in:Lists/*")
+                .unwrap()
+                .1
+        )
+    );
+    assert_eq!(
+        "hash IN (SELECT hash FROM headers WHERE name LIKE \"X-Mailer\" AND value LIKE \"%meli%\") ",
+        &query_to_sql(&query().parse_complete("header:X-Mailer:meli").unwrap().1)
+    );
+    assert_eq!(
+        "(list_id LIKE \"%devel%\" ) OR (message_id LIKE \"%a@b%\" ) ",
+        &query_to_sql(
+            &query()
+                .parse_complete("list:devel or message-id:a@b")
+                .unwrap()
+                .1
+        )
+    );
 }
@@ -32,7 +32,7 @@ use super::*;
 //use crate::plugins::PluginManager;
 use melib::backends::{AccountHash, BackendEventConsumer};
 
-use crate::jobs::JobExecutor;
+use crate::jobs::{JobExecutor, Timer};
 use crate::terminal::screen::Screen;
 use crossbeam::channel::{unbounded, Receiver, Sender};
 use indexmap::IndexMap;
@@ -104,6 +104,8 @@ pub struct Context {
 
     /// Events queue that components send back to the state
     pub replies: VecDeque<UIEvent>,
+    /// Images to draw over the cells in the next render
+    pub images: Vec<ImagePlacement>,
     pub sender: Sender<ThreadEvent>,
     receiver: Receiver<ThreadEvent>,
     input_thread: InputHandler,
@@ -166,6 +168,8 @@ impl Context {
 pub struct State {
     screen: Screen,
     draw_rate_limit: RateLimit,
+    /// Periodically sends the accounts' outbox messages that are due.
+    outbox_timer: Timer,
     child: Option<ForkType>,
     pub mode: UIMode,
     overlay: Vec<Box<dyn Component>>,
@@ -315,6 +319,7 @@ impl State {
                 } else {
                     Screen::draw_horizontal_segment_no_color
                 },
+                images: Vec::new(),
             },
             child: None,
             mode: UIMode::Normal,
@@ -322,6 +327,10 @@ impl State {
             overlay: Vec::new(),
             timer,
             draw_rate_limit: RateLimit::new(1, 3, job_executor.clone()),
+            outbox_timer: job_executor.clone().create_timer(
+                std::time::Duration::from_secs(30),
+                std::time::Duration::from_secs(5),
+            ),
             display_messages: SmallVec::new(),
             display_messages_expiration_start: None,
             display_messages_pos: 0,
@@ -334,6 +343,7 @@ impl State {
                 settings: settings,
                 dirty_areas: VecDeque::with_capacity(5),
                 replies: VecDeque::with_capacity(5),
+                images: Vec::new(),
                 temp_files: Vec::new(),
                 job_executor,
                 children: vec![],
@@ -532,6 +542,8 @@ impl State {
                 );
             }
         }
+        let images = std::mem::take(&mut self.context.images);
+        self.screen.draw_images(images, &areas);
 
         if self.display_messages_dirty && self.display_messages_active {
             if let Some(DisplayMessage {
@@ -849,6 +861,80 @@ impl State {
                     Some(NotificationType::Error(ErrorKind::None)),
                 ));
             }
+            AccountAction(ref account_name, ApplyRules(ref path)) => {
+                if let Some(account) = self
+                    .context
+                    .accounts
+                    .values_mut()
+                    .find(|a| a.name() == account_name)
+                {
+                    match account.mailbox_by_path(path) {
+                        Ok(mailbox_hash) => {
+                            let env_hashes = account
+                                .collection
+                                .get_mailbox(mailbox_hash)
+                                .iter()
+                                .cloned()
+                                .collect::<Vec<EnvelopeHash>>();
+                            account.apply_rules(mailbox_hash, &env_hashes, true);
+                            self.context.replies.push_back(UIEvent::StatusEvent(
+                                StatusEvent::DisplayMessage(format!(
+                                    "Applying filtering rules to {} messages of {}.",
+                                    env_hashes.len(),
+                                    path
+                                )),
+                            ));
+                        }
+                        Err(err) => {
+                            self.context.replies.push_back(UIEvent::StatusEvent(
+                                StatusEvent::DisplayMessage(err.to_string()),
+                            ));
+                        }
+                    }
+                } else {
+                    self.context.replies.push_back(UIEvent::Notification(
+                        None,
+                        format!("Account {} was not found.", account_name),
+                        Some(NotificationType::Error(ErrorKind::None)),
+                    ));
+                }
+            }
+            AccountAction(ref account_name, ManageFilters) => {
+                if let Some(account_hash) = self
+                    .context
+                    .accounts
+                    .iter()
+                    .find(|(_, acc)| acc.name() == account_name)
+                    .map(|(h, _)| *h)
+                {
+                    let filters = SieveFilters::new(account_hash, &self.context);
+                    self.rcv_event(UIEvent::Action(Tab(New(Some(Box::new(filters))))));
+                } else {
+                    self.context.replies.push_back(UIEvent::Notification(
+                        None,
+                        format!("Account {} was not found.", account_name),
+                        Some(NotificationType::Error(ErrorKind::None)),
+                    ));
+                }
+            }
+            AccountAction(ref account_name, ManageOutbox) => {
+                if let Some(account_hash) = self
+                    .context
+                    .accounts
+                    .iter()
+                    .find(|(_, acc)| acc.name() == account_name)
+                    .map(|(h, _)| *h)
+                {
+                    let outbox = OutboxView::new(account_hash, &self.context);
+                    self.rcv_event(UIEvent::Action(Tab(New(Some(Box::new(outbox))))));
+                } else {
+                    self.context.replies.push_back(UIEvent::Notification(
+                        None,
+                        format!("Account {} was not found.", account_name),
+                        Some(NotificationType::Error(ErrorKind::None)),
+                    ));
+                }
+            }
             AccountAction(ref account_name, PrintAccountSetting(ref setting)) => {
                 let path = setting.split(".").collect::<SmallVec<[&str; 16]>>();
                 if let Some(pos) = self
@@ -887,6 +973,10 @@ impl State {
                             .unwrap_or_else(|err| err.to_string())
                     ))));
             }
+            UnifiedInbox => {
+                let listing = UnifiedListing::new(&self.context);
+                self.rcv_event(UIEvent::Action(Tab(New(Some(Box::new(listing))))));
+            }
             ToggleMouse => {
                 self.screen.mouse = !self.screen.mouse;
                 self.screen.set_mouse(self.screen.mouse);
@@ -1037,6 +1127,31 @@ impl State {
                 self.redraw();
                 return;
             }
+            UIEvent::Timer(id)
+                if self
+                    .context
+                    .accounts
+                    .values()
+                    .any(|account| account.is_saved_searches_timer(id)) =>
+            {
+                for account in self.context.accounts.values_mut() {
+                    if account.is_saved_searches_timer(id) {
+                        account.run_pending_saved_searches();
+                    }
+                }
+                return;
+            }
+            UIEvent::Timer(id) if id == self.outbox_timer.id() => {
+                let Context {
+                    ref mut accounts,
+                    ref settings,
+                    ..
+                } = self.context;
+                for account in accounts.values_mut() {
+                    account.process_outbox(settings);
+                }
+                return;
+            }
             UIEvent::Input(ref key)
                 if *key
                     == self
@@ -1099,6 +1214,10 @@ impl State {
                 self.overlay.push(dialog);
                 return;
             }
+            UIEvent::Action(CopyToClipboard(ref text)) => {
+                self.screen.set_clipboard(text);
+                return;
+            }
             _ => {}
         }
         let Self {
@@ -35,8 +35,10 @@ mod cells;
 #[macro_use]
 mod keys;
 pub mod embed;
+mod image;
 mod text_editing;
 pub use self::cells::*;
+pub use self::image::*;
 pub use self::keys::*;
 pub use self::position::*;
 pub use self::text_editing::*;
@@ -471,6 +473,8 @@ pub mod screen {
         pub mouse: bool,
         pub draw_horizontal_segment_fn:
             fn(&mut CellBuffer, &mut StateStdout, usize, usize, usize) -> (),
+        /// Images currently on the screen.
+        pub images: Vec<ImagePlacement>,
     }
 
     impl Screen {
@@ -551,6 +555,55 @@ pub mod screen {
             }
             self.flush();
         }
+        /// Copy `text` to the clipboard of the terminal (or of the system, if the terminal
+        /// supports it) with an OSC 52 escape sequence.
+        pub fn set_clipboard(&mut self, text: &str) {
+            if let Some(stdout) = self.stdout.as_mut() {
+                write!(
+                    stdout,
+                    "\x1b]52;c;{}\x07",
+                    melib::data_encoding::BASE64.encode(text.as_bytes())
+                )
+                .unwrap();
+            }
+            self.flush();
+        }
+        /// Draw `new` images over the cells that were drawn in `dirty_areas`. Images that were
+        /// on the screen in a dirty area and weren't placed again are removed.
+        pub fn draw_images(&mut self, new: Vec<ImagePlacement>, dirty_areas: &[Area]) {
+            let stdout = match self.stdout.as_mut() {
+                Some(stdout) => stdout,
+                None => return,
+            };
+            let old = std::mem::take(&mut self.images);
+            for image in old {
+                if new.iter().any(|i| i.id == image.id)
+                    || !dirty_areas.iter().any(|a| areas_intersect(*a, image.area))
+                {
+                    if !new.iter().any(|i| i.id == image.id) {
+                        self.images.push(image);
+                    }
+                    continue;
+                }
+                /* Sixels are overwritten by the cells drawn over them */
+                if image.protocol == GraphicsProtocol::Kitty {
+                    write!(stdout, "{}", kitty_delete(image.id)).unwrap();
+                }
+            }
+            for image in new {
+                let (x, y) = upper_left!(image.area);
+                write!(
+                    stdout,
+                    "{}{}",
+                    cursor::Goto(x as u16 + 1, y as u16 + 1),
+                    image.sequence
+                )
+                .unwrap();
+                self.images.push(image);
+            }
+            self.flush();
+        }
+
         /// On `SIGWNICH` the `State` redraws itself according to the new terminal size.
         pub fn update_size(&mut self) {
             let termsize = termion::terminal_size().ok();
@@ -0,0 +1,581 @@
+/*
+ * meli
+ *
+ * Copyright 2017-2020 Manos Pitsidianakis
+ *
+ * This file is part of meli.
+ *
+ * meli is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * meli is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with meli. If not, see <http://www.gnu.org/licenses/>.
+ */
+
+/*!
+ Draw images in the terminal, with the sixel or the kitty graphics protocol if the terminal
+ supports one of them, or else with half block characters in cells.
+*/
+
+use super::{position::*, CellBuffer, Color};
+use melib::{MeliError, Result};
+use std::collections::BTreeSet;
+use std::sync::atomic::{AtomicU32, Ordering};
+
+/// How images are drawn.
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum GraphicsProtocol {
+    /// The kitty terminal graphics protocol.
+    Kitty,
+    /// DEC sixel graphics.
+    Sixel,
+    /// Half block characters coloured with the image's pixels, two pixels per cell.
+    HalfBlocks,
+    /// Don't show images.
+    None,
+}
+
+impl GraphicsProtocol {
+    /// Guess the protocol the terminal supports from the environment.
+    pub fn detect() -> Self {
+        Self::detect_from(|name| std::env::var(name).ok())
+    }
+
+    fn detect_from(var: impl Fn(&str) -> Option<String>) -> Self {
+        let term = var("TERM").unwrap_or_default();
+        let term_program = var("TERM_PROGRAM").unwrap_or_default();
+        /* Terminal multiplexers don't pass graphics through */
+        if var("TMUX").is_some() || term.starts_with("screen") || term.starts_with("tmux") {
+            return GraphicsProtocol::HalfBlocks;
+        }
+        if var("KITTY_WINDOW_ID").is_some()
+            || term.contains("kitty")
+            || term.contains("ghostty")
+            || term_program == "ghostty"
+        {
+            return GraphicsProtocol::Kitty;
+        }
+        if ["mlterm", "foot", "yaft", "contour", "sixel"]
+            .iter()
+            .any(|t| term.contains(t))
+            || ["WezTerm", "mintty", "iTerm.app"].contains(&term_program.as_str())
+        {
+            return GraphicsProtocol::Sixel;
+        }
+        GraphicsProtocol::HalfBlocks
+    }
+}
+
+/// An image decoded to RGBA pixels, row by row.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Image {
+    width: usize,
+    height: usize,
+    pixels: Vec<[u8; 4]>,
+}
+
+impl Image {
+    pub fn new(width: usize, height: usize, pixels: Vec<[u8; 4]>) -> Self {
+        assert_eq!(pixels.len(), width * height);
+        Image {
+            width,
+            height,
+            pixels,
+        }
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    pub fn pixel(&self, x: usize, y: usize) -> [u8; 4] {
+        self.pixels[y * self.width + x]
+    }
+
+    /// Most bytes a decoded image may take, the same as the default limit of the `png` crate.
+    const MAX_DECODED_SIZE: usize = 64 * 1024 * 1024;
+
+    /// Whether `bytes` are in an image format that can be decoded.
+    pub fn is_supported(bytes: &[u8]) -> bool {
+        cfg!(feature = "image-preview")
+            && (bytes.starts_with(PNG_MAGIC) || bytes.starts_with(JPEG_MAGIC))
+    }
+
+    /// Decode a PNG or JPEG image.
+    #[cfg(feature = "image-preview")]
+    pub fn decode(bytes: &[u8]) -> Result<Self> {
+        if bytes.starts_with(PNG_MAGIC) {
+            let mut decoder = png::Decoder::new(bytes);
+            decoder.set_transformations(png::Transformations::normalize_to_color8());
+            let mut reader = decoder
+                .read_info()
+                .map_err(|err| MeliError::new(format!("Could not decode PNG image: {}", err)))?;
+            let mut buf = vec![0; reader.output_buffer_size()];
+            let info = reader
+                .next_frame(&mut buf)
+                .map_err(|err| MeliError::new(format!("Could not decode PNG image: {}", err)))?;
+            let buf = &buf[..info.buffer_size()];
+            let pixels = match info.color_type {
+                png::ColorType::Rgba => buf
+                    .chunks_exact(4)
+                    .map(|p| [p[0], p[1], p[2], p[3]])
+                    .collect(),
+                png::ColorType::Rgb => buf
+                    .chunks_exact(3)
+                    .map(|p| [p[0], p[1], p[2], 255])
+                    .collect(),
+                png::ColorType::GrayscaleAlpha => buf
+                    .chunks_exact(2)
+                    .map(|p| [p[0], p[0], p[0], p[1]])
+                    .collect(),
+                png::ColorType::Grayscale => buf.iter().map(|&l| [l, l, l, 255]).collect(),
+                png::ColorType::Indexed => {
+                    return Err(MeliError::new(
+                        "Could not decode PNG image: unexpanded palette",
+                    ));
+                }
+            };
+            Ok(Image::new(
+                info.width as usize,
+                info.height as usize,
+                pixels,
+            ))
+        } else if bytes.starts_with(JPEG_MAGIC) {
+            let mut decoder = jpeg_decoder::Decoder::new(bytes);
+            decoder.set_max_decoding_buffer_size(Self::MAX_DECODED_SIZE);
+            let buf = decoder
+                .decode()
+                .map_err(|err| MeliError::new(format!("Could not decode JPEG image: {}", err)))?;
+            let info = decoder
+                .info()
+                .ok_or_else(|| MeliError::new("Could not decode JPEG image"))?;
+            let pixels = match info.pixel_format {
+                jpeg_decoder::PixelFormat::RGB24 => buf
+                    .chunks_exact(3)
+                    .map(|p| [p[0], p[1], p[2], 255])
+                    .collect(),
+                jpeg_decoder::PixelFormat::L8 => buf.iter().map(|&l| [l, l, l, 255]).collect(),
+                jpeg_decoder::PixelFormat::L16 => buf
+                    .chunks_exact(2)
+                    .map(|p| [p[0], p[0], p[0], 255])
+                    .collect(),
+                jpeg_decoder::PixelFormat::CMYK32 => buf
+                    .chunks_exact(4)
+                    .map(|p| {
+                        let k = 255 - p[3] as u32;
+                        let c = |v: u8| ((255 - v as u32) * k / 255) as u8;
+                        [c(p[0]), c(p[1]), c(p[2]), 255]
+                    })
+                    .collect(),
+            };
+            Ok(Image::new(
+                info.width as usize,
+                info.height as usize,
+                pixels,
+            ))
+        } else {
+            Err(MeliError::new(
+                "Unsupported image format: only PNG and JPEG images can be shown.",
+            ))
+        }
+    }
+
+    #[cfg(not(feature = "image-preview"))]
+    pub fn decode(_bytes: &[u8]) -> Result<Self> {
+        Err(MeliError::new(
+            "meli was built without the `image-preview` feature and can't decode images.",
+        ))
+    }
+
+    /// The image scaled down to fit in `max_width`×`max_height` pixels, keeping its aspect
+    /// ratio. Each pixel is the average of the pixels it covers. Images are not enlarged.
+    pub fn scale_to_fit(&self, max_width: usize, max_height: usize) -> Self {
+        if self.width == 0 || self.height == 0 {
+            return self.clone();
+        }
+        let (max_width, max_height) = (max_width.max(1), max_height.max(1));
+        if self.width <= max_width && self.height <= max_height {
+            return self.clone();
+        }
+        /* width / self.width == height / self.height */
+        let (width, height) = if max_width * self.height <= max_height * self.width {
+            (max_width, (self.height * max_width / self.width).max(1))
+        } else {
+            ((self.width * max_height / self.height).max(1), max_height)
+        };
+        let mut pixels = Vec::with_capacity(width * height);
+        for y in 0..height {
+            let (y0, y1) = (y * self.height / height, (y + 1) * self.height / height);
+            for x in 0..width {
+                let (x0, x1) = (x * self.width / width, (x + 1) * self.width / width);
+                let mut sum = [0u32; 4];
+                for sy in y0..y1.max(y0 + 1) {
+                    for sx in x0..x1.max(x0 + 1) {
+                        for (s, v) in sum.iter_mut().zip(self.pixel(sx, sy).iter()) {
+                            *s += *v as u32;
+                        }
+                    }
+                }
+                let n = ((y1.max(y0 + 1) - y0) * (x1.max(x0 + 1) - x0)) as u32;
+                pixels.push([
+                    (sum[0] / n) as u8,
+                    (sum[1] / n) as u8,
+                    (sum[2] / n) as u8,
+                    (sum[3] / n) as u8,
+                ]);
+            }
+        }
+        Image::new(width, height, pixels)
+    }
+}
+
+const PNG_MAGIC: &[u8] = b"\x89PNG\r\n\x1a\n";
+const JPEG_MAGIC: &[u8] = b"\xff\xd8\xff";
+
+/// Pixels with less opacity are not drawn.
+const ALPHA_THRESHOLD: u8 = 128;
+
+/// Size of a cell in pixels, or a common size if the terminal doesn't report it.
+pub fn cell_size_pixels() -> (usize, usize) {
+    match (termion::terminal_size(), termion::terminal_size_pixels()) {
+        (Ok((cols, rows)), Ok((width, height)))
+            if cols > 0 && rows > 0 && width >= cols && height >= rows =>
+        {
+            ((width / cols) as usize, (height / rows) as usize)
+        }
+        _ => (10, 20),
+    }
+}
+
+/// Get a new id for a kitty graphics protocol image.
+pub fn new_image_id() -> u32 {
+    static ID: AtomicU32 = AtomicU32::new(1);
+    ID.fetch_add(1, Ordering::Relaxed)
+}
+
+/// Encode `image` as sixels, with its colours reduced to a 6×6×6 colour cube. Transparent
+/// pixels are left as they are on the screen.
+pub fn encode_sixel(image: &Image) -> String {
+    fn colour_index(p: [u8; 4]) -> Option<usize> {
+        if p[3] < ALPHA_THRESHOLD {
+            return None;
+        }
+        let level = |v: u8| (v as usize * 5 + 127) / 255;
+        Some(level(p[0]) * 36 + level(p[1]) * 6 + level(p[2]))
+    }
+    fn push_run(ret: &mut String, ch: char, count: usize) {
+        if count > 3 {
+            ret.push_str(&format!("!{}{}", count, ch));
+        } else {
+            ret.push_str(&ch.to_string().repeat(count));
+        }
+    }
+    let indices = image
+        .pixels
+        .iter()
+        .map(|&p| colour_index(p))
+        .collect::<Vec<Option<usize>>>();
+    let mut ret = format!("\x1bP0;1;0q\"1;1;{};{}", image.width, image.height);
+    let used = indices.iter().flatten().collect::<BTreeSet<&usize>>();
+    for &&idx in &used {
+        let percent = |level: usize| level * 100 / 5;
+        ret.push_str(&format!(
+            "#{};2;{};{};{}",
+            idx,
+            percent(idx / 36),
+            percent(idx / 6 % 6),
+            percent(idx % 6)
+        ));
+    }
+    for band in (0..image.height).step_by(6) {
+        let rows = band..std::cmp::min(band + 6, image.height);
+        let colours = rows
+            .clone()
+            .flat_map(|y| indices[y * image.width..(y + 1) * image.width].iter())
+            .flatten()
+            .collect::<BTreeSet<&usize>>();
+        for &&colour in &colours {
+            ret.push_str(&format!("#{}", colour));
+            let mut run = ('?', 0);
+            for x in 0..image.width {
+                let bits = rows
+                    .clone()
+                    .filter(|y| indices[y * image.width + x] == Some(colour))
+                    .fold(0, |acc, y| acc | (1 << (y - band)));
+                let ch = (63 + bits as u8) as char;
+                if ch == run.0 {
+                    run.1 += 1;
+                } else {
+                    push_run(&mut ret, run.0, run.1);
+                    run = (ch, 1);
+                }
+            }
+            /* Trailing empty sixels need not be sent */
+            if run.0 != '?' {
+                push_run(&mut ret, run.0, run.1);
+            }
+            ret.push('$');
+        }
+        ret.push('-');
+    }
+    ret.push_str("\x1b\\");
+    ret
+}
+
+/// Encode `image` with the kitty graphics protocol, to be shown in `cols`×`rows` cells as image
+/// `id`. The terminal is asked not to reply and not to move the cursor.
+pub fn encode_kitty(image: &Image, id: u32, cols: usize, rows: usize) -> String {
+    const CHUNK_SIZE: usize = 4096;
+    let data = image.pixels.iter().flatten().cloned().collect::<Vec<u8>>();
+    let payload = melib::data_encoding::BASE64.encode(&data);
+    let chunks = payload
+        .as_bytes()
+        .chunks(CHUNK_SIZE)
+        .collect::<Vec<&[u8]>>();
+    let mut ret = String::with_capacity(payload.len() + chunks.len() * 16);
+    for (i, chunk) in chunks.iter().enumerate() {
+        let more = if i + 1 < chunks.len() { 1 } else { 0 };
+        if i == 0 {
+            ret.push_str(&format!(
+                "\x1b_Ga=T,f=32,s={},v={},i={},c={},r={},C=1,q=2,m={};",
+                image.width, image.height, id, cols, rows, more
+            ));
+        } else {
+            ret.push_str(&format!("\x1b_Gm={};", more));
+        }
+        /* base64 is ASCII */
+        ret.push_str(std::str::from_utf8(chunk).unwrap());
+        ret.push_str("\x1b\\");
+    }
+    ret
+}
+
+/// Delete kitty graphics protocol image `id` and free its data.
+pub fn kitty_delete(id: u32) -> String {
+    format!("\x1b_Ga=d,d=I,i={},q=2\x1b\\", id)
+}
+
+/// An image to draw over the cells of `area`, starting from its upper left corner, as an escape
+/// sequence.
+#[derive(Debug, Clone)]
+pub struct ImagePlacement {
+    pub id: u32,
+    pub protocol: GraphicsProtocol,
+    pub area: Area,
+    pub sequence: String,
+}
+
+impl ImagePlacement {
+    /// Show `image` in `area` with `protocol`, which must be `Kitty` or `Sixel`. The image is
+    /// scaled down to fit in the area, whose cells are `cell_size` pixels.
+    pub fn new(
+        image: &Image,
+        id: u32,
+        protocol: GraphicsProtocol,
+        area: Area,
+        cell_size: (usize, usize),
+    ) -> Self {
+        let (cell_width, cell_height) = (cell_size.0.max(1), cell_size.1.max(1));
+        let scaled = image.scale_to_fit(width!(area) * cell_width, height!(area) * cell_height);
+        let sequence = match protocol {
+            GraphicsProtocol::Kitty => encode_kitty(
+                &scaled,
+                id,
+                scaled.width.div_ceil(cell_width),
+                scaled.height.div_ceil(cell_height),
+            ),
+            _ => encode_sixel(&scaled),
+        };
+        ImagePlacement {
+            id,
+            protocol,
+            area,
+            sequence,
+        }
+    }
+}
+
+/// Draw `image` in `area` of `grid` with half block characters: each cell shows two pixels,
+/// the upper one in the foreground colour and the lower one in the background colour. The image
+/// is scaled down to fit in the area.
+pub fn draw_half_blocks(image: &Image, grid: &mut CellBuffer, area: Area) {
+    if !is_valid_area!(area) {
+        return;
+    }
+    let scaled = image.scale_to_fit(width!(area), height!(area) * 2);
+    let colour = |p: [u8; 4]| {
+        if p[3] < ALPHA_THRESHOLD {
+            None
+        } else {
+            Some(Color::Rgb(p[0], p[1], p[2]))
+        }
+    };
+    let upper_left = upper_left!(area);
+    for y in 0..scaled.height.div_ceil(2) {
+        for x in 0..scaled.width {
+            let upper = colour(scaled.pixel(x, 2 * y));
+            let lower = if 2 * y + 1 < scaled.height {
+                colour(scaled.pixel(x, 2 * y + 1))
+            } else {
+                None
+            };
+            let (ch, fg, bg) = match (upper, lower) {
+                (Some(upper), lower) => ('▀', upper, lower.unwrap_or(Color::Default)),
+                (None, Some(lower)) => ('▄', lower, Color::Default),
+                (None, None) => (' ', Color::Default, Color::Default),
+            };
+            if let Some(cell) = grid.get_mut(get_x(upper_left) + x, get_y(upper_left) + y) {
+                cell.set_ch(ch).set_fg(fg).set_bg(bg);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn checkerboard(width: usize, height: usize) -> Image {
+        let pixels = (0..width * height)
+            .map(|i| {
+                if (i % width + i / width) & 1 == 0 {
+                    [255, 0, 0, 255]
+                } else {
+                    [0, 0, 255, 255]
+                }
+            })
+            .collect();
+        Image::new(width, height, pixels)
+    }
+
+    #[test]
+    fn test_image_detect_graphics_protocol() {
+        let env = |vars: &'static [(&'static str, &'static str)]| {
+            move |name: &str| {
+                vars.iter()
+                    .find(|(n, _)| *n == name)
+                    .map(|(_, v)| v.to_string())
+            }
+        };
+        assert_eq!(
+            GraphicsProtocol::detect_from(env(&[("TERM", "xterm-kitty")])),
+            GraphicsProtocol::Kitty
+        );
+        assert_eq!(
+            GraphicsProtocol::detect_from(env(&[("TERM", "foot")])),
+            GraphicsProtocol::Sixel
+        );
+        assert_eq!(
+            GraphicsProtocol::detect_from(env(&[
+                ("TERM", "tmux-256color"),
+                ("KITTY_WINDOW_ID", "1")
+            ])),
+            GraphicsProtocol::HalfBlocks
+        );
+        assert_eq!(
+            GraphicsProtocol::detect_from(env(&[("TERM", "xterm-256color")])),
+            GraphicsProtocol::HalfBlocks
+        );
+    }
+
+    #[cfg(feature = "image-preview")]
+    #[test]
+    fn test_image_decode_png() {
+        let mut bytes = vec![];
+        {
+            let mut encoder = png::Encoder::new(&mut bytes, 2, 1);
+            encoder.set_color(png::ColorType::Rgb);
+            encoder.set_depth(png::BitDepth::Eight);
+            let mut writer = encoder.write_header().unwrap();
+            writer.write_image_data(&[1, 2, 3, 4, 5, 6]).unwrap();
+        }
+        assert!(Image::is_supported(&bytes));
+        assert_eq!(
+            Image::decode(&bytes).unwrap(),
+            Image::new(2, 1, vec![[1, 2, 3, 255], [4, 5, 6, 255]])
+        );
+        assert!(!Image::is_supported(b"GIF89a"));
+        assert!(Image::decode(b"GIF89a").is_err());
+    }
+
+    #[test]
+    fn test_image_scale_to_fit() {
+        let image = checkerboard(40, 20);
+        let scaled = image.scale_to_fit(10, 10);
+        assert_eq!((scaled.width(), scaled.height()), (10, 5));
+        /* Each pixel averages as many red as blue pixels */
+        assert_eq!(scaled.pixel(0, 0), [127, 0, 127, 255]);
+        assert_eq!(image.scale_to_fit(100, 100), image);
+    }
+
+    #[test]
+    fn test_image_encode_sixel() {
+        let mut pixels = vec![[255, 0, 0, 255]; 8 * 7];
+        /* Last row is transparent, so the second band is empty */
+        for p in pixels[8 * 6..].iter_mut() {
+            *p = [0, 0, 0, 0];
+        }
+        /* First column is blue */
+        for y in 0..6 {
+            pixels[y * 8] = [0, 0, 255, 255];
+        }
+        let image = Image::new(8, 7, pixels);
+        assert_eq!(
+            encode_sixel(&image),
+            "\x1bP0;1;0q\"1;1;8;7#5;2;0;0;100#180;2;100;0;0#5~$#180?!7~$--\x1b\\"
+        );
+    }
+
+    #[test]
+    fn test_image_encode_kitty() {
+        let image = Image::new(2, 1, vec![[1, 2, 3, 255], [4, 5, 6, 255]]);
+        assert_eq!(
+            encode_kitty(&image, 7, 1, 1),
+            "\x1b_Ga=T,f=32,s=2,v=1,i=7,c=1,r=1,C=1,q=2,m=0;AQID/wQFBv8=\x1b\\"
+        );
+        let large = checkerboard(64, 64);
+        let sequence = encode_kitty(&large, 8, 4, 4);
+        /* 16KiB of pixels are 21848 bytes of base64, sent in 6 chunks */
+        assert_eq!(sequence.matches("\x1b_G").count(), 6);
+        assert_eq!(sequence.matches("m=1;").count(), 5);
+        assert_eq!(sequence.matches("m=0;").count(), 1);
+    }
+
+    #[test]
+    fn test_image_draw_half_blocks() {
+        let image = checkerboard(4, 3);
+        let mut grid = CellBuffer::new(10, 10, super::super::Cell::with_char(' '));
+        draw_half_blocks(&image, &mut grid, ((1, 1), (9, 9)));
+        let red = Color::Rgb(255, 0, 0);
+        let blue = Color::Rgb(0, 0, 255);
+        assert_eq!(grid[(1, 1)].ch(), '▀');
+        assert_eq!((grid[(1, 1)].fg(), grid[(1, 1)].bg()), (red, blue));
+        assert_eq!((grid[(2, 1)].fg(), grid[(2, 1)].bg()), (blue, red));
+        /* Odd last row: lower half is empty */
+        assert_eq!(
+            (grid[(1, 2)].fg(), grid[(1, 2)].bg()),
+            (red, Color::Default)
+        );
+        assert_eq!(grid[(5, 1)].ch(), ' ');
+        assert_eq!(grid[(1, 3)].ch(), ' ');
+    }
+
+    #[test]
+    fn test_image_areas_intersect() {
+        assert!(areas_intersect(((0, 0), (5, 5)), ((5, 5), (9, 9))));
+        assert!(!areas_intersect(((0, 0), (5, 5)), ((6, 0), (9, 9))));
+        assert!(areas_intersect(((3, 0), (4, 9)), ((0, 3), (9, 4))));
+    }
+}
@@ -239,3 +239,11 @@ pub fn place_in_area(area: Area, (width, height): (usize, usize), upper: bool, l
         ),
     )
 }
+
+/// Whether two areas have any cell in common.
+pub fn areas_intersect(a: Area, b: Area) -> bool {
+    !(get_x(bottom_right!(a)) < get_x(upper_left!(b))
+        || get_x(bottom_right!(b)) < get_x(upper_left!(a))
+        || get_y(bottom_right!(a)) < get_y(upper_left!(b))
+        || get_y(bottom_right!(b)) < get_y(upper_left!(a)))
+}